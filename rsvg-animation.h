@@ -0,0 +1,72 @@
+/* -*- Mode: C; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set sw=4 sts=4 expandtab: */
+/*
+   rsvg-animation.h: SMIL animation elements
+
+   This program is free software; you can redistribute it and/or
+   modify it under the terms of the GNU Library General Public License as
+   published by the Free Software Foundation; either version 2 of the
+   License, or (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+   Library General Public License for more details.
+
+   You should have received a copy of the GNU Library General Public
+   License along with this program; if not, write to the
+   Free Software Foundation, Inc., 59 Temple Place - Suite 330,
+   Boston, MA 02111-1307, USA.
+*/
+
+#ifndef RSVG_ANIMATION_H
+#define RSVG_ANIMATION_H
+
+#include "rsvg-private.h"
+
+G_BEGIN_DECLS 
+
+/* Implemented in rust/src/animation.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_animate_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/animation.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_animate_color_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/animation.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_animate_motion_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/animation.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_animate_transform_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/animation.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_set_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/animation.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_mpath_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/animation.rs
+ *
+ * Returns a new reference to the element modified by the animation, or NULL.
+ */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_animation_get_target (RsvgNode *node, RsvgHandle *handle);
+
+/* Implemented in rust/src/animation.rs
+ *
+ * Stores the animated value of the target's attribute in @atts, and also
+ * in @animated if the attribute is a property, so that it can override
+ * the style sheets.
+ */
+G_GNUC_INTERNAL
+void rsvg_node_animation_apply (RsvgNode *node, RsvgHandle *handle, double time,
+                                RsvgPropertyBag *atts, RsvgPropertyBag *animated);
+
+G_END_DECLS
+
+#endif                          /* RSVG_ANIMATION_H */
@@ -33,17 +33,22 @@
 #include "rsvg-styles.h"
 #include "rsvg-shapes.h"
 #include "rsvg-structure.h"
+#include "rsvg-animation.h"
 #include "rsvg-image.h"
 #include "rsvg-io.h"
 #include "rsvg-text.h"
+#include "rsvg-fonts.h"
 #include "rsvg-filter.h"
 #include "rsvg-mask.h"
 #include "rsvg-marker.h"
 #include "rsvg-cairo-render.h"
+#include "rsvg-cairo-clip.h"
+#include "rsvg-cairo-outline.h"
 
 #include <libxml/uri.h>
 #include <libxml/parser.h>
 #include <libxml/parserInternals.h>
+#include <libxml/SAX2.h>
 
 #include <gio/gio.h>
 
@@ -268,12 +273,12 @@ static const NodeCreator node_creators[] = {
     /* "altGlyph",           TRUE,  */
     /* "altGlyphDef",        FALSE, */
     /* "altGlyphItem",       FALSE, */
-    /* "animate",            FALSE, */
-    /* "animateColor",       FALSE, */
-    /* "animateMotion",      FALSE, */
-    /* "animateTransform",   FALSE, */
+    { "animate",             FALSE, rsvg_node_animate_new },
+    { "animateColor",        FALSE, rsvg_node_animate_color_new },
+    { "animateMotion",       FALSE, rsvg_node_animate_motion_new },
+    { "animateTransform",    FALSE, rsvg_node_animate_transform_new },
     { "circle",              TRUE,  rsvg_node_circle_new },
-    { "clipPath",            TRUE,  rsvg_new_clip_path },
+    { "clipPath",            TRUE,  rsvg_node_clip_path_new },
     /* "color-profile",      FALSE, */
     { "conicalGradient",     TRUE,  rsvg_node_radial_gradient_new },
     /* "cursor",             FALSE, */
@@ -305,15 +310,15 @@ static const NodeCreator node_creators[] = {
     { "feTile",              TRUE,  rsvg_new_filter_primitive_tile },
     { "feTurbulence",        TRUE,  rsvg_new_filter_primitive_turbulence },
     { "filter",              TRUE,  rsvg_new_filter },
-    /* "font",               TRUE,  */
-    /* "font-face",          FALSE, */
+    { "font",                TRUE,  rsvg_node_font_new },
+    { "font-face",           FALSE, rsvg_node_font_face_new },
     /* "font-face-format",   FALSE, */
     /* "font-face-name",     FALSE, */
     /* "font-face-src",      FALSE, */
     /* "font-face-uri",      FALSE, */
-    /* "foreignObject",      TRUE,  */
+    { "foreignObject",       TRUE,  rsvg_node_foreign_object_new },
     { "g",                   TRUE,  rsvg_node_group_new },
-    /* "glyph",              TRUE,  */
+    { "glyph",               TRUE,  rsvg_node_glyph_new },
     /* "glyphRef",           TRUE,  */
     /* "hkern",              FALSE, */
     { "image",               TRUE,  rsvg_new_image },
@@ -322,8 +327,8 @@ static const NodeCreator node_creators[] = {
     { "marker",              TRUE,  rsvg_node_marker_new },
     { "mask",                TRUE,  rsvg_new_mask },
     /* "metadata",           FALSE, */
-    /* "missing-glyph",      TRUE,  */
-    /* "mpath"               FALSE, */
+    { "missing-glyph",       TRUE,  rsvg_node_missing_glyph_new },
+    { "mpath",               FALSE, rsvg_node_mpath_new },
     { "multiImage",          FALSE, rsvg_node_switch_new }, /* hack to make multiImage sort-of work */
     { "path",                TRUE,  rsvg_node_path_new },
     { "pattern",             TRUE,  rsvg_node_pattern_new },
@@ -332,7 +337,7 @@ static const NodeCreator node_creators[] = {
     { "radialGradient",      TRUE,  rsvg_node_radial_gradient_new },
     { "rect",                TRUE,  rsvg_node_rect_new },
     /* "script",             FALSE, */
-    /* "set",                FALSE, */
+    { "set",                 FALSE, rsvg_node_set_new },
     { "stop",                TRUE,  rsvg_node_stop_new },
     /* "style",              FALSE, */
     { "subImage",            FALSE, rsvg_node_group_new },
@@ -341,7 +346,7 @@ static const NodeCreator node_creators[] = {
     { "switch",              TRUE,  rsvg_node_switch_new },
     { "symbol",              TRUE,  rsvg_node_symbol_new },
     { "text",                TRUE,  rsvg_new_text },
-    /* "textPath",           TRUE,  */
+    { "textPath",            TRUE,  rsvg_new_text_path },
     /* "title",              TRUE,  */
     { "tref",                TRUE,  rsvg_new_tref },
     { "tspan",               TRUE,  rsvg_new_tspan },
@@ -389,28 +394,167 @@ get_node_creator_for_element_name (const char *name)
 }
 
 static void
-node_set_atts (RsvgNode * node, RsvgHandle * ctx, const NodeCreator *creator, RsvgPropertyBag * atts)
+node_set_atts (RsvgNode * node, RsvgHandle * ctx, RsvgPropertyBag * atts)
 {
-    if (rsvg_property_bag_size (atts) > 0) {
-        const char *id;
-        const char *klazz;
-
+    if (rsvg_property_bag_size (atts) > 0)
         rsvg_node_set_atts (node, ctx, atts);
+}
 
-        /* The "svg" node is special; it will load its id/class
-         * attributes until the end, when rsvg_end_element() calls
-         * _rsvg_node_svg_apply_atts()
-         */
-        if (rsvg_node_get_type (node) != RSVG_NODE_TYPE_SVG) {
-            id = rsvg_property_bag_lookup (atts, "id");
+/* Attributes of an element as they appear in the document.  We keep
+ * them until the document is loaded, to compute the styles.  After that
+ * they are only kept for the elements that are animated, so that the
+ * animated values can be computed from the base values every time that
+ * rsvg_handle_set_time() gets called; documents without animation
+ * elements drop all of them.
+ */
+typedef struct {
+    RsvgNode *node;
+    RsvgPropertyBag *atts;
+} ElementAtts;
 
-            if (creator->supports_class_attribute)
-                klazz = rsvg_property_bag_lookup (atts, "class");
-            else
-                klazz = NULL;
+typedef struct {
+    RsvgNode *node;
+    guint target; /* index in priv->element_atts */
+} Animation;
+
+static void
+element_atts_free (ElementAtts *element_atts)
+{
+    element_atts->node = rsvg_node_unref (element_atts->node);
+    rsvg_property_bag_free (element_atts->atts);
+    g_free (element_atts);
+}
+
+static void
+animation_free (Animation *animation)
+{
+    animation->node = rsvg_node_unref (animation->node);
+    g_free (animation);
+}
+
+static void
+save_element_atts (RsvgHandle *ctx, RsvgNode *node, RsvgPropertyBag *atts)
+{
+    ElementAtts *element_atts;
+
+    if (ctx->priv->element_atts == NULL)
+        ctx->priv->element_atts = g_ptr_array_new_with_free_func ((GDestroyNotify) element_atts_free);
+
+    element_atts = g_new (ElementAtts, 1);
+    element_atts->node = rsvg_node_ref (node);
+    element_atts->atts = rsvg_property_bag_dup (atts);
+
+    g_ptr_array_add (ctx->priv->element_atts, element_atts);
+}
+
+/* Moves the line and column past the byte at @c.  Like libxml2, we count
+ * characters, not the bytes in UTF-8 sequences.
+ */
+static void
+advance_position (const xmlChar *c, int *line, int *column)
+{
+    if (*c == '\n') {
+        (*line)++;
+        *column = 1;
+    } else if ((*c & 0xc0) != 0x80) {
+        (*column)++;
+    }
+}
+
+/* libxml2 only tells us where the parser is when it calls us back for a
+ * start tag, which is at its closing '>' or "/>".  To point diagnostics
+ * at the element and at each attribute instead, we go back through the
+ * start tag, which is still in the parser's input buffer.  Attribute
+ * values cannot contain '<', so the last one before the parser's
+ * position is where the tag starts.
+ */
+static void
+set_node_locations (RsvgNode *node, xmlParserCtxtPtr ctxt)
+{
+    const xmlChar *base, *start, *end, *p;
+    int line, column, end_line, end_column;
+
+    end_line = xmlSAX2GetLineNumber (ctxt);
+    end_column = xmlSAX2GetColumnNumber (ctxt);
+
+    if (ctxt->input == NULL || ctxt->input->base == NULL || ctxt->input->cur == NULL) {
+        rsvg_node_set_location (node, end_line, end_column);
+        return;
+    }
+
+    base = ctxt->input->base;
+    end = ctxt->input->cur;
+
+    for (start = end; start > base && *start != '<'; start--)
+        ;
+
+    if (*start != '<') {
+        rsvg_node_set_location (node, end_line, end_column);
+        return;
+    }
+
+    /* Find the position of the tag from the one of the end */
+    line = end_line;
+    column = end_column;
+
+    for (p = start; p < end; p++) {
+        if (*p == '\n')
+            line--;
+        else if ((*p & 0xc0) != 0x80)
+            column--;
+    }
 
-            rsvg_parse_style_attrs (ctx, node, creator->element_name, klazz, id, atts);
+    /* If the tag spans several lines, count from the start of its line instead */
+    if (line != end_line) {
+        for (p = start; p > base && p[-1] != '\n'; p--)
+            ;
+
+        column = 1;
+        for (; p < start; p++)
+            advance_position (p, &line, &column);
+    }
+
+    rsvg_node_set_location (node, line, column);
+
+    /* Skip the element name, then go through the attributes */
+    for (p = start + 1; p < end && !g_ascii_isspace (*p) && *p != '/' && *p != '>'; p++)
+        advance_position (p, &line, &column);
+
+    while (p < end) {
+        const xmlChar *name;
+        xmlChar quote;
+        char *attr_name;
+
+        if (g_ascii_isspace (*p)) {
+            advance_position (p++, &line, &column);
+            continue;
         }
+
+        if (*p == '/' || *p == '>')
+            break;
+
+        name = p;
+        for (; p < end && *p != '=' && !g_ascii_isspace (*p); p++)
+            ;
+
+        attr_name = g_strndup ((const char *) name, p - name);
+        rsvg_node_set_attribute_location (node, attr_name, line, column);
+        g_free (attr_name);
+
+        for (p = name; p < end && *p != '"' && *p != '\''; p++)
+            advance_position (p, &line, &column);
+
+        if (p == end)
+            break;
+
+        quote = *p;
+        advance_position (p++, &line, &column);
+
+        for (; p < end && *p != quote; p++)
+            advance_position (p, &line, &column);
+
+        if (p < end)
+            advance_position (p++, &line, &column);
     }
 }
 
@@ -442,7 +586,15 @@ rsvg_standard_element_start (RsvgHandle * ctx, const char *name, RsvgPropertyBag
 
     ctx->priv->currentnode = rsvg_node_ref (newnode);
 
-    node_set_atts (newnode, ctx, creator, atts);
+    if (ctx->priv->ctxt != NULL)
+        set_node_locations (newnode, ctx->priv->ctxt);
+
+    rsvg_node_set_element (newnode, name, creator->supports_class_attribute, atts);
+    node_set_atts (newnode, ctx, atts);
+    save_element_atts (ctx, newnode, atts);
+
+    if (rsvg_node_get_type (newnode) == RSVG_NODE_TYPE_ANIMATION)
+        ctx->priv->has_animation_elements = TRUE;
 
     newnode = rsvg_node_unref (newnode);
 }
@@ -785,6 +937,192 @@ rsvg_start_xinclude (RsvgHandle * ctx, RsvgPropertyBag * atts)
 
 /* end xinclude */
 
+/* start foreignObject */
+
+#define XHTML_NAMESPACE "http://www.w3.org/1999/xhtml"
+
+typedef struct _RsvgSaxHandlerForeignObject {
+    RsvgSaxHandler super;
+
+    RsvgHandle *ctx;
+    RsvgNode *node;
+    GString *paragraph;
+    guint heading_level;
+    gboolean last_was_space;
+} RsvgSaxHandlerForeignObject;
+
+/* Elements that start a new paragraph */
+static const char *xhtml_block_elements[] = {
+    "address", "article", "aside", "blockquote", "body", "div", "footer", "h1", "h2", "h3",
+    "h4", "h5", "h6", "header", "html", "li", "ol", "p", "pre", "section", "ul", NULL
+};
+
+/* Elements that we render as plain text within the current paragraph */
+static const char *xhtml_inline_elements[] = {
+    "a", "abbr", "b", "bdi", "cite", "code", "em", "i", "kbd", "mark", "q", "s", "samp",
+    "small", "span", "strong", "sub", "sup", "u", "var", NULL
+};
+
+static gboolean
+xhtml_element_is_one_of (const char *name, const char **names)
+{
+    guint i;
+
+    for (i = 0; names[i] != NULL; i++)
+        if (!strcmp (name, names[i]))
+            return TRUE;
+
+    return FALSE;
+}
+
+static const char *
+xhtml_local_name (const char *name)
+{
+    const char *colon;
+
+    colon = strrchr (name, ':');
+    return colon ? colon + 1 : name;
+}
+
+static guint
+xhtml_heading_level (const char *name)
+{
+    if (name[0] == 'h' && name[1] >= '1' && name[1] <= '6' && name[2] == '\0')
+        return name[1] - '0';
+
+    return 0;
+}
+
+static void
+rsvg_foreign_object_flush_paragraph (RsvgSaxHandlerForeignObject *z)
+{
+    if (z->paragraph->len > 0 && z->paragraph->str[z->paragraph->len - 1] == ' ')
+        g_string_truncate (z->paragraph, z->paragraph->len - 1);
+
+    if (z->paragraph->len > 0)
+        rsvg_node_foreign_object_add_paragraph (z->node, z->paragraph->str, z->heading_level);
+
+    g_string_truncate (z->paragraph, 0);
+    z->last_was_space = TRUE;
+}
+
+static void
+rsvg_foreign_object_handler_free (RsvgSaxHandler * self)
+{
+    RsvgSaxHandlerForeignObject *z = (RsvgSaxHandlerForeignObject *) self;
+
+    rsvg_foreign_object_flush_paragraph (z);
+
+    g_string_free (z->paragraph, TRUE);
+    z->node = rsvg_node_unref (z->node);
+
+    g_free (self);
+}
+
+/* Collapses runs of whitespace into a single space, like CSS's "white-space: normal" */
+static void
+rsvg_foreign_object_handler_characters (RsvgSaxHandler * self, const char *ch, int len)
+{
+    RsvgSaxHandlerForeignObject *z = (RsvgSaxHandlerForeignObject *) self;
+    char *utf8 = NULL;
+    int i;
+
+    if (!ch || !len)
+        return;
+
+    if (!g_utf8_validate (ch, len, NULL)) {
+        utf8 = rsvg_make_valid_utf8 (ch, len);
+        ch = utf8;
+        len = strlen (utf8);
+    }
+
+    for (i = 0; i < len; i++) {
+        if (g_ascii_isspace (ch[i])) {
+            if (!z->last_was_space)
+                g_string_append_c (z->paragraph, ' ');
+
+            z->last_was_space = TRUE;
+        } else {
+            g_string_append_c (z->paragraph, ch[i]);
+            z->last_was_space = FALSE;
+        }
+    }
+
+    g_free (utf8);
+}
+
+static void
+rsvg_foreign_object_handler_start (RsvgSaxHandler * self, const char *name, RsvgPropertyBag * atts)
+{
+    RsvgSaxHandlerForeignObject *z = (RsvgSaxHandlerForeignObject *) self;
+    const char *xmlns;
+
+    xmlns = rsvg_property_bag_lookup (atts, "xmlns");
+    if (xmlns && strcmp (xmlns, XHTML_NAMESPACE) != 0) {
+        rsvg_node_foreign_object_set_unsupported (z->node);
+        return;
+    }
+
+    name = xhtml_local_name (name);
+
+    if (xhtml_element_is_one_of (name, xhtml_block_elements)) {
+        rsvg_foreign_object_flush_paragraph (z);
+        z->heading_level = xhtml_heading_level (name);
+    } else if (!strcmp (name, "br")) {
+        if (z->paragraph->len > 0 && z->paragraph->str[z->paragraph->len - 1] == ' ')
+            g_string_truncate (z->paragraph, z->paragraph->len - 1);
+
+        g_string_append_c (z->paragraph, '\n');
+        z->last_was_space = TRUE;
+    } else if (!xhtml_element_is_one_of (name, xhtml_inline_elements)) {
+        /* images, forms, tables, nested SVG, etc. */
+        rsvg_node_foreign_object_set_unsupported (z->node);
+    }
+}
+
+static void
+rsvg_foreign_object_handler_end (RsvgSaxHandler * self, const char *name)
+{
+    RsvgSaxHandlerForeignObject *z = (RsvgSaxHandlerForeignObject *) self;
+
+    name = xhtml_local_name (name);
+
+    if (xhtml_element_is_one_of (name, xhtml_block_elements)) {
+        rsvg_foreign_object_flush_paragraph (z);
+        z->heading_level = 0;
+    }
+}
+
+/* We don't build nodes for the contents of a <foreignObject>.  Instead, we
+ * collect the text of simple XHTML content into paragraphs which the
+ * foreignObject node lays out with Pango.  Anything we don't know how to
+ * render marks the node as unsupported, so that a <switch> can pick a
+ * fallback instead.
+ */
+static void
+rsvg_start_foreign_object (RsvgHandle * ctx, RsvgPropertyBag * atts)
+{
+    RsvgSaxHandlerForeignObject *handler;
+
+    rsvg_standard_element_start (ctx, "foreignObject", atts);
+
+    handler = g_new0 (RsvgSaxHandlerForeignObject, 1);
+
+    handler->super.free = rsvg_foreign_object_handler_free;
+    handler->super.characters = rsvg_foreign_object_handler_characters;
+    handler->super.start_element = rsvg_foreign_object_handler_start;
+    handler->super.end_element = rsvg_foreign_object_handler_end;
+    handler->ctx = ctx;
+    handler->node = rsvg_node_ref (ctx->priv->currentnode);
+    handler->paragraph = g_string_new (NULL);
+    handler->heading_level = 0;
+    handler->last_was_space = TRUE;
+
+    ctx->priv->handler = &handler->super;
+}
+
+/* end foreignObject */
+
 static void
 rsvg_start_element (void *data, const xmlChar * name, const xmlChar ** atts)
 {
@@ -813,6 +1151,8 @@ rsvg_start_element (void *data, const xmlChar * name, const xmlChar ** atts)
             rsvg_start_metadata (ctx);
         else if (!strcmp ((const char *) name, "include"))      /* xi:include */
             rsvg_start_xinclude (ctx, bag);
+        else if (!strcmp ((const char *) name, "foreignObject"))
+            rsvg_start_foreign_object (ctx, bag);
         else
             rsvg_standard_element_start (ctx, (const char *) name, bag);
     }
@@ -849,12 +1189,6 @@ rsvg_end_element (void *data, const xmlChar * xmlname)
             ctx->priv->currentnode = parent;
             pop_element_name (ctx);
         }
-
-        /* FIXMEchpe: shouldn't this check that currentnode == treebase or sth like that? */
-        if (ctx->priv->treebase && !strcmp (name, "svg")) {
-            g_assert (rsvg_node_get_type (ctx->priv->treebase) == RSVG_NODE_TYPE_SVG);
-            rsvg_node_svg_apply_atts (ctx->priv->treebase, ctx);
-        }
     }
 }
 
@@ -918,7 +1252,8 @@ find_last_chars_node (RsvgNode *node, gpointer data)
 
     if (rsvg_node_get_type (node) == RSVG_NODE_TYPE_CHARS) {
         *dest = rsvg_node_ref (node);
-    } else if (rsvg_node_get_type (node) == RSVG_NODE_TYPE_TSPAN) {
+    } else if (rsvg_node_get_type (node) == RSVG_NODE_TYPE_TSPAN
+               || rsvg_node_get_type (node) == RSVG_NODE_TYPE_TEXT_PATH) {
         *dest = rsvg_node_unref (*dest); /* Discard the last chars node we found */
     }
 
@@ -935,7 +1270,9 @@ rsvg_characters_impl (RsvgHandle * ctx, const xmlChar * ch, int len)
 
     if (ctx->priv->currentnode) {
         RsvgNodeType type = rsvg_node_get_type (ctx->priv->currentnode);
-        if (type == RSVG_NODE_TYPE_TSPAN || type == RSVG_NODE_TYPE_TEXT) {
+        if (type == RSVG_NODE_TYPE_TSPAN
+            || type == RSVG_NODE_TYPE_TEXT
+            || type == RSVG_NODE_TYPE_TEXT_PATH) {
             RsvgNodeChars *self;
 
             /* find the last CHARS node in the text or tspan node, so that we
@@ -1077,6 +1414,60 @@ rsvg_error_cb (void *data, const char *msg, ...)
 #endif
 }
 
+/* Loads an external style sheet, from xml-stylesheet processing
+ * instructions and @import rules.
+ */
+void
+rsvg_handle_load_css (RsvgHandle *handle, const char *href)
+{
+    char *style_data;
+    gsize style_data_len;
+    char *mime_type = NULL;
+
+    style_data = _rsvg_handle_acquire_data (handle,
+                                            href,
+                                            &mime_type,
+                                            &style_data_len,
+                                            NULL);
+    if (style_data &&
+        mime_type &&
+        strcmp (mime_type, "text/css") == 0) {
+        rsvg_parse_cssbuffer (handle, style_data, style_data_len);
+    }
+
+    g_free (mime_type);
+    g_free (style_data);
+}
+
+gboolean
+rsvg_handle_load_font_face (RsvgHandle *handle, const char *family, const char *href)
+{
+    char *data;
+    gsize data_len;
+    gboolean loaded;
+
+    data = _rsvg_handle_acquire_data (handle, href, NULL, &data_len, NULL);
+    if (data == NULL)
+        return FALSE;
+
+    loaded = rsvg_fonts_add_data (handle->priv->fonts, family, data, data_len);
+    g_free (data);
+
+    return loaded;
+}
+
+gboolean
+rsvg_handle_load_local_font_face (RsvgHandle *handle, const char *family, const char *name)
+{
+    return rsvg_fonts_add_local (handle->priv->fonts, family, name);
+}
+
+void
+rsvg_handle_register_svg_font (RsvgHandle *handle, const char *family, RsvgNode *font)
+{
+    rsvg_fonts_add_svg_font (handle->priv->fonts, family, font);
+}
+
 static void
 rsvg_processing_instruction (void *ctx, const xmlChar * target, const xmlChar * data)
 {
@@ -1098,25 +1489,8 @@ rsvg_processing_instruction (void *ctx, const xmlChar * target, const xmlChar *
                 value = rsvg_property_bag_lookup (atts, "type");
                 if (value && strcmp (value, "text/css") == 0) {
                     value = rsvg_property_bag_lookup (atts, "href");
-                    if (value && value[0]) {
-                        char *style_data;
-                        gsize style_data_len;
-                        char *mime_type = NULL;
-
-                        style_data = _rsvg_handle_acquire_data (handle,
-                                                                value,
-                                                                &mime_type,
-                                                                &style_data_len,
-                                                                NULL);
-                        if (style_data &&
-                            mime_type &&
-                            strcmp (mime_type, "text/css") == 0) {
-                            rsvg_parse_cssbuffer (handle, style_data, style_data_len);
-                        }
-
-                        g_free (mime_type);
-                        g_free (style_data);
-                    }
+                    if (value && value[0])
+                        rsvg_handle_load_css (handle, value);
                 }
             }
 
@@ -1386,6 +1760,363 @@ rsvg_handle_close_impl (RsvgHandle * handle, GError ** error)
     return TRUE;
 }
 
+static void
+rsvg_handle_apply_animations (RsvgHandle *handle)
+{
+    RsvgHandlePrivate *priv = handle->priv;
+    GPtrArray *animated_atts, *animated_properties;
+    RsvgPropertyBag *empty;
+    guint i;
+
+    if (priv->animations == NULL)
+        return;
+
+    animated_atts = g_ptr_array_new_with_free_func ((GDestroyNotify) rsvg_property_bag_free);
+    animated_properties = g_ptr_array_new_with_free_func ((GDestroyNotify) rsvg_property_bag_free);
+
+    /* Only bags from rsvg_property_bag_dup() can be modified */
+    empty = rsvg_property_bag_new (NULL);
+
+    for (i = 0; i < priv->element_atts->len; i++) {
+        ElementAtts *element_atts = g_ptr_array_index (priv->element_atts, i);
+
+        g_ptr_array_add (animated_atts, rsvg_property_bag_dup (element_atts->atts));
+        g_ptr_array_add (animated_properties, rsvg_property_bag_dup (empty));
+    }
+
+    rsvg_property_bag_free (empty);
+
+    /* Animations are applied in document order, so later ones override
+     * or add to the earlier ones.
+     */
+    for (i = 0; i < priv->animations->len; i++) {
+        Animation *animation = g_ptr_array_index (priv->animations, i);
+
+        rsvg_node_animation_apply (animation->node,
+                                   handle,
+                                   priv->time,
+                                   g_ptr_array_index (animated_atts, animation->target),
+                                   g_ptr_array_index (animated_properties, animation->target));
+    }
+
+    for (i = 0; i < priv->element_atts->len; i++) {
+        ElementAtts *element_atts = g_ptr_array_index (priv->element_atts, i);
+        RsvgNode *node = element_atts->node;
+
+        rsvg_state_reinit (rsvg_node_get_state (node));
+        node_set_atts (node, handle, g_ptr_array_index (animated_atts, i));
+        rsvg_parse_style_attrs (handle, node,
+                                g_ptr_array_index (animated_atts, i),
+                                g_ptr_array_index (animated_properties, i));
+    }
+
+    g_ptr_array_free (animated_properties, TRUE);
+    g_ptr_array_free (animated_atts, TRUE);
+}
+
+static guint
+find_element_atts (GPtrArray *element_atts, RsvgNode *node)
+{
+    guint i;
+
+    for (i = 0; i < element_atts->len; i++) {
+        ElementAtts *e = g_ptr_array_index (element_atts, i);
+
+        if (rsvg_node_is_same (e->node, node))
+            return i;
+    }
+
+    return element_atts->len;
+}
+
+/* Called once the document is loaded, to compute the style of every
+ * element in document order.
+ */
+static void
+rsvg_handle_cascade (RsvgHandle *handle)
+{
+    RsvgHandlePrivate *priv = handle->priv;
+    guint i;
+
+    if (priv->element_atts == NULL)
+        return;
+
+    for (i = 0; i < priv->element_atts->len; i++) {
+        ElementAtts *element_atts = g_ptr_array_index (priv->element_atts, i);
+
+        rsvg_parse_style_attrs (handle, element_atts->node, element_atts->atts, NULL);
+    }
+
+    /* Without animations, nothing needs the attributes anymore */
+    if (!priv->has_animation_elements) {
+        g_ptr_array_free (priv->element_atts, TRUE);
+        priv->element_atts = NULL;
+    }
+}
+
+/* Called once the document is loaded, to resolve the target elements
+ * of animations.  We only keep the attributes of the elements that are
+ * animated.
+ */
+static void
+rsvg_handle_setup_animations (RsvgHandle *handle)
+{
+    RsvgHandlePrivate *priv = handle->priv;
+    GPtrArray *targets;
+    guint i;
+
+    if (priv->element_atts == NULL)
+        return;
+
+    targets = g_ptr_array_new_with_free_func ((GDestroyNotify) element_atts_free);
+
+    for (i = 0; i < priv->all_nodes->len; i++) {
+        RsvgNode *node = g_ptr_array_index (priv->all_nodes, i);
+        RsvgNode *target;
+        Animation *animation;
+        guint index;
+
+        if (rsvg_node_get_type (node) != RSVG_NODE_TYPE_ANIMATION)
+            continue;
+
+        target = rsvg_node_animation_get_target (node, handle);
+        if (target == NULL)
+            continue;
+
+        index = find_element_atts (targets, target);
+
+        if (index == targets->len) {
+            guint j = find_element_atts (priv->element_atts, target);
+
+            if (j == priv->element_atts->len) {
+                target = rsvg_node_unref (target);
+                continue;
+            }
+
+            /* Move the element's attributes over to the targets */
+            g_ptr_array_add (targets, g_ptr_array_index (priv->element_atts, j));
+            g_ptr_array_index (priv->element_atts, j) = NULL;
+        }
+
+        target = rsvg_node_unref (target);
+
+        if (priv->animations == NULL)
+            priv->animations = g_ptr_array_new_with_free_func ((GDestroyNotify) animation_free);
+
+        animation = g_new (Animation, 1);
+        animation->node = rsvg_node_ref (node);
+        animation->target = index;
+
+        g_ptr_array_add (priv->animations, animation);
+    }
+
+    /* The elements that are not animated were moved away above */
+    g_ptr_array_set_free_func (priv->element_atts, NULL);
+
+    for (i = 0; i < priv->element_atts->len; i++) {
+        ElementAtts *element_atts = g_ptr_array_index (priv->element_atts, i);
+
+        if (element_atts != NULL)
+            element_atts_free (element_atts);
+    }
+
+    g_ptr_array_free (priv->element_atts, TRUE);
+    priv->element_atts = targets;
+
+    if (priv->has_time)
+        rsvg_handle_apply_animations (handle);
+}
+
+/* Called once the document is loaded and the styles are computed, to
+ * collect the errors of all the elements in document order.
+ */
+static void
+rsvg_handle_collect_diagnostics (RsvgHandle *handle)
+{
+    RsvgHandlePrivate *priv = handle->priv;
+    guint i;
+
+    for (i = 0; i < priv->all_nodes->len; i++)
+        rsvg_diagnostics_add_node (priv->diagnostics, g_ptr_array_index (priv->all_nodes, i));
+}
+
+static gboolean
+rsvg_handle_finish_load (RsvgHandle *handle, GError **error)
+{
+    rsvg_handle_cascade (handle);
+    rsvg_handle_collect_diagnostics (handle);
+
+    if (handle->priv->flags & RSVG_HANDLE_FLAG_STRICT) {
+        char *first_error = rsvg_diagnostics_get_first (handle->priv->diagnostics);
+
+        if (first_error) {
+            g_set_error (error, RSVG_ERROR, RSVG_ERROR_INVALID_ATTRIBUTE, "%s", first_error);
+            g_free (first_error);
+            return FALSE;
+        }
+    }
+
+    rsvg_handle_setup_animations (handle);
+    return TRUE;
+}
+
+RsvgNode *
+rsvg_handle_lookup_node (RsvgHandle *handle, const char *url)
+{
+    return rsvg_defs_lookup (handle->priv->defs, url);
+}
+
+RsvgNode *
+rsvg_handle_get_root_node (RsvgHandle *handle)
+{
+    return handle->priv->treebase;
+}
+
+RsvgPathBuilder *
+rsvg_handle_get_clip_path_geometry (RsvgHandle *handle, const char *id, const cairo_rectangle_t *bbox)
+{
+    RsvgDrawingCtx *draw;
+    RsvgNode *node;
+    RsvgBbox clip_bbox;
+    cairo_matrix_t identity;
+    cairo_surface_t *target;
+    cairo_t *cr;
+    RsvgPathBuilder *geometry = NULL;
+
+    g_return_val_if_fail (handle, NULL);
+
+    if (!handle->priv->treebase || !id || !id[0])
+        return NULL;
+
+    node = rsvg_defs_lookup (handle->priv->defs, id);
+    if (!node || rsvg_node_get_type (node) != RSVG_NODE_TYPE_CLIP_PATH)
+        return NULL;
+
+    if (rsvg_node_clip_path_get_units (node) == objectBoundingBox && !bbox)
+        return NULL;
+
+    cairo_matrix_init_identity (&identity);
+    rsvg_bbox_init (&clip_bbox, &identity);
+
+    if (bbox) {
+        clip_bbox.rect = *bbox;
+        clip_bbox.virgin = FALSE;
+    }
+
+    target = cairo_image_surface_create (CAIRO_FORMAT_RGB24, 1, 1);
+    cr = cairo_create (target);
+
+    draw = rsvg_cairo_new_drawing_ctx (cr, handle);
+    if (draw) {
+        /* Work in the user space of the clipped element, not in the
+         * document's pixels.
+         */
+        rsvg_current_state (draw)->affine = identity;
+
+        geometry = rsvg_cairo_clip_get_geometry (draw, node, &clip_bbox);
+
+        rsvg_drawing_ctx_free (draw);
+    }
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (target);
+
+    return geometry;
+}
+
+RsvgPathBuilder *
+rsvg_handle_get_text_outlines (RsvgHandle *handle, RsvgNode *node)
+{
+    RsvgDrawingCtx *draw;
+    cairo_surface_t *target;
+    cairo_t *cr;
+    RsvgPathBuilder *outlines = NULL;
+
+    g_return_val_if_fail (handle, NULL);
+
+    if (!node || rsvg_node_get_type (node) != RSVG_NODE_TYPE_TEXT)
+        return NULL;
+
+    target = cairo_image_surface_create (CAIRO_FORMAT_RGB24, 1, 1);
+    cr = cairo_create (target);
+
+    draw = rsvg_cairo_new_drawing_ctx (cr, handle);
+    if (draw) {
+        outlines = rsvg_cairo_get_text_outlines (draw, node);
+
+        rsvg_drawing_ctx_free (draw);
+    }
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (target);
+
+    return outlines;
+}
+
+RsvgDiagnostics *
+rsvg_handle_get_diagnostics_collector (RsvgHandle *handle)
+{
+    return handle->priv->diagnostics;
+}
+
+/**
+ * rsvg_handle_get_diagnostics:
+ * @handle: An #RsvgHandle
+ *
+ * Returns a report of the errors that were found in the elements and
+ * attributes of the SVG while loading it.  Elements with errors are
+ * not rendered, and invalid presentation attributes and declarations in
+ * style attributes are ignored, so this can be used to detect documents
+ * that would not render as their authors intended.
+ *
+ * The report has one line per error, in document order, with the line
+ * and column of the attribute in the source, the element's name and id,
+ * the attribute, and a description of the error.
+ *
+ * Returns: (nullable) (transfer full): a newly-allocated string with the
+ * report, or %NULL if there were no errors or the handle is not loaded yet.
+ *
+ * Since: 2.42
+ */
+char *
+rsvg_handle_get_diagnostics (RsvgHandle *handle)
+{
+    g_return_val_if_fail (handle, NULL);
+
+    return rsvg_diagnostics_to_string (handle->priv->diagnostics);
+}
+
+/**
+ * rsvg_handle_get_svg_with_text_as_paths:
+ * @handle: An #RsvgHandle
+ *
+ * Writes the loaded document back out as SVG, with every text element
+ * replaced by a path with the outlines of its glyphs.  The paths keep
+ * the id, transform and style of the text elements they replace, and
+ * have all of the positioning of the text applied, so the result
+ * renders the same on systems that don't have the fonts, and can be
+ * used by consumers that only understand geometry.
+ *
+ * Only the elements and attributes that librsvg understands are
+ * written, and styles from style sheets get folded into the style
+ * attribute of each element.
+ *
+ * Returns: (nullable) (transfer full): a newly-allocated string with the
+ * SVG document, or %NULL if the handle is not loaded yet.
+ *
+ * Since: 2.42
+ */
+char *
+rsvg_handle_get_svg_with_text_as_paths (RsvgHandle *handle)
+{
+    g_return_val_if_fail (handle, NULL);
+
+    if (!handle->priv->treebase)
+        return NULL;
+
+    return rsvg_serialize_with_text_as_paths (handle, handle->priv->treebase);
+}
+
 void
 rsvg_drawing_ctx_free (RsvgDrawingCtx * handle)
 {
@@ -1398,6 +2129,9 @@ rsvg_drawing_ctx_free (RsvgDrawingCtx * handle)
     g_warn_if_fail (handle->acquired_nodes == NULL);
     g_slist_free (handle->acquired_nodes);
 
+    g_warn_if_fail (handle->drawn_nodes == NULL);
+    g_slist_free (handle->drawn_nodes);
+
     if (handle->pango_context != NULL)
         g_object_unref (handle->pango_context);
 
@@ -1805,6 +2539,33 @@ rsvg_handle_set_dpi_x_y (RsvgHandle * handle, double dpi_x, double dpi_y)
         handle->priv->dpi_y = dpi_y;
 }
 
+/**
+ * rsvg_handle_set_time:
+ * @handle: An #RsvgHandle
+ * @time: Document time, in seconds
+ *
+ * Sets the document time at which the SVG's animations are rendered;
+ * these are the animate, animateColor, animateMotion, animateTransform
+ * and set elements.  Until this function is called, animations are
+ * ignored and the SVG renders as if it had none.
+ *
+ * This can be called before or after the handle is closed, and it can
+ * be called again to render the SVG at a different time.
+ *
+ * Since: 2.42
+ */
+void
+rsvg_handle_set_time (RsvgHandle * handle, double time)
+{
+    g_return_if_fail (handle != NULL);
+
+    handle->priv->time = time;
+    handle->priv->has_time = TRUE;
+
+    if (handle->priv->state == RSVG_HANDLE_STATE_CLOSED_OK)
+        rsvg_handle_apply_animations (handle);
+}
+
 /**
  * rsvg_handle_set_size_callback:
  * @handle: An #RsvgHandle
@@ -1943,7 +2704,8 @@ rsvg_handle_close (RsvgHandle * handle, GError ** error)
         return ret;
     }
 
-    result = rsvg_handle_close_impl (handle, error);
+    result = rsvg_handle_close_impl (handle, error)
+        && rsvg_handle_finish_load (handle, error);
 
     if (result) {
         priv->state = RSVG_HANDLE_STATE_CLOSED_OK;
@@ -2062,6 +2824,9 @@ rsvg_handle_read_stream_sync (RsvgHandle   *handle,
     priv->error = NULL;
     g_clear_object (&priv->cancellable);
 
+    if (res)
+        res = rsvg_handle_finish_load (handle, error);
+
     if (res) {
         priv->state = RSVG_HANDLE_STATE_CLOSED_OK;
     } else {
@@ -2359,15 +3124,23 @@ rsvg_drawing_ctx_draw_node_from_stack (RsvgDrawingCtx *ctx, RsvgNode *node, int
 
     if (state->visible) {
         rsvg_state_push (ctx);
+        ctx->drawn_nodes = g_slist_prepend (ctx->drawn_nodes, node);
 
         rsvg_node_draw (node, ctx, dominate);
 
+        ctx->drawn_nodes = g_slist_delete_link (ctx->drawn_nodes, ctx->drawn_nodes);
         rsvg_state_pop (ctx);
     }
 
     ctx->drawsub_stack = stacksave;
 }
 
+RsvgNode *
+rsvg_drawing_ctx_get_current_node (RsvgDrawingCtx *ctx)
+{
+    return ctx->drawn_nodes ? ctx->drawn_nodes->data : NULL;
+}
+
 cairo_matrix_t
 rsvg_drawing_ctx_get_current_state_affine (RsvgDrawingCtx *ctx)
 {
@@ -42,68 +42,247 @@
 
 typedef struct RsvgCairoClipRender RsvgCairoClipRender;
 
+/* This render doesn't draw anything.  Instead, it accumulates the area
+ * covered by the shapes in a clipPath.
+ *
+ * As long as none of the shapes has a clip-path of its own and they all
+ * have the same clip-rule, their outlines just go into the path of the
+ * cairo context, which then gets passed to cairo_clip(); shapes that
+ * overlap then combine by that clip-rule, like the subpaths of any other
+ * path, instead of making a union.  Otherwise, each
+ * shape's area gets computed as a path, in the coordinates of the current
+ * state's affine.  Each discrete layer (for example, a <use> element) gets
+ * its own path, so that the layer's clip-path can be applied to it alone
+ * before it gets added to its parent's.  The areas get added up by
+ * appending their paths, which is their union under the nonzero rule.
+ */
 struct RsvgCairoClipRender {
     RsvgCairoRender super;
     RsvgCairoRender *parent;
+
+    gboolean direct;
+    int direct_clip_rule;
+    guint direct_depth;
+
+    RsvgPathBuilder *geometry;
+    GSList *geometry_stack;
 };
 
 #define RSVG_CAIRO_CLIP_RENDER(render) (_RSVG_RENDER_CIC ((render), RSVG_RENDER_TYPE_CAIRO_CLIP, RsvgCairoClipRender))
 
+static const cairo_matrix_t identity_matrix = { 1.0, 0.0, 0.0, 1.0, 0.0, 0.0 };
+
+/* The paths in the cairo context are offset by the parent's surface */
+static void
+rsvg_cairo_clip_get_offset (RsvgCairoRender *render, cairo_matrix_t *matrix)
+{
+    gboolean nest = render->cr != render->initial_cr;
+
+    cairo_matrix_init_translate (matrix,
+                                 nest ? 0 : render->offset_x,
+                                 nest ? 0 : render->offset_y);
+}
+
+/* Whether the current shape can still go straight into the path of the
+ * cairo context.  If so, this sets up the context's matrix for it.
+ */
+static gboolean
+rsvg_cairo_clip_prepare_direct (RsvgCairoClipRender *render, RsvgState *state)
+{
+    cairo_matrix_t matrix;
+
+    if (!render->direct
+        || state->clip_path
+        || (render->direct_clip_rule != -1 && render->direct_clip_rule != state->clip_rule))
+        return FALSE;
+
+    render->direct_clip_rule = state->clip_rule;
+
+    rsvg_cairo_clip_get_offset (render->parent, &matrix);
+    cairo_matrix_multiply (&matrix, &state->affine, &matrix);
+    cairo_set_matrix (render->super.cr, &matrix);
+
+    return TRUE;
+}
+
+/* Switches to computing the area as a path, starting with the shapes
+ * that went into the path of the cairo context so far.
+ */
 static void
-rsvg_cairo_clip_apply_affine (RsvgCairoClipRender *render, cairo_matrix_t *affine)
+rsvg_cairo_clip_use_geometry (RsvgCairoClipRender *render)
 {
-    RsvgCairoRender *cairo_render = &render->super;
+    cairo_t *cr = render->super.cr;
     cairo_matrix_t matrix;
-    gboolean nest = cairo_render->cr != cairo_render->initial_cr;
+    cairo_path_t *path;
+    RsvgPathBuilder *shapes;
+    guint i;
+
+    if (!render->direct)
+        return;
+
+    render->direct = FALSE;
+
+    cairo_save (cr);
+    rsvg_cairo_clip_get_offset (render->parent, &matrix);
+    cairo_set_matrix (cr, &matrix);
+    path = cairo_copy_path (cr);
+    cairo_restore (cr);
+    cairo_new_path (cr);
+
+    shapes = rsvg_path_builder_new ();
+    rsvg_cairo_add_path_to_path_builder (path, shapes);
+    cairo_path_destroy (path);
+
+    render->geometry = rsvg_path_builder_new ();
+    rsvg_path_builder_add_filled_area (render->geometry, shapes, &identity_matrix,
+                                       render->direct_clip_rule == -1
+                                       ? CAIRO_FILL_RULE_WINDING : render->direct_clip_rule);
+    rsvg_path_builder_destroy (shapes);
+
+    /* None of the layers that are open has a clip-path */
+    for (i = 0; i < render->direct_depth; i++) {
+        render->geometry_stack = g_slist_prepend (render->geometry_stack, render->geometry);
+        render->geometry = rsvg_path_builder_new ();
+    }
+    render->direct_depth = 0;
+}
+
+static void
+rsvg_cairo_clip_intersect_with_clip_path (RsvgDrawingCtx *ctx,
+                                          const char *clip_path,
+                                          RsvgBbox *bbox,
+                                          RsvgPathBuilder *content)
+{
+    RsvgNode *node;
+    RsvgPathBuilder *clip;
+
+    if (!clip_path)
+        return;
+
+    /* This returns NULL for clip paths that reference themselves */
+    node = rsvg_drawing_ctx_acquire_node_of_type (ctx, clip_path, RSVG_NODE_TYPE_CLIP_PATH);
+    if (!node)
+        return;
+
+    clip = rsvg_cairo_clip_get_geometry (ctx, node, bbox);
+    rsvg_path_builder_intersect_filled_area (content, clip);
+    rsvg_path_builder_destroy (clip);
 
-    cairo_matrix_init (&matrix,
-                       affine->xx, affine->yx,
-                       affine->xy, affine->yy,
-                       affine->x0 + (nest ? 0 : render->parent->offset_x),
-                       affine->y0 + (nest ? 0 : render->parent->offset_y));
-    cairo_set_matrix (cairo_render->cr, &matrix);
+    rsvg_drawing_ctx_release_node (ctx, node);
+}
+
+/* Clips @content, which is in the coordinates of the current state's
+ * affine, by the state's clip-path, and adds it to @geometry.
+ */
+static void
+rsvg_cairo_clip_add_content (RsvgDrawingCtx *ctx, RsvgPathBuilder *geometry, RsvgPathBuilder *content)
+{
+    RsvgState *state = rsvg_current_state (ctx);
+
+    if (state->clip_path) {
+        RsvgBbox bbox;
+
+        rsvg_bbox_init (&bbox, &state->affine);
+        bbox.virgin = !rsvg_path_builder_get_fill_bbox_in_user_space (content, &state->affine, &bbox.rect);
+
+        rsvg_cairo_clip_intersect_with_clip_path (ctx, state->clip_path, &bbox, content);
+    }
+
+    rsvg_path_builder_append (geometry, content);
 }
 
 static void
 rsvg_cairo_clip_render_pango_layout (RsvgDrawingCtx * ctx, PangoLayout * layout, double x, double y)
 {
     RsvgCairoClipRender *render = RSVG_CAIRO_CLIP_RENDER (ctx->render);
-    RsvgCairoRender *cairo_render = &render->super;
-    cairo_matrix_t affine;
+    RsvgState *state = rsvg_current_state (ctx);
+    cairo_t *cr = render->super.cr;
     PangoGravity gravity = pango_context_get_gravity (pango_layout_get_context (layout));
+    cairo_matrix_t inverse;
+    cairo_path_t *path;
+    RsvgPathBuilder *text;
+    RsvgPathBuilder *content;
     double rotation;
 
-    affine = rsvg_drawing_ctx_get_current_state_affine (ctx);
-    rsvg_cairo_clip_apply_affine (render, &affine);
+    /* cairo would go into an error state with a singular matrix */
+    inverse = state->affine;
+    if (cairo_matrix_invert (&inverse) != CAIRO_STATUS_SUCCESS)
+        return;
 
     rotation = pango_gravity_to_rotation (gravity);
 
-    cairo_save (cairo_render->cr);
-    cairo_move_to (cairo_render->cr, x, y);
+    if (rsvg_cairo_clip_prepare_direct (render, state)) {
+        cairo_save (cr);
+        cairo_move_to (cr, x, y);
+        if (rotation != 0.)
+            cairo_rotate (cr, -rotation);
+
+        pango_cairo_update_layout (cr, layout);
+        pango_cairo_layout_path (cr, layout);
+        cairo_restore (cr);
+        return;
+    }
+
+    rsvg_cairo_clip_use_geometry (render);
+
+    /* Let cairo compute the outlines of the glyphs, and take them back
+     * in the coordinates of the state's affine.
+     */
+    cairo_save (cr);
+    cairo_new_path (cr);
+    cairo_set_matrix (cr, &state->affine);
+
+    cairo_move_to (cr, x, y);
     if (rotation != 0.)
-        cairo_rotate (cairo_render->cr, -rotation);
+        cairo_rotate (cr, -rotation);
+
+    pango_cairo_update_layout (cr, layout);
+    pango_cairo_layout_path (cr, layout);
+
+    cairo_identity_matrix (cr);
+    path = cairo_copy_path (cr);
+    cairo_new_path (cr);
+    cairo_restore (cr);
 
-    pango_cairo_update_layout (cairo_render->cr, layout);
-    pango_cairo_layout_path (cairo_render->cr, layout);
+    text = rsvg_path_builder_new ();
+    rsvg_cairo_add_path_to_path_builder (path, text);
+    cairo_path_destroy (path);
 
-    cairo_restore (cairo_render->cr);
+    content = rsvg_path_builder_new ();
+    rsvg_path_builder_add_filled_area (content, text, &identity_matrix, state->clip_rule);
+    rsvg_path_builder_destroy (text);
+
+    rsvg_cairo_clip_add_content (ctx, render->geometry, content);
+    rsvg_path_builder_destroy (content);
 }
 
 static void
 rsvg_cairo_clip_render_path_builder (RsvgDrawingCtx * ctx, RsvgPathBuilder *builder)
 {
     RsvgCairoClipRender *render = RSVG_CAIRO_CLIP_RENDER (ctx->render);
-    RsvgCairoRender *cairo_render = &render->super;
     RsvgState *state = rsvg_current_state (ctx);
-    cairo_t *cr;
+    RsvgPathBuilder *content;
+    cairo_matrix_t inverse;
+
+    if (render->direct) {
+        /* cairo would go into an error state with a singular matrix */
+        inverse = state->affine;
+        if (cairo_matrix_invert (&inverse) != CAIRO_STATUS_SUCCESS)
+            return;
+    }
 
-    cr = cairo_render->cr;
+    if (rsvg_cairo_clip_prepare_direct (render, state)) {
+        rsvg_path_builder_add_to_cairo_context (builder, render->super.cr);
+        return;
+    }
 
-    rsvg_cairo_clip_apply_affine (render, &state->affine);
+    rsvg_cairo_clip_use_geometry (render);
 
-    cairo_set_fill_rule (cr, rsvg_current_state (ctx)->clip_rule);
+    content = rsvg_path_builder_new ();
+    rsvg_path_builder_add_filled_area (content, builder, &state->affine, state->clip_rule);
 
-    rsvg_path_builder_add_to_cairo_context (builder, cr);
+    rsvg_cairo_clip_add_content (ctx, render->geometry, content);
+    rsvg_path_builder_destroy (content);
 }
 
 static void
@@ -122,17 +301,48 @@ rsvg_cairo_clip_render_free (RsvgRender * self)
 {
     RsvgCairoClipRender *clip_render = RSVG_CAIRO_CLIP_RENDER (self);
 
+    g_assert (clip_render->geometry_stack == NULL);
+
     g_free (clip_render);
 }
 
 static void
 rsvg_cairo_clip_push_discrete_layer (RsvgDrawingCtx * ctx)
 {
+    RsvgCairoClipRender *render = RSVG_CAIRO_CLIP_RENDER (ctx->render);
+
+    if (rsvg_current_state (ctx)->clip_path)
+        rsvg_cairo_clip_use_geometry (render);
+
+    if (render->direct) {
+        render->direct_depth++;
+        return;
+    }
+
+    render->geometry_stack = g_slist_prepend (render->geometry_stack, render->geometry);
+    render->geometry = rsvg_path_builder_new ();
 }
 
 static void
 rsvg_cairo_clip_pop_discrete_layer (RsvgDrawingCtx * ctx)
 {
+    RsvgCairoClipRender *render = RSVG_CAIRO_CLIP_RENDER (ctx->render);
+    RsvgPathBuilder *layer;
+
+    if (render->direct) {
+        g_assert (render->direct_depth > 0);
+        render->direct_depth--;
+        return;
+    }
+
+    layer = render->geometry;
+    g_assert (render->geometry_stack != NULL);
+
+    render->geometry = render->geometry_stack->data;
+    render->geometry_stack = g_slist_delete_link (render->geometry_stack, render->geometry_stack);
+
+    rsvg_cairo_clip_add_content (ctx, render->geometry, layer);
+    rsvg_path_builder_destroy (layer);
 }
 
 static void
@@ -175,31 +385,55 @@ rsvg_cairo_clip_render_new (cairo_t *cr, RsvgCairoRender *parent)
     cairo_render->font_map_for_testing    = parent->font_map_for_testing;
 
     clip_render->parent = parent;
+    clip_render->direct = FALSE;
+    clip_render->direct_clip_rule = -1;
+    clip_render->direct_depth = 0;
+    clip_render->geometry = NULL;
+    clip_render->geometry_stack = NULL;
 
     return render;
 }
 
-void
-rsvg_cairo_clip (RsvgDrawingCtx * ctx, RsvgNode *node_clip_path, RsvgBbox * bbox)
+/* Draws the children of the clipPath with a clip render, which gets
+ * returned.  With @direct, their outlines go into the path of the parent's
+ * cairo context for as long as that works out.
+ */
+static RsvgCairoClipRender *
+rsvg_cairo_clip_render_children (RsvgDrawingCtx *ctx, RsvgNode *node_clip_path, RsvgBbox *bbox,
+                                 gboolean direct)
 {
-    RsvgClipPath *clip;
     RsvgCairoClipRender *clip_render;
-    RsvgCairoRender *save = RSVG_CAIRO_RENDER (ctx->render);
-    cairo_matrix_t affinesave;
+    RsvgRender *save = ctx->render;
+    RsvgCairoRender *parent;
     RsvgState *clip_path_state;
-    cairo_t *cr;
+    cairo_matrix_t affinesave;
+    char *clip_path_save;
 
     g_assert (rsvg_node_get_type (node_clip_path) == RSVG_NODE_TYPE_CLIP_PATH);
-    clip = rsvg_rust_cnode_get_impl (node_clip_path);
 
-    cr = save->cr;
-    clip_render = RSVG_CAIRO_CLIP_RENDER (rsvg_cairo_clip_render_new (cr, save));
+    /* Clip paths can be nested, through the clip-path property of a
+     * clipPath or of its children.
+     */
+    if (save->type == RSVG_RENDER_TYPE_CAIRO_CLIP)
+        parent = RSVG_CAIRO_CLIP_RENDER (save)->parent;
+    else
+        parent = RSVG_CAIRO_RENDER (save);
+
+    clip_render = RSVG_CAIRO_CLIP_RENDER (rsvg_cairo_clip_render_new (parent->cr, parent));
+    if (direct) {
+        clip_render->direct = TRUE;
+        cairo_new_path (parent->cr);
+    } else {
+        clip_render->geometry = rsvg_path_builder_new ();
+    }
     ctx->render = &clip_render->super.super;
 
     clip_path_state = rsvg_node_get_state (node_clip_path);
 
     /* Horribly dirty hack to have the bbox premultiplied to everything */
-    if (clip->units == objectBoundingBox) {
+    affinesave = clip_path_state->affine;
+
+    if (rsvg_node_clip_path_get_units (node_clip_path) == objectBoundingBox) {
         cairo_matrix_t bbtransform;
         cairo_matrix_init (&bbtransform,
                            bbox->rect.width,
@@ -208,22 +442,112 @@ rsvg_cairo_clip (RsvgDrawingCtx * ctx, RsvgNode *node_clip_path, RsvgBbox * bbox
                            bbox->rect.height,
                            bbox->rect.x,
                            bbox->rect.y);
-        affinesave = clip_path_state->affine;
         cairo_matrix_multiply (&clip_path_state->affine, &bbtransform, &clip_path_state->affine);
     }
 
+    /* The clipPath's own clip-path is not in the coordinates of its
+     * contents, so keep its discrete layer from applying it.
+     */
+    clip_path_save = clip_path_state->clip_path;
+    clip_path_state->clip_path = NULL;
+
     rsvg_state_push (ctx);
     rsvg_node_draw_children (node_clip_path, ctx, 0);
     rsvg_state_pop (ctx);
 
-    if (clip->units == objectBoundingBox)
-        clip_path_state->affine = affinesave;
+    clip_path_state->affine = affinesave;
+    clip_path_state->clip_path = clip_path_save;
+
+    ctx->render = save;
+
+    return clip_render;
+}
+
+RsvgPathBuilder *
+rsvg_cairo_clip_get_geometry (RsvgDrawingCtx * ctx, RsvgNode *node_clip_path, RsvgBbox * bbox)
+{
+    RsvgCairoClipRender *clip_render;
+    RsvgState *clip_path_state;
+    RsvgPathBuilder *geometry;
+
+    clip_render = rsvg_cairo_clip_render_children (ctx, node_clip_path, bbox, FALSE);
+
+    /* Tidy up the areas that got appended */
+    geometry = rsvg_path_builder_new ();
+    rsvg_path_builder_add_filled_area (geometry, clip_render->geometry, &identity_matrix,
+                                       CAIRO_FILL_RULE_WINDING);
+    rsvg_path_builder_destroy (clip_render->geometry);
+
+    rsvg_cairo_clip_render_free (&clip_render->super.super);
+
+    clip_path_state = rsvg_node_get_state (node_clip_path);
+
+    if (clip_path_state->clip_path) {
+        RsvgBbox geometry_bbox;
+
+        /* FIXME: clip paths in user space get applied before the bbox of
+         * the clipped element is known, so use the bbox of the clip path.
+         */
+        if (!bbox) {
+            RsvgState *state = rsvg_current_state (ctx);
+
+            rsvg_bbox_init (&geometry_bbox, &state->affine);
+            geometry_bbox.virgin = !rsvg_path_builder_get_fill_bbox_in_user_space (geometry,
+                                                                                   &state->affine,
+                                                                                   &geometry_bbox.rect);
+            bbox = &geometry_bbox;
+        }
+
+        rsvg_cairo_clip_intersect_with_clip_path (ctx, clip_path_state->clip_path, bbox, geometry);
+    }
+
+    return geometry;
+}
+
+void
+rsvg_cairo_clip (RsvgDrawingCtx * ctx, RsvgNode *node_clip_path, RsvgBbox * bbox)
+{
+    RsvgCairoRender *render = RSVG_CAIRO_RENDER (ctx->render);
+    RsvgCairoClipRender *clip_render;
+    RsvgPathBuilder *geometry;
+    cairo_t *cr = render->cr;
+    cairo_matrix_t matrix;
+
+    /* The clipPath's own clip-path needs the geometry */
+    if (!rsvg_node_get_state (node_clip_path)->clip_path) {
+        /* The children leave the matrix of the cairo context changed */
+        cairo_save (cr);
+        clip_render = rsvg_cairo_clip_render_children (ctx, node_clip_path, bbox, TRUE);
+        cairo_restore (cr);
+
+        if (clip_render->direct) {
+            cairo_set_fill_rule (cr, clip_render->direct_clip_rule == -1
+                                 ? CAIRO_FILL_RULE_WINDING : clip_render->direct_clip_rule);
+            cairo_clip (cr);
+
+            rsvg_cairo_clip_render_free (&clip_render->super.super);
+            return;
+        }
+
+        geometry = clip_render->geometry;
+        rsvg_cairo_clip_render_free (&clip_render->super.super);
+    } else {
+        geometry = rsvg_cairo_clip_get_geometry (ctx, node_clip_path, bbox);
+    }
+
+    /* The geometry is in the coordinates of the state's affine, which
+     * don't include the offset of the render's surface.
+     */
+    cairo_save (cr);
+    rsvg_cairo_clip_get_offset (render, &matrix);
+    cairo_set_matrix (cr, &matrix);
+
+    cairo_new_path (cr);
+    rsvg_path_builder_add_to_cairo_context (geometry, cr);
+    rsvg_path_builder_destroy (geometry);
 
-    g_assert (clip_render->super.cr_stack == NULL);
-    g_assert (clip_render->super.bb_stack == NULL);
-    g_assert (clip_render->super.surfaces_stack == NULL);
+    cairo_restore (cr);
 
-    g_free (ctx->render);
+    cairo_set_fill_rule (cr, CAIRO_FILL_RULE_WINDING);
     cairo_clip (cr);
-    ctx->render = &save->super;
 }
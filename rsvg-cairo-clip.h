@@ -30,6 +30,7 @@
 
 #include "rsvg-mask.h"
 #include "rsvg-cairo-render.h"
+#include "rsvg-path-builder.h"
 #include <cairo.h>
 
 G_BEGIN_DECLS 
@@ -37,6 +38,12 @@ G_BEGIN_DECLS
 G_GNUC_INTERNAL
 void rsvg_cairo_clip (RsvgDrawingCtx * ctx, RsvgNode *node_clip_path, RsvgBbox * bbox);
 
+/* Returns the area that the clipPath covers, as a new path in the
+ * coordinate system of the current state.
+ */
+G_GNUC_INTERNAL
+RsvgPathBuilder *rsvg_cairo_clip_get_geometry (RsvgDrawingCtx * ctx, RsvgNode *node_clip_path, RsvgBbox * bbox);
+
 G_END_DECLS
 
 #endif
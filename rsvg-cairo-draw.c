@@ -33,6 +33,7 @@
 #include "rsvg-cairo-render.h"
 #include "rsvg-cairo-clip.h"
 #include "rsvg-styles.h"
+#include "rsvg-fonts.h"
 #include "rsvg-path-builder.h"
 #include "rsvg-filter.h"
 #include "rsvg-structure.h"
@@ -156,8 +157,9 @@ set_font_options_for_testing (PangoContext *context)
     cairo_font_options_destroy (font_options);
 }
 
+/* The test suite only sees its own font, plus the ones that the document brings along */
 static void
-create_font_config_for_testing (RsvgCairoRender *render)
+create_font_config_for_testing (RsvgCairoRender *render, RsvgFonts *fonts)
 {
     const char *font_path = SRCDIR "/tests/resources/LiberationSans-Regular.ttf";
 
@@ -169,12 +171,14 @@ create_font_config_for_testing (RsvgCairoRender *render)
     if (!FcConfigAppFontAddFile (render->font_config_for_testing, (const FcChar8 *) font_path)) {
         g_error ("Could not load font file \"%s\" for tests; aborting", font_path);
     }
+
+    rsvg_fonts_add_to_config (fonts, render->font_config_for_testing);
 }
 
 static PangoFontMap *
-get_font_map_for_testing (RsvgCairoRender *render)
+get_font_map_for_testing (RsvgCairoRender *render, RsvgFonts *fonts)
 {
-    create_font_config_for_testing (render);
+    create_font_config_for_testing (render, fonts);
 
     if (!render->font_map_for_testing) {
         render->font_map_for_testing = pango_cairo_font_map_new_for_font_type (CAIRO_FONT_TYPE_FT);
@@ -195,14 +199,14 @@ rsvg_cairo_create_pango_context (RsvgDrawingCtx * ctx)
     double dpi_y;
 
 #ifdef HAVE_PANGOFT2
-    if (ctx->is_testing) {
-        fontmap = get_font_map_for_testing (render);
-    } else {
+    if (ctx->is_testing)
+        fontmap = get_font_map_for_testing (render, ctx->fonts);
+    else
+        fontmap = rsvg_fonts_get_font_map (ctx->fonts);
+
+    if (fontmap == NULL)
 #endif
         fontmap = pango_cairo_font_map_get_default ();
-#ifdef HAVE_PANGOFT2
-    }
-#endif
 
     context = pango_font_map_create_context (fontmap);
     pango_cairo_update_context (render->cr, context);
@@ -226,8 +230,22 @@ setup_cr_for_stroke (cairo_t *cr, RsvgDrawingCtx *ctx, RsvgState *state)
     cairo_set_miter_limit (cr, state->miter_limit);
     cairo_set_line_cap (cr, (cairo_line_cap_t) state->cap);
     cairo_set_line_join (cr, (cairo_line_join_t) state->join);
-    cairo_set_dash (cr, state->dash.dash, state->dash.n_dash,
-                    rsvg_length_normalize (&state->dash.offset, ctx));
+
+    if (state->dash.n_dash > 0) {
+        double *dashes;
+        int i;
+
+        dashes = g_new (double, state->dash.n_dash);
+
+        for (i = 0; i < state->dash.n_dash; i++)
+            dashes[i] = rsvg_length_normalize (&state->dash.dash[i], ctx) * state->path_length_scale;
+
+        cairo_set_dash (cr, dashes, state->dash.n_dash,
+                        rsvg_length_normalize (&state->dash.offset, ctx) * state->path_length_scale);
+        g_free (dashes);
+    } else {
+        cairo_set_dash (cr, NULL, 0, 0.0);
+    }
 }
 
 void
@@ -312,6 +330,38 @@ rsvg_cairo_render_pango_layout (RsvgDrawingCtx * ctx, PangoLayout * layout, doub
 
 
 
+/* The other way around from rsvg_path_builder_add_to_cairo_context() */
+void
+rsvg_cairo_add_path_to_path_builder (cairo_path_t *path, RsvgPathBuilder *builder)
+{
+    int i;
+
+    for (i = 0; i < path->num_data; i += path->data[i].header.length) {
+        cairo_path_data_t *data = &path->data[i];
+
+        switch (data->header.type) {
+        case CAIRO_PATH_MOVE_TO:
+            rsvg_path_builder_move_to (builder, data[1].point.x, data[1].point.y);
+            break;
+
+        case CAIRO_PATH_LINE_TO:
+            rsvg_path_builder_line_to (builder, data[1].point.x, data[1].point.y);
+            break;
+
+        case CAIRO_PATH_CURVE_TO:
+            rsvg_path_builder_curve_to (builder,
+                                        data[1].point.x, data[1].point.y,
+                                        data[2].point.x, data[2].point.y,
+                                        data[3].point.x, data[3].point.y);
+            break;
+
+        case CAIRO_PATH_CLOSE_PATH:
+            rsvg_path_builder_close_path (builder);
+            break;
+        }
+    }
+}
+
 void
 rsvg_cairo_render_path_builder (RsvgDrawingCtx * ctx, RsvgPathBuilder *builder)
 {
@@ -518,28 +568,34 @@ rsvg_cairo_set_cairo_context (RsvgDrawingCtx *ctx, cairo_t *cr)
     render->cr = cr;
 }
 
-static void
-rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx, RsvgBbox *bbox)
+/* Renders the contents of @node_mask into a new surface the size of the
+ * render, and turns it into a mask according to the mask's mask-type,
+ * multiplied by @opacity.  Only the alpha channel of the result is
+ * meaningful.  If the <mask> element has a mask property itself, that
+ * mask gets applied to the result as well.
+ */
+static cairo_surface_t *
+rsvg_cairo_get_mask_surface (RsvgDrawingCtx *ctx, RsvgNode *node_mask, RsvgBbox *bbox, guint8 opacity)
 {
     RsvgCairoRender *render = RSVG_CAIRO_RENDER (ctx->render);
     cairo_surface_t *surface;
     cairo_t *mask_cr, *save_cr;
-    RsvgState *state = rsvg_current_state (ctx);
+    RsvgState *mask_state;
     guint8 *pixels;
     guint32 width = render->width, height = render->height;
     guint32 rowstride = width * 4, row, i;
     cairo_matrix_t affinesave;
     double sx, sy, sw, sh;
-    gboolean nest = cr != render->initial_cr;
     RsvgMask *self;
 
     g_assert (rsvg_node_get_type (node_mask) == RSVG_NODE_TYPE_MASK);
     self = rsvg_rust_cnode_get_impl (node_mask);
+    mask_state = rsvg_node_get_state (node_mask);
 
     surface = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, width, height);
     if (cairo_surface_status (surface) != CAIRO_STATUS_SUCCESS) {
         cairo_surface_destroy (surface);
-        return;
+        return NULL;
     }
 
     pixels = cairo_image_surface_get_data (surface);
@@ -572,7 +628,6 @@ rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx
     /* Horribly dirty hack to have the bbox premultiplied to everything */
     if (self->contentunits == objectBoundingBox) {
         cairo_matrix_t bbtransform;
-        RsvgState *mask_state;
 
         cairo_matrix_init (&bbtransform,
                            bbox->rect.width,
@@ -582,8 +637,6 @@ rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx
                            bbox->rect.x,
                            bbox->rect.y);
 
-        mask_state = rsvg_node_get_state (node_mask);
-
         affinesave = mask_state->affine;
         cairo_matrix_multiply (&mask_state->affine, &bbtransform, &mask_state->affine);
         rsvg_drawing_ctx_push_view_box (ctx, 1, 1);
@@ -594,20 +647,27 @@ rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx
     rsvg_state_pop (ctx);
 
     if (self->contentunits == objectBoundingBox) {
-        RsvgState *mask_state;
-
         rsvg_drawing_ctx_pop_view_box (ctx);
 
-        mask_state = rsvg_node_get_state (node_mask);
         mask_state->affine = affinesave;
     }
 
     render->cr = save_cr;
 
+    cairo_destroy (mask_cr);
+    cairo_surface_flush (surface);
+
     for (row = 0; row < height; row++) {
         guint8 *row_data = (pixels + (row * rowstride));
         for (i = 0; i < width; i++) {
             guint32 *pixel = (guint32 *) row_data + i;
+
+            if (mask_state->mask_type == RSVG_MASK_TYPE_ALPHA) {
+                /* The mask is the alpha channel of the contents */
+                *pixel = (((*pixel >> 24) * opacity + 127) / 255) << 24;
+                continue;
+            }
+
             /*
              *  Assuming, the pixel is linear RGB (not sRGB)
              *  y = luminance
@@ -629,11 +689,49 @@ rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx
              */
             *pixel = ((((*pixel & 0x00ff0000) >> 16) * 14042 +
                        ((*pixel & 0x0000ff00) >>  8) * 47240 +
-                       ((*pixel & 0x000000ff)      ) * 4769    ) * state->opacity);
+                       ((*pixel & 0x000000ff)      ) * 4769    ) * opacity);
         }
     }
 
-    cairo_destroy (mask_cr);
+    cairo_surface_mark_dirty (surface);
+
+    if (mask_state->mask) {
+        RsvgNode *nested;
+
+        /* This returns NULL for masks that reference themselves */
+        nested = rsvg_drawing_ctx_acquire_node_of_type (ctx, mask_state->mask, RSVG_NODE_TYPE_MASK);
+        if (nested) {
+            cairo_surface_t *nested_surface;
+
+            nested_surface = rsvg_cairo_get_mask_surface (ctx, nested, bbox, 0xff);
+            rsvg_drawing_ctx_release_node (ctx, nested);
+
+            if (nested_surface) {
+                mask_cr = cairo_create (surface);
+                cairo_set_operator (mask_cr, CAIRO_OPERATOR_DEST_IN);
+                cairo_set_source_surface (mask_cr, nested_surface, 0, 0);
+                cairo_paint (mask_cr);
+                cairo_destroy (mask_cr);
+
+                cairo_surface_destroy (nested_surface);
+            }
+        }
+    }
+
+    return surface;
+}
+
+static void
+rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx, RsvgBbox *bbox)
+{
+    RsvgCairoRender *render = RSVG_CAIRO_RENDER (ctx->render);
+    RsvgState *state = rsvg_current_state (ctx);
+    gboolean nest = cr != render->initial_cr;
+    cairo_surface_t *surface;
+
+    surface = rsvg_cairo_get_mask_surface (ctx, node_mask, bbox, state->opacity);
+    if (!surface)
+        return;
 
     cairo_identity_matrix (cr);
     cairo_mask_surface (cr, surface,
@@ -642,6 +740,109 @@ rsvg_cairo_generate_mask (cairo_t * cr, RsvgNode *node_mask, RsvgDrawingCtx *ctx
     cairo_surface_destroy (surface);
 }
 
+static gboolean
+rsvg_cairo_blend_mode_to_operator (RsvgBlendMode mode, cairo_operator_t *op)
+{
+    switch (mode) {
+    case RSVG_BLEND_MODE_NORMAL:
+        *op = CAIRO_OPERATOR_OVER;
+        break;
+    case RSVG_BLEND_MODE_MULTIPLY:
+        *op = CAIRO_OPERATOR_MULTIPLY;
+        break;
+    case RSVG_BLEND_MODE_SCREEN:
+        *op = CAIRO_OPERATOR_SCREEN;
+        break;
+    case RSVG_BLEND_MODE_OVERLAY:
+        *op = CAIRO_OPERATOR_OVERLAY;
+        break;
+    case RSVG_BLEND_MODE_DARKEN:
+        *op = CAIRO_OPERATOR_DARKEN;
+        break;
+    case RSVG_BLEND_MODE_LIGHTEN:
+        *op = CAIRO_OPERATOR_LIGHTEN;
+        break;
+    case RSVG_BLEND_MODE_COLOR_DODGE:
+        *op = CAIRO_OPERATOR_COLOR_DODGE;
+        break;
+    case RSVG_BLEND_MODE_COLOR_BURN:
+        *op = CAIRO_OPERATOR_COLOR_BURN;
+        break;
+    case RSVG_BLEND_MODE_HARD_LIGHT:
+        *op = CAIRO_OPERATOR_HARD_LIGHT;
+        break;
+    case RSVG_BLEND_MODE_SOFT_LIGHT:
+        *op = CAIRO_OPERATOR_SOFT_LIGHT;
+        break;
+    case RSVG_BLEND_MODE_DIFFERENCE:
+        *op = CAIRO_OPERATOR_DIFFERENCE;
+        break;
+    case RSVG_BLEND_MODE_EXCLUSION:
+        *op = CAIRO_OPERATOR_EXCLUSION;
+        break;
+    case RSVG_BLEND_MODE_HUE:
+        *op = CAIRO_OPERATOR_HSL_HUE;
+        break;
+    case RSVG_BLEND_MODE_SATURATION:
+        *op = CAIRO_OPERATOR_HSL_SATURATION;
+        break;
+    case RSVG_BLEND_MODE_COLOR:
+        *op = CAIRO_OPERATOR_HSL_COLOR;
+        break;
+    case RSVG_BLEND_MODE_LUMINOSITY:
+        *op = CAIRO_OPERATOR_HSL_LUMINOSITY;
+        break;
+    case RSVG_BLEND_MODE_PLUS_LIGHTER:
+        *op = CAIRO_OPERATOR_ADD;
+        break;
+    default:
+        /* cairo has no operator for this one */
+        return FALSE;
+    }
+
+    return TRUE;
+}
+
+/* Composites @surface onto a copy of the layer that is being drawn to,
+ * with a blend mode that cairo has no operator for.  The result already
+ * contains the layer's contents, so it has to be painted with
+ * CAIRO_OPERATOR_SOURCE.
+ */
+static cairo_surface_t *
+rsvg_cairo_blend_with_backdrop (RsvgCairoRender *render, cairo_surface_t *surface, RsvgBlendMode mode)
+{
+    gboolean nest = render->cr != render->initial_cr;
+    cairo_surface_t *backdrop, *source;
+    cairo_t *cr;
+
+    backdrop = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, render->width, render->height);
+    source = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, render->width, render->height);
+
+    if (cairo_surface_status (backdrop) != CAIRO_STATUS_SUCCESS
+        || cairo_surface_status (source) != CAIRO_STATUS_SUCCESS) {
+        cairo_surface_destroy (backdrop);
+        cairo_surface_destroy (source);
+        return NULL;
+    }
+
+    cr = cairo_create (backdrop);
+    cairo_set_source_surface (cr, cairo_get_target (render->cr),
+                              nest ? 0 : -render->offset_x,
+                              nest ? 0 : -render->offset_y);
+    cairo_paint (cr);
+    cairo_destroy (cr);
+
+    cr = cairo_create (source);
+    cairo_set_source_surface (cr, surface, 0, 0);
+    cairo_paint (cr);
+    cairo_destroy (cr);
+
+    rsvg_blend_mode_composite (backdrop, source, mode);
+    cairo_surface_destroy (source);
+
+    return backdrop;
+}
+
 static void
 rsvg_cairo_push_render_stack (RsvgDrawingCtx * ctx)
 {
@@ -656,9 +857,7 @@ rsvg_cairo_push_render_stack (RsvgDrawingCtx * ctx)
         RsvgNode *node;
         node = rsvg_drawing_ctx_acquire_node_of_type (ctx, rsvg_current_state (ctx)->clip_path, RSVG_NODE_TYPE_CLIP_PATH);
         if (node) {
-            RsvgClipPath *clip_path = rsvg_rust_cnode_get_impl (node);
-
-            switch (clip_path->units) {
+            switch (rsvg_node_clip_path_get_units (node)) {
             case userSpaceOnUse:
                 rsvg_cairo_clip (ctx, node, NULL);
                 break;
@@ -677,6 +876,7 @@ rsvg_cairo_push_render_stack (RsvgDrawingCtx * ctx)
 
     if (state->opacity == 0xFF
         && !state->filter && !state->mask && !lateclip && (state->comp_op == CAIRO_OPERATOR_OVER)
+        && (state->mix_blend_mode == RSVG_BLEND_MODE_NORMAL) && !state->isolate
         && (state->enable_background == RSVG_ENABLE_BACKGROUND_ACCUMULATE))
         return;
 
@@ -727,16 +927,16 @@ rsvg_cairo_pop_render_stack (RsvgDrawingCtx * ctx)
     cairo_t *child_cr = render->cr;
     RsvgNode *lateclip = NULL;
     cairo_surface_t *surface = NULL;
+    cairo_surface_t *blended = NULL;
     RsvgState *state = rsvg_current_state (ctx);
+    cairo_operator_t op;
     gboolean nest, needs_destroy = FALSE;
 
     if (rsvg_current_state (ctx)->clip_path) {
         RsvgNode *node;
         node = rsvg_drawing_ctx_acquire_node_of_type (ctx, rsvg_current_state (ctx)->clip_path, RSVG_NODE_TYPE_CLIP_PATH);
         if (node) {
-            RsvgClipPath *cp = rsvg_rust_cnode_get_impl (node);
-
-            if (cp->units == objectBoundingBox) {
+            if (rsvg_node_clip_path_get_units (node) == objectBoundingBox) {
                 lateclip = node;
             } else {
                 rsvg_drawing_ctx_release_node (ctx, node);
@@ -746,34 +946,37 @@ rsvg_cairo_pop_render_stack (RsvgDrawingCtx * ctx)
 
     if (state->opacity == 0xFF
         && !state->filter && !state->mask && !lateclip && (state->comp_op == CAIRO_OPERATOR_OVER)
+        && (state->mix_blend_mode == RSVG_BLEND_MODE_NORMAL) && !state->isolate
         && (state->enable_background == RSVG_ENABLE_BACKGROUND_ACCUMULATE))
         return;
 
     surface = cairo_get_target (child_cr);
 
     if (state->filter) {
-        RsvgNode *filter;
         cairo_surface_t *output;
 
         output = render->surfaces_stack->data;
         render->surfaces_stack = g_list_delete_link (render->surfaces_stack, render->surfaces_stack);
 
-        filter = rsvg_drawing_ctx_acquire_node_of_type (ctx, state->filter, RSVG_NODE_TYPE_FILTER);
-        if (filter) {
-            needs_destroy = TRUE;
-            surface = rsvg_filter_render (filter, output, ctx, &render->bbox, "2103");
-            rsvg_drawing_ctx_release_node (ctx, filter);
+        needs_destroy = TRUE;
+        surface = rsvg_filter_render (rsvg_drawing_ctx_get_current_node (ctx), output, ctx, &render->bbox);
 
-            /* Don't destroy the output surface, it's owned by child_cr */
-        }
+        /* Don't destroy the output surface, it's owned by child_cr */
     }
 
     render->cr = (cairo_t *) render->cr_stack->data;
     render->cr_stack = g_list_delete_link (render->cr_stack, render->cr_stack);
 
+    if (state->mix_blend_mode == RSVG_BLEND_MODE_NORMAL) {
+        op = state->comp_op;
+    } else if (!rsvg_cairo_blend_mode_to_operator (state->mix_blend_mode, &op)) {
+        blended = rsvg_cairo_blend_with_backdrop (render, surface, state->mix_blend_mode);
+        op = blended ? CAIRO_OPERATOR_SOURCE : CAIRO_OPERATOR_OVER;
+    }
+
     nest = render->cr != render->initial_cr;
     cairo_identity_matrix (render->cr);
-    cairo_set_source_surface (render->cr, surface,
+    cairo_set_source_surface (render->cr, blended ? blended : surface,
                               nest ? 0 : render->offset_x,
                               nest ? 0 : render->offset_y);
 
@@ -782,7 +985,7 @@ rsvg_cairo_pop_render_stack (RsvgDrawingCtx * ctx)
         rsvg_drawing_ctx_release_node (ctx, lateclip);
     }
 
-    cairo_set_operator (render->cr, state->comp_op);
+    cairo_set_operator (render->cr, op);
 
     if (state->mask) {
         RsvgNode *mask;
@@ -809,6 +1012,10 @@ rsvg_cairo_pop_render_stack (RsvgDrawingCtx * ctx)
     if (needs_destroy) {
         cairo_surface_destroy (surface);
     }
+
+    if (blended) {
+        cairo_surface_destroy (blended);
+    }
 }
 
 void
@@ -865,6 +1072,44 @@ rsvg_cairo_get_surface_of_node (RsvgDrawingCtx *ctx,
     return surface;
 }
 
+/**
+ * rsvg_cairo_get_background_image:
+ *
+ * Composites the layers that are currently being drawn, for the
+ * BackgroundImage filter input.
+ *
+ * Returns: (transfer full) (nullable): a new #cairo_surface_t, or %NULL
+ */
+cairo_surface_t *
+rsvg_cairo_get_background_image (RsvgDrawingCtx *ctx)
+{
+    RsvgCairoRender *render = RSVG_CAIRO_RENDER (ctx->render);
+    cairo_surface_t *surface;
+    cairo_t *cr;
+    GList *i;
+
+    surface = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, render->width, render->height);
+    if (cairo_surface_status (surface) != CAIRO_STATUS_SUCCESS) {
+        cairo_surface_destroy (surface);
+        return NULL;
+    }
+
+    cr = cairo_create (surface);
+
+    for (i = g_list_last (render->cr_stack); i != NULL; i = g_list_previous (i)) {
+        cairo_t *draw = i->data;
+        gboolean nest = draw != render->initial_cr;
+        cairo_set_source_surface (cr, cairo_get_target (draw),
+                                  nest ? 0 : -render->offset_x,
+                                  nest ? 0 : -render->offset_y);
+        cairo_paint (cr);
+    }
+
+    cairo_destroy (cr);
+
+    return surface;
+}
+
 cairo_surface_t *
 rsvg_cairo_surface_from_pixbuf (const GdkPixbuf *pixbuf)
 {
@@ -45,6 +45,8 @@ G_GNUC_INTERNAL
 void         rsvg_cairo_render_surface          (RsvgDrawingCtx *ctx, cairo_surface_t *surface,
                                                  double x, double y, double w, double h);
 G_GNUC_INTERNAL
+void         rsvg_cairo_add_path_to_path_builder (cairo_path_t *path, RsvgPathBuilder *builder);
+G_GNUC_INTERNAL
 cairo_t *rsvg_cairo_get_cairo_context (RsvgDrawingCtx *ctx);
 G_GNUC_INTERNAL
 void rsvg_cairo_set_cairo_context (RsvgDrawingCtx *ctx, cairo_t *cr);
@@ -59,6 +61,8 @@ void         rsvg_cairo_add_clipping_rect       (RsvgDrawingCtx *ctx,
 G_GNUC_INTERNAL
 cairo_surface_t*rsvg_cairo_get_surface_of_node  (RsvgDrawingCtx *ctx, RsvgNode *drawable, 
                                                  double width, double height);
+G_GNUC_INTERNAL
+cairo_surface_t *rsvg_cairo_get_background_image (RsvgDrawingCtx *ctx);
 
 G_END_DECLS
 
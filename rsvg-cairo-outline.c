@@ -0,0 +1,228 @@
+/* -*- Mode: C; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set sw=4 sts=4 expandtab: */
+/*
+   rsvg-cairo-outline.c: Convert text to paths
+
+   This program is free software; you can redistribute it and/or
+   modify it under the terms of the GNU Library General Public License as
+   published by the Free Software Foundation; either version 2 of the
+   License, or (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+   Library General Public License for more details.
+
+   You should have received a copy of the GNU Library General Public
+   License along with this program; if not, write to the
+   Free Software Foundation, Inc., 59 Temple Place - Suite 330,
+   Boston, MA 02111-1307, USA.
+*/
+
+#include "config.h"
+
+#include "rsvg-cairo-draw.h"
+#include "rsvg-cairo-outline.h"
+#include "rsvg-cairo-render.h"
+#include "rsvg-styles.h"
+#include "rsvg-path-builder.h"
+
+#include <pango/pangocairo.h>
+
+typedef struct RsvgCairoOutlineRender RsvgCairoOutlineRender;
+
+/* This render doesn't draw anything.  Instead, it collects the outlines
+ * of the glyphs that the text code lays out, in the coordinates of the
+ * current state's affine.  Unlike the clip render, it keeps the curves
+ * and the individual contours of the glyphs, and it ignores fill, stroke,
+ * clip-path and the like.
+ */
+struct RsvgCairoOutlineRender {
+    RsvgCairoRender super;
+
+    RsvgPathBuilder *outlines;
+};
+
+#define RSVG_CAIRO_OUTLINE_RENDER(render) (_RSVG_RENDER_CIC ((render), RSVG_RENDER_TYPE_CAIRO_OUTLINE, RsvgCairoOutlineRender))
+
+static void
+rsvg_cairo_outline_render_pango_layout (RsvgDrawingCtx * ctx, PangoLayout * layout, double x, double y)
+{
+    RsvgCairoOutlineRender *render = RSVG_CAIRO_OUTLINE_RENDER (ctx->render);
+    RsvgState *state = rsvg_current_state (ctx);
+    cairo_t *cr = render->super.cr;
+    cairo_matrix_t inverse;
+    cairo_path_t *path;
+
+    if (!state->visible)
+        return;
+
+    /* cairo would go into an error state with a singular matrix */
+    inverse = state->affine;
+    if (cairo_matrix_invert (&inverse) != CAIRO_STATUS_SUCCESS)
+        return;
+
+    cairo_save (cr);
+    cairo_new_path (cr);
+    cairo_set_matrix (cr, &state->affine);
+
+    cairo_move_to (cr, x, y);
+    pango_cairo_update_layout (cr, layout);
+    pango_cairo_layout_path (cr, layout);
+
+    /* Take the outlines back in device space */
+    cairo_identity_matrix (cr);
+    path = cairo_copy_path (cr);
+    cairo_new_path (cr);
+    cairo_restore (cr);
+
+    rsvg_cairo_add_path_to_path_builder (path, render->outlines);
+    cairo_path_destroy (path);
+}
+
+/* Glyphs that get bent along a textPath come here as paths */
+static void
+rsvg_cairo_outline_render_path_builder (RsvgDrawingCtx * ctx, RsvgPathBuilder *builder)
+{
+    RsvgCairoOutlineRender *render = RSVG_CAIRO_OUTLINE_RENDER (ctx->render);
+    RsvgState *state = rsvg_current_state (ctx);
+    cairo_t *cr = render->super.cr;
+    cairo_matrix_t inverse;
+    cairo_path_t *path;
+
+    if (!state->visible)
+        return;
+
+    inverse = state->affine;
+    if (cairo_matrix_invert (&inverse) != CAIRO_STATUS_SUCCESS)
+        return;
+
+    cairo_save (cr);
+    cairo_new_path (cr);
+    cairo_set_matrix (cr, &state->affine);
+
+    rsvg_path_builder_add_to_cairo_context (builder, cr);
+
+    /* Take the outlines back in device space */
+    cairo_identity_matrix (cr);
+    path = cairo_copy_path (cr);
+    cairo_new_path (cr);
+    cairo_restore (cr);
+
+    rsvg_cairo_add_path_to_path_builder (path, render->outlines);
+    cairo_path_destroy (path);
+}
+
+static void
+rsvg_cairo_outline_render_surface (RsvgDrawingCtx *ctx,
+                                   cairo_surface_t *surface,
+                                   double src_x,
+                                   double src_y,
+                                   double w,
+                                   double h)
+{
+}
+
+static void
+rsvg_cairo_outline_render_free (RsvgRender * self)
+{
+    RsvgCairoOutlineRender *outline_render = RSVG_CAIRO_OUTLINE_RENDER (self);
+
+    g_free (outline_render);
+}
+
+static void
+rsvg_cairo_outline_push_discrete_layer (RsvgDrawingCtx * ctx)
+{
+}
+
+static void
+rsvg_cairo_outline_pop_discrete_layer (RsvgDrawingCtx * ctx)
+{
+}
+
+static void
+rsvg_cairo_outline_add_clipping_rect (RsvgDrawingCtx * ctx, double x, double y, double w, double h)
+{
+}
+
+static RsvgRender *
+rsvg_cairo_outline_render_new (RsvgCairoRender *parent)
+{
+    RsvgCairoOutlineRender *outline_render = g_new0 (RsvgCairoOutlineRender, 1);
+    RsvgCairoRender *cairo_render = &outline_render->super;
+    RsvgRender *render = &cairo_render->super;
+
+    g_assert (parent->super.type == RSVG_RENDER_TYPE_CAIRO);
+
+    render->type = RSVG_RENDER_TYPE_CAIRO_OUTLINE;
+    render->free = rsvg_cairo_outline_render_free;
+    render->create_pango_context = rsvg_cairo_create_pango_context;
+    render->render_pango_layout = rsvg_cairo_outline_render_pango_layout;
+    render->render_path_builder = rsvg_cairo_outline_render_path_builder;
+    render->render_surface = rsvg_cairo_outline_render_surface;
+    render->pop_discrete_layer = rsvg_cairo_outline_pop_discrete_layer;
+    render->push_discrete_layer = rsvg_cairo_outline_push_discrete_layer;
+    render->add_clipping_rect = rsvg_cairo_outline_add_clipping_rect;
+    render->get_surface_of_node = NULL;
+
+    cairo_render->initial_cr = parent->cr;
+    cairo_render->cr         = parent->cr;
+    cairo_render->width      = parent->width;
+    cairo_render->height     = parent->height;
+    cairo_render->offset_x   = parent->offset_x;
+    cairo_render->offset_y   = parent->offset_y;
+    cairo_render->cr_stack   = NULL;
+    cairo_render->bbox       = parent->bbox;
+    cairo_render->bb_stack   = NULL;
+
+    /* We don't copy or ref the following two; we just share them */
+    cairo_render->font_config_for_testing = parent->font_config_for_testing;
+    cairo_render->font_map_for_testing    = parent->font_map_for_testing;
+
+    outline_render->outlines = rsvg_path_builder_new ();
+
+    return render;
+}
+
+RsvgPathBuilder *
+rsvg_cairo_get_text_outlines (RsvgDrawingCtx *ctx, RsvgNode *node)
+{
+    RsvgCairoOutlineRender *outline_render;
+    RsvgRender *save = ctx->render;
+    RsvgState *state;
+    RsvgNode *parent;
+    cairo_matrix_t affine;
+    RsvgPathBuilder *outlines;
+
+    g_assert (rsvg_node_get_type (node) == RSVG_NODE_TYPE_TEXT);
+
+    state = rsvg_current_state (ctx);
+
+    parent = rsvg_node_get_parent (node);
+    if (parent) {
+        rsvg_state_reconstruct (state, parent);
+        parent = rsvg_node_unref (parent);
+    }
+
+    /* The text's own transform gets multiplied into this when it is
+     * drawn, so the outlines end up in its user space.
+     */
+    affine = rsvg_node_get_state (node)->personal_affine;
+    if (cairo_matrix_invert (&affine) != CAIRO_STATUS_SUCCESS)
+        return NULL;
+
+    state->affine = affine;
+
+    outline_render = RSVG_CAIRO_OUTLINE_RENDER (rsvg_cairo_outline_render_new (RSVG_CAIRO_RENDER (save)));
+    ctx->render = &outline_render->super.super;
+
+    rsvg_drawing_ctx_draw_node_from_stack (ctx, node, 0);
+
+    outlines = outline_render->outlines;
+
+    rsvg_cairo_outline_render_free (ctx->render);
+    ctx->render = save;
+
+    return outlines;
+}
@@ -0,0 +1,40 @@
+/* -*- Mode: C; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set sw=4 sts=4 expandtab: */
+/*
+   rsvg-cairo-outline.h: Convert text to paths
+
+   This program is free software; you can redistribute it and/or
+   modify it under the terms of the GNU Library General Public License as
+   published by the Free Software Foundation; either version 2 of the
+   License, or (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+   Library General Public License for more details.
+
+   You should have received a copy of the GNU Library General Public
+   License along with this program; if not, write to the
+   Free Software Foundation, Inc., 59 Temple Place - Suite 330,
+   Boston, MA 02111-1307, USA.
+*/
+
+#ifndef RSVG_CAIRO_OUTLINE_H
+#define RSVG_CAIRO_OUTLINE_H
+
+#include "rsvg-cairo-render.h"
+#include "rsvg-path-builder.h"
+
+G_BEGIN_DECLS
+
+/* Returns the outlines of the glyphs of the <text> element @node, with
+ * all of its positioning applied, as a new path in the text's user
+ * space; that is, inside its own transform.  The ancestors of @node
+ * provide the font and the rest of the inherited properties.
+ */
+G_GNUC_INTERNAL
+RsvgPathBuilder *rsvg_cairo_get_text_outlines (RsvgDrawingCtx *ctx, RsvgNode *node);
+
+G_END_DECLS
+
+#endif
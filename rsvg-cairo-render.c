@@ -172,6 +172,7 @@ rsvg_cairo_new_drawing_ctx (cairo_t * cr, RsvgHandle * handle)
     draw->state = NULL;
 
     draw->defs = handle->priv->defs;
+    draw->fonts = handle->priv->fonts;
     draw->dpi_x = handle->priv->dpi_x;
     draw->dpi_y = handle->priv->dpi_y;
     draw->vb.rect.width = data.em;
@@ -180,6 +181,7 @@ rsvg_cairo_new_drawing_ctx (cairo_t * cr, RsvgHandle * handle)
     draw->vb_stack = NULL;
     draw->drawsub_stack = NULL;
     draw->acquired_nodes = NULL;
+    draw->drawn_nodes = NULL;
     draw->is_testing = handle->priv->is_testing;
 
     rsvg_state_push (draw);
@@ -59,8 +59,11 @@ static const char *implemented_features[] = {
 };
 static const guint nb_implemented_features = G_N_ELEMENTS (implemented_features);
 
-static const char **implemented_extensions = NULL;
-static const guint nb_implemented_extensions = 0;
+/* Keep these sorted alphabetically!  These are used with bsearch() */
+static const char *implemented_extensions[] = {
+    "http://www.w3.org/1999/xhtml"      /* simple text paragraphs inside <foreignObject> */
+};
+static const guint nb_implemented_extensions = G_N_ELEMENTS (implemented_extensions);
 
 static int
 rsvg_feature_compare (const void *a, const void *b)
@@ -78,6 +78,33 @@ display_error (GError * err)
     }
 }
 
+/* Prints the errors that were found while loading a file, prefixed with
+ * its name; returns whether there were none.
+ */
+static gboolean
+print_diagnostics (RsvgHandle *rsvg, const char *filename, FILE *output_file)
+{
+    char *report;
+    char **lines;
+    int i;
+
+    report = rsvg_handle_get_diagnostics (rsvg);
+    if (report == NULL)
+        return TRUE;
+
+    lines = g_strsplit (report, "\n", -1);
+
+    for (i = 0; lines[i] != NULL; i++) {
+        if (*lines[i] != '\0')
+            fprintf (output_file, "%s:%s\n", filename, lines[i]);
+    }
+
+    g_strfreev (lines);
+    g_free (report);
+
+    return FALSE;
+}
+
 static cairo_status_t
 rsvg_cairo_write_func (void *closure, const unsigned char *data, unsigned int length)
 {
@@ -115,6 +142,7 @@ main (int argc, char **argv)
     double zoom = 1.0;
     double dpi_x = -1.0;
     double dpi_y = -1.0;
+    double time_secs = -1.0;
     int width = -1;
     int height = -1;
     int bVersion = 0;
@@ -128,6 +156,9 @@ main (int argc, char **argv)
     gboolean unlimited = FALSE;
     gboolean keep_image_data = FALSE;
     gboolean no_keep_image_data = FALSE;
+    gboolean lint = FALSE;
+    gboolean lint_failed = FALSE;
+    gboolean text_to_paths = FALSE;
     GError *error = NULL;
 
     int i;
@@ -168,6 +199,8 @@ main (int argc, char **argv)
          N_("output filename [optional; defaults to stdout]"), NULL},
         {"export-id", 'i', 0, G_OPTION_ARG_STRING, &export_id,
          N_("SVG id of object to export [optional; defaults to exporting all objects]"), N_("<object id>")},
+        {"time", 't', 0, G_OPTION_ARG_DOUBLE, &time_secs,
+         N_("time in seconds at which to render animations [optional; defaults to ignoring animations]"), N_("<float>")},
         {"keep-aspect-ratio", 'a', 0, G_OPTION_ARG_NONE, &keep_aspect_ratio,
          N_("whether to preserve the aspect ratio [optional; defaults to FALSE]"), NULL},
         {"background-color", 'b', 0, G_OPTION_ARG_STRING, &background_color_str,
@@ -175,6 +208,10 @@ main (int argc, char **argv)
         {"unlimited", 'u', 0, G_OPTION_ARG_NONE, &unlimited, N_("Allow huge SVG files"), NULL},
         {"keep-image-data", 0, 0, G_OPTION_ARG_NONE, &keep_image_data, N_("Keep image data"), NULL},
         {"no-keep-image-data", 0, 0, G_OPTION_ARG_NONE, &no_keep_image_data, N_("Don't keep image data"), NULL},
+        {"lint", 0, 0, G_OPTION_ARG_NONE, &lint,
+         N_("report the errors in the SVG files instead of converting them"), NULL},
+        {"text-to-paths", 0, 0, G_OPTION_ARG_NONE, &text_to_paths,
+         N_("write out the SVG with its text converted to paths"), NULL},
         {"version", 'v', 0, G_OPTION_ARG_NONE, &bVersion, N_("show version information"), NULL},
         {G_OPTION_REMAINING, 0, 0, G_OPTION_ARG_FILENAME_ARRAY, &args, NULL, N_("[FILE...]")},
         {NULL}
@@ -216,10 +253,30 @@ main (int argc, char **argv)
         while (args[n_args] != NULL)
             n_args++;
 
+    /* The SVG gets written out as it is, without rendering it */
+    if (text_to_paths) {
+        if (format != NULL && !g_str_equal (format, "svg")) {
+            g_printerr (_("Text can only be converted to paths in SVG output.\n"));
+            exit (1);
+        }
+
+        if (n_args > 1) {
+            g_printerr (_("Text can only be converted to paths in one SVG file at a time.\n"));
+            exit (1);
+        }
+
+        if (lint || export_id != NULL || background_color_str != NULL || keep_aspect_ratio
+            || zoom != 1.0 || x_zoom != 1.0 || y_zoom != 1.0 || width != -1 || height != -1) {
+            g_printerr (_("--text-to-paths cannot be used with --lint, --export-id, --background-color, "
+                          "--keep-aspect-ratio, --zoom, --x-zoom, --y-zoom, --width or --height.\n"));
+            exit (1);
+        }
+    }
+
     if (n_args == 0) {
         n_args = 1;
         using_stdin = TRUE;
-    } else if (n_args > 1 && (!format || !(!strcmp (format, "ps") || !strcmp (format, "eps") || !strcmp (format, "pdf")))) {
+    } else if (n_args > 1 && !lint && (!format || !(!strcmp (format, "ps") || !strcmp (format, "eps") || !strcmp (format, "pdf")))) {
         g_printerr (_("Multiple SVG files are only allowed for PDF and (E)PS output.\n"));
         exit (1);
     }
@@ -273,6 +330,13 @@ main (int argc, char **argv)
         g_clear_object (&stream);
         g_clear_object (&file);
 
+        if (error != NULL && lint) {
+            fprintf (output_file, "%s: %s\n", using_stdin ? "stdin" : args[i], error->message);
+            g_clear_error (&error);
+            lint_failed = TRUE;
+            continue;
+        }
+
         if (error != NULL) {
             g_printerr (_("Error reading SVG:"));
             display_error (error);
@@ -280,6 +344,29 @@ main (int argc, char **argv)
             exit (1);
         }
 
+        if (lint) {
+            if (!print_diagnostics (rsvg, using_stdin ? "stdin" : args[i], output_file))
+                lint_failed = TRUE;
+
+            g_object_unref (rsvg);
+            continue;
+        }
+
+        if (time_secs >= 0.0)
+            rsvg_handle_set_time (rsvg, time_secs);
+
+        if (text_to_paths) {
+            char *svg = rsvg_handle_get_svg_with_text_as_paths (rsvg);
+
+            if (svg != NULL) {
+                fputs (svg, output_file);
+                g_free (svg);
+            }
+
+            g_object_unref (rsvg);
+            continue;
+        }
+
         export_lookup_id = get_lookup_id_from_command_line (export_id);
         if (export_lookup_id != NULL
             && !rsvg_handle_has_sub (rsvg, export_lookup_id)) {
@@ -439,5 +526,5 @@ main (int argc, char **argv)
 
     rsvg_cleanup ();
 
-    return 0;
+    return lint_failed ? 1 : 0;
 }
@@ -41,8 +41,6 @@
 
 #include <libxml/parser.h>
 
-#include <libcroco/libcroco.h>
-
 #define POINTS_PER_INCH (72.0)
 #define CM_PER_INCH     (2.54)
 #define MM_PER_INCH     (25.4)
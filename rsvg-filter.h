@@ -30,24 +30,17 @@
 
 G_BEGIN_DECLS 
 
-typedef struct  {
-    int x0, y0, x1, y1;
-} RsvgIRect;
-
-typedef RsvgCoordUnits RsvgFilterUnits;
-
-struct _RsvgFilter {
-    RsvgLength x, y, width, height;
-    RsvgFilterUnits filterunits;
-    RsvgFilterUnits primitiveunits;
-};
+/* Implemented in rust/src/filters/ */
 
+/* Applies the filter property of @node, a list of url() references to
+ * filter elements and filter functions like blur(), to @source.  If @node
+ * is NULL, @source is returned unchanged.
+ */
 G_GNUC_INTERNAL
-cairo_surface_t *rsvg_filter_render (RsvgNode *filter_node,
+cairo_surface_t *rsvg_filter_render (RsvgNode *node,
                                      cairo_surface_t *source,
-                                     RsvgDrawingCtx *context, 
-                                     RsvgBbox *dimentions, 
-                                     char *channelmap);
+                                     RsvgDrawingCtx *context,
+                                     RsvgBbox *bbox);
 
 G_GNUC_INTERNAL
 RsvgNode    *rsvg_new_filter	    (const char *element_name, RsvgNode *parent);
@@ -0,0 +1,371 @@
+/* -*- Mode: C; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set sw=4 sts=4 expandtab: */
+/*
+   rsvg-fonts.c: Fonts that come with the document
+
+   This program is free software; you can redistribute it and/or
+   modify it under the terms of the GNU Library General Public License as
+   published by the Free Software Foundation; either version 2 of the
+   License, or (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+   Library General Public License for more details.
+
+   You should have received a copy of the GNU Library General Public
+   License along with this program; if not, write to the
+   Free Software Foundation, Inc., 59 Temple Place - Suite 330,
+   Boston, MA 02111-1307, USA.
+*/
+
+#include "config.h"
+
+#include <string.h>
+#include <glib/gstdio.h>
+
+#include "rsvg-fonts.h"
+
+#ifdef HAVE_PANGOFT2
+#include <unistd.h>
+#include <pango/pangocairo.h>
+#endif
+
+/* Each handle keeps the fonts that its document brings along:
+ *
+ * - Font files from @font-face rules.  Fontconfig can only load fonts
+ *   from files, so we write the data to temporary files, which get added
+ *   to a private FcConfig along with the installed fonts.  Pango knows
+ *   the fonts by the family name in the files, not by the one in the
+ *   @font-face rule, so we keep a table to map one to the other.
+ *
+ * - SVG fonts, that is, <font> elements.  The text code lays them out by
+ *   itself; see rust/src/font.rs.
+ *
+ * Without fontconfig, only SVG fonts are supported.
+ */
+struct _RsvgFonts {
+    GHashTable *families;       /* family key -> family name for Pango */
+    GHashTable *svg_fonts;      /* family key -> RsvgNode for a <font> */
+    GPtrArray *files;           /* paths of the temporary font files */
+
+#ifdef HAVE_PANGOFT2
+    FcConfig *config;
+    PangoFontMap *font_map;
+#endif
+};
+
+/* Family names are case-insensitive, and may be quoted in font-family lists */
+static char *
+get_family_key (const char *family)
+{
+    char *name, *key;
+    gsize len;
+
+    name = g_strstrip (g_strdup (family));
+    len = strlen (name);
+
+    if (len >= 2 && (name[0] == '\'' || name[0] == '"') && name[len - 1] == name[0]) {
+        name[len - 1] = '\0';
+        key = g_ascii_strdown (name + 1, -1);
+    } else {
+        key = g_ascii_strdown (name, -1);
+    }
+
+    g_free (name);
+
+    return key;
+}
+
+RsvgFonts *
+rsvg_fonts_new (void)
+{
+    RsvgFonts *fonts = g_new0 (RsvgFonts, 1);
+
+    fonts->families = g_hash_table_new_full (g_str_hash, g_str_equal, g_free, g_free);
+    fonts->svg_fonts = g_hash_table_new_full (g_str_hash, g_str_equal, g_free, (GDestroyNotify) rsvg_node_unref);
+    fonts->files = g_ptr_array_new ();
+
+    return fonts;
+}
+
+void
+rsvg_fonts_free (RsvgFonts *fonts)
+{
+    guint i;
+
+#ifdef HAVE_PANGOFT2
+    if (fonts->font_map)
+        g_object_unref (fonts->font_map);
+
+    if (fonts->config)
+        FcConfigDestroy (fonts->config);
+#endif
+
+    for (i = 0; i < fonts->files->len; i++) {
+        char *path = g_ptr_array_index (fonts->files, i);
+
+        g_unlink (path);
+        g_free (path);
+    }
+
+    g_ptr_array_free (fonts->files, TRUE);
+    g_hash_table_destroy (fonts->families);
+    g_hash_table_destroy (fonts->svg_fonts);
+    g_free (fonts);
+}
+
+#ifdef HAVE_PANGOFT2
+
+static void
+rsvg_fonts_add_family (RsvgFonts *fonts, const char *family, const char *pango_family)
+{
+    g_hash_table_replace (fonts->families, get_family_key (family), g_strdup (pango_family));
+}
+
+/* Writes @data to a new temporary file, and returns its path */
+static char *
+write_temporary_font_file (const char *data, gsize len)
+{
+    char *path;
+    int fd;
+
+    fd = g_file_open_tmp ("rsvg-font-XXXXXX", &path, NULL);
+    if (fd == -1)
+        return NULL;
+
+    close (fd);
+
+    if (!g_file_set_contents (path, data, len, NULL)) {
+        g_unlink (path);
+        g_free (path);
+        return NULL;
+    }
+
+    return path;
+}
+
+gboolean
+rsvg_fonts_add_data (RsvgFonts *fonts, const char *family, const char *data, gsize len)
+{
+    FcPattern *pattern;
+    FcChar8 *file_family;
+    char *path;
+    int count;
+
+    path = write_temporary_font_file (data, len);
+    if (path == NULL)
+        return FALSE;
+
+    /* This also tells us whether FreeType can load the data at all */
+    pattern = FcFreeTypeQuery ((const FcChar8 *) path, 0, NULL, &count);
+
+    if (pattern == NULL || FcPatternGetString (pattern, FC_FAMILY, 0, &file_family) != FcResultMatch) {
+        if (pattern)
+            FcPatternDestroy (pattern);
+
+        g_unlink (path);
+        g_free (path);
+        return FALSE;
+    }
+
+    rsvg_fonts_add_family (fonts, family, (const char *) file_family);
+    FcPatternDestroy (pattern);
+
+    g_ptr_array_add (fonts->files, path);
+
+    /* The font map gets created again with the new file when it is needed */
+    if (fonts->font_map) {
+        g_object_unref (fonts->font_map);
+        fonts->font_map = NULL;
+
+        FcConfigDestroy (fonts->config);
+        fonts->config = NULL;
+    }
+
+    return TRUE;
+}
+
+/* Returns the family of the first installed font whose @property is @name */
+static char *
+find_installed_family (const char *property, const char *name)
+{
+    FcPattern *pattern;
+    FcObjectSet *object_set;
+    FcFontSet *font_set;
+    FcChar8 *family;
+    char *result = NULL;
+
+    pattern = FcPatternCreate ();
+    FcPatternAddString (pattern, property, (const FcChar8 *) name);
+    object_set = FcObjectSetBuild (FC_FAMILY, NULL);
+
+    font_set = FcFontList (NULL, pattern, object_set);
+
+    if (font_set) {
+        if (font_set->nfont > 0
+            && FcPatternGetString (font_set->fonts[0], FC_FAMILY, 0, &family) == FcResultMatch)
+            result = g_strdup ((const char *) family);
+
+        FcFontSetDestroy (font_set);
+    }
+
+    FcObjectSetDestroy (object_set);
+    FcPatternDestroy (pattern);
+
+    return result;
+}
+
+gboolean
+rsvg_fonts_add_local (RsvgFonts *fonts, const char *family, const char *name)
+{
+    char *installed_family;
+
+    installed_family = find_installed_family (FC_FULLNAME, name);
+    if (installed_family == NULL)
+        installed_family = find_installed_family (FC_POSTSCRIPT_NAME, name);
+
+    if (installed_family == NULL)
+        return FALSE;
+
+    rsvg_fonts_add_family (fonts, family, installed_family);
+    g_free (installed_family);
+
+    return TRUE;
+}
+
+void
+rsvg_fonts_add_to_config (RsvgFonts *fonts, FcConfig *config)
+{
+    guint i;
+
+    for (i = 0; i < fonts->files->len; i++)
+        FcConfigAppFontAddFile (config, g_ptr_array_index (fonts->files, i));
+}
+
+PangoFontMap *
+rsvg_fonts_get_font_map (RsvgFonts *fonts)
+{
+    if (fonts->files->len == 0)
+        return NULL;
+
+    if (fonts->font_map == NULL) {
+        fonts->config = FcInitLoadConfigAndFonts ();
+        rsvg_fonts_add_to_config (fonts, fonts->config);
+
+        fonts->font_map = pango_cairo_font_map_new_for_font_type (CAIRO_FONT_TYPE_FT);
+        pango_fc_font_map_set_config (PANGO_FC_FONT_MAP (fonts->font_map), fonts->config);
+    }
+
+    return fonts->font_map;
+}
+
+#else /* !HAVE_PANGOFT2 */
+
+gboolean
+rsvg_fonts_add_data (RsvgFonts *fonts, const char *family, const char *data, gsize len)
+{
+    return FALSE;
+}
+
+gboolean
+rsvg_fonts_add_local (RsvgFonts *fonts, const char *family, const char *name)
+{
+    return FALSE;
+}
+
+#endif /* HAVE_PANGOFT2 */
+
+void
+rsvg_fonts_add_svg_font (RsvgFonts *fonts, const char *family, RsvgNode *font)
+{
+    g_hash_table_replace (fonts->svg_fonts, get_family_key (family), rsvg_node_ref (font));
+}
+
+/* Fontconfig always has a font for these */
+static const char *generic_families[] = {
+    "serif", "sans-serif", "monospace", "cursive", "fantasy"
+};
+
+/* Whether Pango has a font for the family @key, either installed or from
+ * an @font-face rule.
+ */
+static gboolean
+has_pango_family (RsvgFonts *fonts, PangoFontMap *font_map, const char *key)
+{
+    PangoFontFamily **families;
+    int n_families, i;
+    guint j;
+    gboolean found = FALSE;
+
+    if (g_hash_table_lookup (fonts->families, key) != NULL)
+        return TRUE;
+
+    for (j = 0; j < G_N_ELEMENTS (generic_families); j++)
+        if (strcmp (key, generic_families[j]) == 0)
+            return TRUE;
+
+    pango_font_map_list_families (font_map, &families, &n_families);
+
+    for (i = 0; i < n_families && !found; i++)
+        found = g_ascii_strcasecmp (pango_font_family_get_name (families[i]), key) == 0;
+
+    g_free (families);
+
+    return found;
+}
+
+RsvgNode *
+rsvg_fonts_lookup_svg_font (RsvgFonts *fonts, PangoFontMap *font_map, const char *family)
+{
+    RsvgNode *font = NULL;
+    gboolean found = FALSE;
+    char **names;
+    int i;
+
+    if (family == NULL || g_hash_table_size (fonts->svg_fonts) == 0)
+        return NULL;
+
+    names = g_strsplit (family, ",", -1);
+
+    for (i = 0; names[i] != NULL && !found; i++) {
+        char *key = get_family_key (names[i]);
+
+        font = g_hash_table_lookup (fonts->svg_fonts, key);
+        found = font != NULL || has_pango_family (fonts, font_map, key);
+        g_free (key);
+    }
+
+    g_strfreev (names);
+
+    return font;
+}
+
+char *
+rsvg_fonts_resolve_family (RsvgFonts *fonts, const char *family)
+{
+    GString *result;
+    char **names;
+    int i;
+
+    if (g_hash_table_size (fonts->families) == 0)
+        return g_strdup (family);
+
+    result = g_string_new (NULL);
+    names = g_strsplit (family, ",", -1);
+
+    for (i = 0; names[i] != NULL; i++) {
+        char *key = get_family_key (names[i]);
+        const char *pango_family = g_hash_table_lookup (fonts->families, key);
+
+        if (i > 0)
+            g_string_append_c (result, ',');
+
+        g_string_append (result, pango_family ? pango_family : names[i]);
+        g_free (key);
+    }
+
+    g_strfreev (names);
+
+    return g_string_free (result, FALSE);
+}
@@ -0,0 +1,116 @@
+/* -*- Mode: C; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set sw=4 sts=4 expandtab: */
+/*
+   rsvg-fonts.h: Fonts that come with the document
+
+   This program is free software; you can redistribute it and/or
+   modify it under the terms of the GNU Library General Public License as
+   published by the Free Software Foundation; either version 2 of the
+   License, or (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+   Library General Public License for more details.
+
+   You should have received a copy of the GNU Library General Public
+   License along with this program; if not, write to the
+   Free Software Foundation, Inc., 59 Temple Place - Suite 330,
+   Boston, MA 02111-1307, USA.
+*/
+
+#ifndef RSVG_FONTS_H
+#define RSVG_FONTS_H
+
+#include "rsvg-private.h"
+#include "rsvg-path-builder.h"
+
+#include <pango/pango.h>
+
+#ifdef HAVE_PANGOFT2
+#include <pango/pangofc-fontmap.h>
+#endif
+
+G_BEGIN_DECLS
+
+G_GNUC_INTERNAL
+RsvgFonts *rsvg_fonts_new (void);
+
+G_GNUC_INTERNAL
+void rsvg_fonts_free (RsvgFonts *fonts);
+
+/* Adds the font file in @data, from a @font-face rule, under the name
+ * @family.  Returns FALSE if the data is not a font that we can load.
+ */
+G_GNUC_INTERNAL
+gboolean rsvg_fonts_add_data (RsvgFonts *fonts, const char *family, const char *data, gsize len);
+
+/* Makes @family refer to the installed font with the full name or
+ * PostScript name @name, as in src: local(...).  Returns FALSE if there
+ * is no such font.
+ */
+G_GNUC_INTERNAL
+gboolean rsvg_fonts_add_local (RsvgFonts *fonts, const char *family, const char *name);
+
+/* Registers the <font> element @font under the name @family */
+G_GNUC_INTERNAL
+void rsvg_fonts_add_svg_font (RsvgFonts *fonts, const char *family, RsvgNode *font);
+
+/* Goes through the font-family list @family in order, and returns the
+ * SVG font for the first family that is available.  Returns NULL if that
+ * family is one that Pango has in @font_map instead, or if none of them
+ * is available.  This does not add a reference to the node.
+ */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_fonts_lookup_svg_font (RsvgFonts *fonts, PangoFontMap *font_map, const char *family);
+
+/* Returns a newly-allocated copy of the font-family list @family, with
+ * the names from @font-face rules replaced by the names that Pango knows
+ * the fonts by.
+ */
+G_GNUC_INTERNAL
+char *rsvg_fonts_resolve_family (RsvgFonts *fonts, const char *family);
+
+#ifdef HAVE_PANGOFT2
+/* Adds the font files to @config, for the font map that the test suite uses */
+G_GNUC_INTERNAL
+void rsvg_fonts_add_to_config (RsvgFonts *fonts, FcConfig *config);
+
+/* Returns a font map with the installed fonts plus the font files, or
+ * NULL if there are no font files.  This does not add a reference to
+ * the font map.
+ */
+G_GNUC_INTERNAL
+PangoFontMap *rsvg_fonts_get_font_map (RsvgFonts *fonts);
+#endif
+
+/* Implemented in rust/src/font.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_font_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/font.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_font_face_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/font.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_glyph_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/font.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_missing_glyph_new (const char *element_name, RsvgNode *parent);
+
+/* Implemented in rust/src/font.rs
+ *
+ * Lays out @text with the glyphs of the SVG font @font, with the start of
+ * the baseline at the origin, and returns its advance.  The outlines of
+ * the glyphs get appended to @builder, unless it is NULL.
+ */
+G_GNUC_INTERNAL
+double rsvg_node_font_layout_text (RsvgNode *font, const char *text,
+                                   double font_size, double letter_spacing,
+                                   RsvgPathBuilder *builder);
+
+G_END_DECLS
+
+#endif
@@ -39,6 +39,8 @@
 
 #include "rsvg-private.h"
 #include "rsvg-defs.h"
+#include "rsvg-fonts.h"
+#include "rsvg-styles.h"
 
 enum {
     PROP_0,
@@ -78,10 +80,9 @@ rsvg_handle_init (RsvgHandle * self)
     self->priv->dpi_x = rsvg_internal_dpi_x;
     self->priv->dpi_y = rsvg_internal_dpi_y;
 
-    self->priv->css_props = g_hash_table_new_full (g_str_hash,
-                                                   g_str_equal,
-                                                   g_free,
-                                                   (GDestroyNotify) g_hash_table_destroy);
+    self->priv->stylesheet = rsvg_stylesheet_new ();
+    self->priv->fonts = rsvg_fonts_new ();
+    self->priv->diagnostics = rsvg_diagnostics_new ();
 
     self->priv->ctxt = NULL;
     self->priv->currentnode = NULL;
@@ -95,6 +96,11 @@ rsvg_handle_init (RsvgHandle * self)
     self->priv->in_loop = FALSE;
 
     self->priv->is_testing = FALSE;
+
+    self->priv->element_atts = NULL;
+    self->priv->animations = NULL;
+    self->priv->time = 0.0;
+    self->priv->has_time = FALSE;
 }
 
 static void
@@ -134,7 +140,30 @@ rsvg_handle_dispose (GObject *instance)
     rsvg_defs_free (self->priv->defs);
     self->priv->defs = NULL;
 
-    g_hash_table_destroy (self->priv->css_props);
+    if (self->priv->stylesheet) {
+        rsvg_stylesheet_free (self->priv->stylesheet);
+        self->priv->stylesheet = NULL;
+    }
+
+    if (self->priv->fonts) {
+        rsvg_fonts_free (self->priv->fonts);
+        self->priv->fonts = NULL;
+    }
+
+    if (self->priv->diagnostics) {
+        rsvg_diagnostics_free (self->priv->diagnostics);
+        self->priv->diagnostics = NULL;
+    }
+
+    if (self->priv->animations) {
+        g_ptr_array_free (self->priv->animations, TRUE);
+        self->priv->animations = NULL;
+    }
+
+    if (self->priv->element_atts) {
+        g_ptr_array_free (self->priv->element_atts, TRUE);
+        self->priv->element_atts = NULL;
+    }
 
     self->priv->treebase = rsvg_node_unref (self->priv->treebase);
     self->priv->currentnode = rsvg_node_unref (self->priv->currentnode);
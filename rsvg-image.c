@@ -178,6 +178,10 @@ rsvg_node_image_set_atts (RsvgNode *node, gpointer impl, RsvgHandle *handle, Rsv
     /* path is used by some older adobe illustrator versions */
     if ((value = rsvg_property_bag_lookup (atts, "path"))
         || (value = rsvg_property_bag_lookup (atts, "xlink:href"))) {
+        /* attributes get applied again for animations */
+        if (image->surface)
+            cairo_surface_destroy (image->surface);
+
         image->surface = rsvg_cairo_surface_new_from_href (handle,
                                                            value, 
                                                            NULL);
@@ -84,40 +84,3 @@ rsvg_new_mask (const char *element_name, RsvgNode *parent)
                                 rsvg_mask_draw,
                                 g_free);
 }
-
-static void
-rsvg_clip_path_set_atts (RsvgNode *node, gpointer impl, RsvgHandle *handle, RsvgPropertyBag *atts)
-{
-    RsvgClipPath *clip_path = impl;
-    const char *value;
-
-    if ((value = rsvg_property_bag_lookup (atts, "clipPathUnits"))) {
-        if (!strcmp (value, "objectBoundingBox"))
-            clip_path->units = objectBoundingBox;
-        else
-            clip_path->units = userSpaceOnUse;
-    }
-}
-
-static void
-rsvg_clip_path_draw (RsvgNode *node, gpointer impl, RsvgDrawingCtx *ctx, int dominate)
-{
-    /* nothing; this gets drawn specially in rsvg-cairo-draw.c */
-}
-
-RsvgNode *
-rsvg_new_clip_path (const char *element_name, RsvgNode *parent)
-{
-    RsvgClipPath *clip_path;
-
-    clip_path = g_new0 (RsvgClipPath, 1);
-    clip_path->units = userSpaceOnUse;
-
-    return rsvg_rust_cnode_new (RSVG_NODE_TYPE_CLIP_PATH,
-                                parent,
-                                rsvg_state_new (),
-                                clip_path,
-                                rsvg_clip_path_set_atts,
-                                rsvg_clip_path_draw,
-                                g_free); 
-}
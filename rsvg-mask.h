@@ -48,14 +48,13 @@ struct _RsvgMask {
 G_GNUC_INTERNAL
 RsvgNode *rsvg_new_mask	    (const char *element_name, RsvgNode *node);
 
-typedef struct _RsvgClipPath RsvgClipPath;
-
-struct _RsvgClipPath {
-    RsvgCoordUnits units;
-};
+/* Implemented in rust/src/clip_path.rs */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_node_clip_path_new (const char *element_name, RsvgNode *parent);
 
+/* Implemented in rust/src/clip_path.rs */
 G_GNUC_INTERNAL
-RsvgNode *rsvg_new_clip_path	(const char *element_name, RsvgNode *node);
+RsvgCoordUnits rsvg_node_clip_path_get_units (RsvgNode *node);
 
 G_END_DECLS
 #endif
@@ -39,9 +39,59 @@ G_BEGIN_DECLS
 
 typedef struct _RsvgPathBuilder RsvgPathBuilder;
 
+G_GNUC_INTERNAL
+RsvgPathBuilder *rsvg_path_builder_new (void);
+
+G_GNUC_INTERNAL
+void rsvg_path_builder_destroy (RsvgPathBuilder *builder);
+
+G_GNUC_INTERNAL
+void rsvg_path_builder_move_to (RsvgPathBuilder *builder, double x, double y);
+
+G_GNUC_INTERNAL
+void rsvg_path_builder_line_to (RsvgPathBuilder *builder, double x, double y);
+
+G_GNUC_INTERNAL
+void rsvg_path_builder_curve_to (RsvgPathBuilder *builder,
+                                 double x2, double y2,
+                                 double x3, double y3,
+                                 double x4, double y4);
+
+G_GNUC_INTERNAL
+void rsvg_path_builder_close_path (RsvgPathBuilder *builder);
+
+/* Appends the subpaths of @other to @builder */
+G_GNUC_INTERNAL
+void rsvg_path_builder_append (RsvgPathBuilder *builder, RsvgPathBuilder *other);
+
 G_GNUC_INTERNAL
 void rsvg_path_builder_add_to_cairo_context (RsvgPathBuilder *builder, cairo_t *cr);
 
+/* Adds the area covered by filling @builder, transformed by @affine,
+ * to the area covered by @result.  The result is flattened.
+ */
+G_GNUC_INTERNAL
+void rsvg_path_builder_add_filled_area (RsvgPathBuilder *result,
+                                        RsvgPathBuilder *builder,
+                                        const cairo_matrix_t *affine,
+                                        cairo_fill_rule_t fill_rule);
+
+/* Replaces the area covered by @result with its intersection with the
+ * area covered by @clip.  Both are filled with the nonzero rule.
+ */
+G_GNUC_INTERNAL
+void rsvg_path_builder_intersect_filled_area (RsvgPathBuilder *result, RsvgPathBuilder *clip);
+
+/* Exact bounding box of the area covered by filling @builder, which has
+ * already been transformed by @affine; the bbox is in the coordinates
+ * from before the transformation.  Returns FALSE if the path doesn't
+ * cover anything.
+ */
+G_GNUC_INTERNAL
+gboolean rsvg_path_builder_get_fill_bbox_in_user_space (RsvgPathBuilder *builder,
+                                                        const cairo_matrix_t *affine,
+                                                        cairo_rectangle_t *out_rect);
+
 G_END_DECLS
 
 #endif /* RSVG_PATH_BUILDER_H */
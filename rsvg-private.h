@@ -52,9 +52,17 @@ typedef GHashTable RsvgPropertyBag;
 typedef struct _RsvgState RsvgState;
 typedef struct _RsvgDefs RsvgDefs;
 typedef struct _RsvgNode RsvgNode;
-typedef struct _RsvgFilter RsvgFilter;
 typedef struct _RsvgNodeChars RsvgNodeChars;
 
+/* Opaque; implemented in rust/src/css.rs */
+typedef struct _RsvgStylesheet RsvgStylesheet;
+
+/* Opaque; implemented in rust/src/diagnostics.rs */
+typedef struct _RsvgDiagnostics RsvgDiagnostics;
+
+/* Opaque; implemented in rsvg-fonts.c */
+typedef struct _RsvgFonts RsvgFonts;
+
 /* prepare for gettext */
 #ifndef _
 #define _(X) X
@@ -155,7 +163,13 @@ struct RsvgHandlePrivate {
      */
     GSList *element_name_stack;
 
-    GHashTable *css_props;
+    RsvgStylesheet *stylesheet;
+
+    /* Fonts from @font-face rules and <font> elements */
+    RsvgFonts *fonts;
+
+    /* Errors found in the elements while loading */
+    RsvgDiagnostics *diagnostics;
 
     /* not a handler stack. each nested handler keeps
      * track of its parent
@@ -184,6 +198,17 @@ struct RsvgHandlePrivate {
     GInputStream *data_input_stream; /* for rsvg_handle_write of svgz data */
 
     gboolean is_testing; /* Are we being run from the test suite? */
+
+    /* Attributes of the elements while loading; only those of the
+     * animated elements are kept after loading, if the document has
+     * animation elements at all.
+     */
+    GPtrArray *element_atts;
+    GPtrArray *animations;
+    gboolean has_animation_elements;
+
+    double time;                /* see rsvg_handle_set_time() */
+    gboolean has_time;
 };
 
 /* Keep this in sync with rust/src/viewbox.rs::RsvgViewBox */
@@ -199,12 +224,14 @@ struct RsvgDrawingCtx {
     RsvgState *state;
     GError **error;
     RsvgDefs *defs;
+    RsvgFonts *fonts;
     PangoContext *pango_context;
     double dpi_x, dpi_y;
     RsvgViewBox vb;
     GSList *vb_stack;
     GSList *drawsub_stack;
     GSList *acquired_nodes;
+    GSList *drawn_nodes;        /* the nodes being drawn, innermost first */
     gboolean is_testing;
 };
 
@@ -216,7 +243,8 @@ typedef enum {
   RSVG_RENDER_TYPE_BASE,
 
   RSVG_RENDER_TYPE_CAIRO = 8,
-  RSVG_RENDER_TYPE_CAIRO_CLIP
+  RSVG_RENDER_TYPE_CAIRO_CLIP,
+  RSVG_RENDER_TYPE_CAIRO_OUTLINE
 } RsvgRenderType;
 
 struct RsvgRender {
@@ -281,6 +309,7 @@ typedef struct {
     gboolean virgin;
 } RsvgBbox;
 
+/* Keep this in sync with rust/src/clip_path.rs:ClipPathUnits */
 typedef enum {
     objectBoundingBox, userSpaceOnUse
 } RsvgCoordUnits;
@@ -289,6 +318,7 @@ typedef enum {
 typedef enum {
     RSVG_NODE_TYPE_INVALID = 0,
 
+    RSVG_NODE_TYPE_ANIMATION,
     RSVG_NODE_TYPE_CHARS,
     RSVG_NODE_TYPE_CIRCLE,
     RSVG_NODE_TYPE_CLIP_PATH,
@@ -296,6 +326,10 @@ typedef enum {
     RSVG_NODE_TYPE_DEFS,
     RSVG_NODE_TYPE_ELLIPSE,
     RSVG_NODE_TYPE_FILTER,
+    RSVG_NODE_TYPE_FONT,
+    RSVG_NODE_TYPE_FONT_FACE,
+    RSVG_NODE_TYPE_FOREIGN_OBJECT,
+    RSVG_NODE_TYPE_GLYPH,
     RSVG_NODE_TYPE_GROUP,
     RSVG_NODE_TYPE_IMAGE,
     RSVG_NODE_TYPE_LIGHT_SOURCE,
@@ -303,6 +337,7 @@ typedef enum {
     RSVG_NODE_TYPE_LINEAR_GRADIENT,
     RSVG_NODE_TYPE_MARKER,
     RSVG_NODE_TYPE_MASK,
+    RSVG_NODE_TYPE_MPATH,
     RSVG_NODE_TYPE_PATH,
     RSVG_NODE_TYPE_PATTERN,
     RSVG_NODE_TYPE_POLYGON,
@@ -314,6 +349,7 @@ typedef enum {
     RSVG_NODE_TYPE_SWITCH,
     RSVG_NODE_TYPE_SYMBOL,
     RSVG_NODE_TYPE_TEXT,
+    RSVG_NODE_TYPE_TEXT_PATH,
     RSVG_NODE_TYPE_TREF,
     RSVG_NODE_TYPE_TSPAN,
     RSVG_NODE_TYPE_USE,
@@ -397,6 +433,24 @@ void rsvg_node_add_child (RsvgNode *node, RsvgNode *child);
 G_GNUC_INTERNAL
 void rsvg_node_set_atts (RsvgNode *node, RsvgHandle *handle, RsvgPropertyBag *atts);
 
+/* Records the element name and attributes for matching CSS selectors.
+ * Implemented in rust/src/node.rs
+ */
+G_GNUC_INTERNAL
+void rsvg_node_set_element (RsvgNode *node, const char *element_name, gboolean supports_class, RsvgPropertyBag *atts);
+
+/* Records the position of the element's start tag in the source, for diagnostics.
+ * Implemented in rust/src/node.rs
+ */
+G_GNUC_INTERNAL
+void rsvg_node_set_location (RsvgNode *node, int line, int column);
+
+/* Records the position of one of the element's attributes in the source, for
+ * diagnostics about it.  Implemented in rust/src/node.rs
+ */
+G_GNUC_INTERNAL
+void rsvg_node_set_attribute_location (RsvgNode *node, const char *attr_name, int line, int column);
+
 /* Implemented in rust/src/node.rs */
 G_GNUC_INTERNAL
 void rsvg_node_draw (RsvgNode *node, RsvgDrawingCtx *draw, int dominate);
@@ -422,6 +476,7 @@ void rsvg_node_foreach_child (RsvgNode *node, RsvgNodeForeachChildFn fn, gpointe
 G_GNUC_INTERNAL
 void rsvg_node_draw_children (RsvgNode *node, RsvgDrawingCtx *ctx, int dominate);
 
+/* Keep this in sync with rust/src/serializer.rs:RsvgNodeChars */
 struct _RsvgNodeChars {
     GString *contents;
 };
@@ -433,6 +488,8 @@ RsvgPropertyBag	    *rsvg_property_bag_new       (const char **atts);
 G_GNUC_INTERNAL
 RsvgPropertyBag	    *rsvg_property_bag_dup       (RsvgPropertyBag * bag);
 G_GNUC_INTERNAL
+void                 rsvg_property_bag_insert    (RsvgPropertyBag * bag, const char *key, const char *value);
+G_GNUC_INTERNAL
 void                 rsvg_property_bag_free      (RsvgPropertyBag * bag);
 G_GNUC_INTERNAL
 const char          *rsvg_property_bag_lookup    (RsvgPropertyBag * bag, const char *key);
@@ -441,6 +498,93 @@ guint                rsvg_property_bag_size	     (RsvgPropertyBag * bag);
 G_GNUC_INTERNAL
 void                 rsvg_property_bag_enumerate (RsvgPropertyBag * bag, RsvgPropertyBagEnumFunc func,
                                                   gpointer user_data);
+
+/* Returns the node referenced by a URL like "#foo", or NULL; this
+ * does not add a reference to the node.
+ */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_handle_lookup_node (RsvgHandle *handle, const char *url);
+
+G_GNUC_INTERNAL
+void rsvg_handle_load_css (RsvgHandle *handle, const char *href);
+
+/* Loads the font file for a @font-face rule, or makes the rule's family
+ * refer to an installed font, as in src: local(...).  These return FALSE
+ * if the font can't be loaded, so that the next source can be tried.
+ * Used by rust/src/css.rs
+ */
+G_GNUC_INTERNAL
+gboolean rsvg_handle_load_font_face (RsvgHandle *handle, const char *family, const char *href);
+
+G_GNUC_INTERNAL
+gboolean rsvg_handle_load_local_font_face (RsvgHandle *handle, const char *family, const char *name);
+
+/* Makes text whose font-family is @family use the <font> element @font.
+ * Used by rust/src/font.rs
+ */
+G_GNUC_INTERNAL
+void rsvg_handle_register_svg_font (RsvgHandle *handle, const char *family, RsvgNode *font);
+
+/* Returns the toplevel <svg> element, or NULL; this does not add a
+ * reference to the node.  Used by rust/src/api.rs
+ */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_handle_get_root_node (RsvgHandle *handle);
+
+/* Returns a new path with the area that the clipPath @id covers, in the
+ * user space of the elements that it clips, or NULL if there is no such
+ * clipPath.  @bbox is the bounding box of the clipped element; it is
+ * only needed for clipPathUnits="objectBoundingBox".  Used by
+ * rust/src/api.rs
+ */
+G_GNUC_INTERNAL
+RsvgPathBuilder *rsvg_handle_get_clip_path_geometry (RsvgHandle *handle,
+                                                     const char *id,
+                                                     const cairo_rectangle_t *bbox);
+
+/* Returns a new path with the outlines of the glyphs of the <text>
+ * element @node, in the text's user space, or NULL if @node is not a
+ * text element.  Used by rust/src/handle.rs
+ */
+G_GNUC_INTERNAL
+RsvgPathBuilder *rsvg_handle_get_text_outlines (RsvgHandle *handle, RsvgNode *node);
+
+/* Returns a newly-allocated SVG document for the tree under @root, with
+ * text converted to paths.  Implemented in rust/src/serializer.rs
+ */
+G_GNUC_INTERNAL
+char *rsvg_serialize_with_text_as_paths (RsvgHandle *handle, RsvgNode *root);
+
+/* Returns the handle's collector of diagnostics; used by rust/src/api.rs */
+G_GNUC_INTERNAL
+RsvgDiagnostics *rsvg_handle_get_diagnostics_collector (RsvgHandle *handle);
+
+/* Implemented in rust/src/diagnostics.rs */
+G_GNUC_INTERNAL
+RsvgDiagnostics *rsvg_diagnostics_new (void);
+
+/* Implemented in rust/src/diagnostics.rs */
+G_GNUC_INTERNAL
+void rsvg_diagnostics_free (RsvgDiagnostics *diagnostics);
+
+/* Collects the errors of a node.
+ * Implemented in rust/src/diagnostics.rs
+ */
+G_GNUC_INTERNAL
+void rsvg_diagnostics_add_node (RsvgDiagnostics *diagnostics, RsvgNode *node);
+
+/* Returns a newly-allocated report with one line per error, or NULL if there are none.
+ * Implemented in rust/src/diagnostics.rs
+ */
+G_GNUC_INTERNAL
+char *rsvg_diagnostics_to_string (RsvgDiagnostics *diagnostics);
+
+/* Returns a newly-allocated description of the first error, or NULL if there are none.
+ * Implemented in rust/src/diagnostics.rs
+ */
+G_GNUC_INTERNAL
+char *rsvg_diagnostics_get_first (RsvgDiagnostics *diagnostics);
+
 /* for some reason this one's public... */
 GdkPixbuf *rsvg_pixbuf_from_data_with_size_data (const guchar * buff,
                                                  size_t len,
@@ -468,6 +612,12 @@ void rsvg_drawing_ctx_add_node_and_ancestors_to_stack (RsvgDrawingCtx *draw_ctx,
 G_GNUC_INTERNAL
 void rsvg_drawing_ctx_draw_node_from_stack            (RsvgDrawingCtx *ctx, RsvgNode *node, int dominate);
 
+/* Returns the innermost node that is being drawn, whose state is at the top
+ * of the state stack, or NULL.  This does not add a reference to the node.
+ */
+G_GNUC_INTERNAL
+RsvgNode *rsvg_drawing_ctx_get_current_node (RsvgDrawingCtx *ctx);
+
 G_GNUC_INTERNAL
 void rsvg_render_path_builder   (RsvgDrawingCtx * ctx, RsvgPathBuilder *builder);
 G_GNUC_INTERNAL
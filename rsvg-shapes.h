@@ -62,6 +62,33 @@ RsvgNode *rsvg_node_circle_new (const char *element_name, RsvgNode *parent);
 G_GNUC_INTERNAL
 RsvgNode *rsvg_node_ellipse_new (const char *element_name, RsvgNode *parent);
 
+/* Implemented in rust/src/shapes.rs */
+typedef struct _RsvgPathMeasure RsvgPathMeasure;
+
+/* Implemented in rust/src/shapes.rs; returns NULL if the node is not a path */
+G_GNUC_INTERNAL
+RsvgPathMeasure *rsvg_node_path_measure_new (RsvgNode *node);
+
+/* Implemented in rust/src/shapes.rs */
+G_GNUC_INTERNAL
+void rsvg_path_measure_free (RsvgPathMeasure *measure);
+
+/* Implemented in rust/src/shapes.rs */
+G_GNUC_INTERNAL
+double rsvg_path_measure_get_length (RsvgPathMeasure *measure);
+
+/* Implemented in rust/src/shapes.rs */
+G_GNUC_INTERNAL
+double rsvg_path_measure_get_author_length (RsvgPathMeasure *measure);
+
+/* Implemented in rust/src/shapes.rs */
+G_GNUC_INTERNAL
+gboolean rsvg_path_measure_get_point (RsvgPathMeasure *measure,
+                                      double distance,
+                                      double *out_x,
+                                      double *out_y,
+                                      double *out_angle);
+
 G_END_DECLS
 
 #endif                          /* RSVG_SHAPES_H */
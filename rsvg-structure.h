@@ -60,15 +60,23 @@ RsvgNode *rsvg_node_symbol_new (const char *element_name, RsvgNode *parent);
 
 /* Implemented in rust/src/structure.rs */
 G_GNUC_INTERNAL
-void rsvg_node_svg_get_size (RsvgNode *node, RsvgLength *out_width, RsvgLength *out_height);
+RsvgNode *rsvg_node_foreign_object_new (const char *element_name, RsvgNode *parent);
 
 /* Implemented in rust/src/structure.rs */
 G_GNUC_INTERNAL
-RsvgViewBox rsvg_node_svg_get_view_box (RsvgNode *node);
+void rsvg_node_foreign_object_add_paragraph (RsvgNode *node, const char *text, guint heading_level);
+
+/* Implemented in rust/src/structure.rs */
+G_GNUC_INTERNAL
+void rsvg_node_foreign_object_set_unsupported (RsvgNode *node);
 
 /* Implemented in rust/src/structure.rs */
 G_GNUC_INTERNAL
-void rsvg_node_svg_apply_atts (RsvgNode *node, RsvgHandle *handle);
+void rsvg_node_svg_get_size (RsvgNode *node, RsvgLength *out_width, RsvgLength *out_height);
+
+/* Implemented in rust/src/structure.rs */
+G_GNUC_INTERNAL
+RsvgViewBox rsvg_node_svg_get_view_box (RsvgNode *node);
 
 G_END_DECLS
 
@@ -37,8 +37,6 @@
 #include "rsvg-mask.h"
 #include "rsvg-marker.h"
 
-#include <libcroco/libcroco.h>
-
 #define RSVG_DEFAULT_FONT "Times New Roman"
 
 enum {
@@ -103,24 +101,16 @@ rsvg_state_init (RsvgState * state)
     state->miter_limit = 4;
     state->cap = CAIRO_LINE_CAP_BUTT;
     state->join = CAIRO_LINE_JOIN_MITER;
-
-    /* The following two start as INHERIT, even though has_stop_color and
-     * has_stop_opacity get initialized to FALSE below.  This is so that the
-     * first pass of rsvg_state_inherit_run(), called from
-     * rsvg_state_reconstruct() from the "stop" element code, will correctly
-     * initialize the destination state from the toplevel element.
-     *
-     */
-    state->stop_color.kind = RSVG_CSS_COLOR_SPEC_INHERIT;
-    state->stop_opacity.kind = RSVG_OPACITY_INHERIT;
+    state->path_length_scale = 1.0;
 
     state->fill_rule = CAIRO_FILL_RULE_WINDING;
     state->clip_rule = CAIRO_FILL_RULE_WINDING;
     state->enable_background = RSVG_ENABLE_BACKGROUND_ACCUMULATE;
+    state->mask_type = RSVG_MASK_TYPE_LUMINANCE;
+    state->mix_blend_mode = RSVG_BLEND_MODE_NORMAL;
+    state->isolate = FALSE;
     state->comp_op = CAIRO_OPERATOR_OVER;
     state->overflow = FALSE;
-    state->flood_color = 0;
-    state->flood_opacity = 255;
 
     state->font_family = g_strdup (RSVG_DEFAULT_FONT);
     state->font_size = rsvg_length_parse ("12.0", LENGTH_DIR_BOTH);
@@ -130,6 +120,7 @@ rsvg_state_init (RsvgState * state)
     state->font_stretch = PANGO_STRETCH_NORMAL;
     state->text_dir = PANGO_DIRECTION_LTR;
     state->text_gravity = PANGO_GRAVITY_SOUTH;
+    state->glyph_orientation_vertical = GLYPH_ORIENTATION_AUTO;
     state->unicode_bidi = UNICODE_BIDI_NORMAL;
     state->text_anchor = TEXT_ANCHOR_START;
     state->letter_spacing = rsvg_length_parse ("0.0", LENGTH_DIR_HORIZONTAL);
@@ -143,8 +134,6 @@ rsvg_state_init (RsvgState * state)
 
     state->has_baseline_shift = FALSE;
     state->has_current_color = FALSE;
-    state->has_flood_color = FALSE;
-    state->has_flood_opacity = FALSE;
     state->has_fill_server = FALSE;
     state->has_fill_opacity = FALSE;
     state->has_fill_rule = FALSE;
@@ -159,8 +148,6 @@ rsvg_state_init (RsvgState * state)
     state->has_dashoffset = FALSE;
     state->has_visible = FALSE;
     state->has_cond = FALSE;
-    state->has_stop_color = FALSE;
-    state->has_stop_opacity = FALSE;
     state->has_font_size = FALSE;
     state->has_font_family = FALSE;
     state->has_lang = FALSE;
@@ -171,6 +158,7 @@ rsvg_state_init (RsvgState * state)
     state->has_font_decor = FALSE;
     state->has_text_dir = FALSE;
     state->has_text_gravity = FALSE;
+    state->has_glyph_orientation_vertical = FALSE;
     state->has_unicode_bidi = FALSE;
     state->has_text_anchor = FALSE;
     state->has_letter_spacing = FALSE;
@@ -288,7 +276,7 @@ rsvg_state_clone (RsvgState * dst, const RsvgState * src)
     dst->styles = g_hash_table_ref (src->styles);
 
     if (src->dash.n_dash > 0) {
-        dst->dash.dash = g_new0 (gdouble, src->dash.n_dash);
+        dst->dash.dash = g_new0 (RsvgLength, src->dash.n_dash);
         for (i = 0; i < src->dash.n_dash; i++)
             dst->dash.dash[i] = src->dash.dash[i];
     }
@@ -311,10 +299,6 @@ rsvg_state_inherit_run (RsvgState * dst, const RsvgState * src,
         dst->baseline_shift = src->baseline_shift;
     if (function (dst->has_current_color, src->has_current_color))
         dst->current_color = src->current_color;
-    if (function (dst->has_flood_color, src->has_flood_color))
-        dst->flood_color = src->flood_color;
-    if (function (dst->has_flood_opacity, src->has_flood_opacity))
-        dst->flood_opacity = src->flood_opacity;
     if (function (dst->has_fill_server, src->has_fill_server)) {
         rsvg_paint_server_ref (src->fill);
         if (dst->fill)
@@ -345,18 +329,6 @@ rsvg_state_inherit_run (RsvgState * dst, const RsvgState * src,
         dst->cap = src->cap;
     if (function (dst->has_join, src->has_join))
         dst->join = src->join;
-    if (function (dst->has_stop_color, src->has_stop_color)) {
-        if (dst->stop_color.kind == RSVG_CSS_COLOR_SPEC_INHERIT) {
-            dst->has_stop_color = TRUE;
-            dst->stop_color = src->stop_color;
-        }
-    }
-    if (function (dst->has_stop_opacity, src->has_stop_opacity)) {
-        if (dst->stop_opacity.kind == RSVG_OPACITY_INHERIT) {
-            dst->has_stop_opacity = TRUE;
-            dst->stop_opacity = src->stop_opacity;
-        }
-    }
     if (function (dst->has_cond, src->has_cond))
         dst->cond_true = src->cond_true;
     if (function (dst->has_font_size, src->has_font_size))
@@ -375,6 +347,8 @@ rsvg_state_inherit_run (RsvgState * dst, const RsvgState * src,
         dst->text_dir = src->text_dir;
     if (function (dst->has_text_gravity, src->has_text_gravity))
         dst->text_gravity = src->text_gravity;
+    if (function (dst->has_glyph_orientation_vertical, src->has_glyph_orientation_vertical))
+        dst->glyph_orientation_vertical = src->glyph_orientation_vertical;
     if (function (dst->has_unicode_bidi, src->has_unicode_bidi))
         dst->unicode_bidi = src->unicode_bidi;
     if (function (dst->has_text_anchor, src->has_text_anchor))
@@ -419,7 +393,7 @@ rsvg_state_inherit_run (RsvgState * dst, const RsvgState * src,
         if (dst->has_dash)
             g_free (dst->dash.dash);
 
-        dst->dash.dash = g_new0 (gdouble, src->dash.n_dash);
+        dst->dash.dash = g_new0 (RsvgLength, src->dash.n_dash);
         dst->dash.n_dash = src->dash.n_dash;
         for (i = 0; i < src->dash.n_dash; i++)
             dst->dash.dash[i] = src->dash.dash[i];
@@ -439,6 +413,8 @@ rsvg_state_inherit_run (RsvgState * dst, const RsvgState * src,
         dst->enable_background = src->enable_background;
         dst->opacity = src->opacity;
         dst->comp_op = src->comp_op;
+        dst->mix_blend_mode = src->mix_blend_mode;
+        dst->isolate = src->isolate;
     }
 }
 
@@ -519,7 +495,7 @@ rsvg_state_inherit (RsvgState * dst, const RsvgState * src)
 }
 
 /* Parse a CSS2 style argument, setting the SVG context attributes. */
-static void
+void
 rsvg_parse_style_pair (RsvgState * state,
                        const gchar * name,
                        const gchar * value,
@@ -544,7 +520,7 @@ rsvg_parse_style_pair (RsvgState * state,
         spec = rsvg_css_parse_color (value, ALLOW_INHERIT_YES, ALLOW_CURRENT_COLOR_NO);
         switch (spec.kind) {
         case RSVG_CSS_COLOR_SPEC_INHERIT:
-            /* FIXME: we should inherit; see how stop-color is handled in rsvg-styles.c */
+            /* FIXME: we should inherit, like rust/src/properties.rs does */
             state->has_current_color = FALSE;
             break;
 
@@ -571,49 +547,17 @@ rsvg_parse_style_pair (RsvgState * state,
             state->opacity = 0;
             /* FIXME: handle INHERIT and PARSE_ERROR */
         }
-    } else if (g_str_equal (name, "flood-color")) {
-        RsvgCssColorSpec spec;
-
-        spec = rsvg_css_parse_color (value, ALLOW_INHERIT_YES, ALLOW_CURRENT_COLOR_YES);
-        switch (spec.kind) {
-        case RSVG_CSS_COLOR_SPEC_INHERIT:
-            /* FIXME: we should inherit; see how stop-color is handled in rsvg-styles.c */
-            state->has_current_color = FALSE;
-            break;
-
-        case RSVG_CSS_COLOR_SPEC_CURRENT_COLOR:
-            /* FIXME: in the caller, fix up the current color */
-            state->has_flood_color = FALSE;
-            break;
-
-        case RSVG_CSS_COLOR_SPEC_ARGB:
-            state->flood_color = spec.argb;
-            state->has_flood_color = TRUE;
-            break;
-
-        case RSVG_CSS_COLOR_PARSE_ERROR:
-            /* FIXME: no error handling */
-            state->has_current_color = FALSE;
-            break;
-
-        default:
-            g_assert_not_reached ();
-        }
-    } else if (g_str_equal (name, "flood-opacity")) {
-        RsvgOpacitySpec spec;
-
-        spec = rsvg_css_parse_opacity (value);
-        if (spec.kind == RSVG_OPACITY_SPECIFIED) {
-            state->flood_opacity = spec.opacity;
-        } else {
-            state->flood_opacity = 0;
-            /* FIXME: handle INHERIT and PARSE_ERROR */
-        }
-
-        state->has_flood_opacity = TRUE;
     } else if (g_str_equal (name, "filter")) {
+        /* The value is parsed in rust/src/properties.rs, and the filter code
+         * gets it from the node's computed values; we only need to know
+         * whether there is a filter, to render the element in its own layer.
+         */
         g_free (state->filter);
-        state->filter = rsvg_get_url_string (value, NULL);
+
+        if (g_str_equal (value, "none") || g_str_equal (value, "inherit"))
+            state->filter = NULL;
+        else
+            state->filter = g_strdup (value);
     } else if (g_str_equal (name, "mask")) {
         g_free (state->mask);
         state->mask = rsvg_get_url_string (value, NULL);
@@ -645,6 +589,50 @@ rsvg_parse_style_pair (RsvgState * state,
             state->enable_background = RSVG_ENABLE_BACKGROUND_NEW;
         else
             state->enable_background = RSVG_ENABLE_BACKGROUND_ACCUMULATE;
+    } else if (g_str_equal (name, "mix-blend-mode")) {
+        if (g_str_equal (value, "multiply"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_MULTIPLY;
+        else if (g_str_equal (value, "screen"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_SCREEN;
+        else if (g_str_equal (value, "overlay"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_OVERLAY;
+        else if (g_str_equal (value, "darken"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_DARKEN;
+        else if (g_str_equal (value, "lighten"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_LIGHTEN;
+        else if (g_str_equal (value, "color-dodge"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_COLOR_DODGE;
+        else if (g_str_equal (value, "color-burn"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_COLOR_BURN;
+        else if (g_str_equal (value, "hard-light"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_HARD_LIGHT;
+        else if (g_str_equal (value, "soft-light"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_SOFT_LIGHT;
+        else if (g_str_equal (value, "difference"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_DIFFERENCE;
+        else if (g_str_equal (value, "exclusion"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_EXCLUSION;
+        else if (g_str_equal (value, "hue"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_HUE;
+        else if (g_str_equal (value, "saturation"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_SATURATION;
+        else if (g_str_equal (value, "color"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_COLOR;
+        else if (g_str_equal (value, "luminosity"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_LUMINOSITY;
+        else if (g_str_equal (value, "plus-darker"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_PLUS_DARKER;
+        else if (g_str_equal (value, "plus-lighter"))
+            state->mix_blend_mode = RSVG_BLEND_MODE_PLUS_LIGHTER;
+        else
+            state->mix_blend_mode = RSVG_BLEND_MODE_NORMAL;
+    } else if (g_str_equal (name, "isolation")) {
+        state->isolate = g_str_equal (value, "isolate");
+    } else if (g_str_equal (name, "mask-type")) {
+        if (g_str_equal (value, "alpha"))
+            state->mask_type = RSVG_MASK_TYPE_ALPHA;
+        else
+            state->mask_type = RSVG_MASK_TYPE_LUMINANCE;
     } else if (g_str_equal (name, "comp-op")) {
         if (g_str_equal (value, "clear"))
             state->comp_op = CAIRO_OPERATOR_CLEAR;
@@ -849,7 +837,10 @@ rsvg_parse_style_pair (RsvgState * state,
         else                    /* normal */
             state->unicode_bidi = UNICODE_BIDI_NORMAL;
     } else if (g_str_equal (name, "writing-mode")) {
-        /* TODO: these aren't quite right... */
+        /* Vertical text gets laid out by hand in rsvg-text.c; the gravity
+         * only tells it which way the text goes.  SVG text doesn't wrap,
+         * so the direction in which lines progress doesn't matter.
+         */
 
         state->has_text_dir = TRUE;
         state->has_text_gravity = TRUE;
@@ -864,10 +855,33 @@ rsvg_parse_style_pair (RsvgState * state,
         } else if (g_str_equal (value, "rl-tb") || g_str_equal (value, "rl")) {
             state->text_dir = PANGO_DIRECTION_RTL;
             state->text_gravity = PANGO_GRAVITY_SOUTH;
-        } else if (g_str_equal (value, "tb-rl") || g_str_equal (value, "tb")) {
+        } else if (g_str_equal (value, "horizontal-tb")) {
+            state->text_dir = PANGO_DIRECTION_LTR;
+            state->text_gravity = PANGO_GRAVITY_SOUTH;
+        } else if (g_str_equal (value, "tb-rl") || g_str_equal (value, "tb")
+                   || g_str_equal (value, "vertical-rl") || g_str_equal (value, "vertical-lr")) {
             state->text_dir = PANGO_DIRECTION_LTR;
             state->text_gravity = PANGO_GRAVITY_EAST;
         }
+    } else if (g_str_equal (name, "glyph-orientation-vertical")) {
+        state->has_glyph_orientation_vertical = TRUE;
+        if (g_str_equal (value, "inherit")) {
+            state->glyph_orientation_vertical = GLYPH_ORIENTATION_AUTO;
+            state->has_glyph_orientation_vertical = FALSE;
+        } else if (g_str_equal (value, "auto")) {
+            state->glyph_orientation_vertical = GLYPH_ORIENTATION_AUTO;
+        } else {
+            char *end;
+            double angle = g_ascii_strtod (value, &end);
+
+            /* Only multiples of 90 degrees are allowed; round to the nearest one */
+            if (end != value && (*end == '\0' || g_str_equal (end, "deg"))) {
+                angle = fmod (floor (angle / 90.0 + 0.5) * 90.0, 360.0);
+                if (angle < 0)
+                    angle += 360.0;
+                state->glyph_orientation_vertical = (int) angle;
+            }
+        }
     } else if (g_str_equal (name, "text-anchor")) {
         state->has_text_anchor = TRUE;
         if (g_str_equal (value, "inherit")) {
@@ -884,12 +898,6 @@ rsvg_parse_style_pair (RsvgState * state,
     } else if (g_str_equal (name, "letter-spacing")) {
 	state->has_letter_spacing = TRUE;
 	state->letter_spacing = rsvg_length_parse (value, LENGTH_DIR_HORIZONTAL);
-    } else if (g_str_equal (name, "stop-color")) {
-        state->has_stop_color = TRUE;
-        state->stop_color = rsvg_css_parse_color (value, ALLOW_INHERIT_YES, ALLOW_CURRENT_COLOR_YES);
-    } else if (g_str_equal (name, "stop-opacity")) {
-        state->stop_opacity = rsvg_css_parse_opacity (value);
-        state->has_stop_opacity = TRUE;
     } else if (g_str_equal (name, "marker-start")) {
         g_free (state->startMarker);
         state->startMarker = rsvg_get_url_string (value, NULL);
@@ -962,35 +970,46 @@ rsvg_parse_style_pair (RsvgState * state,
                 state->dash.n_dash = 0;
             }
         } else {
-            gchar **dashes = g_strsplit (value, ",", -1);
+            /* The list is separated by commas and/or whitespace */
+            gchar **dashes = g_strsplit_set (value, ", \t\r\n", -1);
             if (NULL != dashes) {
-                gint n_dashes, i;
+                gint n_dashes, i, j;
                 gboolean is_even = FALSE;
+                gboolean is_valid = TRUE;
                 gdouble total = 0;
 
                 /* count the #dashes */
-                for (n_dashes = 0; dashes[n_dashes] != NULL; n_dashes++);
+                for (n_dashes = 0, i = 0; dashes[i] != NULL; i++)
+                    if (dashes[i][0] != '\0')
+                        n_dashes++;
 
                 is_even = (n_dashes % 2 == 0);
                 state->dash.n_dash = (is_even ? n_dashes : n_dashes * 2);
-                state->dash.dash = g_new0 (double, state->dash.n_dash);
-
-                /* TODO: handle negative value == error case */
+                state->dash.dash = g_new0 (RsvgLength, state->dash.n_dash);
 
                 /* the even and base case */
-                for (i = 0; i < n_dashes; i++) {
-                    state->dash.dash[i] = g_ascii_strtod (dashes[i], NULL);
-                    total += state->dash.dash[i];
+                for (i = 0, j = 0; dashes[i] != NULL; i++) {
+                    if (dashes[i][0] == '\0')
+                        continue;
+
+                    state->dash.dash[j] = rsvg_length_parse (dashes[i], LENGTH_DIR_BOTH);
+
+                    /* negative values are an error, which disables dashing */
+                    if (state->dash.dash[j].length < 0)
+                        is_valid = FALSE;
+
+                    total += state->dash.dash[j].length;
+                    j++;
                 }
                 /* if an odd number of dashes is found, it gets repeated */
                 if (!is_even)
-                    for (; i < state->dash.n_dash; i++)
-                        state->dash.dash[i] = state->dash.dash[i - n_dashes];
+                    for (; j < state->dash.n_dash; j++)
+                        state->dash.dash[j] = state->dash.dash[j - n_dashes];
 
                 g_strfreev (dashes);
                 /* If the dashes add up to 0, then it should 
                    be ignored */
-                if (total == 0) {
+                if (total == 0 || !is_valid) {
                     g_free (state->dash.dash);
                     state->dash.dash = NULL;
                     state->dash.n_dash = 0;
@@ -1026,23 +1045,23 @@ rsvg_parse_style_pairs (RsvgState * state, RsvgPropertyBag * atts)
     rsvg_lookup_parse_style_pair (state, "fill-opacity", atts);
     rsvg_lookup_parse_style_pair (state, "fill-rule", atts);
     rsvg_lookup_parse_style_pair (state, "filter", atts);
-    rsvg_lookup_parse_style_pair (state, "flood-color", atts);
-    rsvg_lookup_parse_style_pair (state, "flood-opacity", atts);
     rsvg_lookup_parse_style_pair (state, "font-family", atts);
     rsvg_lookup_parse_style_pair (state, "font-size", atts);
     rsvg_lookup_parse_style_pair (state, "font-stretch", atts);
     rsvg_lookup_parse_style_pair (state, "font-style", atts);
     rsvg_lookup_parse_style_pair (state, "font-variant", atts);
     rsvg_lookup_parse_style_pair (state, "font-weight", atts);
+    rsvg_lookup_parse_style_pair (state, "glyph-orientation-vertical", atts);
+    rsvg_lookup_parse_style_pair (state, "isolation", atts);
     rsvg_lookup_parse_style_pair (state, "marker-end", atts);
     rsvg_lookup_parse_style_pair (state, "mask", atts);
+    rsvg_lookup_parse_style_pair (state, "mask-type", atts);
     rsvg_lookup_parse_style_pair (state, "marker-mid", atts);
     rsvg_lookup_parse_style_pair (state, "marker-start", atts);
+    rsvg_lookup_parse_style_pair (state, "mix-blend-mode", atts);
     rsvg_lookup_parse_style_pair (state, "opacity", atts);
     rsvg_lookup_parse_style_pair (state, "overflow", atts);
     rsvg_lookup_parse_style_pair (state, "shape-rendering", atts);
-    rsvg_lookup_parse_style_pair (state, "stop-color", atts);
-    rsvg_lookup_parse_style_pair (state, "stop-opacity", atts);
     rsvg_lookup_parse_style_pair (state, "stroke", atts);
     rsvg_lookup_parse_style_pair (state, "stroke-dasharray", atts);
     rsvg_lookup_parse_style_pair (state, "stroke-dashoffset", atts);
@@ -1073,293 +1092,10 @@ rsvg_parse_style_pairs (RsvgState * state, RsvgPropertyBag * atts)
     }
 }
 
-static gboolean
-parse_style_value (const gchar *string, gchar **value, gboolean *important)
-{
-    gchar **strings;
-
-    strings = g_strsplit (string, "!", 2);
-
-    if (strings == NULL || strings[0] == NULL) {
-        g_strfreev (strings);
-        return FALSE;
-    }
-
-    if (strings[1] != NULL && strings[2] == NULL &&
-        g_str_equal (g_strstrip (strings[1]), "important")) {
-        *important = TRUE;
-    } else {
-        *important = FALSE;
-    }
-
-    *value = g_strdup (g_strstrip (strings[0]));
-
-    g_strfreev (strings);
-
-    return TRUE;
-}
-
-/* Split a CSS2 style into individual style arguments, setting attributes
-   in the SVG context.
-   
-   It's known that this is _way_ out of spec. A more complete CSS2
-   implementation will happen later.
-*/
-void
-rsvg_parse_style (RsvgHandle * ctx, RsvgState * state, const char *str)
-{
-    gchar **styles;
-    guint i;
-
-    styles = g_strsplit (str, ";", -1);
-    for (i = 0; i < g_strv_length (styles); i++) {
-        gchar **values;
-        values = g_strsplit (styles[i], ":", 2);
-        if (!values)
-            continue;
-
-        if (g_strv_length (values) == 2) {
-            gboolean important;
-            gchar *style_value = NULL;
-            gchar *first_value = values[0];
-            gchar *second_value = values[1];
-            gchar **split_list;
-
-            /* Just remove single quotes in a trivial way.  No handling for any
-             * special character inside the quotes is done.  This relates
-             * especially to font-family names but cases with special characters
-             * are rare.
-             *
-             * We need a real CSS parser, sigh.
-             */
-            split_list = g_strsplit (second_value, "'", -1);
-            second_value = g_strjoinv(NULL, split_list);
-            g_strfreev(split_list);
-
-            if (parse_style_value (second_value, &style_value, &important))
-                rsvg_parse_style_pair (state,
-                                       g_strstrip (first_value),
-                                       style_value,
-                                       important);
-            g_free (style_value);
-            g_free (second_value);
-        }
-        g_strfreev (values);
-    }
-    g_strfreev (styles);
-}
-
-static void
-rsvg_css_define_style (RsvgHandle * ctx,
-                       const gchar * selector,
-                       const gchar * style_name,
-                       const gchar * style_value,
-                       gboolean important)
-{
-    GHashTable *styles;
-    gboolean need_insert = FALSE;
-
-    /* push name/style pair into HT */
-    styles = g_hash_table_lookup (ctx->priv->css_props, selector);
-    if (styles == NULL) {
-        styles = g_hash_table_new_full (g_str_hash, g_str_equal,
-                                        g_free, (GDestroyNotify) style_value_data_free);
-        g_hash_table_insert (ctx->priv->css_props, (gpointer) g_strdup (selector), styles);
-        need_insert = TRUE;
-    } else {
-        StyleValueData *current_value;
-        current_value = g_hash_table_lookup (styles, style_name);
-        if (current_value == NULL || !current_value->important)
-            need_insert = TRUE;
-    }
-    if (need_insert) {
-        g_hash_table_insert (styles,
-                             (gpointer) g_strdup (style_name),
-                             (gpointer) style_value_data_new (style_value, important));
-    }
-}
-
-typedef struct _CSSUserData {
-    RsvgHandle *ctx;
-    CRSelector *selector;
-} CSSUserData;
-
-static void
-css_user_data_init (CSSUserData * user_data, RsvgHandle * ctx)
-{
-    user_data->ctx = ctx;
-    user_data->selector = NULL;
-}
-
-static void
-ccss_start_selector (CRDocHandler * a_handler, CRSelector * a_selector_list)
-{
-    CSSUserData *user_data;
-
-    g_return_if_fail (a_handler);
-
-    user_data = (CSSUserData *) a_handler->app_data;
-    cr_selector_ref (a_selector_list);
-    user_data->selector = a_selector_list;
-}
-
-static void
-ccss_end_selector (CRDocHandler * a_handler, CRSelector * a_selector_list)
-{
-    CSSUserData *user_data;
-
-    g_return_if_fail (a_handler);
-
-    user_data = (CSSUserData *) a_handler->app_data;
-
-    cr_selector_unref (user_data->selector);
-    user_data->selector = NULL;
-}
-
-static void
-ccss_property (CRDocHandler * a_handler, CRString * a_name, CRTerm * a_expr, gboolean a_important)
-{
-    CSSUserData *user_data;
-    gchar *name = NULL;
-    size_t len = 0;
-
-    g_return_if_fail (a_handler);
-
-    user_data = (CSSUserData *) a_handler->app_data;
-
-    if (a_name && a_expr && user_data->selector) {
-        CRSelector *cur;
-        for (cur = user_data->selector; cur; cur = cur->next) {
-            if (cur->simple_sel) {
-                gchar *selector = (gchar *) cr_simple_sel_to_string (cur->simple_sel);
-                if (selector) {
-                    gchar *style_name, *style_value;
-                    name = (gchar *) cr_string_peek_raw_str (a_name);
-                    len = cr_string_peek_raw_str_len (a_name);
-                    style_name = g_strndup (name, len);
-                    style_value = (gchar *)cr_term_to_string (a_expr);
-                    rsvg_css_define_style (user_data->ctx,
-                                           selector,
-                                           style_name,
-                                           style_value,
-                                           a_important);
-                    g_free (selector);
-                    g_free (style_name);
-                    g_free (style_value);
-                }
-            }
-        }
-    }
-}
-
-static void
-ccss_error (CRDocHandler * a_handler)
-{
-    /* yup, like i care about CSS parsing errors ;-)
-       ignore, chug along */
-    g_message (_("CSS parsing error\n"));
-}
-
-static void
-ccss_unrecoverable_error (CRDocHandler * a_handler)
-{
-    /* yup, like i care about CSS parsing errors ;-)
-       ignore, chug along */
-    g_message (_("CSS unrecoverable error\n"));
-}
-
-static void
- ccss_import_style (CRDocHandler * a_this,
-                    GList * a_media_list,
-                    CRString * a_uri, CRString * a_uri_default_ns, CRParsingLocation * a_location);
-
-static void
-init_sac_handler (CRDocHandler * a_handler)
-{
-    a_handler->start_document = NULL;
-    a_handler->end_document = NULL;
-    a_handler->import_style = ccss_import_style;
-    a_handler->namespace_declaration = NULL;
-    a_handler->comment = NULL;
-    a_handler->start_selector = ccss_start_selector;
-    a_handler->end_selector = ccss_end_selector;
-    a_handler->property = ccss_property;
-    a_handler->start_font_face = NULL;
-    a_handler->end_font_face = NULL;
-    a_handler->start_media = NULL;
-    a_handler->end_media = NULL;
-    a_handler->start_page = NULL;
-    a_handler->end_page = NULL;
-    a_handler->ignorable_at_rule = NULL;
-    a_handler->error = ccss_error;
-    a_handler->unrecoverable_error = ccss_unrecoverable_error;
-}
-
 void
 rsvg_parse_cssbuffer (RsvgHandle * ctx, const char *buff, size_t buflen)
 {
-    CRParser *parser = NULL;
-    CRDocHandler *css_handler = NULL;
-    CSSUserData user_data;
-
-    if (buff == NULL || buflen == 0)
-        return;
-
-    css_handler = cr_doc_handler_new ();
-    init_sac_handler (css_handler);
-
-    css_user_data_init (&user_data, ctx);
-    css_handler->app_data = &user_data;
-
-    /* TODO: fix libcroco to take in const strings */
-    parser = cr_parser_new_from_buf ((guchar *) buff, (gulong) buflen, CR_UTF_8, FALSE);
-    if (parser == NULL) {
-        cr_doc_handler_unref (css_handler);
-        return;
-    }
-
-    cr_parser_set_sac_handler (parser, css_handler);
-    cr_doc_handler_unref (css_handler);
-
-    cr_parser_set_use_core_grammar (parser, FALSE);
-    cr_parser_parse (parser);
-
-    /* FIXME: we aren't reporting errors in the CSS; we have no way to know if
-     * we should print the "buff" for diagnostics.
-     */
-
-    cr_parser_destroy (parser);
-}
-
-static void
-ccss_import_style (CRDocHandler * a_this,
-                   GList * a_media_list,
-                   CRString * a_uri, CRString * a_uri_default_ns, CRParsingLocation * a_location)
-{
-    CSSUserData *user_data = (CSSUserData *) a_this->app_data;
-    char *stylesheet_data;
-    gsize stylesheet_data_len;
-    char *mime_type = NULL;
-
-    if (a_uri == NULL)
-        return;
-
-    stylesheet_data = _rsvg_handle_acquire_data (user_data->ctx,
-                                                 cr_string_peek_raw_str (a_uri),
-                                                 &mime_type,
-                                                 &stylesheet_data_len,
-                                                 NULL);
-    if (stylesheet_data == NULL || 
-        mime_type == NULL || 
-        strcmp (mime_type, "text/css") != 0) {
-        g_free (stylesheet_data);
-        g_free (mime_type);
-        return;
-    }
-
-    rsvg_parse_cssbuffer (user_data->ctx, stylesheet_data, stylesheet_data_len);
-    g_free (stylesheet_data);
-    g_free (mime_type);
+    rsvg_stylesheet_parse (ctx->priv->stylesheet, ctx, buff, buflen);
 }
 
 /**
@@ -1384,149 +1120,35 @@ rsvg_parse_transform_attr (RsvgHandle * ctx, RsvgState * state, const char *str)
     }
 }
 
-typedef struct _StylesData {
-    RsvgHandle *ctx;
-    RsvgState *state;
-} StylesData;
-
-static void
-apply_style (const gchar *key, StyleValueData *value, gpointer user_data)
-{
-    StylesData *data = (StylesData *) user_data;
-    rsvg_parse_style_pair (data->state, key, value->value, value->important);
-}
-
-static gboolean
-rsvg_lookup_apply_css_style (RsvgHandle * ctx, const char *target, RsvgState * state)
-{
-    GHashTable *styles;
-
-    styles = g_hash_table_lookup (ctx->priv->css_props, target);
-
-    if (styles != NULL) {
-        StylesData *data = g_new0 (StylesData, 1);
-        data->ctx = ctx;
-        data->state = state;
-        g_hash_table_foreach (styles, (GHFunc) apply_style, data);
-        g_free (data);
-        return TRUE;
-    }
-    return FALSE;
-}
-
 /**
  * rsvg_parse_style_attrs:
  * @ctx: Rsvg context.
  * @node: Rsvg node whose state should be modified
- * @tag: (nullable): The SVG tag we're processing (eg: circle, ellipse), optionally %NULL
- * @klazz: (nullable): The space delimited class list, optionally %NULL
  * @atts: Attributes in SAX style.
+ * @animated: (nullable): Animated values of properties
  *
- * Parses style and transform attributes and modifies state at top of
- * stack.
+ * Computes the node's style from its presentation attributes, the
+ * document's style sheets, its style attribute and the values that
+ * animations give to its properties, and applies its transform attribute.
  **/
 void
-rsvg_parse_style_attrs (RsvgHandle *ctx,
-                        RsvgNode *node,
-                        const char *tag, const char *klazz, const char *id, RsvgPropertyBag * atts)
+rsvg_parse_style_attrs (RsvgHandle *ctx, RsvgNode *node, RsvgPropertyBag * atts,
+                        RsvgPropertyBag * animated)
 {
-    int i = 0, j = 0;
-    char *target = NULL;
-    gboolean found = FALSE;
-    GString *klazz_list = NULL;
     RsvgState *state;
+    const char *value;
 
     state = rsvg_node_get_state (node);
 
-    if (rsvg_property_bag_size (atts) > 0)
-        rsvg_parse_style_pairs (state, atts);
-
-    /* Try to properly support all of the following, including inheritance:
-     * *
-     * #id
-     * tag
-     * tag#id
-     * tag.class
-     * tag.class#id
-     *
-     * This is basically a semi-compliant CSS2 selection engine
-     */
-
-    /* * */
-    rsvg_lookup_apply_css_style (ctx, "*", state);
-
-    /* tag */
-    if (tag != NULL) {
-        rsvg_lookup_apply_css_style (ctx, tag, state);
-    }
-
-    if (klazz != NULL) {
-        i = strlen (klazz);
-        while (j < i) {
-            found = FALSE;
-            klazz_list = g_string_new (".");
-
-            while (j < i && g_ascii_isspace (klazz[j]))
-                j++;
-
-            while (j < i && !g_ascii_isspace (klazz[j]))
-                g_string_append_c (klazz_list, klazz[j++]);
-
-            /* tag.class#id */
-            if (tag != NULL && klazz_list->len != 1 && id != NULL) {
-                target = g_strdup_printf ("%s%s#%s", tag, klazz_list->str, id);
-                found = found || rsvg_lookup_apply_css_style (ctx, target, state);
-                g_free (target);
-            }
-
-            /* class#id */
-            if (klazz_list->len != 1 && id != NULL) {
-                target = g_strdup_printf ("%s#%s", klazz_list->str, id);
-                found = found || rsvg_lookup_apply_css_style (ctx, target, state);
-                g_free (target);
-            }
-
-            /* tag.class */
-            if (tag != NULL && klazz_list->len != 1) {
-                target = g_strdup_printf ("%s%s", tag, klazz_list->str);
-                found = found || rsvg_lookup_apply_css_style (ctx, target, state);
-                g_free (target);
-            }
-
-            /* didn't find anything more specific, just apply the class style */
-            if (!found) {
-                found = found || rsvg_lookup_apply_css_style (ctx, klazz_list->str, state);
-            }
-            g_string_free (klazz_list, TRUE);
-        }
-    }
-
-    /* #id */
-    if (id != NULL) {
-        target = g_strdup_printf ("#%s", id);
-        rsvg_lookup_apply_css_style (ctx, target, state);
-        g_free (target);
-    }
+    rsvg_parse_style_pairs (state, atts);
 
-    /* tag#id */
-    if (tag != NULL && id != NULL) {
-        target = g_strdup_printf ("%s#%s", tag, id);
-        rsvg_lookup_apply_css_style (ctx, target, state);
-        g_free (target);
-    }
+    rsvg_stylesheet_apply (ctx->priv->stylesheet, node, atts, animated);
 
-    if (rsvg_property_bag_size (atts) > 0) {
-        const char *value;
-
-        if ((value = rsvg_property_bag_lookup (atts, "style")) != NULL)
-            rsvg_parse_style (ctx, state, value);
-
-        if ((value = rsvg_property_bag_lookup (atts, "transform")) != NULL) {
-            if (!rsvg_parse_transform_attr (ctx, state, value)) {
-                rsvg_node_set_attribute_parse_error (node,
-                                                     "transform",
-                                                     "Invalid transformation");
-            }
+    if ((value = rsvg_property_bag_lookup (atts, "transform")) != NULL) {
+        if (!rsvg_parse_transform_attr (ctx, state, value)) {
+            rsvg_node_set_attribute_parse_error (node,
+                                                 "transform",
+                                                 "Invalid transformation");
         }
     }
 }
@@ -1607,6 +1229,21 @@ rsvg_property_bag_dup (RsvgPropertyBag * bag)
     return dup;
 }
 
+/**
+ * rsvg_property_bag_insert:
+ * @bag: a property bag returned by rsvg_property_bag_dup()
+ * @key: attribute name
+ * @value: attribute value
+ *
+ * Sets the value of an attribute, replacing any previous value.  Only bags
+ * that own their strings may be modified.
+ */
+void
+rsvg_property_bag_insert (RsvgPropertyBag * bag, const char *key, const char *value)
+{
+    g_hash_table_insert (bag, (gpointer) g_strdup (key), (gpointer) g_strdup (value));
+}
+
 void
 rsvg_property_bag_free (RsvgPropertyBag * bag)
 {
@@ -1737,28 +1374,8 @@ rsvg_state_get_cond_true (RsvgState *state)
     return state->cond_true;
 }
 
-RsvgCssColorSpec *
-rsvg_state_get_stop_color (RsvgState *state)
-{
-    if (state->has_stop_color) {
-        return &state->stop_color;
-    } else {
-        return NULL;
-    }
-}
-
-RsvgOpacitySpec *
-rsvg_state_get_stop_opacity (RsvgState *state)
-{
-    if (state->has_stop_opacity) {
-        return &state->stop_opacity;
-    } else {
-        return NULL;
-    }
-}
-
-guint32
-rsvg_state_get_current_color (RsvgState *state)
+void
+rsvg_state_set_path_length_scale (RsvgState *state, double scale)
 {
-    return state->current_color;
+    state->path_length_scale = scale;
 }
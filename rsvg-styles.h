@@ -51,6 +51,9 @@ typedef enum {
     TEXT_ANCHOR_END
 } TextAnchor;
 
+/* glyph-orientation-vertical: upright for full-width characters, sideways for the rest */
+#define GLYPH_ORIENTATION_AUTO (-1)
+
 typedef enum {
     UNICODE_BIDI_NORMAL = 0,
     UNICODE_BIDI_EMBED = 1,
@@ -62,12 +65,39 @@ typedef enum {
     RSVG_ENABLE_BACKGROUND_NEW
 } RsvgEnableBackgroundType;
 
+/* Keep this in sync with rust/src/blend_mode.rs:MixBlendMode */
+typedef enum {
+    RSVG_BLEND_MODE_NORMAL,
+    RSVG_BLEND_MODE_MULTIPLY,
+    RSVG_BLEND_MODE_SCREEN,
+    RSVG_BLEND_MODE_OVERLAY,
+    RSVG_BLEND_MODE_DARKEN,
+    RSVG_BLEND_MODE_LIGHTEN,
+    RSVG_BLEND_MODE_COLOR_DODGE,
+    RSVG_BLEND_MODE_COLOR_BURN,
+    RSVG_BLEND_MODE_HARD_LIGHT,
+    RSVG_BLEND_MODE_SOFT_LIGHT,
+    RSVG_BLEND_MODE_DIFFERENCE,
+    RSVG_BLEND_MODE_EXCLUSION,
+    RSVG_BLEND_MODE_HUE,
+    RSVG_BLEND_MODE_SATURATION,
+    RSVG_BLEND_MODE_COLOR,
+    RSVG_BLEND_MODE_LUMINOSITY,
+    RSVG_BLEND_MODE_PLUS_DARKER,
+    RSVG_BLEND_MODE_PLUS_LIGHTER
+} RsvgBlendMode;
+
+typedef enum {
+    RSVG_MASK_TYPE_LUMINANCE,
+    RSVG_MASK_TYPE_ALPHA
+} RsvgMaskType;
+
 typedef struct _RsvgVpathDash RsvgVpathDash;
 
 struct _RsvgVpathDash {
     RsvgLength offset;
     int n_dash;
-    double *dash;
+    RsvgLength *dash;
 };
 
 struct _RsvgState {
@@ -128,6 +158,8 @@ struct _RsvgState {
     gboolean has_text_dir;
     PangoGravity text_gravity;
     gboolean has_text_gravity;
+    int glyph_orientation_vertical; /* degrees, or GLYPH_ORIENTATION_AUTO */
+    gboolean has_glyph_orientation_vertical;
     UnicodeBidi unicode_bidi;
     gboolean has_unicode_bidi;
     TextAnchor text_anchor;
@@ -137,12 +169,6 @@ struct _RsvgState {
 
     guint text_offset;
 
-    RsvgCssColorSpec stop_color;
-    gboolean has_stop_color;
-
-    RsvgOpacitySpec stop_opacity;
-    gboolean has_stop_opacity;
-
     gboolean visible;
     gboolean has_visible;
 
@@ -156,15 +182,14 @@ struct _RsvgState {
     gboolean has_dash;
     gboolean has_dashoffset;
 
+    /* Ratio between the computed length of a shape and its pathLength
+     * attribute; dashes get scaled by this.  Not inherited.
+     */
+    double path_length_scale;
+
     guint32 current_color;
     gboolean has_current_color;
 
-    guint32 flood_color;
-    gboolean has_flood_color;
-
-    guchar flood_opacity;
-    gboolean has_flood_opacity;
-
     char *startMarker;
     char *middleMarker;
     char *endMarker;
@@ -174,6 +199,11 @@ struct _RsvgState {
 
     cairo_operator_t comp_op;
     RsvgEnableBackgroundType enable_background;
+    RsvgBlendMode mix_blend_mode;
+    gboolean isolate;
+
+    /* Only used by <mask> elements.  Not inherited. */
+    RsvgMaskType mask_type;
 
     cairo_antialias_t shape_rendering_type;
     gboolean has_shape_rendering_type;
@@ -205,12 +235,29 @@ void rsvg_state_free_all    (RsvgState * state);
 G_GNUC_INTERNAL
 void rsvg_parse_style_pairs (RsvgState * state, RsvgPropertyBag * atts);
 G_GNUC_INTERNAL
-void rsvg_parse_style	    (RsvgHandle * ctx, RsvgState * state, const char *str);
+void rsvg_parse_style_pair  (RsvgState * state, const gchar * name, const gchar * value, gboolean important);
 G_GNUC_INTERNAL
 void rsvg_parse_cssbuffer   (RsvgHandle * ctx, const char *buff, size_t buflen);
 G_GNUC_INTERNAL
-void rsvg_parse_style_attrs (RsvgHandle *ctx, RsvgNode *node, const char *tag,
-                             const char *klazz, const char *id, RsvgPropertyBag * atts);
+void rsvg_parse_style_attrs (RsvgHandle *ctx, RsvgNode *node, RsvgPropertyBag * atts,
+                             RsvgPropertyBag * animated);
+
+/* Implemented in rust/src/css.rs */
+G_GNUC_INTERNAL
+RsvgStylesheet *rsvg_stylesheet_new (void);
+
+/* Implemented in rust/src/css.rs */
+G_GNUC_INTERNAL
+void rsvg_stylesheet_free (RsvgStylesheet *sheet);
+
+/* Implemented in rust/src/css.rs */
+G_GNUC_INTERNAL
+void rsvg_stylesheet_parse (RsvgStylesheet *sheet, RsvgHandle *handle, const char *buf, size_t len);
+
+/* Implemented in rust/src/css.rs */
+G_GNUC_INTERNAL
+void rsvg_stylesheet_apply (RsvgStylesheet *sheet, RsvgNode *node, RsvgPropertyBag *atts,
+                            RsvgPropertyBag *animated);
 
 /* Implemented in rust/src/transform.rs */
 G_GNUC_INTERNAL
@@ -241,14 +288,12 @@ gboolean rsvg_state_has_overflow (RsvgState *state);
 G_GNUC_INTERNAL
 gboolean rsvg_state_get_cond_true (RsvgState *state);
 
+/* Implemented in rust/src/blend_mode.rs */
 G_GNUC_INTERNAL
-RsvgCssColorSpec *rsvg_state_get_stop_color (RsvgState *state);
-
-G_GNUC_INTERNAL
-RsvgOpacitySpec *rsvg_state_get_stop_opacity (RsvgState *state);
+void rsvg_blend_mode_composite (cairo_surface_t *backdrop, cairo_surface_t *source, RsvgBlendMode mode);
 
 G_GNUC_INTERNAL
-guint32 rsvg_state_get_current_color (RsvgState *state);
+void rsvg_state_set_path_length_scale (RsvgState *state, double scale);
 
 G_END_DECLS
 
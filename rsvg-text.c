@@ -24,18 +24,33 @@
    Author: Raph Levien <raph@artofcode.com>
 */
 
+#include "config.h"
+
 #include <string.h>
 
 #include "rsvg-private.h"
 #include "rsvg-styles.h"
 #include "rsvg-text.h"
 #include "rsvg-css.h"
+#include "rsvg-fonts.h"
 
 #include "rsvg-shapes.h"
+#include "rsvg-cairo-draw.h"
 
 /* what we use for text rendering depends on what cairo has to offer */
 #include <pango/pangocairo.h>
 
+#ifdef HAVE_PANGOFT2
+#include <pango/pangofc-font.h>
+#include <ft2build.h>
+#include FT_FREETYPE_H
+#endif
+
+typedef enum {
+    LENGTH_ADJUST_SPACING,
+    LENGTH_ADJUST_SPACING_AND_GLYPHS
+} LengthAdjust;
+
 typedef struct _RsvgNodeText RsvgNodeText;
 
 struct _RsvgNodeText {
@@ -43,6 +58,15 @@ struct _RsvgNodeText {
     gboolean x_specified;
     gboolean y_specified;
     RsvgLength dx, dy;
+    RsvgLength text_length;
+    gboolean text_length_specified;
+    LengthAdjust length_adjust;
+
+    /* All the values in the x, y, dx, dy and rotate attributes, one per
+     * character.  The first ones are also stored in the fields above.
+     */
+    GArray *xs, *ys, *dxs, *dys;
+    GArray *rotate;
 };
 
 typedef struct _RsvgNodeTref RsvgNodeTref;
@@ -51,6 +75,38 @@ struct _RsvgNodeTref {
     char *link;
 };
 
+typedef enum {
+    TEXT_PATH_METHOD_ALIGN,
+    TEXT_PATH_METHOD_STRETCH
+} TextPathMethod;
+
+typedef enum {
+    TEXT_PATH_SPACING_EXACT,
+    TEXT_PATH_SPACING_AUTO
+} TextPathSpacing;
+
+typedef struct _RsvgNodeTextPath RsvgNodeTextPath;
+
+struct _RsvgNodeTextPath {
+    char *link;
+    RsvgLength start_offset;
+    TextPathMethod method;
+    TextPathSpacing spacing;
+};
+
+/* The path along which the contents of a textPath get laid out.  While
+ * we are inside a textPath, the x coordinate of the current text
+ * position is the distance along the path, and the y coordinate is an
+ * offset perpendicular to the path.
+ */
+typedef struct {
+    RsvgPathMeasure *measure;
+    cairo_matrix_t affine;      /* the path's own transform */
+    double length;
+    TextPathMethod method;
+    TextPathSpacing spacing;
+} TextPath;
+
 char *
 rsvg_make_valid_utf8 (const char *str, int len)
 {
@@ -124,23 +180,289 @@ _rsvg_text_chomp (RsvgState *state, GString * in, gboolean * lastwasspace)
     return out;
 }
 
+/* Fills @list with the lengths in @value, and sets @first to the first of
+ * them.  Returns whether there was any.
+ */
+static gboolean
+parse_length_list (GArray *list, const char *value, LengthDir dir, RsvgLength *first)
+{
+    gchar **tokens;
+    guint i;
+
+    g_array_set_size (list, 0);
+
+    tokens = g_strsplit_set (value, ", \t\r\n", -1);
+    for (i = 0; tokens[i] != NULL; i++) {
+        RsvgLength length;
+
+        if (tokens[i][0] == '\0')
+            continue;
+
+        length = rsvg_length_parse (tokens[i], dir);
+        g_array_append_val (list, length);
+    }
+    g_strfreev (tokens);
+
+    if (list->len == 0)
+        return FALSE;
+
+    *first = g_array_index (list, RsvgLength, 0);
+    return TRUE;
+}
+
+static void
+parse_number_list (GArray *list, const char *value)
+{
+    gchar **tokens;
+    guint i;
+
+    g_array_set_size (list, 0);
+
+    tokens = g_strsplit_set (value, ", \t\r\n", -1);
+    for (i = 0; tokens[i] != NULL; i++) {
+        double number;
+        char *end;
+
+        if (tokens[i][0] == '\0')
+            continue;
+
+        /* The list ends at the first token that is not a number */
+        number = g_ascii_strtod (tokens[i], &end);
+        if (end == tokens[i] || *end != '\0')
+            break;
+
+        g_array_append_val (list, number);
+    }
+    g_strfreev (tokens);
+}
+
 static void
 set_text_common_atts (RsvgNodeText *text, RsvgPropertyBag *atts)
 {
     const char *value;
 
-    if ((value = rsvg_property_bag_lookup (atts, "x"))) {
-        text->x = rsvg_length_parse (value, LENGTH_DIR_HORIZONTAL);
-        text->x_specified = TRUE;
-    }
-    if ((value = rsvg_property_bag_lookup (atts, "y"))) {
-        text->y = rsvg_length_parse (value, LENGTH_DIR_VERTICAL);
-        text->y_specified = TRUE;
-    }
+    if ((value = rsvg_property_bag_lookup (atts, "x")))
+        text->x_specified = parse_length_list (text->xs, value, LENGTH_DIR_HORIZONTAL, &text->x);
+    if ((value = rsvg_property_bag_lookup (atts, "y")))
+        text->y_specified = parse_length_list (text->ys, value, LENGTH_DIR_VERTICAL, &text->y);
     if ((value = rsvg_property_bag_lookup (atts, "dx")))
-        text->dx = rsvg_length_parse (value, LENGTH_DIR_HORIZONTAL);
+        parse_length_list (text->dxs, value, LENGTH_DIR_HORIZONTAL, &text->dx);
     if ((value = rsvg_property_bag_lookup (atts, "dy")))
-        text->dy = rsvg_length_parse (value, LENGTH_DIR_VERTICAL);
+        parse_length_list (text->dys, value, LENGTH_DIR_VERTICAL, &text->dy);
+    if ((value = rsvg_property_bag_lookup (atts, "rotate")))
+        parse_number_list (text->rotate, value);
+
+    if ((value = rsvg_property_bag_lookup (atts, "textLength"))) {
+        text->text_length = rsvg_length_parse (value, LENGTH_DIR_HORIZONTAL);
+        /* Negative values are an error */
+        text->text_length_specified = text->text_length.length >= 0;
+    }
+    if ((value = rsvg_property_bag_lookup (atts, "lengthAdjust"))) {
+        if (g_str_equal (value, "spacingAndGlyphs"))
+            text->length_adjust = LENGTH_ADJUST_SPACING_AND_GLYPHS;
+        else
+            text->length_adjust = LENGTH_ADJUST_SPACING;
+    }
+}
+
+static void
+rsvg_node_text_init_lists (RsvgNodeText *text)
+{
+    text->xs = g_array_new (FALSE, FALSE, sizeof (RsvgLength));
+    text->ys = g_array_new (FALSE, FALSE, sizeof (RsvgLength));
+    text->dxs = g_array_new (FALSE, FALSE, sizeof (RsvgLength));
+    text->dys = g_array_new (FALSE, FALSE, sizeof (RsvgLength));
+    text->rotate = g_array_new (FALSE, FALSE, sizeof (double));
+}
+
+static void
+rsvg_node_text_free (gpointer impl)
+{
+    RsvgNodeText *text = impl;
+
+    g_array_free (text->xs, TRUE);
+    g_array_free (text->ys, TRUE);
+    g_array_free (text->dxs, TRUE);
+    g_array_free (text->dys, TRUE);
+    g_array_free (text->rotate, TRUE);
+    g_free (text);
+}
+
+/* The position lists of the text and tspan elements that enclose the
+ * characters being laid out, innermost first.  Each element's lists apply
+ * to its characters in order, including those in nested elements; where a
+ * list is shorter than that, the lists of the enclosing elements apply.
+ */
+typedef struct {
+    RsvgNodeText *text;
+    guint index;
+
+    /* Set up to fit the element's textLength */
+    double spacing_x, spacing_y;
+    double glyph_scale;
+} TextPositionsFrame;
+
+typedef struct {
+    GSList *frames;
+
+    /* The dx or dy offsets added up while measuring, which a textLength
+     * doesn't stretch along with the glyphs.
+     */
+    double offsets;
+} TextPositions;
+
+typedef struct {
+    gboolean has_x, has_y;
+    double x, y;
+    double dx, dy;
+    double rotate;
+} CharPosition;
+
+static void
+text_positions_push (TextPositions *positions, RsvgNodeText *text)
+{
+    TextPositionsFrame *frame = g_new0 (TextPositionsFrame, 1);
+
+    frame->text = text;
+    frame->index = 0;
+    frame->glyph_scale = 1.0;
+    positions->frames = g_slist_prepend (positions->frames, frame);
+}
+
+static void
+text_positions_pop (TextPositions *positions)
+{
+    g_free (positions->frames->data);
+    positions->frames = g_slist_delete_link (positions->frames, positions->frames);
+}
+
+/* Looks up a value for the character at @index in @list.  The first
+ * value is handled when laying out the element itself, so it is claimed
+ * here but not applied.
+ */
+static gboolean
+text_positions_lookup (GArray *list, guint index, gboolean *found, double *value,
+                       RsvgDrawingCtx *ctx)
+{
+    if (*found || index >= list->len)
+        return FALSE;
+
+    *found = TRUE;
+    if (index == 0)
+        return FALSE;
+
+    *value = rsvg_length_normalize (&g_array_index (list, RsvgLength, index), ctx);
+    return TRUE;
+}
+
+/* Gets the position of the next character and moves past it */
+static void
+text_positions_next (TextPositions *positions, RsvgDrawingCtx *ctx, CharPosition *pos)
+{
+    gboolean found_x = FALSE, found_y = FALSE, found_dx = FALSE, found_dy = FALSE;
+    gboolean found_rotate = FALSE;
+    GSList *l;
+
+    memset (pos, 0, sizeof (*pos));
+
+    for (l = positions->frames; l != NULL; l = l->next) {
+        TextPositionsFrame *frame = l->data;
+        RsvgNodeText *text = frame->text;
+
+        pos->has_x |= text_positions_lookup (text->xs, frame->index, &found_x, &pos->x, ctx);
+        pos->has_y |= text_positions_lookup (text->ys, frame->index, &found_y, &pos->y, ctx);
+        text_positions_lookup (text->dxs, frame->index, &found_dx, &pos->dx, ctx);
+        text_positions_lookup (text->dys, frame->index, &found_dy, &pos->dy, ctx);
+
+        /* Extra spacing goes between the element's characters */
+        if (frame->index > 0) {
+            pos->dx += frame->spacing_x;
+            pos->dy += frame->spacing_y;
+        }
+
+        /* The last rotation applies to the rest of the characters */
+        if (!found_rotate && text->rotate->len > 0) {
+            found_rotate = TRUE;
+            pos->rotate = g_array_index (text->rotate, double,
+                                         MIN (frame->index, text->rotate->len - 1));
+        }
+
+        frame->index++;
+    }
+}
+
+/* Undoes the last text_positions_next() */
+static void
+text_positions_back (TextPositions *positions)
+{
+    GSList *l;
+
+    for (l = positions->frames; l != NULL; l = l->next) {
+        TextPositionsFrame *frame = l->data;
+
+        frame->index--;
+    }
+}
+
+/* Gets the factor by which the glyphs get stretched along the text
+ * direction to fit the textLength of the enclosing elements.
+ */
+static double
+text_positions_get_glyph_scale (TextPositions *positions)
+{
+    double scale = 1.0;
+    GSList *l;
+
+    for (l = positions->frames; l != NULL; l = l->next) {
+        TextPositionsFrame *frame = l->data;
+
+        scale *= frame->glyph_scale;
+    }
+
+    return scale;
+}
+
+static void
+text_positions_skip (TextPositions *positions, const char *text)
+{
+    glong n_chars = g_utf8_strlen (text, -1);
+    GSList *l;
+
+    for (l = positions->frames; l != NULL; l = l->next) {
+        TextPositionsFrame *frame = l->data;
+
+        frame->index += n_chars;
+    }
+}
+
+static GArray *
+text_positions_save (TextPositions *positions)
+{
+    GArray *saved = g_array_new (FALSE, FALSE, sizeof (guint));
+    GSList *l;
+
+    for (l = positions->frames; l != NULL; l = l->next) {
+        TextPositionsFrame *frame = l->data;
+
+        g_array_append_val (saved, frame->index);
+    }
+
+    return saved;
+}
+
+static void
+text_positions_restore (TextPositions *positions, GArray *saved)
+{
+    GSList *l;
+    guint i;
+
+    for (l = positions->frames, i = 0; l != NULL; l = l->next, i++) {
+        TextPositionsFrame *frame = l->data;
+
+        frame->index = g_array_index (saved, guint, i);
+    }
+
+    g_array_free (saved, TRUE);
 }
 
 
@@ -152,29 +474,43 @@ rsvg_node_text_set_atts (RsvgNode *node, gpointer impl, RsvgHandle *handle, Rsvg
     set_text_common_atts (text, atts);
 }
 
-static void rsvg_text_render_text (RsvgDrawingCtx * ctx, const char *text, gdouble * x, gdouble * y);
+static void rsvg_text_render_text (RsvgDrawingCtx * ctx, const char *text, gboolean stretched,
+                                   gdouble * x, gdouble * y);
+
+static void rsvg_text_render_positioned_text (RsvgDrawingCtx *ctx, TextPositions *positions,
+                                              const char *text, gdouble *x, gdouble *y);
+
+static void rsvg_text_render_text_on_path (RsvgDrawingCtx * ctx, TextPath * path,
+                                           const char *text, gdouble * x, gdouble * y);
 
 static void
-_rsvg_node_text_type_children (RsvgNode * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_type_children (RsvgNode * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                                gdouble * x, gdouble * y, gboolean * lastwasspace,
-                               gboolean usetextonly);
+                               gboolean usetextonly, TextPath * path);
 
 static void
 _rsvg_node_text_type_tspan (RsvgNode *node, RsvgNodeText *self, RsvgDrawingCtx *ctx,
-                            gdouble *x, gdouble *y, gboolean *lastwasspace,
-                            gboolean usetextonly);
+                            TextPositions *positions, gdouble *x, gdouble *y,
+                            gboolean *lastwasspace, gboolean usetextonly, TextPath *path);
 
 static void
-_rsvg_node_text_type_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_type_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                            gdouble * x, gdouble * y, gboolean * lastwasspace,
-                           gboolean usetextonly);
+                           gboolean usetextonly, TextPath * path);
+
+static void
+_rsvg_node_text_type_text_path (RsvgNode *node, RsvgNodeTextPath *self, RsvgDrawingCtx *ctx,
+                                TextPositions *positions, gdouble *x, gdouble *y,
+                                gboolean *lastwasspace);
 
 typedef struct {
     RsvgDrawingCtx *ctx;
+    TextPositions *positions;
     gdouble *x;
     gdouble *y;
     gboolean *lastwasspace;
     gboolean usetextonly;
+    TextPath *path;
 } DrawTextClosure;
 
 static gboolean
@@ -188,16 +524,25 @@ draw_text_child (RsvgNode *node, gpointer data)
     if (type == RSVG_NODE_TYPE_CHARS) {
         RsvgNodeChars *chars = rsvg_rust_cnode_get_impl (node);
         GString *str = _rsvg_text_chomp (rsvg_current_state (closure->ctx), chars->contents, closure->lastwasspace);
-        rsvg_text_render_text (closure->ctx, str->str, closure->x, closure->y);
+        if (closure->path) {
+            /* Text on a path doesn't get positioned per character */
+            text_positions_skip (closure->positions, str->str);
+            rsvg_text_render_text_on_path (closure->ctx, closure->path, str->str, closure->x, closure->y);
+        } else {
+            rsvg_text_render_positioned_text (closure->ctx, closure->positions, str->str,
+                                              closure->x, closure->y);
+        }
         g_string_free (str, TRUE);
     } else {
         if (closure->usetextonly) {
             _rsvg_node_text_type_children (node,
                                            closure->ctx,
+                                           closure->positions,
                                            closure->x,
                                            closure->y,
                                            closure->lastwasspace,
-                                           closure->usetextonly);
+                                           closure->usetextonly,
+                                           closure->path);
         } else {
             if (type == RSVG_NODE_TYPE_TSPAN) {
                 RsvgNodeText *tspan = rsvg_rust_cnode_get_impl (node);
@@ -205,19 +550,35 @@ draw_text_child (RsvgNode *node, gpointer data)
                 _rsvg_node_text_type_tspan (node,
                                             tspan,
                                             closure->ctx,
+                                            closure->positions,
                                             closure->x,
                                             closure->y,
                                             closure->lastwasspace,
-                                            closure->usetextonly);
+                                            closure->usetextonly,
+                                            closure->path);
                 rsvg_state_pop (closure->ctx);
             } else if (type == RSVG_NODE_TYPE_TREF) {
                 RsvgNodeTref *tref = rsvg_rust_cnode_get_impl (node);
                 _rsvg_node_text_type_tref (tref,
                                            closure->ctx,
+                                           closure->positions,
                                            closure->x,
                                            closure->y,
                                            closure->lastwasspace,
-                                           closure->usetextonly);
+                                           closure->usetextonly,
+                                           closure->path);
+            } else if (type == RSVG_NODE_TYPE_TEXT_PATH && closure->path == NULL) {
+                /* textPath elements can't be nested */
+                RsvgNodeTextPath *text_path = rsvg_rust_cnode_get_impl (node);
+                rsvg_state_push (closure->ctx);
+                _rsvg_node_text_type_text_path (node,
+                                                text_path,
+                                                closure->ctx,
+                                                closure->positions,
+                                                closure->x,
+                                                closure->y,
+                                                closure->lastwasspace);
+                rsvg_state_pop (closure->ctx);
             }
         }
     }
@@ -227,19 +588,21 @@ draw_text_child (RsvgNode *node, gpointer data)
 
 /* This function is responsible of selecting render for a text element including its children and giving it the drawing context */
 static void
-_rsvg_node_text_type_children (RsvgNode * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_type_children (RsvgNode * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                                gdouble * x, gdouble * y, gboolean * lastwasspace,
-                               gboolean usetextonly)
+                               gboolean usetextonly, TextPath * path)
 {
     DrawTextClosure closure;
 
     rsvg_push_discrete_layer (ctx);
 
     closure.ctx = ctx;
+    closure.positions = positions;
     closure.x = x;
     closure.y = y;
     closure.lastwasspace = lastwasspace;
     closure.usetextonly = usetextonly;
+    closure.path = path;
 
     rsvg_node_foreach_child (self, draw_text_child, &closure);
 
@@ -247,12 +610,12 @@ _rsvg_node_text_type_children (RsvgNode * self, RsvgDrawingCtx * ctx,
 }
 
 static gboolean
-_rsvg_node_text_length_children (RsvgNode * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_length_children (RsvgNode * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                                  gdouble * length, gboolean * lastwasspace,
                                  gboolean usetextonly);
 
 static gboolean
-_rsvg_node_text_length_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_length_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                              gdouble * x, gboolean * lastwasspace,
                              gboolean usetextonly);
 
@@ -260,14 +623,19 @@ static gboolean
 _rsvg_node_text_length_tspan (RsvgNode *node,
                               RsvgNodeText *self,
                               RsvgDrawingCtx *ctx,
+                              TextPositions *positions,
                               gdouble *x,
                               gboolean *lastwasspace,
                               gboolean usetextonly);
 
 static gdouble rsvg_text_length_text_as_string (RsvgDrawingCtx * ctx, const char *text);
 
+static gboolean rsvg_text_length_positioned_text (RsvgDrawingCtx *ctx, TextPositions *positions,
+                                                  const char *text, gdouble *length);
+
 typedef struct {
     RsvgDrawingCtx *ctx;
+    TextPositions *positions;
     gdouble *length;
     gboolean *lastwasspace;
     gboolean usetextonly;
@@ -290,12 +658,14 @@ compute_child_length (RsvgNode *node, gpointer data)
     if (type == RSVG_NODE_TYPE_CHARS) {
         RsvgNodeChars *chars = rsvg_rust_cnode_get_impl (node);
         GString *str = _rsvg_text_chomp (rsvg_current_state (closure->ctx), chars->contents, closure->lastwasspace);
-        *closure->length += rsvg_text_length_text_as_string (closure->ctx, str->str);
+        done = rsvg_text_length_positioned_text (closure->ctx, closure->positions, str->str,
+                                                 closure->length);
         g_string_free (str, TRUE);
     } else {
         if (closure->usetextonly) {
             done = _rsvg_node_text_length_children (node,
                                                     closure->ctx,
+                                                    closure->positions,
                                                     closure->length,
                                                     closure->lastwasspace,
                                                     closure->usetextonly);
@@ -305,6 +675,7 @@ compute_child_length (RsvgNode *node, gpointer data)
                 done = _rsvg_node_text_length_tspan (node,
                                                      tspan,
                                                      closure->ctx,
+                                                     closure->positions,
                                                      closure->length,
                                                      closure->lastwasspace,
                                                      closure->usetextonly);
@@ -312,9 +683,13 @@ compute_child_length (RsvgNode *node, gpointer data)
                 RsvgNodeTref *tref = rsvg_rust_cnode_get_impl (node);
                 done = _rsvg_node_text_length_tref (tref,
                                                     closure->ctx,
+                                                    closure->positions,
                                                     closure->length,
                                                     closure->lastwasspace,
                                                     closure->usetextonly);
+            } else if (type == RSVG_NODE_TYPE_TEXT_PATH) {
+                /* The text in a textPath is positioned independently */
+                done = TRUE;
             }
         }
     }
@@ -326,13 +701,14 @@ compute_child_length (RsvgNode *node, gpointer data)
 }
 
 static gboolean
-_rsvg_node_text_length_children (RsvgNode * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_length_children (RsvgNode * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                                  gdouble * length, gboolean * lastwasspace,
                                  gboolean usetextonly)
 {
     ChildrenLengthClosure closure;
 
     closure.ctx = ctx;
+    closure.positions = positions;
     closure.length = length;
     closure.lastwasspace = lastwasspace;
     closure.usetextonly = usetextonly;
@@ -343,24 +719,78 @@ _rsvg_node_text_length_children (RsvgNode * self, RsvgDrawingCtx * ctx,
     return closure.done;
 }
 
+/* Measures the advance of the children of a text or tspan element, up to
+ * the next absolutely positioned character.  If the element has a
+ * textLength, its characters get spaced or stretched to fit it, and that
+ * gets returned instead.  The element must be at the top of @positions.
+ */
+static double
+rsvg_node_text_measure (RsvgNode *node, RsvgNodeText *self, RsvgDrawingCtx *ctx,
+                        TextPositions *positions, gboolean lastwasspace, gboolean usetextonly)
+{
+    TextPositionsFrame *frame = positions->frames->data;
+    GArray *saved;
+    double length = 0, text_length, offsets, saved_offsets;
+    guint n_chars;
+
+    saved = text_positions_save (positions);
+    saved_offsets = positions->offsets;
+    positions->offsets = 0;
+    _rsvg_node_text_length_children (node, ctx, positions, &length, &lastwasspace, usetextonly);
+    n_chars = frame->index;
+    offsets = positions->offsets;
+    positions->offsets = saved_offsets;
+    text_positions_restore (positions, saved);
+
+    if (!self->text_length_specified)
+        return length;
+
+    text_length = rsvg_length_normalize (&self->text_length, ctx);
+
+    if (self->length_adjust == LENGTH_ADJUST_SPACING_AND_GLYPHS) {
+        /* Only the glyphs get stretched; the offsets stay as they are */
+        if (length - offsets <= 0 || text_length - offsets <= 0)
+            return length;
+
+        frame->glyph_scale = (text_length - offsets) / (length - offsets);
+    } else {
+        double spacing;
+
+        if (n_chars < 2)
+            return length;
+
+        spacing = (text_length - length) / (n_chars - 1);
+        if (PANGO_GRAVITY_IS_VERTICAL (rsvg_current_state (ctx)->text_gravity))
+            frame->spacing_y = spacing;
+        else
+            frame->spacing_x = spacing;
+    }
+
+    return text_length;
+}
 
 static void
 rsvg_node_text_draw (RsvgNode *node, gpointer impl, RsvgDrawingCtx *ctx, int dominate)
 {
     RsvgNodeText *text = impl;
+    TextPositions positions = { NULL };
     double x, y, dx, dy, length = 0;
     gboolean lastwasspace = TRUE;
 
     rsvg_state_reinherit_top (ctx, rsvg_node_get_state (node), dominate);
 
+    text_positions_push (&positions, text);
+
     x = rsvg_length_normalize (&text->x, ctx);
     y = rsvg_length_normalize (&text->y, ctx);
     dx = rsvg_length_normalize (&text->dx, ctx);
     dy = rsvg_length_normalize (&text->dy, ctx);
 
-    if (rsvg_current_state (ctx)->text_anchor != TEXT_ANCHOR_START) {
-        _rsvg_node_text_length_children (node, ctx, &length, &lastwasspace, FALSE);
-        if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_MIDDLE)
+    if (rsvg_current_state (ctx)->text_anchor != TEXT_ANCHOR_START || text->text_length_specified) {
+        length = rsvg_node_text_measure (node, text, ctx, &positions, lastwasspace, FALSE);
+        if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_START)
+            length = 0;
+        else if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_MIDDLE)
             length /= 2;
     }
     if (PANGO_GRAVITY_IS_VERTICAL (rsvg_current_state (ctx)->text_gravity)) {
@@ -380,7 +810,9 @@ rsvg_node_text_draw (RsvgNode *node, gpointer impl, RsvgDrawingCtx *ctx, int dom
     y += dy;
 
     lastwasspace = TRUE;
-    _rsvg_node_text_type_children (node, ctx, &x, &y, &lastwasspace, FALSE);
+    _rsvg_node_text_type_children (node, ctx, &positions, &x, &y, &lastwasspace, FALSE, NULL);
+
+    text_positions_pop (&positions);
 }
 
 RsvgNode *
@@ -390,6 +822,7 @@ rsvg_new_text (const char *element_name, RsvgNode *parent)
 
     text = g_new0 (RsvgNodeText, 1);
     text->x = text->y = text->dx = text->dy = rsvg_length_parse ("0", LENGTH_DIR_BOTH);
+    rsvg_node_text_init_lists (text);
 
     return rsvg_rust_cnode_new (RSVG_NODE_TYPE_TEXT,
                                 parent,
@@ -397,13 +830,13 @@ rsvg_new_text (const char *element_name, RsvgNode *parent)
                                 text,
                                 rsvg_node_text_set_atts,
                                 rsvg_node_text_draw,
-                                g_free);                                
+                                rsvg_node_text_free);
 }
 
 static void
 _rsvg_node_text_type_tspan (RsvgNode *node, RsvgNodeText *self, RsvgDrawingCtx *ctx,
-                            gdouble *x, gdouble *y, gboolean *lastwasspace,
-                            gboolean usetextonly)
+                            TextPositions *positions, gdouble *x, gdouble *y,
+                            gboolean *lastwasspace, gboolean usetextonly, TextPath *path)
 {
     double dx, dy, length = 0;
 
@@ -412,10 +845,13 @@ _rsvg_node_text_type_tspan (RsvgNode *node, RsvgNodeText *self, RsvgDrawingCtx *
     dx = rsvg_length_normalize (&self->dx, ctx);
     dy = rsvg_length_normalize (&self->dy, ctx);
 
-    if (rsvg_current_state (ctx)->text_anchor != TEXT_ANCHOR_START) {
-        gboolean lws = *lastwasspace;
-        _rsvg_node_text_length_children (node, ctx, &length, &lws, usetextonly);
-        if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_MIDDLE)
+    text_positions_push (positions, self);
+
+    if (rsvg_current_state (ctx)->text_anchor != TEXT_ANCHOR_START || self->text_length_specified) {
+        length = rsvg_node_text_measure (node, self, ctx, positions, *lastwasspace, usetextonly);
+        if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_START)
+            length = 0;
+        else if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_MIDDLE)
             length /= 2;
     }
 
@@ -442,27 +878,51 @@ _rsvg_node_text_type_tspan (RsvgNode *node, RsvgNodeText *self, RsvgDrawingCtx *
         }
     }
     *y += dy;
-    _rsvg_node_text_type_children (node, ctx, x, y, lastwasspace, usetextonly);
+    _rsvg_node_text_type_children (node, ctx, positions, x, y, lastwasspace, usetextonly, path);
+
+    text_positions_pop (positions);
 }
 
 static gboolean
 _rsvg_node_text_length_tspan (RsvgNode *node,
                               RsvgNodeText *self,
                               RsvgDrawingCtx *ctx,
+                              TextPositions *positions,
                               gdouble *length,
                               gboolean *lastwasspace,
                               gboolean usetextonly)
 {
+    gboolean done;
+    double start, offset, offsets;
+
     if (self->x_specified || self->y_specified)
         return TRUE;
 
     if (PANGO_GRAVITY_IS_VERTICAL (rsvg_current_state (ctx)->text_gravity))
-        *length += rsvg_length_normalize (&self->dy, ctx);
+        offset = rsvg_length_normalize (&self->dy, ctx);
     else
-        *length += rsvg_length_normalize (&self->dx, ctx);
+        offset = rsvg_length_normalize (&self->dx, ctx);
 
-    return _rsvg_node_text_length_children (node, ctx, length,
+    *length += offset;
+    positions->offsets += offset;
+
+    start = *length;
+    offsets = positions->offsets;
+
+    text_positions_push (positions, self);
+    done = _rsvg_node_text_length_children (node, ctx, positions, length,
                                             lastwasspace, usetextonly);
+    text_positions_pop (positions);
+
+    /* The tspan's characters get fitted into its textLength, which then
+     * counts as the advance of its glyphs.
+     */
+    if (self->text_length_specified && !done) {
+        *length = start + rsvg_length_normalize (&self->text_length, ctx);
+        positions->offsets = offsets;
+    }
+
+    return done;
 }
 
 static void
@@ -486,6 +946,7 @@ rsvg_new_tspan (const char *element_name, RsvgNode *parent)
 
     text = g_new0 (RsvgNodeText, 1);
     text->dx = text->dy = rsvg_length_parse ("0", LENGTH_DIR_BOTH);
+    rsvg_node_text_init_lists (text);
 
     return rsvg_rust_cnode_new (RSVG_NODE_TYPE_TSPAN,
                                 parent,
@@ -493,13 +954,13 @@ rsvg_new_tspan (const char *element_name, RsvgNode *parent)
                                 text,
                                 rsvg_node_tspan_set_atts,
                                 rsvg_node_tspan_draw,
-                                g_free);
+                                rsvg_node_text_free);
 }
 
 static void
-_rsvg_node_text_type_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx,
+_rsvg_node_text_type_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx, TextPositions * positions,
                            gdouble * x, gdouble * y, gboolean * lastwasspace,
-                           gboolean usetextonly)
+                           gboolean usetextonly, TextPath * path)
 {
     RsvgNode *link;
 
@@ -509,14 +970,14 @@ _rsvg_node_text_type_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx,
     if (link == NULL)
       return;
 
-    _rsvg_node_text_type_children (link, ctx, x, y, lastwasspace, TRUE);
+    _rsvg_node_text_type_children (link, ctx, positions, x, y, lastwasspace, TRUE, path);
 
     rsvg_drawing_ctx_release_node (ctx, link);
 }
 
 static gboolean
-_rsvg_node_text_length_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx, gdouble * x,
-                             gboolean * lastwasspace, gboolean usetextonly)
+_rsvg_node_text_length_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx, TextPositions * positions,
+                             gdouble * x, gboolean * lastwasspace, gboolean usetextonly)
 {
     gboolean result;
     RsvgNode *link;
@@ -527,7 +988,7 @@ _rsvg_node_text_length_tref (RsvgNodeTref * self, RsvgDrawingCtx * ctx, gdouble
     if (link == NULL)
       return FALSE;
 
-    result = _rsvg_node_text_length_children (link, ctx, x, lastwasspace, TRUE);
+    result = _rsvg_node_text_length_children (link, ctx, positions, x, lastwasspace, TRUE);
 
     rsvg_drawing_ctx_release_node (ctx, link);
 
@@ -578,6 +1039,138 @@ rsvg_new_tref (const char *element_name, RsvgNode *parent)
                                 rsvg_node_tref_free);
 }
 
+static void
+_rsvg_node_text_type_text_path (RsvgNode *node, RsvgNodeTextPath *self, RsvgDrawingCtx *ctx,
+                                TextPositions *positions, gdouble *x, gdouble *y,
+                                gboolean *lastwasspace)
+{
+    RsvgNode *link;
+    TextPath path;
+    double distance, offset, length = 0;
+    double px, py, angle;
+
+    rsvg_state_reinherit_top (ctx, rsvg_node_get_state (node), 0);
+
+    if (self->link == NULL)
+        return;
+    link = rsvg_drawing_ctx_acquire_node (ctx, self->link);
+    if (link == NULL)
+        return;
+
+    path.measure = rsvg_node_path_measure_new (link);
+    path.affine = rsvg_node_get_state (link)->personal_affine;
+
+    rsvg_drawing_ctx_release_node (ctx, link);
+
+    if (path.measure == NULL)
+        return;
+
+    path.length = rsvg_path_measure_get_length (path.measure);
+    path.method = self->method;
+    path.spacing = self->spacing;
+
+    /* Percentages refer to the length of the path; other lengths are
+     * in terms of the path's pathLength, if it has one.
+     */
+    if (self->start_offset.unit == LENGTH_UNIT_PERCENT) {
+        distance = self->start_offset.length * path.length;
+    } else {
+        double author_length = rsvg_path_measure_get_author_length (path.measure);
+
+        distance = rsvg_length_normalize (&self->start_offset, ctx);
+        if (author_length > 0)
+            distance *= path.length / author_length;
+    }
+
+    if (rsvg_current_state (ctx)->text_anchor != TEXT_ANCHOR_START) {
+        gboolean lws = *lastwasspace;
+        GArray *saved = text_positions_save (positions);
+
+        _rsvg_node_text_length_children (node, ctx, positions, &length, &lws, FALSE);
+        text_positions_restore (positions, saved);
+        if (rsvg_current_state (ctx)->text_anchor == TEXT_ANCHOR_MIDDLE)
+            length /= 2;
+    }
+    distance -= length;
+
+    offset = 0;
+    _rsvg_node_text_type_children (node, ctx, positions, &distance, &offset, lastwasspace, FALSE, &path);
+
+    /* Text after the textPath continues where the text on the path ended */
+    if (rsvg_path_measure_get_point (path.measure, distance, &px, &py, &angle)) {
+        cairo_matrix_transform_point (&path.affine, &px, &py);
+        *x = px;
+        *y = py;
+    }
+
+    rsvg_path_measure_free (path.measure);
+}
+
+static void
+rsvg_node_text_path_free (gpointer impl)
+{
+    RsvgNodeTextPath *self = impl;
+
+    g_free (self->link);
+    g_free (self);
+}
+
+static void
+rsvg_node_text_path_set_atts (RsvgNode *node, gpointer impl, RsvgHandle *handle, RsvgPropertyBag *atts)
+{
+    RsvgNodeTextPath *text_path = impl;
+    const char *value;
+
+    if ((value = rsvg_property_bag_lookup (atts, "xlink:href"))
+        || (value = rsvg_property_bag_lookup (atts, "href"))) {
+        g_free (text_path->link);
+        text_path->link = g_strdup (value);
+    }
+
+    if ((value = rsvg_property_bag_lookup (atts, "startOffset")))
+        text_path->start_offset = rsvg_length_parse (value, LENGTH_DIR_HORIZONTAL);
+
+    if ((value = rsvg_property_bag_lookup (atts, "method"))) {
+        if (g_str_equal (value, "stretch"))
+            text_path->method = TEXT_PATH_METHOD_STRETCH;
+        else
+            text_path->method = TEXT_PATH_METHOD_ALIGN;
+    }
+
+    if ((value = rsvg_property_bag_lookup (atts, "spacing"))) {
+        if (g_str_equal (value, "auto"))
+            text_path->spacing = TEXT_PATH_SPACING_AUTO;
+        else
+            text_path->spacing = TEXT_PATH_SPACING_EXACT;
+    }
+}
+
+static void
+rsvg_node_text_path_draw (RsvgNode *node, gpointer impl, RsvgDrawingCtx *ctx, int dominate)
+{
+    /* nothing; textPath only gets rendered from within a text element */
+}
+
+RsvgNode *
+rsvg_new_text_path (const char *element_name, RsvgNode *parent)
+{
+    RsvgNodeTextPath *text_path;
+
+    text_path = g_new0 (RsvgNodeTextPath, 1);
+    text_path->link = NULL;
+    text_path->start_offset = rsvg_length_parse ("0", LENGTH_DIR_HORIZONTAL);
+    text_path->method = TEXT_PATH_METHOD_ALIGN;
+    text_path->spacing = TEXT_PATH_SPACING_EXACT;
+
+    return rsvg_rust_cnode_new (RSVG_NODE_TYPE_TEXT_PATH,
+                                parent,
+                                rsvg_state_new (),
+                                text_path,
+                                rsvg_node_text_path_set_atts,
+                                rsvg_node_text_path_draw,
+                                rsvg_node_text_path_free);
+}
+
 typedef struct _RsvgTextLayout RsvgTextLayout;
 
 struct _RsvgTextLayout {
@@ -612,13 +1205,14 @@ rsvg_text_create_layout (RsvgDrawingCtx * ctx, const char *text, PangoContext *
     if (state->unicode_bidi == UNICODE_BIDI_OVERRIDE || state->unicode_bidi == UNICODE_BIDI_EMBED)
         pango_context_set_base_dir (context, state->text_dir);
 
-    if (PANGO_GRAVITY_IS_VERTICAL (state->text_gravity))
-        pango_context_set_base_gravity (context, state->text_gravity);
-
     font_desc = pango_font_description_copy (pango_context_get_font_description (context));
 
-    if (state->font_family)
-        pango_font_description_set_family_static (font_desc, state->font_family);
+    if (state->font_family) {
+        char *family = rsvg_fonts_resolve_family (ctx->fonts, state->font_family);
+
+        pango_font_description_set_family (font_desc, family);
+        g_free (family);
+    }
 
     pango_font_description_set_style (font_desc, state->font_style);
     pango_font_description_set_variant (font_desc, state->font_variant);
@@ -690,15 +1284,268 @@ rsvg_text_layout_new (RsvgDrawingCtx * ctx, const char *text)
     return layout;
 }
 
+/* Gets the angle in degrees by which the glyph for @c gets rotated in
+ * vertical text.  Glyphs at 0 or 180 degrees are upright and advance by
+ * their vertical metrics; glyphs at 90 or 270 degrees are sideways and
+ * advance by their horizontal metrics.
+ */
+static int
+rsvg_text_get_vertical_orientation (RsvgState *state, gunichar c)
+{
+    if (state->glyph_orientation_vertical != GLYPH_ORIENTATION_AUTO)
+        return state->glyph_orientation_vertical;
+
+    /* Full-width characters, like CJK ideographs and kana, are upright */
+    return g_unichar_iswide (c) ? 0 : 90;
+}
+
+static void
+rsvg_text_render_glyph_outlines (RsvgDrawingCtx *ctx, RsvgPathBuilder *builder)
+{
+    RsvgState *state;
+    gint saved_fill_rule;
+
+    /* Glyph outlines are defined with the nonzero winding rule */
+    state = rsvg_current_state (ctx);
+    saved_fill_rule = state->fill_rule;
+    state->fill_rule = CAIRO_FILL_RULE_WINDING;
+
+    rsvg_render_path_builder (ctx, builder);
+
+    state->fill_rule = saved_fill_rule;
+}
+
+/* Renders @layout with its top-left corner at (@x, @y).  Pango places the
+ * glyphs wrongly when the affine stretches them along one axis only, so
+ * text that is @stretched gets rendered as a path instead.
+ */
+static void
+rsvg_text_render_layout (RsvgDrawingCtx *ctx, PangoLayout *layout, double x, double y,
+                         gboolean stretched)
+{
+    cairo_surface_t *surface;
+    cairo_t *cr;
+    cairo_path_t *outline;
+    RsvgPathBuilder *builder;
+
+    if (!stretched) {
+        ctx->render->render_pango_layout (ctx, layout, x, y);
+        return;
+    }
+
+    surface = cairo_image_surface_create (CAIRO_FORMAT_A8, 1, 1);
+    cr = cairo_create (surface);
+
+    cairo_move_to (cr, x, y);
+    pango_cairo_layout_path (cr, layout);
+    outline = cairo_copy_path (cr);
+
+    builder = rsvg_path_builder_new ();
+    rsvg_cairo_add_path_to_path_builder (outline, builder);
+
+    rsvg_text_render_glyph_outlines (ctx, builder);
+
+    rsvg_path_builder_destroy (builder);
+    cairo_path_destroy (outline);
+    cairo_destroy (cr);
+    cairo_surface_destroy (surface);
+}
+
+/* How far the first glyph in @layout advances when it is upright, scaled
+ * from the vertical metrics of its font.  Fonts without vertical metrics
+ * advance by one @em.
+ */
+static double
+rsvg_text_get_vertical_advance (PangoLayout *layout, double em)
+{
+    double advance = em;
+
+#ifdef HAVE_PANGOFT2
+    PangoLayoutIter *iter;
+    PangoLayoutRun *run;
+
+    iter = pango_layout_get_iter (layout);
+    run = pango_layout_iter_get_run_readonly (iter);
+
+    if (run != NULL && run->glyphs->num_glyphs > 0 && PANGO_IS_FC_FONT (run->item->analysis.font)) {
+        PangoFcFont *font = PANGO_FC_FONT (run->item->analysis.font);
+        PangoGlyph glyph = run->glyphs->glyphs[0].glyph;
+        FT_Face face;
+
+        face = pango_fc_font_lock_face (font);
+
+        /* Missing glyphs get drawn as boxes that the font knows nothing about */
+        if (face != NULL && FT_HAS_VERTICAL (face)
+            && (glyph & PANGO_GLYPH_UNKNOWN_FLAG) == 0
+            && FT_Load_Glyph (face, glyph, FT_LOAD_NO_SCALE | FT_LOAD_VERTICAL_LAYOUT) == 0)
+            advance = em * face->glyph->metrics.vertAdvance / face->units_per_EM;
+
+        pango_fc_font_unlock_face (font);
+    }
+
+    pango_layout_iter_free (iter);
+#endif
+
+    return advance;
+}
+
+/* Lays out @text from top to bottom, starting at (@x, @y), which is on
+ * the vertical line through the middle of the glyphs.  Upright glyphs get
+ * stacked one at a time, and advance by their font's vertical metrics, or
+ * by one em if it has none; runs of sideways glyphs get rotated as a
+ * whole so that they keep their kerning and ligatures.  The glyphs get
+ * rendered if @render is TRUE, as paths if @stretched is TRUE; either
+ * way, *@y gets advanced past the text.
+ */
+static void
+rsvg_text_layout_vertical (RsvgDrawingCtx *ctx, const char *text, gboolean render,
+                           gboolean stretched, gdouble *x, gdouble *y)
+{
+    PangoContext *context;
+    RsvgState *state;
+    cairo_matrix_t saved_affine;
+    double em, letter_spacing, shift;
+    const char *start, *p;
+
+    state = rsvg_current_state (ctx);
+
+    if (ctx->pango_context == NULL)
+        ctx->pango_context = ctx->render->create_pango_context (ctx);
+    context = render ? ctx->render->create_pango_context (ctx) : g_object_ref (ctx->pango_context);
+
+    em = rsvg_drawing_ctx_get_normalized_font_size (ctx);
+    letter_spacing = rsvg_length_normalize (&state->letter_spacing, ctx);
+    shift = _rsvg_css_accumulate_baseline_shift (state, ctx);
+    saved_affine = state->affine;
+
+    for (start = text; *start != '\0'; start = p) {
+        PangoLayout *layout;
+        PangoLayoutIter *iter;
+        PangoRectangle logical;
+        char *unit;
+        int angle;
+        double width, baseline, ascent, descent, central, advance;
+
+        angle = rsvg_text_get_vertical_orientation (state, g_utf8_get_char (start));
+
+        /* Marks stay with the character they apply to */
+        for (p = g_utf8_next_char (start); *p != '\0'; p = g_utf8_next_char (p)) {
+            gunichar c = g_utf8_get_char (p);
+
+            if (g_unichar_ismark (c))
+                continue;
+
+            if (angle % 180 == 0 || rsvg_text_get_vertical_orientation (state, c) != angle)
+                break;
+        }
+
+        unit = g_strndup (start, p - start);
+        layout = rsvg_text_create_layout (ctx, unit, context);
+        g_free (unit);
+
+        iter = pango_layout_get_iter (layout);
+        pango_layout_iter_get_line_extents (iter, NULL, &logical);
+        baseline = pango_layout_iter_get_baseline (iter) / (double) PANGO_SCALE;
+        pango_layout_iter_free (iter);
+
+        width = logical.width / (double) PANGO_SCALE;
+        ascent = baseline - logical.y / (double) PANGO_SCALE;
+        descent = (logical.y + logical.height) / (double) PANGO_SCALE - baseline;
+
+        /* Glyphs are centered on the central baseline, halfway between
+         * the ascent and the descent.
+         */
+        central = baseline - (ascent - descent) / 2;
+
+        if (angle % 180 == 0)
+            advance = rsvg_text_get_vertical_advance (layout, em) + letter_spacing;
+        else
+            advance = width;
+
+        if (render) {
+            /* The baseline shift moves glyphs to the right, which is "up"
+             * for sideways glyphs.
+             */
+            cairo_matrix_translate (&state->affine, *x + shift, *y + advance / 2);
+            cairo_matrix_rotate (&state->affine, angle * M_PI / 180.0);
+
+            rsvg_text_render_layout (ctx, layout, -width / 2, -central, stretched);
+
+            state->affine = saved_affine;
+        }
+
+        *y += advance;
+
+        g_object_unref (layout);
+    }
+
+    g_object_unref (context);
+}
+
+/* The SVG font for the current font-family, unless a family that comes
+ * before it in the list is one that Pango has.
+ */
+static RsvgNode *
+rsvg_text_lookup_svg_font (RsvgDrawingCtx *ctx)
+{
+    if (ctx->pango_context == NULL)
+        ctx->pango_context = ctx->render->create_pango_context (ctx);
+
+    return rsvg_fonts_lookup_svg_font (ctx->fonts,
+                                       pango_context_get_font_map (ctx->pango_context),
+                                       rsvg_current_state (ctx)->font_family);
+}
+
+/* Renders @text with the glyphs of the SVG font @font, as a path */
+static void
+rsvg_text_render_svg_font_text (RsvgDrawingCtx *ctx, RsvgNode *font, const char *text,
+                                gdouble *x, gdouble *y)
+{
+    RsvgState *state;
+    RsvgPathBuilder *builder;
+    cairo_matrix_t saved_affine;
+    gint saved_fill_rule;
+    double advance;
+
+    state = rsvg_current_state (ctx);
+    builder = rsvg_path_builder_new ();
+
+    advance = rsvg_node_font_layout_text (font, text,
+                                          rsvg_drawing_ctx_get_normalized_font_size (ctx),
+                                          rsvg_length_normalize (&state->letter_spacing, ctx),
+                                          builder);
+
+    saved_affine = state->affine;
+    cairo_matrix_translate (&state->affine, *x, *y - _rsvg_css_accumulate_baseline_shift (state, ctx));
+
+    /* Glyph outlines are defined with the nonzero winding rule */
+    saved_fill_rule = state->fill_rule;
+    state->fill_rule = CAIRO_FILL_RULE_WINDING;
+
+    rsvg_render_path_builder (ctx, builder);
+
+    state->fill_rule = saved_fill_rule;
+    state->affine = saved_affine;
+
+    *x += advance;
+
+    rsvg_path_builder_destroy (builder);
+}
+
+/* @stretched says whether the state's affine stretches the text along its
+ * direction, to fit a textLength.
+ */
 void
-rsvg_text_render_text (RsvgDrawingCtx * ctx, const char *text, gdouble * x, gdouble * y)
+rsvg_text_render_text (RsvgDrawingCtx * ctx, const char *text, gboolean stretched,
+                       gdouble * x, gdouble * y)
 {
     PangoContext *context;
     PangoLayout *layout;
     PangoLayoutIter *iter;
     RsvgState *state;
+    RsvgNode *svg_font;
     gint w, h;
-    double offset_x, offset_y, offset;
+    double offset;
 
     state = rsvg_current_state (ctx);
 
@@ -706,28 +1553,529 @@ rsvg_text_render_text (RsvgDrawingCtx * ctx, const char *text, gdouble * x, gdou
     if (state->font_size.length == 0)
         return;
 
+    if (PANGO_GRAVITY_IS_VERTICAL (state->text_gravity)) {
+        rsvg_text_layout_vertical (ctx, text, TRUE, stretched, x, y);
+        return;
+    }
+
+    svg_font = rsvg_text_lookup_svg_font (ctx);
+    if (svg_font != NULL) {
+        rsvg_text_render_svg_font_text (ctx, svg_font, text, x, y);
+        return;
+    }
+
     context = ctx->render->create_pango_context (ctx);
     layout = rsvg_text_create_layout (ctx, text, context);
     pango_layout_get_size (layout, &w, &h);
     iter = pango_layout_get_iter (layout);
     offset = pango_layout_iter_get_baseline (iter) / (double) PANGO_SCALE;
     offset += _rsvg_css_accumulate_baseline_shift (state, ctx);
-    if (PANGO_GRAVITY_IS_VERTICAL (state->text_gravity)) {
-        offset_x = -offset;
-        offset_y = 0;
-    } else {
-        offset_x = 0;
-        offset_y = offset;
-    }
     pango_layout_iter_free (iter);
-    ctx->render->render_pango_layout (ctx, layout, *x - offset_x, *y - offset_y);
+    rsvg_text_render_layout (ctx, layout, *x, *y - offset, stretched);
+    *x += w / (double)PANGO_SCALE;
+
+    g_object_unref (layout);
+    g_object_unref (context);
+}
+
+/* Renders a run of text rotated by @angle degrees about the current text
+ * position, and stretched by @scale along the text direction.  The rotation
+ * doesn't affect the advance.
+ */
+static void
+rsvg_text_render_run (RsvgDrawingCtx *ctx, const char *text, gsize len,
+                      double angle, double scale, gdouble *x, gdouble *y)
+{
+    RsvgState *state;
+    cairo_matrix_t saved_affine;
+    double advance_x = 0, advance_y = 0;
+    char *run;
+
+    if (len == 0)
+        return;
+
+    run = g_strndup (text, len);
+
+    if (angle == 0 && scale == 1.0) {
+        rsvg_text_render_text (ctx, run, FALSE, x, y);
+        g_free (run);
+        return;
+    }
+
+    state = rsvg_current_state (ctx);
+    saved_affine = state->affine;
+
+    cairo_matrix_translate (&state->affine, *x, *y);
+    cairo_matrix_rotate (&state->affine, angle * M_PI / 180.0);
     if (PANGO_GRAVITY_IS_VERTICAL (state->text_gravity))
-        *y += w / (double)PANGO_SCALE;
+        cairo_matrix_scale (&state->affine, 1.0, scale);
     else
-        *x += w / (double)PANGO_SCALE;
+        cairo_matrix_scale (&state->affine, scale, 1.0);
+
+    rsvg_text_render_text (ctx, run, scale != 1.0, &advance_x, &advance_y);
+
+    state->affine = saved_affine;
+
+    *x += advance_x * scale;
+    *y += advance_y * scale;
+
+    g_free (run);
+}
+
+/* Adds to @length the advance of @text up to the next character that gets
+ * an absolute position, and returns whether there is such a character.
+ */
+static gboolean
+rsvg_text_length_positioned_text (RsvgDrawingCtx *ctx, TextPositions *positions,
+                                  const char *text, gdouble *length)
+{
+    gboolean vertical = PANGO_GRAVITY_IS_VERTICAL (rsvg_current_state (ctx)->text_gravity);
+    double scale = text_positions_get_glyph_scale (positions);
+    const char *p;
+    char *chunk;
+
+    for (p = text; *p != '\0'; p = g_utf8_next_char (p)) {
+        CharPosition pos;
+
+        text_positions_next (positions, ctx, &pos);
+
+        if (pos.has_x || pos.has_y) {
+            text_positions_back (positions);
+            break;
+        }
+
+        *length += vertical ? pos.dy : pos.dx;
+        positions->offsets += vertical ? pos.dy : pos.dx;
+    }
+
+    chunk = g_strndup (text, p - text);
+    *length += rsvg_text_length_text_as_string (ctx, chunk) * scale;
+    g_free (chunk);
+
+    return *p != '\0';
+}
+
+/* Renders @text, splitting it into runs wherever a character gets its own
+ * position or rotation from the x, y, dx, dy and rotate lists.
+ */
+static void
+rsvg_text_render_positioned_text (RsvgDrawingCtx *ctx, TextPositions *positions,
+                                  const char *text, gdouble *x, gdouble *y)
+{
+    RsvgState *state = rsvg_current_state (ctx);
+    gboolean vertical = PANGO_GRAVITY_IS_VERTICAL (state->text_gravity);
+    double scale = text_positions_get_glyph_scale (positions);
+    const char *run = text;
+    const char *p;
+
+    for (p = text; *p != '\0'; p = g_utf8_next_char (p)) {
+        const char *next = g_utf8_next_char (p);
+        CharPosition pos;
+
+        text_positions_next (positions, ctx, &pos);
+
+        if (!pos.has_x && !pos.has_y && pos.dx == 0 && pos.dy == 0 && pos.rotate == 0)
+            continue;
+
+        rsvg_text_render_run (ctx, run, p - run, 0, scale, x, y);
+        run = p;
+
+        if (pos.has_x)
+            *x = pos.x;
+        if (pos.has_y)
+            *y = pos.y;
+
+        /* An absolute position starts a new chunk, which gets anchored on
+         * its own.  Only the rest of this string counts towards its length.
+         */
+        if ((pos.has_x || pos.has_y) && state->text_anchor != TEXT_ANCHOR_START) {
+            GArray *saved = text_positions_save (positions);
+            char *first = g_strndup (p, next - p);
+            double length;
+
+            length = rsvg_text_length_text_as_string (ctx, first) * scale;
+            g_free (first);
+
+            rsvg_text_length_positioned_text (ctx, positions, next, &length);
+            text_positions_restore (positions, saved);
+
+            if (state->text_anchor == TEXT_ANCHOR_MIDDLE)
+                length /= 2;
+
+            if (vertical)
+                *y -= length;
+            else
+                *x -= length;
+        }
+
+        *x += pos.dx;
+        *y += pos.dy;
+
+        if (pos.rotate != 0) {
+            rsvg_text_render_run (ctx, p, next - p, pos.rotate, scale, x, y);
+            run = next;
+        }
+    }
+
+    rsvg_text_render_run (ctx, run, p - run, 0, scale, x, y);
+}
+
+/* Default font scale for XHTML h1..h6, from the HTML user agent stylesheet */
+static const double heading_font_scale[] = { 1.0, 2.0, 1.5, 1.17, 1.0, 0.83, 0.67 };
+
+/* Lays out a paragraph of XHTML text from a <foreignObject>, wrapped to
+ * @width, with its top-left corner at (@x, @y).  Returns the height of the
+ * paragraph, so that the caller can stack paragraphs vertically.
+ */
+double
+rsvg_text_render_paragraph (RsvgDrawingCtx *ctx, const char *text, guint heading_level,
+                            double x, double y, double width)
+{
+    PangoContext *context;
+    PangoLayout *layout;
+    PangoFontDescription *font_desc;
+    RsvgState *state;
+    gint h;
+
+    g_assert (heading_level < G_N_ELEMENTS (heading_font_scale));
+
+    state = rsvg_current_state (ctx);
+
+    if (state->font_size.length == 0)
+        return 0.0;
+
+    context = ctx->render->create_pango_context (ctx);
+    layout = rsvg_text_create_layout (ctx, text, context);
+
+    if (heading_level > 0) {
+        font_desc = pango_font_description_copy (pango_layout_get_font_description (layout));
+        pango_font_description_set_size (font_desc,
+                                         pango_font_description_get_size (font_desc)
+                                         * heading_font_scale[heading_level]);
+        pango_font_description_set_weight (font_desc, PANGO_WEIGHT_BOLD);
+        pango_layout_set_font_description (layout, font_desc);
+        pango_font_description_free (font_desc);
+    }
+
+    pango_layout_set_width (layout, width * PANGO_SCALE);
+    pango_layout_set_wrap (layout, PANGO_WRAP_WORD_CHAR);
+    pango_layout_get_size (layout, NULL, &h);
+
+    ctx->render->render_pango_layout (ctx, layout, x, y);
 
     g_object_unref (layout);
     g_object_unref (context);
+
+    return h / (double) PANGO_SCALE;
+}
+
+typedef struct {
+    int start;              /* byte index of the cluster's text */
+    double x;               /* position within the layout */
+    double width;
+    PangoGlyphItem *run;    /* owned by the layout */
+    int first_glyph;
+    int n_glyphs;
+} TextCluster;
+
+/* Rate of change of the path's angle around @distance */
+static double
+text_path_get_curvature (TextPath *path, double distance, double delta)
+{
+    double x, y, angle1, angle2, diff;
+
+    if (delta <= 0)
+        return 0;
+
+    if (!rsvg_path_measure_get_point (path->measure, distance - delta, &x, &y, &angle1)
+        || !rsvg_path_measure_get_point (path->measure, distance + delta, &x, &y, &angle2))
+        return 0;
+
+    diff = angle2 - angle1;
+    while (diff > M_PI)
+        diff -= 2 * M_PI;
+    while (diff < -M_PI)
+        diff += 2 * M_PI;
+
+    return diff / (2 * delta);
+}
+
+/* Maps a point in the coordinate system of a layout whose left edge
+ * is at distance @start along the path, and whose top is @offset_y
+ * away from the path, to user space.
+ */
+static void
+text_path_warp_point (TextPath *path, double start, double offset_y, double *x, double *y)
+{
+    double px, py, angle, v;
+
+    if (!rsvg_path_measure_get_point (path->measure, start + *x, &px, &py, &angle))
+        return;
+
+    v = *y + offset_y;
+
+    *x = px - v * sin (angle);
+    *y = py + v * cos (angle);
+
+    cairo_matrix_transform_point (&path->affine, x, y);
+}
+
+static void
+text_path_warp_line_to (TextPath *path, RsvgPathBuilder *builder,
+                        double start, double offset_y, double max_step,
+                        double x0, double y0, double x1, double y1)
+{
+    int i, n_steps;
+
+    /* Straight lines in the glyphs become curves along the path, so
+     * subdivide them before warping.
+     */
+    n_steps = (int) ceil (hypot (x1 - x0, y1 - y0) / max_step);
+    if (n_steps < 1)
+        n_steps = 1;
+
+    for (i = 1; i <= n_steps; i++) {
+        double x = x0 + (x1 - x0) * i / n_steps;
+        double y = y0 + (y1 - y0) * i / n_steps;
+
+        text_path_warp_point (path, start, offset_y, &x, &y);
+        rsvg_path_builder_line_to (builder, x, y);
+    }
+}
+
+/* Outline of the glyphs of a cluster, with the origin of the first
+ * glyph at (@x, @y).  The glyphs come from the layout of the whole
+ * text, so they keep the kerning and ligatures across clusters.
+ */
+static cairo_path_t *
+rsvg_text_cluster_get_outline (TextCluster *cluster, double x, double y, gboolean flat)
+{
+    cairo_surface_t *surface;
+    cairo_t *cr;
+    cairo_path_t *outline;
+    PangoGlyphString glyphs;
+
+    glyphs = *cluster->run->glyphs;
+    glyphs.num_glyphs = cluster->n_glyphs;
+    glyphs.glyphs += cluster->first_glyph;
+    glyphs.log_clusters += cluster->first_glyph;
+
+    surface = cairo_image_surface_create (CAIRO_FORMAT_A8, 1, 1);
+    cr = cairo_create (surface);
+
+    cairo_move_to (cr, x, y);
+    pango_cairo_glyph_string_path (cr, cluster->run->item->analysis.font, &glyphs);
+
+    if (flat)
+        outline = cairo_copy_path_flat (cr);
+    else
+        outline = cairo_copy_path (cr);
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (surface);
+
+    return outline;
+}
+
+/* For method="stretch": the outlines of the glyphs get bent to follow the path */
+static void
+rsvg_text_render_cluster_stretched (RsvgDrawingCtx *ctx, TextPath *path, TextCluster *cluster,
+                                    double start, double offset_y, double baseline)
+{
+    cairo_path_t *outline;
+    RsvgPathBuilder *builder;
+    double max_step;
+    double last_x = 0, last_y = 0, subpath_x = 0, subpath_y = 0;
+    int i;
+
+    outline = rsvg_text_cluster_get_outline (cluster, 0, baseline, TRUE);
+
+    max_step = rsvg_drawing_ctx_get_normalized_font_size (ctx) / 16;
+    if (max_step <= 0)
+        max_step = 1;
+
+    builder = rsvg_path_builder_new ();
+
+    for (i = 0; i < outline->num_data; i += outline->data[i].header.length) {
+        cairo_path_data_t *data = &outline->data[i];
+        double x, y;
+
+        switch (data->header.type) {
+        case CAIRO_PATH_MOVE_TO:
+            last_x = subpath_x = x = data[1].point.x;
+            last_y = subpath_y = y = data[1].point.y;
+            text_path_warp_point (path, start, offset_y, &x, &y);
+            rsvg_path_builder_move_to (builder, x, y);
+            break;
+
+        case CAIRO_PATH_LINE_TO:
+            text_path_warp_line_to (path, builder, start, offset_y, max_step,
+                                    last_x, last_y, data[1].point.x, data[1].point.y);
+            last_x = data[1].point.x;
+            last_y = data[1].point.y;
+            break;
+
+        case CAIRO_PATH_CLOSE_PATH:
+            text_path_warp_line_to (path, builder, start, offset_y, max_step,
+                                    last_x, last_y, subpath_x, subpath_y);
+            rsvg_path_builder_close_path (builder);
+            last_x = subpath_x;
+            last_y = subpath_y;
+            break;
+
+        case CAIRO_PATH_CURVE_TO:
+        default:
+            /* cairo_copy_path_flat() doesn't return curves */
+            break;
+        }
+    }
+
+    rsvg_text_render_glyph_outlines (ctx, builder);
+
+    rsvg_path_builder_destroy (builder);
+    cairo_path_destroy (outline);
+}
+
+/* For method="align": each cluster is rendered upright with respect to the
+ * path's direction at the cluster's midpoint.
+ */
+static void
+rsvg_text_render_cluster_aligned (RsvgDrawingCtx *ctx, TextPath *path, TextCluster *cluster,
+                                  double middle, double offset_y, double baseline)
+{
+    RsvgState *state;
+    cairo_matrix_t affine, saved_affine;
+    cairo_path_t *outline;
+    RsvgPathBuilder *builder;
+    double px, py, angle;
+
+    if (!rsvg_path_measure_get_point (path->measure, middle, &px, &py, &angle))
+        return;
+
+    outline = rsvg_text_cluster_get_outline (cluster, -cluster->width / 2, offset_y + baseline, FALSE);
+    builder = rsvg_path_builder_new ();
+    rsvg_cairo_add_path_to_path_builder (outline, builder);
+
+    state = rsvg_current_state (ctx);
+    saved_affine = state->affine;
+
+    cairo_matrix_init_translate (&affine, px, py);
+    cairo_matrix_rotate (&affine, angle);
+    cairo_matrix_multiply (&affine, &affine, &path->affine);
+    cairo_matrix_multiply (&state->affine, &affine, &saved_affine);
+
+    rsvg_text_render_glyph_outlines (ctx, builder);
+
+    state->affine = saved_affine;
+
+    rsvg_path_builder_destroy (builder);
+    cairo_path_destroy (outline);
+}
+
+static void
+rsvg_text_render_text_on_path (RsvgDrawingCtx * ctx, TextPath * path,
+                               const char *text, gdouble * x, gdouble * y)
+{
+    PangoContext *context;
+    PangoLayout *layout;
+    PangoLayoutIter *iter;
+    RsvgState *state;
+    GArray *clusters;
+    gint w, text_len;
+    guint i;
+    double baseline, offset, shift;
+
+    state = rsvg_current_state (ctx);
+
+    /* Do not render the text if the font size is zero. See bug #581491. */
+    if (state->font_size.length == 0)
+        return;
+
+    context = ctx->render->create_pango_context (ctx);
+    layout = rsvg_text_create_layout (ctx, text, context);
+    pango_layout_get_size (layout, &w, NULL);
+    text_len = strlen (text);
+
+    iter = pango_layout_get_iter (layout);
+    baseline = pango_layout_iter_get_baseline (iter) / (double) PANGO_SCALE;
+    offset = baseline + _rsvg_css_accumulate_baseline_shift (state, ctx);
+
+    clusters = g_array_new (FALSE, FALSE, sizeof (TextCluster));
+
+    do {
+        PangoRectangle logical;
+        TextCluster cluster;
+        int g;
+
+        pango_layout_iter_get_cluster_extents (iter, NULL, &logical);
+
+        cluster.start = pango_layout_iter_get_index (iter);
+        cluster.x = logical.x / (double) PANGO_SCALE;
+        cluster.width = logical.width / (double) PANGO_SCALE;
+        cluster.run = pango_layout_iter_get_run_readonly (iter);
+        cluster.first_glyph = 0;
+        cluster.n_glyphs = 0;
+
+        /* The end of the line has no run */
+        if (cluster.run == NULL)
+            continue;
+
+        /* The glyphs of a cluster are contiguous in the run */
+        for (g = 0; g < cluster.run->glyphs->num_glyphs; g++) {
+            if (cluster.run->item->offset + cluster.run->glyphs->log_clusters[g] == cluster.start) {
+                if (cluster.n_glyphs == 0)
+                    cluster.first_glyph = g;
+                cluster.n_glyphs++;
+            }
+        }
+
+        if (cluster.start < text_len && cluster.width > 0 && cluster.n_glyphs > 0)
+            g_array_append_val (clusters, cluster);
+    } while (pango_layout_iter_next_cluster (iter));
+
+    pango_layout_iter_free (iter);
+
+    shift = 0;
+
+    /* Clusters come in visual order */
+    for (i = 0; i < clusters->len; i++) {
+        TextCluster *cluster = &g_array_index (clusters, TextCluster, i);
+        double start, advance, middle;
+
+        start = *x + cluster->x + shift;
+        advance = cluster->width;
+
+        /* With spacing="auto", compensate for the way glyphs spread apart
+         * on the outside of curves and bunch together on the inside, so
+         * that the spacing looks even halfway up the glyphs.
+         */
+        if (path->spacing == TEXT_PATH_SPACING_AUTO) {
+            double curvature = text_path_get_curvature (path, start + advance / 2, advance / 2);
+            double factor = CLAMP (1.0 + curvature * baseline / 2, 0.5, 2.0);
+
+            shift += advance / factor - advance;
+            advance /= factor;
+        }
+
+        middle = start + advance / 2;
+
+        /* Glyphs whose midpoint is off the path don't get rendered */
+        if (path->length <= 0 || middle < 0 || middle > path->length)
+            continue;
+
+        if (path->method == TEXT_PATH_METHOD_STRETCH)
+            rsvg_text_render_cluster_stretched (ctx, path, cluster,
+                                                middle - cluster->width / 2, *y - offset, baseline);
+        else
+            rsvg_text_render_cluster_aligned (ctx, path, cluster,
+                                              middle, *y - offset, baseline);
+    }
+
+    *x += w / (double) PANGO_SCALE + shift;
+
+    g_array_free (clusters, TRUE);
+    g_object_unref (layout);
+    g_object_unref (context);
 }
 
 static gdouble
@@ -743,9 +2091,26 @@ rsvg_text_layout_width (RsvgTextLayout * layout)
 static gdouble
 rsvg_text_length_text_as_string (RsvgDrawingCtx * ctx, const char *text)
 {
+    RsvgState *state = rsvg_current_state (ctx);
     RsvgTextLayout *layout;
+    RsvgNode *svg_font;
     gdouble x;
 
+    if (PANGO_GRAVITY_IS_VERTICAL (state->text_gravity)) {
+        gdouble y = 0;
+
+        x = 0;
+        rsvg_text_layout_vertical (ctx, text, FALSE, FALSE, &x, &y);
+        return y;
+    }
+
+    svg_font = rsvg_text_lookup_svg_font (ctx);
+    if (svg_font != NULL)
+        return rsvg_node_font_layout_text (svg_font, text,
+                                           rsvg_drawing_ctx_get_normalized_font_size (ctx),
+                                           rsvg_length_normalize (&state->letter_spacing, ctx),
+                                           NULL);
+
     layout = rsvg_text_layout_new (ctx, text);
     layout->x = layout->y = 0;
 
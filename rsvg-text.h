@@ -39,6 +39,11 @@ RsvgNode    *rsvg_new_tspan	    (const char *element_name, RsvgNode *parent);
 G_GNUC_INTERNAL
 RsvgNode    *rsvg_new_tref	    (const char *element_name, RsvgNode *parent);
 G_GNUC_INTERNAL
+RsvgNode    *rsvg_new_text_path     (const char *element_name, RsvgNode *parent);
+G_GNUC_INTERNAL
+double       rsvg_text_render_paragraph (RsvgDrawingCtx *ctx, const char *text, guint heading_level,
+                                         double x, double y, double width);
+G_GNUC_INTERNAL
 char	    *rsvg_make_valid_utf8   (const char *str, int len);
 
 G_END_DECLS
@@ -55,11 +55,15 @@ GType rsvg_handle_get_type (void);
 /**
  * RsvgError:
  * @RSVG_ERROR_FAILED: the request failed
+ * @RSVG_ERROR_INVALID_ATTRIBUTE: an element or one of its attributes has an
+ *   invalid value; only returned when loading with %RSVG_HANDLE_FLAG_STRICT.
+ *   Since: 2.42
  *
  * An enumeration representing possible errors
  */
 typedef enum {
-    RSVG_ERROR_FAILED
+    RSVG_ERROR_FAILED,
+    RSVG_ERROR_INVALID_ATTRIBUTE
 } RsvgError;
 
 #define RSVG_ERROR (rsvg_error_quark ())
@@ -133,6 +137,8 @@ void rsvg_set_default_dpi_x_y	(double dpi_x, double dpi_y);
 void rsvg_handle_set_dpi	(RsvgHandle * handle, double dpi);
 void rsvg_handle_set_dpi_x_y	(RsvgHandle * handle, double dpi_x, double dpi_y);
 
+void rsvg_handle_set_time	(RsvgHandle * handle, double time);
+
 RsvgHandle  *rsvg_handle_new		(void);
 gboolean     rsvg_handle_write		(RsvgHandle * handle, const guchar * buf, 
                                      gsize count, GError ** error);
@@ -150,6 +156,10 @@ gboolean rsvg_handle_get_position_sub (RsvgHandle * handle, RsvgPositionData * p
 
 gboolean rsvg_handle_has_sub (RsvgHandle * handle, const char *id);
 
+char *rsvg_handle_get_diagnostics (RsvgHandle * handle);
+
+char *rsvg_handle_get_svg_with_text_as_paths (RsvgHandle * handle);
+
 /* GIO APIs */
 
 /**
@@ -162,12 +172,17 @@ gboolean rsvg_handle_has_sub (RsvgHandle * handle, const char *id);
  *  for use by cairo when painting to e.g. a PDF surface. This will make the
  *  resulting PDF file smaller and faster.
  *  Since: 2.40.3
+ * @RSVG_HANDLE_FLAG_STRICT: Fail to load documents where an element or
+ *  attribute has an invalid value, with %RSVG_ERROR_INVALID_ATTRIBUTE, instead
+ *  of loading them and not rendering the invalid elements.
+ *  Since: 2.42
  */
 typedef enum /*< flags >*/ 
 {
     RSVG_HANDLE_FLAGS_NONE           = 0,
     RSVG_HANDLE_FLAG_UNLIMITED       = 1 << 0,
-    RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA = 1 << 1
+    RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA = 1 << 1,
+    RSVG_HANDLE_FLAG_STRICT          = 1 << 2
 } RsvgHandleFlags;
 
 RsvgHandle *rsvg_handle_new_with_flags (RsvgHandleFlags flags);
@@ -0,0 +1,1413 @@
+use ::libc;
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::f64;
+use std::fmt;
+use std::ptr;
+use std::str::FromStr;
+
+use color::*;
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle;
+use handle::RsvgHandle;
+use node::*;
+use parsers;
+use parsers::Parse;
+use parsers::ParseError;
+use path_builder::*;
+use path_geometry::PathMeasure;
+use path_parser;
+use properties::is_presentation_attribute;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+use shapes;
+
+// Declarative animation with the SMIL elements of SVG 1.1:
+// https://www.w3.org/TR/SVG11/animate.html
+//
+// We don't have a running clock; rsvg_handle_set_time() asks each
+// animation for the values of its target's attributes at a certain
+// document time, and rsvg-base.c re-applies the target's attributes
+// with those values.  Animated values are plain strings, so they get
+// parsed by the target element like any other attribute.
+//
+// Only offset values are supported for begin/end; syncbase, event,
+// repeat, accessKey and wallclock values never resolve, as if they
+// were "indefinite".
+
+/***** Clock values *****/
+
+// Clock-val, in seconds
+// https://www.w3.org/TR/SMIL/smil-timing.html#Timing-ClockValueSyntax
+fn parse_clock_value (s: &str) -> Result<f64, ParseError> {
+    let s = s.trim ();
+
+    if s.contains (':') {
+        let parts: Vec<&str> = s.split (':').collect ();
+
+        if parts.len () > 3 {
+            return Err (ParseError::new ("invalid clock value"));
+        }
+
+        let last = parts.len () - 1;
+        let mut seconds = 0.0;
+
+        for (i, part) in parts.iter ().enumerate () {
+            let value = decimal (part)?;
+
+            // Only the seconds can have a fraction, and minutes/seconds are two digits
+            if (i != last && part.contains ('.'))
+                || (i != 0 && (value >= 60.0 || part.find ('.').unwrap_or (part.len ()) != 2)) {
+                return Err (ParseError::new ("invalid clock value"));
+            }
+
+            seconds = seconds * 60.0 + value;
+        }
+
+        Ok (seconds)
+    } else {
+        let (number, scale) = if s.ends_with ("ms") {
+            (&s[..s.len () - 2], 0.001)
+        } else if s.ends_with ("min") {
+            (&s[..s.len () - 3], 60.0)
+        } else if s.ends_with ("h") {
+            (&s[..s.len () - 1], 3600.0)
+        } else if s.ends_with ("s") {
+            (&s[..s.len () - 1], 1.0)
+        } else {
+            (s, 1.0)
+        };
+
+        Ok (decimal (number)? * scale)
+    }
+}
+
+// DIGIT+ ("." DIGIT+)?
+fn decimal (s: &str) -> Result<f64, ParseError> {
+    let valid = !s.is_empty ()
+        && !s.starts_with ('.')
+        && !s.ends_with ('.')
+        && s.matches ('.').count () <= 1
+        && s.chars ().all (|c| c.is_ascii_digit () || c == '.');
+
+    if valid {
+        f64::from_str (s).map_err (|_| ParseError::new ("invalid number"))
+    } else {
+        Err (ParseError::new ("invalid number"))
+    }
+}
+
+// Offset-value ::= ( S? "+" | "-" S? )? ( Clock-value )
+fn parse_offset_value (s: &str) -> Result<f64, ParseError> {
+    let s = s.trim ();
+
+    if s.starts_with ('+') {
+        parse_clock_value (&s[1..])
+    } else if s.starts_with ('-') {
+        parse_clock_value (&s[1..]).map (|v| -v)
+    } else {
+        parse_clock_value (s)
+    }
+}
+
+// Sorted list of the resolved times in a begin or end attribute
+fn parse_time_list (s: &str) -> Vec<f64> {
+    let mut times: Vec<f64> = s.split (';')
+        .filter_map (|v| parse_offset_value (v).ok ())
+        .collect ();
+
+    times.sort_by (|a, b| a.partial_cmp (b).unwrap ());
+    times
+}
+
+/***** Timing *****/
+
+// Indefinite durations are represented as f64::INFINITY
+#[derive(Debug, Clone, PartialEq)]
+struct Timing {
+    begin:        Vec<f64>,
+    end:          Vec<f64>,
+    dur:          f64,
+    repeat_count: Option<f64>,
+    repeat_dur:   Option<f64>,
+    freeze:       bool
+}
+
+impl Default for Timing {
+    fn default () -> Timing {
+        Timing {
+            begin:        vec! [0.0],
+            end:          Vec::new (),
+            dur:          f64::INFINITY,
+            repeat_count: None,
+            repeat_dur:   None,
+            freeze:       false
+        }
+    }
+}
+
+impl Timing {
+    // https://www.w3.org/TR/SMIL/smil-timing.html#Timing-ComputingActiveDur
+    fn active_duration (&self) -> f64 {
+        let iterated = self.repeat_count.map (|count| self.dur * count);
+
+        match (iterated, self.repeat_dur) {
+            (None, None)       => self.dur,
+            (Some (i), None)   => i,
+            (None, Some (r))   => r,
+            (Some (i), Some (r)) => i.min (r)
+        }
+    }
+
+    // Returns the fraction of the simple duration and the iteration
+    // number at the specified document time, or None if the animation
+    // has no effect at that time.
+    fn sample (&self, time: f64) -> Option<(f64, f64)> {
+        let begin = *self.begin.iter ().rev ().find (|&&b| b <= time)?;
+
+        let mut end = begin + self.active_duration ();
+
+        if let Some (e) = self.end.iter ().find (|&&e| e >= begin) {
+            end = end.min (*e);
+        }
+
+        let (elapsed, frozen) = if time < end {
+            (time - begin, false)
+        } else if self.freeze {
+            (end - begin, true)
+        } else {
+            return None;
+        };
+
+        if self.dur.is_infinite () {
+            return Some ((0.0, 0.0));
+        }
+
+        let mut iteration = (elapsed / self.dur).floor ();
+        let mut fraction = elapsed / self.dur - iteration;
+
+        // Frozen at the end of an iteration means the end of that
+        // iteration, not the start of the next one.
+        if frozen && fraction == 0.0 && iteration > 0.0 {
+            iteration -= 1.0;
+            fraction = 1.0;
+        }
+
+        Some ((fraction, iteration))
+    }
+}
+
+/***** Animated values *****/
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number (f64, String),       // with an optional unit suffix
+    List (Vec<f64>),
+    Color ([f64; 4]),           // RGBA, 0.0 to 255.0
+    Other (String)
+}
+
+impl Value {
+    fn parse (s: &str) -> Value {
+        let s = s.trim ();
+
+        if let Some ((number, unit)) = parse_number_with_unit (s) {
+            Value::Number (number, unit)
+        } else if let Ok (Color::RGBA (rgba)) = Color::parse (s, (AllowInherit::No, AllowCurrentColor::No)) {
+            Value::Color ([rgba.red as f64, rgba.green as f64, rgba.blue as f64, rgba.alpha as f64])
+        } else if let Some (list) = parse_number_list (s) {
+            Value::List (list)
+        } else {
+            Value::Other (s.to_string ())
+        }
+    }
+
+    fn combine<F> (&self, other: &Value, f: F) -> Option<Value>
+        where F: Fn (f64, f64) -> f64
+    {
+        match (self, other) {
+            (&Value::Number (a, ref unit_a), &Value::Number (b, ref unit_b)) if unit_a == unit_b =>
+                Some (Value::Number (f (a, b), unit_a.clone ())),
+
+            (&Value::List (ref a), &Value::List (ref b)) if a.len () == b.len () =>
+                Some (Value::List (a.iter ().zip (b).map (|(a, b)| f (*a, *b)).collect ())),
+
+            (&Value::Color (ref a), &Value::Color (ref b)) => {
+                let mut c = [0.0; 4];
+
+                for i in 0..4 {
+                    c[i] = f (a[i], b[i]).max (0.0).min (255.0);
+                }
+
+                Some (Value::Color (c))
+            },
+
+            _ => None
+        }
+    }
+
+    fn interpolate (&self, to: &Value, t: f64) -> Option<Value> {
+        self.combine (to, |a, b| a + (b - a) * t)
+    }
+
+    fn add (&self, other: &Value) -> Option<Value> {
+        self.combine (other, |a, b| a + b)
+    }
+
+    fn scale (&self, factor: f64) -> Option<Value> {
+        self.combine (self, |a, _| a * factor)
+    }
+
+    // Used for calcMode="paced"
+    fn distance (&self, other: &Value) -> Option<f64> {
+        match (self, other) {
+            (&Value::Number (a, ref unit_a), &Value::Number (b, ref unit_b)) if unit_a == unit_b =>
+                Some ((b - a).abs ()),
+
+            (&Value::List (ref a), &Value::List (ref b)) if a.len () == b.len () =>
+                Some (a.iter ().zip (b).map (|(a, b)| (b - a) * (b - a)).sum::<f64> ().sqrt ()),
+
+            (&Value::Color (ref a), &Value::Color (ref b)) =>
+                Some (a[..3].iter ().zip (&b[..3]).map (|(a, b)| (b - a) * (b - a)).sum::<f64> ().sqrt ()),
+
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt (&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Number (n, ref unit) => write! (f, "{}{}", n, unit),
+
+            Value::List (ref v) => {
+                let strings: Vec<String> = v.iter ().map (|n| n.to_string ()).collect ();
+                write! (f, "{}", strings.join (" "))
+            },
+
+            Value::Color (c) => {
+                let channel = |v: f64| v.round () as u8;
+
+                if channel (c[3]) == 255 {
+                    write! (f, "#{:02x}{:02x}{:02x}", channel (c[0]), channel (c[1]), channel (c[2]))
+                } else {
+                    write! (f, "rgba({}, {}, {}, {})",
+                            channel (c[0]), channel (c[1]), channel (c[2]), c[3] / 255.0)
+                }
+            },
+
+            Value::Other (ref s) => write! (f, "{}", s)
+        }
+    }
+}
+
+// number followed by an optional unit, like "10", "1.5em" or "50%"
+fn parse_number_with_unit (s: &str) -> Option<(f64, String)> {
+    let bytes = s.as_bytes ();
+    let mut i = 0;
+
+    if i < bytes.len () && (bytes[i] == b'+' || bytes[i] == b'-') {
+        i += 1;
+    }
+
+    let digits_start = i;
+
+    while i < bytes.len () && (bytes[i].is_ascii_digit () || bytes[i] == b'.') {
+        i += 1;
+    }
+
+    if i == digits_start {
+        return None;
+    }
+
+    // An exponent, but not the start of "em" or "ex"
+    if i < bytes.len () && (bytes[i] == b'e' || bytes[i] == b'E') {
+        let mut j = i + 1;
+
+        if j < bytes.len () && (bytes[j] == b'+' || bytes[j] == b'-') {
+            j += 1;
+        }
+
+        if j < bytes.len () && bytes[j].is_ascii_digit () {
+            while j < bytes.len () && bytes[j].is_ascii_digit () {
+                j += 1;
+            }
+
+            i = j;
+        }
+    }
+
+    let number = f64::from_str (&s[..i]).ok ()?;
+    let unit = &s[i..];
+
+    if unit == "%" || unit.chars ().all (|c| c.is_ascii_alphabetic ()) {
+        Some ((number, unit.to_string ()))
+    } else {
+        None
+    }
+}
+
+// numbers separated by whitespace and/or commas
+fn parse_number_list (s: &str) -> Option<Vec<f64>> {
+    let list: Vec<f64> = s.split (|c: char| c == ',' || c.is_whitespace ())
+        .filter (|v| !v.is_empty ())
+        .map (f64::from_str)
+        .collect::<Result<Vec<f64>, _>> ()
+        .ok ()?;
+
+    if !list.is_empty () && list.iter ().all (|v| v.is_finite ()) {
+        Some (list)
+    } else {
+        None
+    }
+}
+
+// semicolon-separated list, as for the values and keyTimes attributes
+fn split_semicolons (s: &str) -> Vec<&str> {
+    s.split (';')
+        .map (|v| v.trim ())
+        .filter (|v| !v.is_empty ())
+        .collect ()
+}
+
+/***** Interpolation *****/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalcMode {
+    Discrete,
+    Linear,
+    Paced,
+    Spline
+}
+
+impl Parse for CalcMode {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<CalcMode, AttributeError> {
+        match s.trim () {
+            "discrete" => Ok (CalcMode::Discrete),
+            "linear"   => Ok (CalcMode::Linear),
+            "paced"    => Ok (CalcMode::Paced),
+            "spline"   => Ok (CalcMode::Spline),
+            _          => Err (AttributeError::Parse (ParseError::new ("invalid calcMode")))
+        }
+    }
+}
+
+// Control points of a cubic Bézier easing function from (0, 0) to (1, 1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct KeySpline (f64, f64, f64, f64);
+
+impl KeySpline {
+    fn parse (s: &str) -> Result<KeySpline, ParseError> {
+        match parse_number_list (s) {
+            Some (ref v) if v.len () == 4 && v.iter ().all (|&c| c >= 0.0 && c <= 1.0) =>
+                Ok (KeySpline (v[0], v[1], v[2], v[3])),
+
+            _ => Err (ParseError::new ("expected four numbers between 0 and 1"))
+        }
+    }
+
+    fn ease (&self, x: f64) -> f64 {
+        let KeySpline (x1, y1, x2, y2) = *self;
+
+        let bezier = |p1: f64, p2: f64, t: f64| {
+            let u = 1.0 - t;
+            3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+        };
+
+        // x(t) is monotonic since the control points are within [0, 1]
+        let mut lo = 0.0;
+        let mut hi = 1.0;
+
+        for _ in 0..50 {
+            let mid = (lo + hi) / 2.0;
+
+            if bezier (x1, x2, mid) < x {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        bezier (y1, y2, (lo + hi) / 2.0)
+    }
+}
+
+// Computes the value of the animation function at a fraction of the simple duration
+// https://www.w3.org/TR/SMIL/smil-animation.html#animationNS-InterpolationAndIndefSimpleDur
+fn interpolate_values (values:      &[Value],
+                       calc_mode:   CalcMode,
+                       key_times:   &[f64],
+                       key_splines: &[KeySpline],
+                       fraction:    f64) -> Value {
+    assert! (!values.is_empty ());
+
+    let n = values.len ();
+
+    // Values that can't be interpolated, like keywords, switch discretely
+    let interpolable = values.windows (2).all (|w| w[0].interpolate (&w[1], 0.0).is_some ());
+
+    let calc_mode = if interpolable { calc_mode } else { CalcMode::Discrete };
+
+    if calc_mode == CalcMode::Discrete {
+        let index = if key_times.len () == n {
+            key_times.iter ().rposition (|&t| t <= fraction).unwrap_or (0)
+        } else {
+            ((fraction * n as f64).floor () as usize).min (n - 1)
+        };
+
+        return values[index].clone ();
+    }
+
+    if n == 1 {
+        return values[0].clone ();
+    }
+
+    if calc_mode == CalcMode::Paced {
+        let distances: Option<Vec<f64>> = values.windows (2).map (|w| w[0].distance (&w[1])).collect ();
+
+        if let Some (distances) = distances {
+            let total: f64 = distances.iter ().sum ();
+
+            if total <= 0.0 {
+                return values[0].clone ();
+            }
+
+            let mut remaining = fraction * total;
+
+            for (i, d) in distances.iter ().enumerate () {
+                if remaining <= *d || i == distances.len () - 1 {
+                    let t = if *d > 0.0 { (remaining / d).min (1.0) } else { 1.0 };
+                    return values[i].interpolate (&values[i + 1], t).unwrap ();
+                }
+
+                remaining -= *d;
+            }
+        }
+    }
+
+    let times: Vec<f64> = if key_times.len () == n {
+        key_times.to_vec ()
+    } else {
+        (0..n).map (|i| i as f64 / (n - 1) as f64).collect ()
+    };
+
+    let i = times.iter ().rposition (|&t| t <= fraction).unwrap_or (0).min (n - 2);
+    let span = times[i + 1] - times[i];
+
+    let mut t = if span > 0.0 { ((fraction - times[i]) / span).min (1.0) } else { 1.0 };
+
+    if calc_mode == CalcMode::Spline && i < key_splines.len () {
+        t = key_splines[i].ease (t);
+    }
+
+    values[i].interpolate (&values[i + 1], t).unwrap ()
+}
+
+/***** NodeAnimation *****/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AnimationKind {
+    Animate,
+    AnimateColor,
+    AnimateMotion,
+    AnimateTransform,
+    Set
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttributeType {
+    Auto,
+    Css,
+    Xml
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransformType {
+    Translate,
+    Scale,
+    Rotate,
+    SkewX,
+    SkewY
+}
+
+impl TransformType {
+    fn name (&self) -> &'static str {
+        match *self {
+            TransformType::Translate => "translate",
+            TransformType::Scale     => "scale",
+            TransformType::Rotate    => "rotate",
+            TransformType::SkewX     => "skewX",
+            TransformType::SkewY     => "skewY"
+        }
+    }
+
+    // Fills in the optional parameters, so that values can be interpolated
+    fn normalize (&self, params: &[f64]) -> Option<Vec<f64>> {
+        match (*self, params.len ()) {
+            (TransformType::Translate, 1) => Some (vec! [params[0], 0.0]),
+            (TransformType::Translate, 2) => Some (params.to_vec ()),
+            (TransformType::Scale, 1)     => Some (vec! [params[0], params[0]]),
+            (TransformType::Scale, 2)     => Some (params.to_vec ()),
+            (TransformType::Rotate, 1)    => Some (vec! [params[0], 0.0, 0.0]),
+            (TransformType::Rotate, 3)    => Some (params.to_vec ()),
+            (TransformType::SkewX, 1)     => Some (params.to_vec ()),
+            (TransformType::SkewY, 1)     => Some (params.to_vec ()),
+            _                             => None
+        }
+    }
+
+    fn identity (&self) -> Vec<f64> {
+        match *self {
+            TransformType::Translate => vec! [0.0, 0.0],
+            TransformType::Scale     => vec! [1.0, 1.0],
+            TransformType::Rotate    => vec! [0.0, 0.0, 0.0],
+            TransformType::SkewX     => vec! [0.0],
+            TransformType::SkewY     => vec! [0.0]
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MotionRotate {
+    Angle (f64),
+    Auto,
+    AutoReverse
+}
+
+pub struct NodeAnimation {
+    kind:           AnimationKind,
+    link:           RefCell<Option<String>>,
+    attribute_name: RefCell<Option<String>>,
+    attribute_type: Cell<AttributeType>,
+    timing:         RefCell<Timing>,
+    values:         RefCell<Option<Vec<String>>>,
+    from:           RefCell<Option<String>>,
+    to:             RefCell<Option<String>>,
+    by:             RefCell<Option<String>>,
+    calc_mode:      Cell<CalcMode>,
+    key_times:      RefCell<Vec<f64>>,
+    key_splines:    RefCell<Vec<KeySpline>>,
+    additive:       Cell<bool>,
+    accumulate:     Cell<bool>,
+    transform_type: Cell<TransformType>,
+    path:           RefCell<Option<RsvgPathBuilder>>,
+    key_points:     RefCell<Vec<f64>>,
+    rotate:         Cell<MotionRotate>
+}
+
+impl NodeAnimation {
+    fn new (kind: AnimationKind) -> NodeAnimation {
+        NodeAnimation {
+            kind:           kind,
+            link:           RefCell::new (None),
+            attribute_name: RefCell::new (None),
+            attribute_type: Cell::new (AttributeType::Auto),
+            timing:         RefCell::new (Timing::default ()),
+            values:         RefCell::new (None),
+            from:           RefCell::new (None),
+            to:             RefCell::new (None),
+            by:             RefCell::new (None),
+            calc_mode:      Cell::new (CalcMode::Linear),
+            key_times:      RefCell::new (Vec::new ()),
+            key_splines:    RefCell::new (Vec::new ()),
+            additive:       Cell::new (false),
+            accumulate:     Cell::new (false),
+            transform_type: Cell::new (TransformType::Translate),
+            path:           RefCell::new (None),
+            key_points:     RefCell::new (Vec::new ()),
+            rotate:         Cell::new (MotionRotate::Angle (0.0))
+        }
+    }
+
+    fn parse_timing (&self, pbag: *const RsvgPropertyBag) -> Result<Timing, NodeError> {
+        let mut timing = Timing::default ();
+
+        if let Some (begin) = property_bag::lookup (pbag, "begin") {
+            timing.begin = parse_time_list (&begin);
+        }
+
+        if let Some (end) = property_bag::lookup (pbag, "end") {
+            timing.end = parse_time_list (&end);
+        }
+
+        if let Some (dur) = property_bag::lookup (pbag, "dur") {
+            timing.dur = match dur.trim () {
+                "indefinite" | "media" => f64::INFINITY,
+
+                d => {
+                    let d = parse_clock_value (d).map_err (|e| NodeError::parse_error ("dur", e))?;
+
+                    if d <= 0.0 {
+                        return Err (NodeError::value_error ("dur", "must be greater than zero"));
+                    }
+
+                    d
+                }
+            };
+        }
+
+        if let Some (count) = property_bag::lookup (pbag, "repeatCount") {
+            timing.repeat_count = Some (match count.trim () {
+                "indefinite" => f64::INFINITY,
+
+                c => {
+                    let c = parsers::number (c).map_err (|e| NodeError::parse_error ("repeatCount", e))?;
+
+                    if c <= 0.0 {
+                        return Err (NodeError::value_error ("repeatCount", "must be greater than zero"));
+                    }
+
+                    c
+                }
+            });
+        }
+
+        if let Some (dur) = property_bag::lookup (pbag, "repeatDur") {
+            timing.repeat_dur = Some (match dur.trim () {
+                "indefinite" => f64::INFINITY,
+                d            => parse_clock_value (d).map_err (|e| NodeError::parse_error ("repeatDur", e))?
+            });
+        }
+
+        timing.freeze = match property_bag::lookup (pbag, "fill").as_ref ().map (|s| s.trim ()) {
+            None | Some ("remove") => false,
+            Some ("freeze")        => true,
+            Some (_)               => return Err (NodeError::parse_error ("fill", ParseError::new ("expected freeze or remove")))
+        };
+
+        Ok (timing)
+    }
+
+    // Number of values in the animation function, if known in advance
+    fn num_values (&self) -> Option<usize> {
+        if !self.key_points.borrow ().is_empty () {
+            Some (self.key_points.borrow ().len ())
+        } else if let Some (ref values) = *self.values.borrow () {
+            Some (values.len ())
+        } else if self.path.borrow ().is_none ()
+            && (self.from.borrow ().is_some () || self.to.borrow ().is_some () || self.by.borrow ().is_some ()) {
+            Some (2)
+        } else {
+            None
+        }
+    }
+
+    // https://www.w3.org/TR/SVG11/animate.html#KeyTimesAttribute
+    fn validate_keys (&self) -> NodeResult {
+        let key_times = self.key_times.borrow ();
+        let calc_mode = self.calc_mode.get ();
+
+        if !key_times.is_empty () && calc_mode != CalcMode::Paced {
+            let valid = key_times[0] == 0.0
+                && key_times.windows (2).all (|w| w[0] <= w[1])
+                && key_times.iter ().all (|&t| t <= 1.0)
+                && (calc_mode == CalcMode::Discrete || key_times[key_times.len () - 1] == 1.0)
+                && self.num_values ().map_or (true, |n| n == key_times.len ());
+
+            if !valid {
+                return Err (NodeError::value_error ("keyTimes", "invalid list of key times for the animation values"));
+            }
+        }
+
+        if calc_mode == CalcMode::Spline {
+            let num_intervals = if !key_times.is_empty () {
+                Some (key_times.len () - 1)
+            } else {
+                self.num_values ().map (|n| n.saturating_sub (1))
+            };
+
+            if num_intervals.map_or (false, |n| n != self.key_splines.borrow ().len ()) {
+                return Err (NodeError::value_error ("keySplines", "must have one spline per interval"));
+            }
+        }
+
+        Ok (())
+    }
+
+    // Returns the values of the animation function, whether it is
+    // additive, and whether it can accumulate.
+    //
+    // https://www.w3.org/TR/SMIL/smil-animation.html#animationNS-FromToBy
+    fn animation_values<F> (&self, parse: F, base: Option<Value>) -> Option<(Vec<Value>, bool, bool)>
+        where F: Fn (&str) -> Option<Value>
+    {
+        let additive = self.additive.get ();
+
+        if let Some (ref values) = *self.values.borrow () {
+            let values: Option<Vec<Value>> = values.iter ().map (|v| parse (v)).collect ();
+            return values.map (|v| (v, additive, true));
+        }
+
+        let from = self.from.borrow ().as_ref ().map (|s| parse (s));
+        let to = self.to.borrow ().as_ref ().map (|s| parse (s));
+        let by = self.by.borrow ().as_ref ().map (|s| parse (s));
+
+        match (from, to, by) {
+            (Some (from), Some (to), _) => Some ((vec! [from?, to?], additive, true)),
+
+            (Some (from), None, Some (by)) => {
+                let from = from?;
+                let to = from.add (&by?)?;
+                Some ((vec! [from, to], additive, true))
+            },
+
+            // by-animation is always additive
+            (None, None, Some (by)) => {
+                let by = by?;
+                Some ((vec! [by.scale (0.0)?, by], true, true))
+            },
+
+            // to-animation goes from the underlying value; it's never additive
+            (None, Some (to), _) => {
+                let to = to?;
+                Some ((vec! [base.unwrap_or (to.clone ()), to], false, false))
+            },
+
+            _ => None
+        }
+    }
+
+    fn compute (&self, values: &[Value], fraction: f64, iteration: f64, cumulative: bool) -> Value {
+        let mut value = interpolate_values (values,
+                                            self.calc_mode.get (),
+                                            &self.key_times.borrow (),
+                                            &self.key_splines.borrow (),
+                                            fraction);
+
+        if cumulative && self.accumulate.get () && iteration > 0.0 {
+            let accumulated = values[values.len () - 1].scale (iteration)
+                .and_then (|acc| value.add (&acc));
+
+            if let Some (v) = accumulated {
+                value = v;
+            }
+        }
+
+        value
+    }
+
+    fn apply_set (&self, pbag: *mut RsvgPropertyBag, animated: *mut RsvgPropertyBag) {
+        if let (Some (ref name), Some (ref to)) = (self.attribute_name.borrow ().clone (), self.to.borrow ().clone ()) {
+            set_attribute (pbag, animated, name, to, self.attribute_type.get ());
+        }
+    }
+
+    fn apply_animate (&self, fraction: f64, iteration: f64, pbag: *mut RsvgPropertyBag, animated: *mut RsvgPropertyBag) {
+        let name = match *self.attribute_name.borrow () {
+            Some (ref name) => name.clone (),
+            None => return
+        };
+
+        let base = property_bag::lookup (pbag, &name).map (|b| Value::parse (&b));
+
+        if let Some ((values, additive, cumulative)) = self.animation_values (|s| Some (Value::parse (s)), base.clone ()) {
+            let mut value = self.compute (&values, fraction, iteration, cumulative);
+
+            if additive {
+                if let Some (sum) = base.and_then (|b| b.add (&value)) {
+                    value = sum;
+                }
+            }
+
+            set_attribute (pbag, animated, &name, &value.to_string (), self.attribute_type.get ());
+        }
+    }
+
+    fn apply_transform (&self, fraction: f64, iteration: f64, pbag: *mut RsvgPropertyBag) {
+        let name = self.attribute_name.borrow ().clone ().unwrap_or ("transform".to_string ());
+        let transform_type = self.transform_type.get ();
+
+        let parse = |s: &str| parse_number_list (s)
+            .and_then (|params| transform_type.normalize (&params))
+            .map (Value::List);
+
+        let identity = Value::List (transform_type.identity ());
+
+        if let Some ((values, additive, cumulative)) = self.animation_values (parse, Some (identity)) {
+            if let Value::List (params) = self.compute (&values, fraction, iteration, cumulative) {
+                let strings: Vec<String> = params.iter ().map (|p| p.to_string ()).collect ();
+                let transform = format! ("{}({})", transform_type.name (), strings.join (" "));
+
+                // The animated transform is post-multiplied onto the base value
+                let value = match property_bag::lookup (pbag, &name) {
+                    Some (ref base) if additive => format! ("{} {}", base, transform),
+                    _ => transform
+                };
+
+                property_bag::insert (pbag, &name, &value);
+            }
+        }
+    }
+
+    // The motion path and the distances along it of each of the
+    // animation's values, if the path is made out of them.
+    //
+    // https://www.w3.org/TR/SVG11/animate.html#AnimateMotionElement
+    fn motion_path (&self, node: &RsvgNode, handle: *const RsvgHandle) -> Option<(PathMeasure, Vec<f64>)> {
+        for child in &*node.children.borrow () {
+            if let Some (mpath) = child.get_impl::<NodeMPath> () {
+                return mpath.link.borrow ().as_ref ()
+                    .and_then (|link| handle::lookup_node (handle, link))
+                    .and_then (|path_node| shapes::path_node_measure (&path_node))
+                    .map (|measure| (measure, Vec::new ()));
+            }
+        }
+
+        if let Some (ref builder) = *self.path.borrow () {
+            return Some ((PathMeasure::new (builder), Vec::new ()));
+        }
+
+        let parse = |s: &str| parse_number_list (s)
+            .and_then (|v| if v.len () == 2 { Some (Value::List (v)) } else { None });
+
+        let (points, _, _) = self.animation_values (parse, Some (Value::List (vec! [0.0, 0.0])))?;
+
+        let mut builder = RsvgPathBuilder::new ();
+        let mut distances = Vec::new ();
+        let mut distance = 0.0;
+
+        for (i, point) in points.iter ().enumerate () {
+            if let Value::List (ref p) = *point {
+                if i == 0 {
+                    builder.move_to (p[0], p[1]);
+                } else {
+                    builder.line_to (p[0], p[1]);
+                    distance += points[i - 1].distance (point).unwrap ();
+                }
+
+                distances.push (distance);
+            }
+        }
+
+        Some ((PathMeasure::new (&builder), distances))
+    }
+
+    fn apply_motion (&self, node: &RsvgNode, handle: *const RsvgHandle, fraction: f64, iteration: f64, pbag: *mut RsvgPropertyBag) {
+        let (measure, vertices) = match self.motion_path (node, handle) {
+            Some (path) => path,
+            None => return
+        };
+
+        let length = measure.length ();
+        let key_points = self.key_points.borrow ();
+
+        let scalar = |values: Vec<f64>, calc_mode: CalcMode| {
+            let values: Vec<Value> = values.into_iter ().map (|v| Value::Number (v, String::new ())).collect ();
+
+            match interpolate_values (&values, calc_mode, &self.key_times.borrow (), &self.key_splines.borrow (), fraction) {
+                Value::Number (n, _) => n,
+                _ => unreachable! ()
+            }
+        };
+
+        let distance = if !key_points.is_empty () {
+            let calc_mode = match self.calc_mode.get () {
+                CalcMode::Paced => CalcMode::Linear,
+                c => c
+            };
+
+            scalar (key_points.clone (), calc_mode) * length
+        } else if self.calc_mode.get () == CalcMode::Paced || vertices.len () < 2 {
+            fraction * length
+        } else {
+            scalar (vertices, self.calc_mode.get ())
+        };
+
+        let (mut x, mut y) = match measure.point_at_length (distance) {
+            Some (p) => p,
+            None => return
+        };
+
+        if self.accumulate.get () && iteration > 0.0 {
+            if let (Some (start), Some (end)) = (measure.point_at_length (0.0), measure.point_at_length (length)) {
+                x += (end.0 - start.0) * iteration;
+                y += (end.1 - start.1) * iteration;
+            }
+        }
+
+        let tangent_angle = || measure.tangent_at_length (distance)
+            .map_or (0.0, |(tx, ty)| ty.atan2 (tx).to_degrees ());
+
+        let angle = match self.rotate.get () {
+            MotionRotate::Angle (a)    => a,
+            MotionRotate::Auto         => tangent_angle (),
+            MotionRotate::AutoReverse  => tangent_angle () + 180.0
+        };
+
+        let mut motion = format! ("translate({} {})", x, y);
+
+        if angle != 0.0 {
+            motion.push_str (&format! (" rotate({})", angle));
+        }
+
+        // The motion is applied before the element's own transform
+        let value = match property_bag::lookup (pbag, "transform") {
+            Some (base) => format! ("{} {}", motion, base),
+            None => motion
+        };
+
+        property_bag::insert (pbag, "transform", &value);
+    }
+
+    fn apply (&self,
+              node: &RsvgNode,
+              handle: *const RsvgHandle,
+              time: f64,
+              pbag: *mut RsvgPropertyBag,
+              animated: *mut RsvgPropertyBag) {
+        let (fraction, iteration) = match self.timing.borrow ().sample (time) {
+            Some (sample) => sample,
+            None => return
+        };
+
+        match self.kind {
+            AnimationKind::Set              => self.apply_set (pbag, animated),
+            AnimationKind::AnimateMotion    => self.apply_motion (node, handle, fraction, iteration, pbag),
+            AnimationKind::AnimateTransform => self.apply_transform (fraction, iteration, pbag),
+
+            AnimationKind::Animate |
+            AnimationKind::AnimateColor     => self.apply_animate (fraction, iteration, pbag, animated)
+        }
+    }
+}
+
+// Animated properties also go in `animated`, whose values the cascade
+// puts after the ones from the style sheets and the style attribute.
+fn set_attribute (pbag: *mut RsvgPropertyBag,
+                  animated: *mut RsvgPropertyBag,
+                  name: &str,
+                  value: &str,
+                  attribute_type: AttributeType) {
+    property_bag::insert (pbag, name, value);
+
+    if attribute_type != AttributeType::Xml && is_presentation_attribute (name) {
+        property_bag::insert (animated, name, value);
+    }
+}
+
+impl NodeTrait for NodeAnimation {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        *self.link.borrow_mut () = property_bag::lookup (pbag, "xlink:href")
+            .or_else (|| property_bag::lookup (pbag, "href"));
+
+        *self.attribute_name.borrow_mut () = property_bag::lookup (pbag, "attributeName");
+
+        self.attribute_type.set (match property_bag::lookup (pbag, "attributeType").as_ref ().map (|s| s.trim ()) {
+            None | Some ("auto") => AttributeType::Auto,
+            Some ("CSS")         => AttributeType::Css,
+            Some ("XML")         => AttributeType::Xml,
+            Some (_)             => return Err (NodeError::parse_error ("attributeType",
+                                                                        ParseError::new ("expected CSS, XML, or auto")))
+        });
+
+        *self.timing.borrow_mut () = self.parse_timing (pbag)?;
+
+        *self.values.borrow_mut () = property_bag::lookup (pbag, "values")
+            .map (|v| split_semicolons (&v).iter ().map (|s| s.to_string ()).collect::<Vec<String>> ())
+            .and_then (|v| if v.is_empty () { None } else { Some (v) });
+
+        *self.from.borrow_mut () = property_bag::lookup (pbag, "from");
+        *self.to.borrow_mut () = property_bag::lookup (pbag, "to");
+        *self.by.borrow_mut () = property_bag::lookup (pbag, "by");
+
+        let default_calc_mode = if self.kind == AnimationKind::AnimateMotion {
+            CalcMode::Paced
+        } else {
+            CalcMode::Linear
+        };
+
+        self.calc_mode.set (property_bag::parse_or_value (pbag, "calcMode", (), default_calc_mode, None)?);
+
+        *self.key_times.borrow_mut () = match property_bag::lookup (pbag, "keyTimes") {
+            Some (v) => split_semicolons (&v).iter ()
+                .map (|t| parsers::number (t))
+                .collect::<Result<Vec<f64>, ParseError>> ()
+                .map_err (|e| NodeError::parse_error ("keyTimes", e))?,
+
+            None => Vec::new ()
+        };
+
+        *self.key_splines.borrow_mut () = match property_bag::lookup (pbag, "keySplines") {
+            Some (v) => split_semicolons (&v).iter ()
+                .map (|s| KeySpline::parse (s))
+                .collect::<Result<Vec<KeySpline>, ParseError>> ()
+                .map_err (|e| NodeError::parse_error ("keySplines", e))?,
+
+            None => Vec::new ()
+        };
+
+        self.additive.set (property_bag::lookup (pbag, "additive").map_or (false, |s| s.trim () == "sum"));
+        self.accumulate.set (property_bag::lookup (pbag, "accumulate").map_or (false, |s| s.trim () == "sum"));
+
+        if self.kind == AnimationKind::AnimateTransform {
+            self.transform_type.set (match property_bag::lookup (pbag, "type").as_ref ().map (|s| s.trim ()) {
+                None | Some ("translate") => TransformType::Translate,
+                Some ("scale")            => TransformType::Scale,
+                Some ("rotate")           => TransformType::Rotate,
+                Some ("skewX")            => TransformType::SkewX,
+                Some ("skewY")            => TransformType::SkewY,
+                Some (_)                  => return Err (NodeError::parse_error ("type",
+                                                                                 ParseError::new ("invalid transform type")))
+            });
+        }
+
+        if self.kind == AnimationKind::AnimateMotion {
+            *self.path.borrow_mut () = match property_bag::lookup (pbag, "path") {
+                Some (d) => {
+                    let mut builder = RsvgPathBuilder::new ();
+
+                    // A partial path is OK, like for the path element
+                    let _ = path_parser::parse_path_into_builder (&d, &mut builder);
+
+                    Some (builder)
+                },
+
+                None => None
+            };
+
+            *self.key_points.borrow_mut () = match property_bag::lookup (pbag, "keyPoints") {
+                Some (v) => split_semicolons (&v).iter ()
+                    .map (|p| parsers::number (p))
+                    .collect::<Result<Vec<f64>, ParseError>> ()
+                    .map_err (|e| NodeError::parse_error ("keyPoints", e))?,
+
+                None => Vec::new ()
+            };
+
+            self.rotate.set (match property_bag::lookup (pbag, "rotate").as_ref ().map (|s| s.trim ()) {
+                None                 => MotionRotate::Angle (0.0),
+                Some ("auto")        => MotionRotate::Auto,
+                Some ("auto-reverse") => MotionRotate::AutoReverse,
+                Some (a)             => MotionRotate::Angle (parsers::number (a)
+                                                             .map_err (|e| NodeError::parse_error ("rotate", e))?)
+            });
+        }
+
+        self.validate_keys ()
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; animations are applied with rsvg_handle_set_time()
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+/***** NodeMPath *****/
+
+struct NodeMPath {
+    link: RefCell<Option<String>>
+}
+
+impl NodeMPath {
+    fn new () -> NodeMPath {
+        NodeMPath {
+            link: RefCell::new (None)
+        }
+    }
+}
+
+impl NodeTrait for NodeMPath {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        *self.link.borrow_mut () = property_bag::lookup (pbag, "xlink:href")
+            .or_else (|| property_bag::lookup (pbag, "href"));
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+/***** C Prototypes *****/
+
+#[no_mangle]
+pub extern fn rsvg_node_animate_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Animation,
+                    raw_parent,
+                    Box::new (NodeAnimation::new (AnimationKind::Animate)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_animate_color_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Animation,
+                    raw_parent,
+                    Box::new (NodeAnimation::new (AnimationKind::AnimateColor)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_animate_motion_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Animation,
+                    raw_parent,
+                    Box::new (NodeAnimation::new (AnimationKind::AnimateMotion)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_animate_transform_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Animation,
+                    raw_parent,
+                    Box::new (NodeAnimation::new (AnimationKind::AnimateTransform)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_set_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Animation,
+                    raw_parent,
+                    Box::new (NodeAnimation::new (AnimationKind::Set)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_mpath_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::MPath,
+                    raw_parent,
+                    Box::new (NodeMPath::new ()))
+}
+
+// Returns a new reference to the element that the animation modifies:
+// the one given by its xlink:href, or its parent.  Returns NULL if
+// the animation is in error, or if there is no such element.
+#[no_mangle]
+pub extern fn rsvg_node_animation_get_target (raw_node: *const RsvgNode, handle: *const RsvgHandle) -> *mut RsvgNode {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    if node.get_result ().is_err () {
+        return ptr::null_mut ();
+    }
+
+    let mut target = None;
+
+    node.with_impl (|animation: &NodeAnimation| {
+        target = match *animation.link.borrow () {
+            Some (ref link) => handle::lookup_node (handle, link),
+            None => node.get_parent ()
+        };
+    });
+
+    match target {
+        Some (target) => box_node (target),
+        None => ptr::null_mut ()
+    }
+}
+
+// Stores the animated value of the target's attribute at the
+// specified time in @pbag, which holds the attributes of the target
+// as modified by the animations that come before this one.  Animated
+// properties also get stored in @animated.
+#[no_mangle]
+pub extern fn rsvg_node_animation_apply (raw_node: *const RsvgNode,
+                                         handle:   *const RsvgHandle,
+                                         time:     f64,
+                                         pbag:     *mut RsvgPropertyBag,
+                                         animated: *mut RsvgPropertyBag) {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    assert! (!pbag.is_null ());
+    assert! (!animated.is_null ());
+
+    node.with_impl (|animation: &NodeAnimation| {
+        animation.apply (node, handle, time, pbag, animated);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_clock_values () {
+        assert_eq! (parse_clock_value ("02:30:03"), Ok (9003.0));
+        assert_eq! (parse_clock_value ("50:00:10.25"), Ok (180010.25));
+        assert_eq! (parse_clock_value ("02:33"), Ok (153.0));
+        assert_eq! (parse_clock_value ("00:10.5"), Ok (10.5));
+        assert_eq! (parse_clock_value ("3.2h"), Ok (11520.0));
+        assert_eq! (parse_clock_value ("45min"), Ok (2700.0));
+        assert_eq! (parse_clock_value ("30s"), Ok (30.0));
+        assert_eq! (parse_clock_value ("5ms"), Ok (0.005));
+        assert_eq! (parse_clock_value ("12.467"), Ok (12.467));
+        assert_eq! (parse_clock_value (" 2s "), Ok (2.0));
+    }
+
+    #[test]
+    fn invalid_clock_values_yield_error () {
+        assert! (parse_clock_value ("").is_err ());
+        assert! (parse_clock_value ("s").is_err ());
+        assert! (parse_clock_value (".5s").is_err ());
+        assert! (parse_clock_value ("5.s").is_err ());
+        assert! (parse_clock_value ("1:2").is_err ());
+        assert! (parse_clock_value ("00:75").is_err ());
+        assert! (parse_clock_value ("1.5:00").is_err ());
+        assert! (parse_clock_value ("1:00:00:00").is_err ());
+        assert! (parse_clock_value ("-1s").is_err ());
+        assert! (parse_clock_value ("1 s").is_err ());
+    }
+
+    #[test]
+    fn parses_time_lists () {
+        assert_eq! (parse_time_list ("0"), vec! [0.0]);
+        assert_eq! (parse_time_list ("2s; -1s ;+ 0.5s"), vec! [-1.0, 0.5, 2.0]);
+        assert_eq! (parse_time_list ("indefinite"), Vec::<f64>::new ());
+        assert_eq! (parse_time_list ("foo.click; 1s"), vec! [1.0]);
+    }
+
+    fn timing (begin: f64, dur: f64) -> Timing {
+        Timing {
+            begin: vec! [begin],
+            dur: dur,
+            .. Timing::default ()
+        }
+    }
+
+    #[test]
+    fn samples_simple_duration () {
+        let t = timing (1.0, 2.0);
+
+        assert_eq! (t.sample (0.5), None);
+        assert_eq! (t.sample (1.0), Some ((0.0, 0.0)));
+        assert_eq! (t.sample (2.0), Some ((0.5, 0.0)));
+        assert_eq! (t.sample (3.0), None);
+    }
+
+    #[test]
+    fn samples_repeats_and_freeze () {
+        let t = Timing {
+            repeat_count: Some (2.5),
+            freeze: true,
+            .. timing (0.0, 2.0)
+        };
+
+        assert_eq! (t.sample (3.0), Some ((0.5, 1.0)));
+        assert_eq! (t.sample (10.0), Some ((0.5, 2.0)));
+
+        let t = Timing {
+            repeat_count: Some (2.0),
+            freeze: true,
+            .. timing (0.0, 2.0)
+        };
+
+        assert_eq! (t.sample (10.0), Some ((1.0, 1.0)));
+
+        let t = Timing {
+            repeat_count: Some (f64::INFINITY),
+            repeat_dur: Some (5.0),
+            .. timing (0.0, 2.0)
+        };
+
+        assert_eq! (t.sample (4.5), Some ((0.25, 2.0)));
+        assert_eq! (t.sample (5.0), None);
+    }
+
+    #[test]
+    fn end_cuts_active_duration () {
+        let t = Timing {
+            end: vec! [1.0],
+            freeze: true,
+            .. timing (0.0, 4.0)
+        };
+
+        assert_eq! (t.sample (0.5), Some ((0.125, 0.0)));
+        assert_eq! (t.sample (2.0), Some ((0.25, 0.0)));
+    }
+
+    #[test]
+    fn indefinite_duration_holds_first_value () {
+        let t = Timing::default ();
+
+        assert_eq! (t.sample (100.0), Some ((0.0, 0.0)));
+    }
+
+    #[test]
+    fn parses_values () {
+        assert_eq! (Value::parse ("10"), Value::Number (10.0, String::new ()));
+        assert_eq! (Value::parse ("-1.5em"), Value::Number (-1.5, "em".to_string ()));
+        assert_eq! (Value::parse ("1e2px"), Value::Number (100.0, "px".to_string ()));
+        assert_eq! (Value::parse ("50%"), Value::Number (50.0, "%".to_string ()));
+        assert_eq! (Value::parse ("#ff0000"), Value::Color ([255.0, 0.0, 0.0, 255.0]));
+        assert_eq! (Value::parse ("10,20 30"), Value::List (vec! [10.0, 20.0, 30.0]));
+        assert_eq! (Value::parse ("none"), Value::Other ("none".to_string ()));
+        assert_eq! (Value::parse ("url(#foo)"), Value::Other ("url(#foo)".to_string ()));
+    }
+
+    #[test]
+    fn interpolates_values () {
+        let a = Value::Number (0.0, "px".to_string ());
+        let b = Value::Number (10.0, "px".to_string ());
+        assert_eq! (a.interpolate (&b, 0.25).unwrap ().to_string (), "2.5px");
+
+        let a = Value::parse ("red");
+        let b = Value::parse ("blue");
+        assert_eq! (a.interpolate (&b, 0.5).unwrap ().to_string (), "#800080");
+
+        let a = Value::parse ("0 0");
+        let b = Value::parse ("10 20");
+        assert_eq! (a.interpolate (&b, 0.5).unwrap ().to_string (), "5 10");
+
+        assert! (Value::parse ("1px").interpolate (&Value::parse ("1em"), 0.5).is_none ());
+        assert! (Value::parse ("1 2").interpolate (&Value::parse ("1 2 3"), 0.5).is_none ());
+    }
+
+    fn numbers (v: &[f64]) -> Vec<Value> {
+        v.iter ().map (|n| Value::Number (*n, String::new ())).collect ()
+    }
+
+    #[test]
+    fn interpolation_modes () {
+        let values = numbers (&[0.0, 10.0, 30.0]);
+
+        assert_eq! (interpolate_values (&values, CalcMode::Linear, &[], &[], 0.25), Value::Number (5.0, String::new ()));
+        assert_eq! (interpolate_values (&values, CalcMode::Linear, &[], &[], 0.75), Value::Number (20.0, String::new ()));
+        assert_eq! (interpolate_values (&values, CalcMode::Linear, &[0.0, 0.5, 1.0], &[], 1.0), Value::Number (30.0, String::new ()));
+        assert_eq! (interpolate_values (&values, CalcMode::Linear, &[0.0, 0.8, 1.0], &[], 0.4), Value::Number (5.0, String::new ()));
+
+        assert_eq! (interpolate_values (&values, CalcMode::Paced, &[], &[], 0.5), Value::Number (15.0, String::new ()));
+
+        assert_eq! (interpolate_values (&values, CalcMode::Discrete, &[], &[], 0.5), Value::Number (10.0, String::new ()));
+        assert_eq! (interpolate_values (&values, CalcMode::Discrete, &[0.0, 0.8, 0.9], &[], 0.5), Value::Number (0.0, String::new ()));
+        assert_eq! (interpolate_values (&values, CalcMode::Discrete, &[], &[], 1.0), Value::Number (30.0, String::new ()));
+    }
+
+    #[test]
+    fn keywords_switch_discretely () {
+        let values = vec! [Value::parse ("visible"), Value::parse ("hidden")];
+
+        assert_eq! (interpolate_values (&values, CalcMode::Linear, &[], &[], 0.25), Value::parse ("visible"));
+        assert_eq! (interpolate_values (&values, CalcMode::Linear, &[], &[], 0.75), Value::parse ("hidden"));
+    }
+
+    #[test]
+    fn key_splines_ease () {
+        let linear = KeySpline (0.0, 0.0, 1.0, 1.0);
+        assert! ((linear.ease (0.3) - 0.3).abs () < 1e-6);
+
+        let ease_in = KeySpline (0.42, 0.0, 1.0, 1.0);
+        assert! (ease_in.ease (0.5) < 0.5);
+        assert! ((ease_in.ease (1.0) - 1.0).abs () < 1e-6);
+
+        assert! (KeySpline::parse ("0 0 1 1").is_ok ());
+        assert! (KeySpline::parse ("0,0,1,1").is_ok ());
+        assert! (KeySpline::parse ("0 0 1").is_err ());
+        assert! (KeySpline::parse ("0 0 1 2").is_err ());
+    }
+
+    #[test]
+    fn normalizes_transform_params () {
+        assert_eq! (TransformType::Translate.normalize (&[5.0]), Some (vec! [5.0, 0.0]));
+        assert_eq! (TransformType::Scale.normalize (&[2.0]), Some (vec! [2.0, 2.0]));
+        assert_eq! (TransformType::Rotate.normalize (&[45.0]), Some (vec! [45.0, 0.0, 0.0]));
+        assert_eq! (TransformType::Rotate.normalize (&[45.0, 1.0]), None);
+        assert_eq! (TransformType::SkewX.normalize (&[1.0, 2.0]), None);
+    }
+}
@@ -0,0 +1,529 @@
+//! Safe Rust API for loading and rendering SVG documents.
+//!
+//! This wraps the same `RsvgHandle` that the C API uses, so a program
+//! that uses this crate as an rlib must also link against librsvg.
+//!
+//! ```ignore
+//! let handle = Loader::new ().read_path ("example.svg")?;
+//! let (width, height) = handle.dimensions ();
+//!
+//! let surface = cairo::ImageSurface::create (cairo::Format::ARgb32, width, height).unwrap ();
+//! let cr = cairo::Context::new (&surface);
+//!
+//! Renderer::new (&handle).render (&cr)?;
+//! ```
+
+use ::cairo;
+use ::cairo_sys;
+use ::glib;
+use ::glib::translate::*;
+use ::glib_sys;
+use ::libc;
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use std::ptr;
+
+use diagnostics::{Diagnostic, Diagnostics};
+use handle::{self, RsvgHandle};
+use node::{NodeType, RsvgNode};
+use path_builder::RsvgPathBuilder;
+use serializer;
+
+// Keep in sync with rsvg.h:RsvgHandleFlags
+const RSVG_HANDLE_FLAG_UNLIMITED: u32 = 1 << 0;
+const RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA: u32 = 1 << 1;
+const RSVG_HANDLE_FLAG_STRICT: u32 = 1 << 2;
+
+// Keep in sync with rsvg.h:RsvgError
+const RSVG_ERROR_INVALID_ATTRIBUTE: libc::c_int = 1;
+
+// Keep in sync with rsvg.h:RsvgDimensionData
+#[repr(C)]
+struct RsvgDimensionData {
+    width:  libc::c_int,
+    height: libc::c_int,
+    em:     f64,
+    ex:     f64
+}
+
+extern "C" {
+    fn rsvg_error_quark () -> glib_sys::GQuark;
+
+    fn rsvg_handle_new_with_flags (flags: u32) -> *mut RsvgHandle;
+    fn rsvg_handle_set_base_uri (handle: *mut RsvgHandle, base_uri: *const libc::c_char);
+    fn rsvg_handle_set_dpi_x_y (handle: *mut RsvgHandle, dpi_x: f64, dpi_y: f64);
+
+    fn rsvg_handle_write (handle: *mut RsvgHandle,
+                          buf:    *const u8,
+                          count:  libc::size_t,
+                          error:  *mut *mut glib_sys::GError) -> glib_sys::gboolean;
+
+    fn rsvg_handle_close (handle: *mut RsvgHandle, error: *mut *mut glib_sys::GError) -> glib_sys::gboolean;
+
+    fn rsvg_handle_get_dimensions (handle: *mut RsvgHandle, dimension_data: *mut RsvgDimensionData);
+    fn rsvg_handle_has_sub (handle: *mut RsvgHandle, id: *const libc::c_char) -> glib_sys::gboolean;
+    fn rsvg_handle_get_root_node (handle: *mut RsvgHandle) -> *const RsvgNode;
+    fn rsvg_handle_get_clip_path_geometry (handle: *mut RsvgHandle,
+                                           id: *const libc::c_char,
+                                           bbox: *const cairo::Rectangle) -> *mut RsvgPathBuilder;
+    fn rsvg_handle_get_diagnostics_collector (handle: *mut RsvgHandle) -> *const Diagnostics;
+
+    fn rsvg_handle_render_cairo_sub (handle: *mut RsvgHandle,
+                                     cr:     *mut cairo_sys::cairo_t,
+                                     id:     *const libc::c_char) -> glib_sys::gboolean;
+
+    fn g_object_unref (object: *mut libc::c_void);
+}
+
+/// An error from loading an SVG document.
+#[derive(Debug)]
+pub enum LoadingError {
+    /// The data could not be read from its file or stream.
+    Io (io::Error),
+
+    /// The data is not well-formed XML, or it goes over the size limits
+    /// of a `Loader` without `unlimited_size()`.  This is the error from
+    /// the XML parser, in the `rsvg-error-quark` domain.
+    Xml (glib::Error),
+
+    /// The XML is well-formed, but its toplevel element is not an `<svg>`.
+    NoSvgRoot,
+
+    /// An element or attribute has an invalid value; only returned by
+    /// a `Loader` with `strict()`.
+    InvalidAttribute (Diagnostic)
+}
+
+impl fmt::Display for LoadingError {
+    fn fmt (&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadingError::Io (ref e) => write! (f, "could not read SVG data: {}", e),
+            LoadingError::Xml (ref e) => write! (f, "could not parse SVG data: {}", e),
+            LoadingError::NoSvgRoot => write! (f, "the toplevel element is not an <svg>"),
+            LoadingError::InvalidAttribute (ref d) => write! (f, "invalid SVG data: {}", d)
+        }
+    }
+}
+
+impl error::Error for LoadingError {
+    fn description (&self) -> &str {
+        match *self {
+            LoadingError::Io (_) => "I/O error",
+            LoadingError::Xml (_) => "XML parse error",
+            LoadingError::NoSvgRoot => "no toplevel <svg> element",
+            LoadingError::InvalidAttribute (_) => "invalid attribute"
+        }
+    }
+}
+
+impl From<io::Error> for LoadingError {
+    fn from (e: io::Error) -> LoadingError {
+        LoadingError::Io (e)
+    }
+}
+
+impl From<glib::Error> for LoadingError {
+    fn from (e: glib::Error) -> LoadingError {
+        LoadingError::Xml (e)
+    }
+}
+
+/// An error from rendering an SVG document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderingError {
+    /// There is no element with the requested id.
+    InvalidId (String),
+
+    /// The cairo context was in an error state, or went into one
+    /// while rendering.
+    Cairo (cairo::Status)
+}
+
+impl fmt::Display for RenderingError {
+    fn fmt (&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenderingError::InvalidId (ref id) => write! (f, "no element with id \"{}\"", id),
+            RenderingError::Cairo (status) => write! (f, "cairo error: {:?}", status)
+        }
+    }
+}
+
+impl error::Error for RenderingError {
+    fn description (&self) -> &str {
+        match *self {
+            RenderingError::InvalidId (_) => "invalid element id",
+            RenderingError::Cairo (_) => "cairo error"
+        }
+    }
+}
+
+/// Options for loading SVG documents.
+///
+/// A `Loader` can be reused to load any number of documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loader {
+    unlimited_size:  bool,
+    keep_image_data: bool,
+    strict:          bool,
+    base_url:        Option<String>,
+    dpi:             Option<(f64, f64)>
+}
+
+impl Default for Loader {
+    fn default () -> Loader {
+        Loader::new ()
+    }
+}
+
+impl Loader {
+    pub fn new () -> Loader {
+        Loader {
+            unlimited_size:  false,
+            keep_image_data: false,
+            strict:          false,
+            base_url:        None,
+            dpi:             None
+        }
+    }
+
+    /// Disables the limits on the size of the XML.  For security
+    /// reasons, only use this for trusted input.
+    pub fn unlimited_size (mut self, unlimited: bool) -> Loader {
+        self.unlimited_size = unlimited;
+        self
+    }
+
+    /// Keeps the original data of embedded images, for use by cairo
+    /// when rendering to PDF or similar surfaces.
+    pub fn keep_image_data (mut self, keep: bool) -> Loader {
+        self.keep_image_data = keep;
+        self
+    }
+
+    /// Makes loading fail with `LoadingError::InvalidAttribute` for the
+    /// first invalid element or attribute in the document.  By default,
+    /// invalid elements are loaded but not rendered, and the errors are
+    /// available from `SvgHandle::diagnostics()`.
+    pub fn strict (mut self, strict: bool) -> Loader {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the URL or file name against which relative references
+    /// are resolved.  `read_path()` uses the file's own path if this
+    /// is not set.
+    pub fn base_url (mut self, url: &str) -> Loader {
+        self.base_url = Some (url.to_string ());
+        self
+    }
+
+    /// Sets the resolution used to convert physical units like `in`
+    /// into pixels.  The default is 90 DPI.
+    pub fn dpi (mut self, dpi_x: f64, dpi_y: f64) -> Loader {
+        self.dpi = Some ((dpi_x, dpi_y));
+        self
+    }
+
+    fn flags (&self) -> u32 {
+        let mut flags = 0;
+
+        if self.unlimited_size {
+            flags |= RSVG_HANDLE_FLAG_UNLIMITED;
+        }
+
+        if self.keep_image_data {
+            flags |= RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA;
+        }
+
+        if self.strict {
+            flags |= RSVG_HANDLE_FLAG_STRICT;
+        }
+
+        flags
+    }
+
+    fn new_handle (&self) -> SvgHandle {
+        let raw = unsafe { rsvg_handle_new_with_flags (self.flags ()) };
+        assert! (!raw.is_null ());
+
+        let handle = SvgHandle { raw: raw };
+
+        if let Some (ref url) = self.base_url {
+            unsafe { rsvg_handle_set_base_uri (raw, url.to_glib_none ().0); }
+        }
+
+        if let Some ((dpi_x, dpi_y)) = self.dpi {
+            unsafe { rsvg_handle_set_dpi_x_y (raw, dpi_x, dpi_y); }
+        }
+
+        handle
+    }
+
+    /// Loads a document from a stream, which is read until its end.
+    pub fn read<R: Read> (&self, mut reader: R) -> Result<SvgHandle, LoadingError> {
+        let handle = self.new_handle ();
+        let mut buf = [0u8; 4096];
+
+        loop {
+            let len = match reader.read (&mut buf) {
+                Ok (0) => break,
+                Ok (len) => len,
+                Err (ref e) if e.kind () == io::ErrorKind::Interrupted => continue,
+                Err (e) => return Err (LoadingError::Io (e))
+            };
+
+            handle.write (&buf[..len])?;
+        }
+
+        handle.close ()?;
+        Ok (handle)
+    }
+
+    /// Loads a document from a buffer in memory.
+    pub fn read_bytes (&self, data: &[u8]) -> Result<SvgHandle, LoadingError> {
+        let handle = self.new_handle ();
+
+        handle.write (data)?;
+        handle.close ()?;
+
+        Ok (handle)
+    }
+
+    /// Loads a document from a file.
+    pub fn read_path<P: AsRef<Path>> (&self, path: P) -> Result<SvgHandle, LoadingError> {
+        let path = path.as_ref ();
+        let file = File::open (path)?;
+
+        if self.base_url.is_some () {
+            self.read (file)
+        } else {
+            let mut loader = self.clone ();
+            loader.base_url = Some (path.to_string_lossy ().into_owned ());
+            loader.read (file)
+        }
+    }
+}
+
+/// A loaded SVG document.
+pub struct SvgHandle {
+    raw: *mut RsvgHandle
+}
+
+impl SvgHandle {
+    fn write (&self, data: &[u8]) -> Result<(), glib::Error> {
+        let mut error = ptr::null_mut ();
+
+        let ok: bool = unsafe {
+            from_glib (rsvg_handle_write (self.raw, data.as_ptr (), data.len (), &mut error))
+        };
+
+        if ok {
+            Ok (())
+        } else {
+            Err (unsafe { from_glib_full (error) })
+        }
+    }
+
+    fn close (&self) -> Result<(), LoadingError> {
+        let mut error: *mut glib_sys::GError = ptr::null_mut ();
+
+        let ok: bool = unsafe { from_glib (rsvg_handle_close (self.raw, &mut error)) };
+
+        if ok {
+            return match self.root () {
+                Some (root) if root.get_type () == NodeType::Svg => Ok (()),
+                _ => Err (LoadingError::NoSvgRoot)
+            };
+        }
+
+        let invalid_attribute = unsafe {
+            (*error).domain == rsvg_error_quark () && (*error).code == RSVG_ERROR_INVALID_ATTRIBUTE
+        };
+
+        let error: glib::Error = unsafe { from_glib_full (error) };
+
+        match self.diagnostics ().into_iter ().next () {
+            Some (diagnostic) if invalid_attribute => Err (LoadingError::InvalidAttribute (diagnostic)),
+            _ => Err (LoadingError::from (error))
+        }
+    }
+
+    fn root (&self) -> Option<&RsvgNode> {
+        let raw_root = unsafe { rsvg_handle_get_root_node (self.raw) };
+
+        if raw_root.is_null () {
+            None
+        } else {
+            Some (unsafe { &*raw_root })
+        }
+    }
+
+    /// Returns the size of the document in pixels, as computed from
+    /// the width, height and viewBox of its toplevel `<svg>` element.
+    pub fn dimensions (&self) -> (i32, i32) {
+        let mut data = RsvgDimensionData {
+            width:  0,
+            height: 0,
+            em:     0.0,
+            ex:     0.0
+        };
+
+        unsafe { rsvg_handle_get_dimensions (self.raw, &mut data); }
+
+        (data.width, data.height)
+    }
+
+    /// Returns the errors that were found in the document's elements
+    /// and attributes while loading it, in document order.  Elements
+    /// with errors are not rendered.
+    pub fn diagnostics (&self) -> Vec<Diagnostic> {
+        let diagnostics = unsafe { &*rsvg_handle_get_diagnostics_collector (self.raw) };
+
+        diagnostics.get_list ()
+    }
+
+    /// Writes the document back out as normalized SVG, with only the
+    /// elements and attributes that librsvg understands.  Styles from
+    /// `<style>` elements get folded into each element's `style`
+    /// attribute, paths use absolute commands, and transforms become
+    /// `matrix()`.  This is useful to sanitize untrusted documents.
+    pub fn to_svg (&self) -> String {
+        self.root ().map_or (String::new (), serializer::serialize)
+    }
+
+    /// Like `to_svg()`, but every `<text>` element gets written as a
+    /// `<path>` with the outlines of its glyphs, so that the result
+    /// renders the same without the fonts.  The path keeps the text's
+    /// id, transform and style.
+    pub fn to_svg_with_text_as_paths (&self) -> String {
+        self.root ().map_or (String::new (), |root| {
+            serializer::serialize_with_text_outlines (root, |node| handle::get_text_outlines (self.raw, node))
+        })
+    }
+
+    /// Returns the outlines of the glyphs of the `<text>` element with
+    /// the given id, which must be a fragment like `"#foo"`.  The path is
+    /// in the text's user space, with all of the positioning from `x`,
+    /// `y`, `dx`, `dy`, `rotate`, `textLength`, `<tspan>`, `<tref>` and
+    /// `<textPath>` applied.  Returns `None` if there is no such element
+    /// or it is not a `<text>`.
+    pub fn text_outlines (&self, id: &str) -> Option<RsvgPathBuilder> {
+        match handle::lookup_node (self.raw, id) {
+            Some (ref node) if node.get_type () == NodeType::Text => handle::get_text_outlines (self.raw, node),
+            _ => None
+        }
+    }
+
+    /// Returns whether the document has an element with the given id,
+    /// which must be a fragment like `"#foo"`.
+    pub fn has_element_with_id (&self, id: &str) -> bool {
+        unsafe { from_glib (rsvg_handle_has_sub (self.raw, id.to_glib_none ().0)) }
+    }
+
+    /// Returns the area that the `<clipPath>` with the given id covers,
+    /// as a path in the user space of the elements that it clips.  The
+    /// id must be a fragment like `"#foo"`.
+    ///
+    /// `bbox` is the bounding box of the clipped element, which is only
+    /// needed if the clipping path has `clipPathUnits="objectBoundingBox"`.
+    /// Curves get flattened, and text in the clipping path is ignored.
+    /// Returns `None` if there is no such clipping path, or if it needs
+    /// a bounding box and none was given.
+    pub fn clip_path_geometry (&self, id: &str, bbox: Option<&cairo::Rectangle>) -> Option<RsvgPathBuilder> {
+        let raw_bbox = bbox.map_or (ptr::null (), |r| r as *const cairo::Rectangle);
+
+        let raw_builder = unsafe {
+            rsvg_handle_get_clip_path_geometry (self.raw, id.to_glib_none ().0, raw_bbox)
+        };
+
+        if raw_builder.is_null () {
+            None
+        } else {
+            Some (*unsafe { Box::from_raw (raw_builder) })
+        }
+    }
+}
+
+impl Drop for SvgHandle {
+    fn drop (&mut self) {
+        unsafe { g_object_unref (self.raw as *mut libc::c_void); }
+    }
+}
+
+/// Renders an `SvgHandle` to cairo contexts.
+pub struct Renderer<'a> {
+    handle: &'a SvgHandle
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new (handle: &'a SvgHandle) -> Renderer<'a> {
+        Renderer {
+            handle: handle
+        }
+    }
+
+    /// Renders the whole document with the current transformation of `cr`.
+    pub fn render (&self, cr: &cairo::Context) -> Result<(), RenderingError> {
+        self.render_sub (cr, None)
+    }
+
+    /// Renders a single element and its children, which must be given as
+    /// a fragment like `"#foo"`.  The element is drawn at the position it
+    /// has in the whole document.
+    pub fn render_element (&self, cr: &cairo::Context, id: &str) -> Result<(), RenderingError> {
+        if !self.handle.has_element_with_id (id) {
+            return Err (RenderingError::InvalidId (id.to_string ()));
+        }
+
+        self.render_sub (cr, Some (id))
+    }
+
+    fn render_sub (&self, cr: &cairo::Context, id: Option<&str>) -> Result<(), RenderingError> {
+        check_status (cr.status ())?;
+
+        unsafe { rsvg_handle_render_cairo_sub (self.handle.raw, cr.to_glib_none ().0, id.to_glib_none ().0); }
+
+        check_status (cr.status ())
+    }
+}
+
+fn check_status (status: cairo::Status) -> Result<(), RenderingError> {
+    if status == cairo::Status::Success {
+        Ok (())
+    } else {
+        Err (RenderingError::Cairo (status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loader_sets_flags () {
+        assert_eq! (Loader::new ().flags (), 0);
+        assert_eq! (Loader::new ().unlimited_size (true).flags (), RSVG_HANDLE_FLAG_UNLIMITED);
+        assert_eq! (Loader::new ().unlimited_size (true).keep_image_data (true).flags (),
+                    RSVG_HANDLE_FLAG_UNLIMITED | RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA);
+        assert_eq! (Loader::new ().strict (true).flags (), RSVG_HANDLE_FLAG_STRICT);
+    }
+
+    #[test]
+    fn checks_cairo_status () {
+        assert_eq! (check_status (cairo::Status::Success), Ok (()));
+        assert_eq! (check_status (cairo::Status::NoMemory), Err (RenderingError::Cairo (cairo::Status::NoMemory)));
+    }
+
+    #[test]
+    fn formats_errors () {
+        let e = LoadingError::from (io::Error::new (io::ErrorKind::NotFound, "no such file"));
+        assert_eq! (e.to_string (), "could not read SVG data: no such file");
+
+        assert_eq! (RenderingError::InvalidId ("#foo".to_string ()).to_string (), "no element with id \"#foo\"");
+    }
+}
@@ -3,10 +3,12 @@
 //! This module handles preserveAspectRatio values [per the SVG specification][spec].
 //! We have an [`AspectRatio`] struct which encapsulates such a value.
 //!
-//! [`AspectRatio`] implements `FromStr`, so it can be parsed easily:
+//! [`AspectRatio`] implements `Parse`, so it can be parsed easily:
 //!
 //! ```
-//! assert_eq! (AspectRatio::from_str ("xMidYMid"),
+//! use rsvg_internals::{AspectRatio, Align, AlignMode, FitMode, Parse};
+//!
+//! assert_eq! (AspectRatio::parse ("xMidYMid", ()),
 //!             Ok (AspectRatio { defer: false,
 //!                               align: Align::Aligned { align: AlignMode::XmidYmid,
 //!                                                       fit: FitMode::Meet } }));
@@ -1,9 +1,15 @@
 use ::cairo;
 use ::glib_sys;
 
+use std::f64;
+
 use glib::translate::*;
 use cairo::MatrixTrait;
 
+use path_builder::*;
+use path_geometry::PathSegment;
+use util::DBL_EPSILON;
+
 /* Keep this in sync with ../../rsvg-private.h:RsvgBbox */
 #[repr(C)]
 pub struct RsvgBbox {
@@ -162,3 +168,726 @@ pub extern fn rsvg_bbox_clip (raw_dst: *mut RsvgBbox, raw_src: *const RsvgBbox)
     dst.rect.y = ymin;
     dst.rect.height = ymax - ymin;
 }
+
+/* Bounding boxes for the geometry in an RsvgPathBuilder.
+ *
+ * cairo_fill_extents() and cairo_stroke_extents() flatten the path
+ * with the current tolerance and transformation, so their results
+ * depend on how the path gets rendered.  The functions below work
+ * directly on the path's segments instead: the fill bbox solves for
+ * the extrema of each Bézier curve analytically, so it is exact, and
+ * the stroke bbox takes into account the line width, joins, miter
+ * limit and caps, but finds the extrema of curved strokes numerically.
+ */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeParams {
+    pub width:       f64,
+    pub line_cap:    cairo::LineCap,
+    pub line_join:   cairo::LineJoin,
+    pub miter_limit: f64
+}
+
+struct Extents {
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64
+}
+
+impl Extents {
+    fn new () -> Extents {
+        Extents {
+            xmin: f64::INFINITY,
+            ymin: f64::INFINITY,
+            xmax: f64::NEG_INFINITY,
+            ymax: f64::NEG_INFINITY
+        }
+    }
+
+    fn add_point (&mut self, p: (f64, f64)) {
+        self.xmin = self.xmin.min (p.0);
+        self.ymin = self.ymin.min (p.1);
+        self.xmax = self.xmax.max (p.0);
+        self.ymax = self.ymax.max (p.1);
+    }
+
+    fn to_rectangle (&self) -> Option<cairo::Rectangle> {
+        if self.xmin > self.xmax || self.ymin > self.ymax {
+            return None;
+        }
+
+        Some (cairo::Rectangle { x:      self.xmin,
+                                 y:      self.ymin,
+                                 width:  self.xmax - self.xmin,
+                                 height: self.ymax - self.ymin })
+    }
+}
+
+pub struct Subpath {
+    pub segments: Vec<PathSegment>,
+    pub closed:   bool
+}
+
+/* Splits the path into subpaths.  A subpath is started by a moveto,
+ * or implicitly by a command that follows a closepath.  Subpaths that
+ * consist only of a moveto have no geometry, so they are dropped.
+ */
+pub fn path_builder_to_subpaths (builder: &RsvgPathBuilder) -> Vec<Subpath> {
+    let mut subpaths = Vec::new ();
+    let mut segments = Vec::new ();
+
+    let mut cur = (0.0, 0.0);
+    let mut subpath_start = (0.0, 0.0);
+
+    for command in builder.get_path_commands () {
+        match *command {
+            PathCommand::MoveTo (x, y) => {
+                if !segments.is_empty () {
+                    subpaths.push (Subpath { segments: segments, closed: false });
+                    segments = Vec::new ();
+                }
+
+                cur = (x, y);
+                subpath_start = cur;
+            },
+
+            PathCommand::LineTo (x, y) => {
+                segments.push (PathSegment::Line (cur, (x, y)));
+                cur = (x, y);
+            },
+
+            PathCommand::CurveTo (p2, p3, p4) => {
+                segments.push (PathSegment::Curve (cur, p2, p3, p4));
+                cur = p4;
+            },
+
+            PathCommand::ClosePath => {
+                segments.push (PathSegment::Line (cur, subpath_start));
+                subpaths.push (Subpath { segments: segments, closed: true });
+                segments = Vec::new ();
+                cur = subpath_start;
+            }
+        }
+    }
+
+    if !segments.is_empty () {
+        subpaths.push (Subpath { segments: segments, closed: false });
+    }
+
+    subpaths
+}
+
+// Real roots of a*t^2 + b*t + c = 0
+fn solve_quadratic (a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs () < DBL_EPSILON {
+        if b.abs () < DBL_EPSILON {
+            return Vec::new ();
+        }
+
+        return vec! [-c / b];
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant < 0.0 {
+        Vec::new ()
+    } else if discriminant == 0.0 {
+        vec! [-b / (2.0 * a)]
+    } else {
+        let sqrt_d = discriminant.sqrt ();
+        vec! [(-b + sqrt_d) / (2.0 * a), (-b - sqrt_d) / (2.0 * a)]
+    }
+}
+
+/* Parameters in (0, 1) where one of the coordinates of a cubic Bézier
+ * reaches a local extremum, i.e. where the derivative of that
+ * coordinate, which is a quadratic polynomial, is zero.
+ */
+fn cubic_extrema (p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), p4: (f64, f64)) -> Vec<f64> {
+    let mut result = Vec::new ();
+
+    for &(c1, c2, c3, c4) in &[(p1.0, p2.0, p3.0, p4.0), (p1.1, p2.1, p3.1, p4.1)] {
+        let a = 3.0 * (-c1 + 3.0 * c2 - 3.0 * c3 + c4);
+        let b = 6.0 * (c1 - 2.0 * c2 + c3);
+        let c = 3.0 * (c2 - c1);
+
+        result.extend (solve_quadratic (a, b, c).into_iter ().filter (|&t| t > 0.0 && t < 1.0));
+    }
+
+    result
+}
+
+fn add_segment_to_fill_extents (extents: &mut Extents, segment: &PathSegment) {
+    extents.add_point (segment.start ());
+    extents.add_point (segment.end ());
+
+    if let PathSegment::Curve (p1, p2, p3, p4) = *segment {
+        for t in cubic_extrema (p1, p2, p3, p4) {
+            extents.add_point (segment.point_at (t));
+        }
+    }
+}
+
+/// Returns the exact bounding box of the area covered by filling the path,
+/// or `None` if the path has no segments.
+pub fn path_builder_fill_bbox (builder: &RsvgPathBuilder) -> Option<cairo::Rectangle> {
+    let mut extents = Extents::new ();
+
+    for subpath in path_builder_to_subpaths (builder) {
+        for segment in &subpath.segments {
+            add_segment_to_fill_extents (&mut extents, segment);
+        }
+    }
+
+    extents.to_rectangle ()
+}
+
+// Normal that points to the left of a unit direction vector
+pub fn left_normal (v: (f64, f64)) -> (f64, f64) {
+    (-v.1, v.0)
+}
+
+pub fn offset_point (p: (f64, f64), v: (f64, f64), distance: f64) -> (f64, f64) {
+    (p.0 + v.0 * distance, p.1 + v.1 * distance)
+}
+
+pub fn cross (a: (f64, f64), b: (f64, f64)) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+pub fn dot (a: (f64, f64), b: (f64, f64)) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+const AXIS_DIRECTIONS: [(f64, f64); 4] = [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)];
+
+const OFFSET_CURVE_SAMPLES: usize = 64;
+const GOLDEN_SECTION_ITERATIONS: usize = 64;
+
+// Point on the curve displaced by `distance` along the curve's left normal at t
+fn curve_offset_point (segment: &PathSegment, t: f64, distance: f64) -> (f64, f64) {
+    let p = segment.point_at (t);
+
+    match segment.tangent_at (t) {
+        Some (v) => offset_point (p, left_normal (v), distance),
+        None     => p
+    }
+}
+
+// Finds the t in [a, b] that maximizes f, assuming f is unimodal in that interval
+fn golden_section_max<F> (f: F, mut a: f64, mut b: f64) -> f64
+    where F: Fn (f64) -> f64
+{
+    let ratio = (5.0f64.sqrt () - 1.0) / 2.0;
+
+    let mut c = b - (b - a) * ratio;
+    let mut d = a + (b - a) * ratio;
+    let mut fc = f (c);
+    let mut fd = f (d);
+
+    for _ in 0..GOLDEN_SECTION_ITERATIONS {
+        if fc > fd {
+            b = d;
+            d = c;
+            fd = fc;
+            c = b - (b - a) * ratio;
+            fc = f (c);
+        } else {
+            a = c;
+            c = d;
+            fc = fd;
+            d = a + (b - a) * ratio;
+            fd = f (d);
+        }
+    }
+
+    (a + b) / 2.0
+}
+
+/* The area swept by a stroked segment is bounded by the two curves
+ * that are offset by half the line width on either side of the
+ * segment.  For lines these are just the segment's endpoints moved
+ * along the normal.  Offset curves of Béziers are not polynomial, so
+ * we find their extrema numerically: sample the curve to bracket each
+ * local extremum, and then refine it with a golden-section search.
+ */
+fn add_segment_to_stroke_extents (extents: &mut Extents, segment: &PathSegment, half_width: f64) {
+    match *segment {
+        PathSegment::Line (p1, p2) => {
+            if let Some (v) = segment.tangent_at (0.0) {
+                let n = left_normal (v);
+
+                for &d in &[half_width, -half_width] {
+                    extents.add_point (offset_point (p1, n, d));
+                    extents.add_point (offset_point (p2, n, d));
+                }
+            }
+        },
+
+        PathSegment::Curve (..) => {
+            for &d in &[half_width, -half_width] {
+                extents.add_point (curve_offset_point (segment, 0.0, d));
+                extents.add_point (curve_offset_point (segment, 1.0, d));
+
+                let samples: Vec<(f64, f64)> = (0..OFFSET_CURVE_SAMPLES + 1)
+                    .map (|i| curve_offset_point (segment, i as f64 / OFFSET_CURVE_SAMPLES as f64, d))
+                    .collect ();
+
+                for i in 1..OFFSET_CURVE_SAMPLES {
+                    let a = (i - 1) as f64 / OFFSET_CURVE_SAMPLES as f64;
+                    let b = (i + 1) as f64 / OFFSET_CURVE_SAMPLES as f64;
+
+                    for &axis in &AXIS_DIRECTIONS {
+                        let project = |p: (f64, f64)| dot (p, axis);
+
+                        let prev = project (samples[i - 1]);
+                        let here = project (samples[i]);
+                        let next = project (samples[i + 1]);
+
+                        if here >= prev && here >= next {
+                            let t = golden_section_max (|t| project (curve_offset_point (segment, t, d)), a, b);
+                            extents.add_point (curve_offset_point (segment, t, d));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/* Adds the extreme points of a circular arc of less than 180 degrees,
+ * which goes from the unit vector `from` to the unit vector `to`.
+ */
+fn add_arc_to_extents (extents: &mut Extents,
+                       center: (f64, f64),
+                       radius: f64,
+                       from: (f64, f64),
+                       to: (f64, f64)) {
+    extents.add_point (offset_point (center, from, radius));
+    extents.add_point (offset_point (center, to, radius));
+
+    let sign = if cross (from, to) >= 0.0 { 1.0 } else { -1.0 };
+
+    for &axis in &AXIS_DIRECTIONS {
+        if sign * cross (from, axis) >= 0.0 && sign * cross (axis, to) >= 0.0 && dot (axis, from) + dot (axis, to) > 0.0 {
+            extents.add_point (offset_point (center, axis, radius));
+        }
+    }
+}
+
+// Adds a cap at point p, for a segment whose direction at p, pointing away from the path, is v
+fn add_cap_to_extents (extents: &mut Extents, p: (f64, f64), v: (f64, f64), params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+    let n = left_normal (v);
+
+    match params.line_cap {
+        cairo::LineCap::Butt => (),
+
+        cairo::LineCap::Round => {
+            extents.add_point (offset_point (p, n, half_width));
+            extents.add_point (offset_point (p, n, -half_width));
+
+            for &axis in &AXIS_DIRECTIONS {
+                if dot (axis, v) >= 0.0 {
+                    extents.add_point (offset_point (p, axis, half_width));
+                }
+            }
+        },
+
+        cairo::LineCap::Square => {
+            let q = offset_point (p, v, half_width);
+
+            extents.add_point (offset_point (q, n, half_width));
+            extents.add_point (offset_point (q, n, -half_width));
+        }
+    }
+}
+
+/* Adds the join at point p between a segment that arrives with
+ * direction v_in and one that leaves with direction v_out.  The inner
+ * side of the join is always covered by the segments themselves, and
+ * so is the bevel between the outer corners; only round joins and
+ * miters that don't exceed the miter limit stick out from there.
+ */
+fn add_join_to_extents (extents: &mut Extents,
+                        p: (f64, f64),
+                        v_in: (f64, f64),
+                        v_out: (f64, f64),
+                        params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+    let turn = cross (v_in, v_out);
+    let cos_turn = dot (v_in, v_out);
+
+    if turn.abs () < DBL_EPSILON {
+        if cos_turn > 0.0 {
+            // The path goes straight through p
+            return;
+        }
+
+        // The path turns back on itself; cairo never draws a miter here
+        if params.line_join == cairo::LineJoin::Round {
+            let n = left_normal (v_in);
+
+            extents.add_point (offset_point (p, n, half_width));
+            extents.add_point (offset_point (p, n, -half_width));
+
+            for &axis in &AXIS_DIRECTIONS {
+                if dot (axis, v_in) >= 0.0 {
+                    extents.add_point (offset_point (p, axis, half_width));
+                }
+            }
+        }
+
+        return;
+    }
+
+    // The outer side of the join is opposite to the direction of the turn
+    let side = if turn > 0.0 { -1.0 } else { 1.0 };
+    let n_in = left_normal (v_in);
+    let n_out = left_normal (v_out);
+    let n_in = (n_in.0 * side, n_in.1 * side);
+    let n_out = (n_out.0 * side, n_out.1 * side);
+
+    match params.line_join {
+        cairo::LineJoin::Bevel => (),
+
+        cairo::LineJoin::Round => {
+            add_arc_to_extents (extents, p, half_width, n_in, n_out);
+        },
+
+        cairo::LineJoin::Miter => {
+            // sin (theta / 2), where theta is the angle between the two segments
+            let sin_half_theta = ((1.0 + cos_turn) / 2.0).sqrt ();
+
+            if sin_half_theta > DBL_EPSILON && 1.0 / sin_half_theta <= params.miter_limit {
+                let bisector = (n_in.0 + n_out.0, n_in.1 + n_out.1);
+                let len = bisector.0.hypot (bisector.1);
+                let bisector = (bisector.0 / len, bisector.1 / len);
+
+                extents.add_point (offset_point (p, bisector, half_width / sin_half_theta));
+            }
+        }
+    }
+}
+
+fn add_subpath_to_stroke_extents (extents: &mut Extents, subpath: &Subpath, params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+
+    // Zero-length segments are not drawn, and they don't take part in joins
+    let segments: Vec<&PathSegment> = subpath.segments
+        .iter ()
+        .filter (|s| s.tangent_at (0.0).is_some ())
+        .collect ();
+
+    if segments.is_empty () {
+        /* A subpath of zero length gets drawn as a dot if it has
+         * round caps, or as an axis-aligned square if it has square
+         * caps, just like cairo does.
+         */
+        let p = subpath.segments[0].start ();
+
+        match params.line_cap {
+            cairo::LineCap::Butt => (),
+
+            cairo::LineCap::Round | cairo::LineCap::Square => {
+                extents.add_point ((p.0 - half_width, p.1 - half_width));
+                extents.add_point ((p.0 + half_width, p.1 + half_width));
+            }
+        }
+
+        return;
+    }
+
+    for segment in &segments {
+        add_segment_to_stroke_extents (extents, segment, half_width);
+    }
+
+    for pair in segments.windows (2) {
+        add_join_to_extents (extents,
+                             pair[0].end (),
+                             pair[0].tangent_at (1.0).unwrap (),
+                             pair[1].tangent_at (0.0).unwrap (),
+                             params);
+    }
+
+    let first = segments[0];
+    let last = segments[segments.len () - 1];
+
+    if subpath.closed {
+        add_join_to_extents (extents,
+                             last.end (),
+                             last.tangent_at (1.0).unwrap (),
+                             first.tangent_at (0.0).unwrap (),
+                             params);
+    } else {
+        let v_start = first.tangent_at (0.0).unwrap ();
+
+        add_cap_to_extents (extents, first.start (), (-v_start.0, -v_start.1), params);
+        add_cap_to_extents (extents, last.end (), last.tangent_at (1.0).unwrap (), params);
+    }
+}
+
+/// Returns the bounding box of the area covered by stroking the path with
+/// the specified parameters, or `None` if the stroke would not cover
+/// anything.  Dashing is not taken into account.
+///
+/// The box is exact for lines, caps and joins.  For curves it is
+/// approximate: the extrema of their offset curves are found by sampling
+/// and refining numerically, so two extrema that are closer together than
+/// the sampling interval may be merged into one.
+pub fn path_builder_stroke_bbox (builder: &RsvgPathBuilder, params: &StrokeParams) -> Option<cairo::Rectangle> {
+    if params.width <= 0.0 {
+        return None;
+    }
+
+    let mut extents = Extents::new ();
+
+    for subpath in path_builder_to_subpaths (builder) {
+        add_subpath_to_stroke_extents (&mut extents, &subpath, params);
+    }
+
+    extents.to_rectangle ()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rect_approx_eq (rect: cairo::Rectangle, x: f64, y: f64, width: f64, height: f64) {
+        let tolerance = 1e-6;
+
+        assert! ((rect.x - x).abs () < tolerance
+                 && (rect.y - y).abs () < tolerance
+                 && (rect.width - width).abs () < tolerance
+                 && (rect.height - height).abs () < tolerance,
+                 "got ({}, {}, {}, {}), expected ({}, {}, {}, {})",
+                 rect.x, rect.y, rect.width, rect.height, x, y, width, height);
+    }
+
+    fn stroke (width: f64, line_cap: cairo::LineCap, line_join: cairo::LineJoin, miter_limit: f64) -> StrokeParams {
+        StrokeParams {
+            width:       width,
+            line_cap:    line_cap,
+            line_join:   line_join,
+            miter_limit: miter_limit
+        }
+    }
+
+    fn triangle () -> RsvgPathBuilder {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 0.0);
+        builder.line_to (0.0, 10.0);
+        builder.close_path ();
+
+        builder
+    }
+
+    #[test]
+    fn empty_path_has_no_bbox () {
+        let builder = RsvgPathBuilder::new ();
+
+        assert! (path_builder_fill_bbox (&builder).is_none ());
+        assert! (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Round, cairo::LineJoin::Round, 4.0)).is_none ());
+    }
+
+    #[test]
+    fn lone_moveto_has_no_bbox () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (10.0, 10.0);
+
+        assert! (path_builder_fill_bbox (&builder).is_none ());
+    }
+
+    #[test]
+    fn fill_bbox_of_polygon () {
+        assert_rect_approx_eq (path_builder_fill_bbox (&triangle ()).unwrap (), 0.0, 0.0, 10.0, 10.0);
+    }
+
+    #[test]
+    fn fill_bbox_excludes_control_points () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (0.0, 10.0, 10.0, 10.0, 10.0, 0.0);
+
+        // The curve's apex is at t = 0.5, at 3/4 of the height of the control points
+        assert_rect_approx_eq (path_builder_fill_bbox (&builder).unwrap (), 0.0, 0.0, 10.0, 7.5);
+    }
+
+    #[test]
+    fn fill_bbox_includes_extrema_in_both_axes () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (-10.0, 10.0, 20.0, 20.0, 10.0, 0.0);
+
+        let rect = path_builder_fill_bbox (&builder).unwrap ();
+
+        assert! (rect.x < 0.0);
+        assert! (rect.x + rect.width > 10.0);
+        assert! (rect.height > 0.0 && rect.height < 20.0);
+
+        // Every point on the curve is inside the bbox, and the bbox touches the curve
+        let segment = PathSegment::Curve ((0.0, 0.0), (-10.0, 10.0), (20.0, 20.0), (10.0, 0.0));
+        let mut xmin = f64::INFINITY;
+        let mut xmax = f64::NEG_INFINITY;
+        let mut ymax = f64::NEG_INFINITY;
+
+        for i in 0..10001 {
+            let (x, y) = segment.point_at (i as f64 / 10000.0);
+            xmin = xmin.min (x);
+            xmax = xmax.max (x);
+            ymax = ymax.max (y);
+        }
+
+        assert! ((rect.x - xmin).abs () < 1e-4);
+        assert! ((rect.x + rect.width - xmax).abs () < 1e-4);
+        assert! ((rect.y + rect.height - ymax).abs () < 1e-4);
+    }
+
+    #[test]
+    fn fill_bbox_of_circle () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (20.0, 10.0);
+        builder.arc (20.0, 10.0, 10.0, 10.0, 0.0, LargeArc (false), Sweep::Positive, 0.0, 10.0);
+        builder.arc (0.0, 10.0, 10.0, 10.0, 0.0, LargeArc (false), Sweep::Positive, 20.0, 10.0);
+        builder.close_path ();
+
+        let rect = path_builder_fill_bbox (&builder).unwrap ();
+
+        assert! ((rect.x - 0.0).abs () < 0.01);
+        assert! ((rect.y - 0.0).abs () < 0.01);
+        assert! ((rect.width - 20.0).abs () < 0.01);
+        assert! ((rect.height - 20.0).abs () < 0.01);
+    }
+
+    #[test]
+    fn zero_width_stroke_has_no_bbox () {
+        assert! (path_builder_stroke_bbox (&triangle (), &stroke (0.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, 4.0)).is_none ());
+    }
+
+    #[test]
+    fn stroke_bbox_of_line_with_caps () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 0.0);
+
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               0.0, -1.0, 10.0, 2.0);
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Square, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 2.0);
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Round, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 2.0);
+    }
+
+    #[test]
+    fn stroke_bbox_of_diagonal_line_with_round_caps () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 10.0);
+
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Round, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 12.0);
+
+        // Butt caps leave the corners of the bbox uncovered
+        let h = 1.0 / 2.0f64.sqrt ();
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               -h, -h, 10.0 + 2.0 * h, 10.0 + 2.0 * h);
+    }
+
+    #[test]
+    fn stroke_bbox_of_zero_length_subpath () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (5.0, 5.0);
+        builder.line_to (5.0, 5.0);
+
+        assert! (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, 4.0)).is_none ());
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Round, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               4.0, 4.0, 2.0, 2.0);
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Square, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               4.0, 4.0, 2.0, 2.0);
+    }
+
+    #[test]
+    fn stroke_bbox_of_rectangle_with_joins () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 0.0);
+        builder.line_to (10.0, 10.0);
+        builder.line_to (0.0, 10.0);
+        builder.close_path ();
+
+        // Miters at right angles are sqrt(2) times the line width, well within the default limit
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 12.0);
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Round, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 12.0);
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Bevel, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 12.0);
+    }
+
+    #[test]
+    fn stroke_bbox_of_closed_subpath_has_no_caps () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 0.0);
+        builder.close_path ();
+
+        // The path turns back on itself at both ends, so the joins are bevels
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Square, cairo::LineJoin::Miter, 4.0)).unwrap (),
+                               0.0, -1.0, 10.0, 2.0);
+        assert_rect_approx_eq (path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Square, cairo::LineJoin::Round, 4.0)).unwrap (),
+                               -1.0, -1.0, 12.0, 2.0);
+    }
+
+    #[test]
+    fn stroke_bbox_respects_miter_limit () {
+        // A sharp spike pointing up
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 10.0);
+        builder.line_to (1.0, 0.0);
+        builder.line_to (2.0, 10.0);
+
+        let v = (1.0 / 101.0f64.sqrt (), -10.0 / 101.0f64.sqrt ());
+        let sin_half_theta = v.0;
+        let miter_length = 1.0 / sin_half_theta;
+
+        let mitered = path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, miter_length + 1.0)).unwrap ();
+        assert! ((mitered.y - (0.0 - miter_length)).abs () < 1e-6);
+
+        // Above the limit the join is beveled, and its top is at the outer corners of the segments
+        let beveled = path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, miter_length - 1.0)).unwrap ();
+        assert! ((beveled.y - (0.0 - v.0)).abs () < 1e-6);
+
+        let rounded = path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Round, 4.0)).unwrap ();
+        assert! ((rounded.y - -1.0).abs () < 1e-6);
+    }
+
+    #[test]
+    fn stroke_bbox_of_curve_includes_offset_extrema () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (0.0, 10.0, 10.0, 10.0, 10.0, 0.0);
+
+        // The apex of the curve is horizontal, so the stroke extends half the width below it
+        let rect = path_builder_stroke_bbox (&builder, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter, 4.0)).unwrap ();
+        assert! ((rect.y + rect.height - 8.5).abs () < 1e-6);
+
+        // The ends of the curve are vertical, so butt caps don't extend above them
+        assert! ((rect.y - 0.0).abs () < 1e-6);
+        assert! ((rect.x - -1.0).abs () < 1e-6);
+        assert! ((rect.width - 12.0).abs () < 1e-6);
+    }
+}
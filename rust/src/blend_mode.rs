@@ -0,0 +1,194 @@
+use ::cairo;
+use ::cairo_sys;
+
+use error::*;
+use parsers::{Parse, ParseError};
+use properties::Property;
+
+// Blend modes from CSS Compositing and Blending, for the mix-blend-mode
+// property.  All of them but plus-darker map to cairo operators;
+// plus-darker gets composited by composite_pixel() instead.
+//
+// https://www.w3.org/TR/compositing-1/#blending
+//
+// Keep in sync with rsvg-styles.h:RsvgBlendMode
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MixBlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+    PlusDarker,
+    PlusLighter
+}
+
+impl Default for MixBlendMode {
+    fn default () -> MixBlendMode {
+        MixBlendMode::Normal
+    }
+}
+
+impl Parse for MixBlendMode {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<MixBlendMode, AttributeError> {
+        match s.trim () {
+            "normal"       => Ok (MixBlendMode::Normal),
+            "multiply"     => Ok (MixBlendMode::Multiply),
+            "screen"       => Ok (MixBlendMode::Screen),
+            "overlay"      => Ok (MixBlendMode::Overlay),
+            "darken"       => Ok (MixBlendMode::Darken),
+            "lighten"      => Ok (MixBlendMode::Lighten),
+            "color-dodge"  => Ok (MixBlendMode::ColorDodge),
+            "color-burn"   => Ok (MixBlendMode::ColorBurn),
+            "hard-light"   => Ok (MixBlendMode::HardLight),
+            "soft-light"   => Ok (MixBlendMode::SoftLight),
+            "difference"   => Ok (MixBlendMode::Difference),
+            "exclusion"    => Ok (MixBlendMode::Exclusion),
+            "hue"          => Ok (MixBlendMode::Hue),
+            "saturation"   => Ok (MixBlendMode::Saturation),
+            "color"        => Ok (MixBlendMode::Color),
+            "luminosity"   => Ok (MixBlendMode::Luminosity),
+            "plus-darker"  => Ok (MixBlendMode::PlusDarker),
+            "plus-lighter" => Ok (MixBlendMode::PlusLighter),
+            _              => Err (AttributeError::Parse (ParseError::new ("invalid blend mode")))
+        }
+    }
+}
+
+impl Property for MixBlendMode {
+    fn inherits_automatically () -> bool {
+        false
+    }
+}
+
+/// Composites a premultiplied RGBA `source` pixel over a `backdrop` pixel
+/// with the specified blend mode.  Only plus-darker is implemented here,
+/// since cairo has operators for all the other modes; the rest get
+/// composited as normal.
+pub fn composite_pixel (mode: MixBlendMode, source: [u8; 4], backdrop: [u8; 4]) -> [u8; 4] {
+    let qs = source[3] as f64 / 255.0;
+    let qb = backdrop[3] as f64 / 255.0;
+
+    let ps = [source[0] as f64 / 255.0, source[1] as f64 / 255.0, source[2] as f64 / 255.0];
+    let pb = [backdrop[0] as f64 / 255.0, backdrop[1] as f64 / 255.0, backdrop[2] as f64 / 255.0];
+
+    let (qo, po) = match mode {
+        MixBlendMode::PlusDarker => {
+            let qo = (qs + qb).min (1.0);
+            let darker = |s: f64, b: f64| (qo - (qs - s) - (qb - b)).max (0.0);
+
+            (qo, [darker (ps[0], pb[0]), darker (ps[1], pb[1]), darker (ps[2], pb[2])])
+        },
+
+        _ => {
+            let qo = qs + qb * (1.0 - qs);
+            let over = |i: usize| ps[i] + pb[i] * (1.0 - qs);
+
+            (qo, [over (0), over (1), over (2)])
+        }
+    };
+
+    let to_u8 = |v: f64| (v.max (0.0).min (qo) * 255.0).round () as u8;
+
+    [to_u8 (po[0]), to_u8 (po[1]), to_u8 (po[2]), (qo * 255.0).round () as u8]
+}
+
+/// Composites `raw_source` onto `raw_backdrop` in place with the specified
+/// blend mode.  This is the fallback for the blend modes that cairo doesn't
+/// have an operator for.  Both surfaces must be ARGB32 image surfaces of the
+/// same size.
+#[no_mangle]
+pub extern fn rsvg_blend_mode_composite (raw_backdrop: *mut cairo_sys::cairo_surface_t,
+                                         raw_source:   *mut cairo_sys::cairo_surface_t,
+                                         mode:         MixBlendMode) {
+    assert! (!raw_backdrop.is_null ());
+    assert! (!raw_source.is_null ());
+
+    unsafe {
+        assert! (cairo_sys::cairo_image_surface_get_format (raw_backdrop) == cairo::Format::ARgb32);
+        assert! (cairo_sys::cairo_image_surface_get_format (raw_source) == cairo::Format::ARgb32);
+
+        let width = cairo_sys::cairo_image_surface_get_width (raw_backdrop);
+        let height = cairo_sys::cairo_image_surface_get_height (raw_backdrop);
+
+        assert! (cairo_sys::cairo_image_surface_get_width (raw_source) == width);
+        assert! (cairo_sys::cairo_image_surface_get_height (raw_source) == height);
+
+        cairo_sys::cairo_surface_flush (raw_backdrop);
+        cairo_sys::cairo_surface_flush (raw_source);
+
+        let backdrop_stride = cairo_sys::cairo_image_surface_get_stride (raw_backdrop) as isize;
+        let source_stride = cairo_sys::cairo_image_surface_get_stride (raw_source) as isize;
+        let backdrop_pixels = cairo_sys::cairo_image_surface_get_data (raw_backdrop);
+        let source_pixels = cairo_sys::cairo_image_surface_get_data (raw_source);
+
+        if backdrop_pixels.is_null () || source_pixels.is_null () {
+            return;
+        }
+
+        let to_rgba = |argb: u32| [(argb >> 16) as u8, (argb >> 8) as u8, argb as u8, (argb >> 24) as u8];
+
+        for y in 0..height as isize {
+            let backdrop_row = backdrop_pixels.offset (y * backdrop_stride) as *mut u32;
+            let source_row = source_pixels.offset (y * source_stride) as *const u32;
+
+            for x in 0..width as isize {
+                let source = *source_row.offset (x);
+
+                // Fully transparent source pixels leave the backdrop alone
+                if source >> 24 == 0 {
+                    continue;
+                }
+
+                let p = composite_pixel (mode, to_rgba (source), to_rgba (*backdrop_row.offset (x)));
+
+                *backdrop_row.offset (x) = (p[3] as u32) << 24 | (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32;
+            }
+        }
+
+        cairo_sys::cairo_surface_mark_dirty (raw_backdrop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_blend_modes () {
+        assert_eq! (MixBlendMode::parse ("multiply", ()), Ok (MixBlendMode::Multiply));
+        assert_eq! (MixBlendMode::parse (" luminosity ", ()), Ok (MixBlendMode::Luminosity));
+        assert_eq! (MixBlendMode::parse ("plus-darker", ()), Ok (MixBlendMode::PlusDarker));
+        assert! (MixBlendMode::parse ("src-over", ()).is_err ());
+    }
+
+    #[test]
+    fn composites_over_transparent_backdrop () {
+        let source = [0, 64, 0, 128];
+
+        assert_eq! (composite_pixel (MixBlendMode::PlusDarker, source, [0; 4]), source);
+    }
+
+    #[test]
+    fn composites_plus_darker () {
+        let a = [200, 100, 50, 255];
+        let b = [100, 100, 100, 255];
+
+        assert_eq! (composite_pixel (MixBlendMode::PlusDarker, a, b), [45, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,111 @@
+use ::libc;
+
+use std::cell::Cell;
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use node::*;
+use parsers::{Parse, ParseError};
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+/// The coordinate system for the contents of a `<clipPath>`, from its
+/// `clipPathUnits` attribute.
+// Keep in sync with rsvg-private.h:RsvgCoordUnits
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClipPathUnits {
+    ObjectBoundingBox,
+    UserSpaceOnUse
+}
+
+impl Parse for ClipPathUnits {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<ClipPathUnits, AttributeError> {
+        match s {
+            "userSpaceOnUse"    => Ok (ClipPathUnits::UserSpaceOnUse),
+            "objectBoundingBox" => Ok (ClipPathUnits::ObjectBoundingBox),
+            _                   => Err (AttributeError::Parse (ParseError::new ("expected 'userSpaceOnUse' or 'objectBoundingBox'")))
+        }
+    }
+}
+
+impl Default for ClipPathUnits {
+    fn default () -> ClipPathUnits {
+        ClipPathUnits::UserSpaceOnUse
+    }
+}
+
+pub struct NodeClipPath {
+    units: Cell<ClipPathUnits>
+}
+
+impl NodeClipPath {
+    fn new () -> NodeClipPath {
+        NodeClipPath {
+            units: Cell::new (ClipPathUnits::default ())
+        }
+    }
+
+    pub fn get_units (&self) -> ClipPathUnits {
+        self.units.get ()
+    }
+}
+
+impl NodeTrait for NodeClipPath {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.units.set (property_bag::parse_or_default (pbag, "clipPathUnits", (), None)?);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; clip paths get evaluated specially in rsvg-cairo-clip.c
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_clip_path_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::ClipPath,
+                    raw_parent,
+                    Box::new (NodeClipPath::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_clip_path_get_units (raw_node: *const RsvgNode) -> ClipPathUnits {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    let mut units = ClipPathUnits::default ();
+
+    node.with_impl (|clip_path: &NodeClipPath| {
+        units = clip_path.get_units ();
+    });
+
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units () {
+        assert_eq! (ClipPathUnits::parse ("userSpaceOnUse", ()), Ok (ClipPathUnits::UserSpaceOnUse));
+        assert_eq! (ClipPathUnits::parse ("objectBoundingBox", ()), Ok (ClipPathUnits::ObjectBoundingBox));
+        assert! (ClipPathUnits::parse ("", ()).is_err ());
+        assert! (ClipPathUnits::parse ("foo", ()).is_err ());
+    }
+
+    #[test]
+    fn default_units_are_user_space () {
+        assert_eq! (ClipPathUnits::default (), ClipPathUnits::UserSpaceOnUse);
+    }
+}
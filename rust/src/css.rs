@@ -0,0 +1,1429 @@
+use ::cssparser::{
+    self,
+    AtRuleParser,
+    AtRuleType,
+    BasicParseError,
+    CowRcStr,
+    DeclarationListParser,
+    DeclarationParser,
+    Parser,
+    ParserInput,
+    QualifiedRuleParser,
+    RuleListParser,
+    Token
+};
+use ::glib::translate::*;
+use ::glib_sys;
+use ::libc;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::slice;
+
+use error::NodeError;
+use handle::RsvgHandle;
+use node::*;
+use parsers::ParseError;
+use properties::{SpecifiedValues, is_presentation_attribute};
+use property_bag;
+use property_bag::RsvgPropertyBag;
+use state::RsvgState;
+
+// CSS style sheets, selector matching, and the cascade.
+//
+// Style sheets come from <style> elements, xml-stylesheet processing
+// instructions, and @import rules.  @font-face rules get handed to
+// rsvg-base.c as they are parsed, so that it can load the fonts.  After the document is loaded,
+// rsvg-base.c calls rsvg_stylesheet_apply() for each element, which
+// figures out the declarations that apply to it, stores the resulting
+// SpecifiedValues in the node, and hands the declarations to
+// rsvg_parse_style_pair() in cascade order:
+//
+//   1. presentation attributes (rsvg_parse_style_pairs(), in C)
+//   2. normal declarations from the style sheets, by specificity and then by source order
+//   3. normal declarations from the style attribute
+//   4. animated values of properties, from <set>, <animate> and <animateColor>
+//   5. !important declarations from the style sheets
+//   6. !important declarations from the style attribute
+//
+// https://www.w3.org/TR/CSS2/cascade.html#cascading-order
+
+/***** Declarations *****/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name:      String,
+    pub value:     String,
+    pub important: bool
+}
+
+struct DeclParser;
+
+impl<'i> DeclarationParser<'i> for DeclParser {
+    type Declaration = Declaration;
+    type Error = ();
+
+    fn parse_value<'t> (&mut self, name: CowRcStr<'i>, input: &mut Parser<'i, 't>)
+                        -> Result<Declaration, cssparser::ParseError<'i, ()>> {
+        let start = input.position ();
+        let mut end = start;
+        let mut important = false;
+
+        loop {
+            let before = input.position ();
+
+            if input.try (|i| -> Result<(), BasicParseError<'i>> {
+                cssparser::parse_important (i)?;
+                i.expect_exhausted ()
+            }).is_ok () {
+                important = true;
+                end = before;
+                break;
+            }
+
+            let is_block = match input.next_including_whitespace_and_comments () {
+                Ok (&Token::Function (_)) |
+                Ok (&Token::ParenthesisBlock) |
+                Ok (&Token::SquareBracketBlock) |
+                Ok (&Token::CurlyBracketBlock) => true,
+
+                Ok (_) => false,
+                Err (_) => break
+            };
+
+            // Skip to the end of the block, so that the value includes it
+            if is_block {
+                let _ = input.parse_nested_block (|_| -> Result<(), cssparser::ParseError<'i, ()>> { Ok (()) });
+            }
+
+            end = input.position ();
+        }
+
+        let value = unquote_value (input.slice (start..end));
+        let value = value.trim ();
+
+        if value.is_empty () {
+            return Err (cssparser::ParseError::Custom (()));
+        }
+
+        Ok (Declaration {
+            name:      name.to_ascii_lowercase (),
+            value:     value.to_string (),
+            important: important
+        })
+    }
+}
+
+// The C parsers for property values don't know about CSS quoting, as
+// in font-family: 'Foo Bar' or fill: url('#foo'), so we replace the
+// quoted strings with their contents and keep the rest as written.
+fn unquote_value (s: &str) -> String {
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+    let mut value = String::new ();
+
+    append_unquoted (&mut parser, &mut value);
+
+    value
+}
+
+fn append_unquoted<'i, 't> (input: &mut Parser<'i, 't>, value: &mut String) {
+    loop {
+        let start = input.position ();
+
+        let token = match input.next_including_whitespace_and_comments () {
+            Ok (t) => t.clone (),
+            Err (_) => break
+        };
+
+        match token {
+            Token::QuotedString (ref s) => value.push_str (s),
+
+            Token::Function (_) |
+            Token::ParenthesisBlock |
+            Token::SquareBracketBlock |
+            Token::CurlyBracketBlock => {
+                value.push_str (input.slice_from (start));
+
+                let mut block_end = input.position ();
+
+                let _ = input.parse_nested_block (|i| -> Result<(), cssparser::ParseError<'i, ()>> {
+                    append_unquoted (i, value);
+                    block_end = i.position ();
+                    Ok (())
+                });
+
+                // The closing parenthesis or bracket, if any
+                value.push_str (input.slice_from (block_end));
+            },
+
+            _ => value.push_str (input.slice_from (start))
+        }
+    }
+}
+
+impl<'i> AtRuleParser<'i> for DeclParser {
+    type Prelude = ();
+    type AtRule = Declaration;
+    type Error = ();
+}
+
+fn parse_declaration_list<'i, 't> (input: &mut Parser<'i, 't>) -> Vec<Declaration> {
+    DeclarationListParser::new (input, DeclParser)
+        .filter_map (|d| d.ok ())
+        .collect ()
+}
+
+// Parses the contents of a style attribute, or of a rule's block
+pub fn parse_declarations (s: &str) -> Vec<Declaration> {
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+
+    parse_declaration_list (&mut parser)
+}
+
+// Like parse_declarations(), but for diagnostics it also returns errors
+// for the declarations in a style attribute that get ignored:  the ones
+// that don't parse, and the ones with invalid values for the properties
+// that we know.
+pub fn parse_style_attribute (s: &str) -> (Vec<Declaration>, Vec<NodeError>) {
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+    let mut list = DeclarationListParser::new (&mut parser, DeclParser);
+
+    let mut values = SpecifiedValues::default ();
+    let mut declarations = Vec::new ();
+    let mut errors = Vec::new ();
+
+    while let Some (result) = list.next () {
+        match result {
+            Ok (decl) => {
+                if is_presentation_attribute (&decl.name) {
+                    if let Err (e) = values.set_property (&decl.name, &decl.value) {
+                        let description = format! ("invalid value for property \"{}\": {}", decl.name, e);
+                        errors.push (NodeError::value_error ("style", &description));
+                    }
+                }
+
+                declarations.push (decl);
+            },
+
+            Err (e) => {
+                let description = format! ("invalid declaration \"{}\"", list.input.slice (e.span).trim ().trim_right_matches (';'));
+                errors.push (NodeError::parse_error ("style", ParseError::new (description)));
+            }
+        }
+    }
+
+    (declarations, errors)
+}
+
+/***** Selectors *****/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttrOperator {
+    Exists,
+    Equals,      // [a=v]
+    Includes,    // [a~=v]
+    DashMatch,   // [a|=v]
+    Prefix,      // [a^=v]
+    Suffix,      // [a$=v]
+    Substring    // [a*=v]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SimpleSelector {
+    Universal,
+    Type (String),
+    Id (String),
+    Class (String),
+    Attribute (String, AttrOperator, String),
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    FirstOfType,
+    LastOfType,
+    OnlyOfType,
+    NthChild (i32, i32),
+    NthLastChild (i32, i32),
+    NthOfType (i32, i32),
+    NthLastOfType (i32, i32),
+    Not (Vec<SimpleSelector>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,         // A B
+    Child,              // A > B
+    NextSibling,        // A + B
+    SubsequentSibling   // A ~ B
+}
+
+// (ids, classes/attributes/pseudo-classes, types)
+pub type Specificity = (u32, u32, u32);
+
+// A complex selector like "g > .foo rect".  The compound selectors are
+// stored left to right; combinators[i] goes between compounds[i] and
+// compounds[i + 1].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    compounds:   Vec<Vec<SimpleSelector>>,
+    combinators: Vec<Combinator>
+}
+
+type SelectorResult<'i, T> = Result<T, BasicParseError<'i>>;
+
+fn unexpected<'i, T> (token: Token<'i>) -> SelectorResult<'i, T> {
+    Err (BasicParseError::UnexpectedToken (token))
+}
+
+fn skip_whitespace<'i, 't> (input: &mut Parser<'i, 't>) -> bool {
+    let mut skipped = false;
+
+    loop {
+        let position = input.position ();
+
+        match input.next_including_whitespace_and_comments () {
+            Ok (&Token::WhiteSpace (_)) |
+            Ok (&Token::Comment (_)) => skipped = true,
+
+            _ => {
+                input.reset (position);
+                return skipped;
+            }
+        }
+    }
+}
+
+fn parse_attribute_selector<'i, 't> (input: &mut Parser<'i, 't>) -> SelectorResult<'i, SimpleSelector> {
+    skip_whitespace (input);
+
+    let mut name = input.expect_ident ()?.to_string ();
+
+    // Namespaced attributes like [xlink|href] are stored as "xlink:href"
+    let position = input.position ();
+    if let Ok (&Token::Delim ('|')) = input.next_including_whitespace () {
+        name = format! ("{}:{}", name, input.expect_ident ()?);
+    } else {
+        input.reset (position);
+    }
+
+    skip_whitespace (input);
+
+    let operator = match input.next () {
+        Err (BasicParseError::EndOfInput) => return Ok (SimpleSelector::Attribute (name, AttrOperator::Exists, String::new ())),
+
+        Ok (&Token::Delim ('='))     => AttrOperator::Equals,
+        Ok (&Token::IncludeMatch)    => AttrOperator::Includes,
+        Ok (&Token::DashMatch)       => AttrOperator::DashMatch,
+        Ok (&Token::PrefixMatch)     => AttrOperator::Prefix,
+        Ok (&Token::SuffixMatch)     => AttrOperator::Suffix,
+        Ok (&Token::SubstringMatch)  => AttrOperator::Substring,
+
+        Ok (t) => return unexpected (t.clone ()),
+        Err (e) => return Err (e)
+    };
+
+    let value = input.expect_ident_or_string ()?.to_string ();
+
+    input.expect_exhausted ()?;
+
+    Ok (SimpleSelector::Attribute (name, operator, value))
+}
+
+fn parse_nth_function<'i, 't> (input: &mut Parser<'i, 't>) -> SelectorResult<'i, (i32, i32)> {
+    let nth = cssparser::parse_nth (input)?;
+    input.expect_exhausted ()?;
+    Ok (nth)
+}
+
+fn parse_pseudo_class<'i, 't> (input: &mut Parser<'i, 't>) -> SelectorResult<'i, SimpleSelector> {
+    match input.next_including_whitespace ()?.clone () {
+        Token::Ident (name) => {
+            match name.to_ascii_lowercase ().as_str () {
+                "root"          => Ok (SimpleSelector::Root),
+                "empty"         => Ok (SimpleSelector::Empty),
+                "first-child"   => Ok (SimpleSelector::FirstChild),
+                "last-child"    => Ok (SimpleSelector::LastChild),
+                "only-child"    => Ok (SimpleSelector::OnlyChild),
+                "first-of-type" => Ok (SimpleSelector::FirstOfType),
+                "last-of-type"  => Ok (SimpleSelector::LastOfType),
+                "only-of-type"  => Ok (SimpleSelector::OnlyOfType),
+
+                // Dynamic pseudo-classes like :hover never match in a static
+                // rendering, so we reject them along with unknown ones.
+                _ => unexpected (Token::Ident (name.clone ()))
+            }
+        },
+
+        Token::Function (name) => {
+            let selector = match name.to_ascii_lowercase ().as_str () {
+                "nth-child"        => SimpleSelector::NthChild as fn (i32, i32) -> SimpleSelector,
+                "nth-last-child"   => SimpleSelector::NthLastChild,
+                "nth-of-type"      => SimpleSelector::NthOfType,
+                "nth-last-of-type" => SimpleSelector::NthLastOfType,
+
+                "not" => {
+                    return input.parse_nested_block (|i| {
+                        skip_whitespace (i);
+                        let compound = parse_compound_selector (i)?;
+                        skip_whitespace (i);
+                        i.expect_exhausted ()?;
+                        Ok (SimpleSelector::Not (compound))
+                    }).map_err (|e: cssparser::ParseError<()>| e.basic ());
+                },
+
+                _ => return unexpected (Token::Function (name.clone ()))
+            };
+
+            let (a, b) = input.parse_nested_block (|i| parse_nth_function (i).map_err (cssparser::ParseError::Basic))
+                .map_err (|e: cssparser::ParseError<()>| e.basic ())?;
+
+            Ok (selector (a, b))
+        },
+
+        t => unexpected (t)
+    }
+}
+
+// A sequence of simple selectors without whitespace, like "rect.foo#bar:first-child"
+fn parse_compound_selector<'i, 't> (input: &mut Parser<'i, 't>) -> SelectorResult<'i, Vec<SimpleSelector>> {
+    let mut compound = Vec::new ();
+
+    loop {
+        let position = input.position ();
+
+        let token = match input.next_including_whitespace () {
+            Ok (t) => t.clone (),
+            Err (_) => break
+        };
+
+        let simple = match token {
+            Token::Ident (ref name) if compound.is_empty () => SimpleSelector::Type (name.to_string ()),
+            Token::Delim ('*') if compound.is_empty ()      => SimpleSelector::Universal,
+            Token::IDHash (ref id)                           => SimpleSelector::Id (id.to_string ()),
+
+            Token::Delim ('.') => {
+                match input.next_including_whitespace ()?.clone () {
+                    Token::Ident (class) => SimpleSelector::Class (class.to_string ()),
+                    t => return unexpected (t)
+                }
+            },
+
+            Token::SquareBracketBlock => {
+                input.parse_nested_block (|i| parse_attribute_selector (i).map_err (cssparser::ParseError::Basic))
+                    .map_err (|e: cssparser::ParseError<()>| e.basic ())?
+            },
+
+            Token::Colon => parse_pseudo_class (input)?,
+
+            _ => {
+                input.reset (position);
+                break;
+            }
+        };
+
+        compound.push (simple);
+    }
+
+    if compound.is_empty () {
+        match input.next () {
+            Ok (t) => unexpected (t.clone ()),
+            Err (e) => Err (e)
+        }
+    } else {
+        Ok (compound)
+    }
+}
+
+fn parse_selector<'i, 't> (input: &mut Parser<'i, 't>) -> SelectorResult<'i, Selector> {
+    let mut compounds = Vec::new ();
+    let mut combinators = Vec::new ();
+
+    skip_whitespace (input);
+
+    loop {
+        compounds.push (parse_compound_selector (input)?);
+
+        let had_whitespace = skip_whitespace (input);
+        let position = input.position ();
+
+        let combinator = match input.next () {
+            Err (_) => break,
+
+            Ok (&Token::Delim ('>')) => Combinator::Child,
+            Ok (&Token::Delim ('+')) => Combinator::NextSibling,
+            Ok (&Token::Delim ('~')) => Combinator::SubsequentSibling,
+
+            Ok (t) => {
+                if had_whitespace {
+                    input.reset (position);
+                    Combinator::Descendant
+                } else {
+                    return unexpected (t.clone ());
+                }
+            }
+        };
+
+        skip_whitespace (input);
+        combinators.push (combinator);
+    }
+
+    Ok (Selector {
+        compounds:   compounds,
+        combinators: combinators
+    })
+}
+
+impl Selector {
+    pub fn specificity (&self) -> Specificity {
+        self.compounds.iter ()
+            .flat_map (|c| c.iter ())
+            .fold ((0, 0, 0), |acc, s| add_specificity (acc, simple_specificity (s)))
+    }
+
+    pub fn matches (&self, node: &RsvgNode) -> bool {
+        self.matches_from (self.compounds.len () - 1, node)
+    }
+
+    // Matches compounds[0..index + 1] right to left, starting at node
+    fn matches_from (&self, index: usize, node: &RsvgNode) -> bool {
+        if !self.compounds[index].iter ().all (|s| matches_simple (s, node)) {
+            return false;
+        }
+
+        if index == 0 {
+            return true;
+        }
+
+        match self.combinators[index - 1] {
+            Combinator::Child =>
+                node.get_parent ().map_or (false, |p| self.matches_from (index - 1, &p)),
+
+            Combinator::Descendant => {
+                let mut ancestor = node.get_parent ();
+
+                while let Some (a) = ancestor {
+                    if self.matches_from (index - 1, &a) {
+                        return true;
+                    }
+
+                    ancestor = a.get_parent ();
+                }
+
+                false
+            },
+
+            Combinator::NextSibling =>
+                previous_element_sibling (node).map_or (false, |s| self.matches_from (index - 1, &s)),
+
+            Combinator::SubsequentSibling => {
+                let mut sibling = previous_element_sibling (node);
+
+                while let Some (s) = sibling {
+                    if self.matches_from (index - 1, &s) {
+                        return true;
+                    }
+
+                    sibling = previous_element_sibling (&s);
+                }
+
+                false
+            }
+        }
+    }
+}
+
+fn add_specificity (a: Specificity, b: Specificity) -> Specificity {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn simple_specificity (simple: &SimpleSelector) -> Specificity {
+    match *simple {
+        SimpleSelector::Universal => (0, 0, 0),
+        SimpleSelector::Type (_)  => (0, 0, 1),
+        SimpleSelector::Id (_)    => (1, 0, 0),
+
+        // The negation pseudo-class itself doesn't count, but its argument does
+        SimpleSelector::Not (ref compound) =>
+            compound.iter ().fold ((0, 0, 0), |acc, s| add_specificity (acc, simple_specificity (s))),
+
+        _ => (0, 1, 0)
+    }
+}
+
+/***** Matching *****/
+
+fn is_element (node: &RsvgNode) -> bool {
+    node.get_type () != NodeType::Chars
+}
+
+// Where an element is among its siblings.  The tree doesn't change once
+// the document is loaded, so the first time a selector asks about a
+// node, we compute this for all the children of its parent at once,
+// instead of walking the siblings again for every selector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SiblingPositions {
+    child:   usize,      // index in the parent's children, including character data
+    element: (i32, i32), // 1-based, from the start and from the end, among the element siblings
+    of_type: (i32, i32)  // the same, among the siblings with the same element name
+}
+
+fn sibling_positions (node: &RsvgNode) -> SiblingPositions {
+    if let Some (positions) = node.get_sibling_positions () {
+        return positions;
+    }
+
+    match node.get_parent () {
+        Some (parent) => compute_sibling_positions (&parent),
+
+        None => node.set_sibling_positions (SiblingPositions {
+            child:   0,
+            element: (1, 1),
+            of_type: (1, 1)
+        })
+    }
+
+    node.get_sibling_positions ().unwrap ()
+}
+
+fn compute_sibling_positions (parent: &RsvgNode) {
+    let children = parent.children.borrow ();
+
+    let elements: Vec<(usize, String)> = children.iter ().enumerate ()
+        .filter (|&(_, c)| is_element (c))
+        .map (|(i, c)| (i, c.get_element_name ()))
+        .collect ();
+
+    let mut type_counts: HashMap<&str, i32> = HashMap::new ();
+
+    let type_indexes: Vec<i32> = elements.iter ()
+        .map (|&(_, ref name)| {
+            let count = type_counts.entry (name.as_str ()).or_insert (0);
+            *count += 1;
+            *count
+        })
+        .collect ();
+
+    let count = elements.len () as i32;
+
+    for (i, &(child, ref name)) in elements.iter ().enumerate () {
+        let position = i as i32 + 1;
+        let type_position = type_indexes[i];
+
+        children[child].set_sibling_positions (SiblingPositions {
+            child:   child,
+            element: (position, count - position + 1),
+            of_type: (type_position, type_counts[name.as_str ()] - type_position + 1)
+        });
+    }
+}
+
+fn previous_element_sibling (node: &RsvgNode) -> Option<RsvgNode> {
+    let parent = node.get_parent ()?;
+    let child = sibling_positions (node).child;
+    let children = parent.children.borrow ();
+
+    children[..child].iter ().rev ().find (|c| is_element (c)).cloned ()
+}
+
+// Whether a 1-based position matches an+b for some n >= 0
+fn matches_nth (a: i32, b: i32, position: i32) -> bool {
+    if a == 0 {
+        position == b
+    } else {
+        let n = position - b;
+        n % a == 0 && n / a >= 0
+    }
+}
+
+fn matches_attribute (node: &RsvgNode, name: &str, operator: AttrOperator, expected: &str) -> bool {
+    let value = match node.get_attribute (name) {
+        Some (v) => v,
+        None => return false
+    };
+
+    match operator {
+        AttrOperator::Exists    => true,
+        AttrOperator::Equals    => value == expected,
+        AttrOperator::Includes  => value.split_whitespace ().any (|v| v == expected),
+        AttrOperator::DashMatch => value == expected || value.starts_with (&format! ("{}-", expected)),
+        AttrOperator::Prefix    => !expected.is_empty () && value.starts_with (expected),
+        AttrOperator::Suffix    => !expected.is_empty () && value.ends_with (expected),
+        AttrOperator::Substring => !expected.is_empty () && value.contains (expected)
+    }
+}
+
+fn matches_simple (simple: &SimpleSelector, node: &RsvgNode) -> bool {
+    match *simple {
+        SimpleSelector::Universal      => true,
+        SimpleSelector::Type (ref t)   => node.get_element_name () == *t,
+        SimpleSelector::Id (ref id)    => node.get_attribute ("id").map_or (false, |v| v == *id),
+        SimpleSelector::Class (ref c)  => matches_attribute (node, "class", AttrOperator::Includes, c),
+
+        SimpleSelector::Attribute (ref name, operator, ref value) =>
+            matches_attribute (node, name, operator, value),
+
+        SimpleSelector::Root           => node.get_parent ().is_none (),
+        SimpleSelector::Empty          => node.children.borrow ().is_empty (),
+
+        SimpleSelector::FirstChild     => sibling_positions (node).element.0 == 1,
+        SimpleSelector::LastChild      => sibling_positions (node).element.1 == 1,
+        SimpleSelector::OnlyChild      => sibling_positions (node).element == (1, 1),
+        SimpleSelector::FirstOfType    => sibling_positions (node).of_type.0 == 1,
+        SimpleSelector::LastOfType     => sibling_positions (node).of_type.1 == 1,
+        SimpleSelector::OnlyOfType     => sibling_positions (node).of_type == (1, 1),
+
+        SimpleSelector::NthChild (a, b)      => matches_nth (a, b, sibling_positions (node).element.0),
+        SimpleSelector::NthLastChild (a, b)  => matches_nth (a, b, sibling_positions (node).element.1),
+        SimpleSelector::NthOfType (a, b)     => matches_nth (a, b, sibling_positions (node).of_type.0),
+        SimpleSelector::NthLastOfType (a, b) => matches_nth (a, b, sibling_positions (node).of_type.1),
+
+        SimpleSelector::Not (ref compound) => !compound.iter ().all (|s| matches_simple (s, node))
+    }
+}
+
+/***** @font-face *****/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontFaceSource {
+    Url (String),
+    Local (String)
+}
+
+// https://www.w3.org/TR/css-fonts-3/#font-face-rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub family:  String,
+    pub sources: Vec<FontFaceSource>
+}
+
+enum FontFaceDescriptor {
+    Family (String),
+    Src (Vec<FontFaceSource>),
+    Other
+}
+
+// Formats that FreeType can load.  Sources in other formats, like "svg"
+// or "embedded-opentype", are skipped so that the next one gets tried.
+const SUPPORTED_FONT_FORMATS: &[&str] = &["truetype", "opentype", "woff", "woff2", "collection"];
+
+// A quoted family name, or a sequence of identifiers like Foo Bar
+fn parse_family_name<'i, 't> (input: &mut Parser<'i, 't>) -> Result<String, BasicParseError<'i>> {
+    if let Ok (name) = input.try (|i| i.expect_string ().map (|s| s.to_string ())) {
+        return Ok (name);
+    }
+
+    let mut words = vec! [input.expect_ident ()?.to_string ()];
+
+    while let Ok (word) = input.try (|i| i.expect_ident ().map (|s| s.to_string ())) {
+        words.push (word);
+    }
+
+    Ok (words.join (" "))
+}
+
+// Returns None for a url() whose format() we don't support
+fn parse_font_face_source<'i, 't> (input: &mut Parser<'i, 't>) -> Result<Option<FontFaceSource>, BasicParseError<'i>> {
+    if input.try (|i| i.expect_function_matching ("local")).is_ok () {
+        let name = input.parse_nested_block (|i| {
+            let name = parse_family_name (i)?;
+            i.expect_exhausted ()?;
+            Ok (name)
+        }).map_err (|e: cssparser::ParseError<()>| e.basic ())?;
+
+        return Ok (Some (FontFaceSource::Local (name)));
+    }
+
+    let url = input.expect_url ()?.to_string ();
+    let mut supported = true;
+
+    if input.try (|i| i.expect_function_matching ("format")).is_ok () {
+        let formats = input.parse_nested_block (|i| {
+            i.parse_comma_separated (|i| {
+                i.expect_string ()
+                    .map (|s| s.to_ascii_lowercase ())
+                    .map_err (cssparser::ParseError::Basic)
+            })
+        }).map_err (|e: cssparser::ParseError<()>| e.basic ())?;
+
+        supported = formats.iter ().any (|f| SUPPORTED_FONT_FORMATS.contains (&f.as_str ()));
+    }
+
+    input.expect_exhausted ()?;
+
+    if supported {
+        Ok (Some (FontFaceSource::Url (url)))
+    } else {
+        Ok (None)
+    }
+}
+
+struct FontFaceParser;
+
+impl<'i> DeclarationParser<'i> for FontFaceParser {
+    type Declaration = FontFaceDescriptor;
+    type Error = ();
+
+    fn parse_value<'t> (&mut self, name: CowRcStr<'i>, input: &mut Parser<'i, 't>)
+                        -> Result<FontFaceDescriptor, cssparser::ParseError<'i, ()>> {
+        match name.to_ascii_lowercase ().as_str () {
+            "font-family" => {
+                let family = parse_family_name (input)?;
+                input.expect_exhausted ()?;
+
+                Ok (FontFaceDescriptor::Family (family))
+            },
+
+            "src" => {
+                let sources = input.parse_comma_separated (|i| {
+                    parse_font_face_source (i).map_err (cssparser::ParseError::Basic)
+                })?;
+
+                Ok (FontFaceDescriptor::Src (sources.into_iter ().filter_map (|s| s).collect ()))
+            },
+
+            // Descriptors like font-weight and unicode-range choose among the
+            // faces of a family; we let the font files speak for themselves.
+            _ => Ok (FontFaceDescriptor::Other)
+        }
+    }
+}
+
+impl<'i> AtRuleParser<'i> for FontFaceParser {
+    type Prelude = ();
+    type AtRule = FontFaceDescriptor;
+    type Error = ();
+}
+
+fn parse_font_face<'i, 't> (input: &mut Parser<'i, 't>) -> Option<FontFace> {
+    let mut family = None;
+    let mut sources = Vec::new ();
+
+    for descriptor in DeclarationListParser::new (input, FontFaceParser).filter_map (|d| d.ok ()) {
+        match descriptor {
+            FontFaceDescriptor::Family (f) => family = Some (f),
+            FontFaceDescriptor::Src (s)    => sources = s,
+            FontFaceDescriptor::Other      => ()
+        }
+    }
+
+    match family {
+        Some (family) if !sources.is_empty () => Some (FontFace {
+            family:  family,
+            sources: sources
+        }),
+
+        _ => None
+    }
+}
+
+/***** Style sheets *****/
+
+// Where a declaration comes from; later origins win over earlier ones
+// with the same importance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Origin {
+    StyleSheet,
+    StyleAttribute,
+    Animation
+}
+
+struct Rule {
+    selector:     Selector,
+    specificity:  Specificity,
+    declarations: Rc<Vec<Declaration>>
+}
+
+pub struct Stylesheet {
+    // This is a RefCell because @import rules make us re-enter the
+    // parser for the same style sheet through the C code.
+    rules: RefCell<Vec<Rule>>
+}
+
+enum TopLevelRule {
+    Style (Vec<Selector>, Vec<Declaration>),
+    Import (String),
+    FontFace (FontFace)
+}
+
+struct TopLevelParser;
+
+impl<'i> QualifiedRuleParser<'i> for TopLevelParser {
+    type Prelude = Vec<Selector>;
+    type QualifiedRule = TopLevelRule;
+    type Error = ();
+
+    // If any selector in the list is invalid, the whole rule is ignored
+    fn parse_prelude<'t> (&mut self, input: &mut Parser<'i, 't>)
+                          -> Result<Vec<Selector>, cssparser::ParseError<'i, ()>> {
+        input.parse_comma_separated (|i| parse_selector (i).map_err (cssparser::ParseError::Basic))
+    }
+
+    fn parse_block<'t> (&mut self, selectors: Vec<Selector>, input: &mut Parser<'i, 't>)
+                        -> Result<TopLevelRule, cssparser::ParseError<'i, ()>> {
+        Ok (TopLevelRule::Style (selectors, parse_declaration_list (input)))
+    }
+}
+
+impl<'i> AtRuleParser<'i> for TopLevelParser {
+    type Prelude = ();
+    type AtRule = TopLevelRule;
+    type Error = ();
+
+    fn parse_prelude<'t> (&mut self, name: CowRcStr<'i>, input: &mut Parser<'i, 't>)
+                          -> Result<AtRuleType<(), TopLevelRule>, cssparser::ParseError<'i, ()>> {
+        match name.to_ascii_lowercase ().as_str () {
+            "import" => {
+                // We don't support media queries, so ignore imports that have them
+                let url = input.expect_url_or_string ()?.to_string ();
+                input.expect_exhausted ()?;
+
+                Ok (AtRuleType::WithoutBlock (TopLevelRule::Import (url)))
+            },
+
+            "font-face" => {
+                input.expect_exhausted ()?;
+
+                Ok (AtRuleType::WithBlock (()))
+            },
+
+            _ => Err (cssparser::ParseError::Basic (BasicParseError::AtRuleInvalid (name.clone ())))
+        }
+    }
+
+    // Only @font-face has a block
+    fn parse_block<'t> (&mut self, _: (), input: &mut Parser<'i, 't>)
+                        -> Result<TopLevelRule, cssparser::ParseError<'i, ()>> {
+        match parse_font_face (input) {
+            Some (font_face) => Ok (TopLevelRule::FontFace (font_face)),
+            None => Err (cssparser::ParseError::Basic (BasicParseError::AtRuleBodyInvalid))
+        }
+    }
+}
+
+extern "C" {
+    fn rsvg_handle_load_css (handle: *const RsvgHandle, href: *const libc::c_char);
+
+    fn rsvg_handle_load_font_face (handle: *const RsvgHandle,
+                                   family: *const libc::c_char,
+                                   href:   *const libc::c_char) -> glib_sys::gboolean;
+
+    fn rsvg_handle_load_local_font_face (handle: *const RsvgHandle,
+                                         family: *const libc::c_char,
+                                         name:   *const libc::c_char) -> glib_sys::gboolean;
+
+    fn rsvg_parse_style_pair (state:     *mut RsvgState,
+                              name:      *const libc::c_char,
+                              value:     *const libc::c_char,
+                              important: glib_sys::gboolean);
+}
+
+impl Stylesheet {
+    pub fn new () -> Stylesheet {
+        Stylesheet {
+            rules: RefCell::new (Vec::new ())
+        }
+    }
+
+    // Adds the rules in a style sheet.  Invalid rules and declarations
+    // are ignored, as per the CSS error handling rules.  The import
+    // function gets called with the URL of each @import rule, and the
+    // font_face function with each valid @font-face rule.
+    pub fn parse<F, G> (&self, s: &str, mut import: F, mut font_face: G)
+        where F: FnMut (&str),
+              G: FnMut (&FontFace)
+    {
+        let mut input = ParserInput::new (s);
+        let mut parser = Parser::new (&mut input);
+
+        for rule in RuleListParser::new_for_stylesheet (&mut parser, TopLevelParser) {
+            match rule {
+                Ok (TopLevelRule::Style (selectors, declarations)) => {
+                    let declarations = Rc::new (declarations);
+
+                    // Each selector in a list gets its own specificity
+                    for selector in selectors {
+                        self.rules.borrow_mut ().push (Rule {
+                            specificity:  selector.specificity (),
+                            selector:     selector,
+                            declarations: declarations.clone ()
+                        });
+                    }
+                },
+
+                Ok (TopLevelRule::Import (url)) => import (&url),
+
+                Ok (TopLevelRule::FontFace (face)) => font_face (&face),
+
+                Err (_) => ()
+            }
+        }
+    }
+
+    // Returns the declarations that apply to the node, from the style
+    // sheets, from its style attribute and from its animations, sorted
+    // in cascade order so that later ones override earlier ones.
+    pub fn cascade (&self,
+                    node: &RsvgNode,
+                    style_declarations: &[Declaration],
+                    animated_declarations: &[Declaration]) -> Vec<Declaration> {
+        // (important, origin, specificity, source order)
+        let mut matched: Vec<((bool, Origin, Specificity, usize), Declaration)> = Vec::new ();
+        let mut order = 0;
+
+        for rule in self.rules.borrow ().iter () {
+            if rule.selector.matches (node) {
+                for decl in rule.declarations.iter () {
+                    matched.push (((decl.important, Origin::StyleSheet, rule.specificity, order), decl.clone ()));
+                    order += 1;
+                }
+            }
+        }
+
+        for decl in style_declarations {
+            matched.push (((decl.important, Origin::StyleAttribute, (0, 0, 0), order), decl.clone ()));
+            order += 1;
+        }
+
+        for decl in animated_declarations {
+            matched.push (((decl.important, Origin::Animation, (0, 0, 0), order), decl.clone ()));
+            order += 1;
+        }
+
+        matched.sort_by_key (|&(key, _)| key);
+
+        matched.into_iter ().map (|(_, decl)| decl).collect ()
+    }
+}
+
+pub fn apply_declarations (state: *mut RsvgState, declarations: &[Declaration]) {
+    for decl in declarations {
+        unsafe {
+            rsvg_parse_style_pair (state,
+                                   decl.name.to_glib_none ().0,
+                                   decl.value.to_glib_none ().0,
+                                   decl.important.to_glib ());
+        }
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_stylesheet_new () -> *mut Stylesheet {
+    Box::into_raw (Box::new (Stylesheet::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_stylesheet_free (raw_sheet: *mut Stylesheet) {
+    assert! (!raw_sheet.is_null ());
+
+    let _ = unsafe { Box::from_raw (raw_sheet) };
+}
+
+#[no_mangle]
+pub extern fn rsvg_stylesheet_parse (raw_sheet: *const Stylesheet,
+                                     handle:    *const RsvgHandle,
+                                     buf:       *const libc::c_char,
+                                     len:       libc::size_t) {
+    assert! (!raw_sheet.is_null ());
+    let sheet: &Stylesheet = unsafe { & *raw_sheet };
+
+    if buf.is_null () || len == 0 {
+        return;
+    }
+
+    let bytes = unsafe { slice::from_raw_parts (buf as *const u8, len) };
+
+    sheet.parse (&String::from_utf8_lossy (bytes),
+                 |url| unsafe { rsvg_handle_load_css (handle, url.to_glib_none ().0); },
+                 |face| load_font_face (handle, face));
+}
+
+// Tries the sources of a @font-face rule in order, until one of them loads
+fn load_font_face (handle: *const RsvgHandle, face: &FontFace) {
+    let family = face.family.to_glib_none ();
+
+    for source in &face.sources {
+        let loaded: bool = unsafe {
+            match *source {
+                FontFaceSource::Url (ref url) =>
+                    from_glib (rsvg_handle_load_font_face (handle, family.0, url.to_glib_none ().0)),
+
+                FontFaceSource::Local (ref name) =>
+                    from_glib (rsvg_handle_load_local_font_face (handle, family.0, name.to_glib_none ().0))
+            }
+        };
+
+        if loaded {
+            break;
+        }
+    }
+}
+
+// Builds the node's specified values from its presentation attributes
+// and the declarations that the cascade found for it.  Invalid values
+// are ignored, as per the CSS error handling rules; the errors in
+// presentation attributes are returned for diagnostics.
+pub fn specified_values (attributes: &[(String, String)], declarations: &[Declaration]) -> (SpecifiedValues, Vec<NodeError>) {
+    let mut values = SpecifiedValues::default ();
+    let mut errors = Vec::new ();
+
+    for &(ref name, ref value) in attributes {
+        if is_presentation_attribute (name) {
+            if let Err (e) = values.set_property (name, value) {
+                errors.push (NodeError::attribute_error (name, e));
+            }
+        }
+    }
+
+    for decl in declarations {
+        let _ = values.set_property (&decl.name, &decl.value);
+    }
+
+    (values, errors)
+}
+
+// Applies the presentation attributes, the style sheet, and the style
+// attribute from the property bag to the node.
+#[no_mangle]
+pub extern fn rsvg_stylesheet_apply (raw_sheet: *const Stylesheet,
+                                     raw_node:  *const RsvgNode,
+                                     pbag:      *const RsvgPropertyBag,
+                                     animated:  *const RsvgPropertyBag) {
+    assert! (!raw_sheet.is_null ());
+    let sheet: &Stylesheet = unsafe { & *raw_sheet };
+
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    let (style_declarations, style_errors) = property_bag::lookup (pbag, "style")
+        .map_or ((Vec::new (), Vec::new ()), |style| parse_style_attribute (&style));
+
+    let animated_declarations: Vec<Declaration> = if animated.is_null () {
+        Vec::new ()
+    } else {
+        property_bag::to_vec (animated).into_iter ()
+            .map (|(name, value)| Declaration { name, value, important: false })
+            .collect ()
+    };
+
+    let declarations = sheet.cascade (node, &style_declarations, &animated_declarations);
+
+    let (values, errors) = specified_values (&property_bag::to_vec (pbag), &declarations);
+
+    for error in errors.into_iter ().chain (style_errors) {
+        node.add_error (error);
+    }
+
+    node.set_specified_values (values);
+
+    apply_declarations (node.get_state (), &declarations);
+
+    node.set_style_declarations (declarations);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use properties::*;
+    use test_utils;
+
+    fn element (parent: Option<&RsvgNode>, name: &str, atts: &[(&str, &str)]) -> RsvgNode {
+        test_utils::element (NodeType::Group, parent, name, atts)
+    }
+
+    fn parse (s: &str) -> Result<Selector, ()> {
+        let mut input = ParserInput::new (s);
+        let mut parser = Parser::new (&mut input);
+
+        parser.parse_entirely (|p| parse_selector (p).map_err (cssparser::ParseError::Basic))
+            .map_err (|_: cssparser::ParseError<()>| ())
+    }
+
+    fn matches (selector: &str, node: &RsvgNode) -> bool {
+        parse (selector).unwrap ().matches (node)
+    }
+
+    fn specificity (selector: &str) -> Specificity {
+        parse (selector).unwrap ().specificity ()
+    }
+
+    #[test]
+    fn parses_declarations () {
+        assert_eq! (parse_declarations ("fill: red; stroke:blue !important;;"),
+                    vec! [Declaration { name: "fill".to_string (), value: "red".to_string (), important: false },
+                          Declaration { name: "stroke".to_string (), value: "blue".to_string (), important: true }]);
+
+        assert_eq! (parse_declarations ("font-family: 'Foo Bar', serif; fill: url('#grad') none"),
+                    vec! [Declaration { name: "font-family".to_string (), value: "Foo Bar, serif".to_string (), important: false },
+                          Declaration { name: "fill".to_string (), value: "url(#grad) none".to_string (), important: false }]);
+
+        // Only quoted strings get unquoted; quotes inside them stay
+        assert_eq! (parse_declarations ("font-family: \"Rock 'n' Roll\"; fill: url(\"#a\") rgb(0, 0, 0)"),
+                    vec! [Declaration { name: "font-family".to_string (), value: "Rock 'n' Roll".to_string (), important: false },
+                          Declaration { name: "fill".to_string (), value: "url(#a) rgb(0, 0, 0)".to_string (), important: false }]);
+
+        assert_eq! (parse_declarations ("fill: ; : red; stroke-width: 2 ! important"),
+                    vec! [Declaration { name: "stroke-width".to_string (), value: "2".to_string (), important: true }]);
+    }
+
+    #[test]
+    fn parses_selectors () {
+        assert! (parse ("rect").is_ok ());
+        assert! (parse ("*").is_ok ());
+        assert! (parse ("g > rect.foo#bar").is_ok ());
+        assert! (parse ("g  rect + circle ~ path").is_ok ());
+        assert! (parse ("g>rect+circle~path").is_ok ());
+        assert! (parse ("[fill]").is_ok ());
+        assert! (parse ("[fill = 'red']").is_ok ());
+        assert! (parse ("[xlink|href^='#']").is_ok ());
+        assert! (parse ("rect:nth-child(2n+1)").is_ok ());
+        assert! (parse ("rect:not(.foo)").is_ok ());
+        assert! (parse (":first-child:last-of-type").is_ok ());
+    }
+
+    #[test]
+    fn invalid_selectors_yield_error () {
+        assert! (parse ("").is_err ());
+        assert! (parse ("rect >").is_err ());
+        assert! (parse ("> rect").is_err ());
+        assert! (parse ("rect:hover").is_err ());
+        assert! (parse ("rect::before").is_err ());
+        assert! (parse ("rect:nth-child(foo)").is_err ());
+        assert! (parse ("rect:not(g rect)").is_err ());
+        assert! (parse ("[fill=]").is_err ());
+        assert! (parse (". foo").is_err ());
+        assert! (parse ("rect#").is_err ());
+    }
+
+    #[test]
+    fn computes_specificity () {
+        assert_eq! (specificity ("*"), (0, 0, 0));
+        assert_eq! (specificity ("rect"), (0, 0, 1));
+        assert_eq! (specificity ("g rect"), (0, 0, 2));
+        assert_eq! (specificity (".foo"), (0, 1, 0));
+        assert_eq! (specificity ("rect.foo:first-child[x]"), (0, 3, 1));
+        assert_eq! (specificity ("#foo"), (1, 0, 0));
+        assert_eq! (specificity ("rect.blue#white"), (1, 1, 1));
+        assert_eq! (specificity ("rect:not(#foo)"), (1, 0, 1));
+    }
+
+    #[test]
+    fn matches_simple_selectors () {
+        let svg = element (None, "svg", &[]);
+        let rect = element (Some (&svg), "rect", &[("id", "r"), ("class", "foo  bar"), ("fill", "red"), ("lang", "en-US")]);
+
+        assert! (matches ("*", &rect));
+        assert! (matches ("rect", &rect));
+        assert! (!matches ("circle", &rect));
+        assert! (matches ("#r", &rect));
+        assert! (matches (".foo.bar", &rect));
+        assert! (!matches (".fo", &rect));
+        assert! (matches ("rect.bar#r", &rect));
+        assert! (matches ("[fill]", &rect));
+        assert! (matches ("[fill=red]", &rect));
+        assert! (!matches ("[stroke]", &rect));
+        assert! (matches ("[class~=bar]", &rect));
+        assert! (matches ("[lang|=en]", &rect));
+        assert! (matches ("[fill^=r][fill$=d][fill*=e]", &rect));
+        assert! (matches (":not(circle)", &rect));
+        assert! (!matches (":not(.foo)", &rect));
+        assert! (matches ("svg:root", &svg));
+        assert! (!matches (":root", &rect));
+        assert! (matches (":empty", &rect));
+        assert! (!matches (":empty", &svg));
+    }
+
+    #[test]
+    fn matches_combinators () {
+        let svg = element (None, "svg", &[]);
+        let g = element (Some (&svg), "g", &[("class", "outer")]);
+        let rect = element (Some (&g), "rect", &[]);
+        let circle = element (Some (&g), "circle", &[]);
+        let path = element (Some (&g), "path", &[]);
+
+        assert! (matches ("svg rect", &rect));
+        assert! (matches ("svg > g > rect", &rect));
+        assert! (!matches ("svg > rect", &rect));
+        assert! (matches (".outer path", &path));
+        assert! (matches ("rect + circle", &circle));
+        assert! (!matches ("rect + path", &path));
+        assert! (matches ("rect ~ path", &path));
+        assert! (!matches ("path ~ rect", &rect));
+        assert! (matches ("svg g rect ~ path", &path));
+    }
+
+    #[test]
+    fn matches_structural_pseudo_classes () {
+        let g = element (None, "g", &[]);
+        let children: Vec<RsvgNode> = ["rect", "circle", "rect", "rect", "circle"].iter ()
+            .map (|name| element (Some (&g), name, &[]))
+            .collect ();
+
+        assert! (matches (":first-child", &children[0]));
+        assert! (!matches (":first-child", &children[1]));
+        assert! (matches (":last-child", &children[4]));
+        assert! (matches (":nth-child(2)", &children[1]));
+        assert! (matches (":nth-child(odd)", &children[2]));
+        assert! (!matches (":nth-child(even)", &children[2]));
+        assert! (matches (":nth-child(-n+2)", &children[1]));
+        assert! (!matches (":nth-child(-n+2)", &children[2]));
+        assert! (matches (":nth-last-child(1)", &children[4]));
+        assert! (matches ("circle:first-of-type", &children[1]));
+        assert! (matches ("rect:last-of-type", &children[3]));
+        assert! (matches ("rect:nth-of-type(2)", &children[2]));
+        assert! (matches ("circle:nth-last-of-type(1)", &children[4]));
+        assert! (!matches (":only-child", &children[0]));
+        assert! (matches (":only-child", &g));
+        assert! (matches (":only-of-type", &g));
+    }
+
+    #[test]
+    fn cascade_orders_by_specificity_and_importance () {
+        let svg = element (None, "svg", &[]);
+        let rect = element (Some (&svg), "rect", &[("id", "r"), ("class", "blue")]);
+
+        let sheet = Stylesheet::new ();
+        let mut imports = Vec::new ();
+
+        sheet.parse ("@import url(base.css);
+                      @import 'print.css' print;
+                      #r { fill: green; }
+                      rect.blue, circle { fill: blue; stroke: black !important; }
+                      rect { fill: red; stroke: white; }
+                      @media print { rect { fill: yellow; } }
+                      rect:hover { fill: pink; }
+                      svg > rect { stroke-width: 2; }",
+                     |url| imports.push (url.to_string ()),
+                     |_| ());
+
+        assert_eq! (imports, vec! ["base.css".to_string ()]);
+
+        let values: Vec<(String, String)> = sheet.cascade (&rect, &parse_declarations ("fill: gray; stroke: none"), &[])
+            .into_iter ()
+            .map (|d| (d.name, d.value))
+            .collect ();
+
+        let pairs: Vec<(&str, &str)> = values.iter ().map (|&(ref n, ref v)| (n.as_str (), v.as_str ())).collect ();
+
+        assert_eq! (pairs, vec! [("fill", "red"),
+                                 ("stroke", "white"),
+                                 ("stroke-width", "2"),
+                                 ("fill", "blue"),
+                                 ("fill", "green"),
+                                 ("fill", "gray"),
+                                 ("stroke", "none"),
+                                 ("stroke", "black")]);
+    }
+
+    #[test]
+    fn animated_values_override_normal_declarations () {
+        let svg = element (None, "svg", &[]);
+        let rect = element (Some (&svg), "rect", &[("id", "r")]);
+
+        let sheet = Stylesheet::new ();
+        sheet.parse ("#r { fill: red; stroke: red !important; }", |_| (), |_| ());
+
+        let animated = vec! [Declaration { name: "fill".to_string (), value: "green".to_string (), important: false },
+                             Declaration { name: "stroke".to_string (), value: "green".to_string (), important: false }];
+
+        let values: Vec<(String, String)> = sheet.cascade (&rect, &parse_declarations ("fill: blue"), &animated)
+            .into_iter ()
+            .map (|d| (d.name, d.value))
+            .collect ();
+
+        let pairs: Vec<(&str, &str)> = values.iter ().map (|&(ref n, ref v)| (n.as_str (), v.as_str ())).collect ();
+
+        assert_eq! (pairs, vec! [("fill", "red"),
+                                 ("fill", "blue"),
+                                 ("fill", "green"),
+                                 ("stroke", "green"),
+                                 ("stroke", "red")]);
+    }
+
+    #[test]
+    fn parses_font_face_rules () {
+        let sheet = Stylesheet::new ();
+        let mut faces = Vec::new ();
+
+        sheet.parse ("@font-face { font-family: 'Foo Sans'; src: local(Foo Sans Regular), url(foo.woff2) format('woff2'); }
+                      @font-face { font-family: Bar; font-weight: bold; src: url(bar.svg#bar) format('svg'), url('data:font/ttf;base64,AAEAAA=='); }
+                      @font-face { font-family: NoSources; src: url(baz.eot) format('embedded-opentype'); }
+                      @font-face { src: url(nofamily.ttf); }
+                      @font-face foo { font-family: Prelude; src: url(prelude.ttf); }
+                      rect { fill: red; }",
+                     |_| (),
+                     |face| faces.push (face.clone ()));
+
+        assert_eq! (faces,
+                    vec! [FontFace {
+                              family:  "Foo Sans".to_string (),
+                              sources: vec! [FontFaceSource::Local ("Foo Sans Regular".to_string ()),
+                                             FontFaceSource::Url ("foo.woff2".to_string ())]
+                          },
+                          FontFace {
+                              family:  "Bar".to_string (),
+                              sources: vec! [FontFaceSource::Url ("data:font/ttf;base64,AAEAAA==".to_string ())]
+                          }]);
+
+        assert_eq! (sheet.rules.borrow ().len (), 1);
+    }
+
+    #[test]
+    fn declarations_override_presentation_attributes () {
+        let svg = element (None, "svg", &[]);
+        let rect = element (Some (&svg), "rect", &[]);
+
+        let sheet = Stylesheet::new ();
+        sheet.parse ("rect { fill-rule: evenodd; stroke-linecap: bogus; }", |_| (), |_| ());
+
+        let attributes = vec! [("fill-rule".to_string (), "nonzero".to_string ()),
+                               ("stroke-linecap".to_string (), "round".to_string ()),
+                               ("x".to_string (), "10".to_string ())];
+
+        rect.set_specified_values (specified_values (&attributes, &sheet.cascade (&rect, &parse_declarations ("opacity: 0.5"), &[])).0);
+
+        let values = rect.get_computed_values ();
+
+        assert_eq! (values.fill_rule, FillRule::EvenOdd);
+        assert_eq! (values.stroke_linecap, StrokeLinecap::Round);
+        assert_eq! (values.opacity, GroupOpacity (0.5));
+    }
+
+    #[test]
+    fn reports_invalid_presentation_attributes () {
+        let attributes = vec! [("fill-rule".to_string (), "bogus".to_string ()),
+                               ("stroke-linecap".to_string (), "round".to_string ()),
+                               ("x".to_string (), "bogus".to_string ())];
+
+        let (_, errors) = specified_values (&attributes, &[]);
+
+        assert_eq! (errors.len (), 1);
+        assert_eq! (errors[0].get_attr_name (), "fill-rule");
+    }
+
+    #[test]
+    fn reports_invalid_style_declarations () {
+        let (declarations, errors) = parse_style_attribute ("fill: bogus; stroke; -foo-bar: 1; opacity: 0.5");
+
+        let names: Vec<&str> = declarations.iter ().map (|d| d.name.as_str ()).collect ();
+        assert_eq! (names, vec! ["fill", "-foo-bar", "opacity"]);
+
+        assert_eq! (errors.len (), 2);
+        assert_eq! (errors[0].get_attr_name (), "style");
+        assert! (errors[0].to_string ().contains ("invalid value for property \"fill\""));
+        assert_eq! (errors[1].get_attr_name (), "style");
+        assert! (errors[1].to_string ().contains ("invalid declaration \"stroke\""));
+    }
+
+    #[test]
+    fn valid_style_attribute_has_no_errors () {
+        let (declarations, errors) = parse_style_attribute ("fill: url('#g') red; font-family: 'Foo', serif");
+
+        assert_eq! (declarations.len (), 2);
+        assert_eq! (errors, vec! []);
+    }
+}
@@ -0,0 +1,167 @@
+//! Collects the errors found while loading a document, so that they
+//! can be reported to the caller instead of just making the affected
+//! elements not render.
+
+use ::glib::translate::*;
+use ::libc;
+
+use std::cell::RefCell;
+use std::fmt;
+
+use node::RsvgNode;
+
+/// An error in an element or in one of its attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub element:   String,
+    pub id:        Option<String>,
+    pub attribute: Option<String>,
+    pub message:   String,
+
+    // Position of the attribute in the source, or of the element's start
+    // tag if the attribute is not there; both start at 1
+    pub line:      u32,
+    pub column:    u32
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt (&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write! (f, "{}:{}: <{}", self.line, self.column, self.element)?;
+
+        if let Some (ref id) = self.id {
+            write! (f, " id=\"{}\"", id)?;
+        }
+
+        write! (f, ">")?;
+
+        if let Some (ref attribute) = self.attribute {
+            write! (f, " attribute \"{}\"", attribute)?;
+        }
+
+        write! (f, ": {}", self.message)
+    }
+}
+
+/// The diagnostics of a document, in document order.
+pub struct Diagnostics {
+    list: RefCell<Vec<Diagnostic>>
+}
+
+impl Diagnostics {
+    pub fn new () -> Diagnostics {
+        Diagnostics {
+            list: RefCell::new (Vec::new ())
+        }
+    }
+
+    pub fn add_node (&self, node: &RsvgNode) {
+        let id = node.get_attribute ("id");
+
+        for error in node.get_errors () {
+            let (line, column) = node.get_attribute_location (error.get_attr_name ());
+
+            self.list.borrow_mut ().push (Diagnostic {
+                element:   node.get_element_name (),
+                id:        id.clone (),
+                attribute: Some (error.get_attr_name ().to_string ()),
+                message:   error.get_error ().to_string (),
+                line:      line,
+                column:    column
+            });
+        }
+    }
+
+    pub fn get_list (&self) -> Vec<Diagnostic> {
+        self.list.borrow ().clone ()
+    }
+
+    pub fn get_first (&self) -> Option<Diagnostic> {
+        self.list.borrow ().first ().cloned ()
+    }
+
+    // One line per diagnostic, or None if there are none
+    pub fn to_report (&self) -> Option<String> {
+        let list = self.list.borrow ();
+
+        if list.is_empty () {
+            None
+        } else {
+            Some (list.iter ().map (|d| format! ("{}\n", d)).collect ())
+        }
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_diagnostics_new () -> *mut Diagnostics {
+    Box::into_raw (Box::new (Diagnostics::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_diagnostics_free (raw_diagnostics: *mut Diagnostics) {
+    assert! (!raw_diagnostics.is_null ());
+
+    let _ = unsafe { Box::from_raw (raw_diagnostics) };
+}
+
+#[no_mangle]
+pub extern fn rsvg_diagnostics_add_node (raw_diagnostics: *const Diagnostics, raw_node: *const RsvgNode) {
+    assert! (!raw_diagnostics.is_null ());
+    let diagnostics: &Diagnostics = unsafe { & *raw_diagnostics };
+
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    diagnostics.add_node (node);
+}
+
+#[no_mangle]
+pub extern fn rsvg_diagnostics_to_string (raw_diagnostics: *const Diagnostics) -> *mut libc::c_char {
+    assert! (!raw_diagnostics.is_null ());
+    let diagnostics: &Diagnostics = unsafe { & *raw_diagnostics };
+
+    diagnostics.to_report ().to_glib_full ()
+}
+
+#[no_mangle]
+pub extern fn rsvg_diagnostics_get_first (raw_diagnostics: *const Diagnostics) -> *mut libc::c_char {
+    assert! (!raw_diagnostics.is_null ());
+    let diagnostics: &Diagnostics = unsafe { & *raw_diagnostics };
+
+    diagnostics.get_first ().map (|d| d.to_string ()).to_glib_full ()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic (id: Option<&str>, attribute: Option<&str>) -> Diagnostic {
+        Diagnostic {
+            element:   "rect".to_string (),
+            id:        id.map (|s| s.to_string ()),
+            attribute: attribute.map (|s| s.to_string ()),
+            message:   "value must be non-negative".to_string (),
+            line:      3,
+            column:    42
+        }
+    }
+
+    #[test]
+    fn formats_diagnostics () {
+        assert_eq! (diagnostic (Some ("r"), Some ("width")).to_string (),
+                    "3:42: <rect id=\"r\"> attribute \"width\": value must be non-negative");
+
+        assert_eq! (diagnostic (None, None).to_string (),
+                    "3:42: <rect>: value must be non-negative");
+    }
+
+    #[test]
+    fn empty_report_is_none () {
+        let diagnostics = Diagnostics::new ();
+        assert_eq! (diagnostics.to_report (), None);
+
+        diagnostics.list.borrow_mut ().push (diagnostic (None, Some ("x")));
+        diagnostics.list.borrow_mut ().push (diagnostic (None, Some ("y")));
+        assert_eq! (diagnostics.to_report ().unwrap ().lines ().count (), 2);
+        assert_eq! (diagnostics.get_first (), Some (diagnostic (None, Some ("x"))));
+    }
+}
@@ -4,9 +4,6 @@ use ::glib_sys;
 use ::glib::translate::*;
 use ::libc;
 
-use color::*;
-use error::*;
-use opacity::*;
 use node::RsvgNode;
 use node::NodeType;
 use path_builder::RsvgPathBuilder;
@@ -58,15 +55,12 @@ extern "C" {
 
     fn rsvg_current_state (draw_ctx: *const RsvgDrawingCtx) -> *mut RsvgState;
     fn rsvg_state_new () -> *mut RsvgState;
-    fn rsvg_state_free (state: *mut RsvgState);
     fn rsvg_state_reinit (state: *mut RsvgState);
     fn rsvg_state_reconstruct (state: *mut RsvgState, node: *const RsvgNode);
     fn rsvg_state_is_overflow (state: *const RsvgState) -> glib_sys::gboolean;
     fn rsvg_state_has_overflow (state: *const RsvgState) -> glib_sys::gboolean;
     fn rsvg_state_get_cond_true (state: *const RsvgState) -> glib_sys::gboolean;
-    fn rsvg_state_get_stop_color (state: *const RsvgState) -> *const ColorSpec;
-    fn rsvg_state_get_stop_opacity (state: *const RsvgState) -> *const OpacitySpec;
-    fn rsvg_state_get_current_color (state: *const RsvgState) -> u32;
+    fn rsvg_state_set_path_length_scale (state: *mut RsvgState, scale: f64);
 
     fn rsvg_state_push (draw_ctx: *const RsvgDrawingCtx);
     fn rsvg_state_pop (draw_ctx: *const RsvgDrawingCtx);
@@ -83,6 +77,13 @@ extern "C" {
 
     fn rsvg_cairo_get_cairo_context (draw_ctx: *const RsvgDrawingCtx) -> *mut cairo_sys::cairo_t;
     fn rsvg_cairo_set_cairo_context (draw_ctx: *const RsvgDrawingCtx, cr: *const cairo_sys::cairo_t);
+
+    fn rsvg_cairo_get_background_image (draw_ctx: *const RsvgDrawingCtx) -> *mut cairo_sys::cairo_surface_t;
+
+    fn rsvg_get_surface_of_node (draw_ctx: *const RsvgDrawingCtx,
+                                 node:     *const RsvgNode,
+                                 width:    f64,
+                                 height:   f64) -> *mut cairo_sys::cairo_surface_t;
 }
 
 pub fn get_dpi (draw_ctx: *const RsvgDrawingCtx) -> (f64, f64) {
@@ -172,6 +173,35 @@ pub fn set_cairo_context (draw_ctx: *const RsvgDrawingCtx, cr: &cairo::Context)
     }
 }
 
+// Composites the layers that are currently being drawn into a single
+// surface, for the BackgroundImage filter input.
+pub fn get_background_image (draw_ctx: *const RsvgDrawingCtx) -> Option<cairo::ImageSurface> {
+    unsafe {
+        let raw_surface = rsvg_cairo_get_background_image (draw_ctx);
+
+        if raw_surface.is_null () {
+            None
+        } else {
+            Some (cairo::ImageSurface::from_glib_full (raw_surface))
+        }
+    }
+}
+
+pub fn get_surface_of_node (draw_ctx: *const RsvgDrawingCtx,
+                            node:     &RsvgNode,
+                            width:    f64,
+                            height:   f64) -> Option<cairo::ImageSurface> {
+    unsafe {
+        let raw_surface = rsvg_get_surface_of_node (draw_ctx, node, width, height);
+
+        if raw_surface.is_null () {
+            None
+        } else {
+            Some (cairo::ImageSurface::from_glib_full (raw_surface))
+        }
+    }
+}
+
 pub fn get_current_state_affine (draw_ctx: *const RsvgDrawingCtx) -> cairo::Matrix {
     unsafe {
         rsvg_drawing_ctx_get_current_state_affine (draw_ctx)
@@ -206,10 +236,6 @@ pub fn state_new () -> *mut RsvgState {
     unsafe { rsvg_state_new () }
 }
 
-pub fn state_free (state: *mut RsvgState) {
-    unsafe { rsvg_state_free (state); }
-}
-
 pub fn state_reinit (state: *mut RsvgState) {
     unsafe { rsvg_state_reinit (state); }
 }
@@ -242,32 +268,6 @@ pub fn state_pop (draw_ctx: *const RsvgDrawingCtx) {
     }
 }
 
-pub fn state_get_stop_color (state: *const RsvgState) -> Result<Option<Color>, AttributeError> {
-    unsafe {
-        let spec_ptr = rsvg_state_get_stop_color (state);
-
-        if spec_ptr.is_null () {
-            Ok (None)
-        } else {
-            Color::from_color_spec (&*spec_ptr).map (|color| Some (color))
-        }
-    }
-}
-
-pub fn state_get_stop_opacity (state: *const RsvgState) -> Result<Option<Opacity>, AttributeError> {
-    unsafe {
-        let opacity_ptr = rsvg_state_get_stop_opacity (state);
-
-        if opacity_ptr.is_null () {
-            Ok (None)
-        } else {
-            Opacity::from_opacity_spec (&*opacity_ptr).map (|opacity| Some (opacity))
-        }
-    }
-}
-
-pub fn state_get_current_color (state: *const RsvgState) -> Color {
-    let argb = unsafe { rsvg_state_get_current_color (state) };
-
-    Color::from (argb)
+pub fn state_set_path_length_scale (state: *mut RsvgState, scale: f64) {
+    unsafe { rsvg_state_set_path_length_scale (state, scale); }
 }
@@ -41,6 +41,25 @@ impl NodeError {
     }
 }
 
+impl NodeError {
+    pub fn get_attr_name (&self) -> &str {
+        &self.attr_name
+    }
+
+    pub fn get_error (&self) -> &AttributeError {
+        &self.err
+    }
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt (&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AttributeError::Parse (ref n) => write! (f, "{}", n.display),
+            AttributeError::Value (ref s) => write! (f, "{}", s)
+        }
+    }
+}
+
 impl error::Error for NodeError {
     fn description (&self) -> &str {
         match self.err {
@@ -0,0 +1,171 @@
+use ::libc;
+
+use std::cell::{Cell, RefCell};
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, Input, IRect, Primitive, Surface, parse_input};
+use super::surface::A;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    Normal,
+    Multiply,
+    Screen,
+    Darken,
+    Lighten
+}
+
+impl Mode {
+    // Unknown modes are treated as "normal", as we have always done
+    fn parse (s: &str) -> Mode {
+        match s {
+            "multiply" => Mode::Multiply,
+            "screen"   => Mode::Screen,
+            "darken"   => Mode::Darken,
+            "lighten"  => Mode::Lighten,
+            _          => Mode::Normal
+        }
+    }
+}
+
+/// The `feBlend` element.
+pub struct Blend {
+    base: Primitive,
+    mode: Cell<Mode>,
+    in2:  RefCell<Option<Input>>
+}
+
+impl Blend {
+    fn new () -> Blend {
+        Blend {
+            base: Primitive::new (),
+            mode: Cell::new (Mode::Normal),
+            in2:  RefCell::new (None)
+        }
+    }
+}
+
+impl NodeTrait for Blend {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.mode.set (property_bag::lookup (pbag, "mode").map_or (Mode::Normal, |m| Mode::parse (&m)));
+        *self.in2.borrow_mut () = parse_input (pbag, "in2");
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Blend {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let input2 = match ctx.get_input_surface (&self.in2.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let output = blend (self.mode.get (), &input, &input2, bounds);
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn blend (mode: Mode, input: &Surface, input2: &Surface, bounds: IRect) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ()).intersection (&input2.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let a = input.get_pixel (x, y);
+            let b = input2.get_pixel (x, y);
+
+            let qa = a[A] as f64 / 255.0;
+            let qb = b[A] as f64 / 255.0;
+            let qr = 1.0 - (1.0 - qa) * (1.0 - qb);
+
+            let mut pixel = [0u8; 4];
+
+            for ch in 0..3 {
+                let ca = a[ch] as f64 / 255.0;
+                let cb = b[ch] as f64 / 255.0;
+
+                let cr = match mode {
+                    Mode::Normal   => (1.0 - qa) * cb + ca,
+                    Mode::Multiply => (1.0 - qa) * cb + (1.0 - qb) * ca + ca * cb,
+                    Mode::Screen   => cb + ca - ca * cb,
+                    Mode::Darken   => ((1.0 - qa) * cb + ca).min ((1.0 - qb) * ca + cb),
+                    Mode::Lighten  => ((1.0 - qa) * cb + ca).max ((1.0 - qb) * ca + cb)
+                };
+
+                pixel[ch] = (cr * 255.0).max (0.0).min (255.0) as u8;
+            }
+
+            pixel[A] = (qr * 255.0) as u8;
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_blend (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveBlend,
+                    raw_parent,
+                    Box::new (Blend::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel (p: [u8; 4]) -> Surface {
+        let mut s = Surface::new (1, 1);
+        s.set_pixel (0, 0, p);
+        s
+    }
+
+    #[test]
+    fn blends_with_each_mode () {
+        let a = pixel ([128, 0, 0, 128]);
+        let b = pixel ([0, 0, 255, 255]);
+        let bounds = IRect::new (0, 0, 1, 1);
+
+        assert_eq! (blend (Mode::Normal, &a, &b, bounds).get_pixel (0, 0), [128, 0, 127, 255]);
+        assert_eq! (blend (Mode::Multiply, &a, &b, bounds).get_pixel (0, 0), [0, 0, 127, 255]);
+        assert_eq! (blend (Mode::Screen, &a, &b, bounds).get_pixel (0, 0), [128, 0, 255, 255]);
+        assert_eq! (blend (Mode::Darken, &a, &b, bounds).get_pixel (0, 0), [0, 0, 127, 255]);
+        assert_eq! (blend (Mode::Lighten, &a, &b, bounds).get_pixel (0, 0), [128, 0, 255, 255]);
+    }
+
+    #[test]
+    fn leaves_outside_of_bounds_transparent () {
+        let mut a = Surface::new (2, 1);
+        a.set_pixel (0, 0, [255, 255, 255, 255]);
+        a.set_pixel (1, 0, [255, 255, 255, 255]);
+
+        let out = blend (Mode::Normal, &a, &a, IRect::new (1, 0, 2, 1));
+
+        assert_eq! (out.get_pixel (0, 0), [0, 0, 0, 0]);
+        assert_eq! (out.get_pixel (1, 0), [255, 255, 255, 255]);
+    }
+}
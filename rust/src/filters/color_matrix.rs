@@ -0,0 +1,249 @@
+use ::libc;
+
+use std::cell::Cell;
+use std::cmp::{min, max};
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use node::*;
+use parsers;
+use parsers::{ListLength, ParseError};
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface};
+use super::surface::A;
+
+// The 4x5 matrix, with its values scaled by 255
+pub type Kernel = [i32; 20];
+
+const IDENTITY: Kernel = [255, 0, 0, 0, 0,
+                          0, 255, 0, 0, 0,
+                          0, 0, 255, 0, 0,
+                          0, 0, 0, 255, 0];
+
+pub fn saturate (value: f64) -> Kernel {
+    let s = value * 255.0;
+    let mut k = [0; 20];
+
+    k[0] = (0.213 * 255.0 + 0.787 * s) as i32;
+    k[1] = (0.715 * 255.0 - 0.715 * s) as i32;
+    k[2] = (0.072 * 255.0 - 0.072 * s) as i32;
+    k[5] = (0.213 * 255.0 - 0.213 * s) as i32;
+    k[6] = (0.715 * 255.0 + 0.285 * s) as i32;
+    k[7] = (0.072 * 255.0 - 0.072 * s) as i32;
+    k[10] = (0.213 * 255.0 - 0.213 * s) as i32;
+    k[11] = (0.715 * 255.0 - 0.715 * s) as i32;
+    k[12] = (0.072 * 255.0 + 0.928 * s) as i32;
+    k[18] = 255;
+
+    k
+}
+
+pub fn hue_rotate (degrees: f64) -> Kernel {
+    let (sin, cos) = degrees.to_radians ().sin_cos ();
+    let mut k = [0; 20];
+
+    k[0] = ((0.213 + cos * 0.787 + sin * -0.213) * 255.0) as i32;
+    k[1] = ((0.715 + cos * -0.715 + sin * -0.715) * 255.0) as i32;
+    k[2] = ((0.072 + cos * -0.072 + sin * 0.928) * 255.0) as i32;
+    k[5] = ((0.213 + cos * -0.213 + sin * 0.143) * 255.0) as i32;
+    k[6] = ((0.715 + cos * 0.285 + sin * 0.140) * 255.0) as i32;
+    k[7] = ((0.072 + cos * -0.072 + sin * -0.283) * 255.0) as i32;
+    k[10] = ((0.213 + cos * -0.213 + sin * -0.787) * 255.0) as i32;
+    k[11] = ((0.715 + cos * -0.715 + sin * 0.715) * 255.0) as i32;
+    k[12] = ((0.072 + cos * 0.928 + sin * 0.072) * 255.0) as i32;
+    k[18] = 255;
+
+    k
+}
+
+// Scales a list of 20 values, which must have been checked by the caller
+pub fn from_values (values: &[f64]) -> Kernel {
+    let mut k = [0; 20];
+
+    for (k, v) in k.iter_mut ().zip (values.iter ()) {
+        *k = (v * 255.0) as i32;
+    }
+
+    k
+}
+
+fn luminance_to_alpha () -> Kernel {
+    let mut k = [0; 20];
+
+    k[15] = (0.2125 * 255.0) as i32;
+    k[16] = (0.7154 * 255.0) as i32;
+    k[17] = (0.0721 * 255.0) as i32;
+
+    k
+}
+
+/// The `feColorMatrix` element.
+pub struct ColorMatrix {
+    base:   Primitive,
+    kernel: Cell<Kernel>
+}
+
+impl ColorMatrix {
+    fn new () -> ColorMatrix {
+        ColorMatrix {
+            base:   Primitive::new (),
+            kernel: Cell::new (IDENTITY)
+        }
+    }
+}
+
+impl NodeTrait for ColorMatrix {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        let values = match property_bag::lookup (pbag, "values") {
+            Some (v) => parsers::number_list (&v, ListLength::Maximum (20))
+                .map_err (|_| NodeError::parse_error ("values", ParseError::new ("invalid number list")))?,
+
+            None => Vec::new ()
+        };
+
+        // Unknown types, and matrices without 20 values, leave the colors untouched
+        let kernel = match property_bag::lookup (pbag, "type").as_ref ().map (String::as_str) {
+            Some ("saturate")         => saturate (values.first ().cloned ().unwrap_or (1.0)),
+            Some ("hueRotate")        => hue_rotate (values.first ().cloned ().unwrap_or (0.0)),
+            Some ("luminanceToAlpha") => luminance_to_alpha (),
+
+            _ => {
+                if values.len () == 20 {
+                    from_values (&values)
+                } else {
+                    IDENTITY
+                }
+            }
+        };
+
+        self.kernel.set (kernel);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for ColorMatrix {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let output = color_matrix (&self.kernel.get (), &input, bounds);
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn clamp (v: i32) -> u8 {
+    max (0, min (v, 255)) as u8
+}
+
+pub fn color_matrix (kernel: &Kernel, input: &Surface, bounds: IRect) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let src = input.get_pixel (x, y);
+            let alpha = src[A] as i32;
+            let mut pixel = [0u8; 4];
+
+            for ch in 0..4 {
+                let row = &kernel[ch * 5..ch * 5 + 5];
+
+                let sum = if alpha == 0 {
+                    row[4]
+                } else {
+                    // the matrix applies to unpremultiplied colors
+                    row[0] * src[0] as i32 / alpha
+                        + row[1] * src[1] as i32 / alpha
+                        + row[2] * src[2] as i32 / alpha
+                        + row[3] * alpha / 255
+                        + row[4]
+                };
+
+                pixel[ch] = clamp (sum);
+            }
+
+            for ch in 0..3 {
+                pixel[ch] = (pixel[ch] as u32 * pixel[A] as u32 / 255) as u8;
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_color_matrix (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveColorMatrix,
+                    raw_parent,
+                    Box::new (ColorMatrix::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply (kernel: &Kernel, pixel: [u8; 4]) -> [u8; 4] {
+        let mut s = Surface::new (1, 1);
+        s.set_pixel (0, 0, pixel);
+
+        color_matrix (kernel, &s, IRect::new (0, 0, 1, 1)).get_pixel (0, 0)
+    }
+
+    #[test]
+    fn identity_keeps_colors () {
+        assert_eq! (apply (&IDENTITY, [10, 20, 30, 255]), [10, 20, 30, 255]);
+        assert_eq! (apply (&IDENTITY, [0, 0, 0, 0]), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn saturate_zero_makes_gray () {
+        let p = apply (&saturate (0.0), [255, 0, 0, 255]);
+
+        assert_eq! (p[0], p[1]);
+        assert_eq! (p[1], p[2]);
+        assert_eq! (p[A], 255);
+    }
+
+    #[test]
+    fn hue_rotate_uses_degrees () {
+        // a half turn moves red towards cyan
+        let p = apply (&hue_rotate (180.0), [255, 0, 0, 255]);
+        assert! (p[0] < p[1] && p[0] < p[2]);
+    }
+
+    #[test]
+    fn luminance_to_alpha_has_black_color () {
+        let p = apply (&luminance_to_alpha (), [255, 255, 255, 255]);
+
+        assert_eq! (p, [0, 0, 0, 254]);
+    }
+
+    #[test]
+    fn transparent_pixels_get_the_offsets () {
+        let mut k = IDENTITY;
+        k[4] = 255;
+        k[19] = 128;
+
+        assert_eq! (apply (&k, [0, 0, 0, 0]), [128, 0, 0, 128]);
+    }
+}
@@ -0,0 +1,372 @@
+use ::libc;
+
+use std::cell::RefCell;
+use std::cmp::{min, max};
+use std::ffi::CStr;
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use node::*;
+use parsers;
+use parsers::{ListLength, ParseError};
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface, parse_number};
+use super::surface::{R, G, B, A};
+
+/// The `feComponentTransfer` element.
+pub struct ComponentTransfer {
+    base: Primitive
+}
+
+impl ComponentTransfer {
+    fn new () -> ComponentTransfer {
+        ComponentTransfer {
+            base: Primitive::new ()
+        }
+    }
+}
+
+impl NodeTrait for ComponentTransfer {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for ComponentTransfer {
+    fn render (&self, node: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        // For each channel, the last feFuncX child wins
+        let mut functions = [TransferFunction::identity (),
+                             TransferFunction::identity (),
+                             TransferFunction::identity (),
+                             TransferFunction::identity ()];
+
+        for child in node.children.borrow ().iter () {
+            if child.get_type () != NodeType::ComponentTransferFunction {
+                continue;
+            }
+
+            let func = child.get_impl::<NodeComponentTransferFunction> ().unwrap ();
+            functions[func.channel] = func.function.borrow ().clone ();
+        }
+
+        let output = component_transfer (&functions, &input, bounds);
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FunctionType {
+    Identity,
+    Table,
+    Discrete,
+    Linear,
+    Gamma
+}
+
+// A transfer function; the values other than `exponent` are scaled by 255
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferFunction {
+    function_type: FunctionType,
+    table_values:  Vec<i32>,
+    slope:         i32,
+    intercept:     i32,
+    amplitude:     i32,
+    exponent:      f64,
+    offset:        i32
+}
+
+// Computes base^exp for values in [0, 255]
+fn fixpow (base: i32, exp: i32) -> i32 {
+    let mut out = 255;
+
+    for _ in 0..exp {
+        out = out * base / 255;
+    }
+
+    out
+}
+
+impl TransferFunction {
+    pub fn identity () -> TransferFunction {
+        TransferFunction {
+            function_type: FunctionType::Identity,
+            table_values:  Vec::new (),
+            slope:         0,
+            intercept:     0,
+            amplitude:     0,
+            exponent:      0.0,
+            offset:        0
+        }
+    }
+
+    pub fn table (values: &[f64]) -> TransferFunction {
+        TransferFunction {
+            function_type: FunctionType::Table,
+            table_values:  values.iter ().map (|v| (v * 255.0) as i32).collect (),
+            ..TransferFunction::identity ()
+        }
+    }
+
+    pub fn linear (slope: f64, intercept: f64) -> TransferFunction {
+        TransferFunction {
+            function_type: FunctionType::Linear,
+            slope:         (slope * 255.0) as i32,
+            intercept:     (intercept * 255.0) as i32,
+            ..TransferFunction::identity ()
+        }
+    }
+
+    fn apply (&self, c: i32) -> i32 {
+        let n = self.table_values.len () as i32;
+
+        match self.function_type {
+            FunctionType::Identity => c,
+
+            FunctionType::Table => {
+                if n == 0 {
+                    return c;
+                }
+
+                let k = c * (n - 1) / 255;
+                let vk = self.table_values[min (k, n - 1) as usize];
+                let vk1 = self.table_values[min (k + 1, n - 1) as usize];
+                let distance_from_last = c * (n - 1) - k * 255;
+
+                vk + distance_from_last * (vk1 - vk) / 255
+            },
+
+            FunctionType::Discrete => {
+                if n == 0 {
+                    return c;
+                }
+
+                let k = c * n / 255;
+                self.table_values[max (0, min (k, n - 1)) as usize]
+            },
+
+            FunctionType::Linear => self.slope * c / 255 + self.intercept,
+
+            FunctionType::Gamma => {
+                if self.exponent.floor () == self.exponent {
+                    self.amplitude * fixpow (c, self.exponent as i32) / 255 + self.offset
+                } else {
+                    (self.amplitude as f64 * (c as f64 / 255.0).powf (self.exponent)) as i32 + self.offset
+                }
+            }
+        }
+    }
+}
+
+pub fn component_transfer (functions: &[TransferFunction; 4], input: &Surface, bounds: IRect) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let src = input.get_pixel (x, y);
+            let alpha = src[A] as i32;
+            let mut pixel = [0u8; 4];
+
+            for ch in 0..4 {
+                // the functions apply to unpremultiplied colors
+                let value = if ch == A {
+                    alpha
+                } else if alpha == 0 {
+                    0
+                } else {
+                    src[ch] as i32 * 255 / alpha
+                };
+
+                pixel[ch] = max (0, min (functions[ch].apply (value), 255)) as u8;
+            }
+
+            for ch in 0..3 {
+                pixel[ch] = (pixel[ch] as u32 * pixel[A] as u32 / 255) as u8;
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+/// The `feFuncR`, `feFuncG`, `feFuncB` and `feFuncA` elements.
+pub struct NodeComponentTransferFunction {
+    channel:  usize,
+    function: RefCell<TransferFunction>
+}
+
+impl NodeComponentTransferFunction {
+    fn new (channel: usize) -> NodeComponentTransferFunction {
+        NodeComponentTransferFunction {
+            channel:  channel,
+            function: RefCell::new (TransferFunction::identity ())
+        }
+    }
+}
+
+fn scaled (value: Option<f64>, default: f64) -> i32 {
+    (value.unwrap_or (default) * 255.0) as i32
+}
+
+impl NodeTrait for NodeComponentTransferFunction {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        // Unknown types are taken as the identity function
+        let function_type = match property_bag::lookup (pbag, "type").as_ref ().map (String::as_str) {
+            Some ("table")    => FunctionType::Table,
+            Some ("discrete") => FunctionType::Discrete,
+            Some ("linear")   => FunctionType::Linear,
+            Some ("gamma")    => FunctionType::Gamma,
+            _                 => FunctionType::Identity
+        };
+
+        let table_values = match property_bag::lookup (pbag, "tableValues") {
+            Some (v) => parsers::number_list (&v, ListLength::Maximum (256))
+                .map_err (|_| NodeError::parse_error ("tableValues", ParseError::new ("invalid number list")))?
+                .iter ()
+                .map (|v| (v * 255.0) as i32)
+                .collect (),
+
+            None => Vec::new ()
+        };
+
+        *self.function.borrow_mut () = TransferFunction {
+            function_type: function_type,
+            table_values:  table_values,
+            slope:         scaled (parse_number (pbag, "slope")?, 1.0),
+            intercept:     scaled (parse_number (pbag, "intercept")?, 0.0),
+            amplitude:     scaled (parse_number (pbag, "amplitude")?, 1.0),
+            exponent:      parse_number (pbag, "exponent")?.unwrap_or (1.0),
+            offset:        scaled (parse_number (pbag, "offset")?, 0.0)
+        };
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; feComponentTransfer uses its children
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_component_transfer (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveComponentTransfer,
+                    raw_parent,
+                    Box::new (ComponentTransfer::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_node_component_transfer_function (element_name: *const libc::c_char,
+                                                         raw_parent: *const RsvgNode) -> *const RsvgNode {
+    assert! (!element_name.is_null ());
+
+    let name = unsafe { CStr::from_ptr (element_name) };
+
+    let channel = match name.to_str () {
+        Ok ("feFuncR") => R,
+        Ok ("feFuncG") => G,
+        Ok ("feFuncB") => B,
+        Ok ("feFuncA") => A,
+        _              => unreachable! ()
+    };
+
+    boxed_node_new (NodeType::ComponentTransferFunction,
+                    raw_parent,
+                    Box::new (NodeComponentTransferFunction::new (channel)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function (function_type: FunctionType, table_values: &[f64]) -> TransferFunction {
+        TransferFunction {
+            function_type: function_type,
+            table_values:  table_values.iter ().map (|v| (v * 255.0) as i32).collect (),
+            ..TransferFunction::identity ()
+        }
+    }
+
+    #[test]
+    fn table_interpolates () {
+        let f = function (FunctionType::Table, &[0.0, 1.0, 0.0]);
+
+        assert_eq! (f.apply (0), 0);
+        assert_eq! (f.apply (64), 128);
+        assert_eq! (f.apply (255), 0);
+    }
+
+    #[test]
+    fn discrete_steps () {
+        let f = function (FunctionType::Discrete, &[0.0, 0.5, 1.0]);
+
+        assert_eq! (f.apply (0), 0);
+        assert_eq! (f.apply (100), 127);
+        assert_eq! (f.apply (255), 255);
+    }
+
+    #[test]
+    fn empty_tables_are_identity () {
+        assert_eq! (function (FunctionType::Table, &[]).apply (42), 42);
+        assert_eq! (function (FunctionType::Discrete, &[]).apply (42), 42);
+    }
+
+    #[test]
+    fn linear_and_gamma () {
+        let mut f = TransferFunction::identity ();
+
+        f.function_type = FunctionType::Linear;
+        f.slope = 127;
+        f.intercept = 10;
+        assert_eq! (f.apply (200), 109);
+
+        f.function_type = FunctionType::Gamma;
+        f.amplitude = 255;
+        f.offset = 0;
+        f.exponent = 2.0;
+        assert_eq! (f.apply (128), 64);
+
+        f.exponent = 0.5;
+        assert_eq! (f.apply (64), 127);
+    }
+
+    #[test]
+    fn applies_to_unpremultiplied_colors () {
+        let mut s = Surface::new (1, 1);
+        s.set_pixel (0, 0, [64, 0, 0, 128]);
+
+        let invert = function (FunctionType::Table, &[1.0, 0.0]);
+        let functions = [invert,
+                         TransferFunction::identity (),
+                         TransferFunction::identity (),
+                         TransferFunction::identity ()];
+
+        let out = component_transfer (&functions, &s, IRect::new (0, 0, 1, 1));
+
+        // 64 unpremultiplies to 127, which inverts to 128
+        assert_eq! (out.get_pixel (0, 0), [64, 0, 0, 128]);
+    }
+}
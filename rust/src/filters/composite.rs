@@ -0,0 +1,242 @@
+use ::libc;
+
+use std::cell::{Cell, RefCell};
+use std::cmp::{min, max};
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Input, Primitive, Surface, parse_input, parse_number};
+use super::surface::A;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Operator {
+    Over,
+    In,
+    Out,
+    Atop,
+    Xor,
+    Arithmetic
+}
+
+impl Operator {
+    // Unknown operators are taken as "over"
+    fn parse (s: &str) -> Operator {
+        match s {
+            "in"         => Operator::In,
+            "out"        => Operator::Out,
+            "atop"       => Operator::Atop,
+            "xor"        => Operator::Xor,
+            "arithmetic" => Operator::Arithmetic,
+            _            => Operator::Over
+        }
+    }
+}
+
+/// The `feComposite` element.
+pub struct Composite {
+    base:     Primitive,
+    operator: Cell<Operator>,
+    in2:      RefCell<Option<Input>>,
+
+    // k1..k4, scaled by 255
+    k:        Cell<[i32; 4]>
+}
+
+impl Composite {
+    fn new () -> Composite {
+        Composite {
+            base:     Primitive::new (),
+            operator: Cell::new (Operator::Over),
+            in2:      RefCell::new (None),
+            k:        Cell::new ([0; 4])
+        }
+    }
+}
+
+impl NodeTrait for Composite {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.operator.set (property_bag::lookup (pbag, "operator").map_or (Operator::Over, |o| Operator::parse (&o)));
+        *self.in2.borrow_mut () = parse_input (pbag, "in2");
+
+        let mut k = [0; 4];
+        for (i, key) in ["k1", "k2", "k3", "k4"].iter ().enumerate () {
+            k[i] = (parse_number (pbag, key)?.unwrap_or (0.0) * 255.0) as i32;
+        }
+        self.k.set (k);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Composite {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let input2 = match ctx.get_input_surface (&self.in2.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let output = match self.operator.get () {
+            Operator::Arithmetic => arithmetic (self.k.get (), &input, &input2, bounds),
+            op                   => porter_duff (op, &input, &input2, bounds)
+        };
+
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn arithmetic (k: [i32; 4], input: &Surface, input2: &Surface, bounds: IRect) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ()).intersection (&input2.bounds ());
+
+    let [k1, k2, k3, k4] = k;
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let a = input.get_pixel (x, y);
+            let b = input2.get_pixel (x, y);
+
+            let qa = a[A] as i32;
+            let qb = b[A] as i32;
+            let qr = max (0, min ((k1 * qa * qb / 255 + k2 * qa + k3 * qb) / 255, 255));
+
+            let mut pixel = [0, 0, 0, qr as u8];
+
+            if qr != 0 {
+                for ch in 0..3 {
+                    let ca = a[ch] as i32;
+                    let cb = b[ch] as i32;
+                    let cr = (ca * cb * k1 / 255 + ca * k2 + cb * k3 + k4 * qr) / 255;
+
+                    pixel[ch] = max (0, min (cr, qr)) as u8;
+                }
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+// Multiplies two 8-bit values that represent numbers in [0, 1]
+fn mul_un8 (a: u8, b: u8) -> u8 {
+    let t = a as u32 * b as u32 + 0x80;
+
+    (((t >> 8) + t) >> 8) as u8
+}
+
+// Composites `input` onto a copy of `input2` within `bounds`; outside of
+// them the result is just `input2`.
+pub fn porter_duff (operator: Operator, input: &Surface, input2: &Surface, bounds: IRect) -> Surface {
+    let mut output = input2.clone ();
+    let bounds = bounds.intersection (&input.bounds ()).intersection (&input2.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let s = input.get_pixel (x, y);
+            let d = input2.get_pixel (x, y);
+
+            let sa = s[A];
+            let da = d[A];
+
+            let mut pixel = [0u8; 4];
+
+            for ch in 0..4 {
+                pixel[ch] = match operator {
+                    Operator::Over => s[ch].saturating_add (mul_un8 (d[ch], 255 - sa)),
+                    Operator::In   => mul_un8 (s[ch], da),
+                    Operator::Out  => mul_un8 (s[ch], 255 - da),
+                    Operator::Atop => mul_un8 (s[ch], da).saturating_add (mul_un8 (d[ch], 255 - sa)),
+                    Operator::Xor  => mul_un8 (s[ch], 255 - da).saturating_add (mul_un8 (d[ch], 255 - sa)),
+
+                    Operator::Arithmetic => unreachable! ()
+                };
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_composite (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveComposite,
+                    raw_parent,
+                    Box::new (Composite::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel (p: [u8; 4]) -> Surface {
+        let mut s = Surface::new (1, 1);
+        s.set_pixel (0, 0, p);
+        s
+    }
+
+    fn composite (op: Operator, a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+        porter_duff (op, &pixel (a), &pixel (b), IRect::new (0, 0, 1, 1)).get_pixel (0, 0)
+    }
+
+    #[test]
+    fn porter_duff_operators () {
+        let half_red = [128, 0, 0, 128];
+        let blue = [0, 0, 255, 255];
+
+        assert_eq! (composite (Operator::Over, half_red, blue), [128, 0, 127, 255]);
+        assert_eq! (composite (Operator::In, half_red, blue), half_red);
+        assert_eq! (composite (Operator::Out, half_red, blue), [0, 0, 0, 0]);
+        assert_eq! (composite (Operator::Atop, half_red, blue), [128, 0, 127, 255]);
+        assert_eq! (composite (Operator::Xor, half_red, blue), [0, 0, 127, 127]);
+    }
+
+    #[test]
+    fn leaves_in2_outside_of_bounds () {
+        let mut a = Surface::new (2, 1);
+        let mut b = Surface::new (2, 1);
+        a.set_pixel (0, 0, [255, 0, 0, 255]);
+        a.set_pixel (1, 0, [255, 0, 0, 255]);
+        b.set_pixel (0, 0, [0, 255, 0, 255]);
+        b.set_pixel (1, 0, [0, 255, 0, 255]);
+
+        let out = porter_duff (Operator::Over, &a, &b, IRect::new (1, 0, 2, 1));
+
+        assert_eq! (out.get_pixel (0, 0), [0, 255, 0, 255]);
+        assert_eq! (out.get_pixel (1, 0), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn arithmetic_adds_inputs () {
+        // k2 = k3 = 0.5
+        let out = arithmetic ([0, 127, 127, 0],
+                              &pixel ([255, 0, 0, 255]),
+                              &pixel ([0, 0, 255, 255]),
+                              IRect::new (0, 0, 1, 1));
+
+        assert_eq! (out.get_pixel (0, 0), [127, 0, 127, 254]);
+    }
+}
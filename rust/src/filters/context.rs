@@ -0,0 +1,330 @@
+use ::cairo;
+use ::cairo::MatrixTrait;
+use ::glib::translate::*;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use bbox::RsvgBbox;
+use drawing_ctx;
+use drawing_ctx::RsvgDrawingCtx;
+use length::RsvgLength;
+use paint_server::PaintServerUnits;
+
+use super::{Input, NodeFilter, Primitive};
+use super::surface::{IRect, Surface};
+
+/// A primitive's result, along with the subregion it was computed for.
+#[derive(Debug, Clone)]
+pub struct FilterOutput {
+    pub surface: Rc<Surface>,
+    pub bounds:  IRect
+}
+
+// A rectangle in device space, as (x0, y0, x1, y1)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DeviceRect (pub f64, pub f64, pub f64, pub f64);
+
+// Returns the bounding box of the rectangle x, y, w, h transformed by the matrix
+fn transform_rect (affine: &cairo::Matrix, x: f64, y: f64, w: f64, h: f64) -> DeviceRect {
+    let corners = [(x, y), (x + w, y), (x, y + h), (x + w, y + h)];
+
+    let mut r = DeviceRect (::std::f64::MAX, ::std::f64::MAX, ::std::f64::MIN, ::std::f64::MIN);
+
+    for &(cx, cy) in corners.iter () {
+        let tx = affine.xx * cx + affine.xy * cy + affine.x0;
+        let ty = affine.yx * cx + affine.yy * cy + affine.y0;
+
+        r.0 = r.0.min (tx);
+        r.1 = r.1.min (ty);
+        r.2 = r.2.max (tx);
+        r.3 = r.3.max (ty);
+    }
+
+    r
+}
+
+// Returns `src` clamped into `dst`; if they don't intersect, the result
+// is empty and lies on the edge of `dst`.
+fn clip_rect (dst: DeviceRect, src: DeviceRect) -> DeviceRect {
+    let x0 = src.0.min (dst.2).max (dst.0);
+    let y0 = src.1.min (dst.3).max (dst.1);
+    let x1 = src.2.max (dst.0).min (dst.2);
+    let y1 = src.3.max (dst.1).min (dst.3);
+
+    DeviceRect (x0, y0, x1, y1)
+}
+
+fn device_rect_to_irect (r: DeviceRect) -> IRect {
+    IRect::new (r.0 as i32, r.1 as i32, r.2 as i32, r.3 as i32)
+}
+
+/// The state of a filter while its primitives are being rendered.
+pub struct FilterContext {
+    draw_ctx:        *const RsvgDrawingCtx,
+    primitive_units: PaintServerUnits,
+
+    width:           i32,
+    height:          i32,
+
+    // Transform from primitiveUnits to device space
+    paffine:         cairo::Matrix,
+
+    // The filter region, in device space
+    region:          DeviceRect,
+
+    source:          Rc<Surface>,
+    background:      Option<Option<Rc<Surface>>>,
+
+    results:         HashMap<String, FilterOutput>,
+    last_result:     FilterOutput
+}
+
+impl FilterContext {
+    pub fn new (filter:   &NodeFilter,
+                source:   Surface,
+                draw_ctx: *const RsvgDrawingCtx,
+                bbox:     &RsvgBbox) -> FilterContext {
+        let state_affine = drawing_ctx::get_current_state_affine (draw_ctx);
+
+        let bbox_affine = cairo::Matrix::new (bbox.rect.width, 0.0,
+                                              0.0, bbox.rect.height,
+                                              bbox.rect.x, bbox.rect.y);
+
+        let units_affine = |units| {
+            if units == PaintServerUnits::ObjectBoundingBox {
+                cairo::Matrix::multiply (&bbox_affine, &state_affine)
+            } else {
+                state_affine
+            }
+        };
+
+        let filter_units = filter.filter_units.get ();
+        let primitive_units = filter.primitive_units.get ();
+
+        let affine = units_affine (filter_units);
+        let paffine = units_affine (primitive_units);
+
+        if filter_units == PaintServerUnits::ObjectBoundingBox {
+            drawing_ctx::push_view_box (draw_ctx, 1.0, 1.0);
+        }
+
+        let region = transform_rect (&affine,
+                                     filter.x.get ().normalize (draw_ctx),
+                                     filter.y.get ().normalize (draw_ctx),
+                                     filter.width.get ().normalize (draw_ctx),
+                                     filter.height.get ().normalize (draw_ctx));
+
+        if filter_units == PaintServerUnits::ObjectBoundingBox {
+            drawing_ctx::pop_view_box (draw_ctx);
+        }
+
+        FilterContext::with_region (source, draw_ctx, primitive_units, paffine, region)
+    }
+
+    /// Creates a context for an already resolved filter region, in
+    /// device space, and transformation for the primitives.
+    pub fn with_region (source:          Surface,
+                        draw_ctx:        *const RsvgDrawingCtx,
+                        primitive_units: PaintServerUnits,
+                        paffine:         cairo::Matrix,
+                        region:          DeviceRect) -> FilterContext {
+        let width = source.width ();
+        let height = source.height ();
+        let source = Rc::new (source);
+
+        let mut ctx = FilterContext {
+            draw_ctx:        draw_ctx,
+            primitive_units: primitive_units,
+            width:           width,
+            height:          height,
+            paffine:         paffine,
+            region:          region,
+            source:          source.clone (),
+            background:      None,
+            results:         HashMap::new (),
+            last_result:     FilterOutput {
+                surface: source,
+                bounds:  IRect::new (0, 0, 0, 0)
+            }
+        };
+
+        ctx.last_result.bounds = ctx.subregion_bounds (None);
+
+        ctx
+    }
+
+    pub fn draw_ctx (&self) -> *const RsvgDrawingCtx {
+        self.draw_ctx
+    }
+
+    pub fn width (&self) -> i32 {
+        self.width
+    }
+
+    pub fn height (&self) -> i32 {
+        self.height
+    }
+
+    pub fn bounds (&self) -> IRect {
+        IRect::new (0, 0, self.width, self.height)
+    }
+
+    /// The transformation from the primitives' coordinate system to device space
+    pub fn paffine (&self) -> cairo::Matrix {
+        self.paffine
+    }
+
+    /// Computes the region that a primitive operates on: its subregion,
+    /// clipped to the filter region and to the surface.
+    pub fn primitive_bounds (&self, primitive: Option<&Primitive>) -> IRect {
+        match primitive {
+            Some (p) if p.has_subregion () => {
+                let subregion = self.resolve_subregion (p);
+                self.subregion_bounds (Some (subregion))
+            },
+
+            _ => self.subregion_bounds (None)
+        }
+    }
+
+    // Resolves the primitive's x, y, width and height to (x, y, w, h) in primitiveUnits
+    fn resolve_subregion (&self, primitive: &Primitive) -> (f64, f64, f64, f64) {
+        let draw_ctx = self.draw_ctx;
+
+        if self.primitive_units == PaintServerUnits::ObjectBoundingBox {
+            drawing_ctx::push_view_box (draw_ctx, 1.0, 1.0);
+        }
+
+        let (vbox_width, vbox_height) = drawing_ctx::get_view_box_size (draw_ctx);
+        let normalize = |length: Option<RsvgLength>, default: f64| {
+            length.map_or (default, |l| l.normalize (draw_ctx))
+        };
+
+        let subregion = (normalize (primitive.x.get (), 0.0),
+                         normalize (primitive.y.get (), 0.0),
+                         normalize (primitive.width.get (), vbox_width),
+                         normalize (primitive.height.get (), vbox_height));
+
+        if self.primitive_units == PaintServerUnits::ObjectBoundingBox {
+            drawing_ctx::pop_view_box (draw_ctx);
+        }
+
+        subregion
+    }
+
+    /// Transforms a subregion given as (x, y, w, h) in primitiveUnits to
+    /// device space, and clips it to the filter region and to the
+    /// surface.  Without a subregion, this gives the filter region.
+    pub fn subregion_bounds (&self, subregion: Option<(f64, f64, f64, f64)>) -> IRect {
+        let mut rect = self.region;
+
+        if let Some ((x, y, w, h)) = subregion {
+            rect = clip_rect (rect, transform_rect (&self.paffine, x, y, w, h));
+        }
+
+        let surface_rect = DeviceRect (0.0, 0.0, self.width as f64, self.height as f64);
+
+        device_rect_to_irect (clip_rect (rect, surface_rect))
+    }
+
+    fn background (&mut self) -> Option<Rc<Surface>> {
+        if self.background.is_none () {
+            let bg = drawing_ctx::get_background_image (self.draw_ctx)
+                .and_then (|s| Surface::from_cairo (s.to_glib_none ().0))
+                .map (Rc::new);
+
+            self.background = Some (bg);
+        }
+
+        self.background.as_ref ().unwrap ().clone ()
+    }
+
+    /// Looks up the result with the given name, or the previous result
+    /// for None.  A reference to a result that does not exist is taken
+    /// to mean the previous result.
+    pub fn get_result (&self, name: Option<&str>) -> FilterOutput {
+        name.and_then (|n| self.results.get (n)).unwrap_or (&self.last_result).clone ()
+    }
+
+    /// Looks up a primitive's input.
+    pub fn get_input (&mut self, input: &Option<Input>) -> Option<FilterOutput> {
+        let full = self.bounds ();
+
+        let surface = match *input {
+            None => return Some (self.get_result (None)),
+
+            Some (Input::SourceGraphic)   => self.source.clone (),
+            Some (Input::SourceAlpha)     => Rc::new (self.source.alpha_only ()),
+            Some (Input::BackgroundImage) => self.background ()?,
+            Some (Input::BackgroundAlpha) => Rc::new (self.background ()?.alpha_only ()),
+
+            Some (Input::Reference (ref name)) => return Some (self.get_result (Some (name)))
+        };
+
+        Some (FilterOutput {
+            surface: surface,
+            bounds:  full
+        })
+    }
+
+    pub fn get_input_surface (&mut self, input: &Option<Input>) -> Option<Rc<Surface>> {
+        self.get_input (input).map (|output| output.surface)
+    }
+
+    /// Stores the result of a primitive, which becomes the input for the next one.
+    pub fn store_output (&mut self, name: &Option<String>, output: FilterOutput) {
+        if let Some (ref name) = *name {
+            self.results.insert (name.clone (), output.clone ());
+        }
+
+        self.last_result = output;
+    }
+
+    /// Stores a result that covers the whole surface.
+    pub fn store_result (&mut self, name: &Option<String>, surface: Surface) {
+        let bounds = self.bounds ();
+
+        self.store_output (name, FilterOutput {
+            surface: Rc::new (surface),
+            bounds:  bounds
+        });
+    }
+
+    /// Consumes the context and returns the filter's final result.
+    pub fn into_output (self) -> Surface {
+        let FilterContext { last_result, results, source, background, .. } = self;
+
+        drop (results);
+        drop (source);
+        drop (background);
+
+        Rc::try_unwrap (last_result.surface).unwrap_or_else (|rc| (*rc).clone ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_rect_to_bounding_box () {
+        let mut m = cairo::Matrix::new (0.0, 1.0, -1.0, 0.0, 10.0, 0.0);
+
+        assert_eq! (transform_rect (&m, 0.0, 0.0, 2.0, 3.0), DeviceRect (7.0, 0.0, 10.0, 2.0));
+
+        m = cairo::Matrix::new (2.0, 0.0, 0.0, 2.0, 1.0, 1.0);
+        assert_eq! (transform_rect (&m, 1.0, 1.0, 2.0, 2.0), DeviceRect (3.0, 3.0, 7.0, 7.0));
+    }
+
+    #[test]
+    fn clips_rects () {
+        let dst = DeviceRect (0.0, 0.0, 10.0, 10.0);
+
+        assert_eq! (clip_rect (dst, DeviceRect (-5.0, 2.0, 5.0, 20.0)), DeviceRect (0.0, 2.0, 5.0, 10.0));
+
+        let r = clip_rect (dst, DeviceRect (20.0, 20.0, 30.0, 30.0));
+        assert_eq! (device_rect_to_irect (r), IRect::new (10, 10, 10, 10));
+        assert! (device_rect_to_irect (r).is_empty ());
+    }
+}
@@ -0,0 +1,311 @@
+use ::cairo;
+use ::libc;
+
+use std::cell::RefCell;
+use std::cmp::{min, max};
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use node::*;
+use parsers;
+use parsers::{ListLength, NumberListError, ParseError};
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface, parse_number, parse_number_optional_number};
+use super::surface::A;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum EdgeMode {
+    Duplicate,
+    Wrap,
+    None
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Params {
+    order_x:            i32,
+    order_y:            i32,
+    kernel:             Vec<f64>,
+    divisor:            f64,
+    bias:               f64,
+    target_x:           i32,
+    target_y:           i32,
+    kernel_unit_length: Option<(f64, f64)>,
+    preserve_alpha:     bool,
+    edge_mode:          EdgeMode
+}
+
+/// The `feConvolveMatrix` element.
+pub struct ConvolveMatrix {
+    base:   Primitive,
+    params: RefCell<Option<Params>>
+}
+
+impl ConvolveMatrix {
+    fn new () -> ConvolveMatrix {
+        ConvolveMatrix {
+            base:   Primitive::new (),
+            params: RefCell::new (None)
+        }
+    }
+}
+
+fn parse_target (pbag: *const RsvgPropertyBag, key: &str, order: i32) -> Result<i32, NodeError> {
+    match parse_number (pbag, key)? {
+        Some (t) if t >= 0.0 && t < order as f64 => Ok (t as i32),
+        Some (_) => Err (NodeError::value_error (key, "must be inside the kernel")),
+        None => Ok (order / 2)
+    }
+}
+
+impl NodeTrait for ConvolveMatrix {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        let (order_x, order_y) = match parse_number_optional_number (pbag, "order")? {
+            Some ((x, y)) => {
+                if x >= 1.0 && x <= 100.0 && y >= 1.0 && y <= 100.0 {
+                    (x as i32, y as i32)
+                } else {
+                    return Err (NodeError::value_error ("order", "invalid size for convolve matrix"));
+                }
+            },
+
+            None => (3, 3)
+        };
+
+        let num_elems = (order_x * order_y) as usize;
+
+        let kernel = match property_bag::lookup (pbag, "kernelMatrix") {
+            Some (v) => parsers::number_list (&v, ListLength::Exact (num_elems))
+                .map_err (|e| match e {
+                    NumberListError::IncorrectNumberOfElements =>
+                        NodeError::value_error ("kernelMatrix", "the number of elements does not match the order"),
+                    NumberListError::Parse (_) =>
+                        NodeError::parse_error ("kernelMatrix", ParseError::new ("expected a matrix of numbers"))
+                })?,
+
+            None => return Err (NodeError::value_error ("kernelMatrix", "attribute is required"))
+        };
+
+        let divisor = match parse_number (pbag, "divisor")? {
+            Some (d) if d != 0.0 => d,
+            _ => {
+                let sum: f64 = kernel.iter ().sum ();
+                if sum == 0.0 { 1.0 } else { sum }
+            }
+        };
+
+        let edge_mode = match property_bag::lookup (pbag, "edgeMode").as_ref ().map (String::as_str) {
+            None | Some ("duplicate") => EdgeMode::Duplicate,
+            Some ("wrap")             => EdgeMode::Wrap,
+            Some ("none")             => EdgeMode::None,
+            Some (_)                  => {
+                return Err (NodeError::parse_error ("edgeMode",
+                                                    ParseError::new ("expected 'duplicate' | 'wrap' | 'none'")));
+            }
+        };
+
+        *self.params.borrow_mut () = Some (Params {
+            order_x:            order_x,
+            order_y:            order_y,
+            kernel:             kernel,
+            divisor:            divisor,
+            bias:               parse_number (pbag, "bias")?.unwrap_or (0.0),
+            target_x:           parse_target (pbag, "targetX", order_x)?,
+            target_y:           parse_target (pbag, "targetY", order_y)?,
+            kernel_unit_length: parse_number_optional_number (pbag, "kernelUnitLength")?,
+            preserve_alpha:     property_bag::lookup (pbag, "preserveAlpha").map_or (false, |v| v == "true"),
+            edge_mode:          edge_mode
+        });
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for ConvolveMatrix {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let params = self.params.borrow ();
+        let params = params.as_ref ().unwrap ();
+
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let output = convolve (params, &input, bounds, &ctx.paffine ());
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn convolve (params: &Params, input: &Surface, bounds: IRect, paffine: &cairo::Matrix) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ());
+
+    let target_x = params.target_x as f64 * paffine.xx;
+    let target_y = params.target_y as f64 * paffine.yy;
+
+    let (dx, dy) = match params.kernel_unit_length {
+        Some ((dx, dy)) if dx != 0.0 || dy != 0.0 => (dx * paffine.xx, dy * paffine.yy),
+        _ => (1.0, 1.0)
+    };
+
+    let num_channels = if params.preserve_alpha { 3 } else { 4 };
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let mut pixel = [0u8; 4];
+
+            for ch in 0..num_channels {
+                let mut sum = 0.0;
+
+                for i in 0..params.order_y {
+                    for j in 0..params.order_x {
+                        let mut sx = (x as f64 - target_x + j as f64 * dx) as i32;
+                        let mut sy = (y as f64 - target_y + i as f64 * dy) as i32;
+
+                        match params.edge_mode {
+                            EdgeMode::Duplicate => {
+                                sx = max (bounds.x0, min (sx, bounds.x1 - 1));
+                                sy = max (bounds.y0, min (sy, bounds.y1 - 1));
+                            },
+
+                            EdgeMode::Wrap => {
+                                sx = bounds.x0 + (sx - bounds.x0).rem_euclid (bounds.width ());
+                                sy = bounds.y0 + (sy - bounds.y0).rem_euclid (bounds.height ());
+                            },
+
+                            EdgeMode::None => {
+                                if !bounds.contains (sx, sy) {
+                                    continue;
+                                }
+                            }
+                        }
+
+                        let src = input.get_pixel (sx, sy);
+                        let alpha = src[A] as u32;
+
+                        // The kernel is applied to unpremultiplied values
+                        let sval = if ch == A {
+                            alpha
+                        } else if alpha != 0 {
+                            min (src[ch] as u32 * 255 / alpha, 255)
+                        } else {
+                            0
+                        };
+
+                        let kx = params.order_x - j - 1;
+                        let ky = params.order_y - i - 1;
+                        let kval = params.kernel[(kx + ky * params.order_x) as usize];
+
+                        sum += sval as f64 * kval;
+                    }
+                }
+
+                let result = (sum / params.divisor + params.bias) as i32;
+                pixel[ch] = max (0, min (result, 255)) as u8;
+            }
+
+            if params.preserve_alpha {
+                pixel[A] = input.get (x, y, A);
+            }
+
+            for ch in 0..3 {
+                pixel[ch] = (pixel[ch] as u32 * pixel[A] as u32 / 255) as u8;
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_convolve_matrix (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveConvolveMatrix,
+                    raw_parent,
+                    Box::new (ConvolveMatrix::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo::MatrixTrait;
+
+    fn params (edge_mode: EdgeMode, kernel: Vec<f64>) -> Params {
+        Params {
+            order_x:            3,
+            order_y:            1,
+            kernel:             kernel,
+            divisor:            1.0,
+            bias:               0.0,
+            target_x:           1,
+            target_y:           0,
+            kernel_unit_length: None,
+            preserve_alpha:     false,
+            edge_mode:          edge_mode
+        }
+    }
+
+    fn row () -> Surface {
+        let mut s = Surface::new (3, 1);
+        s.set_pixel (0, 0, [10, 10, 10, 255]);
+        s.set_pixel (1, 0, [20, 20, 20, 255]);
+        s.set_pixel (2, 0, [30, 30, 30, 255]);
+        s
+    }
+
+    fn reds (s: &Surface) -> Vec<u8> {
+        (0..s.width ()).map (|x| s.get (x, 0, 0)).collect ()
+    }
+
+    #[test]
+    fn flips_the_kernel () {
+        // the kernel is rotated 180 degrees, so [1 0 0] picks the pixel on the right
+        let p = params (EdgeMode::None, vec! [1.0, 0.0, 0.0]);
+        let out = convolve (&p, &row (), IRect::new (0, 0, 3, 1), &cairo::Matrix::identity ());
+
+        assert_eq! (reds (&out), vec! [20, 30, 0]);
+    }
+
+    #[test]
+    fn handles_edge_modes () {
+        let kernel = vec! [0.0, 0.0, 1.0];
+        let bounds = IRect::new (0, 0, 3, 1);
+        let m = cairo::Matrix::identity ();
+
+        assert_eq! (reds (&convolve (&params (EdgeMode::Duplicate, kernel.clone ()), &row (), bounds, &m)),
+                    vec! [10, 10, 20]);
+        assert_eq! (reds (&convolve (&params (EdgeMode::Wrap, kernel.clone ()), &row (), bounds, &m)),
+                    vec! [30, 10, 20]);
+        assert_eq! (reds (&convolve (&params (EdgeMode::None, kernel), &row (), bounds, &m)),
+                    vec! [0, 10, 20]);
+    }
+
+    #[test]
+    fn preserves_alpha () {
+        let mut s = Surface::new (1, 1);
+        s.set_pixel (0, 0, [64, 64, 64, 128]);
+
+        let mut p = params (EdgeMode::Duplicate, vec! [0.0, 2.0, 0.0]);
+        p.preserve_alpha = true;
+
+        let out = convolve (&p, &s, IRect::new (0, 0, 1, 1), &cairo::Matrix::identity ());
+
+        // unpremultiplied 127, doubled to 254, premultiplied by 128
+        assert_eq! (out.get_pixel (0, 0), [127, 127, 127, 128]);
+    }
+}
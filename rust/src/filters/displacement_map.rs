@@ -0,0 +1,146 @@
+use ::libc;
+
+use std::cell::{Cell, RefCell};
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use node::*;
+use parsers::ParseError;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Input, Primitive, Surface, parse_input, parse_number};
+use super::surface::{R, G, B, A};
+
+/// The `feDisplacementMap` element.
+pub struct DisplacementMap {
+    base:      Primitive,
+    in2:       RefCell<Option<Input>>,
+    scale:     Cell<f64>,
+    x_channel: Cell<usize>,
+    y_channel: Cell<usize>
+}
+
+impl DisplacementMap {
+    fn new () -> DisplacementMap {
+        DisplacementMap {
+            base:      Primitive::new (),
+            in2:       RefCell::new (None),
+            scale:     Cell::new (0.0),
+            x_channel: Cell::new (A),
+            y_channel: Cell::new (A)
+        }
+    }
+}
+
+fn parse_channel_selector (pbag: *const RsvgPropertyBag, key: &str) -> Result<usize, NodeError> {
+    match property_bag::lookup (pbag, key).as_ref ().map (String::as_str) {
+        Some ("R")     => Ok (R),
+        Some ("G")     => Ok (G),
+        Some ("B")     => Ok (B),
+        Some ("A") | None => Ok (A),
+        Some (_)       => Err (NodeError::parse_error (key, ParseError::new ("expected R | G | B | A")))
+    }
+}
+
+impl NodeTrait for DisplacementMap {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        *self.in2.borrow_mut () = parse_input (pbag, "in2");
+        self.scale.set (parse_number (pbag, "scale")?.unwrap_or (0.0));
+        self.x_channel.set (parse_channel_selector (pbag, "xChannelSelector")?);
+        self.y_channel.set (parse_channel_selector (pbag, "yChannelSelector")?);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for DisplacementMap {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let map = match ctx.get_input_surface (&self.in2.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let paffine = ctx.paffine ();
+        let scale = (self.scale.get () * paffine.xx, self.scale.get () * paffine.yy);
+
+        let output = displace (&input, &map, bounds, scale, (self.x_channel.get (), self.y_channel.get ()));
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn displace (input: &Surface, map: &Surface, bounds: IRect, scale: (f64, f64), channels: (usize, usize)) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ()).intersection (&map.bounds ());
+
+    let (scale_x, scale_y) = scale;
+    let (x_channel, y_channel) = channels;
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let m = map.get_pixel (x, y);
+
+            let ox = x as f64 + scale_x * (m[x_channel] as f64 / 255.0 - 0.5);
+            let oy = y as f64 + scale_y * (m[y_channel] as f64 / 255.0 - 0.5);
+
+            let mut pixel = [0u8; 4];
+            for ch in 0..4 {
+                pixel[ch] = input.get_interpolated (ox, oy, ch, bounds);
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_displacement_map (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveDisplacementMap,
+                    raw_parent,
+                    Box::new (DisplacementMap::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displaces_by_map_channel () {
+        let mut s = Surface::new (3, 1);
+        s.set_pixel (0, 0, [10, 10, 10, 10]);
+        s.set_pixel (1, 0, [20, 20, 20, 20]);
+        s.set_pixel (2, 0, [30, 30, 30, 30]);
+
+        // red = 255 moves the lookup half of the scale to the right
+        let mut map = Surface::new (3, 1);
+        for x in 0..3 {
+            map.set_pixel (x, 0, [255, 0, 0, 0]);
+        }
+
+        let out = displace (&s, &map, s.bounds (), (2.0, 0.0), (R, G));
+
+        assert_eq! (out.get_pixel (0, 0), [20, 20, 20, 20]);
+        assert_eq! (out.get_pixel (1, 0), [30, 30, 30, 30]);
+        assert_eq! (out.get_pixel (2, 0), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,98 @@
+use ::libc;
+
+use std::rc::Rc;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use opacity::opacity_to_u8;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, FilterOutput, IRect, Primitive, Surface};
+
+/// The `feFlood` element.
+pub struct Flood {
+    base: Primitive
+}
+
+impl Flood {
+    fn new () -> Flood {
+        Flood {
+            base: Primitive::new ()
+        }
+    }
+}
+
+impl NodeTrait for Flood {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Flood {
+    fn render (&self, node: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        // The flood's color comes from the flood-color and flood-opacity
+        // properties, with currentColor resolved against the color property.
+        let values = node.get_computed_values ();
+        let rgba = values.resolve_color (&values.flood_color.0);
+        let alpha = opacity_to_u8 (rgba.alpha_f32 () as f64 * values.flood_opacity.0);
+
+        let output = flood (ctx.width (), ctx.height (), bounds, [rgba.red, rgba.green, rgba.blue, alpha]);
+
+        ctx.store_output (&self.base.result.borrow (), FilterOutput {
+            surface: Rc::new (output),
+            bounds:  bounds
+        });
+    }
+}
+
+// Fills `bounds` with an unpremultiplied color
+pub fn flood (width: i32, height: i32, bounds: IRect, color: [u8; 4]) -> Surface {
+    let mut output = Surface::new (width, height);
+    let bounds = bounds.intersection (&output.bounds ());
+
+    let alpha = color[3] as u32;
+    let pixel = [(color[0] as u32 * alpha / 255) as u8,
+                 (color[1] as u32 * alpha / 255) as u8,
+                 (color[2] as u32 * alpha / 255) as u8,
+                 color[3]];
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_flood (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveFlood,
+                    raw_parent,
+                    Box::new (Flood::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_bounds_with_premultiplied_color () {
+        let out = flood (3, 1, IRect::new (1, 0, 5, 1), [255, 128, 0, 128]);
+
+        assert_eq! (out.get_pixel (0, 0), [0, 0, 0, 0]);
+        assert_eq! (out.get_pixel (1, 0), [128, 64, 0, 128]);
+        assert_eq! (out.get_pixel (2, 0), [128, 64, 0, 128]);
+    }
+}
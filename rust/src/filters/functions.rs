@@ -0,0 +1,425 @@
+//! Filter functions, the shorthands like `blur()` or `sepia()` that
+//! can be used in the `filter` property instead of, or along with,
+//! references to `<filter>` elements.
+//!
+//! https://www.w3.org/TR/filter-effects-1/#filter-functions
+//!
+//! Each function is lowered to the same operations that the equivalent
+//! chain of filter primitives would perform.
+
+use ::cssparser;
+use ::cssparser::{Parser, ParserInput, Token};
+
+use bbox::RsvgBbox;
+use color::*;
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use length::*;
+use parsers::{Parse, ParseError};
+use properties::ComputedValues;
+
+use super::{Input, IRect, NodeFilter, FilterContext, Surface};
+use super::color_matrix;
+use super::component_transfer::{self, TransferFunction};
+use super::composite::{self, Operator};
+use super::flood;
+use super::gaussian_blur;
+use super::merge;
+use super::offset;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterFunction {
+    Url (String),
+    Blur (RsvgLength),
+    Brightness (f64),
+    Contrast (f64),
+    DropShadow { dx: RsvgLength, dy: RsvgLength, std_deviation: RsvgLength, color: Color },
+    Grayscale (f64),
+    HueRotate (f64),
+    Invert (f64),
+    Opacity (f64),
+    Saturate (f64),
+    Sepia (f64)
+}
+
+fn make_err (msg: &str) -> AttributeError {
+    AttributeError::Parse (ParseError::new (msg))
+}
+
+/// Parses the value of the `filter` property.  The result is empty for `none`.
+pub fn parse_filter_functions (s: &str) -> Result<Vec<FilterFunction>, AttributeError> {
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+
+    let mut functions = Vec::new ();
+
+    if parser.try (|p| p.expect_ident_matching ("none")).is_ok () {
+        parser.expect_exhausted ().map_err (|_| make_err ("expected 'none' or a list of filters"))?;
+        return Ok (functions);
+    }
+
+    while !parser.is_exhausted () {
+        if let Ok (url) = parser.try (|p| p.expect_url ()) {
+            functions.push (FilterFunction::Url (url.to_string ()));
+            continue;
+        }
+
+        let name = parser.expect_function ()
+            .map_err (|_| make_err ("expected url() or a filter function"))?
+            .to_string ();
+
+        let args = parser.parse_nested_block (|p| parse_arguments (p))
+            .map_err (|_: cssparser::ParseError<()>| make_err ("invalid filter function arguments"))?;
+
+        functions.push (parse_function (&name, &args)?);
+    }
+
+    if functions.is_empty () {
+        return Err (make_err ("expected 'none' or a list of filters"));
+    }
+
+    Ok (functions)
+}
+
+// Splits the arguments of a function into strings, separated by
+// whitespace or commas; nested functions like rgb() stay in one piece.
+fn parse_arguments<'i, 't> (parser: &mut Parser<'i, 't>) -> Result<Vec<String>, cssparser::ParseError<'i, ()>> {
+    let mut args = Vec::new ();
+
+    loop {
+        let start = parser.position ();
+
+        let is_block = match parser.next () {
+            Ok (&Token::Comma) => continue,
+            Ok (&Token::Function (_)) | Ok (&Token::ParenthesisBlock) => true,
+            Ok (_) => false,
+            Err (_) => break
+        };
+
+        if is_block {
+            parser.parse_nested_block (|p| {
+                while p.next ().is_ok () {}
+                Ok (())
+            }).map_err (|e: cssparser::ParseError<()>| e.basic ())?;
+        }
+
+        args.push (parser.slice_from (start).trim ().to_string ());
+    }
+
+    Ok (args)
+}
+
+fn parse_function (name: &str, args: &[String]) -> Result<FilterFunction, AttributeError> {
+    if name == "drop-shadow" {
+        return parse_drop_shadow (args);
+    }
+
+    if args.len () > 1 {
+        return Err (make_err ("too many arguments for filter function"));
+    }
+
+    let arg = args.first ().map (String::as_str);
+
+    match name {
+        "blur" => {
+            let std_deviation = match arg {
+                Some (a) => RsvgLength::parse (a, LengthDir::Both)?.check_nonnegative ()?,
+                None => RsvgLength::new (0.0, LengthUnit::Default, LengthDir::Both)
+            };
+
+            Ok (FilterFunction::Blur (std_deviation))
+        },
+
+        "brightness" => Ok (FilterFunction::Brightness (parse_amount (arg)?)),
+        "contrast"   => Ok (FilterFunction::Contrast (parse_amount (arg)?)),
+        "grayscale"  => Ok (FilterFunction::Grayscale (parse_amount (arg)?.min (1.0))),
+        "hue-rotate" => Ok (FilterFunction::HueRotate (arg.map_or (Ok (0.0), parse_angle)?)),
+        "invert"     => Ok (FilterFunction::Invert (parse_amount (arg)?.min (1.0))),
+        "opacity"    => Ok (FilterFunction::Opacity (parse_amount (arg)?.min (1.0))),
+        "saturate"   => Ok (FilterFunction::Saturate (parse_amount (arg)?)),
+        "sepia"      => Ok (FilterFunction::Sepia (parse_amount (arg)?.min (1.0))),
+
+        _ => Err (make_err ("unknown filter function"))
+    }
+}
+
+// drop-shadow(<length>{2,3} <color>?), with the color on either side
+fn parse_drop_shadow (args: &[String]) -> Result<FilterFunction, AttributeError> {
+    let is_length = |arg: &String| RsvgLength::parse (arg, LengthDir::Both).is_ok ();
+
+    let (color, lengths) = match (args.first (), args.last ()) {
+        (Some (first), _) if !is_length (first) => (Some (first), &args[1..]),
+        (_, Some (last)) if !is_length (last)   => (Some (last), &args[..args.len () - 1]),
+        _                                       => (None, args)
+    };
+
+    let color = match color {
+        Some (c) => Color::parse (c, (AllowInherit::No, AllowCurrentColor::Yes))?,
+        None => Color::CurrentColor
+    };
+
+    let lengths = lengths.iter ()
+        .map (|l| RsvgLength::parse (l, LengthDir::Both))
+        .collect::<Result<Vec<_>, _>> ()?;
+
+    let zero = RsvgLength::new (0.0, LengthUnit::Default, LengthDir::Both);
+
+    let std_deviation = match lengths.len () {
+        2 => zero,
+        3 => lengths[2].check_nonnegative ()?,
+        _ => return Err (make_err ("drop-shadow() needs two or three lengths"))
+    };
+
+    Ok (FilterFunction::DropShadow {
+        dx:            lengths[0],
+        dy:            lengths[1],
+        std_deviation: std_deviation,
+        color:         color
+    })
+}
+
+fn parse_single_token<T, F> (s: &str, f: F) -> Option<T>
+    where F: FnOnce (&Token) -> Option<T>
+{
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+
+    let result = match parser.next () {
+        Ok (token) => f (token),
+        Err (_) => None
+    };
+
+    if parser.is_exhausted () {
+        result
+    } else {
+        None
+    }
+}
+
+// <number> | <percentage>, non-negative; missing values are 1
+fn parse_amount (arg: Option<&str>) -> Result<f64, AttributeError> {
+    let arg = match arg {
+        Some (a) => a,
+        None => return Ok (1.0)
+    };
+
+    let amount = parse_single_token (arg, |token| match *token {
+        Token::Number { value, .. }          => Some (value as f64),
+        Token::Percentage { unit_value, .. } => Some (unit_value as f64),
+        _                                    => None
+    }).ok_or_else (|| make_err ("expected number or percentage"))?;
+
+    if amount < 0.0 {
+        return Err (AttributeError::Value ("value must be non-negative".to_string ()));
+    }
+
+    Ok (amount)
+}
+
+// <angle>, in degrees; a unitless zero is allowed too
+fn parse_angle (arg: &str) -> Result<f64, AttributeError> {
+    parse_single_token (arg, |token| match *token {
+        Token::Number { int_value: Some (0), .. } => Some (0.0),
+
+        Token::Dimension { value, ref unit, .. } => {
+            let value = value as f64;
+
+            match unit.to_ascii_lowercase ().as_str () {
+                "deg"  => Some (value),
+                "grad" => Some (value * 360.0 / 400.0),
+                "rad"  => Some (value.to_degrees ()),
+                "turn" => Some (value * 360.0),
+                _      => None
+            }
+        },
+
+        _ => None
+    }).ok_or_else (|| make_err ("expected angle"))
+}
+
+fn grayscale (amount: f64) -> color_matrix::Kernel {
+    let s = 1.0 - amount;
+
+    color_matrix::from_values (&[0.2126 + 0.7874 * s, 0.7152 - 0.7152 * s, 0.0722 - 0.0722 * s, 0.0, 0.0,
+                                 0.2126 - 0.2126 * s, 0.7152 + 0.2848 * s, 0.0722 - 0.0722 * s, 0.0, 0.0,
+                                 0.2126 - 0.2126 * s, 0.7152 - 0.7152 * s, 0.0722 + 0.9278 * s, 0.0, 0.0,
+                                 0.0,                 0.0,                 0.0,                 1.0, 0.0])
+}
+
+fn sepia (amount: f64) -> color_matrix::Kernel {
+    let s = 1.0 - amount;
+
+    color_matrix::from_values (&[0.393 + 0.607 * s, 0.769 - 0.769 * s, 0.189 - 0.189 * s, 0.0, 0.0,
+                                 0.349 - 0.349 * s, 0.686 + 0.314 * s, 0.168 - 0.168 * s, 0.0, 0.0,
+                                 0.272 - 0.272 * s, 0.534 - 0.534 * s, 0.131 + 0.869 * s, 0.0, 0.0,
+                                 0.0,               0.0,               0.0,               1.0, 0.0])
+}
+
+// Transfer functions for the red, green, blue and alpha channels
+fn rgb_transfer (function: TransferFunction) -> [TransferFunction; 4] {
+    [function.clone (), function.clone (), function, TransferFunction::identity ()]
+}
+
+fn alpha_transfer (function: TransferFunction) -> [TransferFunction; 4] {
+    [TransferFunction::identity (), TransferFunction::identity (), TransferFunction::identity (), function]
+}
+
+// Like feGaussianBlur + feOffset + feFlood + feComposite + feMerge
+fn drop_shadow (input: &Surface, bounds: IRect, std_deviation: (f64, f64), offset: (i32, i32), color: [u8; 4]) -> Surface {
+    let mut shadow = gaussian_blur::blur (&input.alpha_only (), std_deviation.0, std_deviation.1);
+    shadow.clear_outside (bounds);
+
+    let shadow = offset::offset (&shadow, bounds, offset.0, offset.1);
+    let flood = flood::flood (input.width (), input.height (), bounds, color);
+    let shadow = composite::porter_duff (Operator::In, &flood, &shadow, bounds);
+
+    let mut output = Surface::new (input.width (), input.height ());
+    merge::composite_over (&shadow, &mut output, bounds);
+    merge::composite_over (input, &mut output, bounds);
+
+    output
+}
+
+/// Applies a filter function to `source`, over the default filter
+/// region of the element's bounding box.  The element's `values` are
+/// used to resolve currentColor.
+pub fn apply (function:   &FilterFunction,
+              source:     Surface,
+              values:     &ComputedValues,
+              draw_ctx:   *const RsvgDrawingCtx,
+              bbox:       &RsvgBbox) -> Surface {
+    let mut ctx = FilterContext::new (&NodeFilter::new (), source, draw_ctx, bbox);
+
+    let bounds = ctx.primitive_bounds (None);
+    let paffine = ctx.paffine ();
+
+    let input = match ctx.get_input_surface (&Some (Input::SourceGraphic)) {
+        Some (s) => s,
+        None => return Surface::new (ctx.width (), ctx.height ())
+    };
+
+    match *function {
+        FilterFunction::Url (_) => unreachable! (),
+
+        FilterFunction::Blur (ref std_deviation) => {
+            let sd = std_deviation.normalize (draw_ctx);
+
+            let mut output = gaussian_blur::blur (&input, (sd * paffine.xx).abs (), (sd * paffine.yy).abs ());
+            output.clear_outside (bounds);
+            output
+        },
+
+        FilterFunction::DropShadow { ref dx, ref dy, ref std_deviation, ref color } => {
+            let sd = std_deviation.normalize (draw_ctx);
+            let dx = dx.normalize (draw_ctx);
+            let dy = dy.normalize (draw_ctx);
+
+            let rgba = values.resolve_color (color);
+
+            drop_shadow (&input,
+                         bounds,
+                         ((sd * paffine.xx).abs (), (sd * paffine.yy).abs ()),
+                         ((paffine.xx * dx + paffine.xy * dy) as i32, (paffine.yx * dx + paffine.yy * dy) as i32),
+                         [rgba.red, rgba.green, rgba.blue, rgba.alpha])
+        },
+
+        FilterFunction::Grayscale (amount) => color_matrix::color_matrix (&grayscale (amount), &input, bounds),
+        FilterFunction::Sepia (amount)     => color_matrix::color_matrix (&sepia (amount), &input, bounds),
+        FilterFunction::Saturate (amount)  => color_matrix::color_matrix (&color_matrix::saturate (amount), &input, bounds),
+        FilterFunction::HueRotate (angle)  => color_matrix::color_matrix (&color_matrix::hue_rotate (angle), &input, bounds),
+
+        FilterFunction::Invert (amount) => {
+            let functions = rgb_transfer (TransferFunction::table (&[amount, 1.0 - amount]));
+            component_transfer::component_transfer (&functions, &input, bounds)
+        },
+
+        FilterFunction::Opacity (amount) => {
+            let functions = alpha_transfer (TransferFunction::table (&[0.0, amount]));
+            component_transfer::component_transfer (&functions, &input, bounds)
+        },
+
+        FilterFunction::Brightness (amount) => {
+            let functions = rgb_transfer (TransferFunction::linear (amount, 0.0));
+            component_transfer::component_transfer (&functions, &input, bounds)
+        },
+
+        FilterFunction::Contrast (amount) => {
+            let functions = rgb_transfer (TransferFunction::linear (amount, 0.5 - 0.5 * amount));
+            component_transfer::component_transfer (&functions, &input, bounds)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px (l: f64) -> RsvgLength {
+        RsvgLength::new (l, LengthUnit::Default, LengthDir::Both)
+    }
+
+    #[test]
+    fn parses_none_and_urls () {
+        assert_eq! (parse_filter_functions ("none"), Ok (Vec::new ()));
+        assert_eq! (parse_filter_functions ("url(#a) url(#b)"),
+                    Ok (vec! [FilterFunction::Url ("#a".to_string ()),
+                              FilterFunction::Url ("#b".to_string ())]));
+        assert! (parse_filter_functions ("").is_err ());
+        assert! (parse_filter_functions ("none blur()").is_err ());
+    }
+
+    #[test]
+    fn parses_function_chains () {
+        assert_eq! (parse_filter_functions ("blur(2px) url(#f) grayscale(50%) hue-rotate(0.5turn)"),
+                    Ok (vec! [FilterFunction::Blur (px (2.0)),
+                              FilterFunction::Url ("#f".to_string ()),
+                              FilterFunction::Grayscale (0.5),
+                              FilterFunction::HueRotate (180.0)]));
+    }
+
+    #[test]
+    fn parses_amounts () {
+        assert_eq! (parse_filter_functions ("brightness()"), Ok (vec! [FilterFunction::Brightness (1.0)]));
+        assert_eq! (parse_filter_functions ("contrast(200%)"), Ok (vec! [FilterFunction::Contrast (2.0)]));
+        assert_eq! (parse_filter_functions ("sepia(3)"), Ok (vec! [FilterFunction::Sepia (1.0)]));
+        assert_eq! (parse_filter_functions ("hue-rotate(0)"), Ok (vec! [FilterFunction::HueRotate (0.0)]));
+        assert! (parse_filter_functions ("invert(-1)").is_err ());
+        assert! (parse_filter_functions ("opacity(1, 2)").is_err ());
+        assert! (parse_filter_functions ("hue-rotate(90)").is_err ());
+        assert! (parse_filter_functions ("frobnicate(1)").is_err ());
+    }
+
+    #[test]
+    fn parses_drop_shadow () {
+        assert_eq! (parse_filter_functions ("drop-shadow(1px 2px)"),
+                    Ok (vec! [FilterFunction::DropShadow { dx: px (1.0), dy: px (2.0), std_deviation: px (0.0),
+                                                           color: Color::CurrentColor }]));
+
+        assert_eq! (parse_filter_functions ("drop-shadow(rgb(255, 0, 0) 1px 2px 3px)"),
+                    Ok (vec! [FilterFunction::DropShadow { dx: px (1.0), dy: px (2.0), std_deviation: px (3.0),
+                                                           color: Color::RGBA (cssparser::RGBA::new (255, 0, 0, 255)) }]));
+
+        assert! (parse_filter_functions ("drop-shadow(1px)").is_err ());
+        assert! (parse_filter_functions ("drop-shadow(1px red 2px)").is_err ());
+    }
+
+    #[test]
+    fn grayscale_zero_is_identity () {
+        assert_eq! (grayscale (0.0), color_matrix::from_values (&[1.0, 0.0, 0.0, 0.0, 0.0,
+                                                                  0.0, 1.0, 0.0, 0.0, 0.0,
+                                                                  0.0, 0.0, 1.0, 0.0, 0.0,
+                                                                  0.0, 0.0, 0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn drop_shadow_is_behind_input () {
+        let mut s = Surface::new (4, 1);
+        s.set_pixel (0, 0, [255, 255, 255, 255]);
+
+        let out = drop_shadow (&s, s.bounds (), (0.0, 0.0), (2, 0), [0, 0, 255, 255]);
+
+        assert_eq! (out.get_pixel (0, 0), [255, 255, 255, 255]);
+        assert_eq! (out.get_pixel (1, 0), [0, 0, 0, 0]);
+        assert_eq! (out.get_pixel (2, 0), [0, 0, 255, 255]);
+    }
+}
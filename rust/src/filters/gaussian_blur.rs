@@ -0,0 +1,437 @@
+use ::libc;
+
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, FilterOutput, Primitive, Surface, parse_number_optional_number};
+
+const BPP: usize = 4;
+
+/// The `feGaussianBlur` element.
+pub struct GaussianBlur {
+    base:          Primitive,
+    std_deviation: Cell<(f64, f64)>
+}
+
+impl GaussianBlur {
+    fn new () -> GaussianBlur {
+        GaussianBlur {
+            base:          Primitive::new (),
+            std_deviation: Cell::new ((0.0, 0.0))
+        }
+    }
+}
+
+impl NodeTrait for GaussianBlur {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.std_deviation.set (parse_number_optional_number (pbag, "stdDeviation")?.unwrap_or ((0.0, 0.0)));
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for GaussianBlur {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let paffine = ctx.paffine ();
+        let (sdx, sdy) = self.std_deviation.get ();
+
+        let mut output = blur (&input, (sdx * paffine.xx).abs (), (sdy * paffine.yy).abs ());
+
+        // Hard-clip to the primitive subregion
+        output.clear_outside (bounds);
+
+        ctx.store_output (&self.base.result.borrow (), FilterOutput {
+            surface: Rc::new (output),
+            bounds:  bounds
+        });
+    }
+}
+
+// Blurs one line of `len` pixels with a box of width `box_width`.  For
+// even widths, `even_offset` says whether the box is centered on the
+// boundary with the left (-1) or the right (1) neighbor.
+fn box_blur_line (box_width: i32, even_offset: i32, src: &[u8], dest: &mut [u8], len: i32) {
+    assert! (box_width > 0);
+
+    let mut ac = [0i32; BPP];
+
+    let mut lead = 0;
+    let mut output;
+    let mut trail;
+
+    if box_width % 2 != 0 {
+        output = lead - (box_width - 1) / 2;
+        trail = lead - box_width;
+    } else if even_offset == 1 {
+        output = lead + 1 - box_width / 2;
+        trail = lead - box_width;
+    } else if even_offset == -1 {
+        output = lead - box_width / 2;
+        trail = lead - box_width;
+    } else {
+        unreachable! ();
+    }
+
+    let px = |i: i32, ch: usize| i as usize * BPP + ch;
+
+    // As the box moves along the line, it has a leading edge and a
+    // trailing edge, and the output is in the middle.  Near the ends of
+    // the line only the pixels that are actually covered are averaged.
+    while output < len {
+        let coverage = (if lead < len { lead } else { len - 1 }) - (if trail >= 0 { trail } else { -1 });
+
+        for ch in 0..BPP {
+            if lead < len {
+                ac[ch] += src[px (lead, ch)] as i32;
+            }
+
+            if trail >= 0 {
+                ac[ch] -= src[px (trail, ch)] as i32;
+            }
+
+            if output >= 0 {
+                dest[px (output, ch)] = ((ac[ch] + (coverage >> 1)) / coverage) as u8;
+            }
+        }
+
+        lead += 1;
+        output += 1;
+        trail += 1;
+    }
+}
+
+fn compute_box_blur_width (radius: f64) -> i32 {
+    let width = radius * 3.0 * (2.0 * PI).sqrt () / 4.0;
+
+    (width + 0.5) as i32
+}
+
+fn make_gaussian_convolution_matrix (radius: f64) -> Vec<f64> {
+    let std_dev = radius + 1.0;
+    let radius = std_dev * 2.0;
+
+    let matrix_len = ((2.0 * (radius - 0.5).ceil () + 1.0) as usize).max (1);
+    let mut matrix = vec! [0.0; matrix_len];
+
+    let gaussian = |x: f64| (-(x * x) / (2.0 * std_dev * std_dev)).exp ();
+
+    // Fill the matrix by numerical integration from -2*std_dev to
+    // 2*std_dev, sampling 50 points per pixel.  We do the top half and
+    // mirror it, to avoid asymmetric quantization errors.
+    for i in matrix_len / 2 + 1..matrix_len {
+        let base_x = i as f64 - (matrix_len / 2) as f64 - 0.5;
+
+        let sum: f64 = (1..51)
+            .map (|j| base_x + 0.02 * j as f64)
+            .filter (|&r| r <= radius)
+            .map (&gaussian)
+            .sum ();
+
+        matrix[i] = sum / 50.0;
+    }
+
+    for i in 0..matrix_len / 2 + 1 {
+        matrix[i] = matrix[matrix_len - 1 - i];
+    }
+
+    // The center uses an odd number of samples to keep it symmetric
+    let sum: f64 = (0..51).map (|j| gaussian (-0.5 + 0.02 * j as f64)).sum ();
+    matrix[matrix_len / 2] = sum / 51.0;
+
+    let sum: f64 = matrix.iter ().sum ();
+    for v in matrix.iter_mut () {
+        *v /= sum;
+    }
+
+    matrix
+}
+
+fn gaussian_blur_line (matrix: &[f64], src: &[u8], dest: &mut [u8], len: i32) {
+    let matrix_len = matrix.len () as i32;
+    let matrix_middle = matrix_len / 2;
+
+    let px = |i: i32, ch: usize| i as usize * BPP + ch;
+
+    if matrix_len > len {
+        // the line is shorter than the matrix
+        for row in 0..len {
+            let in_matrix = |j: i32| j + matrix_middle - row >= 0 && j + matrix_middle - row < matrix_len;
+
+            let scale: f64 = (0..len).filter (|&j| in_matrix (j)).map (|j| matrix[j as usize]).sum ();
+
+            for ch in 0..BPP {
+                let sum: f64 = (0..len)
+                    .filter (|&j| in_matrix (j))
+                    .map (|j| src[px (j, ch)] as f64 * matrix[j as usize])
+                    .sum ();
+
+                dest[px (row, ch)] = (sum / scale + 0.5) as u8;
+            }
+        }
+    } else {
+        // left edge; we only use the available pixels and scale to one
+        for row in 0..matrix_middle {
+            let first = matrix_middle - row;
+            let scale: f64 = matrix[first as usize..].iter ().sum ();
+
+            for ch in 0..BPP {
+                let sum: f64 = (first..matrix_len)
+                    .map (|j| src[px (j - first, ch)] as f64 * matrix[j as usize])
+                    .sum ();
+
+                dest[px (row, ch)] = (sum / scale + 0.5) as u8;
+            }
+        }
+
+        for row in matrix_middle..len - matrix_middle {
+            for ch in 0..BPP {
+                let sum: f64 = (0..matrix_len)
+                    .map (|j| matrix[j as usize] * src[px (row - matrix_middle + j, ch)] as f64)
+                    .sum ();
+
+                dest[px (row, ch)] = (sum + 0.5) as u8;
+            }
+        }
+
+        // right edge
+        for row in (len - matrix_middle).max (matrix_middle)..len {
+            let last = len - row + matrix_middle;
+            let scale: f64 = matrix[..last as usize].iter ().sum ();
+
+            for ch in 0..BPP {
+                let sum: f64 = (0..last)
+                    .map (|j| src[px (row - matrix_middle + j, ch)] as f64 * matrix[j as usize])
+                    .sum ();
+
+                dest[px (row, ch)] = (sum / scale + 0.5) as u8;
+            }
+        }
+    }
+}
+
+// Blurs a line in place, with three box blurs for large deviations or
+// a true gaussian kernel for small ones.
+fn blur_line (line: &mut [u8], scratch: &mut [u8], len: i32, sd: f64) {
+    if sd < 10.0 {
+        let matrix = make_gaussian_convolution_matrix (sd);
+        scratch.copy_from_slice (line);
+        gaussian_blur_line (&matrix, scratch, line, len);
+    } else {
+        let box_width = compute_box_blur_width (sd);
+
+        if box_width % 2 != 0 {
+            box_blur_line (box_width, 0, line, scratch, len);
+            box_blur_line (box_width, 0, scratch, line, len);
+            scratch.copy_from_slice (line);
+            box_blur_line (box_width, 0, scratch, line, len);
+        } else {
+            // This method is suggested by the SVG specification: one pass
+            // centered between the output and its left neighbor, one
+            // between the output and its right neighbor, and one of
+            // width n+1 centered on the output.
+            box_blur_line (box_width, -1, line, scratch, len);
+            box_blur_line (box_width, 1, scratch, line, len);
+            scratch.copy_from_slice (line);
+            box_blur_line (box_width + 1, 0, scratch, line, len);
+        }
+    }
+}
+
+pub fn blur (input: &Surface, sdx: f64, sdy: f64) -> Surface {
+    let sdx = sdx.max (0.0);
+    let sdy = sdy.max (0.0);
+
+    let mut output = input.clone ();
+
+    if (sdx == 0.0 && sdy == 0.0) || sdx > 1000.0 || sdy > 1000.0 {
+        return output;
+    }
+
+    let width = output.width ();
+    let height = output.height ();
+
+    if sdx != 0.0 {
+        let mut line = vec! [0u8; width as usize * BPP];
+        let mut scratch = line.clone ();
+
+        for y in 0..height {
+            line.copy_from_slice (output.row (y));
+            blur_line (&mut line, &mut scratch, width, sdx);
+            output.row_mut (y).copy_from_slice (&line);
+        }
+    }
+
+    if sdy != 0.0 {
+        let mut column = vec! [0u8; height as usize * BPP];
+        let mut scratch = column.clone ();
+
+        for x in 0..width {
+            for y in 0..height {
+                column[y as usize * BPP..(y as usize + 1) * BPP].copy_from_slice (&output.get_pixel (x, y));
+            }
+
+            blur_line (&mut column, &mut scratch, height, sdy);
+
+            for y in 0..height {
+                let ofs = y as usize * BPP;
+                output.set_pixel (x, y, [column[ofs], column[ofs + 1], column[ofs + 2], column[ofs + 3]]);
+            }
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_gaussian_blur (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveGaussianBlur,
+                    raw_parent,
+                    Box::new (GaussianBlur::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spot (width: i32, height: i32) -> Surface {
+        let mut s = Surface::new (width, height);
+        s.set_pixel (width / 2, height / 2, [255, 255, 255, 255]);
+        s
+    }
+
+    fn total (s: &Surface, ch: usize) -> u32 {
+        let mut sum = 0;
+
+        for y in 0..s.height () {
+            for x in 0..s.width () {
+                sum += s.get (x, y, ch) as u32;
+            }
+        }
+
+        sum
+    }
+
+    #[test]
+    fn gaussian_matrix_is_normalized_and_symmetric () {
+        for &radius in [0.0, 0.5, 3.0, 9.9].iter () {
+            let m = make_gaussian_convolution_matrix (radius);
+
+            assert! (m.len () % 2 == 1);
+            assert! ((m.iter ().sum::<f64> () - 1.0).abs () < 1e-9);
+
+            for i in 0..m.len () / 2 {
+                assert_eq! (m[i], m[m.len () - 1 - i]);
+                assert! (m[i] <= m[i + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn box_blur_averages_covered_pixels () {
+        let src = [0, 0, 0, 0,  30, 30, 30, 30,  60, 60, 60, 60];
+        let mut dest = [0u8; 12];
+
+        box_blur_line (3, 0, &src, &mut dest, 3);
+        assert_eq! (dest, [15, 15, 15, 15,  30, 30, 30, 30,  45, 45, 45, 45]);
+    }
+
+    #[test]
+    fn zero_deviation_copies_input () {
+        let s = spot (5, 5);
+
+        assert_eq! (blur (&s, 0.0, 0.0), s);
+        assert_eq! (blur (&s, -1.0, -2.0), s);
+        assert_eq! (blur (&s, 1001.0, 1.0), s);
+    }
+
+    #[test]
+    fn blurs_only_along_requested_axis () {
+        let s = spot (21, 21);
+        let out = blur (&s, 2.0, 0.0);
+
+        for y in 0..21 {
+            for x in 0..21 {
+                if y != 10 {
+                    assert_eq! (out.get_pixel (x, y), [0, 0, 0, 0]);
+                }
+            }
+        }
+
+        assert! (out.get (10, 10, 3) < 255);
+        assert! (out.get (9, 10, 3) > 0);
+        assert_eq! (out.get (9, 10, 3), out.get (11, 10, 3));
+    }
+
+    #[test]
+    fn blur_keeps_total_coverage () {
+        let mut s = Surface::new (41, 41);
+
+        for y in 15..26 {
+            for x in 15..26 {
+                s.set_pixel (x, y, [255, 255, 255, 255]);
+            }
+        }
+
+        for &sd in [1.0, 2.0, 4.0].iter () {
+            let out = blur (&s, sd, sd);
+
+            let before = total (&s, 3);
+            let after = total (&out, 3);
+
+            // Rounding may gain or lose a little, but not much
+            assert! (after * 100 >= before * 98 && after * 100 <= before * 102);
+        }
+    }
+
+    #[test]
+    fn blurs_edges_along_both_axes () {
+        for &sd in [3.0, 12.0].iter () {
+            let mut row = Surface::new (100, 1);
+            let mut column = Surface::new (1, 100);
+
+            for i in 0..50 {
+                row.set_pixel (i, 0, [255, 255, 255, 255]);
+                column.set_pixel (0, i, [255, 255, 255, 255]);
+            }
+
+            let row = blur (&row, sd, 0.0);
+            let column = blur (&column, 0.0, sd);
+
+            assert_eq! (row.get_pixel (0, 0), [255, 255, 255, 255]);
+            assert_eq! (row.get_pixel (99, 0), [0, 0, 0, 0]);
+
+            for i in 0..100 {
+                assert_eq! (row.get_pixel (i, 0), column.get_pixel (0, i));
+
+                if i > 0 {
+                    assert! (row.get (i, 0, 3) <= row.get (i - 1, 0, 3));
+                }
+            }
+
+            assert! (row.get (49, 0, 3) > 64 && row.get (50, 0, 3) < 192);
+        }
+    }
+}
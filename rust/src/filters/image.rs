@@ -0,0 +1,183 @@
+use ::glib::translate::*;
+use ::libc;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use drawing_ctx;
+use drawing_ctx::RsvgDrawingCtx;
+use handle;
+use handle::RsvgHandle;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, FilterOutput, IRect, Primitive, Surface};
+
+/// The `feImage` element.
+pub struct Image {
+    base:   Primitive,
+    href:   RefCell<Option<String>>,
+
+    // Needed to resolve relative URLs for external images
+    handle: Cell<*const RsvgHandle>
+}
+
+impl Image {
+    fn new () -> Image {
+        Image {
+            base:   Primitive::new (),
+            href:   RefCell::new (None),
+            handle: Cell::new (::std::ptr::null ())
+        }
+    }
+
+    // Renders an element from the same document, like a <use> would.
+    fn render_node (&self, ctx: &FilterContext, href: &str) -> Option<Surface> {
+        let draw_ctx = ctx.draw_ctx ();
+
+        let raw_node = drawing_ctx::acquire_node (draw_ctx, href);
+        if raw_node.is_null () {
+            return None;
+        }
+
+        let node: &RsvgNode = unsafe { &*raw_node };
+
+        let affine = drawing_ctx::get_current_state_affine (draw_ctx);
+        drawing_ctx::set_current_state_affine (draw_ctx, ctx.paffine ());
+
+        let surface = drawing_ctx::get_surface_of_node (draw_ctx, node, ctx.width () as f64, ctx.height () as f64);
+
+        drawing_ctx::set_current_state_affine (draw_ctx, affine);
+        drawing_ctx::release_node (draw_ctx, raw_node);
+
+        surface.and_then (|s| Surface::from_cairo (s.to_glib_none ().0))
+    }
+
+    // Loads an external image and stretches it over the primitive subregion.
+    fn render_external (&self, ctx: &FilterContext, href: &str, bounds: IRect) -> Option<Surface> {
+        if bounds.is_empty () {
+            return None;
+        }
+
+        let image = handle::load_image_surface (self.handle.get (), href)?;
+        let image = Surface::from_cairo (image.to_glib_none ().0)?;
+
+        Some (stretch (&image, ctx.width (), ctx.height (), bounds))
+    }
+}
+
+// Scales `image` with bilinear interpolation so that it covers `bounds`
+// in a new surface of the given size.
+fn stretch (image: &Surface, width: i32, height: i32, bounds: IRect) -> Surface {
+    let mut output = Surface::new (width, height);
+    let bounds = bounds.intersection (&output.bounds ());
+
+    if bounds.is_empty () {
+        return output;
+    }
+
+    let sx = image.width () as f64 / bounds.width () as f64;
+    let sy = image.height () as f64 / bounds.height () as f64;
+    let image_bounds = image.bounds ();
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            // Sample at pixel centers
+            let ix = (x - bounds.x0) as f64 * sx + (sx - 1.0) / 2.0;
+            let iy = (y - bounds.y0) as f64 * sy + (sy - 1.0) / 2.0;
+
+            let ix = ix.max (0.0).min ((image.width () - 1) as f64);
+            let iy = iy.max (0.0).min ((image.height () - 1) as f64);
+
+            let mut pixel = [0u8; 4];
+            for ch in 0..4 {
+                pixel[ch] = image.get_interpolated (ix, iy, ch, image_bounds);
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+impl NodeTrait for Image {
+    fn set_atts (&self, _: &RsvgNode, handle: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.handle.set (handle);
+        *self.href.borrow_mut () = property_bag::lookup (pbag, "xlink:href");
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Image {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let href = self.href.borrow ();
+
+        let href = match *href {
+            Some (ref h) => h,
+            None => return
+        };
+
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let image = self.render_node (ctx, href)
+            .or_else (|| self.render_external (ctx, href, bounds));
+
+        let mut output = Surface::new (ctx.width (), ctx.height ());
+
+        if let Some (image) = image {
+            let bounds = bounds.intersection (&image.bounds ());
+
+            for y in bounds.y0..bounds.y1 {
+                for x in bounds.x0..bounds.x1 {
+                    output.set_pixel (x, y, image.get_pixel (x, y));
+                }
+            }
+        }
+
+        ctx.store_output (&self.base.result.borrow (), FilterOutput {
+            surface: Rc::new (output),
+            bounds:  bounds
+        });
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_image (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveImage,
+                    raw_parent,
+                    Box::new (Image::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::surface::A;
+
+    #[test]
+    fn stretches_image_over_bounds () {
+        let mut image = Surface::new (2, 1);
+        image.set_pixel (0, 0, [255, 0, 0, 255]);
+        image.set_pixel (1, 0, [0, 0, 255, 255]);
+
+        let out = stretch (&image, 6, 2, IRect::new (1, 0, 5, 1));
+
+        assert_eq! (out.get_pixel (0, 0), [0, 0, 0, 0]);
+        assert_eq! (out.get_pixel (1, 0), [255, 0, 0, 255]);
+        assert_eq! (out.get_pixel (4, 0), [0, 0, 255, 255]);
+        assert_eq! (out.get (5, 0, A), 0);
+        assert_eq! (out.get (1, 1, A), 0);
+    }
+}
@@ -0,0 +1,675 @@
+use ::cairo;
+use ::cairo::MatrixTrait;
+use ::libc;
+
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::f64::consts::PI;
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use length::*;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface, parse_number, parse_number_optional_number};
+use super::surface::A;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Vector {
+    x: f64,
+    y: f64,
+    z: f64
+}
+
+impl Vector {
+    fn new (x: f64, y: f64, z: f64) -> Vector {
+        Vector { x: x, y: y, z: z }
+    }
+
+    fn dot (&self, other: &Vector) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn normalize (&self) -> Vector {
+        let norm = self.dot (self).sqrt ();
+
+        Vector::new (self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    fn scale (&self, factor: f64) -> Vector {
+        Vector::new (self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+/// A light source with its lengths resolved to user space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Light {
+    Distant {
+        azimuth:   f64,
+        elevation: f64
+    },
+
+    Point {
+        position: Vector
+    },
+
+    Spot {
+        position:            Vector,
+        points_at:           Vector,
+        specular_exponent:   f64,
+        limiting_cone_angle: Option<f64>
+    }
+}
+
+impl Light {
+    // The unit vector from the surface point towards the light.  `x`
+    // and `y` are in device space; `iaffine` maps them to user space.
+    fn direction (&self, x: f64, y: f64, z: f64, iaffine: &cairo::Matrix) -> Vector {
+        match *self {
+            Light::Distant { azimuth, elevation } => {
+                Vector::new (azimuth.cos () * elevation.cos (),
+                             azimuth.sin () * elevation.cos (),
+                             elevation.sin ())
+            },
+
+            Light::Point { position } | Light::Spot { position, .. } => {
+                let (ux, uy) = iaffine.transform_point (x, y);
+
+                Vector::new (position.x - ux, position.y - uy, position.z - z).normalize ()
+            }
+        }
+    }
+
+    // The light's color at a surface point; only spot lights vary.
+    fn color (&self, color: Vector, x: f64, y: f64, z: f64, iaffine: &cairo::Matrix) -> Vector {
+        match *self {
+            Light::Spot { position, points_at, specular_exponent, limiting_cone_angle } => {
+                let l = self.direction (x, y, z, iaffine);
+
+                let s = Vector::new (points_at.x - position.x,
+                                     points_at.y - position.y,
+                                     points_at.z - position.z).normalize ();
+
+                let base = -l.dot (&s);
+
+                let outside_cone = match limiting_cone_angle {
+                    Some (angle) => base.acos () > angle,
+                    None         => false
+                };
+
+                if base < 0.0 || outside_cone {
+                    Vector::new (0.0, 0.0, 0.0)
+                } else {
+                    color.scale (base.powf (specular_exponent))
+                }
+            },
+
+            _ => color
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LightKind {
+    Distant,
+    Point,
+    Spot
+}
+
+/// The `feDistantLight`, `fePointLight` and `feSpotLight` elements.
+pub struct NodeLightSource {
+    kind:                LightKind,
+    azimuth:             Cell<f64>,
+    elevation:           Cell<f64>,
+    x:                   Cell<RsvgLength>,
+    y:                   Cell<RsvgLength>,
+    z:                   Cell<RsvgLength>,
+    points_at_x:         Cell<RsvgLength>,
+    points_at_y:         Cell<RsvgLength>,
+    points_at_z:         Cell<RsvgLength>,
+    specular_exponent:   Cell<f64>,
+    limiting_cone_angle: Cell<Option<f64>>
+}
+
+impl NodeLightSource {
+    fn new (kind: LightKind) -> NodeLightSource {
+        NodeLightSource {
+            kind:                kind,
+            azimuth:             Cell::new (0.0),
+            elevation:           Cell::new (0.0),
+            x:                   Cell::new (RsvgLength::default ()),
+            y:                   Cell::new (RsvgLength::default ()),
+            z:                   Cell::new (RsvgLength::default ()),
+            points_at_x:         Cell::new (RsvgLength::default ()),
+            points_at_y:         Cell::new (RsvgLength::default ()),
+            points_at_z:         Cell::new (RsvgLength::default ()),
+            specular_exponent:   Cell::new (1.0),
+            limiting_cone_angle: Cell::new (None)
+        }
+    }
+
+    fn resolve (&self, draw_ctx: *const RsvgDrawingCtx) -> Light {
+        let point = |x: &Cell<RsvgLength>, y: &Cell<RsvgLength>, z: &Cell<RsvgLength>| {
+            Vector::new (x.get ().normalize (draw_ctx),
+                         y.get ().normalize (draw_ctx),
+                         z.get ().normalize (draw_ctx))
+        };
+
+        match self.kind {
+            LightKind::Distant => Light::Distant {
+                azimuth:   self.azimuth.get (),
+                elevation: self.elevation.get ()
+            },
+
+            LightKind::Point => Light::Point {
+                position: point (&self.x, &self.y, &self.z)
+            },
+
+            LightKind::Spot => Light::Spot {
+                position:            point (&self.x, &self.y, &self.z),
+                points_at:           point (&self.points_at_x, &self.points_at_y, &self.points_at_z),
+                specular_exponent:   self.specular_exponent.get (),
+                limiting_cone_angle: self.limiting_cone_angle.get ()
+            }
+        }
+    }
+}
+
+fn parse_angle (pbag: *const RsvgPropertyBag, key: &str) -> Result<Option<f64>, NodeError> {
+    Ok (parse_number (pbag, key)?.map (|degrees| degrees * PI / 180.0))
+}
+
+impl NodeTrait for NodeLightSource {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.azimuth.set (parse_angle (pbag, "azimuth")?.unwrap_or (0.0));
+        self.elevation.set (parse_angle (pbag, "elevation")?.unwrap_or (0.0));
+        self.limiting_cone_angle.set (parse_angle (pbag, "limitingConeAngle")?);
+
+        self.x.set (property_bag::parse_or_default (pbag, "x", LengthDir::Horizontal, None)?);
+        self.y.set (property_bag::parse_or_default (pbag, "y", LengthDir::Vertical, None)?);
+        self.z.set (property_bag::parse_or_default (pbag, "z", LengthDir::Both, None)?);
+        self.points_at_x.set (property_bag::parse_or_default (pbag, "pointsAtX", LengthDir::Horizontal, None)?);
+        self.points_at_y.set (property_bag::parse_or_default (pbag, "pointsAtY", LengthDir::Vertical, None)?);
+        self.points_at_z.set (property_bag::parse_or_default (pbag, "pointsAtZ", LengthDir::Both, None)?);
+
+        self.specular_exponent.set (parse_number (pbag, "specularExponent")?.unwrap_or (1.0));
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; light sources are used by their lighting primitive
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+// Only the first light source child is used
+fn find_light_source (node: &RsvgNode, draw_ctx: *const RsvgDrawingCtx) -> Option<Light> {
+    node.children.borrow ().iter ()
+        .find (|c| c.get_type () == NodeType::LightSource)
+        .and_then (|c| c.get_impl::<NodeLightSource> ().map (|source| source.resolve (draw_ctx)))
+}
+
+// Sobel kernels to compute the surface normal, from the spec.  They
+// are indexed by row * 3 + column, where row and column are 0 for the
+// top/left edge, 1 for the interior, and 2 for the bottom/right edge.
+
+const NORMAL_X: [([i32; 9], f64); 9] = [
+    ([ 0,  0,  0,
+       0, -2,  2,
+       0, -1,  1], 2.0 / 3.0),
+    ([ 0,  0,  0,
+      -2,  0,  2,
+      -1,  0,  1], 1.0 / 3.0),
+    ([ 0,  0,  0,
+      -2,  2,  0,
+      -1,  1,  0], 2.0 / 3.0),
+    ([ 0, -1,  1,
+       0, -2,  2,
+       0, -1,  1], 1.0 / 2.0),
+    ([-1,  0,  1,
+      -2,  0,  2,
+      -1,  0,  1], 1.0 / 4.0),
+    ([-1,  1,  0,
+      -2,  2,  0,
+      -1,  1,  0], 1.0 / 2.0),
+    ([ 0, -1,  1,
+       0, -2,  2,
+       0,  0,  0], 2.0 / 3.0),
+    ([-1,  0,  1,
+      -2,  0,  2,
+       0,  0,  0], 1.0 / 3.0),
+    ([-1,  1,  0,
+      -2,  2,  0,
+       0,  0,  0], 2.0 / 3.0)
+];
+
+const NORMAL_Y: [([i32; 9], f64); 9] = [
+    ([ 0,  0,  0,
+       0, -2, -1,
+       0,  2,  1], 2.0 / 3.0),
+    ([ 0,  0,  0,
+      -1, -2, -1,
+       1,  2,  1], 1.0 / 3.0),
+    ([ 0,  0,  0,
+      -1, -2,  0,
+       1,  2,  0], 2.0 / 3.0),
+    ([ 0, -2, -1,
+       0,  0,  0,
+       0,  2,  1], 1.0 / 2.0),
+    ([-1, -2, -1,
+       0,  0,  0,
+       1,  2,  1], 1.0 / 4.0),
+    ([-1, -2,  0,
+       0,  0,  0,
+       1,  2,  0], 1.0 / 2.0),
+    ([ 0, -2, -1,
+       0,  2,  1,
+       0,  0,  0], 2.0 / 3.0),
+    ([ 0, -2, -1,
+       1,  2,  1,
+       0,  0,  0], 1.0 / 3.0),
+    ([-1, -2,  0,
+       1,  2,  0,
+       0,  0,  0], 2.0 / 3.0)
+];
+
+// The distance between the samples used for the surface normal, in
+// device pixels (`dx`, `dy`) and in user units (`raw_dx`, `raw_dy`).
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct KernelUnit {
+    dx:     f64,
+    dy:     f64,
+    raw_dx: f64,
+    raw_dy: f64
+}
+
+fn surface_normal (input: &Surface, bounds: IRect, x: i32, y: i32, unit: KernelUnit, surface_scale: f64) -> Vector {
+    let fx = x as f64;
+    let fy = y as f64;
+    let KernelUnit { dx, dy, raw_dx, raw_dy } = unit;
+
+    let col = if fx + dx >= (bounds.x1 - 1) as f64 {
+        2
+    } else if fx - dx < (bounds.x0 + 1) as f64 {
+        0
+    } else {
+        1
+    };
+
+    let row = if fy + dy >= (bounds.y1 - 1) as f64 {
+        2
+    } else if fy - dy < (bounds.y0 + 1) as f64 {
+        0
+    } else {
+        1
+    };
+
+    let (kx, factor_x) = NORMAL_X[row * 3 + col];
+    let (ky, factor_y) = NORMAL_Y[row * 3 + col];
+
+    let mut sum_x = 0.0;
+    let mut sum_y = 0.0;
+
+    for j in 0..3 {
+        for i in 0..3 {
+            let sample = input.get_interpolated (fx + (i as f64 - 1.0) * dx,
+                                                 fy + (j as f64 - 1.0) * dy,
+                                                 A,
+                                                 bounds) as f64;
+
+            sum_x += kx[j * 3 + i] as f64 * sample;
+            sum_y += ky[j * 3 + i] as f64 * sample;
+        }
+    }
+
+    let nx = -surface_scale * factor_x / raw_dx * sum_x / 255.0;
+    let ny = -surface_scale * factor_y / raw_dy * sum_y / 255.0;
+
+    Vector::new (nx, ny, 1.0).normalize ()
+}
+
+// Runs the lighting model over the `bounds`.  The `shade` function gets
+// the surface normal, the direction towards the light and the light's
+// color at each pixel, and returns the premultiplied output pixel.
+fn lighting<F> (input:         &Surface,
+                bounds:        IRect,
+                light:         &Light,
+                color:         Vector,
+                surface_scale: f64,
+                unit:          KernelUnit,
+                iaffine:       &cairo::Matrix,
+                shade:         F) -> Surface
+    where F: Fn (Vector, Vector, Vector) -> [u8; 4]
+{
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let z = surface_scale / 255.0 * input.get (x, y, A) as f64;
+
+            let l = light.direction (x as f64, y as f64, z, iaffine);
+            let n = surface_normal (input, bounds, x, y, unit, surface_scale);
+            let light_color = light.color (color, x as f64, y as f64, z, iaffine);
+
+            output.set_pixel (x, y, shade (n, l, light_color));
+        }
+    }
+
+    output
+}
+
+fn clamp_u8 (v: f64) -> u8 {
+    v.max (0.0).min (255.0) as u8
+}
+
+fn diffuse (kd: f64, n: Vector, l: Vector, color: Vector) -> [u8; 4] {
+    let factor = kd * n.dot (&l) * 255.0;
+
+    [clamp_u8 (factor * color.x),
+     clamp_u8 (factor * color.y),
+     clamp_u8 (factor * color.z),
+     255]
+}
+
+fn specular (ks: f64, exponent: f64, n: Vector, l: Vector, color: Vector) -> [u8; 4] {
+    // The halfway vector between the light and the eye, which is
+    // at infinity along the z axis.
+    let h = Vector::new (l.x, l.y, l.z + 1.0).normalize ();
+    let factor = ks * n.dot (&h).powf (exponent) * 255.0;
+
+    let alpha = clamp_u8 (color.x.max (color.y).max (color.z) * factor);
+
+    [(color.x * alpha as f64) as u8,
+     (color.y * alpha as f64) as u8,
+     (color.z * alpha as f64) as u8,
+     alpha]
+}
+
+// The lighting-color, with each component in [0, 1]
+fn lighting_color (node: &RsvgNode) -> Vector {
+    let values = node.get_computed_values ();
+    let rgba = values.resolve_color (&values.lighting_color.0);
+
+    Vector::new (rgba.red as f64 / 255.0,
+                 rgba.green as f64 / 255.0,
+                 rgba.blue as f64 / 255.0)
+}
+
+/// The `feDiffuseLighting` element.
+pub struct DiffuseLighting {
+    base:               Primitive,
+    surface_scale:      Cell<f64>,
+    diffuse_constant:   Cell<f64>,
+    kernel_unit_length: Cell<Option<(f64, f64)>>
+}
+
+impl DiffuseLighting {
+    fn new () -> DiffuseLighting {
+        DiffuseLighting {
+            base:               Primitive::new (),
+            surface_scale:      Cell::new (1.0),
+            diffuse_constant:   Cell::new (1.0),
+            kernel_unit_length: Cell::new (None)
+        }
+    }
+}
+
+impl NodeTrait for DiffuseLighting {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.surface_scale.set (parse_number (pbag, "surfaceScale")?.unwrap_or (1.0));
+        self.diffuse_constant.set (parse_number (pbag, "diffuseConstant")?.unwrap_or (1.0));
+        self.kernel_unit_length.set (parse_number_optional_number (pbag, "kernelUnitLength")?);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for DiffuseLighting {
+    fn render (&self, node: &RsvgNode, ctx: &mut FilterContext) {
+        let light = match find_light_source (node, ctx.draw_ctx ()) {
+            Some (l) => l,
+            None => return
+        };
+
+        let paffine = ctx.paffine ();
+        let iaffine = match paffine.try_invert () {
+            Ok (m) => m,
+            Err (_) => return
+        };
+
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let unit = match self.kernel_unit_length.get () {
+            Some ((dx, dy)) if dx > 0.0 && dy > 0.0 => KernelUnit {
+                dx:     dx * paffine.xx,
+                dy:     dy * paffine.yy,
+                raw_dx: dx,
+                raw_dy: dy
+            },
+
+            Some (_) => KernelUnit { dx: 1.0, dy: 1.0, raw_dx: 1.0, raw_dy: 1.0 },
+
+            None => KernelUnit { dx: paffine.xx, dy: paffine.yy, raw_dx: 1.0, raw_dy: 1.0 }
+        };
+
+        let output = lighting (&input, bounds, &light, lighting_color (node),
+                               self.surface_scale.get (), unit, &iaffine,
+                               |n, l, color| diffuse (self.diffuse_constant.get (), n, l, color));
+
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+/// The `feSpecularLighting` element.
+pub struct SpecularLighting {
+    base:              Primitive,
+    surface_scale:     Cell<f64>,
+    specular_constant: Cell<f64>,
+    specular_exponent: Cell<f64>
+}
+
+impl SpecularLighting {
+    fn new () -> SpecularLighting {
+        SpecularLighting {
+            base:              Primitive::new (),
+            surface_scale:     Cell::new (1.0),
+            specular_constant: Cell::new (1.0),
+            specular_exponent: Cell::new (1.0)
+        }
+    }
+}
+
+impl NodeTrait for SpecularLighting {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.surface_scale.set (parse_number (pbag, "surfaceScale")?.unwrap_or (1.0));
+        self.specular_constant.set (parse_number (pbag, "specularConstant")?.unwrap_or (1.0));
+        self.specular_exponent.set (parse_number (pbag, "specularExponent")?.unwrap_or (1.0));
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for SpecularLighting {
+    fn render (&self, node: &RsvgNode, ctx: &mut FilterContext) {
+        let light = match find_light_source (node, ctx.draw_ctx ()) {
+            Some (l) => l,
+            None => return
+        };
+
+        let paffine = ctx.paffine ();
+        let iaffine = match paffine.try_invert () {
+            Ok (m) => m,
+            Err (_) => return
+        };
+
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let unit = KernelUnit {
+            dx:     1.0,
+            dy:     1.0,
+            raw_dx: 1.0 / paffine.xx,
+            raw_dy: 1.0 / paffine.yy
+        };
+
+        let output = lighting (&input, bounds, &light, lighting_color (node),
+                               self.surface_scale.get (), unit, &iaffine,
+                               |n, l, color| specular (self.specular_constant.get (), self.specular_exponent.get (), n, l, color));
+
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_diffuse_lighting (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveDiffuseLighting,
+                    raw_parent,
+                    Box::new (DiffuseLighting::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_specular_lighting (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveSpecularLighting,
+                    raw_parent,
+                    Box::new (SpecularLighting::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_node_light_source (element_name: *const libc::c_char,
+                                          raw_parent: *const RsvgNode) -> *const RsvgNode {
+    assert! (!element_name.is_null ());
+
+    let name = unsafe { CStr::from_ptr (element_name) };
+
+    let kind = match name.to_str () {
+        Ok ("feDistantLight") => LightKind::Distant,
+        Ok ("fePointLight")   => LightKind::Point,
+        Ok ("feSpotLight")    => LightKind::Spot,
+        _                     => unreachable! ()
+    };
+
+    boxed_node_new (NodeType::LightSource,
+                    raw_parent,
+                    Box::new (NodeLightSource::new (kind)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNIT: KernelUnit = KernelUnit { dx: 1.0, dy: 1.0, raw_dx: 1.0, raw_dy: 1.0 };
+
+    fn opaque (width: i32, height: i32) -> Surface {
+        let mut s = Surface::new (width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                s.set_pixel (x, y, [0, 0, 0, 255]);
+            }
+        }
+
+        s
+    }
+
+    #[test]
+    fn flat_surface_faces_the_viewer () {
+        let s = opaque (5, 5);
+        let n = surface_normal (&s, s.bounds (), 2, 2, UNIT, 1.0);
+
+        assert_eq! (n, Vector::new (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn slope_tilts_the_normal () {
+        let mut s = Surface::new (5, 5);
+        for y in 0..5 {
+            for x in 0..5 {
+                s.set_pixel (x, y, [0, 0, 0, (x * 50) as u8]);
+            }
+        }
+
+        // alpha increases to the right, so the normal points left
+        let n = surface_normal (&s, s.bounds (), 2, 2, UNIT, 1.0);
+        assert! (n.x < 0.0);
+        assert! (n.y.abs () < 1e-9);
+    }
+
+    #[test]
+    fn distant_light_from_above_lights_fully () {
+        let s = opaque (4, 4);
+        let light = Light::Distant { azimuth: 0.0, elevation: PI / 2.0 };
+        let white = Vector::new (1.0, 1.0, 1.0);
+
+        let out = lighting (&s, s.bounds (), &light, white, 1.0, UNIT,
+                            &cairo::Matrix::identity (), |n, l, c| diffuse (1.0, n, l, c));
+
+        assert_eq! (out.get_pixel (1, 1), [255, 255, 255, 255]);
+
+        let out = lighting (&s, IRect::new (0, 0, 3, 3), &light, white, 1.0, UNIT,
+                            &cairo::Matrix::identity (), |n, l, c| diffuse (0.5, n, l, c));
+
+        assert_eq! (out.get_pixel (1, 1), [127, 127, 127, 255]);
+        assert_eq! (out.get_pixel (3, 3), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn spot_light_respects_cone () {
+        let light = Light::Spot {
+            position:            Vector::new (0.0, 0.0, 10.0),
+            points_at:           Vector::new (0.0, 0.0, 0.0),
+            specular_exponent:   1.0,
+            limiting_cone_angle: Some (PI / 4.0)
+        };
+
+        let white = Vector::new (1.0, 1.0, 1.0);
+        let identity = cairo::Matrix::identity ();
+
+        assert_eq! (light.color (white, 0.0, 0.0, 0.0, &identity), white);
+        assert_eq! (light.color (white, 20.0, 0.0, 0.0, &identity), Vector::new (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn specular_alpha_is_brightest_component () {
+        let n = Vector::new (0.0, 0.0, 1.0);
+        let l = Vector::new (0.0, 0.0, 1.0);
+
+        assert_eq! (specular (1.0, 1.0, n, l, Vector::new (1.0, 0.5, 0.0)), [255, 127, 0, 255]);
+    }
+}
@@ -0,0 +1,140 @@
+use ::libc;
+
+use std::cell::RefCell;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Input, Primitive, Surface, parse_input};
+use super::surface::A;
+
+/// The `feMerge` element.
+pub struct Merge {
+    base: Primitive
+}
+
+impl Merge {
+    fn new () -> Merge {
+        Merge {
+            base: Primitive::new ()
+        }
+    }
+}
+
+impl NodeTrait for Merge {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Merge {
+    fn render (&self, node: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+        let mut output = Surface::new (ctx.width (), ctx.height ());
+
+        for child in node.children.borrow ().iter () {
+            if child.get_type () != NodeType::FilterPrimitiveMergeNode {
+                continue;
+            }
+
+            let merge_node = child.get_impl::<MergeNode> ().unwrap ();
+
+            if let Some (input) = ctx.get_input_surface (&merge_node.input.borrow ()) {
+                composite_over (&input, &mut output, bounds);
+            }
+        }
+
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+// Composites `src` over `dst` within `bounds`
+pub fn composite_over (src: &Surface, dst: &mut Surface, bounds: IRect) {
+    let bounds = bounds.intersection (&src.bounds ()).intersection (&dst.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let s = src.get_pixel (x, y);
+            let a = s[A] as u32;
+
+            if a == 0 {
+                continue;
+            }
+
+            let mut d = dst.get_pixel (x, y);
+
+            for ch in 0..4 {
+                d[ch] = (s[ch] as u32 + d[ch] as u32 * (255 - a) / 255) as u8;
+            }
+
+            dst.set_pixel (x, y, d);
+        }
+    }
+}
+
+/// The `feMergeNode` element; it only holds an input for its parent `feMerge`.
+pub struct MergeNode {
+    input: RefCell<Option<Input>>
+}
+
+impl NodeTrait for MergeNode {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        *self.input.borrow_mut () = parse_input (pbag, "in");
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; feMerge renders its children
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_merge (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveMerge,
+                    raw_parent,
+                    Box::new (Merge::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_merge_node (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveMergeNode,
+                    raw_parent,
+                    Box::new (MergeNode { input: RefCell::new (None) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composites_inputs_in_order () {
+        let mut dst = Surface::new (2, 1);
+        let mut red = Surface::new (2, 1);
+        let mut blue = Surface::new (2, 1);
+
+        red.set_pixel (0, 0, [255, 0, 0, 255]);
+        red.set_pixel (1, 0, [255, 0, 0, 255]);
+        blue.set_pixel (0, 0, [0, 0, 128, 128]);
+
+        composite_over (&red, &mut dst, IRect::new (0, 0, 2, 1));
+        composite_over (&blue, &mut dst, IRect::new (0, 0, 2, 1));
+
+        assert_eq! (dst.get_pixel (0, 0), [127, 0, 128, 255]);
+        assert_eq! (dst.get_pixel (1, 0), [255, 0, 0, 255]);
+    }
+}
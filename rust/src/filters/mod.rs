@@ -0,0 +1,450 @@
+//! Filter effects: the `<filter>` element and its primitives.
+//!
+//! https://www.w3.org/TR/SVG/filters.html
+//!
+//! Filters operate on `Surface`s, which hold premultiplied RGBA pixels.
+//! The source graphic is copied out of the cairo surface that the
+//! C code rendered the element to; each primitive reads its inputs
+//! from the `FilterContext` and stores a new surface as its result.
+
+use ::cairo_sys;
+use ::libc;
+
+use std::cell::{Cell, RefCell};
+
+use bbox::RsvgBbox;
+use drawing_ctx;
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use length::*;
+use node::*;
+use paint_server::PaintServerUnits;
+use parsers;
+use parsers::Parse;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+mod context;
+mod functions;
+mod surface;
+
+mod blend;
+mod color_matrix;
+mod component_transfer;
+mod composite;
+mod convolve_matrix;
+mod displacement_map;
+mod flood;
+mod gaussian_blur;
+mod image;
+mod lighting;
+mod merge;
+mod morphology;
+mod offset;
+mod tile;
+mod turbulence;
+
+pub use self::blend::rsvg_new_filter_primitive_blend;
+pub use self::color_matrix::rsvg_new_filter_primitive_color_matrix;
+pub use self::component_transfer::{
+    rsvg_new_filter_primitive_component_transfer,
+    rsvg_new_node_component_transfer_function
+};
+pub use self::composite::rsvg_new_filter_primitive_composite;
+pub use self::convolve_matrix::rsvg_new_filter_primitive_convolve_matrix;
+pub use self::displacement_map::rsvg_new_filter_primitive_displacement_map;
+pub use self::flood::rsvg_new_filter_primitive_flood;
+pub use self::gaussian_blur::rsvg_new_filter_primitive_gaussian_blur;
+pub use self::image::rsvg_new_filter_primitive_image;
+pub use self::lighting::{
+    rsvg_new_filter_primitive_diffuse_lighting,
+    rsvg_new_filter_primitive_specular_lighting,
+    rsvg_new_node_light_source
+};
+pub use self::merge::{
+    rsvg_new_filter_primitive_merge,
+    rsvg_new_filter_primitive_merge_node
+};
+pub use self::morphology::rsvg_new_filter_primitive_erode;
+pub use self::offset::rsvg_new_filter_primitive_offset;
+pub use self::tile::rsvg_new_filter_primitive_tile;
+pub use self::turbulence::rsvg_new_filter_primitive_turbulence;
+
+pub use self::context::{FilterContext, FilterOutput};
+pub use self::functions::{FilterFunction, parse_filter_functions};
+pub use self::surface::{IRect, Surface};
+
+/// The `<filter>` element.
+pub struct NodeFilter {
+    filter_units:    Cell<PaintServerUnits>,
+    primitive_units: Cell<PaintServerUnits>,
+    x:               Cell<RsvgLength>,
+    y:               Cell<RsvgLength>,
+    width:           Cell<RsvgLength>,
+    height:          Cell<RsvgLength>
+}
+
+impl NodeFilter {
+    fn new () -> NodeFilter {
+        NodeFilter {
+            filter_units:    Cell::new (PaintServerUnits::ObjectBoundingBox),
+            primitive_units: Cell::new (PaintServerUnits::UserSpaceOnUse),
+            x:               Cell::new (RsvgLength::parse ("-10%", LengthDir::Horizontal).unwrap ()),
+            y:               Cell::new (RsvgLength::parse ("-10%", LengthDir::Vertical).unwrap ()),
+            width:           Cell::new (RsvgLength::parse ("120%", LengthDir::Horizontal).unwrap ()),
+            height:          Cell::new (RsvgLength::parse ("120%", LengthDir::Vertical).unwrap ())
+        }
+    }
+}
+
+impl NodeTrait for NodeFilter {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.filter_units.set (property_bag::parse_or_value (pbag, "filterUnits", (),
+                                                            PaintServerUnits::ObjectBoundingBox, None)?);
+        self.primitive_units.set (property_bag::parse_or_value (pbag, "primitiveUnits", (),
+                                                               PaintServerUnits::UserSpaceOnUse, None)?);
+
+        self.x.set (property_bag::parse_or_value (pbag, "x", LengthDir::Horizontal, self.x.get (), None)?);
+        self.y.set (property_bag::parse_or_value (pbag, "y", LengthDir::Vertical, self.y.get (), None)?);
+        self.width.set (property_bag::parse_or_value (pbag, "width", LengthDir::Horizontal, self.width.get (), None)?);
+        self.height.set (property_bag::parse_or_value (pbag, "height", LengthDir::Vertical, self.height.get (), None)?);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filters are applied in rsvg-cairo-draw.c
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Filter,
+                    raw_parent,
+                    Box::new (NodeFilter::new ()))
+}
+
+/// A reference to a filter input, from the `in` and `in2` attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    SourceGraphic,
+    SourceAlpha,
+    BackgroundImage,
+    BackgroundAlpha,
+    Reference (String)
+}
+
+impl Input {
+    // An unspecified input, or the "none" that some authoring tools
+    // write, refer to the result of the previous primitive; we
+    // represent that as None.
+    fn parse (s: &str) -> Option<Input> {
+        match s {
+            "SourceGraphic"   => Some (Input::SourceGraphic),
+            "SourceAlpha"     => Some (Input::SourceAlpha),
+            "BackgroundImage" => Some (Input::BackgroundImage),
+            "BackgroundAlpha" => Some (Input::BackgroundAlpha),
+            "" | "none"       => None,
+            _                 => Some (Input::Reference (s.to_string ()))
+        }
+    }
+}
+
+fn parse_input (pbag: *const RsvgPropertyBag, key: &str) -> Option<Input> {
+    property_bag::lookup (pbag, key).and_then (|v| Input::parse (&v))
+}
+
+// Numeric attributes of filter primitives
+
+fn parse_number (pbag: *const RsvgPropertyBag, key: &str) -> Result<Option<f64>, NodeError> {
+    match property_bag::lookup (pbag, key) {
+        Some (v) => parsers::number (&v)
+            .map (Some)
+            .map_err (|e| NodeError::parse_error (key, e)),
+
+        None => Ok (None)
+    }
+}
+
+fn parse_number_optional_number (pbag: *const RsvgPropertyBag, key: &str) -> Result<Option<(f64, f64)>, NodeError> {
+    match property_bag::lookup (pbag, key) {
+        Some (v) => parsers::number_optional_number (&v)
+            .map (Some)
+            .map_err (|e| NodeError::parse_error (key, e)),
+
+        None => Ok (None)
+    }
+}
+
+/// The attributes that are common to all filter primitives.
+pub struct Primitive {
+    x:      Cell<Option<RsvgLength>>,
+    y:      Cell<Option<RsvgLength>>,
+    width:  Cell<Option<RsvgLength>>,
+    height: Cell<Option<RsvgLength>>,
+    input:  RefCell<Option<Input>>,
+    result: RefCell<Option<String>>
+}
+
+impl Primitive {
+    fn new () -> Primitive {
+        Primitive {
+            x:      Cell::new (None),
+            y:      Cell::new (None),
+            width:  Cell::new (None),
+            height: Cell::new (None),
+            input:  RefCell::new (None),
+            result: RefCell::new (None)
+        }
+    }
+
+    fn set_atts (&self, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.x.set (property_bag::parse_or_none (pbag, "x", LengthDir::Horizontal, None)?);
+        self.y.set (property_bag::parse_or_none (pbag, "y", LengthDir::Vertical, None)?);
+        self.width.set (property_bag::parse_or_none (pbag, "width", LengthDir::Horizontal, None)?);
+        self.height.set (property_bag::parse_or_none (pbag, "height", LengthDir::Vertical, None)?);
+
+        *self.input.borrow_mut () = parse_input (pbag, "in");
+        *self.result.borrow_mut () = property_bag::lookup (pbag, "result").and_then (|r| {
+            if r.is_empty () { None } else { Some (r) }
+        });
+
+        Ok (())
+    }
+
+    fn has_subregion (&self) -> bool {
+        self.x.get ().is_some ()
+            || self.y.get ().is_some ()
+            || self.width.get ().is_some ()
+            || self.height.get ().is_some ()
+    }
+}
+
+/// Implemented by the nodes for each filter primitive element.
+pub trait Filter: NodeTrait {
+    /// Renders the primitive and stores its result in the `ctx`.  If an
+    /// input is missing, the primitive stores no result.
+    fn render (&self, node: &RsvgNode, ctx: &mut FilterContext);
+}
+
+fn render_primitive (node: &RsvgNode, ctx: &mut FilterContext) {
+    fn render<T: Filter> (node: &RsvgNode, ctx: &mut FilterContext) {
+        node.with_impl (|primitive: &T| primitive.render (node, ctx));
+    }
+
+    match node.get_type () {
+        NodeType::FilterPrimitiveBlend              => render::<blend::Blend> (node, ctx),
+        NodeType::FilterPrimitiveColorMatrix        => render::<color_matrix::ColorMatrix> (node, ctx),
+        NodeType::FilterPrimitiveComponentTransfer  => render::<component_transfer::ComponentTransfer> (node, ctx),
+        NodeType::FilterPrimitiveComposite          => render::<composite::Composite> (node, ctx),
+        NodeType::FilterPrimitiveConvolveMatrix     => render::<convolve_matrix::ConvolveMatrix> (node, ctx),
+        NodeType::FilterPrimitiveDiffuseLighting    => render::<lighting::DiffuseLighting> (node, ctx),
+        NodeType::FilterPrimitiveDisplacementMap    => render::<displacement_map::DisplacementMap> (node, ctx),
+        NodeType::FilterPrimitiveErode              => render::<morphology::Morphology> (node, ctx),
+        NodeType::FilterPrimitiveFlood              => render::<flood::Flood> (node, ctx),
+        NodeType::FilterPrimitiveGaussianBlur       => render::<gaussian_blur::GaussianBlur> (node, ctx),
+        NodeType::FilterPrimitiveImage              => render::<image::Image> (node, ctx),
+        NodeType::FilterPrimitiveMerge              => render::<merge::Merge> (node, ctx),
+        NodeType::FilterPrimitiveOffset             => render::<offset::Offset> (node, ctx),
+        NodeType::FilterPrimitiveSpecularLighting   => render::<lighting::SpecularLighting> (node, ctx),
+        NodeType::FilterPrimitiveTile               => render::<tile::Tile> (node, ctx),
+        NodeType::FilterPrimitiveTurbulence         => render::<turbulence::Turbulence> (node, ctx),
+
+        // feMergeNode is only meaningful inside feMerge
+        _ => ()
+    }
+}
+
+/// Applies a filter to the `source` surface, which contains the
+/// element as rendered in device space, and returns a new surface
+/// with the result.
+pub fn render (filter_node: &RsvgNode,
+               source:      Surface,
+               draw_ctx:    *const RsvgDrawingCtx,
+               bbox:        &RsvgBbox) -> Surface {
+    let mut ctx = None;
+
+    filter_node.with_impl (|filter: &NodeFilter| {
+        ctx = Some (FilterContext::new (filter, source, draw_ctx, bbox));
+    });
+
+    let mut ctx = ctx.unwrap ();
+
+    for child in filter_node.children.borrow ().iter () {
+        if child.get_result ().is_ok () {
+            render_primitive (child, &mut ctx);
+        }
+    }
+
+    ctx.into_output ()
+}
+
+#[no_mangle]
+pub extern fn rsvg_filter_render (raw_node:   *const RsvgNode,
+                                  raw_source: *mut cairo_sys::cairo_surface_t,
+                                  draw_ctx:   *const RsvgDrawingCtx,
+                                  raw_bbox:   *const RsvgBbox) -> *mut cairo_sys::cairo_surface_t {
+    assert! (!raw_bbox.is_null ());
+
+    let bbox: &RsvgBbox = unsafe { & *raw_bbox };
+
+    let unchanged = || unsafe { cairo_sys::cairo_surface_reference (raw_source) };
+
+    if raw_node.is_null () {
+        return unchanged ();
+    }
+
+    let node: &RsvgNode = unsafe { & *raw_node };
+    let values = node.get_computed_values ();
+    let functions = &values.filter.0;
+
+    // A reference to a missing filter element makes the whole
+    // filter list be ignored.
+    let all_found = functions.iter ().all (|f| match *f {
+        FilterFunction::Url (ref url) => {
+            let raw_node = drawing_ctx::acquire_node_of_type (draw_ctx, url, NodeType::Filter);
+            drawing_ctx::release_node (draw_ctx, raw_node);
+            !raw_node.is_null ()
+        },
+
+        _ => true
+    });
+
+    if functions.is_empty () || !all_found {
+        return unchanged ();
+    }
+
+    // We can only process image surfaces; leave anything else untouched.
+    let mut surface = match Surface::from_cairo (raw_source) {
+        Some (s) => s,
+        None => return unchanged ()
+    };
+
+    for function in functions {
+        surface = match *function {
+            FilterFunction::Url (ref url) => {
+                let raw_node = drawing_ctx::acquire_node_of_type (draw_ctx, url, NodeType::Filter);
+                let filter_node: &RsvgNode = unsafe { & *raw_node };
+
+                // A filter in error disables the rendering of the element
+                // it applies to.
+                let output = if filter_node.get_result ().is_err () {
+                    Surface::new (surface.width (), surface.height ())
+                } else {
+                    render (filter_node, surface, draw_ctx, bbox)
+                };
+
+                drawing_ctx::release_node (draw_ctx, raw_node);
+                output
+            },
+
+            _ => functions::apply (function, surface, &values, draw_ctx, bbox)
+        };
+    }
+
+    surface.to_cairo_raw ()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::context::DeviceRect;
+    use ::cairo;
+    use ::cairo::MatrixTrait;
+    use std::ptr;
+    use std::rc::Rc;
+
+    // A 100x100 surface whose top-left pixel is [1, 2, 3, 4].  Primitive
+    // units get scaled by 2 to device space, and the filter region goes
+    // from (10, 10) to (90, 90) in device space.
+    fn context () -> FilterContext {
+        let mut source = Surface::new (100, 100);
+        source.set_pixel (0, 0, [1, 2, 3, 4]);
+
+        FilterContext::with_region (source,
+                                    ptr::null (),
+                                    PaintServerUnits::UserSpaceOnUse,
+                                    cairo::Matrix::new (2.0, 0.0, 0.0, 2.0, 0.0, 0.0),
+                                    DeviceRect (10.0, 10.0, 90.0, 90.0))
+    }
+
+    fn surface_with_pixel (pixel: [u8; 4]) -> Surface {
+        let mut surface = Surface::new (100, 100);
+        surface.set_pixel (0, 0, pixel);
+        surface
+    }
+
+    fn result_pixel (ctx: &FilterContext, name: Option<&str>) -> [u8; 4] {
+        ctx.get_result (name).surface.get_pixel (0, 0)
+    }
+
+    #[test]
+    fn parses_inputs () {
+        assert_eq! (Input::parse ("SourceGraphic"), Some (Input::SourceGraphic));
+        assert_eq! (Input::parse ("BackgroundAlpha"), Some (Input::BackgroundAlpha));
+        assert_eq! (Input::parse ("blur"), Some (Input::Reference ("blur".to_string ())));
+        assert_eq! (Input::parse (""), None);
+        assert_eq! (Input::parse ("none"), None);
+    }
+
+    #[test]
+    fn primitive_subregions_are_clipped_to_the_filter_region () {
+        let ctx = context ();
+
+        assert_eq! (ctx.subregion_bounds (None), IRect::new (10, 10, 90, 90));
+
+        assert_eq! (ctx.subregion_bounds (Some ((10.0, 10.0, 20.0, 10.0))), IRect::new (20, 20, 60, 40));
+        assert_eq! (ctx.subregion_bounds (Some ((0.0, 30.0, 100.0, 5.0))), IRect::new (10, 60, 90, 70));
+        assert! (ctx.subregion_bounds (Some ((50.0, 50.0, 10.0, 10.0))).is_empty ());
+
+        // The filter region itself gets clipped to the surface
+        let ctx = FilterContext::with_region (Surface::new (100, 100),
+                                              ptr::null (),
+                                              PaintServerUnits::UserSpaceOnUse,
+                                              cairo::Matrix::identity (),
+                                              DeviceRect (-20.0, 50.0, 40.0, 150.0));
+
+        assert_eq! (ctx.subregion_bounds (None), IRect::new (0, 50, 40, 100));
+        assert_eq! (ctx.subregion_bounds (Some ((30.0, 0.0, 100.0, 60.0))), IRect::new (30, 50, 40, 60));
+    }
+
+    #[test]
+    fn results_chain_through_in_and_result () {
+        let mut ctx = context ();
+
+        // The first primitive's default input is the source graphic
+        let first = ctx.get_result (None);
+        assert_eq! (first.surface.get_pixel (0, 0), [1, 2, 3, 4]);
+        assert_eq! (first.bounds, IRect::new (10, 10, 90, 90));
+
+        ctx.store_output (&Some ("a".to_string ()), FilterOutput {
+            surface: Rc::new (surface_with_pixel ([10, 0, 0, 10])),
+            bounds:  IRect::new (20, 20, 30, 30)
+        });
+
+        ctx.store_result (&None, surface_with_pixel ([20, 0, 0, 20]));
+
+        // Without "in", a primitive takes the previous result
+        assert_eq! (result_pixel (&ctx, None), [20, 0, 0, 20]);
+        assert_eq! (ctx.get_result (None).bounds, IRect::new (0, 0, 100, 100));
+
+        // Named results keep their subregion
+        let a = ctx.get_result (Some ("a"));
+        assert_eq! (a.surface.get_pixel (0, 0), [10, 0, 0, 10]);
+        assert_eq! (a.bounds, IRect::new (20, 20, 30, 30));
+
+        // A reference to a result that doesn't exist means the previous result
+        assert_eq! (result_pixel (&ctx, Some ("b")), [20, 0, 0, 20]);
+
+        // A later result with the same name replaces the earlier one
+        ctx.store_result (&Some ("a".to_string ()), surface_with_pixel ([30, 0, 0, 30]));
+        assert_eq! (result_pixel (&ctx, Some ("a")), [30, 0, 0, 30]);
+
+        assert_eq! (ctx.into_output ().get_pixel (0, 0), [30, 0, 0, 30]);
+    }
+}
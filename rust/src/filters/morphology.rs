@@ -0,0 +1,145 @@
+use ::libc;
+
+use std::cell::Cell;
+use std::cmp::{min, max};
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface, parse_number_optional_number};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Operator {
+    Erode,
+    Dilate
+}
+
+/// The `feMorphology` element.
+pub struct Morphology {
+    base:     Primitive,
+    operator: Cell<Operator>,
+    radius:   Cell<(f64, f64)>
+}
+
+impl Morphology {
+    fn new () -> Morphology {
+        Morphology {
+            base:     Primitive::new (),
+            operator: Cell::new (Operator::Erode),
+            radius:   Cell::new ((0.0, 0.0))
+        }
+    }
+}
+
+impl NodeTrait for Morphology {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.radius.set (parse_number_optional_number (pbag, "radius")?.unwrap_or ((0.0, 0.0)));
+
+        // Unknown operators are taken as "erode"
+        self.operator.set (match property_bag::lookup (pbag, "operator").as_ref ().map (String::as_str) {
+            Some ("dilate") => Operator::Dilate,
+            _               => Operator::Erode
+        });
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Morphology {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let paffine = ctx.paffine ();
+        let (rx, ry) = self.radius.get ();
+        let kx = (rx * paffine.xx) as i32;
+        let ky = (ry * paffine.yy) as i32;
+
+        let output = morphology (self.operator.get (), &input, bounds, kx, ky);
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn morphology (operator: Operator, input: &Surface, bounds: IRect, kx: i32, ky: i32) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ());
+
+    let full = input.bounds ();
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let mut pixel = match operator {
+                Operator::Erode  => [255u8; 4],
+                Operator::Dilate => [0u8; 4]
+            };
+
+            for sy in y - ky..y + ky + 1 {
+                for sx in x - kx..x + kx + 1 {
+                    if !full.contains (sx, sy) {
+                        continue;
+                    }
+
+                    let src = input.get_pixel (sx, sy);
+
+                    for ch in 0..4 {
+                        pixel[ch] = match operator {
+                            Operator::Erode  => min (pixel[ch], src[ch]),
+                            Operator::Dilate => max (pixel[ch], src[ch])
+                        };
+                    }
+                }
+            }
+
+            output.set_pixel (x, y, pixel);
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_erode (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveErode,
+                    raw_parent,
+                    Box::new (Morphology::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alphas (s: &Surface) -> Vec<u8> {
+        (0..s.width ()).map (|x| s.get (x, 0, 3)).collect ()
+    }
+
+    #[test]
+    fn erodes_and_dilates () {
+        let mut s = Surface::new (5, 1);
+        s.set_pixel (1, 0, [0, 0, 0, 255]);
+        s.set_pixel (2, 0, [0, 0, 0, 255]);
+        s.set_pixel (3, 0, [0, 0, 0, 255]);
+
+        let bounds = IRect::new (0, 0, 5, 1);
+
+        assert_eq! (alphas (&morphology (Operator::Erode, &s, bounds, 1, 0)), vec! [0, 0, 255, 0, 0]);
+        assert_eq! (alphas (&morphology (Operator::Dilate, &s, bounds, 1, 0)), vec! [255, 255, 255, 255, 255]);
+        assert_eq! (alphas (&morphology (Operator::Erode, &s, bounds, 0, 0)), alphas (&s));
+    }
+}
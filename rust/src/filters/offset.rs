@@ -0,0 +1,116 @@
+use ::libc;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use length::*;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, FilterOutput, IRect, Primitive, Surface};
+
+/// The `feOffset` element.
+pub struct Offset {
+    base: Primitive,
+    dx:   Cell<RsvgLength>,
+    dy:   Cell<RsvgLength>
+}
+
+impl Offset {
+    fn new () -> Offset {
+        Offset {
+            base: Primitive::new (),
+            dx:   Cell::new (RsvgLength::default ()),
+            dy:   Cell::new (RsvgLength::default ())
+        }
+    }
+}
+
+impl NodeTrait for Offset {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.dx.set (property_bag::parse_or_default (pbag, "dx", LengthDir::Horizontal, None)?);
+        self.dy.set (property_bag::parse_or_default (pbag, "dy", LengthDir::Vertical, None)?);
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Offset {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        let input = match ctx.get_input_surface (&self.base.input.borrow ()) {
+            Some (s) => s,
+            None => return
+        };
+
+        let dx = self.dx.get ().normalize (ctx.draw_ctx ());
+        let dy = self.dy.get ().normalize (ctx.draw_ctx ());
+
+        let paffine = ctx.paffine ();
+        let ox = (paffine.xx * dx + paffine.xy * dy) as i32;
+        let oy = (paffine.yx * dx + paffine.yy * dy) as i32;
+
+        ctx.store_output (&self.base.result.borrow (), FilterOutput {
+            surface: Rc::new (offset (&input, bounds, ox, oy)),
+            bounds:  bounds
+        });
+    }
+}
+
+pub fn offset (input: &Surface, bounds: IRect, ox: i32, oy: i32) -> Surface {
+    let mut output = Surface::new (input.width (), input.height ());
+    let bounds = bounds.intersection (&input.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            if bounds.contains (x - ox, y - oy) {
+                output.set_pixel (x, y, input.get_pixel (x - ox, y - oy));
+            }
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_offset (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveOffset,
+                    raw_parent,
+                    Box::new (Offset::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_pixels_within_bounds () {
+        let mut s = Surface::new (4, 4);
+        s.set_pixel (1, 1, [1, 2, 3, 4]);
+        s.set_pixel (3, 3, [5, 6, 7, 8]);
+
+        let out = offset (&s, IRect::new (0, 0, 4, 4), 2, -1);
+
+        assert_eq! (out.get_pixel (3, 0), [1, 2, 3, 4]);
+        assert_eq! (out.get_pixel (1, 1), [0, 0, 0, 0]);
+
+        // (3, 3) moves outside of the surface
+        let mut expected = Surface::new (4, 4);
+        expected.set_pixel (3, 0, [1, 2, 3, 4]);
+        assert_eq! (out, expected);
+    }
+}
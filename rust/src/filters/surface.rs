@@ -0,0 +1,324 @@
+use ::cairo;
+use ::cairo_sys;
+use ::glib::translate::*;
+
+use std::cmp::{min, max};
+
+/// A rectangle in pixel coordinates; `x1` and `y1` are exclusive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IRect {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32
+}
+
+impl IRect {
+    pub fn new (x0: i32, y0: i32, x1: i32, y1: i32) -> IRect {
+        IRect {
+            x0: x0,
+            y0: y0,
+            x1: x1,
+            y1: y1
+        }
+    }
+
+    pub fn width (&self) -> i32 {
+        self.x1 - self.x0
+    }
+
+    pub fn height (&self) -> i32 {
+        self.y1 - self.y0
+    }
+
+    pub fn is_empty (&self) -> bool {
+        self.x0 >= self.x1 || self.y0 >= self.y1
+    }
+
+    pub fn contains (&self, x: i32, y: i32) -> bool {
+        x >= self.x0 && x < self.x1 && y >= self.y0 && y < self.y1
+    }
+
+    pub fn intersection (&self, other: &IRect) -> IRect {
+        let x0 = max (self.x0, other.x0);
+        let y0 = max (self.y0, other.y0);
+        let x1 = min (self.x1, other.x1);
+        let y1 = min (self.y1, other.y1);
+
+        if x1 > x0 && y1 > y0 {
+            IRect::new (x0, y0, x1, y1)
+        } else {
+            IRect::new (0, 0, 0, 0)
+        }
+    }
+}
+
+// Channel indices within a pixel.  Pixels are stored in this order
+// regardless of the machine's endianness; only the conversions to and
+// from cairo surfaces need to care about the native ARGB32 layout.
+pub const R: usize = 0;
+pub const G: usize = 1;
+pub const B: usize = 2;
+pub const A: usize = 3;
+
+/// A premultiplied RGBA image on which filter primitives operate.
+///
+/// Filter inputs and results are shared between primitives as
+/// `Rc<Surface>`, so primitives never modify their inputs; they always
+/// create a new surface for their output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Surface {
+    width:  i32,
+    height: i32,
+    data:   Vec<u8>
+}
+
+impl Surface {
+    /// Creates a fully transparent surface.
+    pub fn new (width: i32, height: i32) -> Surface {
+        assert! (width >= 0 && height >= 0);
+
+        Surface {
+            width:  width,
+            height: height,
+            data:   vec! [0; (width * height * 4) as usize]
+        }
+    }
+
+    pub fn width (&self) -> i32 {
+        self.width
+    }
+
+    pub fn height (&self) -> i32 {
+        self.height
+    }
+
+    pub fn bounds (&self) -> IRect {
+        IRect::new (0, 0, self.width, self.height)
+    }
+
+    fn offset (&self, x: i32, y: i32) -> usize {
+        debug_assert! (x >= 0 && x < self.width && y >= 0 && y < self.height);
+
+        ((y * self.width + x) * 4) as usize
+    }
+
+    pub fn get (&self, x: i32, y: i32, ch: usize) -> u8 {
+        self.data[self.offset (x, y) + ch]
+    }
+
+    pub fn get_pixel (&self, x: i32, y: i32) -> [u8; 4] {
+        let ofs = self.offset (x, y);
+        [self.data[ofs], self.data[ofs + 1], self.data[ofs + 2], self.data[ofs + 3]]
+    }
+
+    pub fn set_pixel (&mut self, x: i32, y: i32, pixel: [u8; 4]) {
+        let ofs = self.offset (x, y);
+        self.data[ofs..ofs + 4].copy_from_slice (&pixel);
+    }
+
+    pub fn row (&self, y: i32) -> &[u8] {
+        let start = self.offset (0, y);
+        &self.data[start..start + (self.width * 4) as usize]
+    }
+
+    pub fn row_mut (&mut self, y: i32) -> &mut [u8] {
+        let start = self.offset (0, y);
+        let len = (self.width * 4) as usize;
+        &mut self.data[start..start + len]
+    }
+
+    /// Samples a channel at fractional coordinates with bilinear
+    /// interpolation.  Pixels outside of `bounds` count as zero.
+    pub fn get_interpolated (&self, x: f64, y: f64, ch: usize, bounds: IRect) -> u8 {
+        let bounds = bounds.intersection (&self.bounds ());
+
+        let xmod = x - x.floor ();
+        let ymod = y - y.floor ();
+
+        let x0 = x.floor () as i32;
+        let y0 = y.floor () as i32;
+        let x1 = x.ceil () as i32;
+        let y1 = y.ceil () as i32;
+
+        let sample = |sx: i32, sy: i32| {
+            if bounds.contains (sx, sy) {
+                self.get (sx, sy, ch) as f64
+            } else {
+                0.0
+            }
+        };
+
+        let c = sample (x0, y0) * (1.0 - xmod) * (1.0 - ymod)
+            + sample (x1, y0) * xmod * (1.0 - ymod)
+            + sample (x1, y1) * xmod * ymod
+            + sample (x0, y1) * (1.0 - xmod) * ymod;
+
+        c as u8
+    }
+
+    /// Returns a surface with the alpha channel of this one, and zeros
+    /// in the color channels; this is what SourceAlpha and BackgroundAlpha
+    /// refer to.
+    pub fn alpha_only (&self) -> Surface {
+        let mut out = Surface::new (self.width, self.height);
+
+        for (dst, src) in out.data.chunks_mut (4).zip (self.data.chunks (4)) {
+            dst[A] = src[A];
+        }
+
+        out
+    }
+
+    /// Clears all the pixels outside of `bounds` to transparent black.
+    pub fn clear_outside (&mut self, bounds: IRect) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !bounds.contains (x, y) {
+                    self.set_pixel (x, y, [0; 4]);
+                }
+            }
+        }
+    }
+
+    /// Copies the contents of a cairo image surface.  Surfaces without an
+    /// alpha channel are taken to be opaque.
+    pub fn from_cairo (raw_surface: *mut cairo_sys::cairo_surface_t) -> Option<Surface> {
+        unsafe {
+            if raw_surface.is_null ()
+                || cairo_sys::cairo_surface_status (raw_surface) != cairo::Status::Success
+                || cairo_sys::cairo_surface_get_type (raw_surface) != cairo::enums::SurfaceType::Image {
+                return None;
+            }
+
+            let format = cairo_sys::cairo_image_surface_get_format (raw_surface);
+            let has_alpha = match format {
+                cairo::Format::ARgb32 => true,
+                cairo::Format::Rgb24  => false,
+                _                     => return None
+            };
+
+            cairo_sys::cairo_surface_flush (raw_surface);
+
+            let width = cairo_sys::cairo_image_surface_get_width (raw_surface);
+            let height = cairo_sys::cairo_image_surface_get_height (raw_surface);
+            let stride = cairo_sys::cairo_image_surface_get_stride (raw_surface) as isize;
+            let pixels = cairo_sys::cairo_image_surface_get_data (raw_surface);
+
+            if pixels.is_null () {
+                return None;
+            }
+
+            let mut surface = Surface::new (width, height);
+
+            for y in 0..height {
+                let row = pixels.offset (y as isize * stride) as *const u32;
+
+                for x in 0..width {
+                    let argb = *row.offset (x as isize);
+                    let alpha = if has_alpha { (argb >> 24) as u8 } else { 0xff };
+
+                    surface.set_pixel (x, y, [(argb >> 16) as u8,
+                                              (argb >> 8) as u8,
+                                              argb as u8,
+                                              alpha]);
+                }
+            }
+
+            Some (surface)
+        }
+    }
+
+    /// Creates an ARGB32 cairo image surface with the contents of this one.
+    pub fn to_cairo (&self) -> Option<cairo::ImageSurface> {
+        let mut surface = cairo::ImageSurface::create (cairo::Format::ARgb32, self.width, self.height).ok ()?;
+        let stride = surface.get_stride () as usize;
+
+        {
+            let mut data = surface.get_data ().ok ()?;
+
+            for y in 0..self.height {
+                let row = &mut data[y as usize * stride..];
+
+                for x in 0..self.width {
+                    let p = self.get_pixel (x, y);
+                    let argb = (p[A] as u32) << 24 | (p[R] as u32) << 16 | (p[G] as u32) << 8 | p[B] as u32;
+                    let ofs = x as usize * 4;
+
+                    // cairo stores each pixel as a native-endian u32
+                    if cfg! (target_endian = "little") {
+                        row[ofs]     = argb as u8;
+                        row[ofs + 1] = (argb >> 8) as u8;
+                        row[ofs + 2] = (argb >> 16) as u8;
+                        row[ofs + 3] = (argb >> 24) as u8;
+                    } else {
+                        row[ofs]     = (argb >> 24) as u8;
+                        row[ofs + 1] = (argb >> 16) as u8;
+                        row[ofs + 2] = (argb >> 8) as u8;
+                        row[ofs + 3] = argb as u8;
+                    }
+                }
+            }
+        }
+
+        Some (surface)
+    }
+
+    /// Like `to_cairo()`, but returns a raw surface that the caller owns.
+    pub fn to_cairo_raw (&self) -> *mut cairo_sys::cairo_surface_t {
+        match self.to_cairo () {
+            Some (surface) => surface.to_glib_full (),
+            None => ::std::ptr::null_mut ()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersects_rects () {
+        let a = IRect::new (0, 0, 10, 10);
+
+        assert_eq! (a.intersection (&IRect::new (5, -5, 20, 5)), IRect::new (5, 0, 10, 5));
+        assert! (a.intersection (&IRect::new (20, 20, 30, 30)).is_empty ());
+    }
+
+    #[test]
+    fn alpha_only_keeps_alpha () {
+        let mut s = Surface::new (2, 1);
+        s.set_pixel (0, 0, [10, 20, 30, 40]);
+        s.set_pixel (1, 0, [1, 2, 3, 255]);
+
+        let a = s.alpha_only ();
+        assert_eq! (a.get_pixel (0, 0), [0, 0, 0, 40]);
+        assert_eq! (a.get_pixel (1, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn interpolates_between_pixels () {
+        let mut s = Surface::new (2, 1);
+        s.set_pixel (0, 0, [0, 0, 0, 100]);
+        s.set_pixel (1, 0, [0, 0, 0, 200]);
+
+        let bounds = s.bounds ();
+
+        assert_eq! (s.get_interpolated (0.0, 0.0, A, bounds), 100);
+        assert_eq! (s.get_interpolated (0.5, 0.0, A, bounds), 150);
+        assert_eq! (s.get_interpolated (1.5, 0.0, A, bounds), 100);
+        assert_eq! (s.get_interpolated (0.5, 0.0, A, IRect::new (1, 0, 2, 1)), 100);
+    }
+
+    #[test]
+    fn roundtrips_through_cairo () {
+        let mut s = Surface::new (3, 2);
+        s.set_pixel (0, 0, [255, 0, 0, 255]);
+        s.set_pixel (2, 1, [0, 64, 32, 128]);
+
+        let cairo_surface = s.to_cairo ().unwrap ();
+        let back = Surface::from_cairo (cairo_surface.to_glib_none ().0).unwrap ();
+
+        assert_eq! (s, back);
+    }
+}
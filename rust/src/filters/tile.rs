@@ -0,0 +1,95 @@
+use ::libc;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface};
+
+/// The `feTile` element.
+pub struct Tile {
+    base: Primitive
+}
+
+impl Tile {
+    fn new () -> Tile {
+        Tile {
+            base: Primitive::new ()
+        }
+    }
+}
+
+impl NodeTrait for Tile {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Tile {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+
+        // The tile is the input's subregion
+        let input = match ctx.get_input (&self.base.input.borrow ()) {
+            Some (i) => i,
+            None => return
+        };
+
+        let tile_bounds = input.bounds.intersection (&input.surface.bounds ());
+        if tile_bounds.is_empty () {
+            return;
+        }
+
+        let output = tile (&input.surface, tile_bounds, ctx.width (), ctx.height (), bounds);
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+fn tile (input: &Surface, tile_bounds: IRect, width: i32, height: i32, bounds: IRect) -> Surface {
+    let mut output = Surface::new (width, height);
+    let bounds = bounds.intersection (&output.bounds ());
+
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let sx = tile_bounds.x0 + (x - tile_bounds.x0).rem_euclid (tile_bounds.width ());
+            let sy = tile_bounds.y0 + (y - tile_bounds.y0).rem_euclid (tile_bounds.height ());
+
+            output.set_pixel (x, y, input.get_pixel (sx, sy));
+        }
+    }
+
+    output
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_tile (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveTile,
+                    raw_parent,
+                    Box::new (Tile::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_tile_in_both_directions () {
+        let mut s = Surface::new (4, 1);
+        s.set_pixel (1, 0, [1, 1, 1, 1]);
+        s.set_pixel (2, 0, [2, 2, 2, 2]);
+
+        let out = tile (&s, IRect::new (1, 0, 3, 1), 6, 1, IRect::new (0, 0, 6, 1));
+        let reds: Vec<u8> = (0..6).map (|x| out.get (x, 0, 0)).collect ();
+
+        assert_eq! (reds, vec! [2, 1, 2, 1, 2, 1]);
+    }
+}
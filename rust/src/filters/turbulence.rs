@@ -0,0 +1,424 @@
+use ::cairo;
+use ::cairo::MatrixTrait;
+use ::libc;
+
+use std::cell::Cell;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+use super::{Filter, FilterContext, IRect, Primitive, Surface, parse_number, parse_number_optional_number};
+use super::surface::A;
+
+// This is the reference implementation from the SVG specification:
+// https://www.w3.org/TR/SVG/filters.html#feTurbulenceElement
+
+const RAND_M: i64 = 2147483647; // 2**31 - 1
+const RAND_A: i64 = 16807;      // 7**5; primitive root of m
+const RAND_Q: i64 = 127773;     // m / a
+const RAND_R: i64 = 2836;       // m % a
+
+const B_SIZE: usize = 0x100;
+const B_MASK: i32 = 0xff;
+const PERLIN_N: i32 = 0x1000;
+
+fn setup_seed (seed: i64) -> i64 {
+    let mut seed = seed;
+
+    if seed <= 0 {
+        seed = -(seed % (RAND_M - 1)) + 1;
+    }
+
+    if seed > RAND_M - 1 {
+        seed = RAND_M - 1;
+    }
+
+    seed
+}
+
+// Produces results in the range [1, 2**31 - 2].  The algorithm is
+// r = (a * r) mod m, from Park & Miller, CACM vol. 31 no. 10 p. 1195, Oct. 1988
+fn random (seed: i64) -> i64 {
+    let mut result = RAND_A * (seed % RAND_Q) - RAND_R * (seed / RAND_Q);
+
+    if result <= 0 {
+        result += RAND_M;
+    }
+
+    result
+}
+
+struct StitchInfo {
+    width:  i32, // How much to subtract to wrap for stitching
+    height: i32,
+    wrap_x: i32, // Minimum value to wrap
+    wrap_y: i32
+}
+
+// The lattice and gradients for a seed
+struct Noise {
+    lattice_selector: [usize; B_SIZE + B_SIZE + 2],
+    gradient:         [[[f64; 2]; B_SIZE + B_SIZE + 2]; 4]
+}
+
+impl Noise {
+    fn new (seed: i64) -> Noise {
+        let mut lattice_selector = [0; B_SIZE + B_SIZE + 2];
+        let mut gradient = [[[0.0; 2]; B_SIZE + B_SIZE + 2]; 4];
+
+        let mut seed = setup_seed (seed);
+
+        for k in 0..4 {
+            for i in 0..B_SIZE {
+                lattice_selector[i] = i;
+
+                for j in 0..2 {
+                    seed = random (seed);
+                    gradient[k][i][j] = ((seed % (B_SIZE + B_SIZE) as i64) - B_SIZE as i64) as f64 / B_SIZE as f64;
+                }
+
+                let g = gradient[k][i];
+                let s = (g[0] * g[0] + g[1] * g[1]).sqrt ();
+
+                gradient[k][i][0] /= s;
+                gradient[k][i][1] /= s;
+            }
+        }
+
+        for i in (1..B_SIZE).rev () {
+            let k = lattice_selector[i];
+            seed = random (seed);
+            let j = (seed % B_SIZE as i64) as usize;
+
+            lattice_selector[i] = lattice_selector[j];
+            lattice_selector[j] = k;
+        }
+
+        for i in 0..B_SIZE + 2 {
+            lattice_selector[B_SIZE + i] = lattice_selector[i];
+
+            for k in 0..4 {
+                gradient[k][B_SIZE + i] = gradient[k][i];
+            }
+        }
+
+        Noise {
+            lattice_selector: lattice_selector,
+            gradient:         gradient
+        }
+    }
+
+    fn noise2 (&self, color_channel: usize, vec: [f64; 2], stitch: &Option<StitchInfo>) -> f64 {
+        let s_curve = |t: f64| t * t * (3.0 - 2.0 * t);
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+
+        let t = vec[0] + PERLIN_N as f64;
+        let mut bx0 = t as i32;
+        let mut bx1 = bx0 + 1;
+        let rx0 = t - (t as i32) as f64;
+        let rx1 = rx0 - 1.0;
+
+        let t = vec[1] + PERLIN_N as f64;
+        let mut by0 = t as i32;
+        let mut by1 = by0 + 1;
+        let ry0 = t - (t as i32) as f64;
+        let ry1 = ry0 - 1.0;
+
+        // If stitching, adjust lattice points accordingly
+        if let Some (ref stitch) = *stitch {
+            if bx0 >= stitch.wrap_x {
+                bx0 -= stitch.width;
+            }
+
+            if bx1 >= stitch.wrap_x {
+                bx1 -= stitch.width;
+            }
+
+            if by0 >= stitch.wrap_y {
+                by0 -= stitch.height;
+            }
+
+            if by1 >= stitch.wrap_y {
+                by1 -= stitch.height;
+            }
+        }
+
+        let bx0 = (bx0 & B_MASK) as usize;
+        let bx1 = (bx1 & B_MASK) as usize;
+        let by0 = (by0 & B_MASK) as usize;
+        let by1 = (by1 & B_MASK) as usize;
+
+        let i = self.lattice_selector[bx0];
+        let j = self.lattice_selector[bx1];
+        let b00 = self.lattice_selector[i + by0];
+        let b10 = self.lattice_selector[j + by0];
+        let b01 = self.lattice_selector[i + by1];
+        let b11 = self.lattice_selector[j + by1];
+
+        let sx = s_curve (rx0);
+        let sy = s_curve (ry0);
+
+        let gradient = &self.gradient[color_channel];
+
+        let q = gradient[b00];
+        let u = rx0 * q[0] + ry0 * q[1];
+        let q = gradient[b10];
+        let v = rx1 * q[0] + ry0 * q[1];
+        let a = lerp (sx, u, v);
+
+        let q = gradient[b01];
+        let u = rx0 * q[0] + ry1 * q[1];
+        let q = gradient[b11];
+        let v = rx1 * q[0] + ry1 * q[1];
+        let b = lerp (sx, u, v);
+
+        lerp (sy, a, b)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Params {
+    base_frequency: (f64, f64),
+    num_octaves:    i32,
+    seed:           i64,
+    stitch_tiles:   bool,
+    fractal_noise:  bool
+}
+
+// When stitching tiled turbulence, the frequencies must be adjusted
+// so that the tile borders will be continuous.
+fn adjust_frequency (freq: f64, tile_size: f64) -> f64 {
+    if freq == 0.0 {
+        return freq;
+    }
+
+    let lo_freq = (tile_size * freq).floor () / tile_size;
+    let hi_freq = (tile_size * freq).ceil () / tile_size;
+
+    if freq / lo_freq < hi_freq / freq {
+        lo_freq
+    } else {
+        hi_freq
+    }
+}
+
+fn turbulence (noise: &Noise, params: &Params, color_channel: usize, point: (f64, f64), tile: (f64, f64, f64, f64)) -> f64 {
+    let (tile_x, tile_y, tile_width, tile_height) = tile;
+    let (mut freq_x, mut freq_y) = params.base_frequency;
+
+    let mut stitch = None;
+
+    if params.stitch_tiles {
+        freq_x = adjust_frequency (freq_x, tile_width);
+        freq_y = adjust_frequency (freq_y, tile_height);
+
+        let width = (tile_width * freq_x + 0.5) as i32;
+        let height = (tile_height * freq_y + 0.5) as i32;
+
+        stitch = Some (StitchInfo {
+            width:  width,
+            height: height,
+            wrap_x: (tile_x * freq_x) as i32 + PERLIN_N + width,
+            wrap_y: (tile_y * freq_y) as i32 + PERLIN_N + height
+        });
+    }
+
+    let mut sum = 0.0;
+    let mut vec = [point.0 * freq_x, point.1 * freq_y];
+    let mut ratio = 1.0;
+
+    for _ in 0..params.num_octaves {
+        let n = noise.noise2 (color_channel, vec, &stitch);
+
+        if params.fractal_noise {
+            sum += n / ratio;
+        } else {
+            sum += n.abs () / ratio;
+        }
+
+        vec[0] *= 2.0;
+        vec[1] *= 2.0;
+        ratio *= 2.0;
+
+        if let Some (ref mut stitch) = stitch {
+            // Subtracting PerlinN before the multiplication and adding it
+            // afterward simplifies to subtracting it once.
+            stitch.width *= 2;
+            stitch.wrap_x = 2 * stitch.wrap_x - PERLIN_N;
+            stitch.height *= 2;
+            stitch.wrap_y = 2 * stitch.wrap_y - PERLIN_N;
+        }
+    }
+
+    sum
+}
+
+fn render_turbulence (params: &Params, width: i32, height: i32, bounds: IRect, affine: &cairo::Matrix) -> Surface {
+    let mut output = Surface::new (width, height);
+    let bounds = bounds.intersection (&output.bounds ());
+
+    let noise = Noise::new (params.seed);
+
+    let tile_width = bounds.width () as f64;
+    let tile_height = bounds.height () as f64;
+
+    for y in 0..bounds.height () {
+        for x in 0..bounds.width () {
+            let point = affine.transform_point ((x + bounds.x0) as f64, (y + bounds.y0) as f64);
+            let tile = (x as f64, y as f64, tile_width, tile_height);
+
+            let mut pixel = [0u8; 4];
+
+            for ch in 0..4 {
+                let cr = turbulence (&noise, params, ch, point, tile);
+
+                let cr = if params.fractal_noise {
+                    (cr * 255.0 + 255.0) / 2.0
+                } else {
+                    cr * 255.0
+                };
+
+                pixel[ch] = cr.max (0.0).min (255.0) as u8;
+            }
+
+            for ch in 0..3 {
+                pixel[ch] = (pixel[ch] as u32 * pixel[A] as u32 / 255) as u8;
+            }
+
+            output.set_pixel (x + bounds.x0, y + bounds.y0, pixel);
+        }
+    }
+
+    output
+}
+
+/// The `feTurbulence` element.
+pub struct Turbulence {
+    base:   Primitive,
+    params: Cell<Params>
+}
+
+impl Turbulence {
+    fn new () -> Turbulence {
+        Turbulence {
+            base:   Primitive::new (),
+            params: Cell::new (Params {
+                base_frequency: (0.0, 0.0),
+                num_octaves:    1,
+                seed:           0,
+                stitch_tiles:   false,
+                fractal_noise:  false
+            })
+        }
+    }
+}
+
+impl NodeTrait for Turbulence {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.base.set_atts (pbag)?;
+
+        self.params.set (Params {
+            base_frequency: parse_number_optional_number (pbag, "baseFrequency")?.unwrap_or ((0.0, 0.0)),
+            num_octaves:    parse_number (pbag, "numOctaves")?.map_or (1, |n| n as i32),
+            seed:           parse_number (pbag, "seed")?.map_or (0, |s| s as i64),
+            stitch_tiles:   property_bag::lookup (pbag, "stitchTiles").map_or (false, |s| s == "stitch"),
+            fractal_noise:  property_bag::lookup (pbag, "type").map_or (false, |t| t == "fractalNoise")
+        });
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; filter primitives are rendered by their filter
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+impl Filter for Turbulence {
+    fn render (&self, _: &RsvgNode, ctx: &mut FilterContext) {
+        // The noise is computed in the primitive's coordinate system
+        let affine = match ctx.paffine ().try_invert () {
+            Ok (m) => m,
+            Err (_) => return
+        };
+
+        let bounds = ctx.primitive_bounds (Some (&self.base));
+        let output = render_turbulence (&self.params.get (), ctx.width (), ctx.height (), bounds, &affine);
+
+        ctx.store_result (&self.base.result.borrow (), output);
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_new_filter_primitive_turbulence (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FilterPrimitiveTurbulence,
+                    raw_parent,
+                    Box::new (Turbulence::new ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_matches_reference () {
+        let mut seed = setup_seed (1);
+
+        for _ in 0..10000 {
+            seed = random (seed);
+        }
+
+        assert_eq! (seed, 1043618065);
+    }
+
+    #[test]
+    fn setup_seed_clamps () {
+        assert_eq! (setup_seed (0), 1);
+        assert_eq! (setup_seed (-5), 6);
+        assert_eq! (setup_seed (RAND_M + 10), RAND_M - 1);
+    }
+
+    #[test]
+    fn gradients_are_unit_vectors () {
+        let noise = Noise::new (42);
+
+        for k in 0..4 {
+            for g in noise.gradient[k].iter () {
+                assert! (((g[0] * g[0] + g[1] * g[1]).sqrt () - 1.0).abs () < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_is_zero_on_lattice_points () {
+        let noise = Noise::new (0);
+
+        assert_eq! (noise.noise2 (0, [0.0, 0.0], &None), 0.0);
+        assert_eq! (noise.noise2 (3, [5.0, 7.0], &None), 0.0);
+    }
+
+    #[test]
+    fn renders_only_inside_bounds () {
+        let params = Params {
+            base_frequency: (0.05, 0.05),
+            num_octaves:    2,
+            seed:           3,
+            stitch_tiles:   false,
+            fractal_noise:  true
+        };
+
+        let out = render_turbulence (&params, 10, 10, IRect::new (2, 2, 8, 8), &cairo::Matrix::identity ());
+
+        assert_eq! (out.get_pixel (0, 0), [0, 0, 0, 0]);
+        assert! (out.get (5, 5, A) > 0);
+
+        // deterministic for a given seed
+        assert_eq! (out, render_turbulence (&params, 10, 10, IRect::new (2, 2, 8, 8), &cairo::Matrix::identity ()));
+    }
+}
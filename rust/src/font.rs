@@ -0,0 +1,367 @@
+use ::glib::translate::*;
+use ::libc;
+
+use std::cell::Cell;
+use std::cell::RefCell;
+
+use drawing_ctx::RsvgDrawingCtx;
+use error::*;
+use handle::RsvgHandle;
+use node::*;
+use parsers;
+use path_builder::*;
+use path_parser;
+use property_bag;
+use property_bag::RsvgPropertyBag;
+
+// SVG fonts: https://www.w3.org/TR/SVG11/fonts.html
+//
+// A <font> has a <font-face> child with the family name and the size
+// of the em square, and <glyph> children with the outline for each
+// character or ligature.  The <font-face> registers its parent with
+// the handle, so that text whose font-family names it gets rendered
+// with layout_text() instead of Pango.
+//
+// We only support horizontal text, and ignore kerning pairs.
+
+extern "C" {
+    fn rsvg_handle_register_svg_font (handle: *const RsvgHandle,
+                                      family: *const libc::c_char,
+                                      font:   *const RsvgNode);
+}
+
+fn parse_advance (pbag: *const RsvgPropertyBag, key: &str) -> Result<Option<f64>, NodeError> {
+    match property_bag::lookup (pbag, key) {
+        Some (value) => {
+            let advance = parsers::number (&value)
+                .map_err (|e| NodeError::parse_error (key, e))?;
+
+            if advance < 0.0 {
+                Err (NodeError::value_error (key, "value must be non-negative"))
+            } else {
+                Ok (Some (advance))
+            }
+        },
+
+        None => Ok (None)
+    }
+}
+
+/***** NodeFont *****/
+
+struct NodeFont {
+    horiz_adv_x: Cell<f64>
+}
+
+impl NodeFont {
+    fn new () -> NodeFont {
+        NodeFont {
+            horiz_adv_x: Cell::new (0.0)
+        }
+    }
+}
+
+impl NodeTrait for NodeFont {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.horiz_adv_x.set (parse_advance (pbag, "horiz-adv-x")?.unwrap_or (0.0));
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing; the glyphs get used by text elements
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+/***** NodeFontFace *****/
+
+struct NodeFontFace {
+    units_per_em: Cell<f64>
+}
+
+impl NodeFontFace {
+    fn new () -> NodeFontFace {
+        NodeFontFace {
+            units_per_em: Cell::new (1000.0)
+        }
+    }
+}
+
+impl NodeTrait for NodeFontFace {
+    fn set_atts (&self, node: &RsvgNode, handle: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        if let Some (value) = property_bag::lookup (pbag, "units-per-em") {
+            let units = parsers::number (&value)
+                .map_err (|e| NodeError::parse_error ("units-per-em", e))?;
+
+            if units <= 0.0 {
+                return Err (NodeError::value_error ("units-per-em", "value must be positive"));
+            }
+
+            self.units_per_em.set (units);
+        }
+
+        // A <font-face> outside of a <font> describes a font that lives
+        // elsewhere, which we don't support.
+        if let Some (parent) = node.get_parent () {
+            if parent.get_type () == NodeType::Font && !handle.is_null () {
+                if let Some (family) = property_bag::lookup (pbag, "font-family") {
+                    unsafe {
+                        rsvg_handle_register_svg_font (handle,
+                                                       family.to_glib_none ().0,
+                                                       &parent as *const RsvgNode);
+                    }
+                }
+            }
+        }
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+/***** NodeGlyph *****/
+
+// Used for both <glyph> and <missing-glyph>
+struct NodeGlyph {
+    is_missing:  bool,
+    unicode:     RefCell<Option<String>>,
+    horiz_adv_x: Cell<Option<f64>>,
+    builder:     RefCell<RsvgPathBuilder>
+}
+
+impl NodeGlyph {
+    fn new (is_missing: bool) -> NodeGlyph {
+        NodeGlyph {
+            is_missing:  is_missing,
+            unicode:     RefCell::new (None),
+            horiz_adv_x: Cell::new (None),
+            builder:     RefCell::new (RsvgPathBuilder::new ())
+        }
+    }
+
+    // Number of bytes at the start of text that this glyph stands for
+    fn match_len (&self, text: &str) -> Option<usize> {
+        match *self.unicode.borrow () {
+            Some (ref u) if !u.is_empty () && text.starts_with (u.as_str ()) => Some (u.len ()),
+            _ => None
+        }
+    }
+}
+
+impl NodeTrait for NodeGlyph {
+    fn set_atts (&self, node: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        *self.unicode.borrow_mut () = property_bag::lookup (pbag, "unicode");
+        self.horiz_adv_x.set (parse_advance (pbag, "horiz-adv-x")?);
+
+        let mut builder = self.builder.borrow_mut ();
+        *builder = RsvgPathBuilder::new ();
+
+        if let Some (value) = property_bag::lookup (pbag, "d") {
+            // As with <path>, the partial outline is still usable
+            if let Err (e) = path_parser::parse_path_into_builder (&value, &mut *builder) {
+                if !value.trim ().is_empty () {
+                    node.add_error (NodeError::parse_error ("d", parsers::ParseError::new (e.to_string ())));
+                }
+            }
+        }
+
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+        // nothing
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+/***** Layout *****/
+
+// Appends the outline of a glyph, whose origin goes at (x, 0) and whose
+// y axis points up, as in the font's coordinate system.
+fn append_glyph (builder: &mut RsvgPathBuilder, glyph: &RsvgPathBuilder, x: f64, scale: f64) {
+    let transform = |(px, py): (f64, f64)| (x + px * scale, -py * scale);
+
+    for command in glyph.get_path_commands () {
+        builder.push_command (match *command {
+            PathCommand::MoveTo (px, py) => {
+                let (tx, ty) = transform ((px, py));
+                PathCommand::MoveTo (tx, ty)
+            },
+
+            PathCommand::LineTo (px, py) => {
+                let (tx, ty) = transform ((px, py));
+                PathCommand::LineTo (tx, ty)
+            },
+
+            PathCommand::CurveTo (p2, p3, p4) => PathCommand::CurveTo (transform (p2), transform (p3), transform (p4)),
+
+            PathCommand::ClosePath => PathCommand::ClosePath
+        });
+    }
+}
+
+// Lays out text with the glyphs of an SVG font, with the start of the
+// baseline at the origin.  The outlines get appended to the builder if
+// there is one.  Returns the advance of the text.
+pub fn layout_text (font:           &RsvgNode,
+                    text:           &str,
+                    font_size:      f64,
+                    letter_spacing: f64,
+                    builder:        Option<&mut RsvgPathBuilder>) -> f64 {
+    let children = font.children.borrow ();
+
+    let default_advance = font.get_impl::<NodeFont> ().map_or (0.0, |f| f.horiz_adv_x.get ());
+
+    let units_per_em = children.iter ()
+        .filter_map (|c| c.get_impl::<NodeFontFace> ())
+        .next ()
+        .map_or (1000.0, |f| f.units_per_em.get ());
+
+    let glyphs: Vec<&NodeGlyph> = children.iter ()
+        .filter_map (|c| c.get_impl::<NodeGlyph> ())
+        .collect ();
+
+    layout_glyphs (&glyphs, default_advance, font_size / units_per_em, text, letter_spacing, builder)
+}
+
+fn layout_glyphs (glyphs:          &[&NodeGlyph],
+                  default_advance: f64,
+                  scale:           f64,
+                  text:            &str,
+                  letter_spacing:  f64,
+                  mut builder:     Option<&mut RsvgPathBuilder>) -> f64 {
+    let missing = glyphs.iter ().find (|g| g.is_missing);
+
+    let mut x = 0.0;
+    let mut rest = text;
+
+    while let Some (c) = rest.chars ().next () {
+        // The first glyph in document order wins, so fonts list ligatures
+        // before the glyphs for their first character.
+        let (glyph, len) = match glyphs.iter ()
+            .filter (|g| !g.is_missing)
+            .filter_map (|g| g.match_len (rest).map (|len| (g, len)))
+            .next () {
+                Some ((g, len)) => (Some (g), len),
+                None => (missing, c.len_utf8 ())
+            };
+
+        let advance = match glyph {
+            Some (g) => {
+                if let Some (ref mut b) = builder {
+                    append_glyph (b, &g.builder.borrow (), x, scale);
+                }
+
+                g.horiz_adv_x.get ().unwrap_or (default_advance)
+            },
+
+            None => default_advance
+        };
+
+        x += advance * scale + letter_spacing;
+        rest = &rest[len..];
+    }
+
+    x
+}
+
+/***** C Prototypes *****/
+
+#[no_mangle]
+pub extern fn rsvg_node_font_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Font,
+                    raw_parent,
+                    Box::new (NodeFont::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_font_face_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::FontFace,
+                    raw_parent,
+                    Box::new (NodeFontFace::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_glyph_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Glyph,
+                    raw_parent,
+                    Box::new (NodeGlyph::new (false)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_missing_glyph_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::Glyph,
+                    raw_parent,
+                    Box::new (NodeGlyph::new (true)))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_font_layout_text (raw_font:       *const RsvgNode,
+                                          text:           *const libc::c_char,
+                                          font_size:      f64,
+                                          letter_spacing: f64,
+                                          raw_builder:    *mut RsvgPathBuilder) -> f64 {
+    assert! (!raw_font.is_null ());
+    let font: &RsvgNode = unsafe { & *raw_font };
+
+    assert! (!text.is_null ());
+    let text = unsafe { String::from_glib_none (text) };
+
+    let builder = if raw_builder.is_null () {
+        None
+    } else {
+        Some (unsafe { &mut *raw_builder })
+    };
+
+    layout_text (font, &text, font_size, letter_spacing, builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph (unicode: &str, advance: Option<f64>, d: &str) -> NodeGlyph {
+        let glyph = NodeGlyph::new (unicode.is_empty ());
+
+        *glyph.unicode.borrow_mut () = Some (unicode.to_string ());
+        glyph.horiz_adv_x.set (advance);
+        path_parser::parse_path_into_builder (d, &mut *glyph.builder.borrow_mut ()).unwrap ();
+
+        glyph
+    }
+
+    #[test]
+    fn lays_out_glyphs () {
+        let fi = glyph ("fi", Some (800.0), "M0 0 L 800 0");
+        let f = glyph ("f", None, "M0 0 L 0 1000");
+        let missing = glyph ("", Some (250.0), "M0 0 L 250 250");
+        let glyphs = [&fi, &f, &missing];
+
+        // horiz-adv-x of 500 on the <font>, 1000 units per em at a font size of 10
+        assert_eq! (layout_glyphs (&glyphs, 500.0, 0.01, "ffi", 0.0, None), 13.0);
+        assert_eq! (layout_glyphs (&glyphs, 500.0, 0.01, "fx", 1.0, None), 9.5);
+
+        let mut builder = RsvgPathBuilder::new ();
+        layout_glyphs (&glyphs, 500.0, 0.01, "fix", 0.0, Some (&mut builder));
+
+        assert_eq! (builder.get_path_commands (),
+                    &[PathCommand::MoveTo (0.0, 0.0),
+                      PathCommand::LineTo (8.0, 0.0),
+                      PathCommand::MoveTo (8.0, 0.0),
+                      PathCommand::LineTo (10.5, -2.5)]);
+    }
+}
@@ -257,7 +257,7 @@ impl Gradient {
             }
 
             child.with_impl (|stop: &NodeStop| {
-                self.add_color_stop (stop.get_offset (), stop.get_rgba ());
+                self.add_color_stop (stop.get_offset (), stop.get_rgba (&child));
             });
         }
     }
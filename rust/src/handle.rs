@@ -1 +1,55 @@
+use ::cairo;
+use ::cairo_sys;
+use ::glib::translate::*;
+use ::glib_sys;
+use ::libc;
+
+use std::ptr;
+
+use node::RsvgNode;
+use path_builder::RsvgPathBuilder;
+
 pub enum RsvgHandle {}
+
+extern "C" {
+    fn rsvg_handle_lookup_node (handle: *const RsvgHandle, url: *const libc::c_char) -> *const RsvgNode;
+    fn rsvg_handle_get_text_outlines (handle: *const RsvgHandle, node: *const RsvgNode) -> *mut RsvgPathBuilder;
+
+    fn rsvg_cairo_surface_new_from_href (handle: *const RsvgHandle,
+                                         href:   *const libc::c_char,
+                                         error:  *mut *mut glib_sys::GError) -> *mut cairo_sys::cairo_surface_t;
+}
+
+pub fn lookup_node (handle: *const RsvgHandle, url: &str) -> Option<RsvgNode> {
+    let raw_node = unsafe { rsvg_handle_lookup_node (handle, url.to_glib_none ().0) };
+
+    if raw_node.is_null () {
+        None
+    } else {
+        let node: &RsvgNode = unsafe { & *raw_node };
+        Some (node.clone ())
+    }
+}
+
+// Outlines of the glyphs of a <text> element, in its user space
+pub fn get_text_outlines (handle: *const RsvgHandle, node: &RsvgNode) -> Option<RsvgPathBuilder> {
+    let raw_builder = unsafe { rsvg_handle_get_text_outlines (handle, node as *const RsvgNode) };
+
+    if raw_builder.is_null () {
+        None
+    } else {
+        Some (*unsafe { Box::from_raw (raw_builder) })
+    }
+}
+
+pub fn load_image_surface (handle: *const RsvgHandle, href: &str) -> Option<cairo::ImageSurface> {
+    unsafe {
+        let raw_surface = rsvg_cairo_surface_new_from_href (handle, href.to_glib_none ().0, ptr::null_mut ());
+
+        if raw_surface.is_null () {
+            None
+        } else {
+            Some (cairo::ImageSurface::from_glib_full (raw_surface))
+        }
+    }
+}
@@ -10,18 +10,55 @@ extern crate bitflags;
 #[macro_use]
 extern crate downcast_rs;
 
+pub use animation::{
+    rsvg_node_animate_new,
+    rsvg_node_animate_color_new,
+    rsvg_node_animate_motion_new,
+    rsvg_node_animate_transform_new,
+    rsvg_node_set_new,
+    rsvg_node_mpath_new,
+    rsvg_node_animation_get_target,
+    rsvg_node_animation_apply,
+};
+
+pub use api::{
+    Loader,
+    LoadingError,
+    Renderer,
+    RenderingError,
+    SvgHandle
+};
+
 pub use aspect_ratio::{
+    AspectRatio,
+    Align,
+    AlignMode,
+    FitMode,
     rsvg_aspect_ratio_parse,
     rsvg_aspect_ratio_compute
 };
 
 pub use bbox::{
     RsvgBbox,
+    StrokeParams,
+    path_builder_fill_bbox,
+    path_builder_stroke_bbox,
     rsvg_bbox_init,
     rsvg_bbox_insert,
     rsvg_bbox_clip
 };
 
+pub use blend_mode::{
+    MixBlendMode,
+    rsvg_blend_mode_composite
+};
+
+pub use clip_path::{
+    ClipPathUnits,
+    rsvg_node_clip_path_new,
+    rsvg_node_clip_path_get_units
+};
+
 pub use cnode::{
     rsvg_rust_cnode_new,
     rsvg_rust_cnode_get_impl
@@ -35,6 +72,54 @@ pub use color::{
     rsvg_css_parse_color
 };
 
+pub use css::{
+    rsvg_stylesheet_new,
+    rsvg_stylesheet_free,
+    rsvg_stylesheet_parse,
+    rsvg_stylesheet_apply
+};
+
+pub use diagnostics::{
+    Diagnostic,
+    rsvg_diagnostics_new,
+    rsvg_diagnostics_free,
+    rsvg_diagnostics_add_node,
+    rsvg_diagnostics_to_string,
+    rsvg_diagnostics_get_first
+};
+
+pub use filters::{
+    rsvg_filter_render,
+    rsvg_new_filter,
+    rsvg_new_filter_primitive_blend,
+    rsvg_new_filter_primitive_color_matrix,
+    rsvg_new_filter_primitive_component_transfer,
+    rsvg_new_filter_primitive_composite,
+    rsvg_new_filter_primitive_convolve_matrix,
+    rsvg_new_filter_primitive_diffuse_lighting,
+    rsvg_new_filter_primitive_displacement_map,
+    rsvg_new_filter_primitive_erode,
+    rsvg_new_filter_primitive_flood,
+    rsvg_new_filter_primitive_gaussian_blur,
+    rsvg_new_filter_primitive_image,
+    rsvg_new_filter_primitive_merge,
+    rsvg_new_filter_primitive_merge_node,
+    rsvg_new_filter_primitive_offset,
+    rsvg_new_filter_primitive_specular_lighting,
+    rsvg_new_filter_primitive_tile,
+    rsvg_new_filter_primitive_turbulence,
+    rsvg_new_node_component_transfer_function,
+    rsvg_new_node_light_source
+};
+
+pub use font::{
+    rsvg_node_font_new,
+    rsvg_node_font_face_new,
+    rsvg_node_glyph_new,
+    rsvg_node_missing_glyph_new,
+    rsvg_node_font_layout_text
+};
+
 pub use gradient::{
     rsvg_node_linear_gradient_new,
     rsvg_node_radial_gradient_new,
@@ -63,8 +148,11 @@ pub use node::{
     rsvg_node_get_state,
     rsvg_node_add_child,
     rsvg_node_set_atts,
+    rsvg_node_set_element,
     rsvg_node_draw,
     rsvg_node_set_attribute_parse_error,
+    rsvg_node_set_location,
+    rsvg_node_set_attribute_location,
     rsvg_node_foreach_child,
     rsvg_node_draw_children,
 };
@@ -76,12 +164,40 @@ pub use opacity::{
 };
 
 pub use parsers::{
+    Parse,
     rsvg_css_parse_number_list,
     rsvg_css_parse_number_optional_number
 };
 
 pub use path_builder::{
-    rsvg_path_builder_add_to_cairo_context
+    PathCommand,
+    RsvgPathBuilder,
+    rsvg_path_builder_add_to_cairo_context,
+    rsvg_path_builder_new,
+    rsvg_path_builder_destroy,
+    rsvg_path_builder_move_to,
+    rsvg_path_builder_line_to,
+    rsvg_path_builder_curve_to,
+    rsvg_path_builder_close_path,
+    rsvg_path_builder_append
+};
+
+pub use path_boolean::{
+    BooleanOp,
+    path_builder_boolean_op,
+    transform_path,
+    rsvg_path_builder_add_filled_area,
+    rsvg_path_builder_intersect_filled_area,
+    rsvg_path_builder_get_fill_bbox_in_user_space
+};
+
+pub use path_geometry::{
+    PathMeasure,
+    PathSegment
+};
+
+pub use path_parser::{
+    parse_path_into_builder
 };
 
 pub use pattern::{
@@ -89,6 +205,10 @@ pub use pattern::{
     pattern_resolve_fallbacks_and_set_pattern,
 };
 
+pub use serializer::{
+    rsvg_serialize_with_text_as_paths
+};
+
 pub use shapes::{
     rsvg_node_circle_new,
     rsvg_node_ellipse_new,
@@ -97,21 +217,33 @@ pub use shapes::{
     rsvg_node_polygon_new,
     rsvg_node_polyline_new,
     rsvg_node_rect_new,
+    rsvg_node_path_measure_new,
+    rsvg_path_measure_free,
+    rsvg_path_measure_get_length,
+    rsvg_path_measure_get_author_length,
+    rsvg_path_measure_get_point,
 };
 
 pub use stop::{
     rsvg_node_stop_new
 };
 
+pub use stroke::{
+    Dashes,
+    path_builder_stroke_to_path
+};
+
 pub use structure::{
     rsvg_node_group_new,
     rsvg_node_defs_new,
     rsvg_node_switch_new,
     rsvg_node_symbol_new,
+    rsvg_node_foreign_object_new,
+    rsvg_node_foreign_object_add_paragraph,
+    rsvg_node_foreign_object_set_unsupported,
     rsvg_node_svg_new,
     rsvg_node_svg_get_size,
     rsvg_node_svg_get_view_box,
-    rsvg_node_svg_apply_atts,
     rsvg_node_use_new,
 };
 
@@ -124,12 +256,20 @@ pub use viewbox::{
 };
 
 
+mod animation;
+mod api;
 mod aspect_ratio;
 mod bbox;
+mod blend_mode;
+mod clip_path;
 mod cnode;
 mod color;
+mod css;
+mod diagnostics;
 mod drawing_ctx;
 mod error;
+mod filters;
+mod font;
 mod gradient;
 mod handle;
 mod length;
@@ -139,14 +279,22 @@ mod opacity;
 mod paint_server;
 mod parse_transform;
 mod parsers;
+mod path_boolean;
 mod path_builder;
+mod path_geometry;
 mod path_parser;
+mod path_simplify;
 mod pattern;
+mod properties;
 mod property_bag;
+mod serializer;
 mod shapes;
 mod state;
 mod stop;
+mod stroke;
 mod structure;
+#[cfg(test)]
+mod test_utils;
 mod transform;
 mod util;
 mod viewbox;
@@ -5,15 +5,18 @@ use ::libc;
 
 use std::rc::Rc;
 use std::rc::Weak;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::ptr;
 
+use css::{Declaration, SiblingPositions};
 use drawing_ctx::RsvgDrawingCtx;
 use drawing_ctx;
 use error::*;
 use handle::RsvgHandle;
 use parsers::ParseError;
+use property_bag;
 use property_bag::RsvgPropertyBag;
+use properties::{ComputedValues, SpecifiedValues};
 use state::RsvgState;
 
 /* A *const RsvgNode is just a pointer for the C code's benefit: it
@@ -61,8 +64,17 @@ pub struct Node {
     node_type:     NodeType,
     parent:        Option<Weak<Node>>,       // optional; weak ref to parent
     pub children:  RefCell<Vec<Rc<Node>>>,   // strong references to children
+    element_name:  RefCell<String>,          // for matching CSS selectors
+    attributes:    RefCell<Vec<(String, String)>>,
+    values:        RefCell<SpecifiedValues>, // result of the CSS cascade
+    computed:      RefCell<Option<ComputedValues>>, // cached; see get_computed_values()
+    declarations:  RefCell<Vec<Declaration>>, // the cascaded declarations, in increasing priority
+    positions:     Cell<Option<SiblingPositions>>, // for matching CSS selectors; computed on demand
     state:         *mut RsvgState,
     result:        RefCell <NodeResult>,
+    errors:        RefCell<Vec<NodeError>>,  // everything that went wrong while loading
+    location:      Cell<(u32, u32)>,          // line and column of the start tag
+    attribute_locations: RefCell<Vec<(String, (u32, u32))>>, // line and column of each attribute
     node_impl:     Box<NodeTrait>
 }
 
@@ -72,6 +84,7 @@ pub struct Node {
 pub enum NodeType {
     Invalid = 0,
 
+    Animation,
     Chars,
     Circle,
     ClipPath,
@@ -79,6 +92,10 @@ pub enum NodeType {
     Defs,
     Ellipse,
     Filter,
+    Font,
+    FontFace,
+    ForeignObject,
+    Glyph,
     Group,
     Image,
     LightSource,
@@ -86,6 +103,7 @@ pub enum NodeType {
     LinearGradient,
     Marker,
     Mask,
+    MPath,
     Path,
     Pattern,
     Polygon,
@@ -97,6 +115,7 @@ pub enum NodeType {
     Switch,
     Symbol,
     Text,
+    TextPath,
     TRef,
     TSpan,
     Use,
@@ -132,8 +151,17 @@ impl Node {
             node_type: node_type,
             parent:    parent,
             children:  RefCell::new (Vec::new ()),
+            element_name: RefCell::new (String::new ()),
+            attributes: RefCell::new (Vec::new ()),
+            values:    RefCell::new (SpecifiedValues::default ()),
+            computed:  RefCell::new (None),
+            declarations: RefCell::new (Vec::new ()),
+            positions: Cell::new (None),
             state:     state,
             result:    RefCell::new (Ok (())),
+            errors:    RefCell::new (Vec::new ()),
+            location:  Cell::new ((0, 0)),
+            attribute_locations: RefCell::new (Vec::new ()),
             node_impl: node_impl
         }
     }
@@ -146,6 +174,76 @@ impl Node {
         self.state
     }
 
+    pub fn set_element (&self, element_name: &str, attributes: Vec<(String, String)>) {
+        *self.element_name.borrow_mut () = element_name.to_string ();
+        *self.attributes.borrow_mut () = attributes;
+    }
+
+    pub fn get_element_name (&self) -> String {
+        self.element_name.borrow ().clone ()
+    }
+
+    pub fn get_attributes (&self) -> Vec<(String, String)> {
+        self.attributes.borrow ().clone ()
+    }
+
+    // The attribute as it appears in the document
+    pub fn get_attribute (&self, name: &str) -> Option<String> {
+        self.attributes.borrow ().iter ()
+            .find (|&&(ref key, _)| key == name)
+            .map (|&(_, ref value)| value.clone ())
+    }
+
+    pub fn set_specified_values (&self, values: SpecifiedValues) {
+        *self.values.borrow_mut () = values;
+        self.invalidate_computed_values ();
+    }
+
+    pub fn set_style_declarations (&self, declarations: Vec<Declaration>) {
+        *self.declarations.borrow_mut () = declarations;
+    }
+
+    pub fn get_style_declarations (&self) -> Vec<Declaration> {
+        self.declarations.borrow ().clone ()
+    }
+
+    pub fn get_sibling_positions (&self) -> Option<SiblingPositions> {
+        self.positions.get ()
+    }
+
+    pub fn set_sibling_positions (&self, positions: SiblingPositions) {
+        self.positions.set (Some (positions));
+    }
+
+    // Resolves the specified values against the parent's computed values.
+    // The result is cached until the specified values of this node or of
+    // one of its ancestors change, as when animations get applied.
+    pub fn get_computed_values (&self) -> ComputedValues {
+        if let Some (ref values) = *self.computed.borrow () {
+            return values.clone ();
+        }
+
+        let parent_values = match self.get_parent () {
+            Some (parent) => parent.get_computed_values (),
+            None          => ComputedValues::default ()
+        };
+
+        let values = self.values.borrow ().to_computed_values (&parent_values);
+        *self.computed.borrow_mut () = Some (values.clone ());
+
+        values
+    }
+
+    // A node's values only get cached after its parent's, so if this
+    // node has none, neither do its descendants.
+    fn invalidate_computed_values (&self) {
+        if self.computed.borrow_mut ().take ().is_some () {
+            for child in self.children.borrow ().iter () {
+                child.invalidate_computed_values ();
+            }
+        }
+    }
+
     pub fn get_parent (&self) -> Option<Rc<Node>> {
         match self.parent {
             None => None,
@@ -172,7 +270,13 @@ impl Node {
     }
 
     pub fn set_atts (&self, node: &RsvgNode, handle: *const RsvgHandle, pbag: *const RsvgPropertyBag) {
-        *self.result.borrow_mut () = self.node_impl.set_atts (node, handle, pbag);
+        let result = self.node_impl.set_atts (node, handle, pbag);
+
+        if let Err (ref e) = result {
+            self.add_error (e.clone ());
+        }
+
+        *self.result.borrow_mut () = result;
     }
 
     pub fn draw (&self, node: &RsvgNode, draw_ctx: *const RsvgDrawingCtx, dominate: i32) {
@@ -182,9 +286,45 @@ impl Node {
     }
 
     pub fn set_error (&self, error: NodeError) {
+        self.add_error (error.clone ());
         *self.result.borrow_mut () = Err (error);
     }
 
+    // Records an error without putting the node in error, as for invalid
+    // presentation attributes, which are just ignored.  Attributes get
+    // parsed again when animations are applied, so we skip duplicates.
+    pub fn add_error (&self, error: NodeError) {
+        let mut errors = self.errors.borrow_mut ();
+
+        if !errors.contains (&error) {
+            errors.push (error);
+        }
+    }
+
+    pub fn get_errors (&self) -> Vec<NodeError> {
+        self.errors.borrow ().clone ()
+    }
+
+    pub fn set_location (&self, line: u32, column: u32) {
+        self.location.set ((line, column));
+    }
+
+    pub fn get_location (&self) -> (u32, u32) {
+        self.location.get ()
+    }
+
+    pub fn set_attribute_location (&self, attr_name: &str, line: u32, column: u32) {
+        self.attribute_locations.borrow_mut ().push ((attr_name.to_string (), (line, column)));
+    }
+
+    // Where the attribute is in the source, or else where the start tag is
+    pub fn get_attribute_location (&self, attr_name: &str) -> (u32, u32) {
+        self.attribute_locations.borrow ()
+            .iter ()
+            .find (|&&(ref name, _)| name == attr_name)
+            .map_or (self.get_location (), |&(_, location)| location)
+    }
+
     pub fn get_result (&self) -> NodeResult {
         self.result.borrow ().clone ()
     }
@@ -193,6 +333,10 @@ impl Node {
         self.node_impl.get_c_impl ()
     }
 
+    pub fn get_impl<T: NodeTrait> (&self) -> Option<&T> {
+        (&self.node_impl).downcast_ref::<T> ()
+    }
+
     pub fn with_impl<T: NodeTrait, F: FnOnce (&T)> (&self, f: F) {
         if let Some (t) = (&self.node_impl).downcast_ref::<T> () {
             f (t);
@@ -302,7 +446,7 @@ pub extern fn rsvg_node_unref (raw_node: *mut RsvgNode) -> *mut RsvgNode {
 // added Rc::ptr_eq(), but we don't want to depend on unstable Rust
 // just yet.
 
-fn rc_node_ptr_eq<T: ?Sized> (this: &Rc<T>, other: &Rc<T>) -> bool {
+pub fn rc_node_ptr_eq<T: ?Sized> (this: &Rc<T>, other: &Rc<T>) -> bool {
     let this_ptr: *const T = &**this;
     let other_ptr: *const T = &**other;
     this_ptr == other_ptr
@@ -350,6 +494,26 @@ pub extern fn rsvg_node_set_atts (raw_node: *mut RsvgNode, handle: *const RsvgHa
     node.set_atts (node, handle, pbag);
 }
 
+#[no_mangle]
+pub extern fn rsvg_node_set_element (raw_node:       *const RsvgNode,
+                                     element_name:   *const libc::c_char,
+                                     supports_class: glib_sys::gboolean,
+                                     pbag:           *const RsvgPropertyBag) {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    assert! (!element_name.is_null ());
+    let element_name = unsafe { String::from_glib_none (element_name) };
+
+    let supports_class: bool = from_glib (supports_class);
+
+    let attributes = property_bag::to_vec (pbag).into_iter ()
+        .filter (|&(ref key, _)| supports_class || key != "class")
+        .collect ();
+
+    node.set_element (&element_name, attributes);
+}
+
 #[no_mangle]
 pub extern fn rsvg_node_draw (raw_node: *const RsvgNode, draw_ctx: *const RsvgDrawingCtx, dominate: i32) {
     assert! (!raw_node.is_null ());
@@ -375,6 +539,28 @@ pub extern fn rsvg_node_set_attribute_parse_error (raw_node:    *const RsvgNode,
     }
 }
 
+#[no_mangle]
+pub extern fn rsvg_node_set_location (raw_node: *const RsvgNode, line: libc::c_int, column: libc::c_int) {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    node.set_location (line.max (0) as u32, column.max (0) as u32);
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_set_attribute_location (raw_node:  *const RsvgNode,
+                                                attr_name: *const libc::c_char,
+                                                line:      libc::c_int,
+                                                column:    libc::c_int) {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    assert! (!attr_name.is_null ());
+    let attr_name = unsafe { String::from_glib_none (attr_name) };
+
+    node.set_attribute_location (&attr_name, line.max (0) as u32, column.max (0) as u32);
+}
+
 type NodeForeachChild = unsafe extern "C" fn (node: *const RsvgNode, data: *const libc::c_void) -> glib_sys::gboolean;
 
 #[no_mangle]
@@ -407,26 +593,10 @@ pub extern fn rsvg_node_draw_children (raw_node: *const RsvgNode, draw_ctx: *con
 #[cfg(test)]
 mod tests {
     use std::rc::Rc;
-    use drawing_ctx::RsvgDrawingCtx;
-    use handle::RsvgHandle;
-    use property_bag::RsvgPropertyBag;
+    use properties::FillRule;
     use super::*;
     use std::ptr;
-
-    struct TestNodeImpl {}
-
-    impl NodeTrait for TestNodeImpl {
-        fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, _: *const RsvgPropertyBag) -> NodeResult {
-            Ok (())
-        }
-
-        fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
-        }
-
-        fn get_c_impl (&self) -> *const RsvgCNodeImpl {
-            unreachable! ();
-        }
-    }
+    use test_utils::TestNodeImpl;
 
     #[test]
     fn node_refs_and_unrefs () {
@@ -450,6 +620,32 @@ mod tests {
         assert! (weak.upgrade ().is_none ());
     }
 
+    #[test]
+    fn set_error_records_errors_once () {
+        let node = Node::new (NodeType::Path, None, ptr::null_mut (), Box::new (TestNodeImpl {}));
+
+        node.add_error (NodeError::value_error ("fill", "bogus"));
+        assert! (node.get_result ().is_ok ());
+
+        node.set_error (NodeError::value_error ("d", "bogus"));
+        node.set_error (NodeError::value_error ("d", "bogus"));
+        assert! (node.get_result ().is_err ());
+
+        assert_eq! (node.get_errors (), vec! [NodeError::value_error ("fill", "bogus"),
+                                              NodeError::value_error ("d", "bogus")]);
+    }
+
+    #[test]
+    fn attribute_locations_fall_back_to_the_start_tag () {
+        let node = Node::new (NodeType::Path, None, ptr::null_mut (), Box::new (TestNodeImpl {}));
+
+        node.set_location (3, 5);
+        node.set_attribute_location ("d", 4, 9);
+
+        assert_eq! (node.get_attribute_location ("d"), (4, 9));
+        assert_eq! (node.get_attribute_location ("fill"), (3, 5));
+    }
+
     #[test]
     fn reffed_node_is_same_as_original_node () {
         let node = Rc::new (Node::new (NodeType::Path,
@@ -489,6 +685,25 @@ mod tests {
         rsvg_node_unref (ref2);
     }
 
+    #[test]
+    fn computed_values_follow_changes_in_ancestors () {
+        let parent = Rc::new (Node::new (NodeType::Group, None, ptr::null_mut (), Box::new (TestNodeImpl {})));
+        let child = Rc::new (Node::new (NodeType::Path, Some (Rc::downgrade (&parent)), ptr::null_mut (), Box::new (TestNodeImpl {})));
+        parent.add_child (&child);
+
+        let mut values = SpecifiedValues::default ();
+        values.set_property ("fill-rule", "evenodd").unwrap ();
+        parent.set_specified_values (values);
+
+        assert_eq! (child.get_computed_values ().fill_rule, FillRule::EvenOdd);
+
+        let mut values = SpecifiedValues::default ();
+        values.set_property ("fill-rule", "nonzero").unwrap ();
+        parent.set_specified_values (values);
+
+        assert_eq! (child.get_computed_values ().fill_rule, FillRule::NonZero);
+    }
+
     #[test]
     fn node_is_its_own_ancestor () {
         let node = Rc::new (Node::new (NodeType::Path,
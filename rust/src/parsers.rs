@@ -71,6 +71,20 @@ pub fn angle_degrees (s: &str) -> Result <f64, ParseError> {
     Ok (angle)
 }
 
+// number:
+// https://www.w3.org/TR/SVG/types.html#DataTypeNumber
+
+pub fn number (s: &str) -> Result <f64, ParseError> {
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+
+    let value = parser.expect_number ().map_err (|_| ParseError::new ("expected number"))?;
+
+    parser.expect_exhausted ().map_err (|_| ParseError::new ("expected number"))?;
+
+    Ok (value as f64)
+}
+
 fn optional_comma (parser: &mut Parser) {
     let _ = parser.try (|p| p.expect_comma ());
 }
@@ -272,6 +286,21 @@ pub extern fn rsvg_css_parse_number_list (in_str:   *const libc::c_char,
 mod tests {
     use super::*;
 
+    #[test]
+    fn parses_number () {
+        assert_eq! (number ("42"), Ok (42.0));
+        assert_eq! (number ("-1.5"), Ok (-1.5));
+        assert_eq! (number ("  3e2  "), Ok (300.0));
+    }
+
+    #[test]
+    fn invalid_number_yields_error () {
+        assert! (number ("").is_err ());
+        assert! (number ("foo").is_err ());
+        assert! (number ("1 2").is_err ());
+        assert! (number ("10px").is_err ());
+    }
+
     #[test]
     fn parses_number_optional_number () {
         assert_eq! (number_optional_number ("1, 2"), Ok ((1.0, 2.0)));
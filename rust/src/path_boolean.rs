@@ -0,0 +1,892 @@
+use ::cairo;
+use ::cairo::MatrixTrait;
+use ::glib::translate::*;
+use ::glib_sys;
+
+use std::collections::{HashMap, HashSet};
+use std::f64;
+
+use bbox::path_builder_fill_bbox;
+use path_builder::*;
+use path_simplify;
+
+/* Boolean operations on the areas covered by filling paths.
+ *
+ * Both paths get flattened, and all their edges get split at the
+ * points where they cross or touch any other edge, including edges
+ * of the same path.  After that, no two edges cross, so each edge
+ * has a well-defined area on each side.  An edge is on the boundary
+ * of the result if the result is inside on one side of it and
+ * outside on the other; we find that out by computing the winding
+ * numbers of both paths at points just off the middle of the edge,
+ * and applying their fill rules and the operation.
+ *
+ * The edges of the result are oriented so that the inside is on
+ * their left, and then linked into closed subpaths.  Since every
+ * part of the inside has a winding number of 1 and every part of the
+ * outside has a winding number of 0, the result can be filled with
+ * either fill rule.
+ *
+ * To find the crossings, edges only get compared with the ones whose
+ * horizontal extent overlaps theirs; winding numbers only look at the
+ * edges in the horizontal band of the point; and vertices are looked
+ * up in a hash grid.  This is still quadratic for pathological input,
+ * like many long edges that all overlap, but not for the usual shapes.
+ */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+    Xor
+}
+
+impl BooleanOp {
+    fn apply (self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::Union        => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference   => a && !b,
+            BooleanOp::Xor          => a != b
+        }
+    }
+}
+
+// Points closer than this get merged into a single vertex
+const VERTEX_EPSILON: f64 = 1e-9;
+
+// Distance from the middle of an edge to the points that get tested, relative to its length
+const SAMPLE_OFFSET: f64 = 1e-6;
+
+type Point = (f64, f64);
+type Edge = (Point, Point);
+
+fn sub (a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn cross (a: Point, b: Point) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn dot (a: Point, b: Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn lerp (a: Point, b: Point, t: f64) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn is_inside (winding: i32, fill_rule: cairo::FillRule) -> bool {
+    match fill_rule {
+        cairo::FillRule::Winding => winding != 0,
+        cairo::FillRule::EvenOdd => winding % 2 != 0
+    }
+}
+
+/// Returns a copy of the path with all its points transformed by `matrix`.
+pub fn transform_path (builder: &RsvgPathBuilder, matrix: &cairo::Matrix) -> RsvgPathBuilder {
+    let mut transformed = RsvgPathBuilder::new ();
+
+    for command in builder.get_path_commands () {
+        let command = match *command {
+            PathCommand::MoveTo (x, y) => {
+                let (x, y) = matrix.transform_point (x, y);
+                PathCommand::MoveTo (x, y)
+            },
+
+            PathCommand::LineTo (x, y) => {
+                let (x, y) = matrix.transform_point (x, y);
+                PathCommand::LineTo (x, y)
+            },
+
+            PathCommand::CurveTo ((x2, y2), (x3, y3), (x4, y4)) => {
+                PathCommand::CurveTo (matrix.transform_point (x2, y2),
+                                      matrix.transform_point (x3, y3),
+                                      matrix.transform_point (x4, y4))
+            },
+
+            PathCommand::ClosePath => PathCommand::ClosePath
+        };
+
+        transformed.push_command (command);
+    }
+
+    transformed
+}
+
+// Edges of the flattened path, with all subpaths closed since they get filled
+fn path_edges (builder: &RsvgPathBuilder, tolerance: f64) -> Vec<Edge> {
+    let mut edges = Vec::new ();
+
+    for polyline in path_simplify::to_polylines (builder, tolerance) {
+        // Subpaths with infinite or NaN coordinates don't cover any meaningful area
+        if polyline.iter ().any (|p| !p.0.is_finite () || !p.1.is_finite ()) {
+            continue;
+        }
+
+        for pair in polyline.windows (2) {
+            if pair[0] != pair[1] {
+                edges.push ((pair[0], pair[1]));
+            }
+        }
+
+        let first = polyline[0];
+        let last = polyline[polyline.len () - 1];
+
+        if first != last {
+            edges.push ((last, first));
+        }
+    }
+
+    edges
+}
+
+fn winding_number<'a, I> (edges: I, p: Point) -> i32
+    where I: IntoIterator<Item = &'a Edge>
+{
+    let mut winding = 0;
+
+    for &(a, b) in edges {
+        let side = cross (sub (b, a), sub (p, a));
+
+        if a.1 <= p.1 {
+            if b.1 > p.1 && side > 0.0 {
+                winding += 1;
+            }
+        } else if b.1 <= p.1 && side < 0.0 {
+            winding -= 1;
+        }
+    }
+
+    winding
+}
+
+// The edges of a path, bucketed into horizontal bands of the same
+// height.  An edge is in all the bands that its vertical extent touches.
+struct EdgeBands<'a> {
+    edges:       &'a [Edge],
+    y0:          f64,
+    y1:          f64,
+    band_height: f64,
+    bands:       Vec<Vec<usize>>
+}
+
+// Upper limit for the number of bands, to bound the memory for long edges
+const MAX_BANDS: usize = 1024;
+
+impl<'a> EdgeBands<'a> {
+    fn new (edges: &'a [Edge]) -> EdgeBands<'a> {
+        let y0 = edges.iter ().fold (f64::INFINITY, |y, e| y.min ((e.0).1).min ((e.1).1));
+        let y1 = edges.iter ().fold (f64::NEG_INFINITY, |y, e| y.max ((e.0).1).max ((e.1).1));
+
+        // With an infinite height, all the edges go in a single band
+        let n = if (y1 - y0).is_finite () { edges.len ().max (1).min (MAX_BANDS) } else { 1 };
+
+        let mut bands = EdgeBands {
+            edges,
+            y0,
+            y1,
+            band_height: (y1 - y0) / n as f64,
+            bands: vec! [Vec::new (); n]
+        };
+
+        for (i, e) in edges.iter ().enumerate () {
+            let first = bands.band ((e.0).1.min ((e.1).1));
+            let last = bands.band ((e.0).1.max ((e.1).1));
+
+            for band in &mut bands.bands[first..last + 1] {
+                band.push (i);
+            }
+        }
+
+        bands
+    }
+
+    // Band of a y coordinate in [y0, y1]; this is monotonic in y
+    fn band (&self, y: f64) -> usize {
+        let n = self.bands.len ();
+
+        if n == 1 || !(self.band_height > 0.0) {
+            return 0;
+        }
+
+        (((y - self.y0) / self.band_height).floor ().max (0.0) as usize).min (n - 1)
+    }
+
+    fn winding_number (&self, p: Point) -> i32 {
+        // No edge spans points above or below all of them
+        if !(p.1 >= self.y0 && p.1 <= self.y1) {
+            return 0;
+        }
+
+        winding_number (self.bands[self.band (p.1)].iter ().map (|&i| &self.edges[i]), p)
+    }
+}
+
+fn bboxes_overlap (e: &Edge, f: &Edge) -> bool {
+    let eps = VERTEX_EPSILON;
+
+    (e.0).0.min ((e.1).0) <= (f.0).0.max ((f.1).0) + eps
+        && (f.0).0.min ((f.1).0) <= (e.0).0.max ((e.1).0) + eps
+        && (e.0).1.min ((e.1).1) <= (f.0).1.max ((f.1).1) + eps
+        && (f.0).1.min ((f.1).1) <= (e.0).1.max ((e.1).1) + eps
+}
+
+// Parameter of the projection of p on the edge, if p lies on its interior
+fn point_on_edge (e: &Edge, p: Point) -> Option<f64> {
+    let d = sub (e.1, e.0);
+    let len2 = dot (d, d);
+    let t = dot (sub (p, e.0), d) / len2;
+
+    if t <= 0.0 || t >= 1.0 {
+        return None;
+    }
+
+    let q = lerp (e.0, e.1, t);
+
+    if (q.0 - p.0).hypot (q.1 - p.1) <= VERTEX_EPSILON {
+        Some (t)
+    } else {
+        None
+    }
+}
+
+// Adds the parameters at which edges i and j cross or touch each other to their splits
+fn split_pair (edges: &[Edge], i: usize, j: usize, splits: &mut [Vec<f64>]) {
+    let e = &edges[i];
+    let f = &edges[j];
+
+    if !bboxes_overlap (e, f) {
+        return;
+    }
+
+    let r = sub (e.1, e.0);
+    let s = sub (f.1, f.0);
+    let denom = cross (r, s);
+
+    let r_len = r.0.hypot (r.1);
+    let s_len = s.0.hypot (s.1);
+
+    if denom.abs () > f64::EPSILON * r_len * s_len {
+        let t = cross (sub (f.0, e.0), s) / denom;
+        let u = cross (sub (f.0, e.0), r) / denom;
+
+        let t_eps = VERTEX_EPSILON / r_len;
+        let u_eps = VERTEX_EPSILON / s_len;
+
+        if t >= -t_eps && t <= 1.0 + t_eps && u >= -u_eps && u <= 1.0 + u_eps {
+            if t > t_eps && t < 1.0 - t_eps {
+                splits[i].push (t);
+            }
+
+            if u > u_eps && u < 1.0 - u_eps {
+                splits[j].push (u);
+            }
+        }
+    } else {
+        // Parallel edges only touch if they overlap; split each at the other's ends
+
+        for &p in &[f.0, f.1] {
+            if let Some (t) = point_on_edge (e, p) {
+                splits[i].push (t);
+            }
+        }
+
+        for &p in &[e.0, e.1] {
+            if let Some (u) = point_on_edge (f, p) {
+                splits[j].push (u);
+            }
+        }
+    }
+}
+
+/* Returns, for each edge, the parameters at which it needs to be
+ * split so that it only touches other edges at its ends.
+ */
+fn find_splits (edges: &[Edge]) -> Vec<Vec<f64>> {
+    let mut splits = vec! [Vec::new (); edges.len ()];
+
+    let min_x = |e: &Edge| (e.0).0.min ((e.1).0);
+    let max_x = |e: &Edge| (e.0).0.max ((e.1).0);
+
+    // Sweep from left to right, comparing each edge only with the ones
+    // that start before it ends
+    let mut order: Vec<usize> = (0..edges.len ()).collect ();
+    order.sort_by (|&a, &b| min_x (&edges[a]).partial_cmp (&min_x (&edges[b])).unwrap ());
+
+    for (k, &i) in order.iter ().enumerate () {
+        let right = max_x (&edges[i]) + VERTEX_EPSILON;
+
+        for &j in &order[k + 1..] {
+            if min_x (&edges[j]) > right {
+                break;
+            }
+
+            split_pair (edges, i.min (j), i.max (j), &mut splits);
+        }
+    }
+
+    // Huge or non-finite coordinates can yield NaN parameters, which can't be sorted
+    for ts in &mut splits {
+        ts.retain (|t| t.is_finite ());
+    }
+
+    splits
+}
+
+// The vertices of the split edges, with a hash grid to find the ones
+// that are close to a point.  Cells are at least VERTEX_EPSILON wide,
+// so close points are always in the same or in adjacent cells.
+struct Vertices {
+    points:    Vec<Point>,
+    cell_size: f64,
+    grid:      HashMap<(i64, i64), Vec<usize>>
+}
+
+impl Vertices {
+    fn new (cell_size: f64) -> Vertices {
+        Vertices {
+            points:    Vec::new (),
+            cell_size: cell_size.max (VERTEX_EPSILON * 4.0),
+            grid:      HashMap::new ()
+        }
+    }
+
+    fn cell (&self, p: Point) -> (i64, i64) {
+        ((p.0 / self.cell_size).floor () as i64, (p.1 / self.cell_size).floor () as i64)
+    }
+
+    fn find_or_add (&mut self, p: Point) -> usize {
+        let (cx, cy) = self.cell (p);
+        let mut found = None;
+
+        for dx in -1..2 {
+            for dy in -1..2 {
+                if let Some (indices) = self.grid.get (&(cx.wrapping_add (dx), cy.wrapping_add (dy))) {
+                    let points = &self.points;
+
+                    // Like a linear scan, prefer the vertex that was added first
+                    let close = indices.iter ()
+                        .cloned ()
+                        .filter (|&i| {
+                            let q = points[i];
+                            (q.0 - p.0).abs () <= VERTEX_EPSILON && (q.1 - p.1).abs () <= VERTEX_EPSILON
+                        })
+                        .min ();
+
+                    found = match (found, close) {
+                        (Some (a), Some (b)) => Some (usize::min (a, b)),
+                        (a, b)               => a.or (b)
+                    };
+                }
+            }
+        }
+
+        match found {
+            Some (index) => index,
+
+            None => {
+                let index = self.points.len ();
+                self.points.push (p);
+                self.grid.entry ((cx, cy)).or_default ().push (index);
+                index
+            }
+        }
+    }
+}
+
+// Splits the edges and returns them as unique pairs of vertex indices
+fn split_edges (edges: &[Edge], vertices: &mut Vertices) -> Vec<(usize, usize)> {
+    let mut split = Vec::new ();
+    let mut seen = HashSet::new ();
+
+    for (edge, mut ts) in edges.iter ().zip (find_splits (edges)) {
+        ts.sort_by (|a, b| a.partial_cmp (b).unwrap ());
+        ts.push (1.0);
+
+        let mut from = vertices.find_or_add (edge.0);
+
+        for t in ts {
+            let to = if t == 1.0 {
+                vertices.find_or_add (edge.1)
+            } else {
+                vertices.find_or_add (lerp (edge.0, edge.1, t))
+            };
+
+            // Overlapping edges from both paths yield the same pieces; keep only one of them
+            if from != to && seen.insert ((from.min (to), from.max (to))) {
+                split.push ((from, to));
+            }
+
+            from = to;
+        }
+    }
+
+    split
+}
+
+// Links edges, which must leave no vertex unbalanced, into closed subpaths
+fn link_edges (edges: &[(usize, usize)], vertices: &Vertices) -> RsvgPathBuilder {
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new ();
+
+    for (i, &(from, _)) in edges.iter ().enumerate () {
+        outgoing.entry (from).or_default ().push (i);
+    }
+
+    let direction = |i: usize| {
+        let (from, to) = edges[i];
+        sub (vertices.points[to], vertices.points[from])
+    };
+
+    let mut used = vec! [false; edges.len ()];
+    let mut builder = RsvgPathBuilder::new ();
+
+    for first in 0..edges.len () {
+        if used[first] {
+            continue;
+        }
+
+        let start = edges[first].0;
+        let mut current = first;
+
+        let p = vertices.points[start];
+        builder.move_to (p.0, p.1);
+
+        loop {
+            used[current] = true;
+
+            let end = edges[current].1;
+
+            if end == start {
+                break;
+            }
+
+            let p = vertices.points[end];
+            builder.line_to (p.0, p.1);
+
+            /* Where several edges leave the vertex, take the one that
+             * turns the most to the left, i.e. into the inside.  This
+             * way, shapes that touch at a vertex become separate
+             * subpaths.
+             */
+            let d_in = direction (current);
+
+            // Degenerate input, like subpaths with no area, can leave a
+            // vertex with no edges out of it; close the contour there.
+            let candidates = match outgoing.get (&end) {
+                Some (c) => c,
+                None => break
+            };
+
+            let next = candidates.iter ()
+                .filter (|&&i| !used[i])
+                .map (|&i| {
+                    let d_out = direction (i);
+                    (i, cross (d_in, d_out).atan2 (dot (d_in, d_out)))
+                })
+                .fold (None, |best: Option<(usize, f64)>, (i, angle)| {
+                    match best {
+                        Some ((_, best_angle)) if best_angle >= angle => best,
+                        _ => Some ((i, angle))
+                    }
+                });
+
+            match next {
+                Some ((i, _)) => current = i,
+                None => break
+            }
+        }
+
+        builder.close_path ();
+    }
+
+    builder
+}
+
+/// Computes a boolean operation between the areas covered by filling
+/// the paths `a` and `b` with their respective fill rules.  The curves
+/// get flattened so that they are within `tolerance` of the original
+/// ones; the result only has straight lines, and it is a path with
+/// closed subpaths that can be filled with either fill rule.
+pub fn path_builder_boolean_op (a: &RsvgPathBuilder,
+                                a_fill_rule: cairo::FillRule,
+                                b: &RsvgPathBuilder,
+                                b_fill_rule: cairo::FillRule,
+                                op: BooleanOp,
+                                tolerance: f64) -> RsvgPathBuilder {
+    let a_edges = path_edges (a, tolerance);
+    let b_edges = path_edges (b, tolerance);
+
+    let mut all_edges = a_edges.clone ();
+    all_edges.extend (b_edges.iter ().cloned ());
+
+    let a_bands = EdgeBands::new (&a_edges);
+    let b_bands = EdgeBands::new (&b_edges);
+
+    let mut vertices = Vertices::new (tolerance);
+    let mut result_edges = Vec::new ();
+
+    for (from, to) in split_edges (&all_edges, &mut vertices) {
+        let p = vertices.points[from];
+        let q = vertices.points[to];
+
+        let middle = lerp (p, q, 0.5);
+        let d = sub (q, p);
+
+        // Left normal, scaled to the sample offset
+        let n = (-d.1 * SAMPLE_OFFSET, d.0 * SAMPLE_OFFSET);
+
+        let inside_at = |s: Point| {
+            op.apply (is_inside (a_bands.winding_number (s), a_fill_rule),
+                      is_inside (b_bands.winding_number (s), b_fill_rule))
+        };
+
+        let left = inside_at ((middle.0 + n.0, middle.1 + n.1));
+        let right = inside_at ((middle.0 - n.0, middle.1 - n.1));
+
+        if left && !right {
+            result_edges.push ((from, to));
+        } else if right && !left {
+            result_edges.push ((to, from));
+        }
+    }
+
+    let linked = link_edges (&result_edges, &vertices);
+
+    // Remove the vertices that only came from splitting straight edges
+    path_simplify::merge_collinear_lines (&linked, VERTEX_EPSILON)
+}
+
+// Flattening tolerance for clipping paths, in the units of the clipped element
+const FILLED_AREA_TOLERANCE: f64 = 0.01;
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_add_filled_area (raw_result: *mut RsvgPathBuilder,
+                                                 raw_builder: *const RsvgPathBuilder,
+                                                 affine: *const cairo::Matrix,
+                                                 fill_rule: cairo::FillRule) {
+    assert! (!raw_result.is_null ());
+    assert! (!raw_builder.is_null ());
+    assert! (!affine.is_null ());
+
+    let result: &mut RsvgPathBuilder = unsafe { &mut *raw_result };
+    let builder: &RsvgPathBuilder = unsafe { &*raw_builder };
+    let affine: &cairo::Matrix = unsafe { &*affine };
+
+    *result = path_builder_boolean_op (result, cairo::FillRule::Winding,
+                                       &transform_path (builder, affine), fill_rule,
+                                       BooleanOp::Union,
+                                       FILLED_AREA_TOLERANCE);
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_intersect_filled_area (raw_result: *mut RsvgPathBuilder,
+                                                       raw_clip: *const RsvgPathBuilder) {
+    assert! (!raw_result.is_null ());
+    assert! (!raw_clip.is_null ());
+
+    let result: &mut RsvgPathBuilder = unsafe { &mut *raw_result };
+    let clip: &RsvgPathBuilder = unsafe { &*raw_clip };
+
+    *result = path_builder_boolean_op (result, cairo::FillRule::Winding,
+                                       clip, cairo::FillRule::Winding,
+                                       BooleanOp::Intersection,
+                                       FILLED_AREA_TOLERANCE);
+}
+
+/* The bounding box of a path that was transformed by `affine`, in the
+ * coordinates from before the transformation; clip paths with
+ * clipPathUnits="objectBoundingBox" need this for shapes that they
+ * have already transformed.
+ */
+#[no_mangle]
+pub extern fn rsvg_path_builder_get_fill_bbox_in_user_space (raw_builder: *const RsvgPathBuilder,
+                                                             affine: *const cairo::Matrix,
+                                                             out_rect: *mut cairo::Rectangle) -> glib_sys::gboolean {
+    assert! (!raw_builder.is_null ());
+    assert! (!affine.is_null ());
+    assert! (!out_rect.is_null ());
+
+    let builder: &RsvgPathBuilder = unsafe { &*raw_builder };
+    let affine: &cairo::Matrix = unsafe { &*affine };
+
+    let bbox = affine.try_invert ()
+        .ok ()
+        .and_then (|inverse| path_builder_fill_bbox (&transform_path (builder, &inverse)));
+
+    match bbox {
+        Some (r) => {
+            unsafe { *out_rect = r; }
+            true.to_glib ()
+        },
+
+        None => false.to_glib ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect (x: f64, y: f64, w: f64, h: f64) -> RsvgPathBuilder {
+        let mut builder = RsvgPathBuilder::new ();
+        builder.move_to (x, y);
+        builder.line_to (x + w, y);
+        builder.line_to (x + w, y + h);
+        builder.line_to (x, y + h);
+        builder.close_path ();
+        builder
+    }
+
+    // Signed area of the flattened path, which is the covered area for our results
+    fn area (builder: &RsvgPathBuilder) -> f64 {
+        let mut total = 0.0;
+
+        for polyline in path_simplify::to_polylines (builder, 0.01) {
+            let n = polyline.len ();
+
+            for i in 0..n {
+                let a = polyline[i];
+                let b = polyline[(i + 1) % n];
+                total += cross (a, b) / 2.0;
+            }
+        }
+
+        total
+    }
+
+    fn op (a: &RsvgPathBuilder, b: &RsvgPathBuilder, op: BooleanOp) -> RsvgPathBuilder {
+        path_builder_boolean_op (a, cairo::FillRule::Winding, b, cairo::FillRule::Winding, op, 0.01)
+    }
+
+    fn assert_area (builder: &RsvgPathBuilder, expected: f64) {
+        let actual = area (builder);
+        assert! ((actual - expected).abs () < 1e-6, "area is {}, expected {}", actual, expected);
+    }
+
+    #[test]
+    fn overlapping_rectangles () {
+        let a = rect (0.0, 0.0, 10.0, 10.0);
+        let b = rect (5.0, 5.0, 10.0, 10.0);
+
+        assert_area (&op (&a, &b, BooleanOp::Union), 175.0);
+        assert_area (&op (&a, &b, BooleanOp::Intersection), 25.0);
+        assert_area (&op (&a, &b, BooleanOp::Difference), 75.0);
+        assert_area (&op (&a, &b, BooleanOp::Xor), 150.0);
+
+        let r = path_builder_fill_bbox (&op (&a, &b, BooleanOp::Intersection)).unwrap ();
+        assert_eq! ((r.x, r.y, r.width, r.height), (5.0, 5.0, 5.0, 5.0));
+    }
+
+    #[test]
+    fn intersection_is_a_simple_square () {
+        let a = rect (0.0, 0.0, 10.0, 10.0);
+        let b = rect (5.0, 5.0, 10.0, 10.0);
+
+        let result = op (&a, &b, BooleanOp::Intersection);
+        assert_eq! (result.get_path_commands ().len (), 5);
+    }
+
+    #[test]
+    fn non_finite_coordinates_do_not_panic () {
+        let a = rect (0.0, 0.0, 10.0, 10.0);
+
+        let mut b = RsvgPathBuilder::new ();
+        b.move_to (f64::INFINITY, 5.0);
+        b.line_to (5.0, 5.0);
+        b.line_to (f64::NAN, 0.0);
+        b.close_path ();
+
+        assert_area (&op (&a, &b, BooleanOp::Union), 100.0);
+
+        // Differences between these overflow to infinity
+        let mut c = RsvgPathBuilder::new ();
+        c.move_to (5.0, 5.0);
+        c.line_to (-1e308, 1e308);
+        c.line_to (1e308, -1e308);
+        c.line_to (1e308, 1e308);
+        c.close_path ();
+
+        for &kind in &[BooleanOp::Union, BooleanOp::Intersection, BooleanOp::Difference, BooleanOp::Xor] {
+            op (&a, &c, kind);
+        }
+    }
+
+    #[test]
+    fn disjoint_and_contained_shapes () {
+        let a = rect (0.0, 0.0, 10.0, 10.0);
+        let far = rect (20.0, 0.0, 10.0, 10.0);
+        let inner = rect (2.0, 2.0, 2.0, 2.0);
+
+        assert_area (&op (&a, &far, BooleanOp::Union), 200.0);
+        assert! (op (&a, &far, BooleanOp::Intersection).get_path_commands ().is_empty ());
+
+        assert_area (&op (&a, &inner, BooleanOp::Difference), 96.0);
+        assert_area (&op (&a, &inner, BooleanOp::Intersection), 4.0);
+    }
+
+    #[test]
+    fn shared_edges_merge () {
+        let a = rect (0.0, 0.0, 10.0, 10.0);
+        let b = rect (10.0, 0.0, 10.0, 10.0);
+
+        let union = op (&a, &b, BooleanOp::Union);
+        assert_area (&union, 200.0);
+
+        // The result is a single rectangle
+        assert_eq! (union.get_path_commands ().len (), 5);
+
+        assert_area (&op (&a, &a, BooleanOp::Union), 100.0);
+        assert! (op (&a, &a, BooleanOp::Xor).get_path_commands ().is_empty ());
+    }
+
+    #[test]
+    fn respects_fill_rules () {
+        // Two nested squares in the same direction, so the hole is only empty with evenodd
+        let mut nested = rect (0.0, 0.0, 10.0, 10.0);
+        for command in rect (2.0, 2.0, 6.0, 6.0).get_path_commands () {
+            nested.push_command (command.clone ());
+        }
+
+        let empty = RsvgPathBuilder::new ();
+
+        let winding = path_builder_boolean_op (&nested, cairo::FillRule::Winding,
+                                               &empty, cairo::FillRule::Winding,
+                                               BooleanOp::Union, 0.01);
+        assert_area (&winding, 100.0);
+
+        let even_odd = path_builder_boolean_op (&nested, cairo::FillRule::EvenOdd,
+                                                &empty, cairo::FillRule::Winding,
+                                                BooleanOp::Union, 0.01);
+        assert_area (&even_odd, 64.0);
+    }
+
+    #[test]
+    fn self_intersecting_path () {
+        // A bow tie, whose two triangles have opposite windings
+        let mut bow_tie = RsvgPathBuilder::new ();
+        bow_tie.move_to (0.0, 0.0);
+        bow_tie.line_to (10.0, 10.0);
+        bow_tie.line_to (10.0, 0.0);
+        bow_tie.line_to (0.0, 10.0);
+        bow_tie.close_path ();
+
+        let square = rect (0.0, 0.0, 10.0, 10.0);
+
+        assert_area (&op (&bow_tie, &square, BooleanOp::Intersection), 50.0);
+        assert_area (&op (&square, &bow_tie, BooleanOp::Difference), 50.0);
+    }
+
+    #[test]
+    fn degenerate_subpaths () {
+        let square = rect (0.0, 0.0, 10.0, 10.0);
+
+        // A subpath that goes back on itself, and one smaller than the vertex tolerance
+        let mut flat = RsvgPathBuilder::new ();
+        flat.move_to (20.0, 0.0);
+        flat.line_to (30.0, 0.0);
+        flat.line_to (20.0, 0.0);
+        flat.close_path ();
+        flat.move_to (5.0, 5.0);
+        flat.line_to (5.0 + 1e-10, 5.0);
+        flat.line_to (5.0, 5.0 + 1e-10);
+        flat.close_path ();
+
+        assert! (op (&flat, &flat, BooleanOp::Union).get_path_commands ().is_empty ());
+
+        for &kind in &[BooleanOp::Union, BooleanOp::Difference, BooleanOp::Xor] {
+            assert_area (&op (&square, &flat, kind), 100.0);
+        }
+
+        assert! (op (&square, &flat, BooleanOp::Intersection).get_path_commands ().is_empty ());
+    }
+
+    #[test]
+    fn nearly_coincident_vertices () {
+        // Vertices that are a bit farther apart than VERTEX_EPSILON, and
+        // edges that pass closer than that to them, used to leave the
+        // result with a dangling edge.
+        let mut a = RsvgPathBuilder::new ();
+        a.move_to (10.0 - 1.5e-9, 0.0);
+        a.line_to (0.0, 20.0);
+        a.line_to (10.0 - 1.5e-9, 10.0);
+        a.close_path ();
+
+        let mut b = RsvgPathBuilder::new ();
+        b.move_to (0.0, 0.0);
+        b.line_to (0.0, 20.0);
+        b.line_to (10.0 + 1.5e-9, 10.0);
+        b.close_path ();
+
+        assert_area (&op (&a, &b, BooleanOp::Union), 350.0 / 3.0);
+        assert_area (&op (&a, &b, BooleanOp::Intersection), 100.0 / 3.0);
+        assert_area (&op (&a, &b, BooleanOp::Difference), 50.0 / 3.0);
+        assert_area (&op (&a, &b, BooleanOp::Xor), 250.0 / 3.0);
+    }
+
+    #[test]
+    fn self_touching_path () {
+        // Two triangles that touch at (5, 5), in a single subpath
+        let mut hourglass = RsvgPathBuilder::new ();
+        hourglass.move_to (0.0, 0.0);
+        hourglass.line_to (10.0, 0.0);
+        hourglass.line_to (5.0, 5.0);
+        hourglass.line_to (10.0, 10.0);
+        hourglass.line_to (0.0, 10.0);
+        hourglass.line_to (5.0, 5.0);
+        hourglass.close_path ();
+
+        let union = op (&hourglass, &RsvgPathBuilder::new (), BooleanOp::Union);
+        assert_area (&union, 50.0);
+
+        // The triangles become separate subpaths
+        let moves = union.get_path_commands ().iter ()
+            .filter (|c| match **c { PathCommand::MoveTo (..) => true, _ => false })
+            .count ();
+        assert_eq! (moves, 2);
+
+        assert_area (&op (&rect (0.0, 0.0, 10.0, 10.0), &hourglass, BooleanOp::Difference), 50.0);
+    }
+
+    #[test]
+    fn many_shapes () {
+        // A grid of squares that touch their neighbors, one at a time like clip paths do
+        let mut union = RsvgPathBuilder::new ();
+
+        for i in 0..20 {
+            for j in 0..20 {
+                union = op (&union, &rect (i as f64, j as f64, 1.0, 1.0), BooleanOp::Union);
+            }
+        }
+
+        assert_area (&union, 400.0);
+    }
+
+    #[test]
+    fn curves_get_flattened () {
+        let mut circle = RsvgPathBuilder::new ();
+        circle.move_to (10.0, 0.0);
+        circle.arc (10.0, 0.0, 10.0, 10.0, 0.0, LargeArc (false), Sweep::Positive, -10.0, 0.0);
+        circle.arc (-10.0, 0.0, 10.0, 10.0, 0.0, LargeArc (false), Sweep::Positive, 10.0, 0.0);
+        circle.close_path ();
+
+        let half = rect (0.0, -20.0, 20.0, 40.0);
+        let result = op (&circle, &half, BooleanOp::Intersection);
+
+        assert! ((area (&result) - f64::consts::PI * 50.0).abs () < 1.0);
+        assert! (result.get_path_commands ().iter ().all (|c| match *c {
+            PathCommand::CurveTo (..) => false,
+            _ => true
+        }));
+    }
+
+    #[test]
+    fn transforms_paths () {
+        let m = cairo::Matrix::new (2.0, 0.0, 0.0, 3.0, 1.0, 1.0);
+        let transformed = transform_path (&rect (0.0, 0.0, 1.0, 1.0), &m);
+
+        assert_eq! (transformed.get_path_commands ()[2], PathCommand::LineTo (3.0, 4.0));
+    }
+}
@@ -1,8 +1,15 @@
+use ::cairo;
 use ::cairo_sys;
 
 use std::f64;
 use std::f64::consts::*;
 
+use path_boolean::{self, BooleanOp};
+use path_geometry::PathMeasure;
+use path_simplify;
+use stroke::{self, Dashes};
+use bbox::StrokeParams;
+
 #[derive(Debug, PartialEq)]
 pub struct LargeArc(pub bool);
 
@@ -12,7 +19,7 @@ pub enum Sweep {
     Positive
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum PathCommand {
     MoveTo (f64, f64),
     LineTo (f64, f64),
@@ -21,6 +28,7 @@ pub enum PathCommand {
 }
 
 #[repr(C)]
+#[derive(Clone)]
 pub struct RsvgPathBuilder {
     path_commands: Vec<PathCommand>,
 }
@@ -50,10 +58,74 @@ impl RsvgPathBuilder {
         self.path_commands.push (PathCommand::ClosePath);
     }
 
+    pub fn push_command (&mut self, command: PathCommand) {
+        self.path_commands.push (command);
+    }
+
     pub fn get_path_commands (&self) -> &[PathCommand] {
         &self.path_commands
     }
 
+    // Total length of the path; see path_geometry.rs for the details
+    pub fn length (&self) -> f64 {
+        PathMeasure::new (self).length ()
+    }
+
+    // Point at a distance along the path, or None if the path is empty
+    pub fn point_at_length (&self, distance: f64) -> Option<(f64, f64)> {
+        PathMeasure::new (self).point_at_length (distance)
+    }
+
+    // Unit tangent vector at a distance along the path, or None if there is no direction there
+    pub fn tangent_at_length (&self, distance: f64) -> Option<(f64, f64)> {
+        PathMeasure::new (self).tangent_at_length (distance)
+    }
+
+    // Splits the path in two at a distance along the path
+    pub fn split_at_length (&self, distance: f64) -> (RsvgPathBuilder, RsvgPathBuilder) {
+        PathMeasure::new (self).split_at_length (self, distance)
+    }
+
+    // Shortest "d" string for the path, with absolute commands; see path_simplify.rs
+    pub fn to_path_data (&self) -> String {
+        path_simplify::to_path_data (self)
+    }
+
+    // Copy of the path with the curves turned into lines, within a tolerance in user units
+    pub fn flatten (&self, tolerance: f64) -> RsvgPathBuilder {
+        path_simplify::flatten (self, tolerance)
+    }
+
+    // Flattened subpaths as lists of points
+    pub fn to_polylines (&self, tolerance: f64) -> Vec<Vec<(f64, f64)>> {
+        path_simplify::to_polylines (self, tolerance)
+    }
+
+    // Copy of the path without the segments that don't move the current point
+    pub fn remove_zero_length_segments (&self) -> RsvgPathBuilder {
+        path_simplify::remove_zero_length_segments (self)
+    }
+
+    // Copy of the path with runs of collinear lines merged into single lines
+    pub fn merge_collinear_lines (&self, tolerance: f64) -> RsvgPathBuilder {
+        path_simplify::merge_collinear_lines (self, tolerance)
+    }
+
+    // Outline of the stroke as a path to be filled with the nonzero rule; see stroke.rs
+    pub fn stroke_to_path (&self, params: &StrokeParams, dashes: Option<&Dashes>) -> RsvgPathBuilder {
+        stroke::path_builder_stroke_to_path (self, params, dashes)
+    }
+
+    // Flattened result of a boolean operation between the filled areas of two paths; see path_boolean.rs
+    pub fn boolean_op (&self,
+                       fill_rule: cairo::FillRule,
+                       other: &RsvgPathBuilder,
+                       other_fill_rule: cairo::FillRule,
+                       op: BooleanOp,
+                       tolerance: f64) -> RsvgPathBuilder {
+        path_boolean::path_builder_boolean_op (self, fill_rule, other, other_fill_rule, op, tolerance)
+    }
+
     /**
      * x1/y1: starting coordinates
      * rx/ry: radiuses before rotation
@@ -276,3 +348,71 @@ pub extern fn rsvg_path_builder_add_to_cairo_context (raw_builder: *mut RsvgPath
         }
     }
 }
+
+/* Used from C to build paths that don't come from SVG path data,
+ * like the outlines of glyphs.
+ */
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_new () -> *mut RsvgPathBuilder {
+    Box::into_raw (Box::new (RsvgPathBuilder::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_destroy (raw_builder: *mut RsvgPathBuilder) {
+    assert! (!raw_builder.is_null ());
+
+    let _ = unsafe { Box::from_raw (raw_builder) };
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_move_to (raw_builder: *mut RsvgPathBuilder, x: f64, y: f64) {
+    assert! (!raw_builder.is_null ());
+
+    let builder: &mut RsvgPathBuilder = unsafe { &mut (*raw_builder) };
+
+    builder.move_to (x, y);
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_line_to (raw_builder: *mut RsvgPathBuilder, x: f64, y: f64) {
+    assert! (!raw_builder.is_null ());
+
+    let builder: &mut RsvgPathBuilder = unsafe { &mut (*raw_builder) };
+
+    builder.line_to (x, y);
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_curve_to (raw_builder: *mut RsvgPathBuilder,
+                                          x2: f64, y2: f64,
+                                          x3: f64, y3: f64,
+                                          x4: f64, y4: f64) {
+    assert! (!raw_builder.is_null ());
+
+    let builder: &mut RsvgPathBuilder = unsafe { &mut (*raw_builder) };
+
+    builder.curve_to (x2, y2, x3, y3, x4, y4);
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_close_path (raw_builder: *mut RsvgPathBuilder) {
+    assert! (!raw_builder.is_null ());
+
+    let builder: &mut RsvgPathBuilder = unsafe { &mut (*raw_builder) };
+
+    builder.close_path ();
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_builder_append (raw_builder: *mut RsvgPathBuilder, raw_other: *const RsvgPathBuilder) {
+    assert! (!raw_builder.is_null ());
+    assert! (!raw_other.is_null ());
+
+    let builder: &mut RsvgPathBuilder = unsafe { &mut (*raw_builder) };
+    let other: &RsvgPathBuilder = unsafe { &*raw_other };
+
+    for command in other.get_path_commands () {
+        builder.push_command (*command);
+    }
+}
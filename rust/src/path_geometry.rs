@@ -0,0 +1,705 @@
+use std::f64;
+
+use path_builder::*;
+
+/* Arc-length measurement of the paths in an RsvgPathBuilder.
+ *
+ * A path is seen as a sequence of segments, each of which is either a
+ * straight line or a cubic Bézier curve.  Elliptical arcs don't need
+ * special treatment here, since RsvgPathBuilder::arc() already turns
+ * them into curves.  Movetos don't contribute to the length of a
+ * path, but closepaths do, as they are an implicit line back to the
+ * start of the subpath.
+ *
+ * This is the machinery needed for textPath, pathLength, and for
+ * computing dashes by hand.
+ */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathSegment {
+    Line ((f64, f64), (f64, f64)),                           // start, end
+    Curve ((f64, f64), (f64, f64), (f64, f64), (f64, f64))   // start, control 1, control 2, end
+}
+
+// Nodes and weights for 5-point Gauss-Legendre quadrature on [-1, 1]
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+    ( 0.0,                0.5688888888888889),
+    (-0.5384693101056831, 0.4786286704993665),
+    ( 0.5384693101056831, 0.4786286704993665),
+    (-0.906179845938664, 0.2369268850561891),
+    ( 0.906179845938664, 0.2369268850561891)
+];
+
+const LENGTH_TOLERANCE: f64 = 1e-9;
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+
+fn lerp (a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t,
+     a.1 + (b.1 - a.1) * t)
+}
+
+fn distance (a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot (b.1 - a.1)
+}
+
+fn normalize (v: (f64, f64)) -> Option<(f64, f64)> {
+    let len = v.0.hypot (v.1);
+
+    if len > f64::EPSILON {
+        Some ((v.0 / len, v.1 / len))
+    } else {
+        None
+    }
+}
+
+impl PathSegment {
+    pub fn start (&self) -> (f64, f64) {
+        match *self {
+            PathSegment::Line (p1, _)        => p1,
+            PathSegment::Curve (p1, _, _, _) => p1
+        }
+    }
+
+    pub fn end (&self) -> (f64, f64) {
+        match *self {
+            PathSegment::Line (_, p2)        => p2,
+            PathSegment::Curve (_, _, _, p4) => p4
+        }
+    }
+
+    // Point on the segment at parameter t in [0, 1]
+    pub fn point_at (&self, t: f64) -> (f64, f64) {
+        match *self {
+            PathSegment::Line (p1, p2) => lerp (p1, p2, t),
+
+            PathSegment::Curve (p1, p2, p3, p4) => {
+                let mt = 1.0 - t;
+
+                let a = mt * mt * mt;
+                let b = 3.0 * mt * mt * t;
+                let c = 3.0 * mt * t * t;
+                let d = t * t * t;
+
+                (a * p1.0 + b * p2.0 + c * p3.0 + d * p4.0,
+                 a * p1.1 + b * p2.1 + c * p3.1 + d * p4.1)
+            }
+        }
+    }
+
+    // Derivative of the segment with respect to its parameter t
+    pub fn derivative_at (&self, t: f64) -> (f64, f64) {
+        match *self {
+            PathSegment::Line (p1, p2) => (p2.0 - p1.0, p2.1 - p1.1),
+
+            PathSegment::Curve (p1, p2, p3, p4) => {
+                let mt = 1.0 - t;
+
+                let a = 3.0 * mt * mt;
+                let b = 6.0 * mt * t;
+                let c = 3.0 * t * t;
+
+                (a * (p2.0 - p1.0) + b * (p3.0 - p2.0) + c * (p4.0 - p3.0),
+                 a * (p2.1 - p1.1) + b * (p3.1 - p2.1) + c * (p4.1 - p3.1))
+            }
+        }
+    }
+
+    /* Unit tangent vector at parameter t, or None if the segment has
+     * no directionality at all (i.e. all its points coincide).
+     *
+     * Curves whose control points coincide with an endpoint have a
+     * zero derivative there; in that case we look at where the curve is
+     * heading just next to that point.
+     */
+    pub fn tangent_at (&self, t: f64) -> Option<(f64, f64)> {
+        if let Some (v) = normalize (self.derivative_at (t)) {
+            return Some (v);
+        }
+
+        match *self {
+            PathSegment::Line (..) => None,
+
+            PathSegment::Curve (p1, p2, p3, p4) => {
+                if t < 0.5 {
+                    normalize ((p2.0 - p1.0, p2.1 - p1.1))
+                        .or_else (|| normalize ((p3.0 - p1.0, p3.1 - p1.1)))
+                        .or_else (|| normalize ((p4.0 - p1.0, p4.1 - p1.1)))
+                } else {
+                    normalize ((p4.0 - p3.0, p4.1 - p3.1))
+                        .or_else (|| normalize ((p4.0 - p2.0, p4.1 - p2.1)))
+                        .or_else (|| normalize ((p4.0 - p1.0, p4.1 - p1.1)))
+                }
+            }
+        }
+    }
+
+    // Splits the segment at parameter t, using de Casteljau's algorithm for curves
+    pub fn split (&self, t: f64) -> (PathSegment, PathSegment) {
+        match *self {
+            PathSegment::Line (p1, p2) => {
+                let m = lerp (p1, p2, t);
+
+                (PathSegment::Line (p1, m), PathSegment::Line (m, p2))
+            },
+
+            PathSegment::Curve (p1, p2, p3, p4) => {
+                let p12 = lerp (p1, p2, t);
+                let p23 = lerp (p2, p3, t);
+                let p34 = lerp (p3, p4, t);
+
+                let p123 = lerp (p12, p23, t);
+                let p234 = lerp (p23, p34, t);
+
+                let m = lerp (p123, p234, t);
+
+                (PathSegment::Curve (p1, p12, p123, m), PathSegment::Curve (m, p234, p34, p4))
+            }
+        }
+    }
+
+    pub fn length (&self) -> f64 {
+        self.length_between (0.0, 1.0)
+    }
+
+    // Arc length of the piece of the segment between parameters t0 and t1
+    pub fn length_between (&self, t0: f64, t1: f64) -> f64 {
+        match *self {
+            PathSegment::Line (p1, p2) => distance (p1, p2) * (t1 - t0).abs (),
+
+            PathSegment::Curve (..) => {
+                let whole = self.gauss_legendre_length (t0, t1);
+                self.adaptive_length (t0, t1, whole, 0)
+            }
+        }
+    }
+
+    fn speed_at (&self, t: f64) -> f64 {
+        let (dx, dy) = self.derivative_at (t);
+        dx.hypot (dy)
+    }
+
+    fn gauss_legendre_length (&self, t0: f64, t1: f64) -> f64 {
+        let half = (t1 - t0) / 2.0;
+        let mid = (t1 + t0) / 2.0;
+
+        GAUSS_LEGENDRE.iter ()
+            .map (|&(x, w)| w * self.speed_at (mid + half * x))
+            .sum::<f64> () * half
+    }
+
+    /* Gauss-Legendre quadrature is very precise for smooth curves, but
+     * not so much around cusps and sharp bends.  So, we subdivide until
+     * the result for both halves of an interval agrees with the result
+     * for the whole interval.
+     */
+    fn adaptive_length (&self, t0: f64, t1: f64, whole: f64, depth: u32) -> f64 {
+        let mid = (t0 + t1) / 2.0;
+
+        let left = self.gauss_legendre_length (t0, mid);
+        let right = self.gauss_legendre_length (mid, t1);
+
+        if depth >= MAX_SUBDIVISION_DEPTH || (left + right - whole).abs () <= LENGTH_TOLERANCE {
+            left + right
+        } else {
+            self.adaptive_length (t0, mid, left, depth + 1) + self.adaptive_length (mid, t1, right, depth + 1)
+        }
+    }
+
+    /* Returns the parameter t at which the arc length from the start of
+     * the segment equals the specified distance.  The distance is
+     * clamped to the segment's length.
+     */
+    pub fn t_at_length (&self, distance: f64) -> f64 {
+        let length = self.length ();
+
+        if distance <= 0.0 || length <= 0.0 {
+            return 0.0;
+        }
+
+        if distance >= length {
+            return 1.0;
+        }
+
+        if let PathSegment::Line (..) = *self {
+            return distance / length;
+        }
+
+        // Newton's method, falling back to bisection when it steps out of the bracket
+
+        let mut lo = 0.0;
+        let mut hi = 1.0;
+        let mut t = distance / length;
+
+        for _ in 0 .. 64 {
+            let diff = self.length_between (0.0, t) - distance;
+
+            if diff.abs () <= LENGTH_TOLERANCE {
+                break;
+            }
+
+            if diff > 0.0 {
+                hi = t;
+            } else {
+                lo = t;
+            }
+
+            let speed = self.speed_at (t);
+            let newton = if speed > f64::EPSILON { t - diff / speed } else { -1.0 };
+
+            t = if newton > lo && newton < hi {
+                newton
+            } else {
+                (lo + hi) / 2.0
+            };
+
+            if hi - lo <= f64::EPSILON {
+                break;
+            }
+        }
+
+        t
+    }
+}
+
+// A segment together with its position along the whole path
+#[derive(Debug)]
+struct MeasuredSegment {
+    segment:       PathSegment,
+    offset:        f64,             // distance from the start of the path to the start of the segment
+    length:        f64,
+    command_index: usize,           // index of the PathCommand that generated this segment
+    subpath_start: (f64, f64)
+}
+
+/* Caches the segments of a path and their lengths, so that several
+ * queries can be made about the same path without recomputing
+ * everything; for example, when placing glyphs along a path.
+ */
+#[derive(Debug)]
+pub struct PathMeasure {
+    segments: Vec<MeasuredSegment>,
+    length:   f64
+}
+
+impl PathMeasure {
+    pub fn new (builder: &RsvgPathBuilder) -> PathMeasure {
+        let mut segments = Vec::new ();
+        let mut length = 0.0;
+
+        let mut cur = (0.0, 0.0);
+        let mut subpath_start = (0.0, 0.0);
+
+        for (i, command) in builder.get_path_commands ().iter ().enumerate () {
+            let segment = match *command {
+                PathCommand::MoveTo (x, y) => {
+                    cur = (x, y);
+                    subpath_start = cur;
+                    continue;
+                },
+
+                PathCommand::LineTo (x, y) => PathSegment::Line (cur, (x, y)),
+
+                PathCommand::CurveTo (p2, p3, p4) => PathSegment::Curve (cur, p2, p3, p4),
+
+                PathCommand::ClosePath => PathSegment::Line (cur, subpath_start)
+            };
+
+            let segment_length = segment.length ();
+
+            segments.push (MeasuredSegment {
+                segment:       segment,
+                offset:        length,
+                length:        segment_length,
+                command_index: i,
+                subpath_start: subpath_start
+            });
+
+            length += segment_length;
+            cur = segment.end ();
+        }
+
+        PathMeasure {
+            segments: segments,
+            length:   length
+        }
+    }
+
+    pub fn length (&self) -> f64 {
+        self.length
+    }
+
+    /* Finds the segment where the specified distance falls, and the
+     * parameter t within that segment.  Zero-length segments are
+     * skipped, since they have no directionality.
+     */
+    fn find (&self, distance: f64) -> Option<(&MeasuredSegment, f64)> {
+        if self.segments.is_empty () {
+            return None;
+        }
+
+        let distance = distance.max (0.0).min (self.length);
+
+        let measured = self.segments.iter ()
+            .find (|s| s.length > 0.0 && distance <= s.offset + s.length)
+            .unwrap_or (&self.segments[0]);
+
+        Some ((measured, measured.segment.t_at_length (distance - measured.offset)))
+    }
+
+    // Point at the specified distance from the start of the path
+    pub fn point_at_length (&self, distance: f64) -> Option<(f64, f64)> {
+        self.find (distance)
+            .map (|(measured, t)| measured.segment.point_at (t))
+    }
+
+    // Unit tangent vector at the specified distance from the start of the path
+    pub fn tangent_at_length (&self, distance: f64) -> Option<(f64, f64)> {
+        self.find (distance)
+            .and_then (|(measured, t)| measured.segment.tangent_at (t))
+    }
+
+    /* Splits the path in two at the specified distance from its start.
+     *
+     * The second path begins with a moveto to the split point.  If the
+     * split point is inside a closed subpath, the closepath in the second
+     * path gets turned into a lineto to the start of the original subpath,
+     * since the second path's subpath now begins at the split point.
+     */
+    pub fn split_at_length (&self, builder: &RsvgPathBuilder, distance: f64) -> (RsvgPathBuilder, RsvgPathBuilder) {
+        let commands = builder.get_path_commands ();
+
+        if distance <= 0.0 || self.segments.is_empty () {
+            return (RsvgPathBuilder::new (), builder.clone ());
+        }
+
+        if distance >= self.length {
+            return (builder.clone (), RsvgPathBuilder::new ());
+        }
+
+        let (measured, t) = self.find (distance).unwrap ();
+        let (first_half, second_half) = measured.segment.split (t);
+        let split_point = first_half.end ();
+
+        let mut first = RsvgPathBuilder::new ();
+        let mut second = RsvgPathBuilder::new ();
+
+        for command in &commands[.. measured.command_index] {
+            first.push_command (*command);
+        }
+
+        second.move_to (split_point.0, split_point.1);
+
+        match (first_half, second_half) {
+            (PathSegment::Curve (_, a2, a3, a4), PathSegment::Curve (_, b2, b3, b4)) => {
+                first.curve_to (a2.0, a2.1, a3.0, a3.1, a4.0, a4.1);
+                second.curve_to (b2.0, b2.1, b3.0, b3.1, b4.0, b4.1);
+            },
+
+            (_, second_half) => {
+                let end = second_half.end ();
+
+                first.line_to (split_point.0, split_point.1);
+                second.line_to (end.0, end.1);
+            }
+        }
+
+        let mut in_split_subpath = true;
+
+        for command in &commands[measured.command_index + 1 ..] {
+            match *command {
+                PathCommand::MoveTo (..) => {
+                    in_split_subpath = false;
+                    second.push_command (*command);
+                },
+
+                PathCommand::ClosePath if in_split_subpath => {
+                    let (x, y) = measured.subpath_start;
+                    second.line_to (x, y);
+                },
+
+                _ => second.push_command (*command)
+            }
+        }
+
+        (first, second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::*;
+
+    fn assert_approx_eq (a: f64, b: f64, tolerance: f64) {
+        assert! ((a - b).abs () <= tolerance, "{} != {} (tolerance {})", a, b, tolerance);
+    }
+
+    fn assert_point_approx_eq (a: (f64, f64), b: (f64, f64)) {
+        assert_approx_eq (a.0, b.0, 1e-6);
+        assert_approx_eq (a.1, b.1, 1e-6);
+    }
+
+    fn setup_open_polyline () -> RsvgPathBuilder {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (10.0, 10.0);
+        builder.line_to (40.0, 10.0);
+        builder.line_to (40.0, 50.0);
+
+        builder
+    }
+
+    fn setup_closed_square () -> RsvgPathBuilder {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 0.0);
+        builder.line_to (10.0, 10.0);
+        builder.line_to (0.0, 10.0);
+        builder.close_path ();
+
+        builder
+    }
+
+    #[test]
+    fn empty_path_has_no_length () {
+        let builder = RsvgPathBuilder::new ();
+
+        assert_eq! (builder.length (), 0.0);
+        assert_eq! (builder.point_at_length (0.0), None);
+        assert_eq! (builder.tangent_at_length (0.0), None);
+    }
+
+    #[test]
+    fn measures_lines () {
+        assert_approx_eq (setup_open_polyline ().length (), 70.0, 1e-9);
+    }
+
+    #[test]
+    fn closepath_contributes_to_length () {
+        assert_approx_eq (setup_closed_square ().length (), 40.0, 1e-9);
+    }
+
+    #[test]
+    fn moveto_does_not_contribute_to_length () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.line_to (10.0, 0.0);
+        builder.move_to (100.0, 100.0);
+        builder.line_to (100.0, 110.0);
+
+        assert_approx_eq (builder.length (), 20.0, 1e-9);
+    }
+
+    #[test]
+    fn measures_straight_curve () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (10.0, 0.0, 20.0, 0.0, 30.0, 0.0);
+
+        assert_approx_eq (builder.length (), 30.0, 1e-9);
+    }
+
+    #[test]
+    fn measures_curve_with_cusp () {
+        // goes from (0, 0) to (10, 0) and doubles back to (0, 0)
+        let segment = PathSegment::Curve ((0.0, 0.0), (10.0, 0.0), (10.0, 0.0), (0.0, 0.0));
+
+        // maximum x is at t = 0.5, with x = 7.5
+        assert_approx_eq (segment.length (), 15.0, 1e-6);
+    }
+
+    #[test]
+    fn measures_arcs () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.arc (0.0, 0.0, 50.0, 50.0, 0.0, LargeArc (false), Sweep::Positive, 100.0, 0.0);
+
+        // The arc is approximated with Bézier curves, so allow for some error
+        assert_approx_eq (builder.length (), PI * 50.0, 0.1);
+    }
+
+    #[test]
+    fn finds_point_at_length_on_lines () {
+        let builder = setup_open_polyline ();
+
+        assert_point_approx_eq (builder.point_at_length (0.0).unwrap (), (10.0, 10.0));
+        assert_point_approx_eq (builder.point_at_length (15.0).unwrap (), (25.0, 10.0));
+        assert_point_approx_eq (builder.point_at_length (30.0).unwrap (), (40.0, 10.0));
+        assert_point_approx_eq (builder.point_at_length (50.0).unwrap (), (40.0, 30.0));
+        assert_point_approx_eq (builder.point_at_length (70.0).unwrap (), (40.0, 50.0));
+    }
+
+    #[test]
+    fn clamps_distances_outside_the_path () {
+        let builder = setup_open_polyline ();
+
+        assert_point_approx_eq (builder.point_at_length (-5.0).unwrap (), (10.0, 10.0));
+        assert_point_approx_eq (builder.point_at_length (1000.0).unwrap (), (40.0, 50.0));
+    }
+
+    #[test]
+    fn finds_point_at_length_on_closepath () {
+        let builder = setup_closed_square ();
+
+        assert_point_approx_eq (builder.point_at_length (35.0).unwrap (), (0.0, 5.0));
+        assert_point_approx_eq (builder.tangent_at_length (35.0).unwrap (), (0.0, -1.0));
+    }
+
+    #[test]
+    fn finds_point_at_length_on_curves () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        // a symmetric curve; the middle of its length is its midpoint
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (0.0, 20.0, 40.0, 20.0, 40.0, 0.0);
+
+        let half = builder.length () / 2.0;
+
+        assert_point_approx_eq (builder.point_at_length (half).unwrap (), (20.0, 15.0));
+        assert_point_approx_eq (builder.tangent_at_length (half).unwrap (), (1.0, 0.0));
+    }
+
+    #[test]
+    fn finds_point_at_length_on_arcs () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.arc (0.0, 0.0, 50.0, 50.0, 0.0, LargeArc (false), Sweep::Positive, 100.0, 0.0);
+
+        let quarter = builder.length () / 2.0;
+        let p = builder.point_at_length (quarter).unwrap ();
+
+        assert_approx_eq (p.0, 50.0, 1e-6);
+        assert_approx_eq (p.1, -50.0, 1e-2);
+    }
+
+    #[test]
+    fn finds_tangent_at_length () {
+        let builder = setup_open_polyline ();
+
+        assert_point_approx_eq (builder.tangent_at_length (0.0).unwrap (), (1.0, 0.0));
+        assert_point_approx_eq (builder.tangent_at_length (29.0).unwrap (), (1.0, 0.0));
+        assert_point_approx_eq (builder.tangent_at_length (31.0).unwrap (), (0.0, 1.0));
+        assert_point_approx_eq (builder.tangent_at_length (70.0).unwrap (), (0.0, 1.0));
+    }
+
+    #[test]
+    fn finds_tangent_of_curve_with_coincident_control_points () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (0.0, 0.0, 10.0, 10.0, 10.0, 10.0);
+
+        let diagonal = FRAC_1_SQRT_2;
+
+        assert_point_approx_eq (builder.tangent_at_length (0.0).unwrap (), (diagonal, diagonal));
+        assert_point_approx_eq (builder.tangent_at_length (builder.length ()).unwrap (), (diagonal, diagonal));
+    }
+
+    #[test]
+    fn zero_length_path_has_no_tangent () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (10.0, 10.0);
+        builder.line_to (10.0, 10.0);
+
+        assert_point_approx_eq (builder.point_at_length (0.0).unwrap (), (10.0, 10.0));
+        assert_eq! (builder.tangent_at_length (0.0), None);
+    }
+
+    #[test]
+    fn splits_lines () {
+        let (first, second) = setup_open_polyline ().split_at_length (40.0);
+
+        assert_eq! (first.get_path_commands (),
+                    &[PathCommand::MoveTo (10.0, 10.0),
+                      PathCommand::LineTo (40.0, 10.0),
+                      PathCommand::LineTo (40.0, 20.0)]);
+
+        assert_eq! (second.get_path_commands (),
+                    &[PathCommand::MoveTo (40.0, 20.0),
+                      PathCommand::LineTo (40.0, 50.0)]);
+    }
+
+    #[test]
+    fn splits_curves () {
+        let mut builder = RsvgPathBuilder::new ();
+
+        builder.move_to (0.0, 0.0);
+        builder.curve_to (0.0, 20.0, 40.0, 20.0, 40.0, 0.0);
+        builder.line_to (40.0, 40.0);
+
+        let length = builder.length ();
+        let (first, second) = builder.split_at_length (10.0);
+
+        assert_approx_eq (first.length (), 10.0, 1e-6);
+        assert_approx_eq (second.length (), length - 10.0, 1e-6);
+
+        assert_point_approx_eq (first.point_at_length (10.0).unwrap (),
+                                builder.point_at_length (10.0).unwrap ());
+
+        assert_eq! (second.get_path_commands ().last (), Some (&PathCommand::LineTo (40.0, 40.0)));
+    }
+
+    #[test]
+    fn splitting_closed_subpath_turns_closepath_into_lineto () {
+        let (first, second) = setup_closed_square ().split_at_length (15.0);
+
+        assert_eq! (first.get_path_commands (),
+                    &[PathCommand::MoveTo (0.0, 0.0),
+                      PathCommand::LineTo (10.0, 0.0),
+                      PathCommand::LineTo (10.0, 5.0)]);
+
+        assert_eq! (second.get_path_commands (),
+                    &[PathCommand::MoveTo (10.0, 5.0),
+                      PathCommand::LineTo (10.0, 10.0),
+                      PathCommand::LineTo (0.0, 10.0),
+                      PathCommand::LineTo (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn splitting_on_closepath_segment_yields_open_subpaths () {
+        let (first, second) = setup_closed_square ().split_at_length (35.0);
+
+        assert_eq! (first.get_path_commands ().last (), Some (&PathCommand::LineTo (0.0, 5.0)));
+
+        assert_eq! (second.get_path_commands (),
+                    &[PathCommand::MoveTo (0.0, 5.0),
+                      PathCommand::LineTo (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn splitting_keeps_following_subpaths () {
+        let mut builder = setup_closed_square ();
+
+        builder.move_to (20.0, 20.0);
+        builder.line_to (30.0, 20.0);
+        builder.close_path ();
+
+        let (_, second) = builder.split_at_length (35.0);
+
+        assert_eq! (second.get_path_commands (),
+                    &[PathCommand::MoveTo (0.0, 5.0),
+                      PathCommand::LineTo (0.0, 0.0),
+                      PathCommand::MoveTo (20.0, 20.0),
+                      PathCommand::LineTo (30.0, 20.0),
+                      PathCommand::ClosePath]);
+    }
+
+    #[test]
+    fn splitting_outside_the_path_yields_empty_half () {
+        let builder = setup_open_polyline ();
+
+        let (first, second) = builder.split_at_length (0.0);
+        assert! (first.get_path_commands ().is_empty ());
+        assert_eq! (second.get_path_commands (), builder.get_path_commands ());
+
+        let (first, second) = builder.split_at_length (100.0);
+        assert_eq! (first.get_path_commands (), builder.get_path_commands ());
+        assert! (second.get_path_commands ().is_empty ());
+    }
+}
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str;
 use std::str::Chars;
 use std::iter::Enumerate;
@@ -90,9 +91,19 @@ impl<'b> PathParser<'b> {
     fn parse (&mut self) -> bool {
         self.getchar ();
 
-        return self.optional_whitespace () &&
+        let result = self.optional_whitespace () &&
             self.moveto_drawto_command_groups () &&
             self.optional_whitespace ();
+
+        // A malformed command ends the command groups early, and everything
+        // up to it is still a valid path; report the error anyway.
+        if self.has_error {
+            false
+        } else if result && self.lookahead.is_some () {
+            self.error ("Expected command")
+        } else {
+            result
+        }
     }
 
     fn getchar (&mut self) {
@@ -983,11 +994,18 @@ fn char_to_digit (c: char) -> i32 {
     c as i32 - '0' as i32
 }
 
+#[derive(Debug)]
 pub struct ParseError {
     position: usize,
     message: &'static str
 }
 
+impl fmt::Display for ParseError {
+    fn fmt (&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write! (f, "{} at position {}", self.message, self.position)
+    }
+}
+
 pub fn parse_path_into_builder (path_str: &str, builder: &mut RsvgPathBuilder) -> Result <(), ParseError> {
     let mut parser = PathParser::new (builder, path_str);
 
@@ -1486,6 +1504,27 @@ mod tests {
                      ]);
     }
 
+    #[test]
+    fn describes_errors () {
+        let mut builder = RsvgPathBuilder::new ();
+        let error = parse_path_into_builder ("M10 20 L 30", &mut builder).err ().unwrap ();
+
+        assert_eq! (error.to_string (), "Expected coordinate pair after lineto at position 11");
+    }
+
+    #[test]
+    fn keeps_commands_before_trailing_garbage () {
+        let mut builder = RsvgPathBuilder::new ();
+        let error = parse_path_into_builder ("M10 20 L 30 40 # L 50 60", &mut builder).err ().unwrap ();
+
+        assert_eq! (error.to_string (), "Expected command at position 15");
+        assert_eq! (builder.get_path_commands (),
+                    &vec! [
+                        moveto (10.0, 20.0),
+                        lineto (30.0, 40.0)
+                    ]);
+    }
+
     /* FIXME: we don't have a handles_arc() because
      * we don't know what segments will be computed by PathBuilder::arc().
      * Maybe we need to represent arcs as native path builder segments,
@@ -0,0 +1,456 @@
+use std::f64;
+
+use path_builder::*;
+use path_geometry::PathSegment;
+
+/* Simplification and normalization of the paths in an RsvgPathBuilder.
+ *
+ * These work on the absolute moveto/lineto/curveto/closepath commands
+ * that the path parser produces, and don't need a cairo context, so
+ * they can be used to prepare geometry for things other than rendering,
+ * like plotters and cutters that consume polylines.
+ */
+
+// Limits the subdivision of very tight curves, or of curves flattened
+// with a tolerance of zero; 2^16 lines per curve is plenty.
+const MAX_FLATTEN_DEPTH: u32 = 16;
+
+fn distance (a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot (b.1 - a.1)
+}
+
+// Distance from p to the line through a and b, or to a if a and b coincide
+fn distance_to_line (p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let len = distance (a, b);
+
+    if len > f64::EPSILON {
+        ((b.0 - a.0) * (a.1 - p.1) - (a.0 - p.0) * (b.1 - a.1)).abs () / len
+    } else {
+        distance (p, a)
+    }
+}
+
+// Distance from p to the segment between a and b
+fn distance_to_segment (p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_squared = dx * dx + dy * dy;
+
+    if len_squared <= f64::EPSILON {
+        return distance (p, a);
+    }
+
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_squared).max (0.0).min (1.0);
+
+    distance (p, (a.0 + t * dx, a.1 + t * dy))
+}
+
+/***** Path data *****/
+
+fn format_number (n: f64) -> String {
+    // avoid writing "-0"
+    let s = if n == 0.0 { "0".to_string () } else { format! ("{}", n) };
+
+    // "0.5" -> ".5" and "-0.5" -> "-.5"
+    if s.starts_with ("0.") {
+        s[1..].to_string ()
+    } else if s.starts_with ("-0.") {
+        format! ("-{}", &s[2..])
+    } else {
+        s
+    }
+}
+
+// Appends a number, with a separator only where the parser needs one
+fn push_number (out: &mut String, n: f64) {
+    let s = format_number (n);
+
+    let needs_separator = match out.chars ().last () {
+        None                   => false,
+        Some (c) if c.is_ascii_alphabetic () => false,
+        Some (_)               => !s.starts_with ('-')
+    };
+
+    if needs_separator {
+        out.push (' ');
+    }
+
+    out.push_str (&s);
+}
+
+/// Returns the shortest `d` string with absolute commands for the path.
+///
+/// Command letters are omitted when they repeat, and linetos right
+/// after a moveto are written as implicit ones.
+pub fn to_path_data (builder: &RsvgPathBuilder) -> String {
+    let mut out = String::new ();
+
+    // Command that a bare coordinate list would get parsed as
+    let mut implied = None;
+
+    for command in builder.get_path_commands () {
+        let (letter, numbers) = match *command {
+            PathCommand::MoveTo (x, y) => ('M', vec! [x, y]),
+            PathCommand::LineTo (x, y) => ('L', vec! [x, y]),
+            PathCommand::CurveTo ((x2, y2), (x3, y3), (x4, y4)) => ('C', vec! [x2, y2, x3, y3, x4, y4]),
+            PathCommand::ClosePath => ('Z', vec! [])
+        };
+
+        if implied != Some (letter) {
+            out.push (letter);
+        }
+
+        for n in numbers {
+            push_number (&mut out, n);
+        }
+
+        // The coordinate pairs after a moveto's first one are linetos
+        implied = match letter {
+            'M' | 'L' => Some ('L'),
+            'C'       => Some ('C'),
+            _         => None
+        };
+    }
+
+    out
+}
+
+/***** Flattening *****/
+
+// Whether a curve is close enough to the line between its endpoints.
+// The curve lies within the hull of its control points, so it is if
+// they are.  This measures to the segment and not to the infinite line,
+// since control points past the endpoints make the curve overshoot.
+fn is_flat (segment: &PathSegment, tolerance: f64) -> bool {
+    match *segment {
+        PathSegment::Line (..) => true,
+
+        PathSegment::Curve (p1, p2, p3, p4) =>
+            distance_to_segment (p2, p1, p4) <= tolerance && distance_to_segment (p3, p1, p4) <= tolerance
+    }
+}
+
+fn flatten_curve (segment: PathSegment, tolerance: f64, depth: u32, points: &mut Vec<(f64, f64)>) {
+    if depth >= MAX_FLATTEN_DEPTH || is_flat (&segment, tolerance) {
+        points.push (segment.end ());
+    } else {
+        let (first, second) = segment.split (0.5);
+
+        flatten_curve (first, tolerance, depth + 1, points);
+        flatten_curve (second, tolerance, depth + 1, points);
+    }
+}
+
+/// Returns a path where the curves are replaced by linetos, such that
+/// no point of the original curves is farther than `tolerance` from the
+/// lines.
+pub fn flatten (builder: &RsvgPathBuilder, tolerance: f64) -> RsvgPathBuilder {
+    let mut flattened = RsvgPathBuilder::new ();
+    let mut cur = (0.0, 0.0);
+    let mut subpath_start = (0.0, 0.0);
+
+    for command in builder.get_path_commands () {
+        match *command {
+            PathCommand::CurveTo (p2, p3, p4) => {
+                let mut points = Vec::new ();
+                flatten_curve (PathSegment::Curve (cur, p2, p3, p4), tolerance, 0, &mut points);
+
+                for (x, y) in points {
+                    flattened.line_to (x, y);
+                }
+
+                cur = p4;
+            },
+
+            PathCommand::MoveTo (x, y) => {
+                flattened.push_command (*command);
+                cur = (x, y);
+                subpath_start = cur;
+            },
+
+            PathCommand::LineTo (x, y) => {
+                flattened.push_command (*command);
+                cur = (x, y);
+            },
+
+            PathCommand::ClosePath => {
+                flattened.push_command (*command);
+                cur = subpath_start;
+            }
+        }
+    }
+
+    flattened
+}
+
+/// Flattens the path and returns its subpaths as lists of points.
+/// Closed subpaths end with their first point, and subpaths with a
+/// single point are left out.
+pub fn to_polylines (builder: &RsvgPathBuilder, tolerance: f64) -> Vec<Vec<(f64, f64)>> {
+    let mut polylines: Vec<Vec<(f64, f64)>> = Vec::new ();
+
+    for command in flatten (builder, tolerance).get_path_commands () {
+        match *command {
+            PathCommand::MoveTo (x, y) => polylines.push (vec! [(x, y)]),
+
+            PathCommand::LineTo (x, y) => {
+                if let Some (polyline) = polylines.last_mut () {
+                    polyline.push ((x, y));
+                }
+            },
+
+            // Drawing after a closepath starts a new subpath at the same point
+            PathCommand::ClosePath => {
+                if let Some (start) = polylines.last ().map (|polyline| polyline[0]) {
+                    polylines.last_mut ().unwrap ().push (start);
+                    polylines.push (vec! [start]);
+                }
+            },
+
+            PathCommand::CurveTo (..) => unreachable! ()
+        }
+    }
+
+    polylines.retain (|polyline| polyline.len () > 1);
+    polylines
+}
+
+/***** Simplification *****/
+
+/// Returns a path without the linetos and curvetos that stay at the
+/// current point.  Movetos and closepaths are kept, so this doesn't
+/// change how subpaths are joined or closed; however, note that a
+/// zero-length subpath that gets painted as a dot because of its line
+/// caps won't be painted any more.
+pub fn remove_zero_length_segments (builder: &RsvgPathBuilder) -> RsvgPathBuilder {
+    let mut simplified = RsvgPathBuilder::new ();
+    let mut cur = (0.0, 0.0);
+    let mut subpath_start = (0.0, 0.0);
+
+    for command in builder.get_path_commands () {
+        match *command {
+            PathCommand::MoveTo (x, y) => {
+                cur = (x, y);
+                subpath_start = cur;
+            },
+
+            PathCommand::LineTo (x, y) => {
+                if (x, y) == cur {
+                    continue;
+                }
+
+                cur = (x, y);
+            },
+
+            PathCommand::CurveTo (p2, p3, p4) => {
+                if p2 == cur && p3 == cur && p4 == cur {
+                    continue;
+                }
+
+                cur = p4;
+            },
+
+            PathCommand::ClosePath => {
+                cur = subpath_start;
+            }
+        }
+
+        simplified.push_command (*command);
+    }
+
+    simplified
+}
+
+/// Returns a path where runs of linetos that go in the same direction
+/// are merged into a single lineto.  A point gets removed if it, and
+/// all the points removed before it in the same run, are within
+/// `tolerance` of the merged line.
+pub fn merge_collinear_lines (builder: &RsvgPathBuilder, tolerance: f64) -> RsvgPathBuilder {
+    let mut merged = RsvgPathBuilder::new ();
+
+    // Start of the current run, and its points which have not been written yet
+    let mut anchor = (0.0, 0.0);
+    let mut pending: Vec<(f64, f64)> = Vec::new ();
+
+    fn flush (merged: &mut RsvgPathBuilder, pending: &mut Vec<(f64, f64)>) {
+        if let Some (&(x, y)) = pending.last () {
+            merged.line_to (x, y);
+        }
+
+        pending.clear ();
+    }
+
+    for command in builder.get_path_commands () {
+        match *command {
+            PathCommand::LineTo (x, y) => {
+                let p = (x, y);
+
+                // The last pending point can be dropped if the line from the
+                // anchor to p goes through all the pending points, in order
+                let can_merge = pending.last ().map_or (false, |&last| {
+                    let forward = (last.0 - anchor.0) * (p.0 - last.0) + (last.1 - anchor.1) * (p.1 - last.1) > 0.0;
+
+                    forward && pending.iter ().all (|&q| distance_to_line (q, anchor, p) <= tolerance)
+                });
+
+                if !can_merge {
+                    if let Some (&last) = pending.last () {
+                        flush (&mut merged, &mut pending);
+                        anchor = last;
+                    }
+                }
+
+                pending.push (p);
+            },
+
+            PathCommand::MoveTo (x, y) => {
+                flush (&mut merged, &mut pending);
+                merged.push_command (*command);
+                anchor = (x, y);
+            },
+
+            PathCommand::CurveTo (_, _, p4) => {
+                flush (&mut merged, &mut pending);
+                merged.push_command (*command);
+                anchor = p4;
+            },
+
+            PathCommand::ClosePath => {
+                flush (&mut merged, &mut pending);
+                merged.push_command (*command);
+            }
+        }
+    }
+
+    flush (&mut merged, &mut pending);
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use path_parser::parse_path_into_builder;
+
+    fn parse (s: &str) -> RsvgPathBuilder {
+        let mut builder = RsvgPathBuilder::new ();
+        parse_path_into_builder (s, &mut builder).unwrap ();
+        builder
+    }
+
+    fn assert_same_path (a: &RsvgPathBuilder, b: &RsvgPathBuilder) {
+        assert_eq! (a.get_path_commands (), b.get_path_commands ());
+    }
+
+    // The path parser accumulates a little floating-point error in the numbers
+    fn assert_approx_same_path (a: &RsvgPathBuilder, b: &RsvgPathBuilder) {
+        let points = |builder: &RsvgPathBuilder| -> Vec<f64> {
+            builder.get_path_commands ().iter ()
+                .flat_map (|c| match *c {
+                    PathCommand::MoveTo (x, y) | PathCommand::LineTo (x, y) => vec! [x, y],
+                    PathCommand::CurveTo ((x2, y2), (x3, y3), (x4, y4)) => vec! [x2, y2, x3, y3, x4, y4],
+                    PathCommand::ClosePath => vec! []
+                })
+                .collect ()
+        };
+
+        let same_commands = a.get_path_commands ().iter ().zip (b.get_path_commands ())
+            .all (|(ca, cb)| ::std::mem::discriminant (ca) == ::std::mem::discriminant (cb));
+
+        assert! (same_commands && a.get_path_commands ().len () == b.get_path_commands ().len ());
+        assert! (points (a).iter ().zip (points (b).iter ()).all (|(pa, pb)| (pa - pb).abs () < 1e-9));
+    }
+
+    #[test]
+    fn writes_minimal_path_data () {
+        assert_eq! (to_path_data (&parse ("m 10 20 l 30 0 l 0 -0.5 Z")),
+                    "M10 20 40 20 40 19.5Z");
+
+        assert_eq! (to_path_data (&parse ("M 0 0 C 0.5 0.5 1 1 -2 -2 C 1 2 3 4 5 6 M 1 1 L 2 2")),
+                    "M0 0C.5 .5 1 1-2-2 1 2 3 4 5 6M1 1 2 2");
+    }
+
+    #[test]
+    fn path_data_roundtrips () {
+        for s in &["M 10 20 L 30 40 L 50 60 Z M 1 1 L 2 2",
+                   "m -0.25 -0.75 c 1 2 3 4 5 6 s 1 1 2 2 h 10 v -10 z",
+                   "M 0 0 A 10 20 30 1 0 40 50 L 0.125 -0.125"] {
+            let builder = parse (s);
+            assert_approx_same_path (&parse (&to_path_data (&builder)), &builder);
+        }
+    }
+
+    #[test]
+    fn flattens_curves_within_tolerance () {
+        let builder = parse ("M 0 0 C 0 100 100 100 100 0");
+
+        for &tolerance in &[1.0, 0.1, 0.01] {
+            let flattened = flatten (&builder, tolerance);
+            let commands = flattened.get_path_commands ();
+
+            assert! (commands.iter ().skip (1).all (|c| match *c { PathCommand::LineTo (..) => true, _ => false }));
+            assert_eq! (commands.last (), Some (&PathCommand::LineTo (100.0, 0.0)));
+
+            // the top of the curve is at (50, 75)
+            let polyline = &to_polylines (&builder, tolerance)[0];
+            let closest = polyline.windows (2)
+                .map (|w| distance_to_line ((50.0, 75.0), w[0], w[1]))
+                .fold (f64::INFINITY, f64::min);
+
+            assert! (closest <= tolerance);
+        }
+
+        assert! (flatten (&builder, 0.01).get_path_commands ().len () > flatten (&builder, 1.0).get_path_commands ().len ());
+    }
+
+    #[test]
+    fn flattens_curves_that_overshoot_their_endpoints () {
+        // all the control points are on a line, but the curve goes past both ends
+        let curve = PathSegment::Curve ((0.0, 0.0), (100.0, 0.0), (-50.0, 0.0), (10.0, 0.0));
+        let builder = parse ("M0 0 C100 0 -50 0 10 0");
+        let tolerance = 0.1;
+
+        let polyline = &to_polylines (&builder, tolerance)[0];
+
+        for i in 0..101 {
+            let p = curve.point_at (i as f64 / 100.0);
+            let closest = polyline.windows (2)
+                .map (|w| distance_to_segment (p, w[0], w[1]))
+                .fold (f64::INFINITY, f64::min);
+
+            assert! (closest <= tolerance);
+        }
+    }
+
+    #[test]
+    fn makes_polylines_from_subpaths () {
+        assert_eq! (to_polylines (&parse ("M 0 0 L 10 0 L 10 10 Z M 20 20 L 30 30 M 40 40"), 1.0),
+                    vec! [vec! [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 0.0)],
+                          vec! [(20.0, 20.0), (30.0, 30.0)]]);
+
+        assert_eq! (to_polylines (&parse ("M 0 0 L 10 0 Z L 0 10"), 1.0),
+                    vec! [vec! [(0.0, 0.0), (10.0, 0.0), (0.0, 0.0)],
+                          vec! [(0.0, 0.0), (0.0, 10.0)]]);
+    }
+
+    #[test]
+    fn removes_zero_length_segments () {
+        assert_same_path (&remove_zero_length_segments (&parse ("M 0 0 L 0 0 L 10 0 C 10 0 10 0 10 0 L 10 10 Z L 0 0")),
+                          &parse ("M 0 0 L 10 0 L 10 10 Z"));
+    }
+
+    #[test]
+    fn merges_collinear_lines () {
+        assert_same_path (&merge_collinear_lines (&parse ("M 0 0 L 10 0 L 20 0 L 30 0 L 30 10 L 30 20 Z"), 0.0),
+                          &parse ("M 0 0 L 30 0 L 30 20 Z"));
+
+        // doubling back is not a straight run
+        assert_same_path (&merge_collinear_lines (&parse ("M 0 0 L 10 0 L 5 0"), 0.0),
+                          &parse ("M 0 0 L 10 0 L 5 0"));
+
+        // small deviations get merged within the tolerance, but they don't accumulate
+        assert_same_path (&merge_collinear_lines (&parse ("M 0 0 L 10 0.1 L 20 0 L 30 0.1"), 0.2),
+                          &parse ("M 0 0 L 30 0.1"));
+        assert_same_path (&merge_collinear_lines (&parse ("M 0 0 L 10 0.1 L 20 0.2 L 30 0.3 L 40 0"), 0.2),
+                          &parse ("M 0 0 L 30 0.3 L 40 0"));
+    }
+}
@@ -0,0 +1,1356 @@
+use ::cssparser::{self, Parser, ParserInput};
+
+use std::str::FromStr;
+
+use blend_mode::MixBlendMode;
+use color::Color;
+use error::*;
+use filters::{FilterFunction, parse_filter_functions};
+use length::*;
+use opacity::Opacity;
+use parsers;
+use parsers::Parse;
+use parsers::ParseError;
+
+// Typed values for the SVG style properties.
+//
+// Each node keeps a SpecifiedValues with what the cascade in css.rs
+// found for it: presentation attributes, style sheets, and the style
+// attribute.  Its ComputedValues come from resolving those against
+// the parent's computed values:
+//
+//   * unspecified properties are inherited from the parent if they
+//     are inherited properties, or take their initial value otherwise
+//   * "inherit" takes the parent's computed value
+//   * "initial" takes the property's initial value
+//   * specified values are computed relative to the parent if needed,
+//     like font-size: 2em
+//
+// https://www.w3.org/TR/CSS2/cascade.html#value-stages
+//
+// The initial value of each property is the Default of its type.
+
+pub trait Property: Clone + Default + Parse<Data = (), Err = AttributeError> {
+    fn inherits_automatically () -> bool;
+
+    fn compute (&self, _parent: &ComputedValues) -> Self {
+        self.clone ()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpecifiedValue<T: Property> {
+    Unspecified,
+    Inherit,
+    Initial,
+    Specified (T)
+}
+
+impl<T: Property> Default for SpecifiedValue<T> {
+    fn default () -> SpecifiedValue<T> {
+        SpecifiedValue::Unspecified
+    }
+}
+
+impl<T: Property> SpecifiedValue<T> {
+    fn compute (&self, parent_value: &T, parent: &ComputedValues) -> T {
+        match *self {
+            SpecifiedValue::Unspecified => {
+                if T::inherits_automatically () {
+                    parent_value.clone ()
+                } else {
+                    T::default ()
+                }
+            },
+
+            SpecifiedValue::Inherit => parent_value.clone (),
+
+            SpecifiedValue::Initial => T::default (),
+
+            SpecifiedValue::Specified (ref v) => v.compute (parent)
+        }
+    }
+}
+
+fn parse_specified_value<T: Property> (value: &str) -> Result<SpecifiedValue<T>, AttributeError> {
+    match value.trim () {
+        "inherit" => Ok (SpecifiedValue::Inherit),
+        "initial" => Ok (SpecifiedValue::Initial),
+        v         => Ok (SpecifiedValue::Specified (T::parse (v, ())?))
+    }
+}
+
+fn make_err (msg: &str) -> AttributeError {
+    AttributeError::Parse (ParseError::new (msg))
+}
+
+// Properties whose values are a fixed set of keywords
+macro_rules! make_keyword_property {
+    ($name: ident,
+     default: $default: ident,
+     inherits_automatically: $inherits: expr,
+     $($($keyword: tt)|+ => $variant: ident,)+) => {
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl Default for $name {
+            fn default () -> $name {
+                $name::$default
+            }
+        }
+
+        impl Property for $name {
+            fn inherits_automatically () -> bool {
+                $inherits
+            }
+        }
+
+        impl Parse for $name {
+            type Data = ();
+            type Err = AttributeError;
+
+            fn parse (s: &str, _: ()) -> Result<$name, AttributeError> {
+                match s.trim () {
+                    $($($keyword)|+ => Ok ($name::$variant),)+
+                    _ => Err (make_err (concat! ("invalid keyword for ", stringify! ($name))))
+                }
+            }
+        }
+    };
+}
+
+// Properties whose values are a number clamped to [0.0, 1.0]
+macro_rules! make_opacity_property {
+    ($name: ident, inherits_automatically: $inherits: expr) => {
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct $name (pub f64);
+
+        impl Default for $name {
+            fn default () -> $name {
+                $name (1.0)
+            }
+        }
+
+        impl Property for $name {
+            fn inherits_automatically () -> bool {
+                $inherits
+            }
+        }
+
+        impl Parse for $name {
+            type Data = ();
+            type Err = AttributeError;
+
+            fn parse (s: &str, _: ()) -> Result<$name, AttributeError> {
+                match Opacity::from_str (s)? {
+                    Opacity::Specified (v) => Ok ($name (v)),
+                    Opacity::Inherit => Err (make_err ("expected number"))
+                }
+            }
+        }
+    };
+}
+
+// Properties whose values are a color or currentColor
+macro_rules! make_color_property {
+    ($name: ident, inherits_automatically: $inherits: expr) => {
+        make_color_property! ($name,
+                              default: cssparser::RGBA::new (0, 0, 0, 0xff),
+                              inherits_automatically: $inherits);
+    };
+
+    ($name: ident, default: $default: expr, inherits_automatically: $inherits: expr) => {
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct $name (pub Color);
+
+        impl Default for $name {
+            fn default () -> $name {
+                $name (Color::RGBA ($default))
+            }
+        }
+
+        impl Property for $name {
+            fn inherits_automatically () -> bool {
+                $inherits
+            }
+        }
+
+        impl Parse for $name {
+            type Data = ();
+            type Err = AttributeError;
+
+            fn parse (s: &str, _: ()) -> Result<$name, AttributeError> {
+                Ok ($name (parse_color (s)?))
+            }
+        }
+    };
+}
+
+// Properties whose values are a length
+macro_rules! make_length_property {
+    ($name: ident, default: $default: expr, dir: $dir: expr, inherits_automatically: $inherits: expr) => {
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct $name (pub RsvgLength);
+
+        impl Default for $name {
+            fn default () -> $name {
+                $name (RsvgLength::new ($default, LengthUnit::Default, $dir))
+            }
+        }
+
+        impl Parse for $name {
+            type Data = ();
+            type Err = AttributeError;
+
+            fn parse (s: &str, _: ()) -> Result<$name, AttributeError> {
+                Ok ($name (RsvgLength::parse (s, $dir)?))
+            }
+        }
+
+        impl Property for $name {
+            fn inherits_automatically () -> bool {
+                $inherits
+            }
+        }
+    };
+}
+
+// Properties whose values are a reference to an element, like clip-path="url(#foo)"
+macro_rules! make_iri_property {
+    ($name: ident, inherits_automatically: $inherits: expr) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name (pub IRI);
+
+        impl Default for $name {
+            fn default () -> $name {
+                $name (IRI::None)
+            }
+        }
+
+        impl Property for $name {
+            fn inherits_automatically () -> bool {
+                $inherits
+            }
+        }
+
+        impl Parse for $name {
+            type Data = ();
+            type Err = AttributeError;
+
+            fn parse (s: &str, _: ()) -> Result<$name, AttributeError> {
+                Ok ($name (IRI::parse (s, ())?))
+            }
+        }
+    };
+}
+
+fn parse_color (s: &str) -> Result<Color, AttributeError> {
+    let mut input = ParserInput::new (s);
+    let mut parser = Parser::new (&mut input);
+
+    let color = cssparser::Color::parse (&mut parser)
+        .map_err (|_| make_err ("invalid syntax for color"))?;
+
+    parser.expect_exhausted ().map_err (|_| make_err ("invalid syntax for color"))?;
+
+    Ok (Color::from (color))
+}
+
+/***** IRI *****/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IRI {
+    None,
+    Resource (String)
+}
+
+impl Parse for IRI {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<IRI, AttributeError> {
+        let mut input = ParserInput::new (s);
+        let mut parser = Parser::new (&mut input);
+
+        let iri = if parser.try (|p| p.expect_ident_matching ("none")).is_ok () {
+            IRI::None
+        } else {
+            let url = parser.expect_url ().map_err (|_| make_err ("expected 'none' or url()"))?;
+            IRI::Resource (url.to_string ())
+        };
+
+        parser.expect_exhausted ().map_err (|_| make_err ("expected 'none' or url()"))?;
+
+        Ok (iri)
+    }
+}
+
+/***** Paint *****/
+
+// fill and stroke
+//
+// https://www.w3.org/TR/SVG/painting.html#SpecifyingPaint
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    None,
+    Color (Color),
+
+    // A paint server, with an optional fallback for when it can't be used
+    Server (String, Option<Color>)
+}
+
+impl Parse for Paint {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<Paint, AttributeError> {
+        let mut input = ParserInput::new (s);
+        let mut parser = Parser::new (&mut input);
+
+        let paint = if parser.try (|p| p.expect_ident_matching ("none")).is_ok () {
+            Paint::None
+        } else if let Ok (url) = parser.try (|p| p.expect_url ().map (|u| u.to_string ())) {
+            let fallback = if parser.is_exhausted () ||
+                parser.try (|p| p.expect_ident_matching ("none")).is_ok () {
+                None
+            } else {
+                let color = cssparser::Color::parse (&mut parser)
+                    .map_err (|_| make_err ("invalid fallback color"))?;
+
+                Some (Color::from (color))
+            };
+
+            Paint::Server (url, fallback)
+        } else {
+            let color = cssparser::Color::parse (&mut parser)
+                .map_err (|_| make_err ("expected 'none', color, or url()"))?;
+
+            Paint::Color (Color::from (color))
+        };
+
+        parser.expect_exhausted ().map_err (|_| make_err ("invalid syntax for paint"))?;
+
+        Ok (paint)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill (pub Paint);
+
+impl Default for Fill {
+    fn default () -> Fill {
+        Fill (Paint::Color (Color::RGBA (cssparser::RGBA::new (0, 0, 0, 0xff))))
+    }
+}
+
+impl Parse for Fill {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<Fill, AttributeError> {
+        Ok (Fill (Paint::parse (s, ())?))
+    }
+}
+
+impl Property for Fill {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke (pub Paint);
+
+impl Default for Stroke {
+    fn default () -> Stroke {
+        Stroke (Paint::None)
+    }
+}
+
+impl Parse for Stroke {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<Stroke, AttributeError> {
+        Ok (Stroke (Paint::parse (s, ())?))
+    }
+}
+
+impl Property for Stroke {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+/***** color *****/
+
+// The color property can't be currentColor itself
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CurrentColor (pub cssparser::RGBA);
+
+impl Default for CurrentColor {
+    fn default () -> CurrentColor {
+        CurrentColor (cssparser::RGBA::new (0, 0, 0, 0xff))
+    }
+}
+
+impl Parse for CurrentColor {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<CurrentColor, AttributeError> {
+        match parse_color (s)? {
+            Color::RGBA (rgba) => Ok (CurrentColor (rgba)),
+            _ => Err (AttributeError::Value ("currentColor is not allowed here".to_string ()))
+        }
+    }
+}
+
+impl Property for CurrentColor {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+make_color_property! (StopColor, inherits_automatically: false);
+make_color_property! (FloodColor, inherits_automatically: false);
+make_color_property! (LightingColor,
+                      default: cssparser::RGBA::new (0xff, 0xff, 0xff, 0xff),
+                      inherits_automatically: false);
+
+make_opacity_property! (GroupOpacity, inherits_automatically: false);
+make_opacity_property! (FillOpacity, inherits_automatically: true);
+make_opacity_property! (StrokeOpacity, inherits_automatically: true);
+make_opacity_property! (StopOpacity, inherits_automatically: false);
+make_opacity_property! (FloodOpacity, inherits_automatically: false);
+
+make_iri_property! (ClipPath, inherits_automatically: false);
+make_iri_property! (Mask, inherits_automatically: false);
+make_iri_property! (MarkerStart, inherits_automatically: true);
+make_iri_property! (MarkerMid, inherits_automatically: true);
+make_iri_property! (MarkerEnd, inherits_automatically: true);
+
+make_length_property! (StrokeWidth, default: 1.0, dir: LengthDir::Both, inherits_automatically: true);
+make_length_property! (StrokeDashoffset, default: 0.0, dir: LengthDir::Both, inherits_automatically: true);
+make_length_property! (LetterSpacing, default: 0.0, dir: LengthDir::Horizontal, inherits_automatically: true);
+
+make_keyword_property! (FillRule,
+                        default: NonZero,
+                        inherits_automatically: true,
+                        "nonzero" => NonZero,
+                        "evenodd" => EvenOdd,
+);
+
+make_keyword_property! (ClipRule,
+                        default: NonZero,
+                        inherits_automatically: true,
+                        "nonzero" => NonZero,
+                        "evenodd" => EvenOdd,
+);
+
+make_keyword_property! (StrokeLinecap,
+                        default: Butt,
+                        inherits_automatically: true,
+                        "butt"   => Butt,
+                        "round"  => Round,
+                        "square" => Square,
+);
+
+make_keyword_property! (StrokeLinejoin,
+                        default: Miter,
+                        inherits_automatically: true,
+                        "miter" => Miter,
+                        "round" => Round,
+                        "bevel" => Bevel,
+);
+
+make_keyword_property! (Display,
+                        default: Inline,
+                        inherits_automatically: false,
+                        "inline"             => Inline,
+                        "block"              => Block,
+                        "list-item"          => ListItem,
+                        "run-in"             => RunIn,
+                        "compact"            => Compact,
+                        "marker"             => Marker,
+                        "table"              => Table,
+                        "inline-table"       => InlineTable,
+                        "table-row-group"    => TableRowGroup,
+                        "table-header-group" => TableHeaderGroup,
+                        "table-footer-group" => TableFooterGroup,
+                        "table-row"          => TableRow,
+                        "table-column-group" => TableColumnGroup,
+                        "table-column"       => TableColumn,
+                        "table-cell"         => TableCell,
+                        "table-caption"      => TableCaption,
+                        "none"               => None,
+);
+
+make_keyword_property! (Visibility,
+                        default: Visible,
+                        inherits_automatically: true,
+                        "visible"  => Visible,
+                        "hidden"   => Hidden,
+                        "collapse" => Collapse,
+);
+
+make_keyword_property! (Overflow,
+                        default: Visible,
+                        inherits_automatically: false,
+                        "visible" => Visible,
+                        "hidden"  => Hidden,
+                        "scroll"  => Scroll,
+                        "auto"    => Auto,
+);
+
+make_keyword_property! (TextAnchor,
+                        default: Start,
+                        inherits_automatically: true,
+                        "start"  => Start,
+                        "middle" => Middle,
+                        "end"    => End,
+);
+
+make_keyword_property! (Direction,
+                        default: Ltr,
+                        inherits_automatically: true,
+                        "ltr" => Ltr,
+                        "rtl" => Rtl,
+);
+
+make_keyword_property! (UnicodeBidi,
+                        default: Normal,
+                        inherits_automatically: false,
+                        "normal"        => Normal,
+                        "embed"         => Embed,
+                        "bidi-override" => BidiOverride,
+);
+
+make_keyword_property! (WritingMode,
+                        default: LrTb,
+                        inherits_automatically: true,
+                        "lr-tb" => LrTb,
+                        "rl-tb" => RlTb,
+                        "tb-rl" => TbRl,
+                        "lr"    => Lr,
+                        "rl"    => Rl,
+                        "tb"    => Tb,
+                        "horizontal-tb" => HorizontalTb,
+                        "vertical-rl"   => VerticalRl,
+                        "vertical-lr"   => VerticalLr,
+);
+
+// "default" is not in the spec, but librsvg has always accepted it
+make_keyword_property! (ShapeRendering,
+                        default: Auto,
+                        inherits_automatically: true,
+                        "auto" | "default"   => Auto,
+                        "optimizeSpeed"      => OptimizeSpeed,
+                        "crispEdges"         => CrispEdges,
+                        "geometricPrecision" => GeometricPrecision,
+);
+
+make_keyword_property! (TextRendering,
+                        default: Auto,
+                        inherits_automatically: true,
+                        "auto" | "default"   => Auto,
+                        "optimizeSpeed"      => OptimizeSpeed,
+                        "optimizeLegibility" => OptimizeLegibility,
+                        "geometricPrecision" => GeometricPrecision,
+);
+
+make_keyword_property! (FontStyle,
+                        default: Normal,
+                        inherits_automatically: true,
+                        "normal"  => Normal,
+                        "italic"  => Italic,
+                        "oblique" => Oblique,
+);
+
+make_keyword_property! (FontVariant,
+                        default: Normal,
+                        inherits_automatically: true,
+                        "normal"     => Normal,
+                        "small-caps" => SmallCaps,
+);
+
+make_keyword_property! (FontStretch,
+                        default: Normal,
+                        inherits_automatically: true,
+                        "normal"          => Normal,
+                        "wider"           => Wider,
+                        "narrower"        => Narrower,
+                        "ultra-condensed" => UltraCondensed,
+                        "extra-condensed" => ExtraCondensed,
+                        "condensed"       => Condensed,
+                        "semi-condensed"  => SemiCondensed,
+                        "semi-expanded"   => SemiExpanded,
+                        "expanded"        => Expanded,
+                        "extra-expanded"  => ExtraExpanded,
+                        "ultra-expanded"  => UltraExpanded,
+);
+
+make_keyword_property! (XmlSpace,
+                        default: Default,
+                        inherits_automatically: true,
+                        "default"  => Default,
+                        "preserve" => Preserve,
+);
+
+make_keyword_property! (EnableBackground,
+                        default: Accumulate,
+                        inherits_automatically: false,
+                        "accumulate" => Accumulate,
+                        "new"        => New,
+);
+
+make_keyword_property! (Isolation,
+                        default: Auto,
+                        inherits_automatically: false,
+                        "auto"    => Auto,
+                        "isolate" => Isolate,
+);
+
+make_keyword_property! (MaskType,
+                        default: Luminance,
+                        inherits_automatically: false,
+                        "luminance" => Luminance,
+                        "alpha"     => Alpha,
+);
+
+make_keyword_property! (CompOp,
+                        default: SrcOver,
+                        inherits_automatically: false,
+                        "clear"       => Clear,
+                        "src"         => Src,
+                        "dst"         => Dst,
+                        "src-over"    => SrcOver,
+                        "dst-over"    => DstOver,
+                        "src-in"      => SrcIn,
+                        "dst-in"      => DstIn,
+                        "src-out"     => SrcOut,
+                        "dst-out"     => DstOut,
+                        "src-atop"    => SrcAtop,
+                        "dst-atop"    => DstAtop,
+                        "xor"         => Xor,
+                        "plus"        => Plus,
+                        "multiply"    => Multiply,
+                        "screen"      => Screen,
+                        "overlay"     => Overlay,
+                        "darken"      => Darken,
+                        "lighten"     => Lighten,
+                        "color-dodge" => ColorDodge,
+                        "color-burn"  => ColorBurn,
+                        "hard-light"  => HardLight,
+                        "soft-light"  => SoftLight,
+                        "difference"  => Difference,
+                        "exclusion"   => Exclusion,
+);
+
+/***** font-size *****/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FontSize (pub RsvgLength);
+
+impl Default for FontSize {
+    fn default () -> FontSize {
+        FontSize (RsvgLength::new (12.0, LengthUnit::Default, LengthDir::Both))
+    }
+}
+
+impl Parse for FontSize {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<FontSize, AttributeError> {
+        Ok (FontSize (RsvgLength::parse (s, LengthDir::Both)?.check_nonnegative ()?))
+    }
+}
+
+impl Property for FontSize {
+    fn inherits_automatically () -> bool {
+        true
+    }
+
+    // Relative font sizes are computed with respect to the parent's font size
+    fn compute (&self, parent: &ComputedValues) -> FontSize {
+        let parent_size = parent.font_size.0;
+
+        let scale = match self.0.unit {
+            LengthUnit::FontEm |
+            LengthUnit::Percent         => self.0.length,
+            LengthUnit::FontEx          => self.0.length / 2.0,
+            LengthUnit::RelativeLarger  => 1.2,
+            LengthUnit::RelativeSmaller => 1.0 / 1.2,
+            _                           => return *self
+        };
+
+        FontSize (RsvgLength::new (parent_size.length * scale, parent_size.unit, LengthDir::Both))
+    }
+}
+
+/***** font-weight *****/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FontWeight {
+    Normal,
+    Bold,
+    Bolder,
+    Lighter,
+    W100,
+    W200,
+    W300,
+    W400,
+    W500,
+    W600,
+    W700,
+    W800,
+    W900
+}
+
+impl Default for FontWeight {
+    fn default () -> FontWeight {
+        FontWeight::Normal
+    }
+}
+
+impl Parse for FontWeight {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<FontWeight, AttributeError> {
+        match s.trim () {
+            "normal"  => Ok (FontWeight::Normal),
+            "bold"    => Ok (FontWeight::Bold),
+            "bolder"  => Ok (FontWeight::Bolder),
+            "lighter" => Ok (FontWeight::Lighter),
+            "100"     => Ok (FontWeight::W100),
+            "200"     => Ok (FontWeight::W200),
+            "300"     => Ok (FontWeight::W300),
+            "400"     => Ok (FontWeight::W400),
+            "500"     => Ok (FontWeight::W500),
+            "600"     => Ok (FontWeight::W600),
+            "700"     => Ok (FontWeight::W700),
+            "800"     => Ok (FontWeight::W800),
+            "900"     => Ok (FontWeight::W900),
+            _         => Err (make_err ("invalid keyword for FontWeight"))
+        }
+    }
+}
+
+impl FontWeight {
+    // The numeric weight; bolder and lighter only have one once computed
+    fn weight (&self) -> u16 {
+        match *self {
+            FontWeight::W100 => 100,
+            FontWeight::W200 => 200,
+            FontWeight::W300 => 300,
+            FontWeight::W500 => 500,
+            FontWeight::W600 => 600,
+            FontWeight::W700 |
+            FontWeight::Bold => 700,
+            FontWeight::W800 => 800,
+            FontWeight::W900 => 900,
+            _                => 400
+        }
+    }
+}
+
+impl Property for FontWeight {
+    fn inherits_automatically () -> bool {
+        true
+    }
+
+    // Bolder and lighter are relative to the parent's weight
+    //
+    // https://www.w3.org/TR/css-fonts-3/#relative-weights
+    fn compute (&self, parent: &ComputedValues) -> FontWeight {
+        let parent_weight = parent.font_weight.weight ();
+
+        match *self {
+            FontWeight::Bolder => {
+                if parent_weight < 400 {
+                    FontWeight::W400
+                } else if parent_weight < 600 {
+                    FontWeight::W700
+                } else {
+                    FontWeight::W900
+                }
+            },
+
+            FontWeight::Lighter => {
+                if parent_weight < 600 {
+                    FontWeight::W100
+                } else if parent_weight < 800 {
+                    FontWeight::W400
+                } else {
+                    FontWeight::W700
+                }
+            },
+
+            _ => *self
+        }
+    }
+}
+
+/***** font-family, xml:lang *****/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFamily (pub String);
+
+impl Default for FontFamily {
+    fn default () -> FontFamily {
+        FontFamily ("Times New Roman".to_string ())
+    }
+}
+
+impl Parse for FontFamily {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<FontFamily, AttributeError> {
+        let family: String = s.chars ().filter (|&c| c != '\'' && c != '"').collect ();
+        let family = family.trim ();
+
+        if family.is_empty () {
+            Err (make_err ("expected font family"))
+        } else {
+            Ok (FontFamily (family.to_string ()))
+        }
+    }
+}
+
+impl Property for FontFamily {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct XmlLang (pub String);
+
+impl Parse for XmlLang {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<XmlLang, AttributeError> {
+        Ok (XmlLang (s.trim ().to_string ()))
+    }
+}
+
+impl Property for XmlLang {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+/***** baseline-shift *****/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BaselineShift {
+    Baseline,
+    Sub,
+    Super,
+    Length (RsvgLength)
+}
+
+impl Default for BaselineShift {
+    fn default () -> BaselineShift {
+        BaselineShift::Baseline
+    }
+}
+
+impl Parse for BaselineShift {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<BaselineShift, AttributeError> {
+        match s.trim () {
+            "baseline" => Ok (BaselineShift::Baseline),
+            "sub"      => Ok (BaselineShift::Sub),
+            "super"    => Ok (BaselineShift::Super),
+            v          => Ok (BaselineShift::Length (RsvgLength::parse (v, LengthDir::Vertical)?))
+        }
+    }
+}
+
+impl Property for BaselineShift {
+    fn inherits_automatically () -> bool {
+        false
+    }
+}
+
+/***** glyph-orientation-vertical *****/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GlyphOrientationVertical {
+    Auto,
+    Degrees (i32)
+}
+
+impl Default for GlyphOrientationVertical {
+    fn default () -> GlyphOrientationVertical {
+        GlyphOrientationVertical::Auto
+    }
+}
+
+impl Parse for GlyphOrientationVertical {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<GlyphOrientationVertical, AttributeError> {
+        if s.trim () == "auto" {
+            return Ok (GlyphOrientationVertical::Auto);
+        }
+
+        let angle = parsers::angle_degrees (s)?;
+
+        if angle % 90.0 != 0.0 {
+            return Err (AttributeError::Value ("glyph-orientation-vertical must be a multiple of 90 degrees".to_string ()));
+        }
+
+        Ok (GlyphOrientationVertical::Degrees (((angle as i32) % 360 + 360) % 360))
+    }
+}
+
+impl Property for GlyphOrientationVertical {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+/***** text-decoration *****/
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TextDecoration {
+    pub underline:    bool,
+    pub overline:     bool,
+    pub line_through: bool
+}
+
+impl Parse for TextDecoration {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<TextDecoration, AttributeError> {
+        let mut decoration = TextDecoration::default ();
+
+        if s.trim () == "none" {
+            return Ok (decoration);
+        }
+
+        for keyword in s.split_whitespace () {
+            match keyword {
+                "underline"    => decoration.underline = true,
+                "overline"     => decoration.overline = true,
+                "line-through" => decoration.line_through = true,
+                "blink"        => (),
+                _              => return Err (make_err ("invalid keyword for text-decoration"))
+            }
+        }
+
+        Ok (decoration)
+    }
+}
+
+impl Property for TextDecoration {
+    fn inherits_automatically () -> bool {
+        false
+    }
+}
+
+/***** stroke-miterlimit *****/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeMiterlimit (pub f64);
+
+impl Default for StrokeMiterlimit {
+    fn default () -> StrokeMiterlimit {
+        StrokeMiterlimit (4.0)
+    }
+}
+
+impl Parse for StrokeMiterlimit {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<StrokeMiterlimit, AttributeError> {
+        let v = parsers::number (s)?;
+
+        if v < 1.0 {
+            Err (AttributeError::Value ("stroke-miterlimit must be greater than or equal to 1".to_string ()))
+        } else {
+            Ok (StrokeMiterlimit (v))
+        }
+    }
+}
+
+impl Property for StrokeMiterlimit {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+/***** stroke-dasharray *****/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrokeDasharray {
+    None,
+    Dashes (Vec<RsvgLength>)
+}
+
+impl Default for StrokeDasharray {
+    fn default () -> StrokeDasharray {
+        StrokeDasharray::None
+    }
+}
+
+impl Parse for StrokeDasharray {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<StrokeDasharray, AttributeError> {
+        if s.trim () == "none" {
+            return Ok (StrokeDasharray::None);
+        }
+
+        let mut dashes = Vec::new ();
+
+        for dash in s.split (|c: char| c == ',' || c.is_whitespace ()).filter (|d| !d.is_empty ()) {
+            dashes.push (RsvgLength::parse (dash, LengthDir::Both)?.check_nonnegative ()?);
+        }
+
+        if dashes.is_empty () {
+            return Err (make_err ("expected 'none' or list of lengths"));
+        }
+
+        // A dash array with all zeros is the same as no dashes
+        if dashes.iter ().all (|d| d.length == 0.0) {
+            return Ok (StrokeDasharray::None);
+        }
+
+        // An odd number of values gets repeated to yield an even number
+        if dashes.len () % 2 == 1 {
+            let copy = dashes.clone ();
+            dashes.extend (copy);
+        }
+
+        Ok (StrokeDasharray::Dashes (dashes))
+    }
+}
+
+impl Property for StrokeDasharray {
+    fn inherits_automatically () -> bool {
+        true
+    }
+}
+
+/***** filter *****/
+
+// A list of references to filter elements and filter functions; empty for "none"
+//
+// https://www.w3.org/TR/filter-effects-1/#FilterProperty
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filter (pub Vec<FilterFunction>);
+
+impl Parse for Filter {
+    type Data = ();
+    type Err = AttributeError;
+
+    fn parse (s: &str, _: ()) -> Result<Filter, AttributeError> {
+        Ok (Filter (parse_filter_functions (s)?))
+    }
+}
+
+impl Property for Filter {
+    fn inherits_automatically () -> bool {
+        false
+    }
+}
+
+/***** All the properties *****/
+
+macro_rules! make_properties {
+    ($($prop_name: expr => $field: ident: $type: ty,)+) => {
+        #[derive(Debug, Clone, Default)]
+        pub struct SpecifiedValues {
+            $(pub $field: SpecifiedValue<$type>,)+
+        }
+
+        #[derive(Debug, Clone, Default)]
+        pub struct ComputedValues {
+            $(pub $field: $type,)+
+        }
+
+        impl SpecifiedValues {
+            fn set_longhand (&mut self, name: &str, value: &str) -> Result<(), AttributeError> {
+                match name {
+                    $($prop_name => self.$field = parse_specified_value (value)?,)+
+
+                    _ => return Err (AttributeError::Value (format! ("unknown property \"{}\"", name)))
+                }
+
+                Ok (())
+            }
+
+            pub fn to_computed_values (&self, parent: &ComputedValues) -> ComputedValues {
+                ComputedValues {
+                    $($field: self.$field.compute (&parent.$field, parent),)+
+                }
+            }
+        }
+
+        // Presentation attributes have the same names as the properties
+        pub fn is_presentation_attribute (name: &str) -> bool {
+            match name {
+                $($prop_name)|+ => true,
+                _ => false
+            }
+        }
+    };
+}
+
+make_properties! {
+    "baseline-shift"    => baseline_shift:    BaselineShift,
+    "clip-path"         => clip_path:         ClipPath,
+    "clip-rule"         => clip_rule:         ClipRule,
+    "color"             => color:             CurrentColor,
+    "comp-op"           => comp_op:           CompOp,
+    "direction"         => direction:         Direction,
+    "display"           => display:           Display,
+    "enable-background" => enable_background: EnableBackground,
+    "fill"              => fill:              Fill,
+    "fill-opacity"      => fill_opacity:      FillOpacity,
+    "fill-rule"         => fill_rule:         FillRule,
+    "filter"            => filter:            Filter,
+    "flood-color"       => flood_color:       FloodColor,
+    "flood-opacity"     => flood_opacity:     FloodOpacity,
+    "font-family"       => font_family:       FontFamily,
+    "font-size"         => font_size:         FontSize,
+    "font-stretch"      => font_stretch:      FontStretch,
+    "font-style"        => font_style:        FontStyle,
+    "font-variant"      => font_variant:      FontVariant,
+    "font-weight"       => font_weight:       FontWeight,
+    "glyph-orientation-vertical" => glyph_orientation_vertical: GlyphOrientationVertical,
+    "isolation"         => isolation:         Isolation,
+    "letter-spacing"    => letter_spacing:    LetterSpacing,
+    "lighting-color"    => lighting_color:    LightingColor,
+    "marker-end"        => marker_end:        MarkerEnd,
+    "marker-mid"        => marker_mid:        MarkerMid,
+    "marker-start"      => marker_start:      MarkerStart,
+    "mask"              => mask:              Mask,
+    "mask-type"         => mask_type:         MaskType,
+    "mix-blend-mode"    => mix_blend_mode:    MixBlendMode,
+    "opacity"           => opacity:           GroupOpacity,
+    "overflow"          => overflow:          Overflow,
+    "shape-rendering"   => shape_rendering:   ShapeRendering,
+    "stop-color"        => stop_color:        StopColor,
+    "stop-opacity"      => stop_opacity:      StopOpacity,
+    "stroke"            => stroke:            Stroke,
+    "stroke-dasharray"  => stroke_dasharray:  StrokeDasharray,
+    "stroke-dashoffset" => stroke_dashoffset: StrokeDashoffset,
+    "stroke-linecap"    => stroke_linecap:    StrokeLinecap,
+    "stroke-linejoin"   => stroke_linejoin:   StrokeLinejoin,
+    "stroke-miterlimit" => stroke_miterlimit: StrokeMiterlimit,
+    "stroke-opacity"    => stroke_opacity:    StrokeOpacity,
+    "stroke-width"      => stroke_width:      StrokeWidth,
+    "text-anchor"       => text_anchor:       TextAnchor,
+    "text-decoration"   => text_decoration:   TextDecoration,
+    "text-rendering"    => text_rendering:    TextRendering,
+    "unicode-bidi"      => unicode_bidi:      UnicodeBidi,
+    "visibility"        => visibility:        Visibility,
+    "writing-mode"      => writing_mode:      WritingMode,
+    "xml:lang"          => xml_lang:          XmlLang,
+    "xml:space"         => xml_space:         XmlSpace,
+}
+
+impl SpecifiedValues {
+    // Sets a property from a declaration or a presentation attribute.
+    // If the value is invalid, the property is left unchanged, as
+    // per the CSS error handling rules.
+    pub fn set_property (&mut self, name: &str, value: &str) -> Result<(), AttributeError> {
+        match name {
+            // The marker shorthand sets all three marker properties
+            "marker" => {
+                self.set_longhand ("marker-start", value)?;
+                self.set_longhand ("marker-mid", value)?;
+                self.set_longhand ("marker-end", value)
+            },
+
+            _ => self.set_longhand (name, value)
+        }
+    }
+}
+
+impl ComputedValues {
+    // Resolves currentColor in properties like stop-color
+    pub fn resolve_color (&self, color: &Color) -> cssparser::RGBA {
+        match *color {
+            Color::RGBA (rgba) => rgba,
+            _                  => self.color.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute (specified: &[(&str, &str)], parent: &ComputedValues) -> ComputedValues {
+        let mut values = SpecifiedValues::default ();
+
+        for &(name, value) in specified {
+            values.set_property (name, value).unwrap ();
+        }
+
+        values.to_computed_values (parent)
+    }
+
+    #[test]
+    fn parses_keywords () {
+        assert_eq! (FillRule::parse ("evenodd", ()), Ok (FillRule::EvenOdd));
+        assert_eq! (ShapeRendering::parse ("default", ()), Ok (ShapeRendering::Auto));
+        assert_eq! (FontWeight::parse ("700", ()), Ok (FontWeight::W700));
+        assert_eq! (MaskType::parse ("alpha", ()), Ok (MaskType::Alpha));
+        assert! (StrokeLinecap::parse ("foo", ()).is_err ());
+        assert! (Display::parse ("", ()).is_err ());
+    }
+
+    #[test]
+    fn parses_glyph_orientation_vertical () {
+        assert_eq! (GlyphOrientationVertical::parse ("auto", ()), Ok (GlyphOrientationVertical::Auto));
+        assert_eq! (GlyphOrientationVertical::parse ("90", ()), Ok (GlyphOrientationVertical::Degrees (90)));
+        assert_eq! (GlyphOrientationVertical::parse ("-90deg", ()), Ok (GlyphOrientationVertical::Degrees (270)));
+        assert! (GlyphOrientationVertical::parse ("45", ()).is_err ());
+        assert! (GlyphOrientationVertical::parse ("upright", ()).is_err ());
+    }
+
+    #[test]
+    fn parses_inherit_and_initial () {
+        assert_eq! (parse_specified_value::<FillRule> ("inherit"), Ok (SpecifiedValue::Inherit));
+        assert_eq! (parse_specified_value::<FillRule> (" initial "), Ok (SpecifiedValue::Initial));
+        assert_eq! (parse_specified_value::<FillRule> ("evenodd"), Ok (SpecifiedValue::Specified (FillRule::EvenOdd)));
+    }
+
+    #[test]
+    fn parses_paint () {
+        let red = Color::RGBA (cssparser::RGBA::new (255, 0, 0, 255));
+
+        assert_eq! (Paint::parse ("none", ()), Ok (Paint::None));
+        assert_eq! (Paint::parse ("red", ()), Ok (Paint::Color (red)));
+        assert_eq! (Paint::parse ("currentColor", ()), Ok (Paint::Color (Color::CurrentColor)));
+        assert_eq! (Paint::parse ("url(#foo)", ()), Ok (Paint::Server ("#foo".to_string (), None)));
+        assert_eq! (Paint::parse ("url(#foo) none", ()), Ok (Paint::Server ("#foo".to_string (), None)));
+        assert_eq! (Paint::parse ("url(#foo) red", ()), Ok (Paint::Server ("#foo".to_string (), Some (red))));
+        assert! (Paint::parse ("url(#foo) bar", ()).is_err ());
+        assert! (Paint::parse ("red blue", ()).is_err ());
+    }
+
+    #[test]
+    fn parses_iri () {
+        assert_eq! (IRI::parse ("none", ()), Ok (IRI::None));
+        assert_eq! (IRI::parse ("url(#foo)", ()), Ok (IRI::Resource ("#foo".to_string ())));
+        assert! (IRI::parse ("#foo", ()).is_err ());
+    }
+
+    #[test]
+    fn parses_filter () {
+        assert_eq! (Filter::parse ("none", ()), Ok (Filter (Vec::new ())));
+        assert_eq! (Filter::parse ("url(#f) invert()", ()),
+                    Ok (Filter (vec! [FilterFunction::Url ("#f".to_string ()), FilterFunction::Invert (1.0)])));
+        assert! (Filter::parse ("blur(1px 2px)", ()).is_err ());
+    }
+
+    #[test]
+    fn parses_dasharray () {
+        let length = |l| RsvgLength::new (l, LengthUnit::Default, LengthDir::Both);
+
+        assert_eq! (StrokeDasharray::parse ("none", ()), Ok (StrokeDasharray::None));
+        assert_eq! (StrokeDasharray::parse ("0, 0", ()), Ok (StrokeDasharray::None));
+        assert_eq! (StrokeDasharray::parse ("1 2,3", ()),
+                    Ok (StrokeDasharray::Dashes (vec! [length (1.0), length (2.0), length (3.0),
+                                                       length (1.0), length (2.0), length (3.0)])));
+        assert! (StrokeDasharray::parse ("1, -2", ()).is_err ());
+        assert! (StrokeDasharray::parse ("", ()).is_err ());
+    }
+
+    #[test]
+    fn invalid_values_leave_property_unchanged () {
+        let mut values = SpecifiedValues::default ();
+
+        values.set_property ("fill-rule", "evenodd").unwrap ();
+        assert! (values.set_property ("fill-rule", "bogus").is_err ());
+        assert! (values.set_property ("no-such-property", "1").is_err ());
+
+        assert_eq! (values.fill_rule, SpecifiedValue::Specified (FillRule::EvenOdd));
+    }
+
+    #[test]
+    fn inherits_only_inherited_properties () {
+        let parent = compute (&[("fill-rule", "evenodd"), ("opacity", "0.5"), ("stop-color", "red")],
+                              &ComputedValues::default ());
+
+        let child = compute (&[], &parent);
+
+        assert_eq! (child.fill_rule, FillRule::EvenOdd);
+        assert_eq! (child.opacity, GroupOpacity (1.0));
+        assert_eq! (child.stop_color, StopColor::default ());
+
+        let child = compute (&[("opacity", "inherit"), ("fill-rule", "initial")], &parent);
+
+        assert_eq! (child.fill_rule, FillRule::NonZero);
+        assert_eq! (child.opacity, GroupOpacity (0.5));
+    }
+
+    #[test]
+    fn computes_relative_font_size () {
+        let parent = compute (&[("font-size", "20")], &ComputedValues::default ());
+
+        assert_eq! (compute (&[("font-size", "2em")], &parent).font_size.0.length, 40.0);
+        assert_eq! (compute (&[("font-size", "50%")], &parent).font_size.0.length, 10.0);
+        assert_eq! (compute (&[("font-size", "larger")], &parent).font_size.0.length, 24.0);
+        assert_eq! (compute (&[], &parent).font_size.0.length, 20.0);
+    }
+
+    #[test]
+    fn computes_relative_font_weight () {
+        let bold = compute (&[("font-weight", "bold")], &ComputedValues::default ());
+        let light = compute (&[("font-weight", "200")], &ComputedValues::default ());
+
+        assert_eq! (compute (&[("font-weight", "bolder")], &ComputedValues::default ()).font_weight, FontWeight::W700);
+        assert_eq! (compute (&[("font-weight", "bolder")], &bold).font_weight, FontWeight::W900);
+        assert_eq! (compute (&[("font-weight", "bolder")], &light).font_weight, FontWeight::W400);
+        assert_eq! (compute (&[("font-weight", "lighter")], &bold).font_weight, FontWeight::W400);
+        assert_eq! (compute (&[("font-weight", "lighter")], &light).font_weight, FontWeight::W100);
+
+        let bolder = compute (&[("font-weight", "bolder")], &bold);
+        assert_eq! (compute (&[], &bolder).font_weight, FontWeight::W900);
+        assert_eq! (compute (&[("font-weight", "lighter")], &bolder).font_weight, FontWeight::W700);
+    }
+
+    #[test]
+    fn marker_shorthand_sets_all_markers () {
+        let values = compute (&[("marker", "url(#m)"), ("marker-mid", "none")], &ComputedValues::default ());
+
+        assert_eq! (values.marker_start, MarkerStart (IRI::Resource ("#m".to_string ())));
+        assert_eq! (values.marker_mid, MarkerMid (IRI::None));
+        assert_eq! (values.marker_end, MarkerEnd (IRI::Resource ("#m".to_string ())));
+    }
+
+    #[test]
+    fn resolves_current_color () {
+        let values = compute (&[("color", "lime"), ("stop-color", "currentColor")], &ComputedValues::default ());
+
+        assert_eq! (values.resolve_color (&values.stop_color.0), cssparser::RGBA::new (0, 255, 0, 255));
+    }
+
+    #[test]
+    fn identifies_presentation_attributes () {
+        assert! (is_presentation_attribute ("fill"));
+        assert! (is_presentation_attribute ("xml:space"));
+        assert! (!is_presentation_attribute ("marker"));
+        assert! (!is_presentation_attribute ("x"));
+    }
+}
@@ -9,8 +9,10 @@ pub enum RsvgPropertyBag {}
 
 extern "C" {
     fn rsvg_property_bag_lookup (pbag: *const RsvgPropertyBag, key: *const libc::c_char) -> *const libc::c_char;
-    fn rsvg_property_bag_dup (pbag: *const RsvgPropertyBag) -> *mut RsvgPropertyBag;
-    fn rsvg_property_bag_free (pbag: *mut RsvgPropertyBag);
+    fn rsvg_property_bag_insert (pbag: *mut RsvgPropertyBag, key: *const libc::c_char, value: *const libc::c_char);
+    fn rsvg_property_bag_enumerate (pbag: *const RsvgPropertyBag,
+                                    func: extern "C" fn (*const libc::c_char, *const libc::c_char, *mut libc::c_void),
+                                    user_data: *mut libc::c_void);
 }
 
 pub fn lookup (pbag: *const RsvgPropertyBag, key: &str) -> Option<String> {
@@ -20,18 +22,32 @@ pub fn lookup (pbag: *const RsvgPropertyBag, key: &str) -> Option<String> {
     }
 }
 
-pub fn dup (pbag: *const RsvgPropertyBag) -> *mut RsvgPropertyBag {
+// Only for property bags that own their strings, as returned by dup()
+pub fn insert (pbag: *mut RsvgPropertyBag, key: &str, value: &str) {
     unsafe {
-        rsvg_property_bag_dup (pbag)
+        rsvg_property_bag_insert (pbag, key.to_glib_none ().0, value.to_glib_none ().0);
     }
 }
 
-pub fn free (pbag: *mut RsvgPropertyBag) {
+extern "C" fn add_to_vec (key: *const libc::c_char, value: *const libc::c_char, user_data: *mut libc::c_void) {
+    let vec = unsafe { &mut *(user_data as *mut Vec<(String, String)>) };
+
     unsafe {
-        rsvg_property_bag_free (pbag);
+        vec.push ((from_glib_none (key), from_glib_none (value)));
     }
 }
 
+// Returns the (key, value) pairs in no particular order
+pub fn to_vec (pbag: *const RsvgPropertyBag) -> Vec<(String, String)> {
+    let mut vec = Vec::new ();
+
+    unsafe {
+        rsvg_property_bag_enumerate (pbag, add_to_vec, &mut vec as *mut Vec<(String, String)> as *mut libc::c_void);
+    }
+
+    vec
+}
+
 pub fn parse_or_none<T> (pbag: *const RsvgPropertyBag,
                          key: &'static str,
                          data: <T as Parse>::Data,
@@ -0,0 +1,440 @@
+//! Writes a loaded document back out as normalized SVG.
+//!
+//! Only what librsvg understands survives:  unknown elements and their
+//! children, elements in error, and event handler attributes are
+//! dropped.  So are references to anything outside of the document:
+//! links other than fragments or `data:` URLs, and `url()` values
+//! other than fragments.  The style sheet is folded into a `style`
+//! attribute on each element, path data is rewritten with absolute
+//! commands, and transforms become `matrix()`.
+//!
+//! Optionally, `<text>` elements can be written as `<path>` elements
+//! with the outlines of their glyphs, for consumers that have no fonts
+//! or that need plain geometry, like cutting machines.
+
+use ::cairo;
+use ::glib_sys;
+use ::glib::translate::*;
+use ::libc;
+
+use std::collections::HashSet;
+
+use handle::{self, RsvgHandle};
+use node::{NodeType, RsvgNode};
+use path_builder::RsvgPathBuilder;
+use shapes;
+use transform::parse_transform;
+
+// Keep in sync with rsvg-private.h:RsvgNodeChars
+#[repr(C)]
+struct RsvgNodeChars {
+    contents: *mut glib_sys::GString
+}
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Serializes the tree under `root`, which should be the toplevel
+/// `<svg>` element, as a complete XML document.
+pub fn serialize (root: &RsvgNode) -> String {
+    serialize_with_text_outlines (root, |_| None)
+}
+
+/// Like `serialize`, but each `<text>` element for which `text_outlines`
+/// returns a path gets written as a `<path>` with that path data instead.
+/// The path must be in the user space of the text element, so that the
+/// text's transform and style still apply to it.
+pub fn serialize_with_text_outlines<F> (root: &RsvgNode, text_outlines: F) -> String
+    where F: Fn (&RsvgNode) -> Option<RsvgPathBuilder>
+{
+    let mut out = String::from ("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    write_node (&mut out, root, true, &text_outlines);
+    out.push ('\n');
+
+    out
+}
+
+fn write_node<F> (out: &mut String, node: &RsvgNode, is_root: bool, text_outlines: &F)
+    where F: Fn (&RsvgNode) -> Option<RsvgPathBuilder>
+{
+    if node.get_type () == NodeType::Chars {
+        let chars = node.get_c_impl () as *const RsvgNodeChars;
+        let contents: String = unsafe { from_glib_none ((*(*chars).contents).str) };
+
+        out.push_str (&escape (&contents, false));
+        return;
+    }
+
+    if !is_supported (node) {
+        return;
+    }
+
+    if node.get_type () == NodeType::Text {
+        if let Some (outlines) = text_outlines (node) {
+            write_text_as_path (out, node, &outlines);
+            return;
+        }
+    }
+
+    let name = node.get_element_name ();
+
+    out.push ('<');
+    out.push_str (&name);
+
+    for (key, value) in serialized_attributes (node, is_root) {
+        out.push_str (&format! (" {}=\"{}\"", key, escape (&value, true)));
+    }
+
+    let children = node.children.borrow ();
+
+    if children.is_empty () {
+        out.push_str ("/>");
+    } else {
+        out.push ('>');
+
+        for child in children.iter () {
+            write_node (out, child, false, text_outlines);
+        }
+
+        out.push_str (&format! ("</{}>", name));
+    }
+}
+
+// The text's characters and their positions are all in the outlines;
+// its id, transform and style carry over to the path.  Text without
+// any glyphs doesn't render anything, so it gets dropped.
+fn write_text_as_path (out: &mut String, node: &RsvgNode, outlines: &RsvgPathBuilder) {
+    let data = outlines.to_path_data ();
+
+    if data.is_empty () {
+        return;
+    }
+
+    out.push_str ("<path");
+
+    for (key, value) in serialized_attributes (node, false) {
+        match key.as_str () {
+            "x" | "y" | "dx" | "dy" | "rotate" | "textLength" | "lengthAdjust" | "xml:space" => (),
+            _ => out.push_str (&format! (" {}=\"{}\"", key, escape (&value, true)))
+        }
+    }
+
+    out.push_str (&format! (" d=\"{}\"/>", data));
+}
+
+// Elements that we don't know about get loaded as a defs, which
+// doesn't render its children; see default_node_creator in rsvg-base.c
+fn is_supported (node: &RsvgNode) -> bool {
+    if node.get_result ().is_err () {
+        return false;
+    }
+
+    node.get_type () != NodeType::Defs || node.get_element_name () == "defs"
+}
+
+fn serialized_attributes (node: &RsvgNode, is_root: bool) -> Vec<(String, String)> {
+    let mut attributes = Vec::new ();
+
+    for (key, value) in node.get_attributes () {
+        match key.as_str () {
+            // replaced by the cascaded declarations below
+            "style" | "class" => (),
+
+            "d" => {
+                if let Some (builder) = shapes::path_node_builder (node) {
+                    attributes.push ((key, builder.to_path_data ()));
+                }
+            },
+
+            "points" | "verts" => {
+                if let Some (points) = shapes::poly_node_points (node) {
+                    attributes.push (("points".to_string (), format_points (&points)));
+                }
+            },
+
+            "transform" | "gradientTransform" | "patternTransform" => {
+                if let Ok (matrix) = parse_transform (&value) {
+                    attributes.push ((key, format_matrix (&matrix)));
+                }
+            },
+
+            _ if key.starts_with ("on") => (),
+
+            "href" | "xlink:href" if !is_local_reference (&value) => (),
+
+            _ if has_external_url (&value) => (),
+
+            _ => attributes.push ((key, value))
+        }
+    }
+
+    let style = format_declarations (node);
+    if !style.is_empty () {
+        attributes.push (("style".to_string (), style));
+    }
+
+    if is_root {
+        let mut namespaces = Vec::new ();
+
+        for &(name, uri) in &[("xmlns", SVG_NAMESPACE), ("xmlns:xlink", XLINK_NAMESPACE)] {
+            if !attributes.iter ().any (|&(ref key, _)| key == name) {
+                namespaces.push ((name.to_string (), uri.to_string ()));
+            }
+        }
+
+        namespaces.extend (attributes);
+        attributes = namespaces;
+    }
+
+    attributes
+}
+
+// The cascade returns the declarations in increasing order of priority;
+// only the last one for each property matters.
+fn format_declarations (node: &RsvgNode) -> String {
+    let mut seen = HashSet::new ();
+    let mut declarations = Vec::new ();
+
+    for decl in node.get_style_declarations ().into_iter ().rev () {
+        if seen.insert (decl.name.clone ()) && !has_external_url (&decl.value) {
+            declarations.push (format! ("{}:{}", decl.name, decl.value));
+        }
+    }
+
+    declarations.reverse ();
+    declarations.join (";")
+}
+
+// Links can point to a fragment of the document, or carry their data
+fn is_local_reference (value: &str) -> bool {
+    let value = value.trim ();
+
+    value.starts_with ('#') || value.get (..5).map_or (false, |scheme| scheme.eq_ignore_ascii_case ("data:"))
+}
+
+// Whether any url() in a property value points outside of the document
+fn has_external_url (value: &str) -> bool {
+    let lower = value.to_ascii_lowercase ();
+    let mut rest = lower.as_str ();
+
+    while let Some (start) = rest.find ("url(") {
+        rest = &rest[start + 4..];
+
+        let target = rest.trim_left ().trim_left_matches (|c| c == '"' || c == '\'');
+        if !target.starts_with ('#') {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[no_mangle]
+pub extern fn rsvg_serialize_with_text_as_paths (raw_handle: *const RsvgHandle,
+                                                 raw_root: *const RsvgNode) -> *mut libc::c_char {
+    assert! (!raw_handle.is_null ());
+    assert! (!raw_root.is_null ());
+    let root: &RsvgNode = unsafe { & *raw_root };
+
+    serialize_with_text_outlines (root, |node| handle::get_text_outlines (raw_handle, node)).to_glib_full ()
+}
+
+fn format_number (n: f64) -> String {
+    // avoid writing "-0"
+    if n == 0.0 {
+        "0".to_string ()
+    } else {
+        format! ("{}", n)
+    }
+}
+
+fn format_points (points: &[(f64, f64)]) -> String {
+    let points: Vec<String> = points.iter ()
+        .map (|&(x, y)| format! ("{},{}", format_number (x), format_number (y)))
+        .collect ();
+
+    points.join (" ")
+}
+
+fn format_matrix (m: &cairo::Matrix) -> String {
+    format! ("matrix({} {} {} {} {} {})",
+             format_number (m.xx), format_number (m.yx),
+             format_number (m.xy), format_number (m.yy),
+             format_number (m.x0), format_number (m.y0))
+}
+
+fn escape (s: &str, in_attribute: bool) -> String {
+    let mut escaped = String::with_capacity (s.len ());
+
+    for c in s.chars () {
+        match c {
+            '&'                  => escaped.push_str ("&amp;"),
+            '<'                  => escaped.push_str ("&lt;"),
+            '>'                  => escaped.push_str ("&gt;"),
+            '"' if in_attribute  => escaped.push_str ("&quot;"),
+            _                    => escaped.push (c)
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use css::Declaration;
+    use error::NodeError;
+    use node::*;
+    use parsers::ParseError;
+    use test_utils;
+
+    fn element (node_type: NodeType, name: &str, attributes: &[(&str, &str)]) -> RsvgNode {
+        test_utils::element (node_type, None, name, attributes)
+    }
+
+    #[test]
+    fn formats_transforms_as_matrices () {
+        let m = parse_transform ("translate(10, 20) scale(2)").unwrap ();
+        assert_eq! (format_matrix (&m), "matrix(2 0 0 2 10 20)");
+    }
+
+    #[test]
+    fn escapes_markup () {
+        assert_eq! (escape ("a < b & \"c\"", false), "a &lt; b &amp; \"c\"");
+        assert_eq! (escape ("a < b & \"c\"", true), "a &lt; b &amp; &quot;c&quot;");
+    }
+
+    #[test]
+    fn serializes_known_elements () {
+        let svg = element (NodeType::Svg, "svg", &[("width", "100"), ("onload", "alert(1)")]);
+        let g = element (NodeType::Group, "g", &[("transform", "translate(5 5)"), ("class", "foo")]);
+        let unknown = element (NodeType::Defs, "script", &[]);
+        let broken = element (NodeType::Group, "g", &[]);
+
+        g.set_style_declarations (vec! [
+            Declaration { name: "fill".to_string (), value: "red".to_string (), important: false },
+            Declaration { name: "stroke".to_string (), value: "blue".to_string (), important: false },
+            Declaration { name: "fill".to_string (), value: "lime".to_string (), important: false }
+        ]);
+
+        broken.set_error (NodeError::parse_error ("x", ParseError::new ("bad")));
+
+        svg.add_child (&g);
+        svg.add_child (&unknown);
+        svg.add_child (&broken);
+
+        assert_eq! (serialize (&svg),
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <svg xmlns=\"http://www.w3.org/2000/svg\" \
+                     xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"100\">\
+                     <g transform=\"matrix(1 0 0 1 5 5)\" style=\"stroke:blue;fill:lime\"/>\
+                     </svg>\n");
+    }
+
+    #[test]
+    fn recognizes_local_references () {
+        assert! (is_local_reference ("#foo"));
+        assert! (is_local_reference (" #foo"));
+        assert! (is_local_reference ("data:image/png;base64,AAAA"));
+        assert! (is_local_reference ("DATA:image/png;base64,AAAA"));
+
+        assert! (!is_local_reference ("javascript:alert(1)"));
+        assert! (!is_local_reference ("http://example.com/image.png"));
+        assert! (!is_local_reference ("image.png"));
+        assert! (!is_local_reference (""));
+    }
+
+    #[test]
+    fn recognizes_external_urls () {
+        assert! (!has_external_url ("red"));
+        assert! (!has_external_url ("url(#grad)"));
+        assert! (!has_external_url ("url( '#grad' ) red"));
+
+        assert! (has_external_url ("url(http://example.com/a.svg#grad)"));
+        assert! (has_external_url ("URL(\"other.svg#grad\")"));
+        assert! (has_external_url ("url(#grad) url(other.svg#grad)"));
+    }
+
+    fn serialize_children (children: &[RsvgNode]) -> String {
+        let svg = element (NodeType::Svg, "svg", &[]);
+
+        for child in children {
+            svg.add_child (child);
+        }
+
+        serialize (&svg)
+    }
+
+    fn document (body: &str) -> String {
+        format! ("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                  <svg xmlns=\"http://www.w3.org/2000/svg\" \
+                  xmlns:xlink=\"http://www.w3.org/1999/xlink\">{}</svg>\n",
+                 body)
+    }
+
+    #[test]
+    fn drops_external_urls_in_styles () {
+        let rect = element (NodeType::Rect, "rect", &[]);
+
+        rect.set_style_declarations (vec! [
+            Declaration { name: "filter".to_string (), value: "url(http://example.com/f.svg#f)".to_string (), important: false },
+            Declaration { name: "mask".to_string (), value: "url(#mask)".to_string (), important: false }
+        ]);
+
+        assert_eq! (serialize_children (&[rect]), document ("<rect style=\"mask:url(#mask)\"/>"));
+    }
+
+    #[test]
+    fn drops_external_urls_in_attributes () {
+        let rect = element (NodeType::Rect, "rect", &[("fill", "url(other.svg#grad)"), ("stroke", "url(#grad)")]);
+
+        assert_eq! (serialize_children (&[rect]), document ("<rect stroke=\"url(#grad)\"/>"));
+    }
+
+    #[test]
+    fn drops_script_links () {
+        let a = element (NodeType::Group, "a", &[("xlink:href", "javascript:alert(1)")]);
+        let local = element (NodeType::Group, "a", &[("href", "#target")]);
+
+        assert_eq! (serialize_children (&[a, local]), document ("<a/><a href=\"#target\"/>"));
+    }
+
+    #[test]
+    fn drops_external_images () {
+        let image = element (NodeType::Image, "image", &[("xlink:href", "http://example.com/a.png")]);
+        let data = element (NodeType::Image, "image", &[("xlink:href", "data:image/png;base64,AAAA")]);
+
+        assert_eq! (serialize_children (&[image, data]),
+                    document ("<image/><image xlink:href=\"data:image/png;base64,AAAA\"/>"));
+    }
+
+    #[test]
+    fn serializes_text_as_paths () {
+        let svg = element (NodeType::Svg, "svg", &[]);
+        let text = element (NodeType::Text, "text", &[("id", "t"), ("x", "10"), ("y", "20"), ("fill", "red")]);
+        let empty = element (NodeType::Text, "text", &[("x", "10")]);
+
+        svg.add_child (&text);
+        svg.add_child (&empty);
+
+        let outlines = |node: &RsvgNode| {
+            let mut builder = RsvgPathBuilder::new ();
+
+            if node.get_attributes ().iter ().any (|&(ref k, _)| k == "id") {
+                builder.move_to (10.0, 20.0);
+                builder.line_to (15.0, 10.0);
+                builder.close_path ();
+            }
+
+            Some (builder)
+        };
+
+        assert_eq! (serialize_with_text_outlines (&svg, outlines),
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <svg xmlns=\"http://www.w3.org/2000/svg\" \
+                     xmlns:xlink=\"http://www.w3.org/1999/xlink\">\
+                     <path id=\"t\" fill=\"red\" d=\"M10 20 15 10Z\"/>\
+                     </svg>\n");
+    }
+}
@@ -1,7 +1,10 @@
+use ::glib::translate::*;
+use ::glib_sys;
 use ::libc;
 
 use std::cell::RefCell;
 use std::cell::Cell;
+use std::ptr;
 
 use drawing_ctx;
 use drawing_ctx::*;
@@ -12,17 +15,54 @@ use marker;
 use node::*;
 use parsers;
 use path_builder::*;
+use path_geometry::PathMeasure;
 use path_parser;
 use property_bag;
 use property_bag::*;
 use state::RsvgState;
 
+// pathLength:
+// https://www.w3.org/TR/SVG/paths.html#PathLengthAttribute
+//
+// A value of zero gives us no way to scale distances, so we ignore it.
+
+fn parse_path_length (pbag: *const RsvgPropertyBag) -> Result<Option<f64>, NodeError> {
+    match property_bag::lookup (pbag, "pathLength") {
+        Some (value) => {
+            let length = parsers::number (&value)
+                .map_err (|e| NodeError::parse_error ("pathLength", e))?;
+
+            if length < 0.0 {
+                Err (NodeError::value_error ("pathLength", "value must be non-negative"))
+            } else if length == 0.0 {
+                Ok (None)
+            } else {
+                Ok (Some (length))
+            }
+        },
+
+        None => Ok (None)
+    }
+}
+
 fn render_path_builder (builder:  &RsvgPathBuilder,
                         draw_ctx: *const RsvgDrawingCtx,
                         state:    *mut RsvgState,
                         dominate: i32,
-                        render_markers: bool) {
+                        render_markers: bool,
+                        path_length: Option<f64>) {
     drawing_ctx::state_reinherit_top (draw_ctx, state, dominate);
+
+    /* Dash lengths are specified in terms of the author's pathLength,
+     * so they get scaled by the ratio of the real length to it.
+     */
+    let path_length_scale = match path_length {
+        Some (author_length) => builder.length () / author_length,
+        None                 => 1.0
+    };
+
+    drawing_ctx::state_set_path_length_scale (drawing_ctx::get_current_state (draw_ctx), path_length_scale);
+
     drawing_ctx::render_path_builder (draw_ctx, builder);
 
     if render_markers {
@@ -34,6 +74,7 @@ fn render_ellipse (cx: f64,
                    cy: f64,
                    rx: f64,
                    ry: f64,
+                   path_length: Option<f64>,
                    node: &RsvgNode,
                    draw_ctx: *const RsvgDrawingCtx,
                    dominate: i32) {
@@ -70,39 +111,51 @@ fn render_ellipse (cx: f64,
 
     builder.close_path ();
 
-    render_path_builder (&builder, draw_ctx, node.get_state (), dominate, false);
+    render_path_builder (&builder, draw_ctx, node.get_state (), dominate, false, path_length);
 }
 
 /***** NodePath *****/
 
 struct NodePath {
-    builder: RefCell<RsvgPathBuilder>
+    builder:     RefCell<RsvgPathBuilder>,
+    path_length: Cell<Option<f64>>
 }
 
 impl NodePath {
     fn new () -> NodePath {
         NodePath {
-            builder: RefCell::new (RsvgPathBuilder::new ())
+            builder:     RefCell::new (RsvgPathBuilder::new ()),
+            path_length: Cell::new (None)
         }
     }
 }
 
 impl NodeTrait for NodePath {
-    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
-        if let Some (value) = property_bag::lookup (pbag, "d") {
-            let mut builder = self.builder.borrow_mut ();
+    fn set_atts (&self, node: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        // Attributes get re-applied when animating, so start from an empty path
+        let mut builder = self.builder.borrow_mut ();
+        *builder = RsvgPathBuilder::new ();
 
-            if let Err (_) = path_parser::parse_path_into_builder (&value, &mut *builder) {
-                // FIXME: we don't propagate errors upstream, but creating a partial
-                // path is OK per the spec
+        if let Some (value) = property_bag::lookup (pbag, "d") {
+            let result = path_parser::parse_path_into_builder (&value, &mut *builder);
+
+            // Rendering the partial path is OK per the spec, so only record
+            // the error instead of putting the node in error.  An empty path
+            // is valid and just disables rendering.
+            if let Err (e) = result {
+                if !value.trim ().is_empty () {
+                    node.add_error (NodeError::parse_error ("d", parsers::ParseError::new (e.to_string ())));
+                }
             }
         }
 
+        self.path_length.set (parse_path_length (pbag)?);
+
         Ok (())
     }
 
     fn draw (&self, node: &RsvgNode, draw_ctx: *const RsvgDrawingCtx, dominate: i32) {
-        render_path_builder (&*self.builder.borrow (), draw_ctx, node.get_state (), dominate, true);
+        render_path_builder (&*self.builder.borrow (), draw_ctx, node.get_state (), dominate, true, self.path_length.get ());
     }
 
     fn get_c_impl (&self) -> *const RsvgCNodeImpl {
@@ -110,6 +163,19 @@ impl NodeTrait for NodePath {
     }
 }
 
+// Measures the path of a <path> element, for things that follow it like animateMotion
+pub fn path_node_measure (node: &RsvgNode) -> Option<PathMeasure> {
+    node.get_impl::<NodePath> ()
+        .map (|path| PathMeasure::new (&*path.builder.borrow ()))
+}
+
+// The path of a <path> element, with everything converted to absolute
+// moveto/lineto/curveto; used when serializing the document
+pub fn path_node_builder (node: &RsvgNode) -> Option<RsvgPathBuilder> {
+    node.get_impl::<NodePath> ()
+        .map (|path| path.builder.borrow ().clone ())
+}
+
 /***** NodePoly *****/
 
 #[derive(Debug, PartialEq)]
@@ -120,14 +186,16 @@ enum PolyKind {
 
 struct NodePoly {
     points: RefCell <Option<Vec<(f64, f64)>>>,
-    kind: PolyKind
+    kind: PolyKind,
+    path_length: Cell<Option<f64>>
 }
 
 impl NodePoly {
     fn new (kind: PolyKind) -> NodePoly {
         NodePoly {
-            points: RefCell::new (None),
-            kind:   kind
+            points:      RefCell::new (None),
+            kind:        kind,
+            path_length: Cell::new (None)
         }
     }
 }
@@ -151,6 +219,8 @@ impl NodeTrait for NodePoly {
             }
         }
 
+        self.path_length.set (parse_path_length (pbag)?);
+
         Ok (())
     }
 
@@ -170,7 +240,7 @@ impl NodeTrait for NodePoly {
                 builder.close_path ();
             }
 
-            render_path_builder (&builder, draw_ctx, node.get_state (), dominate, true);
+            render_path_builder (&builder, draw_ctx, node.get_state (), dominate, true, self.path_length.get ());
         }
     }
 
@@ -179,13 +249,21 @@ impl NodeTrait for NodePoly {
     }
 }
 
+// The points of a <polygon> or <polyline> element
+pub fn poly_node_points (node: &RsvgNode) -> Option<Vec<(f64, f64)>> {
+    node.get_impl::<NodePoly> ()
+        .and_then (|poly| poly.points.borrow ().clone ())
+}
+
 /***** NodeLine *****/
 
 struct NodeLine {
     x1: Cell<RsvgLength>,
     y1: Cell<RsvgLength>,
     x2: Cell<RsvgLength>,
-    y2: Cell<RsvgLength>
+    y2: Cell<RsvgLength>,
+
+    path_length: Cell<Option<f64>>
 }
 
 impl NodeLine {
@@ -194,7 +272,9 @@ impl NodeLine {
             x1: Cell::new (RsvgLength::default ()),
             y1: Cell::new (RsvgLength::default ()),
             x2: Cell::new (RsvgLength::default ()),
-            y2: Cell::new (RsvgLength::default ())
+            y2: Cell::new (RsvgLength::default ()),
+
+            path_length: Cell::new (None)
         }
     }
 }
@@ -206,6 +286,8 @@ impl NodeTrait for NodeLine {
         self.x2.set (property_bag::parse_or_default (pbag, "x2", LengthDir::Horizontal, None)?);
         self.y2.set (property_bag::parse_or_default (pbag, "y2", LengthDir::Vertical, None)?);
 
+        self.path_length.set (parse_path_length (pbag)?);
+
         Ok (())
     }
 
@@ -220,7 +302,7 @@ impl NodeTrait for NodeLine {
         builder.move_to (x1, y1);
         builder.line_to (x2, y2);
 
-        render_path_builder (&builder, draw_ctx, node.get_state (), dominate, true);
+        render_path_builder (&builder, draw_ctx, node.get_state (), dominate, true, self.path_length.get ());
     }
 
     fn get_c_impl (&self) -> *const RsvgCNodeImpl {
@@ -239,7 +321,9 @@ struct NodeRect {
 
     // Radiuses for rounded corners
     rx: Cell<Option<RsvgLength>>,
-    ry: Cell<Option<RsvgLength>>
+    ry: Cell<Option<RsvgLength>>,
+
+    path_length: Cell<Option<f64>>
 }
 
 impl NodeRect {
@@ -251,7 +335,9 @@ impl NodeRect {
             h: Cell::new (RsvgLength::default ()),
 
             rx: Cell::new (None),
-            ry: Cell::new (None)
+            ry: Cell::new (None),
+
+            path_length: Cell::new (None)
         }
     }
 }
@@ -266,6 +352,8 @@ impl NodeTrait for NodeRect {
         self.rx.set (property_bag::parse_or_none (pbag, "rx", LengthDir::Horizontal, None)?);
         self.ry.set (property_bag::parse_or_none (pbag, "ry", LengthDir::Vertical, None)?);
 
+        self.path_length.set (parse_path_length (pbag)?);
+
         Ok (())
     }
 
@@ -405,7 +493,7 @@ impl NodeTrait for NodeRect {
             builder.close_path ();
         }
 
-        render_path_builder (&builder, draw_ctx, node.get_state (), dominate, false);
+        render_path_builder (&builder, draw_ctx, node.get_state (), dominate, false, self.path_length.get ());
     }
 
     fn get_c_impl (&self) -> *const RsvgCNodeImpl {
@@ -418,7 +506,9 @@ impl NodeTrait for NodeRect {
 struct NodeCircle {
     cx: Cell<RsvgLength>,
     cy: Cell<RsvgLength>,
-    r:  Cell<RsvgLength>
+    r:  Cell<RsvgLength>,
+
+    path_length: Cell<Option<f64>>
 }
 
 impl NodeCircle {
@@ -427,6 +517,8 @@ impl NodeCircle {
             cx: Cell::new (RsvgLength::default ()),
             cy: Cell::new (RsvgLength::default ()),
             r:  Cell::new (RsvgLength::default ()),
+
+            path_length: Cell::new (None)
         }
     }
 }
@@ -439,6 +531,8 @@ impl NodeTrait for NodeCircle {
         self.r.set  (property_bag::parse_or_default (pbag, "r", LengthDir::Both,
                                                      Some(RsvgLength::check_nonnegative))?);
 
+        self.path_length.set (parse_path_length (pbag)?);
+
         Ok (())
     }
 
@@ -447,7 +541,7 @@ impl NodeTrait for NodeCircle {
         let cy = self.cy.get ().normalize (draw_ctx);
         let r = self.r.get ().normalize (draw_ctx);
 
-        render_ellipse (cx, cy, r, r, node, draw_ctx, dominate);
+        render_ellipse (cx, cy, r, r, self.path_length.get (), node, draw_ctx, dominate);
     }
 
     fn get_c_impl (&self) -> *const RsvgCNodeImpl {
@@ -461,7 +555,9 @@ struct NodeEllipse {
     cx: Cell <RsvgLength>,
     cy: Cell <RsvgLength>,
     rx: Cell <RsvgLength>,
-    ry: Cell <RsvgLength>
+    ry: Cell <RsvgLength>,
+
+    path_length: Cell<Option<f64>>
 }
 
 impl NodeEllipse {
@@ -471,6 +567,8 @@ impl NodeEllipse {
             cy: Cell::new (RsvgLength::default ()),
             rx: Cell::new (RsvgLength::default ()),
             ry: Cell::new (RsvgLength::default ()),
+
+            path_length: Cell::new (None)
         }
     }
 }
@@ -486,6 +584,8 @@ impl NodeTrait for NodeEllipse {
         self.ry.set (property_bag::parse_or_default (pbag, "ry", LengthDir::Vertical,
                                                      Some(RsvgLength::check_nonnegative))?);
 
+        self.path_length.set (parse_path_length (pbag)?);
+
         Ok (())
     }
 
@@ -495,7 +595,7 @@ impl NodeTrait for NodeEllipse {
         let rx = self.rx.get ().normalize (draw_ctx);
         let ry = self.ry.get ().normalize (draw_ctx);
 
-        render_ellipse (cx, cy, rx, ry, node, draw_ctx, dominate);
+        render_ellipse (cx, cy, rx, ry, self.path_length.get (), node, draw_ctx, dominate);
     }        
 
     fn get_c_impl (&self) -> *const RsvgCNodeImpl {
@@ -553,3 +653,90 @@ pub extern fn rsvg_node_ellipse_new (_: *const libc::c_char, raw_parent: *const
                     raw_parent,
                     Box::new (NodeEllipse::new ()))
 }
+
+/***** Path measurement for textPath *****/
+
+pub struct RsvgPathMeasure {
+    measure:     PathMeasure,
+    path_length: Option<f64>
+}
+
+/* Returns NULL if the node is not a <path> element */
+#[no_mangle]
+pub extern fn rsvg_node_path_measure_new (raw_node: *const RsvgNode) -> *mut RsvgPathMeasure {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    if let Some (path) = node.get_impl::<NodePath> () {
+        let measure = RsvgPathMeasure {
+            measure:     PathMeasure::new (&*path.builder.borrow ()),
+            path_length: path.path_length.get ()
+        };
+
+        Box::into_raw (Box::new (measure))
+    } else {
+        ptr::null_mut ()
+    }
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_measure_free (raw_measure: *mut RsvgPathMeasure) {
+    assert! (!raw_measure.is_null ());
+
+    let _ = unsafe { Box::from_raw (raw_measure) };
+}
+
+#[no_mangle]
+pub extern fn rsvg_path_measure_get_length (raw_measure: *const RsvgPathMeasure) -> f64 {
+    assert! (!raw_measure.is_null ());
+    let measure: &RsvgPathMeasure = unsafe { & *raw_measure };
+
+    measure.measure.length ()
+}
+
+/* The path's pathLength attribute if it has one, or its computed length otherwise */
+#[no_mangle]
+pub extern fn rsvg_path_measure_get_author_length (raw_measure: *const RsvgPathMeasure) -> f64 {
+    assert! (!raw_measure.is_null ());
+    let measure: &RsvgPathMeasure = unsafe { & *raw_measure };
+
+    measure.path_length.unwrap_or (measure.measure.length ())
+}
+
+/* Gets the point at the specified distance along the path, and the
+ * angle of the path's direction there, in radians.  Distances beyond
+ * either end of the path are extrapolated along the path's direction
+ * at that end.  Returns FALSE if the path has no length.
+ */
+#[no_mangle]
+pub extern fn rsvg_path_measure_get_point (raw_measure: *const RsvgPathMeasure,
+                                           distance:    f64,
+                                           out_x:       *mut f64,
+                                           out_y:       *mut f64,
+                                           out_angle:   *mut f64) -> glib_sys::gboolean {
+    assert! (!raw_measure.is_null ());
+    assert! (!out_x.is_null ());
+    assert! (!out_y.is_null ());
+    assert! (!out_angle.is_null ());
+
+    let measure: &PathMeasure = unsafe { &(*raw_measure).measure };
+
+    let length = measure.length ();
+    let clamped = distance.max (0.0).min (length);
+
+    match (measure.point_at_length (clamped), measure.tangent_at_length (clamped)) {
+        (Some ((x, y)), Some ((tx, ty))) if length > 0.0 => {
+            let extra = distance - clamped;
+
+            unsafe {
+                *out_x = x + tx * extra;
+                *out_y = y + ty * extra;
+                *out_angle = ty.atan2 (tx);
+            }
+
+            true.to_glib ()
+        },
+
+        _ => false.to_glib ()
+    }
+}
@@ -1,32 +1,25 @@
 use ::libc;
 use ::cssparser;
-use ::glib::translate::*;
 
 use std::cell::Cell;
 
-use color::*;
-use drawing_ctx;
 use drawing_ctx::*;
 use error::*;
 use handle::RsvgHandle;
 use length::*;
 use node::*;
 use opacity::*;
-use parsers::Parse;
 use property_bag;
 use property_bag::*;
-use state::RsvgState;
 
 pub struct NodeStop {
-    offset: Cell<f64>,
-    rgba: Cell<u32>
+    offset: Cell<f64>
 }
 
 impl NodeStop {
     fn new () -> NodeStop {
         NodeStop {
-            offset: Cell::new (0.0),
-            rgba: Cell::new (0)
+            offset: Cell::new (0.0)
         }
     }
 
@@ -34,8 +27,15 @@ impl NodeStop {
         self.offset.get ()
     }
 
-    pub fn get_rgba (&self) -> u32 {
-        self.rgba.get ()
+    // The stop's color comes from the stop-color and stop-opacity
+    // properties, with currentColor resolved against the color property.
+    pub fn get_rgba (&self, node: &RsvgNode) -> u32 {
+        let values = node.get_computed_values ();
+
+        let mut rgba = values.resolve_color (&values.stop_color.0);
+        rgba.alpha = opacity_to_u8 (rgba.alpha_f32 () as f64 * values.stop_opacity.0);
+
+        u32_from_rgba (rgba)
     }
 }
 
@@ -61,99 +61,12 @@ fn validate_offset(length: RsvgLength) -> Result<RsvgLength, AttributeError> {
 }
 
 impl NodeTrait for NodeStop {
-    fn set_atts (&self, node: &RsvgNode, handle: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
         let length = property_bag::parse_or_default (pbag, "offset", LengthDir::Both,
                                                      Some(validate_offset))?;
         assert! (length.unit == LengthUnit::Default || length.unit == LengthUnit::Percent);
         self.offset.set (length.length);
 
-        let state = node.get_state ();
-
-        // FIXME: this is the only place where rsvg_parse_style() and
-        // rsvg_parse_style_pairs() are called outside of the
-        // rsvg-base.c machinery.  That one indirectly calls them via
-        // rsvg_parse_style_attrs().
-        //
-        // Should we resolve the stop-color / stop-opacity at
-        // rendering time?
-
-        if let Some (v) = property_bag::lookup (pbag, "style") {
-            unsafe {
-                rsvg_parse_style (handle, state, v.to_glib_none ().0);
-            }
-        }
-
-        unsafe {
-            rsvg_parse_style_pairs (state, pbag);
-        }
-
-        let inherited_state = drawing_ctx::state_new ();
-        let boxed_node = box_node (node.clone ());
-        drawing_ctx::state_reconstruct (inherited_state, boxed_node);
-        rsvg_node_unref (boxed_node);
-
-        let mut color_rgba: cssparser::RGBA;
-
-        let stop_color = drawing_ctx::state_get_stop_color (state)
-            .map_err (|e| NodeError::attribute_error ("stop-color", e))?;
-
-        match stop_color {
-            None => color_rgba = cssparser::RGBA::transparent (),
-
-            Some (Color::Inherit) => {
-                let inherited_stop_color = drawing_ctx::state_get_stop_color (inherited_state)
-                    .map_err (|e| NodeError::attribute_error ("stop-color", e))?;
-
-                match inherited_stop_color {
-                    None => unreachable! (),
-
-                    Some (Color::Inherit) => color_rgba = cssparser::RGBA::transparent (),
-                    Some (Color::CurrentColor) => {
-                        let color = drawing_ctx::state_get_current_color (inherited_state);
-                        match color {
-                            Color::RGBA (rgba) => color_rgba = rgba,
-                            _ => unreachable! ()
-                        }
-                    },
-                    
-                    Some (Color::RGBA (rgba)) => color_rgba = rgba
-                }
-            },
-
-            Some (Color::CurrentColor) => {
-                let color = drawing_ctx::state_get_current_color (inherited_state);
-                match color {
-                    Color::RGBA (rgba) => color_rgba = rgba,
-                    _ => unreachable! ()
-                }
-            }
-
-            Some (Color::RGBA (rgba)) => color_rgba = rgba
-        }
-
-        let stop_opacity = drawing_ctx::state_get_stop_opacity (state)
-            .map_err (|e| NodeError::attribute_error ("stop-opacity", e))?;
-
-        match stop_opacity {
-            None => color_rgba.alpha = 0xff,
-
-            Some (Opacity::Inherit) => {
-                let inherited_opacity = drawing_ctx::state_get_stop_opacity (inherited_state)
-                    .map_err (|e| NodeError::attribute_error ("stop-opacity", e))?;
-
-                match inherited_opacity {
-                    Some (Opacity::Specified (opacity)) => color_rgba.alpha = opacity_to_u8 (opacity),
-                    _ => color_rgba.alpha = 0xff
-                }
-            },
-
-            Some (Opacity::Specified (opacity)) => color_rgba.alpha = opacity_to_u8 (opacity)
-        }
-
-        self.rgba.set (u32_from_rgba (color_rgba));
-
-        drawing_ctx::state_free (inherited_state);
-
         Ok (())
     }
 
@@ -173,11 +86,6 @@ fn u32_from_rgba (rgba: cssparser::RGBA) -> u32 {
     (rgba.alpha as u32)
 }
 
-extern "C" {
-    fn rsvg_parse_style_pairs (state: *mut RsvgState, pbag: *const RsvgPropertyBag);
-    fn rsvg_parse_style (handle: *const RsvgHandle, state: *mut RsvgState, string: *const libc::c_char);
-}
-
 #[no_mangle]
 pub extern fn rsvg_node_stop_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
     boxed_node_new (NodeType::Stop,
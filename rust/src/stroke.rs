@@ -0,0 +1,679 @@
+use ::cairo;
+
+use std::f64;
+use std::f64::consts::*;
+
+use bbox::{StrokeParams, Subpath, cross, dot, left_normal, offset_point, path_builder_to_subpaths};
+use path_builder::*;
+use path_geometry::PathSegment;
+use util::DBL_EPSILON;
+
+/* Conversion of a stroke into a path that can be filled.
+ *
+ * This works on the subpaths from bbox.rs, which unlike the segments
+ * in marker.rs know whether a subpath is closed, and it follows the
+ * same rules for joins, caps and zero-length subpaths as the stroke
+ * bbox computation there.
+ *
+ * Each open subpath becomes a single closed outline that goes along
+ * the left side of the subpath, around the end cap, back along the
+ * right side, and around the start cap.  A closed subpath becomes two
+ * outlines, one for each side, which go in opposite directions.  All
+ * the outlines go around the stroked area in the same direction, so
+ * the result must be filled with the nonzero rule.  Like cairo, inner
+ * joins go through the point where the segments meet, which makes
+ * small loops that the nonzero rule fills in.
+ *
+ * Offset curves of Béziers are not Béziers themselves, so they get
+ * approximated with curves that get subdivided until they are within
+ * a tolerance of the true offset curve.
+ */
+
+/// A dash pattern, with `stroke-dasharray` and `stroke-dashoffset`
+/// resolved to user units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dashes {
+    pub lengths: Vec<f64>,
+    pub offset:  f64
+}
+
+// Fraction of the line width that offset curves may deviate by
+const OFFSET_TOLERANCE: f64 = 1e-3;
+const MAX_OFFSET_DEPTH: u32 = 10;
+
+fn add (a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub (a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale (v: (f64, f64), s: f64) -> (f64, f64) {
+    (v.0 * s, v.1 * s)
+}
+
+fn distance (a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot (b.1 - a.1)
+}
+
+fn line_to (builder: &mut RsvgPathBuilder, p: (f64, f64)) {
+    builder.line_to (p.0, p.1);
+}
+
+/***** Arcs *****/
+
+/* Adds a circular arc around `center` from the direction `from` to
+ * the direction `to`, both of which are unit vectors, turning
+ * counterclockwise (in the sense of increasing angles) if `sweep` is
+ * positive.  The current point must be at the start of the arc.
+ */
+fn arc_to (builder: &mut RsvgPathBuilder,
+           center: (f64, f64),
+           radius: f64,
+           from: (f64, f64),
+           to: (f64, f64),
+           sweep: f64) {
+    let start = from.1.atan2 (from.0);
+    let mut delta = to.1.atan2 (to.0) - start;
+
+    if sweep > 0.0 && delta < 0.0 {
+        delta += 2.0 * PI;
+    } else if sweep < 0.0 && delta > 0.0 {
+        delta -= 2.0 * PI;
+    }
+
+    // Pieces of at most 90 degrees are approximated well by a cubic Bézier
+    let n_pieces = (delta.abs () / FRAC_PI_2).ceil ().max (1.0) as usize;
+    let step = delta / n_pieces as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan () * radius;
+
+    for i in 0..n_pieces {
+        let a0 = start + step * i as f64;
+        let a1 = a0 + step;
+
+        let (sin0, cos0) = a0.sin_cos ();
+        let (sin1, cos1) = a1.sin_cos ();
+
+        let p0 = add (center, (radius * cos0, radius * sin0));
+        let p3 = add (center, (radius * cos1, radius * sin1));
+        let p1 = add (p0, (-k * sin0, k * cos0));
+        let p2 = add (p3, (k * sin1, -k * cos1));
+
+        builder.curve_to (p1.0, p1.1, p2.0, p2.1, p3.0, p3.1);
+    }
+}
+
+/***** Offset segments *****/
+
+// Point on the segment displaced by `distance` along its left normal at t
+fn offset_point_at (segment: &PathSegment, t: f64, distance: f64) -> Option<(f64, f64)> {
+    segment.tangent_at (t)
+        .map (|v| offset_point (segment.point_at (t), left_normal (v), distance))
+}
+
+fn add_offset_curve (builder: &mut RsvgPathBuilder, segment: &PathSegment, offset: f64, tolerance: f64, depth: u32) {
+    let (p1, p2, p3, p4) = match *segment {
+        PathSegment::Curve (p1, p2, p3, p4) => (p1, p2, p3, p4),
+        PathSegment::Line (..) => unreachable! ()
+    };
+
+    let (q1, q4) = match (offset_point_at (segment, 0.0, offset), offset_point_at (segment, 1.0, offset)) {
+        (Some (q1), Some (q4)) => (q1, q4),
+
+        // A piece of zero length, from subdividing around a cusp
+        (_, q4) => {
+            line_to (builder, q4.unwrap_or (p4));
+            return;
+        }
+    };
+
+    // Scale the control handles like the chord gets scaled by the offset
+    let chord = distance (p1, p4);
+    let s = if chord > DBL_EPSILON { distance (q1, q4) / chord } else { 1.0 };
+
+    let q2 = add (q1, scale (sub (p2, p1), s));
+    let q3 = add (q4, scale (sub (p3, p4), s));
+
+    let approximation = PathSegment::Curve (q1, q2, q3, q4);
+
+    let within_tolerance = [0.25, 0.5, 0.75].iter ().all (|&t| {
+        offset_point_at (segment, t, offset)
+            .map_or (true, |exact| distance (exact, approximation.point_at (t)) <= tolerance)
+    });
+
+    if within_tolerance || depth >= MAX_OFFSET_DEPTH {
+        builder.curve_to (q2.0, q2.1, q3.0, q3.1, q4.0, q4.1);
+    } else {
+        let (first, second) = segment.split (0.5);
+
+        add_offset_curve (builder, &first, offset, tolerance, depth + 1);
+        add_offset_curve (builder, &second, offset, tolerance, depth + 1);
+    }
+}
+
+// Adds the segment offset to its left; the current point must be at its offset start
+fn add_offset_segment (builder: &mut RsvgPathBuilder, segment: &PathSegment, half_width: f64) {
+    match *segment {
+        PathSegment::Line (_, p2) => {
+            let n = left_normal (segment.tangent_at (1.0).unwrap ());
+            line_to (builder, offset_point (p2, n, half_width));
+        },
+
+        PathSegment::Curve (..) => {
+            add_offset_curve (builder, segment, half_width, half_width * 2.0 * OFFSET_TOLERANCE, 0);
+        }
+    }
+}
+
+/***** Joins and caps *****/
+
+/* Adds the join on the left side at point p, between a segment that
+ * arrives with direction v_in and one that leaves with direction
+ * v_out.  The current point must be at the end of the first segment's
+ * offset, and the join ends at the start of the second one's.
+ */
+fn add_join (builder: &mut RsvgPathBuilder,
+             p: (f64, f64),
+             v_in: (f64, f64),
+             v_out: (f64, f64),
+             params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+    let turn = cross (v_in, v_out);
+    let cos_turn = dot (v_in, v_out);
+
+    let n_in = left_normal (v_in);
+    let n_out = left_normal (v_out);
+    let end = offset_point (p, n_out, half_width);
+
+    if turn.abs () < DBL_EPSILON {
+        if cos_turn > 0.0 {
+            // The path goes straight through p
+            line_to (builder, end);
+        } else if params.line_join == cairo::LineJoin::Round {
+            // The path turns back on itself; go around the end like a round cap
+            arc_to (builder, p, half_width, n_in, n_out, cross (n_in, v_in));
+        } else {
+            line_to (builder, end);
+        }
+
+        return;
+    }
+
+    if turn > 0.0 {
+        // The path turns to the left, so this is the inner side of the join
+        line_to (builder, p);
+        line_to (builder, end);
+        return;
+    }
+
+    match params.line_join {
+        cairo::LineJoin::Bevel => line_to (builder, end),
+
+        cairo::LineJoin::Round => arc_to (builder, p, half_width, n_in, n_out, -1.0),
+
+        cairo::LineJoin::Miter => {
+            // sin (theta / 2), where theta is the angle between the two segments
+            let sin_half_theta = ((1.0 + cos_turn) / 2.0).sqrt ();
+
+            if sin_half_theta > DBL_EPSILON && 1.0 / sin_half_theta <= params.miter_limit {
+                let bisector = add (n_in, n_out);
+                let len = bisector.0.hypot (bisector.1);
+                let bisector = scale (bisector, 1.0 / len);
+
+                line_to (builder, offset_point (p, bisector, half_width / sin_half_theta));
+            }
+
+            line_to (builder, end);
+        }
+    }
+}
+
+/* Adds a cap at point p, for a segment whose direction at p, pointing
+ * away from the path, is v.  The cap goes from the left side of the
+ * segment to its right side, as seen in the direction of v.
+ */
+fn add_cap (builder: &mut RsvgPathBuilder, p: (f64, f64), v: (f64, f64), params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+    let n = left_normal (v);
+    let right = scale (n, -1.0);
+
+    match params.line_cap {
+        cairo::LineCap::Butt => line_to (builder, offset_point (p, right, half_width)),
+
+        cairo::LineCap::Round => arc_to (builder, p, half_width, n, right, cross (n, v)),
+
+        cairo::LineCap::Square => {
+            let q = offset_point (p, v, half_width);
+
+            line_to (builder, offset_point (q, n, half_width));
+            line_to (builder, offset_point (q, right, half_width));
+            line_to (builder, offset_point (p, right, half_width));
+        }
+    }
+}
+
+/***** Subpaths *****/
+
+fn reverse_segment (segment: &PathSegment) -> PathSegment {
+    match *segment {
+        PathSegment::Line (p1, p2)         => PathSegment::Line (p2, p1),
+        PathSegment::Curve (p1, p2, p3, p4) => PathSegment::Curve (p4, p3, p2, p1)
+    }
+}
+
+// Goes along the left side of the segments, with the joins between them
+fn add_side (builder: &mut RsvgPathBuilder, segments: &[PathSegment], params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+
+    for (i, segment) in segments.iter ().enumerate () {
+        if i > 0 {
+            let prev = &segments[i - 1];
+
+            add_join (builder,
+                      prev.end (),
+                      prev.tangent_at (1.0).unwrap (),
+                      segment.tangent_at (0.0).unwrap (),
+                      params);
+        }
+
+        add_offset_segment (builder, segment, half_width);
+    }
+}
+
+fn move_to_side_start (builder: &mut RsvgPathBuilder, segment: &PathSegment, half_width: f64) {
+    let start = offset_point (segment.start (), left_normal (segment.tangent_at (0.0).unwrap ()), half_width);
+    builder.move_to (start.0, start.1);
+}
+
+fn add_subpath_outline (builder: &mut RsvgPathBuilder, subpath: &Subpath, params: &StrokeParams) {
+    let half_width = params.width / 2.0;
+
+    // Zero-length segments are not drawn, and they don't take part in joins
+    let forward: Vec<PathSegment> = subpath.segments
+        .iter ()
+        .filter (|s| s.tangent_at (0.0).is_some ())
+        .cloned ()
+        .collect ();
+
+    if forward.is_empty () {
+        // A subpath of zero length gets drawn as a dot or as an axis-aligned square
+        let p = subpath.segments[0].start ();
+
+        match params.line_cap {
+            cairo::LineCap::Butt => (),
+
+            cairo::LineCap::Round => {
+                builder.move_to (p.0 + half_width, p.1);
+                arc_to (builder, p, half_width, (1.0, 0.0), (-1.0, 0.0), 1.0);
+                arc_to (builder, p, half_width, (-1.0, 0.0), (1.0, 0.0), 1.0);
+                builder.close_path ();
+            },
+
+            cairo::LineCap::Square => {
+                builder.move_to (p.0 - half_width, p.1 - half_width);
+                builder.line_to (p.0 + half_width, p.1 - half_width);
+                builder.line_to (p.0 + half_width, p.1 + half_width);
+                builder.line_to (p.0 - half_width, p.1 + half_width);
+                builder.close_path ();
+            }
+        }
+
+        return;
+    }
+
+    let backward: Vec<PathSegment> = forward.iter ().rev ().map (reverse_segment).collect ();
+
+    let first = &forward[0];
+    let last = &forward[forward.len () - 1];
+
+    if subpath.closed {
+        for side in &[&forward, &backward] {
+            let first = &side[0];
+            let last = &side[side.len () - 1];
+
+            move_to_side_start (builder, first, half_width);
+            add_side (builder, side, params);
+            add_join (builder, last.end (), last.tangent_at (1.0).unwrap (), first.tangent_at (0.0).unwrap (), params);
+            builder.close_path ();
+        }
+    } else {
+        let v_start = first.tangent_at (0.0).unwrap ();
+
+        move_to_side_start (builder, first, half_width);
+        add_side (builder, &forward, params);
+        add_cap (builder, last.end (), last.tangent_at (1.0).unwrap (), params);
+        add_side (builder, &backward, params);
+        add_cap (builder, first.start (), scale (v_start, -1.0), params);
+        builder.close_path ();
+    }
+}
+
+/***** Dashes *****/
+
+// The piece of the segment between two distances from its start
+fn segment_between (segment: &PathSegment, from: f64, to: f64) -> PathSegment {
+    let t0 = segment.t_at_length (from);
+    let t1 = segment.t_at_length (to);
+
+    if t1 <= 0.0 {
+        let p = segment.start ();
+        return PathSegment::Line (p, p);
+    }
+
+    let (head, _) = segment.split (t1);
+    let (_, piece) = head.split (t0 / t1);
+
+    piece
+}
+
+/* Splits a subpath into the open subpaths for its dashes.  Like in
+ * cairo, if a closed subpath begins and ends inside a dash, the dash
+ * continues through the start of the subpath, with a join there.
+ */
+fn dash_subpath (subpath: &Subpath, dashes: &Dashes) -> Vec<Subpath> {
+    let lengths = &dashes.lengths;
+    let total: f64 = lengths.iter ().sum ();
+
+    // Find where in the pattern the subpath starts
+    let mut index = 0;
+    let mut remaining = dashes.offset % total;
+
+    if remaining < 0.0 {
+        remaining += total;
+    }
+
+    while remaining >= lengths[index] {
+        remaining -= lengths[index];
+        index = (index + 1) % lengths.len ();
+    }
+
+    remaining = lengths[index] - remaining;
+
+    let starts_on = index % 2 == 0;
+
+    let mut pieces = Vec::new ();
+    let mut current: Vec<PathSegment> = Vec::new ();
+
+    for segment in &subpath.segments {
+        let length = segment.length ();
+        let mut consumed = 0.0;
+
+        loop {
+            let on = index % 2 == 0;
+            let step = remaining.min (length - consumed);
+
+            if on {
+                current.push (segment_between (segment, consumed, consumed + step));
+            }
+
+            consumed += step;
+            remaining -= step;
+
+            if remaining > 0.0 {
+                break;
+            }
+
+            if on {
+                pieces.push (Subpath { segments: current, closed: false });
+                current = Vec::new ();
+            }
+
+            index = (index + 1) % lengths.len ();
+            remaining = lengths[index];
+
+            if consumed >= length && remaining > 0.0 {
+                break;
+            }
+        }
+    }
+
+    if !current.is_empty () {
+        if subpath.closed && starts_on && !pieces.is_empty () {
+            let first = pieces.remove (0);
+            current.extend (first.segments);
+        }
+
+        pieces.push (Subpath { segments: current, closed: false });
+    }
+
+    pieces
+}
+
+// Dash patterns that add up to less than this fraction of the length
+// of a subpath are too fine to be seen, and get stroked as solid lines
+const MIN_DASH_PATTERN: f64 = 1e-6;
+
+// Upper limit for the number of dashes and gaps in a subpath
+const MAX_DASHES: f64 = 100_000.0;
+
+fn should_dash (subpath: &Subpath, dashes: &Dashes) -> bool {
+    let total: f64 = dashes.lengths.iter ().sum ();
+    let length: f64 = subpath.segments.iter ().map (|s| s.length ()).sum ();
+
+    total >= length * MIN_DASH_PATTERN && length / total * dashes.lengths.len () as f64 <= MAX_DASHES
+}
+
+// Per the spec, dash arrays with negative values or that add up to zero disable dashing
+fn dashes_are_valid (dashes: &Dashes) -> bool {
+    !dashes.lengths.is_empty ()
+        && dashes.lengths.iter ().all (|&l| l >= 0.0)
+        && dashes.lengths.iter ().sum::<f64> () > 0.0
+}
+
+/// Returns the outline of the area covered by stroking the path with
+/// the specified parameters and dashes.  The outline must be filled
+/// with the nonzero fill rule.
+pub fn path_builder_stroke_to_path (builder: &RsvgPathBuilder,
+                                    params: &StrokeParams,
+                                    dashes: Option<&Dashes>) -> RsvgPathBuilder {
+    let mut outline = RsvgPathBuilder::new ();
+
+    if params.width <= 0.0 {
+        return outline;
+    }
+
+    // An odd number of dashes gets repeated to yield an even number
+    let dashes = dashes
+        .filter (|d| dashes_are_valid (d))
+        .map (|d| {
+            let mut lengths = d.lengths.clone ();
+
+            if lengths.len () % 2 == 1 {
+                lengths.extend (d.lengths.iter ().cloned ());
+            }
+
+            Dashes { lengths: lengths, offset: d.offset }
+        });
+
+    for subpath in path_builder_to_subpaths (builder) {
+        match dashes {
+            Some (ref dashes) if should_dash (&subpath, dashes) => {
+                for piece in dash_subpath (&subpath, dashes) {
+                    add_subpath_outline (&mut outline, &piece, params);
+                }
+            },
+
+            _ => add_subpath_outline (&mut outline, &subpath, params)
+        }
+    }
+
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bbox::{path_builder_fill_bbox, path_builder_stroke_bbox};
+
+    fn stroke (width: f64, line_cap: cairo::LineCap, line_join: cairo::LineJoin) -> StrokeParams {
+        StrokeParams {
+            width:       width,
+            line_cap:    line_cap,
+            line_join:   line_join,
+            miter_limit: 4.0
+        }
+    }
+
+    fn count_subpaths (builder: &RsvgPathBuilder) -> usize {
+        builder.get_path_commands ().iter ()
+            .filter (|c| match **c { PathCommand::MoveTo (..) => true, _ => false })
+            .count ()
+    }
+
+    fn assert_same_bbox (a: cairo::Rectangle, b: cairo::Rectangle, tolerance: f64) {
+        assert! ((a.x - b.x).abs () < tolerance
+                 && (a.y - b.y).abs () < tolerance
+                 && (a.width - b.width).abs () < tolerance
+                 && (a.height - b.height).abs () < tolerance,
+                 "got ({}, {}, {}, {}), expected ({}, {}, {}, {})",
+                 a.x, a.y, a.width, a.height, b.x, b.y, b.width, b.height);
+    }
+
+    fn paths () -> Vec<RsvgPathBuilder> {
+        let mut open = RsvgPathBuilder::new ();
+        open.move_to (0.0, 0.0);
+        open.line_to (10.0, 0.0);
+        open.line_to (10.0, 10.0);
+        open.line_to (20.0, 0.0);
+
+        let mut triangle = RsvgPathBuilder::new ();
+        triangle.move_to (0.0, 0.0);
+        triangle.line_to (10.0, 0.0);
+        triangle.line_to (0.0, 10.0);
+        triangle.close_path ();
+
+        let mut curve = RsvgPathBuilder::new ();
+        curve.move_to (0.0, 0.0);
+        curve.curve_to (0.0, 20.0, 20.0, 20.0, 20.0, 0.0);
+
+        vec! [open, triangle, curve]
+    }
+
+    #[test]
+    fn outline_covers_the_stroke_bbox () {
+        let caps = [cairo::LineCap::Butt, cairo::LineCap::Round, cairo::LineCap::Square];
+        let joins = [cairo::LineJoin::Miter, cairo::LineJoin::Round, cairo::LineJoin::Bevel];
+
+        for path in paths () {
+            for &cap in &caps {
+                for &join in &joins {
+                    let params = stroke (3.0, cap, join);
+                    let outline = path_builder_stroke_to_path (&path, &params, None);
+
+                    assert_same_bbox (path_builder_fill_bbox (&outline).unwrap (),
+                                      path_builder_stroke_bbox (&path, &params).unwrap (),
+                                      1e-2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn open_subpath_has_one_outline_and_closed_subpath_has_two () {
+        let params = stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter);
+        let paths = paths ();
+
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&paths[0], &params, None)), 1);
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&paths[1], &params, None)), 2);
+    }
+
+    #[test]
+    fn outline_of_line_is_rectangle () {
+        let mut path = RsvgPathBuilder::new ();
+        path.move_to (0.0, 0.0);
+        path.line_to (10.0, 0.0);
+
+        let outline = path_builder_stroke_to_path (&path, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter), None);
+
+        assert_eq! (outline.get_path_commands (),
+                    &[PathCommand::MoveTo (0.0, 1.0),
+                      PathCommand::LineTo (10.0, 1.0),
+                      PathCommand::LineTo (10.0, -1.0),
+                      PathCommand::LineTo (0.0, -1.0),
+                      PathCommand::LineTo (0.0, 1.0),
+                      PathCommand::ClosePath]);
+    }
+
+    #[test]
+    fn zero_length_subpath_gets_a_dot () {
+        let mut path = RsvgPathBuilder::new ();
+        path.move_to (5.0, 5.0);
+        path.close_path ();
+
+        let butt = path_builder_stroke_to_path (&path, &stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter), None);
+        assert! (butt.get_path_commands ().is_empty ());
+
+        let round = path_builder_stroke_to_path (&path, &stroke (2.0, cairo::LineCap::Round, cairo::LineJoin::Miter), None);
+        assert_same_bbox (path_builder_fill_bbox (&round).unwrap (),
+                          cairo::Rectangle { x: 4.0, y: 4.0, width: 2.0, height: 2.0 },
+                          1e-9);
+    }
+
+    #[test]
+    fn dashes_split_subpaths () {
+        let mut path = RsvgPathBuilder::new ();
+        path.move_to (0.0, 0.0);
+        path.line_to (20.0, 0.0);
+
+        let params = stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter);
+
+        // dashes at [0, 4], [6, 10], [12, 16], [18, 20]
+        let dashes = Dashes { lengths: vec! [4.0, 2.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&path, &params, Some (&dashes))), 4);
+
+        // an odd number of lengths gets repeated: dashes at [0, 5], [15, 20]
+        let dashes = Dashes { lengths: vec! [5.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&path, &params, Some (&dashes))), 2);
+
+        // the offset shifts the pattern: dashes at [0, 1], [3, 7], ...
+        let dashes = Dashes { lengths: vec! [4.0, 2.0], offset: 3.0 };
+        let outline = path_builder_stroke_to_path (&path, &params, Some (&dashes));
+        assert_eq! (outline.get_path_commands ()[1], PathCommand::LineTo (1.0, 1.0));
+
+        // invalid patterns disable dashing
+        let dashes = Dashes { lengths: vec! [0.0, 0.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&path, &params, Some (&dashes))), 1);
+    }
+
+    #[test]
+    fn tiny_dashes_stroke_as_solid () {
+        let mut path = RsvgPathBuilder::new ();
+        path.move_to (0.0, 0.0);
+        path.line_to (1e6, 0.0);
+
+        let params = stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter);
+
+        // below the minimum pattern length for the path
+        let dashes = Dashes { lengths: vec! [1e-7, 1e-7], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&path, &params, Some (&dashes))), 1);
+
+        // long enough to see, but too many dashes
+        let dashes = Dashes { lengths: vec! [1.0, 1.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&path, &params, Some (&dashes))), 1);
+
+        // fewer dashes get drawn
+        let dashes = Dashes { lengths: vec! [100.0, 100.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&path, &params, Some (&dashes))), 5000);
+    }
+
+    #[test]
+    fn dash_continues_through_start_of_closed_subpath () {
+        let mut square = RsvgPathBuilder::new ();
+        square.move_to (0.0, 0.0);
+        square.line_to (10.0, 0.0);
+        square.line_to (10.0, 10.0);
+        square.line_to (0.0, 10.0);
+        square.close_path ();
+
+        let params = stroke (2.0, cairo::LineCap::Butt, cairo::LineJoin::Miter);
+
+        // dashes at [0, 5], [10, 15], [20, 25], [30, 35]
+        let dashes = Dashes { lengths: vec! [5.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&square, &params, Some (&dashes))), 4);
+
+        // dashes at [0, 5], [12, 17], [24, 29], [36, 40]; the last one continues into the first
+        let dashes = Dashes { lengths: vec! [5.0, 7.0], offset: 0.0 };
+        assert_eq! (count_subpaths (&path_builder_stroke_to_path (&square, &params, Some (&dashes))), 3);
+    }
+}
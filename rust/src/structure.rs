@@ -3,7 +3,6 @@ use ::libc;
 
 use std::cell::RefCell;
 use std::cell::Cell;
-use std::ptr;
 
 use cairo::MatrixTrait;
 
@@ -89,7 +88,7 @@ impl NodeTrait for NodeSwitch {
         drawing_ctx::push_discrete_layer (draw_ctx);
 
         for child in &*node.children.borrow () {
-            if drawing_ctx::state_get_cond_true (child.get_state ()) {
+            if drawing_ctx::state_get_cond_true (child.get_state ()) && is_renderable_in_switch (child) {
                 let boxed_child = box_node (child.clone ());
 
                 drawing_ctx::draw_node_from_stack (draw_ctx, boxed_child, 0);
@@ -108,6 +107,12 @@ impl NodeTrait for NodeSwitch {
     }
 }
 
+// A <foreignObject> whose content we can't render is skipped by <switch>
+// so that the next child can act as a fallback.
+fn is_renderable_in_switch (node: &RsvgNode) -> bool {
+    node.get_impl::<NodeForeignObject> ().map_or (true, |fo| fo.supported.get ())
+}
+
 /***** NodeSvg *****/
 
 struct NodeSvg {
@@ -116,8 +121,7 @@ struct NodeSvg {
     y:                     Cell<RsvgLength>,
     w:                     Cell<RsvgLength>,
     h:                     Cell<RsvgLength>,
-    vbox:                  Cell<Option<ViewBox>>,
-    atts:                  Cell<*mut RsvgPropertyBag>
+    vbox:                  Cell<Option<ViewBox>>
 }
 
 impl NodeSvg {
@@ -128,8 +132,7 @@ impl NodeSvg {
             y:                     Cell::new (RsvgLength::parse ("0", LengthDir::Vertical).unwrap ()),
             w:                     Cell::new (RsvgLength::parse ("100%", LengthDir::Horizontal).unwrap ()),
             h:                     Cell::new (RsvgLength::parse ("100%", LengthDir::Vertical).unwrap ()),
-            vbox:                  Cell::new (None),
-            atts:                  Cell::new (ptr::null_mut ())
+            vbox:                  Cell::new (None)
         }
     }
 }
@@ -159,10 +162,6 @@ impl NodeTrait for NodeSvg {
 
         self.vbox.set (property_bag::parse_or_none (pbag, "viewBox", (), None)?);
 
-        // The "style" sub-element is not loaded yet here, so we need
-        // to store other attributes to be applied later.
-        self.atts.set (property_bag::dup (pbag));
-
         Ok (())
     }
 
@@ -233,16 +232,6 @@ impl NodeTrait for NodeSvg {
     }
 }
 
-impl Drop for NodeSvg {
-    fn drop (&mut self) {
-        let pbag = self.atts.get ();
-
-        if !pbag.is_null () {
-            property_bag::free (pbag);
-        }
-    }
-}
-
 /***** NodeUse *****/
 
 struct NodeUse {
@@ -422,6 +411,99 @@ impl NodeTrait for NodeSymbol {
     }
 }
 
+/***** NodeForeignObject *****/
+
+// A block of XHTML text, as collected by the foreignObject handler in rsvg-base.c
+struct Paragraph {
+    text:          String,
+    heading_level: u32
+}
+
+struct NodeForeignObject {
+    x:          Cell<RsvgLength>,
+    y:          Cell<RsvgLength>,
+    w:          Cell<RsvgLength>,
+    h:          Cell<RsvgLength>,
+    paragraphs: RefCell<Vec<Paragraph>>,
+    supported:  Cell<bool>
+}
+
+impl NodeForeignObject {
+    fn new () -> NodeForeignObject {
+        NodeForeignObject {
+            x:          Cell::new (RsvgLength::default ()),
+            y:          Cell::new (RsvgLength::default ()),
+            w:          Cell::new (RsvgLength::default ()),
+            h:          Cell::new (RsvgLength::default ()),
+            paragraphs: RefCell::new (Vec::new ()),
+            supported:  Cell::new (true)
+        }
+    }
+}
+
+impl NodeTrait for NodeForeignObject {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, pbag: *const RsvgPropertyBag) -> NodeResult {
+        self.x.set (property_bag::parse_or_default (pbag, "x", LengthDir::Horizontal, None)?);
+        self.y.set (property_bag::parse_or_default (pbag, "y", LengthDir::Vertical, None)?);
+
+        self.w.set (property_bag::parse_or_default (pbag, "width", LengthDir::Horizontal,
+                                                    Some(RsvgLength::check_nonnegative))?);
+
+        self.h.set (property_bag::parse_or_default (pbag, "height", LengthDir::Vertical,
+                                                    Some(RsvgLength::check_nonnegative))?);
+
+        Ok (())
+    }
+
+    fn draw (&self, node: &RsvgNode, draw_ctx: *const RsvgDrawingCtx, dominate: i32) {
+        if !self.supported.get () {
+            return;
+        }
+
+        let nx = self.x.get ().normalize (draw_ctx);
+        let ny = self.y.get ().normalize (draw_ctx);
+        let nw = self.w.get ().normalize (draw_ctx);
+        let nh = self.h.get ().normalize (draw_ctx);
+
+        // width or height set to 0 disables rendering of the element
+        // https://www.w3.org/TR/SVG/extend.html#ForeignObjectElementWidthAttribute
+        if double_equals (nw, 0.0) || double_equals (nh, 0.0) {
+            return;
+        }
+
+        drawing_ctx::state_reinherit_top (draw_ctx, node.get_state (), dominate);
+
+        let state = drawing_ctx::get_current_state (draw_ctx);
+
+        drawing_ctx::push_discrete_layer (draw_ctx);
+
+        if !drawing_ctx::state_is_overflow (state) {
+            drawing_ctx::add_clipping_rect (draw_ctx, nx, ny, nw, nh);
+        }
+
+        let mut y = ny;
+
+        for paragraph in &*self.paragraphs.borrow () {
+            if y >= ny + nh {
+                break;
+            }
+
+            let text = paragraph.text.to_glib_none ();
+
+            y += unsafe { rsvg_text_render_paragraph (draw_ctx,
+                                                      text.0,
+                                                      paragraph.heading_level,
+                                                      nx, y, nw) };
+        }
+
+        drawing_ctx::pop_discrete_layer (draw_ctx);
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
 /***** C Prototypes *****/
 
 #[no_mangle]
@@ -466,6 +548,41 @@ pub extern fn rsvg_node_symbol_new (_: *const libc::c_char, raw_parent: *const R
                     Box::new (NodeSymbol::new ()))
 }
 
+#[no_mangle]
+pub extern fn rsvg_node_foreign_object_new (_: *const libc::c_char, raw_parent: *const RsvgNode) -> *const RsvgNode {
+    boxed_node_new (NodeType::ForeignObject,
+                    raw_parent,
+                    Box::new (NodeForeignObject::new ()))
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_foreign_object_add_paragraph (raw_node:      *const RsvgNode,
+                                                      text:          *const libc::c_char,
+                                                      heading_level: u32) {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    assert! (!text.is_null ());
+    let text = unsafe { String::from_glib_none (text) };
+
+    node.with_impl (|fo: &NodeForeignObject| {
+        fo.paragraphs.borrow_mut ().push (Paragraph {
+            text:          text,
+            heading_level: heading_level
+        });
+    });
+}
+
+#[no_mangle]
+pub extern fn rsvg_node_foreign_object_set_unsupported (raw_node: *const RsvgNode) {
+    assert! (!raw_node.is_null ());
+    let node: &RsvgNode = unsafe { & *raw_node };
+
+    node.with_impl (|fo: &NodeForeignObject| {
+        fo.supported.set (false);
+    });
+}
+
 #[no_mangle]
 pub extern fn rsvg_node_svg_get_size (raw_node: *const RsvgNode, out_width: *mut RsvgLength, out_height: *mut RsvgLength) {
     assert! (!raw_node.is_null ());
@@ -497,35 +614,11 @@ pub extern fn rsvg_node_svg_get_view_box (raw_node: *const RsvgNode) -> RsvgView
 }
 
 extern "C" {
-    fn rsvg_parse_style_attrs (handle: *const RsvgHandle,
-                               node:   *const RsvgNode,
-                               tag:    *const libc::c_char,
-                               class:  *const libc::c_char,
-                               id:     *const libc::c_char,
-                               pbag:   *const RsvgPropertyBag);
+    fn rsvg_text_render_paragraph (draw_ctx:      *const RsvgDrawingCtx,
+                                   text:          *const libc::c_char,
+                                   heading_level: u32,
+                                   x:             f64,
+                                   y:             f64,
+                                   width:         f64) -> f64;
 }
 
-#[no_mangle]
-pub extern fn rsvg_node_svg_apply_atts (raw_node: *const RsvgNode, handle: *const RsvgHandle) {
-    assert! (!raw_node.is_null ());
-    let node: &RsvgNode = unsafe { & *raw_node };
-
-    node.with_impl (|svg: &NodeSvg| {
-        let pbag = svg.atts.get ();
-
-        if !pbag.is_null () {
-            let class = property_bag::lookup (pbag, "class");
-            let id = property_bag::lookup (pbag, "id");
-
-            let c_class = class.to_glib_none ();
-            let c_id = id.to_glib_none ();
-
-            unsafe { rsvg_parse_style_attrs (handle,
-                                             raw_node,
-                                             str::to_glib_none ("svg").0,
-                                             c_class.0,
-                                             c_id.0,
-                                             pbag); }
-        }
-    });
-}
@@ -0,0 +1,41 @@
+// Fixtures shared by the unit tests
+
+use std::ptr;
+use std::rc::Rc;
+
+use drawing_ctx::RsvgDrawingCtx;
+use handle::RsvgHandle;
+use node::*;
+use property_bag::RsvgPropertyBag;
+
+// A node that does nothing, for tests that only need the tree
+pub struct TestNodeImpl {}
+
+impl NodeTrait for TestNodeImpl {
+    fn set_atts (&self, _: &RsvgNode, _: *const RsvgHandle, _: *const RsvgPropertyBag) -> NodeResult {
+        Ok (())
+    }
+
+    fn draw (&self, _: &RsvgNode, _: *const RsvgDrawingCtx, _: i32) {
+    }
+
+    fn get_c_impl (&self) -> *const RsvgCNodeImpl {
+        unreachable! ();
+    }
+}
+
+// Creates an element with the given attributes, and appends it to @parent
+pub fn element (node_type: NodeType, parent: Option<&RsvgNode>, name: &str, atts: &[(&str, &str)]) -> RsvgNode {
+    let node = Rc::new (Node::new (node_type,
+                                   parent.map (|p| Rc::downgrade (p)),
+                                   ptr::null_mut (),
+                                   Box::new (TestNodeImpl {})));
+
+    node.set_element (name, atts.iter ().map (|&(k, v)| (k.to_string (), v.to_string ())).collect ());
+
+    if let Some (p) = parent {
+        p.add_child (&node);
+    }
+
+    node
+}
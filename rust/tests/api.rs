@@ -0,0 +1,157 @@
+// Tests for the public Rust API.  It wraps the C RsvgHandle, so these
+// link against the librsvg that "make check" builds; see check-local
+// in the toplevel Makefile.am.
+
+extern crate cairo;
+extern crate rsvg_internals;
+
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use rsvg_internals::{Loader, LoadingError, Renderer, RenderingError};
+
+#[link(name = "rsvg-2")]
+extern "C" {}
+
+const VALID_SVG: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"10\">\
+                         <rect id=\"left\" width=\"10\" height=\"10\" fill=\"lime\"/>\
+                         <rect id=\"right\" x=\"10\" width=\"10\" height=\"10\" fill=\"blue\"/>\
+                         </svg>";
+
+fn fixture (name: &str) -> PathBuf {
+    let mut path = PathBuf::from (env! ("CARGO_MANIFEST_DIR"));
+    path.push ("../tests/fixtures");
+    path.push (name);
+    path
+}
+
+fn image_surface (width: i32, height: i32) -> cairo::ImageSurface {
+    cairo::ImageSurface::create (cairo::Format::ARgb32, width, height).unwrap ()
+}
+
+// Pixels are premultiplied ARGB in native endianness
+fn pixel (surface: &mut cairo::ImageSurface, x: usize, y: usize) -> u32 {
+    let stride = surface.get_stride () as usize;
+    let data = surface.get_data ().unwrap ();
+    let offset = y * stride + x * 4;
+
+    unsafe { *(data[offset..].as_ptr () as *const u32) }
+}
+
+#[test]
+fn loads_from_bytes () {
+    let handle = Loader::new ().read_bytes (VALID_SVG.as_bytes ()).unwrap ();
+
+    assert_eq! (handle.dimensions (), (20, 10));
+    assert! (handle.has_element_with_id ("#left"));
+    assert! (!handle.has_element_with_id ("#nonexistent"));
+}
+
+#[test]
+fn loads_from_reader () {
+    let handle = Loader::new ().read (Cursor::new (VALID_SVG)).unwrap ();
+
+    assert_eq! (handle.dimensions (), (20, 10));
+}
+
+#[test]
+fn loads_from_path () {
+    let handle = Loader::new ().read_path (fixture ("dimensions/bug608102.svg")).unwrap ();
+
+    assert_eq! (handle.dimensions (), (16, 16));
+}
+
+#[test]
+fn reports_missing_files () {
+    match Loader::new ().read_path (fixture ("nonexistent.svg")) {
+        Err (LoadingError::Io (_)) => (),
+        r => panic! ("unexpected result {:?}", r.err ())
+    }
+}
+
+#[test]
+fn reports_malformed_xml () {
+    match Loader::new ().read_bytes (b"<svg xmlns=\"http://www.w3.org/2000/svg\"><rect></svg>") {
+        Err (LoadingError::Xml (_)) => (),
+        r => panic! ("unexpected result {:?}", r.err ())
+    }
+}
+
+#[test]
+fn reports_non_svg_documents () {
+    match Loader::new ().read_bytes (b"<html><body/></html>") {
+        Err (LoadingError::NoSvgRoot) => (),
+        r => panic! ("unexpected result {:?}", r.err ())
+    }
+}
+
+#[test]
+fn strict_loading_reports_invalid_attributes () {
+    let svg = b"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\
+                <path id=\"p\" d=\"M 10 20 L 30\"/>\
+                </svg>";
+
+    match Loader::new ().strict (true).read_bytes (svg) {
+        Err (LoadingError::InvalidAttribute (diagnostic)) => {
+            assert_eq! (diagnostic.element, "path");
+            assert_eq! (diagnostic.id, Some ("p".to_string ()));
+            assert_eq! (diagnostic.attribute, Some ("d".to_string ()));
+        },
+
+        r => panic! ("unexpected result {:?}", r.err ())
+    }
+
+    let handle = Loader::new ().read_bytes (svg).unwrap ();
+    assert_eq! (handle.diagnostics ().len (), 1);
+}
+
+#[test]
+fn renders_document () {
+    let handle = Loader::new ().read_bytes (VALID_SVG.as_bytes ()).unwrap ();
+    let mut surface = image_surface (20, 10);
+
+    {
+        let cr = cairo::Context::new (&surface);
+        Renderer::new (&handle).render (&cr).unwrap ();
+    }
+
+    assert_eq! (pixel (&mut surface, 5, 5), 0xff00ff00);
+    assert_eq! (pixel (&mut surface, 15, 5), 0xff0000ff);
+}
+
+#[test]
+fn renders_single_element () {
+    let handle = Loader::new ().read_bytes (VALID_SVG.as_bytes ()).unwrap ();
+    let mut surface = image_surface (20, 10);
+
+    {
+        let cr = cairo::Context::new (&surface);
+        Renderer::new (&handle).render_element (&cr, "#right").unwrap ();
+    }
+
+    assert_eq! (pixel (&mut surface, 5, 5), 0);
+    assert_eq! (pixel (&mut surface, 15, 5), 0xff0000ff);
+}
+
+#[test]
+fn reports_invalid_element_ids () {
+    let handle = Loader::new ().read_bytes (VALID_SVG.as_bytes ()).unwrap ();
+    let surface = image_surface (20, 10);
+    let cr = cairo::Context::new (&surface);
+
+    assert_eq! (Renderer::new (&handle).render_element (&cr, "#nonexistent"),
+                Err (RenderingError::InvalidId ("#nonexistent".to_string ())));
+}
+
+#[test]
+fn reports_cairo_errors () {
+    let handle = Loader::new ().read_bytes (VALID_SVG.as_bytes ()).unwrap ();
+    let surface = image_surface (20, 10);
+    let cr = cairo::Context::new (&surface);
+
+    // Leaves the context in an error state
+    cr.restore ();
+
+    assert_eq! (Renderer::new (&handle).render (&cr),
+                Err (RenderingError::Cairo (cairo::Status::InvalidRestore)));
+}
@@ -0,0 +1,109 @@
+/* -*- Mode: C; tab-width: 4; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set ts=4 nowrap ai expandtab sw=4: */
+
+#include <glib.h>
+#include "rsvg.h"
+#include "rsvg-compat.h"
+#include "test-utils.h"
+
+/* Samples one pixel of fixtures/render-crash/animation.svg rendered at a
+ * given document time.  The probed rectangle has these animations:
+ *
+ *   x:          10 to 100 over 2s, then frozen
+ *   width:      50, 80 at 0.3s, 20 at 1s; repeats every second
+ *   fill:       red to blue from 0.25s to 1.25s, then back to red
+ *   visibility: hidden from 5s on
+ *
+ * Another rectangle gets its fill from a style sheet rule, and from a
+ * <set> from 1s on.
+ */
+typedef struct _FixtureData
+{
+    const gchar *test_name;
+    gdouble time;               /* negative to not call rsvg_handle_set_time() */
+    gint x;
+    gint y;
+    guint32 pixel;              /* ARGB32 */
+} FixtureData;
+
+static guint32
+get_pixel (cairo_surface_t *surface, gint x, gint y)
+{
+    guchar *data;
+
+    cairo_surface_flush (surface);
+    data = cairo_image_surface_get_data (surface);
+    data += y * cairo_image_surface_get_stride (surface) + x * 4;
+
+    return *(guint32 *) data;
+}
+
+static void
+test_animation (FixtureData *fixture)
+{
+    RsvgHandle *handle;
+    RsvgDimensionData dimensions;
+    cairo_surface_t *surface;
+    cairo_t *cr;
+    gchar *target_file;
+    GError *error = NULL;
+
+    target_file = g_build_filename (test_utils_get_test_data_path (),
+                                    "render-crash", "animation.svg", NULL);
+    handle = rsvg_handle_new_from_file (target_file, &error);
+    g_free (target_file);
+    g_assert_no_error (error);
+
+    if (fixture->time >= 0.0)
+        rsvg_handle_set_time (handle, fixture->time);
+
+    rsvg_handle_get_dimensions (handle, &dimensions);
+    surface = cairo_image_surface_create (CAIRO_FORMAT_ARGB32,
+                                          dimensions.width, dimensions.height);
+    cr = cairo_create (surface);
+    g_assert (rsvg_handle_render_cairo (handle, cr));
+
+    g_assert_cmphex (get_pixel (surface, fixture->x, fixture->y), ==, fixture->pixel);
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (surface);
+    g_object_unref (handle);
+}
+
+static FixtureData fixtures[] =
+{
+    {"/animation/no time/static rect", -1.0, 35, 35, 0xffff0000},
+    {"/animation/no time/nothing to the right", -1.0, 80, 35, 0x00000000},
+    {"/animation/0.5s/moved right", 0.5, 20, 35, 0x00000000},
+    {"/animation/0.5s/color and width interpolated", 0.5, 90, 35, 0xffbf0040},
+    {"/animation/0.5s/width below 80", 0.5, 100, 35, 0x00000000},
+    {"/animation/1.5s/color removed", 1.5, 100, 35, 0xffff0000},
+    {"/animation/1.5s/moved further", 1.5, 70, 35, 0x00000000},
+    {"/animation/3s/position frozen", 3.0, 140, 35, 0xffff0000},
+    {"/animation/3s/width restarted", 3.0, 155, 35, 0x00000000},
+    {"/animation/3s/left of frozen position", 3.0, 95, 35, 0x00000000},
+    {"/animation/6s/hidden", 6.0, 140, 35, 0x00000000},
+    {"/animation/no time/style sheet rule", -1.0, 160, 185, 0xffff0000},
+    {"/animation/1.5s/set overrides style sheet rule", 1.5, 160, 185, 0xff0000ff},
+};
+
+static const gint n_fixtures = G_N_ELEMENTS (fixtures);
+
+int
+main (int argc, char *argv[])
+{
+    gint i;
+    int result;
+
+    RSVG_G_TYPE_INIT;
+    g_test_init (&argc, &argv, NULL);
+
+    for (i = 0; i < n_fixtures; i++)
+        g_test_add_data_func (fixtures[i].test_name, &fixtures[i], (void*)test_animation);
+
+    result = g_test_run ();
+
+    rsvg_cleanup ();
+
+    return result;
+}
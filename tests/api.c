@@ -0,0 +1,480 @@
+/* -*- Mode: C; tab-width: 4; indent-tabs-mode: nil; c-basic-offset: 4 -*- */
+/* vim: set ts=4 nowrap ai expandtab sw=4: */
+
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+#include <glib.h>
+#include <glib/gstdio.h>
+#include "rsvg.h"
+#include "rsvg-compat.h"
+#include "test-utils.h"
+
+/* Line 3 has an invalid path and line 4 a circle with a negative radius */
+static const char *invalid_svg =
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"100\">\n"
+    "  <rect width=\"10\" height=\"10\"/>\n"
+    "  <path id=\"p\" d=\"M 10 20 L 30\"/>\n"
+    "  <circle r=\"-5\"/>\n"
+    "</svg>\n";
+
+static const char *valid_svg =
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"100\">\n"
+    "  <rect width=\"10\" height=\"10\"/>\n"
+    "</svg>\n";
+
+static RsvgHandle *
+load_data (const char *data)
+{
+    RsvgHandle *handle;
+    GError *error = NULL;
+
+    handle = rsvg_handle_new_from_data ((const guint8 *) data, strlen (data), &error);
+    g_assert_no_error (error);
+    g_assert (handle != NULL);
+
+    return handle;
+}
+
+static guint32
+get_pixel (cairo_surface_t *surface, gint x, gint y)
+{
+    guchar *data;
+
+    cairo_surface_flush (surface);
+    data = cairo_image_surface_get_data (surface);
+    data += y * cairo_image_surface_get_stride (surface) + x * 4;
+
+    return *(guint32 *) data;
+}
+
+/* Checks a "line:column: rest" diagnostic; the column has to be the one where
+ * @at first appears in that line of the source, like the attribute's name.
+ */
+static void
+assert_diagnostic (const char *diagnostic, const char *source, int line, const char *at, const char *rest)
+{
+    char **source_lines;
+    const char *found;
+    int diagnostic_line, column, rest_offset = -1;
+
+    g_assert_cmpint (sscanf (diagnostic, "%d:%d: %n", &diagnostic_line, &column, &rest_offset), ==, 2);
+    g_assert_cmpint (rest_offset, >, 0);
+    g_assert_cmpint (diagnostic_line, ==, line);
+    g_assert (g_str_has_prefix (diagnostic + rest_offset, rest));
+
+    source_lines = g_strsplit (source, "\n", -1);
+    g_assert_cmpuint (g_strv_length (source_lines), >=, line);
+    found = strstr (source_lines[line - 1], at);
+    g_assert (found != NULL);
+    g_assert_cmpint (column, ==, found - source_lines[line - 1] + 1);
+    g_strfreev (source_lines);
+}
+
+static void
+test_diagnostics (void)
+{
+    RsvgHandle *handle;
+    char *report;
+    char **lines;
+
+    handle = load_data (invalid_svg);
+    report = rsvg_handle_get_diagnostics (handle);
+    g_assert (report != NULL);
+
+    lines = g_strsplit (report, "\n", -1);
+    g_assert_cmpuint (g_strv_length (lines), ==, 3);
+    assert_diagnostic (lines[0], invalid_svg, 3, "d=", "<path id=\"p\"> attribute \"d\": ");
+    assert_diagnostic (lines[1], invalid_svg, 4, "r=", "<circle> attribute \"r\": ");
+    g_assert_cmpstr (lines[2], ==, "");
+
+    g_strfreev (lines);
+    g_free (report);
+    g_object_unref (handle);
+}
+
+/* The attributes of a start tag that spans several lines, and the invalid
+ * declarations in a style attribute.
+ */
+static const char *located_svg =
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"100\">\n"
+    "  <circle id=\"c\" cx=\"10\"\n"
+    "          r=\"-5\"/>\n"
+    "  <rect id=\"r\" width=\"10\" height=\"10\" style=\"fill: bogus; stroke\"/>\n"
+    "</svg>\n";
+
+static void
+test_diagnostics_locations (void)
+{
+    RsvgHandle *handle;
+    char *report;
+    char **lines;
+
+    handle = load_data (located_svg);
+    report = rsvg_handle_get_diagnostics (handle);
+    g_assert (report != NULL);
+
+    lines = g_strsplit (report, "\n", -1);
+    g_assert_cmpuint (g_strv_length (lines), ==, 4);
+    assert_diagnostic (lines[0], located_svg, 3, "r=", "<circle id=\"c\"> attribute \"r\": ");
+    assert_diagnostic (lines[1], located_svg, 4, "style=",
+                       "<rect id=\"r\"> attribute \"style\": invalid value for property \"fill\"");
+    assert_diagnostic (lines[2], located_svg, 4, "style=",
+                       "<rect id=\"r\"> attribute \"style\": invalid declaration \"stroke\"");
+    g_assert_cmpstr (lines[3], ==, "");
+
+    g_strfreev (lines);
+    g_free (report);
+    g_object_unref (handle);
+}
+
+static void
+test_no_diagnostics (void)
+{
+    RsvgHandle *handle;
+
+    handle = load_data (valid_svg);
+    g_assert (rsvg_handle_get_diagnostics (handle) == NULL);
+    g_object_unref (handle);
+}
+
+static char *
+write_temp_svg (const char *data)
+{
+    char *filename;
+    GError *error = NULL;
+    int fd;
+
+    fd = g_file_open_tmp ("rsvg-api-XXXXXX.svg", &filename, &error);
+    g_assert_no_error (error);
+    close (fd);
+
+    g_file_set_contents (filename, data, -1, &error);
+    g_assert_no_error (error);
+
+    return filename;
+}
+
+/* Runs rsvg-convert --lint on the given data; returns its standard output */
+static char *
+run_lint (const char *data, char **filename, gboolean *success)
+{
+    char *argv[] = { (char *) RSVG_CONVERT, (char *) "--lint", NULL, NULL };
+    char *standard_output;
+    int exit_status;
+    GError *error = NULL;
+
+    *filename = write_temp_svg (data);
+    argv[2] = *filename;
+
+    g_spawn_sync (NULL, argv, NULL, G_SPAWN_STDERR_TO_DEV_NULL, NULL, NULL,
+                  &standard_output, NULL, &exit_status, &error);
+    g_assert_no_error (error);
+
+    *success = g_spawn_check_exit_status (exit_status, NULL);
+
+    return standard_output;
+}
+
+static void
+test_lint (void)
+{
+    char *output, *filename, *prefix;
+    char **lines;
+    gboolean success;
+
+    output = run_lint (invalid_svg, &filename, &success);
+    g_assert (!success);
+
+    lines = g_strsplit (output, "\n", -1);
+    g_assert_cmpuint (g_strv_length (lines), ==, 3);
+
+    prefix = g_strconcat (filename, ":", NULL);
+    g_assert (g_str_has_prefix (lines[0], prefix));
+    g_assert (g_str_has_prefix (lines[1], prefix));
+    assert_diagnostic (lines[0] + strlen (prefix), invalid_svg, 3, "d=", "<path id=\"p\"> attribute \"d\": ");
+    assert_diagnostic (lines[1] + strlen (prefix), invalid_svg, 4, "r=", "<circle> attribute \"r\": ");
+    g_assert_cmpstr (lines[2], ==, "");
+
+    g_free (prefix);
+    g_strfreev (lines);
+    g_free (output);
+    g_unlink (filename);
+    g_free (filename);
+}
+
+static void
+test_lint_valid (void)
+{
+    char *output, *filename;
+    gboolean success;
+
+    output = run_lint (valid_svg, &filename, &success);
+    g_assert (success);
+    g_assert_cmpstr (output, ==, "");
+
+    g_free (output);
+    g_unlink (filename);
+    g_free (filename);
+}
+
+static const char *invalid_view_box_svg =
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"100\" viewBox=\"0 0 foo\">\n"
+    "  <rect width=\"10\" height=\"10\"/>\n"
+    "</svg>\n";
+
+static gboolean
+load_with_flags (const char *data, RsvgHandleFlags flags, GError **error)
+{
+    RsvgHandle *handle;
+    gboolean result;
+
+    handle = rsvg_handle_new_with_flags (flags);
+    g_assert (rsvg_handle_write (handle, (const guchar *) data, strlen (data), NULL));
+    result = rsvg_handle_close (handle, error);
+    g_object_unref (handle);
+
+    return result;
+}
+
+static void
+assert_strict_error (const char *data, const char *attribute)
+{
+    GError *error = NULL;
+
+    g_assert (!load_with_flags (data, RSVG_HANDLE_FLAG_STRICT, &error));
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_INVALID_ATTRIBUTE);
+    g_assert (strstr (error->message, attribute) != NULL);
+    g_error_free (error);
+
+    g_assert (load_with_flags (data, RSVG_HANDLE_FLAGS_NONE, &error));
+    g_assert_no_error (error);
+}
+
+static void
+test_strict_invalid_path (void)
+{
+    assert_strict_error (invalid_svg, "attribute \"d\"");
+}
+
+static void
+test_strict_invalid_view_box (void)
+{
+    assert_strict_error (invalid_view_box_svg, "attribute \"viewBox\"");
+}
+
+static void
+test_strict_valid (void)
+{
+    GError *error = NULL;
+
+    g_assert (load_with_flags (valid_svg, RSVG_HANDLE_FLAG_STRICT, &error));
+    g_assert_no_error (error);
+}
+
+/* The commands before the garbage still render; the second square doesn't */
+static const char *trailing_garbage_svg =
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"100\">\n"
+    "  <path d=\"M 10 10 H 50 V 50 H 10 Z # M 60 60 H 90 V 90 H 60 Z\"/>\n"
+    "</svg>\n";
+
+static void
+test_path_trailing_garbage (void)
+{
+    RsvgHandle *handle;
+    cairo_surface_t *surface;
+    cairo_t *cr;
+
+    assert_strict_error (trailing_garbage_svg, "attribute \"d\"");
+
+    handle = load_data (trailing_garbage_svg);
+
+    surface = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 100, 100);
+    cr = cairo_create (surface);
+    g_assert (rsvg_handle_render_cairo (handle, cr));
+
+    g_assert_cmphex (get_pixel (surface, 30, 30), ==, 0xff000000);
+    g_assert_cmphex (get_pixel (surface, 75, 75), ==, 0x00000000);
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (surface);
+    g_object_unref (handle);
+}
+
+static void
+assert_close (int a, int b)
+{
+    g_assert_cmpint (ABS (a - b), <=, 2);
+}
+
+/* The outlines of each text have to cover about the same area as the text */
+static void
+test_text_to_paths (void)
+{
+    RsvgHandle *handle, *outlined;
+    char *filename, *svg;
+    GError *error = NULL;
+    const char *ids[] = { "#simple", "#rotated", "#fitted" };
+    guint i;
+
+    filename = g_build_filename (test_utils_get_test_data_path (),
+                                 "render-crash", "text-to-paths.svg", NULL);
+    handle = rsvg_handle_new_from_file (filename, &error);
+    g_free (filename);
+    g_assert_no_error (error);
+
+    svg = rsvg_handle_get_svg_with_text_as_paths (handle);
+    g_assert (svg != NULL);
+    g_assert (strstr (svg, "<text") == NULL);
+    g_assert (strstr (svg, "<tspan") == NULL);
+    g_assert (strstr (svg, "<tref") == NULL);
+
+    outlined = rsvg_handle_new_from_data ((const guint8 *) svg, strlen (svg), &error);
+    g_assert_no_error (error);
+    g_free (svg);
+
+    for (i = 0; i < G_N_ELEMENTS (ids); i++) {
+        RsvgDimensionData text_size, path_size;
+        RsvgPositionData text_position, path_position;
+
+        g_assert (rsvg_handle_get_dimensions_sub (handle, &text_size, ids[i]));
+        g_assert (rsvg_handle_get_position_sub (handle, &text_position, ids[i]));
+        g_assert (rsvg_handle_get_dimensions_sub (outlined, &path_size, ids[i]));
+        g_assert (rsvg_handle_get_position_sub (outlined, &path_position, ids[i]));
+
+        g_assert_cmpint (text_size.width, >, 0);
+        g_assert_cmpint (text_size.height, >, 0);
+
+        assert_close (path_position.x, text_position.x);
+        assert_close (path_position.y, text_position.y);
+        assert_close (path_size.width, text_size.width);
+        assert_close (path_size.height, text_size.height);
+    }
+
+    g_object_unref (outlined);
+    g_object_unref (handle);
+}
+
+/* rsvg-convert --text-to-paths writes out the SVG without rendering it,
+ * so it refuses the options that would only apply to a rendering.
+ */
+static void
+test_text_to_paths_options (void)
+{
+    /* NULL stands for a second input file */
+    const char *rejected[] = {
+        "--format=png", "--zoom=2", "--width=100", "--background-color=white",
+        "--export-id=simple", "--lint", NULL
+    };
+    char *filename;
+    guint i;
+
+    filename = g_build_filename (test_utils_get_test_data_path (),
+                                 "render-crash", "text-to-paths.svg", NULL);
+
+    for (i = 0; i < G_N_ELEMENTS (rejected); i++) {
+        char *argv[] = { (char *) RSVG_CONVERT, (char *) "--text-to-paths", NULL, NULL, NULL };
+        char *standard_output;
+        int exit_status;
+        GError *error = NULL;
+
+        argv[2] = rejected[i] ? (char *) rejected[i] : filename;
+        argv[3] = filename;
+
+        g_spawn_sync (NULL, argv, NULL, G_SPAWN_STDERR_TO_DEV_NULL, NULL, NULL,
+                      &standard_output, NULL, &exit_status, &error);
+        g_assert_no_error (error);
+
+        g_assert (!g_spawn_check_exit_status (exit_status, NULL));
+        g_assert_cmpstr (standard_output, ==, "");
+
+        g_free (standard_output);
+    }
+
+    g_free (filename);
+}
+
+/* A TrueType font with the family name "Rsvg Test Box", whose only glyph
+ * is a 1000x1000 square for "x", sitting on the baseline.
+ */
+static const char *box_font_base64 =
+    "AAEAAAAKAIAAAwAgT1MvMlX5VFIAAACsAAAAVmNtYXAADACrAAABBAAAACxnbHlmBOoI1AAA"
+    "ATAAAAAkaGVhZGMGRM0AAAFUAAAANmhoZWEH0gMjAAABjAAAACRobXR4B9AAAAAAAbAAAAAI"
+    "bG9jYQASAAAAAAG4AAAABm1heHAABAAGAAABwAAAACBuYW1lCQsY3wAAAeAAAACOcG9zdP+f"
+    "ADIAAAJwAAAAIAABAfQBkAAFAAAAAAAAAAAAAAAAAAAAAAAAADIA+gAAAAAAAAAAAAAAAAAA"
+    "AAAAAAAAAAAAAAAAAABOT05FAEAAeAB4A+j/OAAAA+gAyAAAAAAAAAAAAAAAAAABAAMAAQAA"
+    "AAwABAAgAAAABAAEAAEAAAB4//8AAAB4////iQABAAAAAAABAAAAAAPoA+gAAwAAAQEBAQAA"
+    "AAAD6AAAAAAD6AAA/BgAAAABAAAAAQAA+OkeZ18PPPUACwPoAAAAAAAAAAAAAAAAAAAAAAAA"
+    "AAAD6APoAAAACAACAAAAAAAAAAEAAAPo/zgAAAPoAAAAAAPoAAEAAAAAAAAAAAAAAAAAAAAC"
+    "A+gAAAPoAAAAAAAAABIAAAABAAAAAgAEAAEAAAAAAAIAAAAAAAAAAAAAAAAAAAAAAAAABAA2"
+    "AAMAAQQJAAEAGgAAAAMAAQQJAAIADgAaAAMAAQQJAAQAGgAoAAMAAQQJAAYAFgBCAFIAcwB2"
+    "AGcAIABUAGUAcwB0ACAAQgBvAHgAUgBlAGcAdQBsAGEAcgBSAHMAdgBnACAAVABlAHMAdAAg"
+    "AEIAbwB4AFIAcwB2AGcAVABlAHMAdABCAG8AeAAAAAMAAAAAAAD/nAAyAAAAAAAAAAAAAAAA"
+    "AAAAAAAAAAA=";
+
+/* No installed font could fill the corners of the square that the "x"
+ * of the embedded font covers, from (10, 20) to (50, 60).
+ */
+static void
+test_data_font_face (void)
+{
+    RsvgHandle *handle;
+    cairo_surface_t *surface;
+    cairo_t *cr;
+    char *svg;
+    GError *error = NULL;
+    const gint points[][2] = { { 15, 25 }, { 45, 25 }, { 30, 40 }, { 15, 55 }, { 45, 55 } };
+    guint i;
+
+    svg = g_strdup_printf ("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"100\">\n"
+                           "  <style type=\"text/css\">\n"
+                           "    @font-face { font-family: \"Embedded\"; src: url(data:font/ttf;base64,%s); }\n"
+                           "  </style>\n"
+                           "  <text x=\"10\" y=\"60\" font-family=\"Embedded\" font-size=\"40\">x</text>\n"
+                           "</svg>\n",
+                           box_font_base64);
+    handle = rsvg_handle_new_from_data ((const guint8 *) svg, strlen (svg), &error);
+    g_free (svg);
+    g_assert_no_error (error);
+
+    surface = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 100, 100);
+    cr = cairo_create (surface);
+    g_assert (rsvg_handle_render_cairo (handle, cr));
+
+    for (i = 0; i < G_N_ELEMENTS (points); i++)
+        g_assert_cmphex (get_pixel (surface, points[i][0], points[i][1]), ==, 0xff000000);
+
+    g_assert_cmphex (get_pixel (surface, 55, 40), ==, 0x00000000);
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (surface);
+    g_object_unref (handle);
+}
+
+int
+main (int argc, char *argv[])
+{
+    int result;
+
+    RSVG_G_TYPE_INIT;
+    g_test_init (&argc, &argv, NULL);
+
+    g_test_add_func ("/api/diagnostics", test_diagnostics);
+    g_test_add_func ("/api/diagnostics/locations", test_diagnostics_locations);
+    g_test_add_func ("/api/diagnostics/none", test_no_diagnostics);
+    g_test_add_func ("/api/lint", test_lint);
+    g_test_add_func ("/api/lint/valid", test_lint_valid);
+    g_test_add_func ("/api/strict/invalid_path", test_strict_invalid_path);
+    g_test_add_func ("/api/strict/invalid_view_box", test_strict_invalid_view_box);
+    g_test_add_func ("/api/strict/valid", test_strict_valid);
+    g_test_add_func ("/api/path/trailing_garbage", test_path_trailing_garbage);
+    g_test_add_func ("/api/text-to-paths", test_text_to_paths);
+    g_test_add_func ("/api/text-to-paths/options", test_text_to_paths_options);
+    g_test_add_func ("/api/font-face/data", test_data_font_face);
+
+    result = g_test_run ();
+
+    rsvg_cleanup ();
+
+    return result;
+}
@@ -105,7 +105,17 @@ static const FixtureData fixtures[] =
     {"/styles/!important/class prior than type", NULL, "styles/important.svg", "#blue", "fill", .expected.color = 0xff0000ff },
     {"/styles/!important/presentation attribute is invalid", NULL, "styles/important.svg", "#white", "fill", .expected.color = 0xffffffff },
     {"/styles/!important/style prior than class", NULL, "styles/important.svg", "#pink", "fill", .expected.color = 0xffffc0cb },
-    /* {"/styles/selectors/descendant", "338160", "styles/bug338160.svg", "#base_shadow", "stroke-width", .expected.length = {2., LENGTH_UNIT_DEFAULT}}, */
+    {"/styles/selectors/descendant", "338160", "styles/bug338160.svg", "#base_shadow", "stroke-width", .expected.length = {2., LENGTH_UNIT_DEFAULT}},
+    {"/styles/selectors/style element after elements", NULL, "styles/selectors.svg", "#early", "fill", .expected.color = 0xff000000},
+    {"/styles/selectors/child and first-child", NULL, "styles/selectors.svg", "#first", "fill", .expected.color = 0xffff0000},
+    {"/styles/selectors/nth-child", NULL, "styles/selectors.svg", "#second", "fill", .expected.color = 0xff008000},
+    {"/styles/selectors/next sibling", NULL, "styles/selectors.svg", "#fourth", "fill", .expected.color = 0xff0000ff},
+    {"/styles/selectors/nth-of-type", NULL, "styles/selectors.svg", "#fourth", "stroke", .expected.color = 0xff0000ff},
+    {"/styles/selectors/only-child", NULL, "styles/selectors.svg", "#nested", "fill", .expected.color = 0xffffffff},
+    {"/styles/selectors/namespaced attribute", NULL, "styles/selectors.svg", "#use", "fill", .expected.color = 0xffffc0cb},
+    {"/styles/selectors/specificity prior than order", NULL, "styles/selectors.svg", "#specific", "fill", .expected.color = 0xff808080},
+    {"/styles/selectors/invalid selector drops rule", NULL, "styles/selectors.svg", "#invalid", "fill", .expected.color = 0xff000000},
+    {"/styles/selectors/last-of-type", NULL, "styles/selectors.svg", "#invalid", "stroke", .expected.color = 0xff008000},
 };
 static const gint n_fixtures = G_N_ELEMENTS (fixtures);
 